@@ -0,0 +1,39 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::compile();
+}
+
+/// The gRPC service is declared here instead of from `proto/`, so building
+/// does not need `protoc`. The messages live in `src/grpc/proto.rs`; both
+/// must match `proto/orchepy/v1/ingestion.proto`.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    fn method(name: &str, route: &str, input: &str, output: &str) -> tonic_build::manual::MethodBuilder {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::proto::{}", input))
+            .output_type(format!("crate::grpc::proto::{}", output))
+            .codec_path("tonic_prost::ProstCodec")
+    }
+
+    pub fn compile() {
+        let ingestion = Service::builder()
+            .name("Ingestion")
+            .package("orchepy.v1")
+            .method(method("create_case", "CreateCase", "CreateCaseRequest", "CaseReply").build())
+            .method(method("move_case", "MoveCase", "MoveCaseRequest", "CaseReply").build())
+            .method(method("send_event", "SendEvent", "EventRequest", "EventReply").build())
+            .method(
+                method("ingest_events", "IngestEvents", "EventRequest", "IngestEventsReply")
+                    .client_streaming()
+                    .build(),
+            )
+            .build();
+
+        Builder::new().compile(&[ingestion]);
+        println!("cargo:rerun-if-changed=build.rs");
+    }
+}
@@ -0,0 +1,268 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::StreamExt;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::validation_response;
+use crate::api::AppState;
+use crate::models::attachment::{CaseAttachment, UploadAttachment};
+use crate::models::ErrorCode;
+use crate::repositories::{CaseAttachmentRepository, CaseRepository};
+use crate::services::storage::{ByteStream, SharedStorage};
+
+const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+/// How long the download links handed out for S3 stay valid.
+const DOWNLOAD_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+fn max_attachment_bytes() -> u64 {
+    std::env::var("ATTACHMENT_MAX_BYTES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|bytes| *bytes > 0)
+        .unwrap_or(DEFAULT_MAX_ATTACHMENT_BYTES)
+}
+
+fn storage(state: &AppState) -> Result<&SharedStorage, ApiError> {
+    state
+        .storage
+        .as_ref()
+        .ok_or_else(|| ApiError::from_code(ErrorCode::StorageNotConfigured, "Object storage is not configured"))
+}
+
+fn internal_error(message: &str) -> ApiError {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+}
+
+fn too_large(limit: u64) -> ApiError {
+    ApiError::from_code(ErrorCode::PayloadTooLarge, format!("Attachments are limited to {} bytes", limit))
+}
+
+/// `attachment` with the filename as is for clients that read RFC 6266's
+/// `filename*`, and with non-ASCII characters replaced for the rest.
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename.chars().map(|c| if c.is_ascii() { c } else { '_' }).collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+/// The request body as a stream that fails once it goes over `limit`,
+/// setting `exceeded`.
+fn limited(body: Body, limit: u64, exceeded: Arc<AtomicBool>) -> ByteStream {
+    let mut received = 0u64;
+    Box::pin(body.into_data_stream().map(move |chunk| {
+        let chunk = chunk.map_err(std::io::Error::other)?;
+        received += chunk.len() as u64;
+        if received > limit {
+            exceeded.store(true, Ordering::Relaxed);
+            return Err(std::io::Error::other("attachment is too large"));
+        }
+        Ok(chunk)
+    }))
+}
+
+/// Streams the request body to object storage as a file of the case,
+/// named by `?filename=` and typed by the `Content-Type` header.
+pub async fn upload_case_attachment(
+    State(state): State<AppState>,
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    Query(upload): Query<UploadAttachment>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, ApiError> {
+    if let Err(errors) = upload.validate() {
+        return Ok(validation_response(&errors));
+    }
+    let storage = storage(&state)?;
+
+    let limit = max_attachment_bytes();
+    let declared = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit) {
+        return Err(too_large(limit));
+    }
+
+    match CaseRepository::new(&region.pool).find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::from_code(ErrorCode::CaseNotFound, "Case not found")),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return Err(internal_error("Failed to fetch case"));
+        }
+    }
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let mut attachment = CaseAttachment::new(case_id, upload, content_type);
+
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let stream = limited(body, limit, exceeded.clone());
+    attachment.size_bytes = match storage.put(&attachment.storage_key, stream, attachment.content_type.as_deref()).await {
+        Ok(size) => size as i64,
+        Err(_) if exceeded.load(Ordering::Relaxed) => return Err(too_large(limit)),
+        Err(err) => {
+            error!("Failed to store attachment of case {}: {}", case_id, err);
+            return Err(internal_error("Failed to store attachment"));
+        }
+    };
+
+    match CaseAttachmentRepository::new(&region.pool).create(&attachment).await {
+        Ok(true) => {
+            info!("Stored attachment {} of case {} ({} bytes)", attachment.id, case_id, attachment.size_bytes);
+            Ok((StatusCode::CREATED, Json(attachment)).into_response())
+        }
+        result => {
+            if let Err(err) = storage.delete(&attachment.storage_key).await {
+                warn!("Failed to remove unrecorded attachment {}: {}", attachment.storage_key, err);
+            }
+            match result {
+                Ok(_) => Err(ApiError::from_code(ErrorCode::CaseNotFound, "Case not found")),
+                Err(err) => {
+                    error!("Failed to save attachment: {}", err);
+                    Err(internal_error("Failed to save attachment"))
+                }
+            }
+        }
+    }
+}
+
+pub async fn get_case_attachments(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> Result<Json<Vec<CaseAttachment>>, ApiError> {
+    match CaseRepository::new(&region.pool).find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::from_code(ErrorCode::CaseNotFound, "Case not found")),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return Err(internal_error("Failed to fetch case"));
+        }
+    }
+
+    CaseAttachmentRepository::new(&region.pool).list_by_case(case_id).await.map(Json).map_err(|err| {
+        error!("Failed to list case attachments: {}", err);
+        internal_error("Failed to list case attachments")
+    })
+}
+
+/// The file itself: a redirect to a short-lived signed URL when the storage
+/// can sign one, or else the content streamed through Orchepy.
+pub async fn download_case_attachment(
+    State(state): State<AppState>,
+    region: Region,
+    Path((case_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, ApiError> {
+    let storage = storage(&state)?;
+
+    let attachment = match CaseAttachmentRepository::new(&region.pool).find(case_id, attachment_id).await {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return Err(ApiError::from_code(ErrorCode::CaseAttachmentNotFound, "Attachment not found")),
+        Err(err) => {
+            error!("Failed to fetch attachment: {}", err);
+            return Err(internal_error("Failed to fetch attachment"));
+        }
+    };
+
+    match storage.signed_url(&attachment.storage_key, DOWNLOAD_URL_TTL).await {
+        Ok(Some(url)) => return Ok((StatusCode::TEMPORARY_REDIRECT, [(header::LOCATION, url)]).into_response()),
+        Ok(None) => {}
+        Err(err) => {
+            error!("Failed to sign download URL of attachment {}: {}", attachment.id, err);
+            return Err(internal_error("Failed to read attachment"));
+        }
+    }
+
+    let object = match storage.get(&attachment.storage_key).await {
+        Ok(Some(object)) => object,
+        Ok(None) => {
+            return Err(ApiError::from_code(ErrorCode::CaseAttachmentNotFound, "Attachment content is missing"))
+        }
+        Err(err) => {
+            error!("Failed to read attachment {}: {}", attachment.id, err);
+            return Err(internal_error("Failed to read attachment"));
+        }
+    };
+
+    let content_type = attachment.content_type.unwrap_or_else(|| "application/octet-stream".to_string());
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_LENGTH, object.size_bytes.to_string()),
+            (header::CONTENT_DISPOSITION, content_disposition(&attachment.filename)),
+        ],
+        Body::from_stream(object.body),
+    )
+        .into_response())
+}
+
+pub async fn delete_case_attachment(
+    State(state): State<AppState>,
+    region: Region,
+    Path((case_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    let storage = storage(&state)?;
+
+    match CaseAttachmentRepository::new(&region.pool).delete(case_id, attachment_id).await {
+        Ok(Some(attachment)) => {
+            if let Err(err) = storage.delete(&attachment.storage_key).await {
+                warn!("Failed to remove attachment {} from storage: {}", attachment.storage_key, err);
+            }
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(None) => Err(ApiError::from_code(ErrorCode::CaseAttachmentNotFound, "Attachment not found")),
+        Err(err) => {
+            error!("Failed to delete attachment: {}", err);
+            Err(internal_error("Failed to delete attachment"))
+        }
+    }
+}
+
+/// Removes the stored files of attachments whose rows are gone, e.g.
+/// because their case was purged.
+pub(crate) async fn remove_stored_attachments(state: &AppState, attachments: &[CaseAttachment]) {
+    let Some(storage) = &state.storage else {
+        return;
+    };
+    for attachment in attachments {
+        if let Err(err) = storage.delete(&attachment.storage_key).await {
+            warn!("Failed to remove attachment {} from storage: {}", attachment.storage_key, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition("Relatório v2.pdf"),
+            "attachment; filename=\"Relat_rio v2.pdf\"; filename*=UTF-8''Relat%C3%B3rio%20v2.pdf"
+        );
+    }
+}
@@ -1,14 +1,26 @@
 use axum::http::StatusCode;
 use axum::Json;
-use serde_json::json;
 use sqlx::PgPool;
-use tracing::{error, info};
+use std::sync::Arc;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
-use crate::engine::AutomationExecutor;
-use crate::models::automation::{AutomationResult, PhaseAutomation};
-use crate::models::case::{Case, CaseHistory};
-use crate::models::{CaseModification, Workflow};
+use crate::api::response::ApiError;
+use crate::engine::{apply_modifications, AutomationExecutor, LimitExceeded, TransientFailure};
+use crate::models::automation::{
+    AutomationResult, AutomationRun, AutomationRunStatus, DeferredAutomation, PhaseAutomation,
+};
+use crate::models::case::Case;
+use crate::models::outbox::OutboxMessage;
+use crate::models::snapshot::DefinitionSnapshot;
+use crate::models::Workflow;
+use crate::repositories::{
+    AutomationRunRepository, DeferredAutomationRepository, DefinitionSnapshotRepository, ServiceAccountRepository,
+};
+use crate::services::mailer::{Mailer, SmtpMailer};
+use crate::services::notification::TwilioConfig;
+use crate::services::secrets::{SecretCipher, Secrets};
+use crate::storage::{CaseStore, PgCaseStore};
 
 pub async fn apply_automation_modifications(
     pool: &PgPool,
@@ -16,119 +28,45 @@ pub async fn apply_automation_modifications(
     workflow: &Workflow,
     automation_result: AutomationResult,
     automation_type: &str,
+    triggered_by: &str,
 ) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
     if automation_result.modifications.is_empty() {
         return Ok(());
     }
 
-    let mut tx = match pool.begin().await {
-        Ok(tx) => tx,
+    let mut store = match PgCaseStore::begin(pool).await {
+        Ok(store) => store,
         Err(e) => {
             error!("Failed to start transaction for {} automation modifications: {}", automation_type, e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to start transaction"}))));
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to start transaction").into_parts());
         }
     };
 
-    let mut current_phase_query = sqlx::query_scalar::<_, String>(
-        "SELECT current_phase FROM orchepy_cases WHERE id = $1"
-    )
-    .bind(case_id)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| {
-        error!("Failed to fetch current phase: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": "Failed to fetch case state"})))
-    })?;
-
-    for modification in automation_result.modifications {
-        match modification {
-            CaseModification::MoveToPhase { phase } => {
-                if !workflow.phases.contains(&phase) {
-                    error!("{} automation tried to move case {} to non-existent phase: {}", automation_type, case_id, phase);
-                    continue;
-                }
-
-                let from_phase = current_phase_query.clone();
-
-                if let Err(e) = sqlx::query(
-                    "UPDATE orchepy_cases SET current_phase = $1, previous_phase = $2, phase_entered_at = NOW(), updated_at = NOW() WHERE id = $3"
-                )
-                .bind(&phase)
-                .bind(&from_phase)
-                .bind(case_id)
-                .execute(&mut *tx)
-                .await {
-                    error!("Failed to apply {} MoveToPhase automation for case {}: {}", automation_type, case_id, e);
-                } else {
-                    info!("{} automation moved case {} from '{}' to '{}'", automation_type, case_id, from_phase, phase);
-
-                    let history = CaseHistory::new(
-                        case_id,
-                        Some(from_phase),
-                        phase.clone(),
-                        Some(format!("{} automation", automation_type)),
-                        Some("system".to_string()),
-                    );
-
-                    if let Err(err) = sqlx::query(
-                        "INSERT INTO orchepy_case_history (id, case_id, from_phase, to_phase, reason, triggered_by, transitioned_at)
-                         VALUES ($1, $2, $3, $4, $5, $6, $7)"
-                    )
-                    .bind(history.id)
-                    .bind(history.case_id)
-                    .bind(&history.from_phase)
-                    .bind(&history.to_phase)
-                    .bind(&history.reason)
-                    .bind(&history.triggered_by)
-                    .bind(history.transitioned_at)
-                    .execute(&mut *tx)
-                    .await
-                    {
-                        error!("Failed to create history entry for {} automation: {}", automation_type, err);
-                    }
-
-                    current_phase_query = phase;
-                }
-            }
-            CaseModification::SetField { field, value } => {
-                let parts: Vec<&str> = field.split('.').collect();
-                if parts.is_empty() {
-                    error!("Invalid field path: {}", field);
-                    continue;
-                }
+    write_modifications(&mut store, case_id, workflow, automation_result, automation_type, triggered_by).await?;
 
-                match parts[0] {
-                    "data" => {
-                        let path = parts[1..].join(".");
-                        if path.is_empty() {
-                            error!("Invalid data field path: {}", field);
-                            continue;
-                        }
-
-                        if let Err(e) = sqlx::query(
-                            "UPDATE orchepy_cases SET data = jsonb_set(data, $1, $2, true), updated_at = NOW() WHERE id = $3"
-                        )
-                        .bind(format!("{{{}}}", path))
-                        .bind(&value)
-                        .bind(case_id)
-                        .execute(&mut *tx)
-                        .await {
-                            error!("Failed to apply {} SetField automation for case {}: {}", automation_type, case_id, e);
-                        } else {
-                            info!("{} automation set field '{}' to {:?} for case {}", automation_type, field, value, case_id);
-                        }
-                    }
-                    _ => {
-                        error!("Unsupported field path for automation: {}", field);
-                    }
-                }
-            }
-        }
+    if let Err(e) = store.commit().await {
+        error!("Failed to commit {} automation modifications: {}", automation_type, e);
+        return Err(ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to commit {} automation modifications", automation_type),
+        )
+        .into_parts());
     }
 
-    if let Err(e) = tx.commit().await {
-        error!("Failed to commit {} automation modifications: {}", automation_type, e);
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({"error": format!("Failed to commit {} automation modifications", automation_type)}))));
+    Ok(())
+}
+
+async fn write_modifications(
+    store: &mut PgCaseStore<'_>,
+    case_id: Uuid,
+    workflow: &Workflow,
+    automation_result: AutomationResult,
+    automation_type: &str,
+    triggered_by: &str,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = apply_modifications(store, case_id, workflow, automation_result, automation_type, triggered_by).await {
+        error!("Failed to fetch current phase: {}", e);
+        return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case state").into_parts());
     }
 
     Ok(())
@@ -141,31 +79,272 @@ pub async fn execute_and_apply_automations(
     from_phase: Option<&str>,
     workflow: &Workflow,
     automation_type: &str,
+    secrets: Option<&SecretCipher>,
+) -> Result<Option<Case>, (StatusCode, Json<serde_json::Value>)> {
+    run_and_apply_automations(pool, None, automations, case, from_phase, workflow, automation_type, None, 0, secrets).await
+}
+
+/// Like `execute_and_apply_automations`, but the run record and
+/// modifications are written in `store`, so they commit or roll back with
+/// the caller's other writes. Everything is read and written through
+/// `store`, so a caller holding a lock there never waits on `pool`. The run
+/// stops at its first webhook, message or delay, which is queued in the
+/// outbox with the actions after it, so the caller must dispatch the case's
+/// outbox once it has committed.
+pub(crate) async fn execute_and_apply_automations_in(
+    pool: &PgPool,
+    store: &mut PgCaseStore<'_>,
+    automations: &[&PhaseAutomation],
+    case: &Case,
+    from_phase: Option<&str>,
+    workflow: &Workflow,
+    automation_type: &str,
+) -> Result<Option<Case>, (StatusCode, Json<serde_json::Value>)> {
+    run_and_apply_automations(pool, Some(store), automations, case, from_phase, workflow, automation_type, None, 0, None)
+        .await
+}
+
+/// Runs the actions `deferred` left for later, as `automation`. The delays
+/// spent before them count toward the workflow's `max_total_delay_ms`.
+pub(crate) async fn resume_deferred_automation(
+    pool: &PgPool,
+    automation: &PhaseAutomation,
+    case: &Case,
+    workflow: &Workflow,
+    deferred: &DeferredAutomation,
+    secrets: Option<&SecretCipher>,
+) -> Result<Option<Case>, (StatusCode, Json<serde_json::Value>)> {
+    run_and_apply_automations(
+        pool,
+        None,
+        &[automation],
+        case,
+        deferred.from_phase.as_deref(),
+        workflow,
+        &deferred.trigger,
+        None,
+        deferred.total_delay_ms.max(0) as u64,
+        secrets,
+    )
+    .await
+}
+
+/// Runs `automations` again for a run that failed transiently. The new run
+/// is recorded as the next attempt of the original one.
+pub(crate) async fn retry_automation_run(
+    pool: &PgPool,
+    automations: &[&PhaseAutomation],
+    case: &Case,
+    workflow: &Workflow,
+    failed: &AutomationRun,
+    secrets: Option<&SecretCipher>,
+) -> Result<Option<Case>, (StatusCode, Json<serde_json::Value>)> {
+    run_and_apply_automations(
+        pool,
+        None,
+        automations,
+        case,
+        failed.from_phase.as_deref(),
+        workflow,
+        &failed.trigger,
+        Some(failed),
+        0,
+        secrets,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_and_apply_automations(
+    pool: &PgPool,
+    mut store: Option<&mut PgCaseStore<'_>>,
+    automations: &[&PhaseAutomation],
+    case: &Case,
+    from_phase: Option<&str>,
+    workflow: &Workflow,
+    automation_type: &str,
+    retry_of: Option<&AutomationRun>,
+    delay_spent_ms: u64,
+    secrets: Option<&SecretCipher>,
 ) -> Result<Option<Case>, (StatusCode, Json<serde_json::Value>)> {
     if automations.is_empty() {
         return Ok(None);
     }
 
-    let executor = AutomationExecutor::new();
-
-    match executor.execute_automations(automations, case, from_phase).await {
-        Ok(automation_result) => {
-            if !automation_result.modifications.is_empty() {
-                apply_automation_modifications(pool, case.id, workflow, automation_result, automation_type).await?;
-
-                match sqlx::query_as::<_, Case>("SELECT * FROM orchepy_cases WHERE id = $1")
-                    .bind(case.id)
-                    .fetch_one(pool)
-                    .await
-                {
-                    Ok(updated_case) => Ok(Some(updated_case)),
-                    Err(e) => {
-                        error!("Failed to re-fetch case after {} automation modifications: {}", automation_type, e);
-                        Ok(None)
-                    }
+    // Inside the caller's transaction the run hands its webhooks and
+    // messages over to the outbox, so it needs neither the secrets they use
+    // nor a mailer. Nothing is read or logged through `pool` while `store`
+    // holds the case's lock, and emails are only logged once sent, after
+    // the caller has committed.
+    let executor = AutomationExecutor::with_limits(workflow.execution_limits.clone()).with_delay_spent(delay_spent_ms);
+    let executor = if store.is_some() {
+        executor.deferring_external_actions()
+    } else {
+        executor
+            .with_twilio(TwilioConfig::from_env())
+            .with_mailer(SmtpMailer::from_env().map(|mailer| Arc::new(mailer.with_log(pool.clone())) as Arc<dyn Mailer>))
+            .with_secrets(Secrets::for_definition(pool, secrets, &automations).await)
+    };
+
+    // Workflows that name a service account run as it: it must exist and be
+    // active, and every modification must be within its allow-lists, or the
+    // run fails without applying anything.
+    let run_as = workflow.automations.as_ref().and_then(|config| config.run_as.as_deref());
+    let account = match run_as {
+        Some(name) => {
+            let account = match store.as_deref_mut() {
+                Some(store) => store.find_service_account(name).await,
+                None => ServiceAccountRepository::new(pool).find_by_name(name).await,
+            };
+            match account {
+                Ok(Some(account)) if account.active => Ok(Some(account)),
+                Ok(_) => Err(format!("Service account '{}' does not exist or is inactive", name)),
+                Err(e) => {
+                    error!("Failed to fetch service account '{}': {}", name, e);
+                    return Err(ApiError::new(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to fetch service account",
+                    )
+                    .into_parts());
                 }
+            }
+        }
+        None => Ok(None),
+    };
+
+    let started_at = chrono::Utc::now();
+    let (outcome, conditions) = match &account {
+        Ok(_) => executor.execute_automations_traced(automations, case, from_phase).await,
+        Err(message) => (Err(anyhow::anyhow!(message.clone())), Vec::new()),
+    };
+    let outcome = match (outcome, &account) {
+        (Ok(result), Ok(Some(account))) => {
+            match result.modifications.iter().find_map(|modification| account.denies(modification)) {
+                Some(denied) => Err(anyhow::anyhow!(denied)),
+                None => Ok(result),
+            }
+        }
+        (outcome, _) => outcome,
+    };
+
+    let mut run = AutomationRun {
+        id: Uuid::new_v4(),
+        case_id: case.id,
+        workflow_id: workflow.id,
+        trigger: automation_type.to_string(),
+        phase: automations[0].phase.clone(),
+        status: AutomationRunStatus::Completed,
+        actions_executed: 0,
+        total_delay_ms: 0,
+        error: None,
+        started_at,
+        completed_at: chrono::Utc::now(),
+        conditions,
+        from_phase: from_phase.map(str::to_string),
+        transient: false,
+        attempt: retry_of.map_or(1, |failed| failed.attempt + 1),
+        retry_of: retry_of.map(|failed| failed.retry_of.unwrap_or(failed.id)),
+        retried_at: None,
+        run_as: run_as.map(str::to_string),
+        workflow_version: Some(workflow.version),
+        definition_hash: None,
+    };
+    let snapshot = DefinitionSnapshot::of_workflow(workflow);
+    let saved = match store.as_deref_mut() {
+        Some(store) => store.save_snapshot(&snapshot).await,
+        None => DefinitionSnapshotRepository::new(pool).save(&snapshot).await,
+    };
+    match saved {
+        Ok(()) => run.definition_hash = Some(snapshot.hash),
+        Err(e) => warn!("Failed to save definition snapshot of workflow {}: {}", workflow.id, e),
+    }
+    match &outcome {
+        Ok(result) => {
+            run.actions_executed = result.actions_executed as i32;
+            run.total_delay_ms = result.total_delay_ms as i64;
+            if result.deferred.is_some() {
+                run.status = AutomationRunStatus::Deferred;
+            }
+        }
+        Err(e) => {
+            run.status = if e.is::<LimitExceeded>() {
+                AutomationRunStatus::LimitExceeded
             } else {
-                Ok(None)
+                AutomationRunStatus::Failed
+            };
+            run.transient = e.is::<TransientFailure>();
+            run.error = Some(e.to_string());
+        }
+    }
+    let recorded = match store.as_deref_mut() {
+        Some(store) => store.record_automation_run(&run).await,
+        None => AutomationRunRepository::new(pool).create(&run).await,
+    };
+    if let Err(e) = recorded {
+        warn!("Failed to record {} automation run for case {}: {}", automation_type, case.id, e);
+    }
+
+    match outcome {
+        Ok(mut automation_result) => {
+            if let Some(deferred) = automation_result.deferred.take() {
+                let deferred = DeferredAutomation {
+                    id: Uuid::new_v4(),
+                    case_id: case.id,
+                    workflow_id: workflow.id,
+                    trigger: automation_type.to_string(),
+                    phase: run.phase.clone(),
+                    from_phase: from_phase.map(str::to_string),
+                    actions: deferred.actions,
+                    resume_at: deferred.resume_at,
+                    created_at: chrono::Utc::now(),
+                    total_delay_ms: run.total_delay_ms,
+                    attempts: 0,
+                };
+                // Inside the caller's transaction, the run stopped before its
+                // first webhook, message or delay; the outbox runs the rest
+                // once the transaction has committed.
+                let stored = match store.as_deref_mut() {
+                    Some(store) => store.enqueue(&[OutboxMessage::automation(deferred.clone())]).await,
+                    None => DeferredAutomationRepository::new(pool).create(&deferred).await,
+                };
+                if let Err(e) = stored {
+                    error!("Failed to defer {} automation actions for case {}: {}", automation_type, case.id, e);
+                } else {
+                    info!(
+                        "Deferred {} remaining {} action(s) for case {} until {}",
+                        deferred.actions.len(), automation_type, case.id, deferred.resume_at
+                    );
+                }
+            }
+
+            if automation_result.modifications.is_empty() {
+                return Ok(None);
+            }
+
+            let triggered_by = run_as.unwrap_or("system");
+            let updated = match store {
+                Some(store) => {
+                    write_modifications(store, case.id, workflow, automation_result, automation_type, triggered_by)
+                        .await?;
+                    store.find_case(case.id).await
+                }
+                None => {
+                    apply_automation_modifications(pool, case.id, workflow, automation_result, automation_type, triggered_by)
+                        .await?;
+                    sqlx::query_as::<_, Case>("SELECT * FROM orchepy_cases WHERE id = $1")
+                        .bind(case.id)
+                        .fetch_optional(pool)
+                        .await
+                        .map_err(Into::into)
+                }
+            };
+
+            match updated {
+                Ok(updated_case) => Ok(updated_case),
+                Err(e) => {
+                    error!("Failed to re-fetch case after {} automation modifications: {}", automation_type, e);
+                    Ok(None)
+                }
             }
         }
         Err(e) => {
@@ -0,0 +1,54 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::models::comment::{CaseComment, CreateCaseComment};
+use crate::models::ErrorCode;
+use crate::repositories::{CaseCommentRepository, CaseRepository};
+
+const CASE_COMMENTS_LIMIT: i64 = 500;
+
+pub async fn get_case_comments(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    match CaseRepository::new(pool).find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    }
+
+    match CaseCommentRepository::new(pool).list_by_case(case_id, CASE_COMMENTS_LIMIT).await {
+        Ok(comments) => (StatusCode::OK, Json(json!(comments))),
+        Err(err) => {
+            error!("Failed to fetch case comments: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case comments").into_parts()
+        }
+    }
+}
+
+pub async fn create_case_comment(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateCaseComment>,
+) -> impl IntoResponse {
+    let comment = CaseComment::new(case_id, payload);
+
+    match CaseCommentRepository::new(&region.pool).create(&comment).await {
+        Ok(true) => (StatusCode::CREATED, Json(json!(comment))),
+        Ok(false) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to create case comment: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create case comment").into_parts()
+        }
+    }
+}
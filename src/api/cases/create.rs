@@ -2,36 +2,40 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde_json::json;
 use tracing::{error, info};
 
-use crate::api::events::internal_create_and_trigger_event;
+use crate::api::outbox::dispatch_case_outbox;
+use crate::api::validation::ValidatedJson;
+use crate::api::region::Region;
+use crate::api::response::ApiError;
 use crate::api::AppState;
-use crate::models::case::{Case, CaseHistory, CreateCase};
-use crate::models::event::CreateEvent;
+use crate::models::case::{track_data_writes, Case, CaseHistory, CreateCase, FieldProvenance};
+use crate::models::outbox::OutboxMessage;
+use crate::models::patch::{DataPatch, PatchOutcome};
+use crate::models::validation::MAX_CASE_TAGS;
+use crate::models::ErrorCode;
+use crate::repositories::case_repository::is_external_id_taken;
 use crate::repositories::{CaseRepository, WorkflowRepository};
+use crate::services::CaseChangeKind;
+use crate::storage::{CaseStore, PgCaseStore};
 
-use super::automation_handler::execute_and_apply_automations;
+use super::automation_handler::execute_and_apply_automations_in;
+use super::query::patch_response;
 
 pub async fn create_case(
     State(state): State<AppState>,
-    Json(payload): Json<CreateCase>,
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<CreateCase>,
 ) -> impl IntoResponse {
-    let pool = &state.pool;
-    let webhook_sender = &state.webhook_sender;
+    let pool = &region.pool;
 
     let workflow_repo = WorkflowRepository::new(pool);
     let workflow = match workflow_repo.find_active_by_id(payload.workflow_id).await {
         Ok(Some(wf)) => wf,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Workflow not found or inactive"})),
-            )
+            return ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found, inactive or archived").into_parts()
         }
         Err(err) => {
             error!("Failed to fetch workflow: {}", err);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to fetch workflow"})),
-            );
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow").into_parts();
         }
     };
 
@@ -40,31 +44,73 @@ pub async fn create_case(
         .unwrap_or(workflow.initial_phase.clone());
 
     if !workflow.has_phase(&initial_phase) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": format!("Phase '{}' not found in workflow", initial_phase)})),
-        );
+        return ApiError::from_code(
+            ErrorCode::PhaseInvalid,
+            format!("Phase '{}' not found in workflow", initial_phase),
+        )
+        .into_parts();
     }
 
-    let mut case = Case::new(
+    let case_repo = CaseRepository::new(pool);
+    let writer = FieldProvenance::api(payload.triggered_by.clone());
+
+    if let Some(external_id) = &payload.external_id {
+        match case_repo.find_by_external_id(workflow.id, external_id).await {
+            Ok(Some(existing)) if payload.upsert => {
+                return upsert_case(&state, &region, &existing, &payload.data, &payload.tags, &writer).await
+            }
+            Ok(Some(existing)) => return external_id_taken(&existing),
+            Ok(None) => {}
+            Err(err) => {
+                error!("Failed to fetch case by external id: {}", err);
+                return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+            }
+        }
+    }
+
+    let mut case = Case::with_clock(
         payload.workflow_id,
         initial_phase.clone(),
         payload.data,
         payload.metadata,
+        state.clock.as_ref(),
     );
+    case.region = Some(region.name.clone());
+    case.add_tags(payload.tags);
+    case.external_id = payload.external_id;
+    track_data_writes(&mut case.field_provenance, &serde_json::Value::Null, &case.data, &writer);
+
+    // The case, its history entry, what its on_enter automations change and
+    // the case.created event and webhook commit together or not at all.
+    let mut store = match PgCaseStore::begin(pool).await {
+        Ok(store) => store,
+        Err(err) => {
+            error!("Failed to start transaction: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create case").into_parts();
+        }
+    };
 
-    let case_repo = CaseRepository::new(pool);
-
-    if let Err(err) = case_repo.create(&case).await {
+    if let Err(err) = store.create_case(&case).await {
+        drop(store);
+        // Another request created the case since the lookup above.
+        if is_external_id_taken(&err) {
+            let external_id = case.external_id.as_deref().unwrap_or_default();
+            return match case_repo.find_by_external_id(workflow.id, external_id).await {
+                Ok(Some(existing)) if payload.upsert => {
+                    upsert_case(&state, &region, &existing, &case.data, &case.tags, &writer).await
+                }
+                Ok(Some(existing)) => external_id_taken(&existing),
+                Ok(None) | Err(_) => ApiError::from_code(
+                    ErrorCode::CaseAlreadyExists,
+                    format!("A case with external_id '{}' already exists", external_id),
+                )
+                .into_parts(),
+            };
+        }
         error!("Failed to create case: {}", err);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Failed to create case"})),
-        );
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create case").into_parts();
     }
 
-    info!("Created case {} in phase '{}'", case.id, case.current_phase);
-
     let history = CaseHistory::new(
         case.id,
         None,
@@ -73,8 +119,9 @@ pub async fn create_case(
         Some("system".to_string()),
     );
 
-    if let Err(err) = case_repo.create_history(&history).await {
+    if let Err(err) = store.create_history(&history).await {
         error!("Failed to create history entry: {}", err);
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create case").into_parts();
     }
 
     if let Some(automations_config) = &workflow.automations {
@@ -83,8 +130,9 @@ pub async fn create_case(
             .into_iter()
             .collect();
 
-        match execute_and_apply_automations(
+        match execute_and_apply_automations_in(
             pool,
+            &mut store,
             &automations_to_run,
             &case,
             None,
@@ -101,46 +149,88 @@ pub async fn create_case(
         }
     }
 
-    let pool_clone = pool.clone();
-    let case_clone_for_event = case.clone();
-    tokio::spawn(async move {
-        info!("Submitting internal event for case.created: {}", case_clone_for_event.id);
-        let event_payload = CreateEvent {
-            event_type: "case.created".to_string(),
-            data: json!({
-                "case_id": case_clone_for_event.id,
-                "workflow_id": case_clone_for_event.workflow_id,
-                "to_phase": case_clone_for_event.current_phase,
-                "from_phase": null,
-                "case_data": case_clone_for_event.data,
-            }),
-            metadata: case_clone_for_event.metadata,
-        };
-
-        if let Err(e) = internal_create_and_trigger_event(&pool_clone, event_payload).await {
-            error!("Failed to submit internal case.created event: {}", e.message);
-        }
-    });
-
     let webhook_on_create = std::env::var("WEBHOOK_ON_CASE_CREATE")
         .unwrap_or_else(|_| "true".to_string())
         .parse::<bool>()
         .unwrap_or(true);
+    let webhook = workflow.webhook().filter(|_| webhook_on_create);
 
-    if webhook_on_create {
-        if let Some(webhook_url) = workflow.webhook_url {
-            let case_clone = case.clone();
-            let webhook_sender_clone = webhook_sender.clone();
-            tokio::spawn(async move {
-                if let Err(err) = webhook_sender_clone
-                    .send_case_moved_with_retry(&webhook_url, &case_clone, None, 3)
-                    .await
-                {
-                    error!("Failed to send webhook: {}", err);
-                }
-            });
-        }
+    if let Err(err) = store.enqueue(&OutboxMessage::case_moved(&case, None, webhook)).await {
+        error!("Failed to queue case.created event: {}", err);
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create case").into_parts();
+    }
+
+    if let Err(err) = store.commit().await {
+        error!("Failed to commit creation of case {}: {}", case.id, err);
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create case").into_parts();
     }
 
+    info!("Created case {} in phase '{}'", case.id, case.current_phase);
+    dispatch_case_outbox(&state, &region, case.id);
+
+    state.case_stream.publish(CaseChangeKind::Created, &region.name, &case);
+
     (StatusCode::CREATED, Json(json!(case)))
 }
+
+fn external_id_taken(existing: &Case) -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::from_code(
+        ErrorCode::CaseAlreadyExists,
+        format!(
+            "Case {} already has external_id '{}'",
+            existing.id,
+            existing.external_id.as_deref().unwrap_or_default()
+        ),
+    )
+    .with_details(json!({"case_id": existing.id}))
+    .into_parts()
+}
+
+/// The upsert half of `POST /cases`: adds `tags` to the existing case and
+/// merge-patches its data with `data`, as `PATCH /cases/{id}/data` would.
+/// The case stays in its phase and keeps its metadata.
+async fn upsert_case(
+    state: &AppState,
+    region: &Region,
+    existing: &Case,
+    data: &serde_json::Value,
+    tags: &[String],
+    writer: &FieldProvenance,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let case_repo = CaseRepository::new(&region.pool);
+
+    if !tags.is_empty() {
+        match case_repo.add_tags(existing.id, tags, MAX_CASE_TAGS).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("A case can have at most {} tags", MAX_CASE_TAGS),
+                )
+                .into_parts()
+            }
+            Err(err) => {
+                error!("Failed to tag case: {}", err);
+                return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to tag case").into_parts();
+            }
+        }
+    }
+
+    let outcome = case_repo.patch_data(existing.id, &DataPatch::Merge(data.clone()), writer).await;
+    if !matches!(outcome, Ok(PatchOutcome::Applied(_))) {
+        return patch_response(state, region, existing.id, outcome).await;
+    }
+
+    match case_repo.find_by_id(existing.id).await {
+        Ok(Some(case)) => {
+            info!("Upserted case {} with external_id {:?}", case.id, case.external_id);
+            state.case_stream.publish(CaseChangeKind::Updated, &region.name, &case);
+            (StatusCode::OK, Json(json!(case)))
+        }
+        Ok(None) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch upserted case: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts()
+        }
+    }
+}
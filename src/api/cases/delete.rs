@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::cases::remove_stored_attachments;
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::AppState;
+use crate::models::ErrorCode;
+use crate::repositories::{CaseAttachmentRepository, CaseRepository};
+
+/// Soft-deletes the case. It stays in the database, visible with
+/// `?include_deleted=true`, until it is purged.
+pub async fn delete_case(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match CaseRepository::new(&region.pool).soft_delete(case_id).await {
+        Ok(true) => {
+            info!("Soft-deleted case {}", case_id);
+            (StatusCode::NO_CONTENT, Json(json!({})))
+        }
+        Ok(false) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to delete case: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete case").into_parts()
+        }
+    }
+}
+
+/// Permanently removes a soft-deleted case and everything recorded for it.
+/// Active cases must be deleted first, so a single call can't destroy data.
+/// The files of its attachments are removed from storage too.
+pub async fn purge_case(
+    State(state): State<AppState>,
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let case_repo = CaseRepository::new(&region.pool);
+
+    let attachments = match CaseAttachmentRepository::new(&region.pool).list_by_case(case_id).await {
+        Ok(attachments) => attachments,
+        Err(err) => {
+            error!("Failed to list case attachments: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to purge case").into_parts();
+        }
+    };
+
+    match case_repo.purge(case_id).await {
+        Ok(true) => {
+            info!("Purged case {}", case_id);
+            remove_stored_attachments(&state, &attachments).await;
+            return (StatusCode::NO_CONTENT, Json(json!({})));
+        }
+        Ok(false) => {}
+        Err(err) => {
+            error!("Failed to purge case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to purge case").into_parts();
+        }
+    }
+
+    match case_repo.find_by_id(case_id).await {
+        Ok(Some(_)) => ApiError::new(StatusCode::CONFLICT, "Case must be deleted before it can be purged").into_parts(),
+        Ok(None) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to purge case").into_parts()
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::models::import::ImportCases;
+use crate::models::ErrorCode;
+use crate::repositories::{CaseRepository, WorkflowRepository};
+
+/// `POST /admin/cases/import`: loads historical cases as they were, with
+/// their timestamps and phase timeline. No automations, events or webhooks
+/// run, and either every case is imported or none is.
+pub async fn import_cases(
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<ImportCases>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    let workflow = match WorkflowRepository::new(pool).find_by_id(payload.workflow_id).await {
+        Ok(Some(workflow)) if workflow.is_archived() => {
+            return ApiError::from_code(ErrorCode::WorkflowArchived, "Workflow is archived and takes no new cases").into_parts()
+        }
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => return ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow").into_parts();
+        }
+    };
+
+    let now = Utc::now();
+    let triggered_by = payload.triggered_by.as_deref();
+    let mut cases = Vec::with_capacity(payload.cases.len());
+    let mut errors = Vec::new();
+    for (index, imported) in payload.cases.into_iter().enumerate() {
+        match imported.into_case(&workflow, triggered_by, now) {
+            Ok((mut case, history)) => {
+                case.region = Some(region.name.clone());
+                cases.push((case, history));
+            }
+            Err(message) => errors.push(json!({"index": index, "error": message})),
+        }
+    }
+
+    if !errors.is_empty() {
+        return ApiError::from_code(ErrorCode::ValidationFailed, "Some cases cannot be imported")
+            .with_details(json!({"cases": errors}))
+            .into_parts();
+    }
+
+    match CaseRepository::new(pool).import(&cases).await {
+        Ok(None) => {
+            info!("Imported {} cases into workflow {}", cases.len(), workflow.id);
+            let case_ids: Vec<_> = cases.iter().map(|(case, _)| case.id).collect();
+            (
+                StatusCode::CREATED,
+                Json(json!({"imported": case_ids.len(), "case_ids": case_ids})),
+            )
+        }
+        Ok(Some(existing)) => {
+            ApiError::from_code(
+                ErrorCode::CaseAlreadyExists,
+                format!("Case {} already exists; nothing was imported", existing),
+            )
+            .into_parts()
+        }
+        Err(err) => {
+            error!("Failed to import cases: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to import cases").into_parts()
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use axum::{extract::{Path, State}, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::events::internal_create_and_trigger_event;
+use crate::api::outbox::dispatch_case_outbox;
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::api::AppState;
+use crate::models::case::{CaseHistory, CaseLifecycleAction, ChangeCaseStatus};
+use crate::models::event::CreateEvent;
+use crate::models::outbox::OutboxMessage;
+use crate::models::ErrorCode;
+use crate::repositories::{CaseRepository, OutboxRepository, WorkflowRepository};
+use crate::services::webhook::CaseStatusWebhookPayload;
+use crate::services::CaseChangeKind;
+
+pub async fn complete_case(
+    State(state): State<AppState>,
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    payload: Option<ValidatedJson<ChangeCaseStatus>>,
+) -> impl IntoResponse {
+    change_status(&state, &region, case_id, payload, CaseLifecycleAction::Complete).await
+}
+
+pub async fn fail_case(
+    State(state): State<AppState>,
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    payload: Option<ValidatedJson<ChangeCaseStatus>>,
+) -> impl IntoResponse {
+    change_status(&state, &region, case_id, payload, CaseLifecycleAction::Fail).await
+}
+
+pub async fn pause_case(
+    State(state): State<AppState>,
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    payload: Option<ValidatedJson<ChangeCaseStatus>>,
+) -> impl IntoResponse {
+    change_status(&state, &region, case_id, payload, CaseLifecycleAction::Pause).await
+}
+
+pub async fn resume_case(
+    State(state): State<AppState>,
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    payload: Option<ValidatedJson<ChangeCaseStatus>>,
+) -> impl IntoResponse {
+    change_status(&state, &region, case_id, payload, CaseLifecycleAction::Resume).await
+}
+
+async fn change_status(
+    state: &AppState,
+    region: &Region,
+    case_id: Uuid,
+    payload: Option<ValidatedJson<ChangeCaseStatus>>,
+    action: CaseLifecycleAction,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let payload = payload.map(|ValidatedJson(payload)| payload).unwrap_or_default();
+    let case_repo = CaseRepository::new(&region.pool);
+
+    let mut case = match case_repo.find_by_id(case_id).await {
+        Ok(Some(c)) => c,
+        Ok(None) => {
+            return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    };
+
+    let from_status = case.status.clone();
+    if !action.allowed_from(&from_status) {
+        return ApiError::from_code(ErrorCode::CaseStatusInvalid, format!("Cannot {} a case in its current status", action.as_str()))
+            .with_details(json!({"status": from_status}))
+            .into_parts();
+    }
+
+    action.apply(&mut case);
+
+    match case_repo.transition_status(&case, &from_status).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return ApiError::new(
+                StatusCode::CONFLICT,
+                "Case status changed concurrently, retry the request",
+            )
+            .into_parts()
+        }
+        Err(err) => {
+            error!("Failed to update case status: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update case status").into_parts();
+        }
+    }
+
+    info!("Case {} {:?} -> {:?}", case_id, from_status, case.status);
+
+    let history = CaseHistory::status_change(&case, from_status.clone(), payload.reason.clone(), payload.triggered_by);
+    if let Err(err) = case_repo.create_history(&history).await {
+        error!("Failed to create history entry: {}", err);
+    }
+
+    let state_clone = state.clone();
+    let region_clone = region.clone();
+    let case_clone_for_event = case.clone();
+    let from_status_for_event = from_status.clone();
+    let reason_for_event = payload.reason.clone();
+    tokio::spawn(async move {
+        info!("Submitting internal event for {}: {}", action.event_type(), case_clone_for_event.id);
+        let event_payload = CreateEvent {
+            event_type: action.event_type().to_string(),
+            data: json!({
+                "case_id": case_clone_for_event.id,
+                "workflow_id": case_clone_for_event.workflow_id,
+                "phase": case_clone_for_event.current_phase,
+                "from_status": from_status_for_event,
+                "to_status": case_clone_for_event.status,
+                "reason": reason_for_event,
+                "case_data": case_clone_for_event.data,
+            }),
+            metadata: case_clone_for_event.metadata,
+        };
+
+        if let Err(e) = internal_create_and_trigger_event(&state_clone, &region_clone, event_payload).await {
+            error!("Failed to submit internal {} event: {}", action.event_type(), e.message);
+        }
+    });
+
+    let webhook_on_status = std::env::var("WEBHOOK_ON_CASE_STATUS")
+        .unwrap_or_else(|_| "true".to_string())
+        .parse::<bool>()
+        .unwrap_or(true);
+
+    if webhook_on_status {
+        match WorkflowRepository::new(&region.pool).find_by_id(case.workflow_id).await {
+            Ok(Some(workflow)) => {
+                if let Some(webhook) = workflow.webhook() {
+                    let payload = CaseStatusWebhookPayload::changed(action.event_type(), &case, from_status, payload.reason);
+                    let message = OutboxMessage::webhook(&case, webhook, action.event_type(), &payload);
+                    match OutboxRepository::new(&region.pool).enqueue(&[message]).await {
+                        Ok(_) => dispatch_case_outbox(state, region, case.id),
+                        Err(err) => error!("Failed to queue status webhook: {}", err),
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(err) => error!("Failed to fetch workflow for status webhook: {}", err),
+        }
+    }
+
+    state.case_stream.publish(CaseChangeKind::Updated, &region.name, &case);
+
+    (StatusCode::OK, Json(json!(case)))
+}
@@ -0,0 +1,92 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::models::link::{CaseLink, CreateCaseLink, LinkedCase};
+use crate::models::ErrorCode;
+use crate::repositories::{CaseLinkRepository, CaseRepository};
+
+pub async fn get_case_links(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    match CaseRepository::new(pool).find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    }
+
+    match CaseLinkRepository::new(pool).list_by_case(case_id).await {
+        Ok(links) => {
+            let linked: Vec<LinkedCase> = links.iter().map(|link| link.seen_from(case_id)).collect();
+            (StatusCode::OK, Json(json!(linked)))
+        }
+        Err(err) => {
+            error!("Failed to fetch case links: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case links").into_parts()
+        }
+    }
+}
+
+/// Both cases must exist in the same region.
+pub async fn create_case_link(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateCaseLink>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    if payload.case_id == case_id {
+        return ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "A case cannot be linked to itself").into_parts();
+    }
+
+    let case_repo = CaseRepository::new(pool);
+    for id in [case_id, payload.case_id] {
+        match case_repo.find_by_id(id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return ApiError::from_code(ErrorCode::CaseNotFound, format!("Case {} not found", id)).into_parts()
+            }
+            Err(err) => {
+                error!("Failed to fetch case: {}", err);
+                return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+            }
+        }
+    }
+
+    let link = CaseLink::new(case_id, payload);
+    match CaseLinkRepository::new(pool).create(&link).await {
+        Ok(true) => {
+            info!("Linked case {} to {}", link.source_case_id, link.target_case_id);
+            (StatusCode::CREATED, Json(json!(link.seen_from(case_id))))
+        }
+        Ok(false) => ApiError::new(StatusCode::CONFLICT, "The cases are already linked this way").into_parts(),
+        Err(err) => {
+            error!("Failed to create case link: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create case link").into_parts()
+        }
+    }
+}
+
+pub async fn delete_case_link(
+    region: Region,
+    Path((case_id, link_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    match CaseLinkRepository::new(&region.pool).delete(case_id, link_id).await {
+        Ok(true) => (StatusCode::NO_CONTENT, Json(json!({}))),
+        Ok(false) => ApiError::from_code(ErrorCode::CaseLinkNotFound, "Case link not found").into_parts(),
+        Err(err) => {
+            error!("Failed to delete case link: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete case link").into_parts()
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::AppState;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::models::message::{reply_token_from_address, CaseMessage, CreateCaseMessage, InboundMessage, MessageChannel};
+use crate::models::ErrorCode;
+use crate::repositories::{CaseMessageRepository, CaseRepository, WorkflowRepository};
+
+use super::automation_handler::execute_and_apply_automations;
+
+const CASE_MESSAGES_LIMIT: i64 = 500;
+
+pub async fn get_case_messages(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let repo = CaseMessageRepository::new(&region.pool);
+    match repo.list_by_case(case_id, CASE_MESSAGES_LIMIT).await {
+        Ok(messages) => (StatusCode::OK, Json(json!(messages))),
+        Err(err) => {
+            error!("Failed to fetch case messages: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case messages").into_parts()
+        }
+    }
+}
+
+/// Logs a message sent outside Orchepy and returns it with the
+/// `reply_token` the sender should embed so replies find their way back.
+pub async fn create_case_message(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateCaseMessage>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    match CaseRepository::new(pool).find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    }
+
+    let mut message = CaseMessage::outbound(
+        case_id,
+        payload.channel,
+        Some(payload.to),
+        payload.subject,
+        payload.body,
+    );
+    message.external_id = payload.external_id;
+    message.public = payload.public;
+
+    match CaseMessageRepository::new(pool).create(&message).await {
+        Ok(()) => (StatusCode::CREATED, Json(json!(message))),
+        Err(err) => {
+            error!("Failed to record case message: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to record case message").into_parts()
+        }
+    }
+}
+
+pub async fn receive_inbound_message(
+    State(state): State<AppState>,
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<InboundMessage>,
+) -> impl IntoResponse {
+    let repo = CaseMessageRepository::new(&region.pool);
+
+    // Email replies carry their token in the plus-addressed recipient.
+    let reply_token = payload.reply_token.clone().or_else(|| match payload.channel {
+        MessageChannel::Email => payload.to.as_deref().and_then(reply_token_from_address),
+        _ => None,
+    });
+
+    let case_id = match &reply_token {
+        Some(token) => repo.find_case_by_reply_token(token).await,
+        None => repo.find_case_by_last_recipient(payload.channel, &payload.from).await,
+    };
+
+    let case_id = match case_id {
+        Ok(Some(case_id)) => case_id,
+        Ok(None) => {
+            return ApiError::new(StatusCode::NOT_FOUND, "No case matches this message").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to correlate inbound message: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to correlate inbound message").into_parts();
+        }
+    };
+
+    let message = CaseMessage::inbound(case_id, payload);
+    if let Err(err) = repo.create(&message).await {
+        error!("Failed to record inbound message: {}", err);
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to record inbound message").into_parts();
+    }
+
+    info!("Routed inbound {:?} message to case {}", message.channel, case_id);
+
+    if let Err(err) = run_reply_automations(&state, &region.pool, &message).await {
+        error!("Failed to run on_reply automations for case {}: {}", case_id, err);
+    }
+
+    (StatusCode::CREATED, Json(json!(message)))
+}
+
+/// Runs the workflow's `on_reply` automations for the case's current phase.
+/// The reply is exposed to the actions as `data.reply` without being saved
+/// on the case.
+pub(crate) async fn run_reply_automations(state: &AppState, pool: &PgPool, message: &CaseMessage) -> anyhow::Result<()> {
+
+    let Some(mut case) = CaseRepository::new(pool).find_by_id(message.case_id).await? else {
+        return Ok(());
+    };
+    let Some(workflow) = WorkflowRepository::new(pool).find_by_id(case.workflow_id).await? else {
+        return Ok(());
+    };
+    let Some(automations_config) = &workflow.automations else {
+        return Ok(());
+    };
+
+    let automations = automations_config.get_on_reply_automations(&case.current_phase);
+    if automations.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(data) = case.data.as_object_mut() {
+        data.insert(
+            "reply".to_string(),
+            json!({
+                "message_id": message.id,
+                "channel": message.channel,
+                "from": message.sender,
+                "subject": message.subject,
+                "body": message.body,
+            }),
+        );
+    }
+
+    execute_and_apply_automations(pool, &automations, &case, None, &workflow, "on_reply", state.secrets.as_ref())
+        .await
+        .map_err(|(status, body)| anyhow::anyhow!("{}: {}", status, body.0))?;
+
+    Ok(())
+}
@@ -1,8 +1,41 @@
+mod attachments;
 mod automation_handler;
+mod comments;
 mod create;
+mod delete;
+mod import;
+mod lifecycle;
+mod links;
+mod messages;
 mod move_case;
+mod presence;
 mod query;
+mod revisions;
+mod stream;
+mod tags;
+mod version;
+mod workflows;
 
+pub(crate) use attachments::remove_stored_attachments;
+pub(crate) use automation_handler::{resume_deferred_automation, retry_automation_run};
+pub(crate) use messages::run_reply_automations;
+pub use attachments::{
+    delete_case_attachment, download_case_attachment, get_case_attachments, upload_case_attachment,
+};
+pub use comments::{create_case_comment, get_case_comments};
 pub use create::create_case;
+pub use delete::{delete_case, purge_case};
+pub use import::import_cases;
+pub use lifecycle::{complete_case, fail_case, pause_case, resume_case};
+pub use links::{create_case_link, delete_case_link, get_case_links};
+pub use messages::{create_case_message, get_case_messages, receive_inbound_message};
 pub use move_case::move_case;
-pub use query::{get_case, get_case_history, list_cases, update_case_data};
+pub use presence::{case_presence_heartbeat, leave_case_presence};
+pub use query::{
+    get_case, get_case_automation_run, get_case_automation_runs, get_case_board, get_case_by_external_id,
+    get_case_history, list_cases, search_all_cases, search_cases, update_case_data,
+};
+pub use revisions::{get_case_revisions, restore_case_revision};
+pub use stream::stream_cases;
+pub use tags::{add_case_tags, remove_case_tag};
+pub use workflows::{get_case_workflows, join_workflow, leave_workflow, move_case_in_workflow};
@@ -1,66 +1,131 @@
-use axum::{extract::{Path, State}, http::StatusCode, response::IntoResponse, Json};
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, response::IntoResponse, Json};
 use serde_json::json;
 use tracing::{error, info};
 use uuid::Uuid;
 
-use crate::api::events::internal_create_and_trigger_event;
+use crate::api::outbox::dispatch_case_outbox;
+use crate::api::validation::ValidatedJson;
+use crate::api::region::Region;
+use crate::api::response::ApiError;
 use crate::api::AppState;
 use crate::models::case::{CaseHistory, MoveCase};
-use crate::models::event::CreateEvent;
+use crate::models::outbox::OutboxMessage;
+use crate::models::phase::PhaseMove;
+use crate::models::{ErrorCode, Workflow};
 use crate::repositories::{CaseRepository, WorkflowRepository};
+use crate::services::CaseChangeKind;
+use crate::storage::{CaseStore, PgCaseStore};
 
-use super::automation_handler::execute_and_apply_automations;
+use super::automation_handler::execute_and_apply_automations_in;
+use super::version::{expected_version, version_mismatch};
+
+/// 409 for a move into a phase that is at its WIP limit.
+pub(super) fn wip_limit_reached(phase: &str, limit: u32, in_progress: i64) -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::from_code(ErrorCode::WipLimitReached, format!("Phase '{}' is at its WIP limit of {}", phase, limit))
+        .with_details(json!({
+            "phase": phase,
+            "wip_limit": limit,
+            "in_progress": in_progress,
+        }))
+        .into_parts()
+}
+
+/// 409 for a move the workflow's transition rules do not allow.
+pub(super) fn transition_not_allowed(workflow: &Workflow, from: &str, to: &str) -> (StatusCode, Json<serde_json::Value>) {
+    let allowed = workflow.allowed_transitions(from).unwrap_or_default();
+    let reason = match allowed {
+        [] => format!("cases in '{}' cannot move to another phase", from),
+        [phase] => format!("cases in '{}' can only move to '{}'", from, phase),
+        [rest @ .., last] => format!(
+            "cases in '{}' can only move to {} or '{}'",
+            from,
+            rest.iter().map(|phase| format!("'{}'", phase)).collect::<Vec<_>>().join(", "),
+            last
+        ),
+    };
+
+    ApiError::from_code(ErrorCode::TransitionNotAllowed, format!("Cannot move from '{}' to '{}': {}", from, to, reason))
+        .with_details(json!({
+            "from_phase": from,
+            "to_phase": to,
+            "allowed_phases": allowed,
+        }))
+        .into_parts()
+}
 
 pub async fn move_case(
     State(state): State<AppState>,
+    region: Region,
     Path(case_id): Path<Uuid>,
-    Json(payload): Json<MoveCase>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<MoveCase>,
 ) -> impl IntoResponse {
-    let pool = &state.pool;
-    let webhook_sender = &state.webhook_sender;
+    let expected_version = match expected_version(&headers) {
+        Ok(version) => version,
+        Err(response) => return response,
+    };
+
+    let pool = &region.pool;
 
     let case_repo = CaseRepository::new(pool);
     let workflow_repo = WorkflowRepository::new(pool);
 
-    let mut case = match case_repo.find_by_id(case_id).await {
-        Ok(Some(c)) => c,
+    let workflow_id = match case_repo.find_by_id(case_id).await {
+        Ok(Some(c)) => c.workflow_id,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Case not found"})),
-            )
+            return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts()
         }
         Err(err) => {
             error!("Failed to fetch case: {}", err);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to fetch case"})),
-            );
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
         }
     };
 
-    let workflow = match workflow_repo.find_by_id(case.workflow_id).await {
+    let workflow = match workflow_repo.find_by_id(workflow_id).await {
         Ok(Some(wf)) => wf,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Workflow not found"})),
-            )
+            return ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts()
         }
         Err(err) => {
             error!("Failed to fetch workflow: {}", err);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to fetch workflow"})),
-            );
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow").into_parts();
+        }
+    };
+
+    // The move, its history entry and what the automations change commit
+    // together or not at all. The case stays locked until then, so moves of
+    // one case see each other's phase. Nothing below takes another
+    // connection from the pool before the commit, so waiting moves can't
+    // starve the one holding the lock.
+    let mut store = match PgCaseStore::begin(pool).await {
+        Ok(store) => store,
+        Err(err) => {
+            error!("Failed to start transaction: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to move case").into_parts();
         }
     };
 
+    let mut case = match store.find_case(case_id).await {
+        Ok(Some(c)) if c.deleted_at.is_none() => c,
+        Ok(_) => {
+            return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    };
+
+    if expected_version.is_some_and(|expected| expected != case.version) {
+        return version_mismatch(case.version);
+    }
+
     if !workflow.has_phase(&payload.to_phase) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": format!("Phase '{}' not found in workflow", payload.to_phase)})),
-        );
+        return ApiError::from_code(
+            ErrorCode::PhaseInvalid,
+            format!("Phase '{}' not found in workflow", payload.to_phase),
+        )
+        .into_parts();
     }
 
     if case.current_phase == payload.to_phase {
@@ -70,22 +135,39 @@ pub async fn move_case(
         );
     }
 
+    if !workflow.allows_transition(&case.current_phase, &payload.to_phase) {
+        return transition_not_allowed(&workflow, &case.current_phase, &payload.to_phase);
+    }
+
     let from_phase = case.current_phase.clone();
     case.move_to_phase(payload.to_phase.clone());
 
-    if let Err(err) = case_repo.update_phase(case_id, &case.current_phase, case.previous_phase.as_deref()).await {
-        error!("Failed to move case: {}", err);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": "Failed to move case"})),
-        );
+    let wip_limit = workflow.wip_limit(&case.current_phase).filter(|_| case.status.is_in_progress());
+    match store
+        .move_case_within_limit(
+            case_id,
+            case.workflow_id,
+            &case.current_phase,
+            case.previous_phase.as_deref(),
+            wip_limit,
+            expected_version,
+        )
+        .await
+    {
+        Ok(PhaseMove::Moved) => {}
+        Ok(PhaseMove::AtWipLimit { limit, in_progress }) => {
+            return wip_limit_reached(&case.current_phase, limit, in_progress)
+        }
+        Ok(PhaseMove::NotFound) => {
+            return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts()
+        }
+        Ok(PhaseMove::Stale { version }) => return version_mismatch(version),
+        Err(err) => {
+            error!("Failed to move case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to move case").into_parts();
+        }
     }
 
-    info!(
-        "Moved case {} from '{}' to '{}'",
-        case_id, from_phase, case.current_phase
-    );
-
     let history = CaseHistory::new(
         case_id,
         Some(from_phase.clone()),
@@ -94,8 +176,9 @@ pub async fn move_case(
         payload.triggered_by,
     );
 
-    if let Err(err) = case_repo.create_history(&history).await {
+    if let Err(err) = store.create_history(&history).await {
         error!("Failed to create history entry: {}", err);
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to move case").into_parts();
     }
 
     if let Some(automations_config) = &workflow.automations {
@@ -104,8 +187,9 @@ pub async fn move_case(
             .into_iter()
             .collect();
 
-        match execute_and_apply_automations(
+        match execute_and_apply_automations_in(
             pool,
+            &mut store,
             &on_exit_automations,
             &case,
             Some(&from_phase),
@@ -126,8 +210,9 @@ pub async fn move_case(
             .into_iter()
             .collect();
 
-        match execute_and_apply_automations(
+        match execute_and_apply_automations_in(
             pool,
+            &mut store,
             &on_enter_automations,
             &case,
             Some(&from_phase),
@@ -144,48 +229,37 @@ pub async fn move_case(
         }
     }
 
-    let pool_clone = pool.clone();
-    let case_clone_for_event = case.clone();
-    let from_phase_for_event = from_phase.clone();
-    tokio::spawn(async move {
-        info!("Submitting internal event for case.moved: {}", case_clone_for_event.id);
-        let event_payload = CreateEvent {
-            event_type: "case.moved".to_string(),
-            data: json!({
-                "case_id": case_clone_for_event.id,
-                "workflow_id": case_clone_for_event.workflow_id,
-                "to_phase": case_clone_for_event.current_phase,
-                "from_phase": from_phase_for_event,
-                "case_data": case_clone_for_event.data,
-            }),
-            metadata: case_clone_for_event.metadata,
-        };
-
-        if let Err(e) = internal_create_and_trigger_event(&pool_clone, event_payload).await {
-            error!("Failed to submit internal case.moved event: {}", e.message);
-        }
-    });
-
     let webhook_on_move = std::env::var("WEBHOOK_ON_CASE_MOVE")
         .unwrap_or_else(|_| "true".to_string())
         .parse::<bool>()
         .unwrap_or(true);
+    let webhook = workflow.webhook().filter(|_| webhook_on_move);
 
-    if webhook_on_move {
-        if let Some(webhook_url) = workflow.webhook_url {
-            let case_clone = case.clone();
-            let webhook_sender_clone = webhook_sender.clone();
-            let from_phase_for_webhook = from_phase.clone();
-            tokio::spawn(async move {
-                if let Err(err) = webhook_sender_clone
-                    .send_case_moved_with_retry(&webhook_url, &case_clone, Some(from_phase_for_webhook), 3)
-                    .await
-                {
-                    error!("Failed to send webhook: {}", err);
-                }
-            });
-        }
+    if let Err(err) = store.enqueue(&OutboxMessage::case_moved(&case, Some(&from_phase), webhook)).await {
+        error!("Failed to queue case.moved event: {}", err);
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to move case").into_parts();
+    }
+
+    if let Err(err) = store.commit().await {
+        error!("Failed to commit move of case {}: {}", case_id, err);
+        return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to move case").into_parts();
+    }
+
+    info!(
+        "Moved case {} from '{}' to '{}'",
+        case_id, from_phase, payload.to_phase
+    );
+
+    dispatch_case_outbox(&state, &region, case_id);
+
+    // The move and any automations changed the case since it was read.
+    match case_repo.version(case_id).await {
+        Ok(Some(version)) => case.version = version,
+        Ok(None) => {}
+        Err(err) => error!("Failed to fetch case version: {}", err),
     }
 
+    state.case_stream.publish(CaseChangeKind::Moved, &region.name, &case);
+
     (StatusCode::OK, Json(json!(case)))
 }
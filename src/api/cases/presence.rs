@@ -0,0 +1,64 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::models::presence::{presence_ttl, PresenceHeartbeat};
+use crate::models::ErrorCode;
+use crate::repositories::{CasePresenceRepository, CaseRepository};
+
+/// Called every few seconds while someone has the case open. Returns
+/// everyone on the case, so a client can warn when another user is
+/// already editing it.
+pub async fn case_presence_heartbeat(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<PresenceHeartbeat>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    match CaseRepository::new(pool).find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    }
+
+    let repo = CasePresenceRepository::new(pool);
+    let ttl = presence_ttl();
+    let presence = match repo.heartbeat(case_id, &payload.user, payload.editing, ttl).await {
+        Ok(()) => repo.list_active(case_id).await,
+        Err(err) => Err(err),
+    };
+
+    match presence {
+        Ok(presence) => (
+            StatusCode::OK,
+            Json(json!({"case_id": case_id, "ttl_secs": ttl.as_secs(), "presence": presence})),
+        ),
+        Err(err) => {
+            error!("Failed to record case presence: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to record case presence").into_parts()
+        }
+    }
+}
+
+/// Sent when someone closes the case, so others don't wait for the TTL.
+pub async fn leave_case_presence(
+    region: Region,
+    Path((case_id, user)): Path<(Uuid, String)>,
+) -> impl IntoResponse {
+    match CasePresenceRepository::new(&region.pool).leave(case_id, &user).await {
+        Ok(true) => (StatusCode::NO_CONTENT, Json(json!({}))),
+        Ok(false) => ApiError::new(StatusCode::NOT_FOUND, "User is not present on this case").into_parts(),
+        Err(err) => {
+            error!("Failed to clear case presence: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to clear case presence").into_parts()
+        }
+    }
+}
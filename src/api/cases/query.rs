@@ -1,116 +1,443 @@
-use axum::{extract::{Path, Query, State}, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde_json::json;
-use sqlx::QueryBuilder;
+use sqlx::{PgPool, QueryBuilder};
+use std::collections::HashMap;
 use tracing::error;
 use uuid::Uuid;
 
+use crate::api::region::{Region, RegionSet};
+use crate::api::response::{list_response, ApiError, Envelope};
+use crate::api::fields::Fields;
 use crate::api::AppState;
-use crate::models::case::{Case, CaseHistory, ListCasesQuery, UpdateCaseData};
+use crate::models::board::{merge_columns, BoardQuery};
+use crate::models::case::{
+    Case, CaseEmbed, CaseHistory, CaseSearch, CaseSort, ExternalIdQuery, FieldProvenance, GlobalSearchQuery,
+    IncludeDeletedQuery, ListCasesQuery, ListedCase, TriggeredByQuery, UpdateCaseData,
+};
+use crate::models::patch::{
+    DataPatch, PatchError, PatchOutcome, JSON_PATCH_CONTENT_TYPE, MERGE_PATCH_CONTENT_TYPE,
+};
+use crate::repositories::case_repository::{push_data_filter, push_workflow_filters};
+use crate::models::snapshot::WithDefinition;
+use crate::models::workflow::WorkflowSummary;
+use crate::models::ErrorCode;
+use crate::repositories::{
+    AutomationRunRepository, CasePresenceRepository, CaseRepository, CaseWorkflowRepository,
+    DefinitionSnapshotRepository, WorkflowRepository,
+};
+use crate::services::CaseChangeKind;
+
+use super::version::{etag, expected_version, version_mismatch};
+
+const AUTOMATION_RUNS_LIMIT: i64 = 100;
 
 pub async fn list_cases(
-    State(state): State<AppState>,
-    Query(query): Query<ListCasesQuery>,
-) -> impl IntoResponse {
-    let pool = &state.pool;
+    regions: RegionSet,
+    envelope: Envelope,
+    fields: Fields,
+    Query(mut query): Query<ListCasesQuery>,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Response {
+    if let Err(err) = fields.validate::<ListedCase>() {
+        return err.into_response();
+    }
+    query.metadata = match ListCasesQuery::metadata_filters(&params) {
+        Ok(filters) => filters,
+        Err(message) => return ApiError::new(StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    query.containment = match query.data_containment() {
+        Ok(containment) => containment,
+        Err(message) => return ApiError::new(StatusCode::BAD_REQUEST, message).into_response(),
+    };
 
     let limit = query.limit.unwrap_or(50).min(100);
     let offset = query.offset.unwrap_or(0);
+    let query = &query;
+
+    let compare = |a: &ListedCase, b: &ListedCase| query.sort.compare(query.order, &a.case, &b.case);
+    let cases = regions
+        .list_by(Some(limit), offset, compare, |region, limit, offset| async move {
+            let mut query_builder = QueryBuilder::new("SELECT * FROM orchepy_cases WHERE 1=1");
+            push_case_filters(&mut query_builder, query);
+            push_case_order(&mut query_builder, query);
+
+            query_builder.push(" LIMIT ");
+            query_builder.push_bind(limit);
+            query_builder.push(" OFFSET ");
+            query_builder.push_bind(offset);
+
+            let cases = query_builder.build_query_as::<Case>().fetch_all(&region.read_pool).await?;
+            let cases = match query.workflow_id {
+                Some(workflow_id) => CaseWorkflowRepository::new(&region.read_pool).scope(workflow_id, cases).await?,
+                None => cases,
+            };
+            match query.embed {
+                Some(CaseEmbed::Workflow) => embed_workflows(&region.read_pool, cases).await,
+                None => Ok(cases.into_iter().map(|case| ListedCase { case, workflow: None }).collect()),
+            }
+        })
+        .await;
 
-    let mut query_builder = QueryBuilder::new("SELECT * FROM orchepy_cases WHERE 1=1");
+    let cases = match cases {
+        Ok(cases) => cases,
+        Err(err) => {
+            error!("Failed to fetch cases: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch cases").into_response();
+        }
+    };
+
+    let total = regions.count(|region| async move {
+        let mut count_builder = QueryBuilder::new("SELECT COUNT(*) FROM orchepy_cases WHERE 1=1");
+        push_case_filters(&mut count_builder, query);
+        Ok(count_builder.build_query_scalar::<i64>().fetch_one(&region.read_pool).await?)
+    });
+
+    list_response(envelope, fields.project(cases), Some(limit), offset, total)
+        .await
+        .into_response()
+}
 
-    if let Some(workflow_id) = query.workflow_id {
-        query_builder.push(" AND workflow_id = ");
-        query_builder.push_bind(workflow_id);
+/// `GET /cases/search`: like `GET /cases`, plus `data.*` filters such as
+/// `data.customer.tier=gold` or `data.amount[gte]=1000`.
+pub async fn search_cases(
+    regions: RegionSet,
+    envelope: Envelope,
+    fields: Fields,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Response {
+    if let Err(err) = fields.validate::<Case>() {
+        return err.into_response();
     }
+    let search = match CaseSearch::from_params(&params) {
+        Ok(search) => search,
+        Err(message) => return ApiError::new(StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let limit = search.limit.unwrap_or(50).min(100);
+    let offset = search.offset.unwrap_or(0);
+    let search = &search;
+
+    let cases = regions
+        .list(Some(limit), offset, |case: &Case| case.created_at, |region, limit, offset| async move {
+            let cases = CaseRepository::new(&region.read_pool).search(search, limit.unwrap_or(50), offset).await?;
+            match search.workflow_id {
+                Some(workflow_id) => CaseWorkflowRepository::new(&region.read_pool).scope(workflow_id, cases).await,
+                None => Ok(cases),
+            }
+        })
+        .await;
+
+    let cases = match cases {
+        Ok(cases) => cases,
+        Err(err) => {
+            error!("Failed to search cases: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to search cases").into_response();
+        }
+    };
+
+    let total = regions.count(|region| async move { CaseRepository::new(&region.read_pool).count_search(search).await });
+
+    list_response(envelope, fields.project(cases), Some(limit), offset, total)
+        .await
+        .into_response()
+}
+
+/// `GET /search/cases?q=`: free text across every workflow in the selected
+/// regions, matched against case data, phase, tags and id.
+pub async fn search_all_cases(
+    regions: RegionSet,
+    envelope: Envelope,
+    Query(query): Query<GlobalSearchQuery>,
+) -> Response {
+    let pattern = match query.pattern() {
+        Ok(pattern) => pattern,
+        Err(message) => return ApiError::new(StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let (pattern, status) = (pattern.as_str(), query.status.as_ref());
+
+    let cases = regions
+        .list(Some(limit), offset, |case: &Case| case.updated_at, |region, limit, offset| async move {
+            CaseRepository::new(&region.read_pool).search_text(pattern, status, limit.unwrap_or(50), offset).await
+        })
+        .await;
+
+    let cases = match cases {
+        Ok(cases) => cases,
+        Err(err) => {
+            error!("Failed to search cases: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to search cases").into_response();
+        }
+    };
 
-    if let Some(current_phase) = &query.current_phase {
-        query_builder.push(" AND current_phase = ");
-        query_builder.push_bind(current_phase);
+    let total = regions.count(|region| async move { CaseRepository::new(&region.read_pool).count_text(pattern, status).await });
+
+    list_response(envelope, cases, Some(limit), offset, total)
+        .await
+        .into_response()
+}
+
+/// `GET /cases/board`: a person's cases from every workflow, in columns by
+/// status or assignee rather than phase.
+pub async fn get_case_board(regions: RegionSet, Query(query): Query<BoardQuery>) -> impl IntoResponse {
+    let field = query.assignee_field();
+    if field.is_empty() || field.len() > 255 {
+        return ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "assignee_field must be between 1 and 255 characters",
+        )
+        .into_parts();
+    }
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let query = &query;
+
+    let columns = futures::future::try_join_all(
+        regions
+            .0
+            .iter()
+            .map(|region| async move { CaseRepository::new(&region.read_pool).board(query, limit).await }),
+    )
+    .await;
+
+    match columns {
+        Ok(columns) => {
+            let columns = merge_columns(columns.into_iter().flatten(), limit as usize);
+            (StatusCode::OK, Json(json!({"group_by": query.group_by, "columns": columns})))
+        }
+        Err(err) => {
+            error!("Failed to load case board: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load case board").into_parts()
+        }
+    }
+}
+
+fn push_case_filters<'q>(query_builder: &mut QueryBuilder<'q, sqlx::Postgres>, query: &'q ListCasesQuery) {
+    if !query.include_deleted {
+        query_builder.push(" AND deleted_at IS NULL");
     }
 
+    push_workflow_filters(query_builder, query.workflow_id, query.current_phase.as_ref());
+
     if let Some(status) = &query.status {
         query_builder.push(" AND status = ");
         query_builder.push_bind(status);
     }
 
-    query_builder.push(" ORDER BY created_at DESC LIMIT ");
-    query_builder.push_bind(limit as i64);
-    query_builder.push(" OFFSET ");
-    query_builder.push_bind(offset as i64);
+    if let Some(tag) = &query.tag {
+        query_builder.push(" AND tags @> ARRAY[");
+        query_builder.push_bind(tag);
+        query_builder.push("]");
+    }
 
-    match query_builder.build_query_as::<Case>().fetch_all(pool).await {
-        Ok(cases) => (StatusCode::OK, Json(json!(cases))),
-        Err(err) => {
-            error!("Failed to fetch cases: {}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to fetch cases"})),
-            )
-        }
+    if let Some(containment) = &query.containment {
+        query_builder.push(" AND data @> ");
+        query_builder.push_bind(containment);
+    }
+
+    for filter in &query.metadata {
+        query_builder.push(" AND ");
+        push_data_filter(query_builder, filter);
     }
 }
 
+/// Pairs each case with its workflow's summary, loaded in one query.
+async fn embed_workflows(pool: &PgPool, cases: Vec<Case>) -> anyhow::Result<Vec<ListedCase>> {
+    let mut ids: Vec<Uuid> = cases.iter().map(|case| case.workflow_id).collect();
+    ids.sort();
+    ids.dedup();
+
+    let workflows: HashMap<Uuid, WorkflowSummary> = WorkflowRepository::new(pool)
+        .find_summaries(&ids)
+        .await?
+        .into_iter()
+        .map(|workflow| (workflow.id, workflow))
+        .collect();
+
+    Ok(cases
+        .into_iter()
+        .map(|case| ListedCase {
+            workflow: workflows.get(&case.workflow_id).cloned(),
+            case,
+        })
+        .collect())
+}
+
+/// `ORDER BY` for `?sort=` and `?order=`; ties go newest first, then by id
+/// so pages don't overlap.
+fn push_case_order<'q>(query_builder: &mut QueryBuilder<'q, sqlx::Postgres>, query: &'q ListCasesQuery) {
+    query_builder.push(" ORDER BY ");
+    match &query.sort {
+        CaseSort::CreatedAt => query_builder.push("created_at"),
+        CaseSort::UpdatedAt => query_builder.push("updated_at"),
+        CaseSort::PhaseEnteredAt => query_builder.push("phase_entered_at"),
+        CaseSort::Data(path) => query_builder.push("data #> ").push_bind(path),
+    };
+
+    // A missing `data` field is NULL and goes last either way.
+    query_builder.push(format!(" {} NULLS LAST, created_at DESC, id", query.order.as_sql()));
+}
+
 pub async fn get_case(
-    State(state): State<AppState>,
+    region: Region,
     Path(case_id): Path<Uuid>,
+    Query(query): Query<IncludeDeletedQuery>,
 ) -> impl IntoResponse {
-    let pool = &state.pool;
+    let case_repo = CaseRepository::new(&region.read_pool);
+    let case = if query.include_deleted {
+        case_repo.find_by_id_with_deleted(case_id).await
+    } else {
+        case_repo.find_by_id(case_id).await
+    };
 
-    match sqlx::query_as::<_, Case>("SELECT * FROM orchepy_cases WHERE id = $1")
-        .bind(case_id)
-        .fetch_optional(pool)
-        .await
-    {
-        Ok(Some(case)) => (StatusCode::OK, Json(json!(case))),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Case not found"})),
-        ),
+    match case {
+        Ok(Some(case)) => {
+            let mut body = json!(case);
+            match CasePresenceRepository::new(&region.read_pool).list_active(case_id).await {
+                Ok(presence) => body["presence"] = json!(presence),
+                Err(err) => error!("Failed to fetch case presence: {}", err),
+            }
+            (StatusCode::OK, [(header::ETAG, etag(case.version))], Json(body)).into_response()
+        }
+        Ok(None) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_response(),
         Err(err) => {
             error!("Failed to fetch case: {}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to fetch case"})),
-            )
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_response()
+        }
+    }
+}
+
+/// The case an integration created with `external_id`, looked up within
+/// one workflow.
+pub async fn get_case_by_external_id(
+    region: Region,
+    Path(external_id): Path<String>,
+    Query(query): Query<ExternalIdQuery>,
+) -> impl IntoResponse {
+    match CaseRepository::new(&region.read_pool).find_by_external_id(query.workflow_id, &external_id).await {
+        Ok(Some(case)) => (StatusCode::OK, [(header::ETAG, etag(case.version))], Json(json!(case))).into_response(),
+        Ok(None) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_response(),
+        Err(err) => {
+            error!("Failed to fetch case by external id: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_response()
         }
     }
 }
 
+/// Reads the update from the body according to its Content-Type:
+/// `application/json` replaces the data (`{"data": ..., "triggered_by": ...}`),
+/// `application/merge-patch+json` is an RFC 7386 merge patch and
+/// `application/json-patch+json` an RFC 6902 patch.
+fn parse_data_patch(headers: &HeaderMap, body: &[u8]) -> Result<(DataPatch, Option<String>), (StatusCode, String)> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let invalid = |err: serde_json::Error| (StatusCode::BAD_REQUEST, format!("Invalid request body: {}", err));
+
+    match content_type.as_str() {
+        "application/json" => {
+            let payload: UpdateCaseData = serde_json::from_slice(body).map_err(invalid)?;
+            Ok((DataPatch::Replace(payload.data), payload.triggered_by))
+        }
+        MERGE_PATCH_CONTENT_TYPE => Ok((DataPatch::Merge(serde_json::from_slice(body).map_err(invalid)?), None)),
+        JSON_PATCH_CONTENT_TYPE => Ok((DataPatch::Json(serde_json::from_slice(body).map_err(invalid)?), None)),
+        _ => Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!(
+                "Expected Content-Type application/json, {} or {}",
+                MERGE_PATCH_CONTENT_TYPE, JSON_PATCH_CONTENT_TYPE
+            ),
+        )),
+    }
+}
+
 pub async fn update_case_data(
     State(state): State<AppState>,
+    region: Region,
     Path(case_id): Path<Uuid>,
-    Json(payload): Json<UpdateCaseData>,
+    Query(query): Query<TriggeredByQuery>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> impl IntoResponse {
-    let pool = &state.pool;
+    let (patch, triggered_by) = match parse_data_patch(&headers, &body) {
+        Ok(parsed) => parsed,
+        Err((status, message)) => return ApiError::new(status, message).into_parts(),
+    };
 
-    match sqlx::query(
-        "UPDATE orchepy_cases SET data = $1, updated_at = NOW() WHERE id = $2 RETURNING id",
-    )
-    .bind(&payload.data)
-    .bind(case_id)
-    .fetch_optional(pool)
-    .await
-    {
-        Ok(Some(_)) => (StatusCode::OK, Json(json!({"message": "Case data updated"}))),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Case not found"})),
-        ),
-        Err(err) => {
-            error!("Failed to update case data: {}", err);
+    let expected_version = match expected_version(&headers) {
+        Ok(version) => version,
+        Err(response) => return response,
+    };
+
+    let writer = FieldProvenance::api(triggered_by.or(query.triggered_by));
+    let outcome = CaseRepository::new(&region.pool)
+        .patch_data_at_version(case_id, &patch, &writer, expected_version)
+        .await;
+    patch_response(&state, &region, case_id, outcome).await
+}
+
+/// The response to a data write: the new data and version, or why it was
+/// not applied.
+pub(super) async fn patch_response(
+    state: &AppState,
+    region: &Region,
+    case_id: Uuid,
+    outcome: anyhow::Result<PatchOutcome>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let repo = CaseRepository::new(&region.pool);
+    match outcome {
+        Ok(PatchOutcome::Applied(data)) => {
+            let case = repo.find_by_id(case_id).await.unwrap_or_else(|err| {
+                error!("Failed to fetch updated case: {}", err);
+                None
+            });
+            if let Some(case) = &case {
+                state.case_stream.publish(CaseChangeKind::Updated, &region.name, case);
+            }
+            let version = case.map(|case| case.version);
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to update case data"})),
+                StatusCode::OK,
+                Json(json!({"message": "Case data updated", "data": data, "version": version})),
             )
         }
+        Ok(PatchOutcome::NotFound) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Ok(PatchOutcome::Rejected(err)) => {
+            let status = match err {
+                PatchError::TestFailed(_) => StatusCode::CONFLICT,
+                PatchError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            };
+            ApiError::new(status, format!("Patch not applied: {}", err)).into_parts()
+        }
+        Ok(PatchOutcome::Conflict(fields)) => {
+            ApiError::from_code(ErrorCode::DataConflict, "Fields were changed concurrently by another writer")
+                .with_details(json!({"fields": fields}))
+                .into_parts()
+        }
+        Ok(PatchOutcome::Stale { version }) => version_mismatch(version),
+        Err(err) => {
+            error!("Failed to update case data: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update case data").into_parts()
+        }
     }
 }
 
 pub async fn get_case_history(
-    State(state): State<AppState>,
+    region: Region,
     Path(case_id): Path<Uuid>,
 ) -> impl IntoResponse {
-    let pool = &state.pool;
+    let pool = &region.read_pool;
     match sqlx::query_as::<_, CaseHistory>(
         "SELECT * FROM orchepy_case_history WHERE case_id = $1 ORDER BY transitioned_at DESC",
     )
@@ -121,10 +448,55 @@ pub async fn get_case_history(
         Ok(history) => (StatusCode::OK, Json(json!(history))),
         Err(err) => {
             error!("Failed to fetch case history: {}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": "Failed to fetch case history"})),
-            )
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case history").into_parts()
+        }
+    }
+}
+
+pub async fn get_case_automation_runs(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let repo = AutomationRunRepository::new(&region.pool);
+    match repo.list_by_case(case_id, AUTOMATION_RUNS_LIMIT).await {
+        Ok(runs) => (StatusCode::OK, Json(json!(runs))),
+        Err(err) => {
+            error!("Failed to fetch automation runs: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch automation runs").into_parts()
+        }
+    }
+}
+
+/// One automation run with the workflow definition it used.
+pub async fn get_case_automation_run(
+    region: Region,
+    Path((case_id, run_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    let run = match AutomationRunRepository::new(pool).find_by_case(case_id, run_id).await {
+        Ok(Some(run)) => run,
+        Ok(None) => {
+            return ApiError::from_code(ErrorCode::AutomationRunNotFound, "Automation run not found").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to fetch automation run: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch automation run").into_parts();
+        }
+    };
+
+    let snapshot = match &run.definition_hash {
+        Some(hash) => DefinitionSnapshotRepository::new(pool).find(hash).await,
+        None => Ok(None),
+    };
+    match snapshot {
+        Ok(snapshot) => (
+            StatusCode::OK,
+            Json(json!(WithDefinition { run, definition: snapshot.map(|snapshot| snapshot.definition) })),
+        ),
+        Err(err) => {
+            error!("Failed to fetch definition snapshot: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch definition snapshot").into_parts()
         }
     }
 }
@@ -0,0 +1,79 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::AppState;
+use crate::models::case::{FieldProvenance, TriggeredByQuery};
+use crate::models::patch::DataPatch;
+use crate::models::ErrorCode;
+use crate::repositories::{CaseRepository, CaseRevisionRepository};
+
+use super::query::patch_response;
+use super::version::expected_version;
+
+const CASE_REVISIONS_LIMIT: i64 = 500;
+
+/// The case's data revisions, newest first.
+pub async fn get_case_revisions(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    match CaseRepository::new(pool).find_by_id_with_deleted(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    }
+
+    match CaseRevisionRepository::new(pool).list_by_case(case_id, CASE_REVISIONS_LIMIT).await {
+        Ok(revisions) => (StatusCode::OK, Json(json!(revisions))),
+        Err(err) => {
+            error!("Failed to fetch case revisions: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case revisions").into_parts()
+        }
+    }
+}
+
+/// Sets the case's data back to what it was at `revision`. The restore is
+/// a data write like any other, so it becomes the newest revision.
+pub async fn restore_case_revision(
+    State(state): State<AppState>,
+    region: Region,
+    Path((case_id, revision)): Path<(Uuid, i32)>,
+    Query(query): Query<TriggeredByQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let expected_version = match expected_version(&headers) {
+        Ok(version) => version,
+        Err(response) => return response,
+    };
+
+    let revision = match CaseRevisionRepository::new(&region.pool).find(case_id, revision).await {
+        Ok(Some(revision)) => revision,
+        Ok(None) => {
+            return ApiError::from_code(ErrorCode::CaseRevisionNotFound, "Case revision not found").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to fetch case revision: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case revision").into_parts();
+        }
+    };
+
+    let writer = FieldProvenance::api(query.triggered_by);
+    let outcome = CaseRepository::new(&region.pool)
+        .patch_data_at_version(case_id, &DataPatch::Replace(revision.data), &writer, expected_version)
+        .await;
+    patch_response(&state, &region, case_id, outcome).await
+}
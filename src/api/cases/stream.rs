@@ -0,0 +1,61 @@
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::Stream;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::AppState;
+use crate::services::CaseChange;
+
+#[derive(Debug, Deserialize)]
+pub struct CaseStreamQuery {
+    pub workflow_id: Option<Uuid>,
+}
+
+struct Subscription {
+    receiver: tokio::sync::broadcast::Receiver<CaseChange>,
+    region: String,
+    workflow_id: Option<Uuid>,
+}
+
+impl Subscription {
+    fn wants(&self, change: &CaseChange) -> bool {
+        change.region == self.region && self.workflow_id.is_none_or(|id| id == change.case.workflow_id)
+    }
+}
+
+/// `GET /cases/stream`: Server-Sent Events for cases created, moved or
+/// updated in the region from now on, optionally only in one workflow.
+/// Each event is named after the change and carries the case. A client
+/// that falls too far behind gets a `lagged` event with how many changes
+/// it missed, and should reload what it shows.
+pub async fn stream_cases(
+    State(state): State<AppState>,
+    region: Region,
+    Query(query): Query<CaseStreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let subscription = Subscription {
+        receiver: state.case_stream.subscribe(),
+        region: region.name,
+        workflow_id: query.workflow_id,
+    };
+
+    let events = futures::stream::unfold(subscription, |mut subscription| async move {
+        loop {
+            let event = match subscription.receiver.recv().await {
+                Ok(change) if subscription.wants(&change) => Event::default().event(change.kind.as_str()).json_data(&change),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(missed)) => Ok(Event::default().event("lagged").data(json!({"missed": missed}).to_string())),
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((event, subscription));
+        }
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,62 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::models::case::AddCaseTags;
+use crate::models::validation::MAX_CASE_TAGS;
+use crate::models::ErrorCode;
+use crate::repositories::CaseRepository;
+
+pub async fn add_case_tags(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<AddCaseTags>,
+) -> impl IntoResponse {
+    let case_repo = CaseRepository::new(&region.pool);
+
+    match case_repo.find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch case").into_parts();
+        }
+    }
+
+    match case_repo.add_tags(case_id, &payload.tags, MAX_CASE_TAGS).await {
+        Ok(Some(tags)) => {
+            info!("Tagged case {} with {:?}", case_id, payload.tags);
+            (StatusCode::OK, Json(json!({"case_id": case_id, "tags": tags})))
+        }
+        Ok(None) => {
+            ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("A case can have at most {} tags", MAX_CASE_TAGS),
+            )
+            .into_parts()
+        }
+        Err(err) => {
+            error!("Failed to tag case: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to tag case").into_parts()
+        }
+    }
+}
+
+/// Removing a tag the case does not have is not an error.
+pub async fn remove_case_tag(
+    region: Region,
+    Path((case_id, tag)): Path<(Uuid, String)>,
+) -> impl IntoResponse {
+    match CaseRepository::new(&region.pool).remove_tag(case_id, &tag).await {
+        Ok(Some(tags)) => (StatusCode::OK, Json(json!({"case_id": case_id, "tags": tags}))),
+        Ok(None) => ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to untag case: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to untag case").into_parts()
+        }
+    }
+}
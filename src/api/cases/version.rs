@@ -0,0 +1,43 @@
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    Json,
+};
+use serde_json::{json, Value};
+
+use crate::api::response::ApiError;
+use crate::models::ErrorCode;
+
+/// The `ETag` of a case at `version`.
+pub(super) fn etag(version: i32) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", version)).expect("a quoted number is a valid header value")
+}
+
+/// The version required by an `If-Match` header, as sent back from a
+/// case's `ETag`. No header, or `*`, requires none.
+pub(super) fn expected_version(headers: &HeaderMap) -> Result<Option<i32>, (StatusCode, Json<Value>)> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+
+    let tag = value.strip_prefix("W/").unwrap_or(value);
+    tag.strip_prefix('"')
+        .and_then(|tag| tag.strip_suffix('"'))
+        .unwrap_or(tag)
+        .parse::<i32>()
+        .map(Some)
+        .map_err(|_| {
+            ApiError::new(StatusCode::BAD_REQUEST, "If-Match must be a single case ETag, e.g. \"3\"").into_parts()
+        })
+}
+
+/// 409 for an update sent with an `If-Match` the case no longer matches.
+pub(super) fn version_mismatch(version: i32) -> (StatusCode, Json<Value>) {
+    ApiError::from_code(ErrorCode::VersionConflict, "Case was changed since it was read; reload it and try again")
+        .with_details(json!({"version": version}))
+        .into_parts()
+}
@@ -0,0 +1,263 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::outbox::dispatch_case_outbox;
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::api::AppState;
+use crate::models::case::{Case, MoveCase};
+use crate::models::membership::{CaseWorkflow, JoinWorkflow};
+use crate::models::outbox::OutboxMessage;
+use crate::models::phase::PhaseMove;
+use crate::models::ErrorCode;
+use crate::repositories::{CaseRepository, CaseWorkflowRepository, WorkflowRepository};
+use crate::services::CaseChangeKind;
+
+use super::move_case::{move_case, transition_not_allowed, wip_limit_reached};
+use super::version::version_mismatch;
+
+type ErrorResponse = (StatusCode, Json<serde_json::Value>);
+
+fn internal_error(message: &str) -> ErrorResponse {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message).into_parts()
+}
+
+fn membership_not_found() -> ErrorResponse {
+    ApiError::new(StatusCode::NOT_FOUND, "Case does not take part in this workflow").into_parts()
+}
+
+async fn fetch_case(case_repo: &CaseRepository<'_>, case_id: Uuid) -> Result<Case, ErrorResponse> {
+    match case_repo.find_by_id(case_id).await {
+        Ok(Some(case)) => Ok(case),
+        Ok(None) => Err(ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts()),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            Err(internal_error("Failed to fetch case"))
+        }
+    }
+}
+
+/// The case's own workflow first, then the ones it joined.
+pub async fn get_case_workflows(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    let case = match fetch_case(&CaseRepository::new(pool), case_id).await {
+        Ok(case) => case,
+        Err(response) => return response,
+    };
+
+    match CaseWorkflowRepository::new(pool).list_by_case(case_id).await {
+        Ok(joined) => {
+            let memberships: Vec<CaseWorkflow> = std::iter::once(CaseWorkflow::primary(&case)).chain(joined).collect();
+            (StatusCode::OK, Json(json!(memberships)))
+        }
+        Err(err) => {
+            error!("Failed to fetch case workflows: {}", err);
+            internal_error("Failed to fetch case workflows")
+        }
+    }
+}
+
+/// Adds the case to another active workflow in the same region, at the
+/// given phase or the workflow's initial one.
+pub async fn join_workflow(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<JoinWorkflow>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+    let case_repo = CaseRepository::new(pool);
+
+    let case = match fetch_case(&case_repo, case_id).await {
+        Ok(case) => case,
+        Err(response) => return response,
+    };
+
+    if case.workflow_id == payload.workflow_id {
+        return ApiError::new(StatusCode::CONFLICT, "The case already belongs to this workflow").into_parts();
+    }
+
+    let workflow = match WorkflowRepository::new(pool).find_active_by_id(payload.workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => {
+            return ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found, inactive or archived").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return internal_error("Failed to fetch workflow");
+        }
+    };
+
+    let phase = payload.phase.unwrap_or_else(|| workflow.initial_phase.clone());
+    if !workflow.has_phase(&phase) {
+        return ApiError::from_code(ErrorCode::PhaseInvalid, format!("Phase '{}' not found in workflow", phase)).into_parts();
+    }
+
+    let membership = CaseWorkflow::new(case_id, workflow.id, phase, payload.triggered_by.clone());
+    match CaseWorkflowRepository::new(pool).create(&membership).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return ApiError::new(StatusCode::CONFLICT, "The case already takes part in this workflow").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to add case to workflow: {}", err);
+            return internal_error("Failed to add case to workflow");
+        }
+    }
+
+    info!("Case {} joined workflow {} in '{}'", case_id, workflow.id, membership.current_phase);
+
+    let history = membership.history(None, None, payload.triggered_by);
+    if let Err(err) = case_repo.create_history(&history).await {
+        error!("Failed to create history entry: {}", err);
+    }
+
+    (StatusCode::CREATED, Json(json!(membership)))
+}
+
+/// Moves the case within one of its workflows. For its own workflow this is
+/// `PUT /cases/{id}/move`; in a joined one only the membership's phase
+/// changes, and that workflow's automations are not run.
+pub async fn move_case_in_workflow(
+    State(state): State<AppState>,
+    region: Region,
+    Path((case_id, workflow_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<MoveCase>,
+) -> Response {
+    let pool = &region.pool;
+    let case_repo = CaseRepository::new(pool);
+    let membership_repo = CaseWorkflowRepository::new(pool);
+
+    let case = match fetch_case(&case_repo, case_id).await {
+        Ok(case) => case,
+        Err(response) => return response.into_response(),
+    };
+
+    if case.workflow_id == workflow_id {
+        return move_case(State(state), region, Path(case_id), headers, ValidatedJson(payload))
+            .await
+            .into_response();
+    }
+
+    let mut membership = match membership_repo.find(case_id, workflow_id).await {
+        Ok(Some(membership)) => membership,
+        Ok(None) => return membership_not_found().into_response(),
+        Err(err) => {
+            error!("Failed to fetch case workflow: {}", err);
+            return internal_error("Failed to fetch case workflow").into_response();
+        }
+    };
+
+    let workflow = match WorkflowRepository::new(pool).find_by_id(workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => {
+            return ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_response()
+        }
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return internal_error("Failed to fetch workflow").into_response();
+        }
+    };
+
+    if !workflow.has_phase(&payload.to_phase) {
+        return ApiError::from_code(
+            ErrorCode::PhaseInvalid,
+            format!("Phase '{}' not found in workflow", payload.to_phase),
+        )
+        .into_response();
+    }
+
+    if membership.current_phase == payload.to_phase {
+        return (
+            StatusCode::OK,
+            Json(json!({"message": "Case already in target phase", "case": membership.scope(case)})),
+        )
+            .into_response();
+    }
+
+    if !workflow.allows_transition(&membership.current_phase, &payload.to_phase) {
+        return transition_not_allowed(&workflow, &membership.current_phase, &payload.to_phase).into_response();
+    }
+
+    let from_phase = membership.current_phase.clone();
+    membership.move_to_phase(payload.to_phase);
+
+    let webhook_on_move = std::env::var("WEBHOOK_ON_CASE_MOVE")
+        .unwrap_or_else(|_| "true".to_string())
+        .parse::<bool>()
+        .unwrap_or(true);
+    let webhook = workflow.webhook().filter(|_| webhook_on_move);
+    let outbox = OutboxMessage::case_moved(&membership.scope(case.clone()), Some(&from_phase), webhook);
+
+    let wip_limit = workflow.wip_limit(&membership.current_phase).filter(|_| case.status.is_in_progress());
+    match membership_repo.update_phase_and_enqueue(&membership, wip_limit, &outbox).await {
+        Ok(PhaseMove::Moved) => {}
+        Ok(PhaseMove::AtWipLimit { limit, in_progress }) => {
+            return wip_limit_reached(&membership.current_phase, limit, in_progress).into_response()
+        }
+        Ok(PhaseMove::NotFound) => return membership_not_found().into_response(),
+        Ok(PhaseMove::Stale { version }) => return version_mismatch(version).into_response(),
+        Err(err) => {
+            error!("Failed to move case: {}", err);
+            return internal_error("Failed to move case").into_response();
+        }
+    }
+
+    info!(
+        "Moved case {} in workflow {} from '{}' to '{}'",
+        case_id, workflow_id, from_phase, membership.current_phase
+    );
+
+    let history = membership.history(Some(from_phase.clone()), payload.reason, payload.triggered_by);
+    if let Err(err) = case_repo.create_history(&history).await {
+        error!("Failed to create history entry: {}", err);
+    }
+
+    let case = membership.scope(case);
+
+    dispatch_case_outbox(&state, &region, case_id);
+
+    state.case_stream.publish(CaseChangeKind::Moved, &region.name, &case);
+
+    (StatusCode::OK, Json(json!(case))).into_response()
+}
+
+pub async fn leave_workflow(
+    region: Region,
+    Path((case_id, workflow_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    let case = match fetch_case(&CaseRepository::new(pool), case_id).await {
+        Ok(case) => case,
+        Err(response) => return response,
+    };
+
+    if case.workflow_id == workflow_id {
+        return ApiError::new(StatusCode::CONFLICT, "A case cannot leave the workflow it was created in").into_parts();
+    }
+
+    match CaseWorkflowRepository::new(pool).delete(case_id, workflow_id).await {
+        Ok(true) => {
+            info!("Case {} left workflow {}", case_id, workflow_id);
+            (StatusCode::NO_CONTENT, Json(json!({})))
+        }
+        Ok(false) => membership_not_found(),
+        Err(err) => {
+            error!("Failed to remove case from workflow: {}", err);
+            internal_error("Failed to remove case from workflow")
+        }
+    }
+}
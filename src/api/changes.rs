@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use axum::{extract::Query, http::StatusCode, Json};
+use serde_json::{json, Value};
+use tokio::time::Instant;
+use tracing::error;
+
+use crate::api::{region::Region, response::ApiError};
+use crate::models::change::{ChangeCursor, ChangesQuery};
+use crate::repositories::ChangeRepository;
+
+/// How often a waiting request checks the change log.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// `GET /changes?since=cursor`: long-polls the region's change log for
+/// clients that cannot hold a stream open. Answers as soon as there are
+/// changes after `since`, or with none once `wait_secs` have passed; either
+/// way `cursor` is where the next request continues from.
+pub async fn get_changes(region: Region, Query(query): Query<ChangesQuery>) -> Result<Json<Value>, ApiError> {
+    let repo = ChangeRepository::new(&region.pool);
+    let internal_error = |err: anyhow::Error| {
+        error!("Failed to read change log: {}", err);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read change log")
+    };
+
+    let Some(since) = &query.since else {
+        let cursor = repo.latest().await.map_err(internal_error)?;
+        return Ok(Json(json!({"changes": [], "cursor": cursor.to_string()})));
+    };
+    let since: ChangeCursor = since.parse().map_err(|message| ApiError::new(StatusCode::BAD_REQUEST, message))?;
+
+    let deadline = Instant::now() + query.wait();
+    loop {
+        let changes = repo.since(since, query.limit()).await.map_err(internal_error)?;
+
+        if !changes.is_empty() || Instant::now() + POLL_INTERVAL > deadline {
+            let cursor = changes.last().map_or_else(|| since.to_string(), |change| change.cursor.clone());
+            return Ok(Json(json!({"changes": changes, "cursor": cursor})));
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
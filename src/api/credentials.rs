@@ -0,0 +1,52 @@
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::error;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::models::credential::ExpiringCredential;
+use crate::repositories::PortalTokenRepository;
+
+const EXPIRING_CREDENTIALS_LIMIT: i64 = 500;
+
+#[derive(Deserialize)]
+pub struct ExpiringQuery {
+    within_hours: Option<i64>,
+}
+
+/// Unrevoked credentials that expire within `within_hours` (default 72),
+/// including expired ones the scheduler has not revoked yet.
+pub async fn list_expiring_credentials(
+    region: Region,
+    Query(query): Query<ExpiringQuery>,
+) -> impl IntoResponse {
+    let within_hours = query.within_hours.unwrap_or(72).clamp(1, 8760);
+    let now = Utc::now();
+    let before = now + Duration::hours(within_hours);
+
+    match PortalTokenRepository::new(&region.pool)
+        .list_expiring(before, EXPIRING_CREDENTIALS_LIMIT)
+        .await
+    {
+        Ok(tokens) => {
+            let credentials: Vec<_> = tokens
+                .iter()
+                .filter_map(|token| ExpiringCredential::from_portal_token(token, now))
+                .collect();
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "within_hours": within_hours,
+                    "before": before,
+                    "credentials": credentials,
+                })),
+            )
+        }
+        Err(err) => {
+            error!("Failed to list expiring credentials: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list expiring credentials").into_parts()
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::error;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::models::email_log::EmailLogQuery;
+use crate::repositories::EmailLogRepository;
+
+/// `GET /admin/emails`: the region's email send log, newest first.
+pub async fn list_emails(region: Region, Query(query): Query<EmailLogQuery>) -> impl IntoResponse {
+    let status = match query.status() {
+        Ok(status) => status,
+        Err(message) => return ApiError::new(StatusCode::BAD_REQUEST, message).into_parts(),
+    };
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match EmailLogRepository::new(&region.read_pool).list(status, query.case_id, limit, offset).await {
+        Ok(emails) => (StatusCode::OK, Json(json!(emails))),
+        Err(err) => {
+            error!("Failed to list emails: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list emails").into_parts()
+        }
+    }
+}
@@ -1,23 +1,34 @@
-use crate::api::response::ApiError;
+use crate::api::response::{list_response, ApiError, Envelope};
 use crate::engine::{Executor, Matcher};
-use crate::models::{event::CreateEvent, Event, Flow};
-use axum::{extract::State, http::StatusCode, Json};
+use crate::models::event::{CreateEvent, EventDetails, EventSearch};
+use crate::models::execution::ExecutionStatus;
+use crate::models::snapshot::DefinitionSnapshot;
+use crate::models::{ErrorCode, Event};
+use crate::repositories::{DefinitionSnapshotRepository, EventRepository, ExecutionRepository, FlowRepository};
+use crate::services::execution_payloads::stored_execution;
+use crate::services::Secrets;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Response,
+    Json,
+};
 use serde_json::{json, Value};
-use sqlx::PgPool;
 use tracing::{error, info};
 use uuid::Uuid;
 
+use super::region::{Region, RegionSet};
 use super::AppState;
 
 #[axum::debug_handler]
 pub async fn create_event(
     State(state): State<AppState>,
+    region: Region,
     Json(payload): Json<CreateEvent>,
 ) -> Result<Json<Value>, ApiError> {
-    let pool = &state.pool;
     info!("Received event via API: {}", payload.event_type);
     let (event_id, execution_ids, matched_count) =
-        internal_create_and_trigger_event(&pool, payload).await?;
+        internal_create_and_trigger_event(&state, &region, payload).await?;
 
     Ok(Json(json!({
         "event_id": event_id,
@@ -26,50 +37,88 @@ pub async fn create_event(
     })))
 }
 
+const DEFAULT_EVENT_LIMIT: i64 = 50;
+const MAX_EVENT_LIMIT: i64 = 100;
+
+/// Stored events, newest first, filtered by `event_type`, a `since`/`until`
+/// range on `received_at` and `data.*` conditions.
+pub async fn list_events(
+    regions: RegionSet,
+    envelope: Envelope,
+    Query(params): Query<Vec<(String, String)>>,
+) -> Result<Response, ApiError> {
+    let search = EventSearch::from_params(&params).map_err(|message| ApiError::new(StatusCode::BAD_REQUEST, message))?;
+
+    let limit = search.limit.unwrap_or(DEFAULT_EVENT_LIMIT).clamp(1, MAX_EVENT_LIMIT);
+    let offset = search.offset.unwrap_or(0).max(0);
+    let filter = &search.filter;
+
+    let events = regions
+        .list(Some(limit), offset, |event: &Event| event.received_at, |region, limit, offset| async move {
+            EventRepository::new(&region.read_pool).search(filter, limit, offset).await
+        })
+        .await;
+
+    let events = match events {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Failed to list events: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
+
+    let total = regions.count(|region| async move { EventRepository::new(&region.read_pool).count(filter).await });
+
+    list_response(envelope, events, Some(limit), offset, total).await
+}
+
+/// The event with the executions it triggered.
+pub async fn get_event(region: Region, Path(id): Path<Uuid>) -> Result<Json<EventDetails>, ApiError> {
+    let pool = &region.read_pool;
+
+    let event = match EventRepository::new(pool).find_by_id(id).await {
+        Ok(Some(event)) => event,
+        Ok(None) => return Err(ApiError::from_code(ErrorCode::EventNotFound, "Event not found")),
+        Err(e) => {
+            error!("Failed to get event: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
+
+    match ExecutionRepository::new(pool).list_by_event(id).await {
+        Ok(executions) => Ok(Json(EventDetails { event, executions })),
+        Err(e) => {
+            error!("Failed to get event executions: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into())
+        }
+    }
+}
+
+/// Stores the event in `region`, sends it to the webhook subscriptions
+/// listening for it and runs the active flows defined there.
 pub(crate) async fn internal_create_and_trigger_event(
-    pool: &PgPool,
+    state: &AppState,
+    region: &Region,
     payload: CreateEvent,
 ) -> Result<(Uuid, Vec<Uuid>, usize), ApiError> {
-    let event = Event::new(payload);
-
-    if let Err(e) = sqlx::query(
-        r#"
-        INSERT INTO orchepy_events (id, event_type, data, metadata, received_at)
-        VALUES ($1, $2, $3, $4, $5)
-        "#,
-    )
-    .bind(&event.id)
-    .bind(&event.event_type)
-    .bind(&event.data)
-    .bind(&event.metadata)
-    .bind(&event.received_at)
-    .execute(pool)
-    .await
-    {
+    let pool = &region.pool;
+    let mut event = Event::new(payload);
+    event.region = Some(region.name.clone());
+
+    if let Err(e) = EventRepository::new(pool).create(&event).await {
         error!("Failed to save event: {}", e);
-        return Err(ApiError {
-            status: StatusCode::INTERNAL_SERVER_ERROR,
-            message: "Failed to save event".to_string(),
-        });
+        return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to save event"));
     }
 
-    let flows = match sqlx::query_as::<_, Flow>(
-        r#"
-        SELECT id, name, trigger, steps, active, created_at, updated_at
-        FROM orchepy_flows
-        WHERE active = true
-        "#,
-    )
-    .fetch_all(pool)
-    .await
-    {
+    state
+        .webhook_sender
+        .notify_subscribers(pool.clone(), event.event_type.clone(), event.id, event.data.clone());
+
+    let flows = match FlowRepository::new(pool).list_active().await {
         Ok(w) => w,
         Err(e) => {
             error!("Failed to load flows: {}", e);
-            return Err(ApiError {
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to load flows".to_string(),
-            });
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load flows"));
         }
     };
 
@@ -77,37 +126,36 @@ pub(crate) async fn internal_create_and_trigger_event(
     let matched_count = matched.len();
     info!("Matched {} flow(s) for event {}", matched_count, event.id);
 
-    let executor = Executor::new();
+    let secrets = Secrets::for_definition(pool, state.secrets.as_ref(), &matched).await;
+    let executor = Executor::new().with_secrets(secrets);
     let mut execution_ids = Vec::new();
 
     for flow in matched {
         info!("Triggering flow: {} for event {}", flow.name, event.id);
 
-        match executor.execute(flow, &event).await {
-            Ok(execution) => {
+        let snapshot = DefinitionSnapshot::of_flow(flow);
+        if let Err(e) = DefinitionSnapshotRepository::new(pool).save(&snapshot).await {
+            error!("Failed to save definition snapshot of flow '{}': {}", flow.name, e);
+        }
+
+        let permit = state.flow_limiter.acquire(flow).await;
+        let result = executor.execute(flow, &event).await;
+        drop(permit);
+
+        match result {
+            Ok(mut execution) => {
+                execution.definition_hash = Some(snapshot.hash);
                 execution_ids.push(execution.id);
 
-                if let Err(e) = sqlx::query(
-                    r#"
-                    INSERT INTO orchepy_executions
-                    (id, flow_id, event_id, status, current_step, steps_status, started_at, completed_at, error)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-                    "#,
-                )
-                .bind(&execution.id)
-                .bind(&execution.flow_id)
-                .bind(&execution.event_id)
-                .bind(&execution.status)
-                .bind(&execution.current_step)
-                .bind(&execution.steps_status)
-                .bind(&execution.started_at)
-                .bind(&execution.completed_at)
-                .bind(&execution.error)
-                .execute(pool)
-                .await
-                {
+                let stored = stored_execution(state.execution_payloads.as_ref(), &execution).await;
+                if let Err(e) = ExecutionRepository::new(pool).create(&stored).await {
                     error!("Failed to save execution: {}", e);
                 }
+
+                if matches!(execution.status, ExecutionStatus::Failed) {
+                    state.webhook_sender.notify_execution_failed(pool.clone(), &execution, &flow.name, &event);
+                }
+                state.event_publisher.publish_execution(&execution, &flow.name, &event);
             }
             Err(e) => {
                 error!("Failed to execute flow '{}': {}", flow.name, e);
@@ -1,95 +1,153 @@
-use crate::api::response::ApiError;
-use crate::models::execution::Execution;
+use crate::api::fields::Fields;
+use crate::api::region::{Region, RegionSet};
+use crate::api::response::{list_response, ApiError, Envelope};
+use crate::models::execution::{Execution, ExecutionFilter, ExecutionStep};
+use crate::models::snapshot::WithDefinition;
+use crate::models::step::Step;
+use crate::models::ErrorCode;
+use crate::repositories::{DefinitionSnapshotRepository, ExecutionRepository, FlowRepository};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
+    response::Response,
     Json,
 };
 use serde::Deserialize;
-use tracing::error;
+use sqlx::PgPool;
+use tracing::{error, warn};
 use uuid::Uuid;
 
 use super::AppState;
 
 #[derive(Deserialize)]
 pub struct ListQuery {
-    status: Option<String>,
-    flow_id: Option<Uuid>,
     limit: Option<i64>,
+    offset: Option<i64>,
 }
 
 pub async fn list_executions(
-    State(state): State<AppState>,
+    regions: RegionSet,
+    envelope: Envelope,
+    fields: Fields,
+    Query(filter): Query<ExecutionFilter>,
     Query(query): Query<ListQuery>,
-) -> Result<Json<Vec<Execution>>, ApiError> {
-    let pool = &state.pool;
-    let mut sql = String::from(
-        r#"
-        SELECT id, flow_id, event_id, status, current_step, steps_status,
-               started_at, completed_at, error
-        FROM orchepy_executions
-        WHERE 1=1
-        "#,
-    );
-
-    let mut params: Vec<String> = Vec::new();
-
-    if let Some(status) = &query.status {
-        params.push(format!("status = '{}'", status));
-    }
+) -> Result<Response, ApiError> {
+    fields.validate::<Execution>()?;
+    let limit = query.limit.unwrap_or(100).max(1);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let filter = &filter;
 
-    if let Some(flow_id) = query.flow_id {
-        params.push(format!("flow_id = '{}'", flow_id));
-    }
+    let executions = regions
+        .list(Some(limit), offset, |execution: &Execution| execution.started_at, |region, limit, offset| async move {
+            ExecutionRepository::new(&region.read_pool).list(filter, limit, offset).await
+        })
+        .await;
 
-    if !params.is_empty() {
-        sql.push_str(" AND ");
-        sql.push_str(&params.join(" AND "));
-    }
+    let executions = match executions {
+        Ok(executions) => executions,
+        Err(e) => {
+            error!("Failed to list executions: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
 
-    sql.push_str(" ORDER BY started_at DESC");
+    let total = regions.count(|region| async move { ExecutionRepository::new(&region.read_pool).count(filter).await });
 
-    if let Some(limit) = query.limit {
-        sql.push_str(&format!(" LIMIT {}", limit));
-    } else {
-        sql.push_str(" LIMIT 100");
-    }
+    list_response(envelope, fields.project(executions), Some(limit), offset, total).await
+}
 
-    match sqlx::query_as::<_, Execution>(&sql).fetch_all(pool).await {
-        Ok(executions) => Ok(Json(executions)),
+/// The execution with the flow definition it ran, and its offloaded
+/// payloads back in place.
+pub async fn get_execution(
+    State(state): State<AppState>,
+    region: Region,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WithDefinition<Execution>>, ApiError> {
+    let pool = &region.read_pool;
+    let mut execution = match ExecutionRepository::new(pool).find_by_id(id).await {
+        Ok(Some(execution)) => execution,
+        Ok(None) => return Err(ApiError::from_code(ErrorCode::ExecutionNotFound, "Execution not found")),
         Err(e) => {
-            error!("Failed to list executions: {}", e);
+            error!("Failed to get execution: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
+
+    restore_payloads(&state, &mut execution).await;
+
+    let snapshot = match &execution.definition_hash {
+        Some(hash) => DefinitionSnapshotRepository::new(pool).find(hash).await,
+        None => Ok(None),
+    };
+    match snapshot {
+        Ok(snapshot) => Ok(Json(WithDefinition {
+            run: execution,
+            definition: snapshot.map(|snapshot| snapshot.definition),
+        })),
+        Err(e) => {
+            error!("Failed to get definition snapshot: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR.into())
         }
     }
 }
 
-pub async fn get_execution(
+/// The execution's steps in flow order, with what each one sent, received
+/// and how long it took.
+pub async fn get_execution_steps(
     State(state): State<AppState>,
+    region: Region,
     Path(id): Path<Uuid>,
-) -> Result<Json<Execution>, ApiError> {
-    let pool = &state.pool;
-    match sqlx::query_as::<_, Execution>(
-        r#"
-        SELECT id, flow_id, event_id, status, current_step, steps_status,
-               started_at, completed_at, error
-        FROM orchepy_executions
-        WHERE id = $1
-        "#,
-    )
-    .bind(id)
-    .fetch_one(pool)
-    .await
-    {
-        Ok(execution) => Ok(Json(execution)),
-        Err(sqlx::Error::RowNotFound) => Err(StatusCode::NOT_FOUND.into()),
+) -> Result<Json<Vec<ExecutionStep>>, ApiError> {
+    let pool = &region.read_pool;
+    let mut execution = match ExecutionRepository::new(pool).find_by_id(id).await {
+        Ok(Some(execution)) => execution,
+        Ok(None) => return Err(ApiError::from_code(ErrorCode::ExecutionNotFound, "Execution not found")),
         Err(e) => {
             error!("Failed to get execution: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into());
+        }
+    };
+
+    restore_payloads(&state, &mut execution).await;
+
+    match definition_steps(pool, &execution).await {
+        Ok(steps) => Ok(Json(execution.steps(&steps))),
+        Err(e) => {
+            error!("Failed to get the flow definition of execution {}: {}", id, e);
             Err(StatusCode::INTERNAL_SERVER_ERROR.into())
         }
     }
 }
 
+/// Loads the payloads offloaded to object storage; ones that can't be read
+/// keep their `$offloaded` reference.
+async fn restore_payloads(state: &AppState, execution: &mut Execution) {
+    if let Some(payloads) = &state.execution_payloads {
+        if let Err(err) = payloads.restore(execution).await {
+            warn!("Failed to restore offloaded payloads of execution {}: {}", execution.id, err);
+        }
+    }
+}
+
+/// The steps of the definition the execution ran: its snapshot, or the
+/// flow version it is pinned to for executions recorded before snapshots.
+async fn definition_steps(pool: &PgPool, execution: &Execution) -> anyhow::Result<Vec<Step>> {
+    if let Some(hash) = &execution.definition_hash {
+        if let Some(snapshot) = DefinitionSnapshotRepository::new(pool).find(hash).await? {
+            let steps = snapshot.definition.get("steps").cloned().unwrap_or_default();
+            return Ok(serde_json::from_value(steps)?);
+        }
+    }
+
+    if let Some(version) = execution.flow_version {
+        if let Some(flow_version) = FlowRepository::new(pool).find_version(execution.flow_id, version).await? {
+            return Ok(flow_version.to_flow().steps);
+        }
+    }
+
+    Ok(Vec::new())
+}
+
 pub async fn retry_execution(
     State(_state): State<AppState>,
     Path(_id): Path<Uuid>,
@@ -0,0 +1,263 @@
+//! Sparse fieldsets: `?fields=id,current_phase,data.amount` on list
+//! endpoints returns only those fields of each item, so pollers don't pull
+//! whole JSON documents.
+
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+};
+use serde::{ser::Error as _, Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::api::response::ApiError;
+use crate::models::case::ListedCase;
+use crate::models::execution::Execution;
+use crate::models::Case;
+
+const MAX_FIELDS: usize = 50;
+const MAX_FIELD_DEPTH: usize = 8;
+
+/// An item that list endpoints can return with `?fields=`.
+pub trait SparseFields: Serialize {
+    /// Top-level fields a client may ask for.
+    const FIELDS: &'static [&'static str];
+    /// Fields holding free-form JSON, which a path may reach into, e.g.
+    /// `data.customer.tier`.
+    const JSON_FIELDS: &'static [&'static str];
+}
+
+impl SparseFields for Case {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "workflow_id",
+        "current_phase",
+        "previous_phase",
+        "data",
+        "status",
+        "metadata",
+        "created_at",
+        "updated_at",
+        "completed_at",
+        "phase_entered_at",
+        "region",
+        "deleted_at",
+        "field_provenance",
+        "comment_count",
+        "tags",
+        "external_id",
+        "version",
+    ];
+    const JSON_FIELDS: &'static [&'static str] = &["data", "metadata", "field_provenance"];
+}
+
+impl SparseFields for ListedCase {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "workflow_id",
+        "current_phase",
+        "previous_phase",
+        "data",
+        "status",
+        "metadata",
+        "created_at",
+        "updated_at",
+        "completed_at",
+        "phase_entered_at",
+        "region",
+        "deleted_at",
+        "field_provenance",
+        "comment_count",
+        "tags",
+        "external_id",
+        "version",
+        "workflow",
+    ];
+    const JSON_FIELDS: &'static [&'static str] = &["data", "metadata", "field_provenance", "workflow"];
+}
+
+impl SparseFields for Execution {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "flow_id",
+        "event_id",
+        "flow_version",
+        "definition_hash",
+        "status",
+        "current_step",
+        "steps_status",
+        "started_at",
+        "completed_at",
+        "error",
+        "resume_at",
+        "resume_step",
+    ];
+    const JSON_FIELDS: &'static [&'static str] = &["steps_status"];
+}
+
+#[derive(Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+/// The paths asked for with `?fields=`, or `None` for whole items.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fields(pub Option<Vec<Vec<String>>>);
+
+impl Fields {
+    pub fn parse(fields: &str) -> Result<Self, String> {
+        let paths: Vec<Vec<String>> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| field.split('.').map(str::to_string).collect())
+            .collect();
+
+        if paths.is_empty() || paths.len() > MAX_FIELDS {
+            return Err(format!("fields must list between 1 and {} fields", MAX_FIELDS));
+        }
+
+        let valid_segment = |segment: &String| {
+            !segment.is_empty() && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+        };
+        let invalid = |path: &&Vec<String>| path.len() > MAX_FIELD_DEPTH || !path.iter().all(valid_segment);
+        if let Some(path) = paths.iter().find(invalid) {
+            return Err(format!("Invalid field '{}'", path.join(".")));
+        }
+
+        Ok(Self(Some(paths)))
+    }
+
+    /// Checks the paths against `T`'s fields, before the list is loaded.
+    pub fn validate<T: SparseFields>(&self) -> Result<(), ApiError> {
+        for path in self.0.iter().flatten() {
+            let field = path[0].as_str();
+            if !T::FIELDS.contains(&field) {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Unknown field '{}'; expected one of: {}", field, T::FIELDS.join(", ")),
+                ));
+            }
+            if path.len() > 1 && !T::JSON_FIELDS.contains(&field) {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Field '{}' has no nested fields", field),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Wraps each item so it serializes with only the requested paths.
+    pub fn project<T: SparseFields>(&self, items: Vec<T>) -> Vec<Sparse<'_, T>> {
+        items
+            .into_iter()
+            .map(|item| Sparse {
+                item,
+                paths: self.0.as_deref(),
+            })
+            .collect()
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Fields {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let fields = Query::<FieldsQuery>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|Query(query)| query.fields);
+
+        match fields {
+            Some(fields) => Self::parse(&fields).map_err(|message| ApiError::new(StatusCode::BAD_REQUEST, message)),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// A listed item, serialized whole or with only the requested paths. Paths
+/// the item doesn't have are left out.
+pub struct Sparse<'a, T> {
+    item: T,
+    paths: Option<&'a [Vec<String>]>,
+}
+
+impl<T: Serialize> Serialize for Sparse<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(paths) = self.paths else {
+            return self.item.serialize(serializer);
+        };
+
+        let item = serde_json::to_value(&self.item).map_err(S::Error::custom)?;
+        let mut projected = Map::new();
+        for path in paths {
+            if let Some(value) = path.iter().try_fold(&item, |value, key| value.get(key)) {
+                insert_path(&mut projected, path, value.clone());
+            }
+        }
+        projected.serialize(serializer)
+    }
+}
+
+fn insert_path(object: &mut Map<String, Value>, path: &[String], value: Value) {
+    let [key, rest @ ..] = path else {
+        return;
+    };
+    if rest.is_empty() {
+        object.insert(key.clone(), value);
+        return;
+    }
+
+    if let Value::Object(inner) = object.entry(key.clone()).or_insert_with(|| Value::Object(Map::new())) {
+        insert_path(inner, rest, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn case() -> Case {
+        Case::new(
+            Uuid::new_v4(),
+            "New".to_string(),
+            json!({"amount": 10, "customer": {"tier": "gold", "name": "Acme"}}),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_case_fields_match_serialization() {
+        let value = serde_json::to_value(case()).unwrap();
+        let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        let mut fields = Case::FIELDS.to_vec();
+        keys.sort();
+        fields.sort();
+        assert_eq!(keys, fields);
+    }
+
+    #[test]
+    fn test_project_paths() {
+        let case = case();
+        let fields = Fields::parse("id, current_phase,data.amount,data.customer.tier,data.missing").unwrap();
+        assert!(fields.validate::<Case>().is_ok());
+        let projected = serde_json::to_value(fields.project(vec![case.clone()])).unwrap();
+        assert_eq!(
+            projected,
+            json!([{
+                "id": case.id,
+                "current_phase": "New",
+                "data": {"amount": 10, "customer": {"tier": "gold"}}
+            }])
+        );
+
+        let whole = serde_json::to_value(Fields::default().project(vec![case.clone()])).unwrap();
+        assert_eq!(whole, json!([case]));
+
+        assert!(Fields::parse("").is_err());
+        assert!(Fields::parse("data..amount").is_err());
+        assert!(Fields::parse("secret").unwrap().validate::<Case>().is_err());
+        assert!(Fields::parse("status.name").unwrap().validate::<Case>().is_err());
+    }
+}
@@ -1,126 +1,203 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use tracing::{error, info};
 use uuid::Uuid;
+use validator::Validate;
 
-use crate::api::{response::ApiError, AppState}; 
-use crate::models::flow::{CreateFlow, Flow, UpdateFlow};
+use crate::api::{
+    region::{Region, RegionSet},
+    response::{list_response, ApiError, Envelope, PageQuery},
+    validation::{field_messages, ValidatedJson},
+};
+use crate::engine::{Executor, Matcher, MockResponse, ReplayReport, Simulation};
+use crate::models::event::{CreateEvent, Event, EventFilter};
+use crate::models::flow::{CreateFlow, Flow, FlowTrigger, UpdateFlow};
+use crate::models::step::Step;
+use crate::models::validation::validate_steps;
+use crate::models::ErrorCode;
+use crate::repositories::{EventRepository, ExecutionRepository, FlowRepository, WorkflowRepository};
+use crate::services::clock::VirtualClock;
+
+#[derive(Deserialize)]
+pub struct LatencyQuery {
+    days: Option<i64>,
+}
+
+fn default_use_recorded() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct SimulateFlow {
+    /// Defaults to the flow's trigger event type.
+    event_type: Option<String>,
+    #[serde(default)]
+    data: Value,
+    metadata: Option<Value>,
+    /// Responses for webhook steps, keyed by step name.
+    #[serde(default)]
+    mocks: HashMap<String, MockResponse>,
+    /// Fall back to each step's last recorded response when no mock is given.
+    #[serde(default = "default_use_recorded")]
+    use_recorded: bool,
+    /// The time the simulation starts at, for checking `delay_until`
+    /// deadlines as of another moment. Defaults to now.
+    now: Option<DateTime<Utc>>,
+}
+
+fn default_replay_limit() -> i64 {
+    50
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ReplaySample {
+    /// Edited trigger and steps to test; the saved ones are used when omitted.
+    #[validate(nested)]
+    trigger: Option<FlowTrigger>,
+    #[validate(custom(function = "crate::models::validation::validate_steps"))]
+    steps: Option<Vec<Step>>,
+    /// Defaults to events of the trigger's event type.
+    #[serde(default)]
+    filter: EventFilter,
+    #[serde(default = "default_replay_limit")]
+    #[validate(range(min = 1, max = 500, message = "must be between 1 and 500"))]
+    limit: i64,
+    #[serde(default)]
+    mocks: HashMap<String, MockResponse>,
+    #[serde(default = "default_use_recorded")]
+    use_recorded: bool,
+}
 
 pub async fn create_flow(
-    State(state): State<AppState>,
-    Json(payload): Json<CreateFlow>,
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<CreateFlow>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let pool = &state.pool;
+    let pool = &region.pool;
+
+    if let Some(message) = case_scope_error(pool, &payload.trigger).await? {
+        return Ok(case_scope_rejection(message));
+    }
+
     let flow = Flow::new(payload);
 
-    match sqlx::query(
-        "INSERT INTO orchepy_flows (id, name, trigger, steps, active, created_at, updated_at)
-         VALUES ($1, $2, $3, $4, $5, $6, $7)",
-    )
-    .bind(flow.id)
-    .bind(&flow.name)
-    .bind(serde_json::to_value(&flow.trigger)?) 
-    .bind(serde_json::to_value(&flow.steps)?) 
-    .bind(flow.active)
-    .bind(flow.created_at)
-    .bind(flow.updated_at)
-    .execute(pool)
-    .await
-    {
-        Ok(_) => {
+    match FlowRepository::new(pool).create(&flow).await {
+        Ok(()) => {
             info!("Created flow {} ({})", flow.id, flow.name);
-            Ok((StatusCode::CREATED, Json(json!(flow)))) 
+            Ok((StatusCode::CREATED, Json(json!(flow))))
         }
         Err(err) => {
             error!("Failed to create flow: {}", err);
-            Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to create flow".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to create flow"))
         }
     }
 }
 
+/// Checks that a case-scoped trigger points at an existing workflow and at
+/// phases that workflow actually has.
+async fn case_scope_error(pool: &PgPool, trigger: &FlowTrigger) -> Result<Option<String>, ApiError> {
+    let Some(scope) = &trigger.case else {
+        return Ok(None);
+    };
+
+    let workflow = WorkflowRepository::new(pool)
+        .find_by_id(scope.workflow_id)
+        .await
+        .map_err(|err| {
+            error!("Failed to fetch workflow: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow")
+        })?;
+
+    let Some(workflow) = workflow else {
+        return Ok(Some(format!("workflow {} not found", scope.workflow_id)));
+    };
+
+    for phase in [&scope.phase, &scope.from_phase].into_iter().flatten() {
+        if !workflow.has_phase(phase) {
+            return Ok(Some(format!(
+                "phase '{}' does not exist in workflow '{}'",
+                phase, workflow.name
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+fn case_scope_rejection(message: String) -> (StatusCode, Json<serde_json::Value>) {
+    field_rejection("trigger.case", message)
+}
+
+/// The 422 `ValidatedJson` gives, for a field checked by hand.
+fn field_rejection(field: &str, message: String) -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::from_code(ErrorCode::ValidationFailed, "Validation failed")
+        .with_details(json!({"fields": {field: [message]}}))
+        .into_parts()
+}
+
 pub async fn get_flow(
-    State(state): State<AppState>,
+    region: Region,
     Path(flow_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    
-    let pool = &state.pool;
-    match sqlx::query_as::<_, Flow>("SELECT * FROM orchepy_flows WHERE id = $1")
-        .bind(flow_id)
-        .fetch_optional(pool)
-        .await
-    {
-        Ok(Some(flow)) => Ok((StatusCode::OK, Json(json!(flow)))), 
-        Ok(None) => Ok((
-            
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Flow not found"})),
-        )),
+    match FlowRepository::new(&region.read_pool).find_by_id(flow_id).await {
+        Ok(Some(flow)) => Ok((StatusCode::OK, Json(json!(flow)))),
+        Ok(None) => Ok(ApiError::from_code(ErrorCode::FlowNotFound, "Flow not found").into_parts()),
         Err(err) => {
             error!("Failed to fetch flow: {}", err);
-            Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to fetch flow".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flow"))
         }
     }
 }
 
-pub async fn list_flows(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
-    
-    let pool = &state.pool;
-    match sqlx::query_as::<_, Flow>("SELECT * FROM orchepy_flows ORDER BY created_at DESC")
-        .fetch_all(pool)
-        .await
-    {
-        Ok(flows) => Ok((StatusCode::OK, Json(json!(flows)))), 
+pub async fn list_flows(
+    regions: RegionSet,
+    envelope: Envelope,
+    Query(page): Query<PageQuery>,
+) -> Result<Response, ApiError> {
+    let offset = page.offset.unwrap_or(0);
+
+    let flows = regions
+        .list(page.limit, offset, |item: &Flow| item.created_at, |region, limit, offset| async move {
+            FlowRepository::new(&region.read_pool).list(limit, offset).await
+        })
+        .await;
+
+    match flows {
+        Ok(flows) => {
+            let total = regions.count(|region| async move { FlowRepository::new(&region.read_pool).count().await });
+            list_response(envelope, flows, page.limit, offset, total).await
+        }
         Err(err) => {
             error!("Failed to list flows: {}", err);
-            Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to list flows".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list flows"))
         }
     }
 }
 
 pub async fn update_flow(
-    State(state): State<AppState>,
+    region: Region,
     Path(flow_id): Path<Uuid>,
     Json(payload): Json<UpdateFlow>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let pool = &state.pool;
+    let pool = &region.pool;
 
-    let mut flow = match sqlx::query_as::<_, Flow>("SELECT * FROM orchepy_flows WHERE id = $1")
-        .bind(flow_id)
-        .fetch_optional(pool)
-        .await
-    {
+    let mut flow = match FlowRepository::new(pool).find_by_id(flow_id).await {
         Ok(Some(f)) => f,
         Ok(None) => {
-            return Ok((
-                
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Flow not found"})),
-            ));
+            return Ok(ApiError::from_code(ErrorCode::FlowNotFound, "Flow not found").into_parts());
         }
         Err(err) => {
             error!("Failed to fetch flow: {}", err);
-            return Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to fetch flow".to_string(),
-            });
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flow"));
         }
     };
 
@@ -128,73 +205,274 @@ pub async fn update_flow(
         flow.name = name;
     }
     if let Some(trigger) = payload.trigger {
+        if let Err(errors) = trigger.validate() {
+            let fields: BTreeMap<_, _> = field_messages(&errors)
+                .into_iter()
+                .map(|(field, messages)| (format!("trigger.{}", field), messages))
+                .collect();
+            return Ok(ApiError::from_code(ErrorCode::ValidationFailed, "Validation failed")
+                .with_details(json!({"fields": fields}))
+                .into_parts());
+        }
+        if let Some(message) = case_scope_error(pool, &trigger).await? {
+            return Ok(case_scope_rejection(message));
+        }
         flow.trigger = trigger;
     }
     if let Some(steps) = payload.steps {
+        if let Err(err) = validate_steps(&steps) {
+            let message = err.message.map(|message| message.to_string()).unwrap_or_else(|| format!("invalid ({})", err.code));
+            return Ok(field_rejection("steps", message));
+        }
         flow.steps = steps;
     }
+    if let Some(limit) = payload.max_concurrent_executions {
+        if limit.is_some_and(|limit| limit < 1) {
+            return Ok(field_rejection("max_concurrent_executions", "must be at least 1".to_string()));
+        }
+        flow.max_concurrent_executions = limit;
+    }
     if let Some(active) = payload.active {
         flow.active = active;
     }
 
     flow.updated_at = chrono::Utc::now();
+    flow.version += 1;
 
-    match sqlx::query(
-        "UPDATE orchepy_flows SET name = $1, trigger = $2, steps = $3, active = $4, updated_at = $5 WHERE id = $6"
-    )
-    .bind(&flow.name)
-    .bind(serde_json::to_value(&flow.trigger)?) 
-    .bind(serde_json::to_value(&flow.steps)?)   
-    .bind(flow.active)
-    .bind(flow.updated_at)
-    .bind(flow_id)
-    .execute(pool)
-    .await
-    {
-        Ok(_) => {
-            info!("Updated flow {}", flow_id);
-            Ok((StatusCode::OK, Json(json!(flow)))) 
+    match FlowRepository::new(pool).update(&flow).await {
+        Ok(true) => {
+            info!("Updated flow {} (version {})", flow_id, flow.version);
+            Ok((StatusCode::OK, Json(json!(flow))))
         }
+        Ok(false) => Ok(ApiError::from_code(
+            ErrorCode::VersionConflict,
+            "Flow was changed while it was being updated; try again",
+        )
+        .into_parts()),
         Err(err) => {
             error!("Failed to update flow: {}", err);
-            Err(ApiError { 
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to update flow".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update flow"))
         }
     }
 }
 
-pub async fn delete_flow(
-    State(state): State<AppState>,
+pub async fn list_flow_versions(
+    region: Region,
     Path(flow_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    
-    let pool = &state.pool;
-    match sqlx::query("DELETE FROM orchepy_flows WHERE id = $1")
-        .bind(flow_id)
-        .execute(pool)
+    match FlowRepository::new(&region.pool).list_versions(flow_id).await {
+        Ok(versions) if versions.is_empty() => Ok(ApiError::from_code(ErrorCode::FlowNotFound, "Flow not found").into_parts()),
+        Ok(versions) => Ok((StatusCode::OK, Json(json!(versions)))),
+        Err(err) => {
+            error!("Failed to list flow versions: {}", err);
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list flow versions"))
+        }
+    }
+}
+
+pub async fn get_flow_latency(
+    region: Region,
+    Path(flow_id): Path<Uuid>,
+    Query(query): Query<LatencyQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let days = query.days.unwrap_or(7).clamp(1, 90);
+    let since = Utc::now() - Duration::days(days);
+
+    let repo = ExecutionRepository::new(&region.read_pool);
+    let result = async {
+        let executions = repo.flow_latency(flow_id, since).await?;
+        let steps = repo.step_latency(flow_id, since).await?;
+        anyhow::Ok((executions, steps))
+    }
+    .await;
+
+    let (executions, steps) = result.map_err(|err| {
+        error!("Failed to load flow latency: {}", err);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load flow latency")
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "flow_id": flow_id,
+            "since": since,
+            "days": days,
+            "executions": executions,
+            "steps": steps,
+        })),
+    ))
+}
+
+pub async fn simulate_flow(
+    region: Region,
+    Path(flow_id): Path<Uuid>,
+    Json(payload): Json<SimulateFlow>,
+) -> Result<impl IntoResponse, ApiError> {
+    let pool = &region.pool;
+
+    let flow = match FlowRepository::new(pool).find_by_id(flow_id).await {
+        Ok(Some(flow)) => flow,
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::FlowNotFound, "Flow not found").into_parts());
+        }
+        Err(err) => {
+            error!("Failed to fetch flow: {}", err);
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flow"));
+        }
+    };
+
+    let recorded = if payload.use_recorded {
+        ExecutionRepository::new(pool)
+            .recorded_responses(flow_id)
+            .await
+            .map_err(|err| {
+                error!("Failed to load recorded responses: {}", err);
+                ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load recorded responses")
+            })?
+    } else {
+        HashMap::new()
+    };
+
+    let event = Event::new(CreateEvent {
+        event_type: payload
+            .event_type
+            .unwrap_or_else(|| flow.trigger.event_type.clone()),
+        data: payload.data,
+        metadata: payload.metadata,
+    });
+    let trigger_matched = Matcher::matches_trigger(&event, &flow.trigger);
+
+    let simulation = Arc::new(Simulation::new(payload.mocks, recorded));
+    let clock = VirtualClock::new(payload.now.unwrap_or_else(Utc::now));
+    let execution = Executor::simulated(simulation.clone())
+        .with_clock(Arc::new(clock))
+        .execute(&flow, &event)
         .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                info!("Deleted flow {}", flow_id);
-                Ok((StatusCode::NO_CONTENT, Json(json!({})))) 
-            } else {
-                Ok((
-                    
-                    StatusCode::NOT_FOUND,
-                    Json(json!({"error": "Flow not found"})),
-                ))
-            }
+        .map_err(|err| {
+            error!("Failed to simulate flow {}: {}", flow_id, err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to simulate flow: {}", err))
+        })?;
+
+    let steps: Vec<Value> = flow
+        .steps
+        .iter()
+        .filter_map(|step| {
+            let mut status = execution.steps_status.get(&step.name)?.clone();
+            status.as_object_mut()?.insert("name".to_string(), json!(step.name));
+            Some(status)
+        })
+        .collect();
+
+    info!("Simulated flow {} ({} request(s) stubbed)", flow_id, simulation.requests().len());
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "flow_id": flow.id,
+            "flow_version": flow.version,
+            "trigger_matched": trigger_matched,
+            "status": execution.status,
+            "error": execution.error,
+            "steps": steps,
+            "requests": simulation.requests(),
+        })),
+    ))
+}
+
+pub async fn delete_flow(
+    region: Region,
+    Path(flow_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    match FlowRepository::new(&region.pool).delete(flow_id).await {
+        Ok(true) => {
+            info!("Deleted flow {}", flow_id);
+            Ok((StatusCode::NO_CONTENT, Json(json!({}))))
         }
+        Ok(false) => Ok(ApiError::from_code(ErrorCode::FlowNotFound, "Flow not found").into_parts()),
         Err(err) => {
             error!("Failed to delete flow: {}", err);
-            Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to delete flow".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete flow"))
+        }
+    }
+}
+
+/// Runs a (possibly edited) flow in shadow mode against stored events and
+/// reports step and branch coverage. Like `simulate`, webhooks are answered
+/// by mocks or recorded responses and no execution is stored.
+pub async fn replay_sample(
+    region: Region,
+    Path(flow_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<ReplaySample>,
+) -> Result<impl IntoResponse, ApiError> {
+    let pool = &region.pool;
+
+    let mut flow = match FlowRepository::new(pool).find_by_id(flow_id).await {
+        Ok(Some(flow)) => flow,
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::FlowNotFound, "Flow not found").into_parts());
+        }
+        Err(err) => {
+            error!("Failed to fetch flow: {}", err);
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flow"));
+        }
+    };
+
+    if let Some(trigger) = payload.trigger {
+        flow.trigger = trigger;
+    }
+    if let Some(steps) = payload.steps {
+        if let Err(err) = validate_steps(&steps) {
+            let message = err.message.map(|message| message.to_string()).unwrap_or_else(|| format!("invalid ({})", err.code));
+            return Ok(field_rejection("steps", message));
         }
+        flow.steps = steps;
     }
+
+    let mut filter = payload.filter;
+    filter.event_type.get_or_insert_with(|| flow.trigger.event_type.clone());
+
+    let loaded = async {
+        let events = EventRepository::new(pool).list_filtered(&filter, payload.limit).await?;
+        let recorded = if payload.use_recorded {
+            ExecutionRepository::new(pool).recorded_responses(flow_id).await?
+        } else {
+            HashMap::new()
+        };
+        anyhow::Ok((events, recorded))
+    }
+    .await;
+
+    let (events, recorded) = loaded.map_err(|err| {
+        error!("Failed to load replay sample: {}", err);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load replay sample")
+    })?;
+
+    let mut report = ReplayReport::new(&flow);
+    for event in &events {
+        if !Matcher::matches_trigger(event, &flow.trigger) {
+            report.record_unmatched();
+            continue;
+        }
+
+        let simulation = Arc::new(Simulation::new(payload.mocks.clone(), recorded.clone()));
+        let execution = Executor::simulated(simulation.clone())
+            .execute(&flow, event)
+            .await
+            .map_err(|err| {
+                error!("Failed to replay event {} against flow {}: {}", event.id, flow_id, err);
+                ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to replay event {}: {}", event.id, err),
+                )
+            })?;
+
+        report.record(&execution, &simulation.branches());
+    }
+
+    info!(
+        "Replayed {} event(s) against flow {} ({} failed)",
+        report.sampled, flow_id, report.failed
+    );
+
+    Ok((StatusCode::OK, Json(json!(report))))
 }
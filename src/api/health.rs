@@ -1,10 +1,207 @@
-use axum::Json;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::repositories::health_repository::{QueueBacklog, MIGRATOR};
+use crate::repositories::HealthRepository;
+use crate::services::load_shedding::LoadStats;
+use crate::services::{DbPools, PoolStats};
+
+use super::AppState;
+
+/// How long a region's database may take to answer before it counts as down.
+const DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Due work beyond which a queue counts as backed up.
+const MAX_HEALTHY_BACKLOG: i64 = 1000;
 
-pub async fn health_check() -> Json<Value> {
-    Json(json!({
-        "status": "healthy",
+/// How overdue the oldest queued item may get before the queue counts as
+/// backed up.
+const MAX_HEALTHY_DELAY_SECS: i64 = 300;
+
+#[derive(Debug, Deserialize)]
+pub struct HealthQuery {
+    #[serde(default)]
+    deep: bool,
+}
+
+/// Worst first wins when combining components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    /// Serving requests, but some background work is stuck or behind.
+    Degraded,
+    /// Cannot serve requests correctly.
+    Unhealthy,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionHealth {
+    pub region: String,
+    pub status: HealthStatus,
+    pub database: Value,
+    pub migrations: Value,
+    pub queues: Value,
+    /// Connections of this instance's pool in use right now.
+    pub pool: PoolStats,
+    /// The read replica's reachability and pool, when one is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replica: Option<Value>,
+}
+
+/// `GET /health`: liveness. With `?deep=true`, also checks each region's
+/// database, that its migrations are applied and its queues are drained,
+/// and that the background workers are running, and reports how busy each
+/// connection pool is. Unhealthy answers 503.
+pub async fn health_check(State(state): State<AppState>, Query(query): Query<HealthQuery>) -> Response {
+    if !query.deep {
+        return Json(json!({
+            "status": HealthStatus::Healthy,
+            "service": "orchepy",
+            "version": env!("CARGO_PKG_VERSION")
+        }))
+        .into_response();
+    }
+
+    let mut regions = Vec::new();
+    for name in state.regions.names() {
+        if let Some(pools) = state.regions.pools(name) {
+            regions.push(check_region(name, pools).await);
+        }
+    }
+
+    let workers = state.workers.statuses();
+    let workers_status = if workers.iter().all(|worker| worker.running) {
+        HealthStatus::Healthy
+    } else {
+        HealthStatus::Degraded
+    };
+
+    let status = regions
+        .iter()
+        .map(|region| region.status)
+        .chain([workers_status])
+        .max()
+        .unwrap_or(HealthStatus::Healthy);
+    let code = match status {
+        HealthStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+        HealthStatus::Healthy | HealthStatus::Degraded => StatusCode::OK,
+    };
+
+    let body = json!({
+        "status": status,
         "service": "orchepy",
-        "version": env!("CARGO_PKG_VERSION")
-    }))
+        "version": env!("CARGO_PKG_VERSION"),
+        "regions": regions,
+        "workers": {"status": workers_status, "workers": workers},
+    });
+    (code, Json(body)).into_response()
 }
+
+fn failed(err: impl std::fmt::Display) -> (HealthStatus, Value) {
+    (HealthStatus::Unhealthy, json!({"status": HealthStatus::Unhealthy, "error": err.to_string()}))
+}
+
+/// Whether the database answers, and how fast.
+async fn ping(pool: &PgPool) -> (HealthStatus, Value) {
+    let started = Instant::now();
+    let ping = tokio::time::timeout(DATABASE_TIMEOUT, HealthRepository::new(pool).ping()).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match ping {
+        Ok(Ok(())) => (HealthStatus::Healthy, json!({"status": HealthStatus::Healthy, "latency_ms": latency_ms})),
+        Ok(Err(err)) => failed(err),
+        Err(_) => failed(format!("no answer within {}s", DATABASE_TIMEOUT.as_secs())),
+    }
+}
+
+async fn check_region(name: &str, pools: &DbPools) -> RegionHealth {
+    let pool = pools.primary();
+    let repo = HealthRepository::new(pool);
+
+    // Reads fail while the replica is down, so it counts like the primary.
+    let mut replica_status = HealthStatus::Healthy;
+    let mut replica = None;
+    if let Some(replica_pool) = pools.replica() {
+        let (status, mut check) = ping(replica_pool).await;
+        check["pool"] = json!(PoolStats::of(replica_pool));
+        replica_status = status;
+        replica = Some(check);
+    }
+
+    let (database_status, database) = ping(pool).await;
+    if database_status != HealthStatus::Healthy {
+        let skipped = json!({"status": HealthStatus::Unhealthy, "error": "database unavailable"});
+        return RegionHealth {
+            region: name.to_string(),
+            status: HealthStatus::Unhealthy,
+            database,
+            migrations: skipped.clone(),
+            queues: skipped,
+            pool: PoolStats::of(pool),
+            replica,
+        };
+    }
+
+    let (migrations_status, migrations) = match repo.applied_migrations().await {
+        Ok(applied) => {
+            let pending: Vec<i64> = MIGRATOR
+                .iter()
+                .map(|migration| migration.version)
+                .filter(|version| !applied.contains(version))
+                .collect();
+            let status = if pending.is_empty() { HealthStatus::Healthy } else { HealthStatus::Unhealthy };
+            (status, json!({"status": status, "applied": applied.len(), "pending": pending}))
+        }
+        Err(err) => failed(err),
+    };
+
+    let (queues_status, queues) = match repo.backlog(Utc::now()).await {
+        Ok(backlog) => {
+            let status = backlog_status(&backlog);
+            (status, json!({"status": status, "queues": backlog}))
+        }
+        Err(err) => failed(err),
+    };
+
+    RegionHealth {
+        region: name.to_string(),
+        status: migrations_status.max(queues_status).max(replica_status),
+        database,
+        migrations,
+        queues,
+        pool: PoolStats::of(pool),
+        replica,
+    }
+}
+
+fn backlog_status(backlog: &[QueueBacklog]) -> HealthStatus {
+    let now = Utc::now();
+    let backed_up = backlog.iter().any(|queue| {
+        queue.due > MAX_HEALTHY_BACKLOG
+            || queue
+                .oldest_due_at
+                .is_some_and(|oldest| (now - oldest).num_seconds() > MAX_HEALTHY_DELAY_SECS)
+    });
+
+    if backed_up {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    }
+}
+
+/// In-flight API requests and how much background work each tier deferred.
+pub async fn get_load(State(state): State<AppState>) -> Json<LoadStats> {
+    Json(state.load_shedder.stats())
+}
+
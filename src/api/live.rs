@@ -0,0 +1,110 @@
+//! `/ws`: pushes case and workflow changes to connected dashboards.
+//!
+//! A connection starts without subscriptions and receives only
+//! `workflow.created`. Clients pick workflows by sending
+//! `{"type": "subscribe", "workflow_ids": [...]}` or `"unsubscribe"`, and
+//! get the resulting list back as `{"type": "subscribed", ...}`. Changes to
+//! subscribed workflows and their cases then arrive as
+//! `{"type": "case.moved", "region": ..., "case": {...}}` or
+//! `{"type": "workflow.updated", "region": ..., "workflow_id": ..., "workflow": {...}}`.
+//! A client that falls behind gets `{"type": "lagged", "missed": n}` and
+//! should reload.
+
+use std::collections::BTreeSet;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::AppState;
+use crate::services::{CaseChange, WorkflowChange, WorkflowChangeKind};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe { workflow_ids: Vec<Uuid> },
+    Unsubscribe { workflow_ids: Vec<Uuid> },
+}
+
+struct Connection {
+    region: String,
+    workflow_ids: BTreeSet<Uuid>,
+}
+
+impl Connection {
+    fn wants_case(&self, change: &CaseChange) -> bool {
+        change.region == self.region && self.workflow_ids.contains(&change.case.workflow_id)
+    }
+
+    /// New workflows go to everyone, since nobody can have subscribed yet.
+    fn wants_workflow(&self, change: &WorkflowChange) -> bool {
+        change.region == self.region
+            && (change.kind == WorkflowChangeKind::Created || self.workflow_ids.contains(&change.workflow_id))
+    }
+
+    /// The reply to a client message.
+    fn handle(&mut self, text: &str) -> Value {
+        match serde_json::from_str::<ClientMessage>(text) {
+            Ok(ClientMessage::Subscribe { workflow_ids }) => self.workflow_ids.extend(workflow_ids),
+            Ok(ClientMessage::Unsubscribe { workflow_ids }) => {
+                for workflow_id in workflow_ids {
+                    self.workflow_ids.remove(&workflow_id);
+                }
+            }
+            Err(err) => return json!({"type": "error", "error": format!("Invalid message: {}", err)}),
+        }
+        json!({"type": "subscribed", "workflow_ids": self.workflow_ids})
+    }
+}
+
+fn lagged(missed: u64) -> Value {
+    json!({"type": "lagged", "missed": missed})
+}
+
+pub async fn live_updates(ws: WebSocketUpgrade, State(state): State<AppState>, region: Region) -> Response {
+    ws.on_upgrade(move |socket| push_changes(socket, state, region.name))
+}
+
+async fn push_changes(mut socket: WebSocket, state: AppState, region: String) {
+    let mut cases = state.case_stream.subscribe();
+    let mut workflows = state.workflow_stream.subscribe();
+    let mut connection = Connection { region, workflow_ids: BTreeSet::new() };
+
+    loop {
+        let outgoing = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => Some(connection.handle(text.as_str())),
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => None,
+            },
+            change = cases.recv() => match change {
+                Ok(change) => connection.wants_case(&change).then(|| json!(change)),
+                Err(RecvError::Lagged(missed)) => Some(lagged(missed)),
+                Err(RecvError::Closed) => break,
+            },
+            change = workflows.recv() => match change {
+                Ok(change) => connection.wants_workflow(&change).then(|| json!(change)),
+                Err(RecvError::Lagged(missed)) => Some(lagged(missed)),
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if let Some(outgoing) = outgoing {
+            if socket.send(Message::Text(outgoing.to_string().into())).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    debug!("Dashboard connection closed");
+}
@@ -1,53 +1,290 @@
 pub mod cases;
+pub mod changes;
+pub mod credentials;
+pub mod emails;
 pub mod events;
 pub mod executions;
+pub mod fields;
 pub mod flows;
 pub mod health;
+pub mod live;
+pub mod outbox;
+pub mod portal;
+pub mod region;
 pub mod response;
+pub mod retention;
+pub mod secrets;
+pub mod service_accounts;
 pub mod ui;
+pub mod usage;
+pub mod validation;
+pub mod webhooks;
+pub mod whitelist;
 pub mod workflows;
 
 use axum::{
+    middleware,
     routing::{delete, get, patch, post, put},
     Router,
 };
 use sqlx::PgPool;
+use std::time::Duration;
 
-use crate::services::WebhookSender;
+use crate::engine::FlowConcurrencyLimiter;
+use crate::middleware::{
+    auth_middleware, idempotency_middleware, load_middleware, request_id_middleware, usage_middleware, Whitelist,
+};
+use crate::services::clock::system_clock;
+use crate::services::{
+    CaseStream, DataRegions, EventPublisher, ExecutionPayloads, JwtAuth, LoadShedder, RetentionConfig, SecretCipher,
+    SharedClock, SharedStorage, UsageRecorder, WebhookSender, WorkerMonitor, WorkflowStream,
+};
+
+const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 3600);
 
 #[derive(Clone)]
 pub struct AppState {
+    /// The default region's database. Region-scoped handlers use the
+    /// [`region::Region`] extractor instead.
     pub pool: PgPool,
+    pub regions: DataRegions,
     pub webhook_sender: WebhookSender,
+    pub flow_limiter: FlowConcurrencyLimiter,
+    pub usage: UsageRecorder,
+    pub load_shedder: LoadShedder,
+    /// The time used for new cases and by the resume schedulers.
+    pub clock: SharedClock,
+    /// How long responses to requests with an `Idempotency-Key` are replayed.
+    pub idempotency_ttl: Duration,
+    /// How long executions and events are kept; see [`crate::services::retention`].
+    pub retention: RetentionConfig,
+    /// Bearer token checks; without them every request is let through.
+    pub auth: Option<JwtAuth>,
+    /// Case changes made through this instance, for `GET /cases/stream`.
+    pub case_stream: CaseStream,
+    /// Workflow changes made through this instance, for `/ws`.
+    pub workflow_stream: WorkflowStream,
+    /// Background workers started by `main`, for `GET /health?deep=true`.
+    pub workers: WorkerMonitor,
+    /// Mirrors case and execution events to a message broker; disabled
+    /// unless `EVENT_BROKER` is set.
+    pub event_publisher: EventPublisher,
+    /// Object storage for case attachments; unset unless `STORAGE_BACKEND` is.
+    pub storage: Option<SharedStorage>,
+    /// Moves large step payloads of finished executions to `storage`.
+    pub execution_payloads: Option<ExecutionPayloads>,
+    /// Encrypts the values of `/secrets`; unset unless `SECRETS_KEY` is.
+    pub secrets: Option<SecretCipher>,
+    /// Clients allowed in when `WHITELIST_ENABLED` is set; disabled by
+    /// default. `main` applies it to the router.
+    pub whitelist: Whitelist,
+}
+
+impl AppState {
+    pub fn new(pool: PgPool, webhook_sender: WebhookSender) -> Self {
+        Self {
+            regions: DataRegions::single(pool.clone()),
+            pool,
+            webhook_sender,
+            flow_limiter: FlowConcurrencyLimiter::new(),
+            usage: UsageRecorder::new(),
+            load_shedder: LoadShedder::default(),
+            clock: system_clock(),
+            idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+            retention: RetentionConfig::default(),
+            auth: None,
+            case_stream: CaseStream::new(),
+            workflow_stream: WorkflowStream::new(),
+            workers: WorkerMonitor::new(),
+            event_publisher: EventPublisher::default(),
+            storage: None,
+            execution_payloads: None,
+            secrets: None,
+            whitelist: Whitelist::default(),
+        }
+    }
+
+    pub fn with_workers(mut self, workers: WorkerMonitor) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn with_event_publisher(mut self, event_publisher: EventPublisher) -> Self {
+        self.event_publisher = event_publisher;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: SharedStorage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_execution_payloads(mut self, execution_payloads: ExecutionPayloads) -> Self {
+        self.execution_payloads = Some(execution_payloads);
+        self
+    }
+
+    pub fn with_secrets(mut self, secrets: SecretCipher) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    pub fn with_whitelist(mut self, whitelist: Whitelist) -> Self {
+        self.whitelist = whitelist;
+        self
+    }
+
+    pub fn with_auth(mut self, auth: JwtAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency_ttl = ttl;
+        self
+    }
+
+    pub fn with_retention(mut self, retention: RetentionConfig) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn with_load_shedder(mut self, load_shedder: LoadShedder) -> Self {
+        self.load_shedder = load_shedder;
+        self
+    }
+
+    pub fn with_regions(mut self, regions: DataRegions) -> Self {
+        self.pool = regions.default_pool().clone();
+        self.regions = regions;
+        self
+    }
 }
 
-pub fn build_router(pool: PgPool, webhook_sender: WebhookSender) -> Router {
-    let state = AppState {
-        pool,
-        webhook_sender,
-    };
+pub fn build_router(state: AppState) -> Router {
+    let idempotent = middleware::from_fn_with_state(state.clone(), idempotency_middleware);
 
     Router::new()
         .route("/", get(ui::dashboard_handler))
         .route("/health", get(health::health_check))
+        .route("/ws", get(live::live_updates))
+        .route("/webhooks/signing-info", get(webhooks::get_signing_info))
+        .route("/webhooks", get(webhooks::list_webhook_subscriptions))
+        .route("/webhooks", post(webhooks::create_webhook_subscription))
+        .route("/webhooks/{id}", get(webhooks::get_webhook_subscription))
+        .route("/webhook-deliveries", get(webhooks::list_webhook_delivery_log))
+        .route("/webhook-deliveries/{id}/redeliver", post(webhooks::redeliver_webhook))
+        .route("/webhook-deliveries/queue", get(webhooks::list_queued_webhook_deliveries))
+        .route("/webhook-deliveries/queue/{id}/retry", post(webhooks::retry_queued_webhook_delivery))
+        .route("/webhooks/{id}", delete(webhooks::delete_webhook_subscription))
         .route("/workflows", get(workflows::list_workflows))
-        .route("/workflows", post(workflows::create_workflow))
+        .route("/workflows", post(workflows::create_workflow).layer(idempotent.clone()))
+        .route("/workflows/validate", post(workflows::validate_workflow))
         .route("/workflows/{id}", get(workflows::get_workflow))
         .route("/workflows/{id}", put(workflows::update_workflow))
         .route("/workflows/{id}", delete(workflows::delete_workflow))
+        .route("/workflows/{id}/analytics", get(workflows::get_workflow_analytics))
+        .route("/workflows/{id}/counts", get(workflows::get_workflow_counts))
+        .route("/workflows/{id}/webhook-secret", post(webhooks::set_workflow_webhook_secret))
+        .route("/workflows/{id}/webhook-secret", delete(webhooks::delete_workflow_webhook_secret))
+        .route("/workflows/{id}/doc", get(workflows::get_workflow_doc))
+        .route("/workflows/{id}/duplicate", post(workflows::duplicate_workflow))
+        .route("/workflows/{id}/archive", post(workflows::archive_workflow))
+        .route("/workflows/{id}/unarchive", post(workflows::unarchive_workflow))
+        .route("/workflows/{id}/versions", get(workflows::list_workflow_versions))
+        .route("/workflows/{id}/rollback/{version}", post(workflows::rollback_workflow))
+        .route("/workflows/{id}/automations/preview", post(workflows::preview_automations))
         .route("/cases", get(cases::list_cases))
-        .route("/cases", post(cases::create_case))
+        .route("/cases", post(cases::create_case).layer(idempotent.clone()))
+        .route("/cases/search", get(cases::search_cases))
+        .route("/cases/stream", get(cases::stream_cases))
+        .route("/cases/board", get(cases::get_case_board))
+        .route("/cases/by-external-id/{external_id}", get(cases::get_case_by_external_id))
+        .route("/search/cases", get(cases::search_all_cases))
+        .route("/changes", get(changes::get_changes))
         .route("/cases/{id}", get(cases::get_case))
+        .route("/cases/{id}", delete(cases::delete_case))
+        .route("/cases/{id}/purge", delete(cases::purge_case))
         .route("/cases/{id}/data", patch(cases::update_case_data))
         .route("/cases/{id}/move", put(cases::move_case))
+        .route("/cases/{id}/complete", post(cases::complete_case))
+        .route("/cases/{id}/fail", post(cases::fail_case))
+        .route("/cases/{id}/pause", post(cases::pause_case))
+        .route("/cases/{id}/resume", post(cases::resume_case))
         .route("/cases/{id}/history", get(cases::get_case_history))
-        .route("/events", post(events::create_event))
+        .route("/cases/{id}/revisions", get(cases::get_case_revisions))
+        .route("/cases/{id}/revisions/{revision}/restore", post(cases::restore_case_revision))
+        .route("/cases/{id}/automation-runs", get(cases::get_case_automation_runs))
+        .route("/cases/{id}/automation-runs/{run_id}", get(cases::get_case_automation_run))
+        .route("/cases/{id}/messages", get(cases::get_case_messages))
+        .route("/cases/{id}/messages", post(cases::create_case_message))
+        .route("/cases/{id}/comments", get(cases::get_case_comments))
+        .route("/cases/{id}/comments", post(cases::create_case_comment))
+        .route("/cases/{id}/attachments", get(cases::get_case_attachments))
+        .route("/cases/{id}/attachments", post(cases::upload_case_attachment))
+        .route("/cases/{id}/attachments/{attachment_id}", get(cases::download_case_attachment))
+        .route("/cases/{id}/attachments/{attachment_id}", delete(cases::delete_case_attachment))
+        .route("/cases/{id}/tags", post(cases::add_case_tags))
+        .route("/cases/{id}/links", get(cases::get_case_links))
+        .route("/cases/{id}/links", post(cases::create_case_link))
+        .route("/cases/{id}/links/{link_id}", delete(cases::delete_case_link))
+        .route("/cases/{id}/tags/{tag}", delete(cases::remove_case_tag))
+        .route("/cases/{id}/presence", put(cases::case_presence_heartbeat))
+        .route("/cases/{id}/presence/{user}", delete(cases::leave_case_presence))
+        .route("/cases/{id}/workflows", get(cases::get_case_workflows))
+        .route("/cases/{id}/workflows", post(cases::join_workflow))
+        .route("/cases/{id}/workflows/{workflow_id}", delete(cases::leave_workflow))
+        .route("/cases/{id}/workflows/{workflow_id}/move", put(cases::move_case_in_workflow))
+        .route("/messages/inbound", post(cases::receive_inbound_message))
+        .route("/cases/{id}/portal-tokens", get(portal::list_portal_tokens))
+        .route("/cases/{id}/portal-tokens", post(portal::create_portal_token))
+        .route("/cases/{id}/portal-tokens/{token_id}", delete(portal::revoke_portal_token))
+        .route("/portal/{token}", get(portal::get_portal_case))
+        .route("/portal/{token}/replies", post(portal::post_portal_reply))
+        .route("/admin/cases/import", post(cases::import_cases))
+        .route("/admin/credentials/expiring", get(credentials::list_expiring_credentials))
+        .route("/admin/emails", get(emails::list_emails))
+        .route("/admin/signing-keys", get(webhooks::list_signing_keys))
+        .route("/admin/signing-keys/rotate", post(webhooks::rotate_signing_key))
+        .route("/admin/load", get(health::get_load))
+        .route("/admin/whitelist", get(whitelist::get_whitelist))
+        .route("/admin/whitelist/reload", post(whitelist::reload_whitelist))
+        .route("/admin/purge", post(retention::purge))
+        .route("/admin/secrets/reencrypt", post(secrets::reencrypt))
+        .route("/service-accounts", get(service_accounts::list_service_accounts))
+        .route("/service-accounts", post(service_accounts::create_service_account))
+        .route("/service-accounts/{name}", get(service_accounts::get_service_account))
+        .route("/service-accounts/{name}", put(service_accounts::update_service_account))
+        .route("/service-accounts/{name}", delete(service_accounts::delete_service_account))
+        .route("/secrets", get(secrets::list_secrets))
+        .route("/secrets", post(secrets::create_secret))
+        .route("/secrets/{name}", get(secrets::get_secret))
+        .route("/secrets/{name}", put(secrets::update_secret))
+        .route("/secrets/{name}", delete(secrets::delete_secret))
+        .route("/events", get(events::list_events))
+        .route("/events", post(events::create_event).layer(idempotent))
+        .route("/events/{id}", get(events::get_event))
         .route("/flows", get(flows::list_flows))
         .route("/flows", post(flows::create_flow))
         .route("/flows/{id}", get(flows::get_flow))
         .route("/flows/{id}", put(flows::update_flow))
         .route("/flows/{id}", delete(flows::delete_flow))
+        .route("/flows/{id}/versions", get(flows::list_flow_versions))
+        .route("/flows/{id}/latency", get(flows::get_flow_latency))
+        .route("/flows/{id}/simulate", post(flows::simulate_flow))
+        .route("/flows/{id}/replay-sample", post(flows::replay_sample))
         .route("/executions", get(executions::list_executions))
         .route("/executions/{id}", get(executions::get_execution))
+        .route("/executions/{id}/steps", get(executions::get_execution_steps))
+        .route("/me/usage", get(usage::get_my_usage))
+        .layer(middleware::from_fn_with_state(state.clone(), usage_middleware))
+        .layer(middleware::from_fn_with_state(state.auth.clone(), auth_middleware))
+        .layer(middleware::from_fn_with_state(state.load_shedder.clone(), load_middleware))
+        .layer(middleware::from_fn(request_id_middleware))
         .with_state(state)
 }
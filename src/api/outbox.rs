@@ -0,0 +1,47 @@
+//! Delivery of the events, webhooks and automation actions queued in the
+//! outbox. A request delivers its own messages right after it commits; the
+//! outbox dispatcher worker retries failures and picks up whatever a crash
+//! or restart left behind. Delivery is at least once.
+
+use tracing::error;
+use uuid::Uuid;
+
+use crate::api::events::internal_create_and_trigger_event;
+use crate::api::region::Region;
+use crate::api::AppState;
+use crate::models::event::CreateEvent;
+use crate::models::outbox::{OutboxDelivery, OutboxScope};
+use crate::services::outbox::dispatch;
+use crate::workers::automation_resume::continue_automation;
+
+/// Delivers the region's due messages in `scope` until none is left to
+/// claim, and returns how many went out.
+pub(crate) async fn dispatch_outbox(state: &AppState, region: &Region, scope: OutboxScope) -> anyhow::Result<usize> {
+    dispatch(&region.pool, &scope, |delivery| deliver(state, region, delivery)).await
+}
+
+/// Delivers what a request just queued for `case_id` without waiting for
+/// the dispatcher's next poll.
+pub(crate) fn dispatch_case_outbox(state: &AppState, region: &Region, case_id: Uuid) {
+    let state = state.clone();
+    let region = region.clone();
+    tokio::spawn(async move {
+        if let Err(err) = dispatch_outbox(&state, &region, OutboxScope::Case(case_id)).await {
+            error!("Failed to dispatch outbox of case {}: {}", case_id, err);
+        }
+    });
+}
+
+async fn deliver(state: &AppState, region: &Region, delivery: OutboxDelivery) -> anyhow::Result<()> {
+    match delivery {
+        OutboxDelivery::Event { event_type, data, metadata } => {
+            let event = CreateEvent { event_type, data, metadata };
+            internal_create_and_trigger_event(state, region, event)
+                .await
+                .map(|_| ())
+                .map_err(|err| anyhow::anyhow!(err.message))
+        }
+        OutboxDelivery::Automation(automation) => continue_automation(&region.pool, state.secrets.as_ref(), automation).await,
+        webhook => state.webhook_sender.deliver(&region.pool, webhook).await,
+    }
+}
@@ -0,0 +1,206 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::{json, Map};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::cases::run_reply_automations;
+use crate::api::region::Region;
+use crate::api::AppState;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::engine::matcher::lookup;
+use crate::models::message::{CaseMessage, MessageChannel, MessageDirection};
+use crate::models::portal::{CreatePortalToken, PortalMessage, PortalReply, PortalToken, PortalView};
+use crate::models::ErrorCode;
+use crate::repositories::{CaseMessageRepository, CaseRepository, PortalTokenRepository, WorkflowRepository};
+
+const PORTAL_MESSAGES_LIMIT: i64 = 200;
+
+fn not_found() -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::from_code(ErrorCode::PortalLinkNotFound, "Portal link not found").into_parts()
+}
+
+fn internal_error(message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message).into_parts()
+}
+
+pub async fn create_portal_token(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreatePortalToken>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    match CaseRepository::new(pool).find_by_id(case_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::from_code(ErrorCode::CaseNotFound, "Case not found").into_parts(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return internal_error("Failed to fetch case");
+        }
+    }
+
+    let token = PortalToken::new(case_id, payload);
+    match PortalTokenRepository::new(pool).create(&token).await {
+        Ok(()) => {
+            info!("Issued portal token {} for case {}", token.id, case_id);
+            (StatusCode::CREATED, Json(json!(token)))
+        }
+        Err(err) => {
+            error!("Failed to create portal token: {}", err);
+            internal_error("Failed to create portal token")
+        }
+    }
+}
+
+pub async fn list_portal_tokens(
+    region: Region,
+    Path(case_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match PortalTokenRepository::new(&region.pool).list_by_case(case_id).await {
+        Ok(tokens) => (StatusCode::OK, Json(json!(tokens))),
+        Err(err) => {
+            error!("Failed to fetch portal tokens: {}", err);
+            internal_error("Failed to fetch portal tokens")
+        }
+    }
+}
+
+pub async fn revoke_portal_token(
+    region: Region,
+    Path((case_id, token_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    match PortalTokenRepository::new(&region.pool).revoke(case_id, token_id).await {
+        Ok(true) => (StatusCode::NO_CONTENT, Json(json!({}))),
+        Ok(false) => ApiError::from_code(ErrorCode::PortalLinkNotFound, "Portal token not found").into_parts(),
+        Err(err) => {
+            error!("Failed to revoke portal token: {}", err);
+            internal_error("Failed to revoke portal token")
+        }
+    }
+}
+
+/// The requester's view of their case. Unknown, expired and revoked tokens
+/// all answer 404 so a link can't be probed for its state.
+pub async fn get_portal_case(
+    region: Region,
+    Path(token): Path<String>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    let token = match PortalTokenRepository::new(pool).find_active(&token).await {
+        Ok(Some(token)) => token,
+        Ok(None) => return not_found(),
+        Err(err) => {
+            error!("Failed to fetch portal token: {}", err);
+            return internal_error("Failed to fetch case");
+        }
+    };
+
+    let case = match CaseRepository::new(pool).find_by_id(token.case_id).await {
+        Ok(Some(case)) => case,
+        Ok(None) => return not_found(),
+        Err(err) => {
+            error!("Failed to fetch case: {}", err);
+            return internal_error("Failed to fetch case");
+        }
+    };
+
+    let workflow = match WorkflowRepository::new(pool).find_by_id(case.workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => return not_found(),
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return internal_error("Failed to fetch case");
+        }
+    };
+
+    let messages = match CaseMessageRepository::new(pool)
+        .list_public_by_case(case.id, PORTAL_MESSAGES_LIMIT)
+        .await
+    {
+        Ok(messages) => messages,
+        Err(err) => {
+            error!("Failed to fetch case messages: {}", err);
+            return internal_error("Failed to fetch case");
+        }
+    };
+
+    let mut fields = Map::new();
+    for field in &token.fields {
+        if let Some(value) = lookup(&case.data, field) {
+            fields.insert(field.clone(), value.clone());
+        }
+    }
+
+    let view = PortalView {
+        case_id: case.id,
+        workflow: workflow.name,
+        current_phase: case.current_phase,
+        status: case.status,
+        fields,
+        messages: messages.into_iter().map(PortalMessage::from).collect(),
+        allow_replies: token.allow_replies,
+        updated_at: case.updated_at,
+    };
+
+    (StatusCode::OK, Json(json!(view)))
+}
+
+/// Adds the requester's reply to the public conversation and runs the
+/// workflow's `on_reply` automations, as for replies on other channels.
+pub async fn post_portal_reply(
+    State(state): State<AppState>,
+    region: Region,
+    Path(token): Path<String>,
+    ValidatedJson(payload): ValidatedJson<PortalReply>,
+) -> impl IntoResponse {
+    let pool = &region.pool;
+
+    let token = match PortalTokenRepository::new(pool).find_active(&token).await {
+        Ok(Some(token)) => token,
+        Ok(None) => return not_found(),
+        Err(err) => {
+            error!("Failed to fetch portal token: {}", err);
+            return internal_error("Failed to record reply");
+        }
+    };
+
+    if !token.allow_replies {
+        return ApiError::new(StatusCode::FORBIDDEN, "Replies are disabled for this link").into_parts();
+    }
+
+    let message = CaseMessage {
+        id: Uuid::new_v4(),
+        case_id: token.case_id,
+        direction: MessageDirection::Inbound,
+        channel: MessageChannel::Portal,
+        sender: None,
+        recipient: None,
+        subject: None,
+        body: payload.body,
+        external_id: None,
+        reply_token: None,
+        public: true,
+        attachments: payload.attachments,
+        created_at: chrono::Utc::now(),
+    };
+
+    if let Err(err) = CaseMessageRepository::new(pool).create(&message).await {
+        error!("Failed to record portal reply: {}", err);
+        return internal_error("Failed to record reply");
+    }
+
+    info!("Recorded portal reply for case {}", token.case_id);
+
+    if let Err(err) = run_reply_automations(&state, &region.pool, &message).await {
+        error!("Failed to run on_reply automations for case {}: {}", token.case_id, err);
+    }
+
+    (StatusCode::CREATED, Json(json!(PortalMessage::from(message))))
+}
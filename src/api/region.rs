@@ -0,0 +1,250 @@
+use axum::{
+    extract::FromRequestParts,
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::cmp::Ordering;
+use std::future::Future;
+
+use crate::api::response::ApiError;
+use crate::api::AppState;
+use crate::models::ErrorCode;
+use crate::services::regions::normalize_region;
+
+pub const REGION_HEADER: &str = "x-orchepy-region";
+
+/// Selects every region. Only list endpoints accept it.
+pub const ALL_REGIONS: &str = "all";
+
+pub struct RegionRejection(pub(crate) String);
+
+impl IntoResponse for RegionRejection {
+    fn into_response(self) -> Response {
+        ApiError::from_code(ErrorCode::RegionInvalid, self.0).into_response()
+    }
+}
+
+/// Requested region from the `X-Orchepy-Region` header or the `region`
+/// query parameter, or `None` for the default region.
+fn requested_region(parts: &Parts) -> Option<String> {
+    if let Some(value) = parts.headers.get(REGION_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(value.trim().to_ascii_lowercase());
+    }
+
+    parts.uri.query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("region="))
+            .map(|value| value.trim().to_ascii_lowercase())
+    })
+}
+
+/// The region whose database serves this request.
+#[derive(Clone)]
+pub struct Region {
+    pub name: String,
+    pub pool: PgPool,
+    /// The region's read replica, or `pool` without one. For lists, lookups
+    /// and analytics that can lag slightly behind writes.
+    pub read_pool: PgPool,
+}
+
+impl Region {
+    pub(crate) fn resolve(state: &AppState, name: Option<String>) -> Result<Self, RegionRejection> {
+        let name = match name {
+            Some(name) => normalize_region(&name).ok_or_else(|| RegionRejection(format!("Invalid region '{}'", name)))?,
+            None => state.regions.default_region().to_string(),
+        };
+
+        let pools = state
+            .regions
+            .pools(&name)
+            .ok_or_else(|| RegionRejection(format!("Unknown region '{}'", name)))?;
+
+        Ok(Self {
+            pool: pools.primary().clone(),
+            read_pool: pools.read().clone(),
+            name,
+        })
+    }
+}
+
+impl FromRequestParts<AppState> for Region {
+    type Rejection = RegionRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        match requested_region(parts) {
+            Some(name) if name == ALL_REGIONS => Err(RegionRejection(
+                "region=all is only supported when listing resources".to_string(),
+            )),
+            name => Region::resolve(state, name),
+        }
+    }
+}
+
+/// The regions a list request reads from. Reading more than one region is
+/// opt-in with `region=all`.
+pub struct RegionSet(pub Vec<Region>);
+
+impl FromRequestParts<AppState> for RegionSet {
+    type Rejection = RegionRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        match requested_region(parts) {
+            Some(name) if name == ALL_REGIONS => Ok(Self(
+                state
+                    .regions
+                    .names()
+                    .map(|name| Region::resolve(state, Some(name.to_string())))
+                    .collect::<Result<_, _>>()?,
+            )),
+            name => Ok(Self(vec![Region::resolve(state, name)?])),
+        }
+    }
+}
+
+impl RegionSet {
+    /// Runs a newest-first list query in each region and merges the pages.
+    /// A single region is queried with `limit`/`offset` as given; several
+    /// regions each return their first `offset + limit` rows so the merged
+    /// page is exact.
+    pub async fn list<T, F, Fut>(
+        &self,
+        limit: Option<i64>,
+        offset: i64,
+        sort_key: fn(&T) -> DateTime<Utc>,
+        fetch: F,
+    ) -> anyhow::Result<Vec<T>>
+    where
+        F: Fn(Region, Option<i64>, i64) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<T>>>,
+    {
+        self.list_by(limit, offset, |a, b| sort_key(b).cmp(&sort_key(a)), fetch).await
+    }
+
+    /// Like [`RegionSet::list`], for queries ordered as `compare` orders
+    /// their rows.
+    pub async fn list_by<T, C, F, Fut>(
+        &self,
+        limit: Option<i64>,
+        offset: i64,
+        compare: C,
+        fetch: F,
+    ) -> anyhow::Result<Vec<T>>
+    where
+        C: Fn(&T, &T) -> Ordering,
+        F: Fn(Region, Option<i64>, i64) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<T>>>,
+    {
+        if let [region] = &self.0[..] {
+            return fetch(region.clone(), limit, offset).await;
+        }
+
+        let window = limit.map(|limit| limit + offset);
+        let pages = futures::future::try_join_all(self.0.iter().map(|region| fetch(region.clone(), window, 0))).await?;
+
+        let mut items: Vec<T> = pages.into_iter().flatten().collect();
+        items.sort_by(compare);
+
+        let items = items.into_iter().skip(offset.max(0) as usize);
+        Ok(match limit {
+            Some(limit) => items.take(limit.max(0) as usize).collect(),
+            None => items.collect(),
+        })
+    }
+
+    /// Sums a count query over the selected regions.
+    pub async fn count<F, Fut>(&self, count: F) -> anyhow::Result<i64>
+    where
+        F: Fn(Region) -> Fut,
+        Fut: Future<Output = anyhow::Result<i64>>,
+    {
+        let counts = futures::future::try_join_all(self.0.iter().map(|region| count(region.clone()))).await?;
+        Ok(counts.into_iter().sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{DataRegions, WebhookSender};
+    use axum::http::Request;
+    use chrono::TimeZone;
+
+    fn lazy_pool() -> PgPool {
+        PgPool::connect_lazy("postgres://localhost/orchepy").unwrap()
+    }
+
+    fn state() -> AppState {
+        AppState::new(lazy_pool(), WebhookSender::new())
+            .with_regions(DataRegions::single_named("eu", lazy_pool()).with_region("us", lazy_pool()))
+    }
+
+    fn parts(uri: &str, header: Option<&str>) -> Parts {
+        let mut request = Request::builder().uri(uri);
+        if let Some(region) = header {
+            request = request.header(REGION_HEADER, region);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    #[tokio::test]
+    async fn test_region_resolution() {
+        let state = state();
+
+        let region = Region::from_request_parts(&mut parts("/cases", None), &state).await.ok().unwrap();
+        assert_eq!(region.name, "eu");
+
+        let region = Region::from_request_parts(&mut parts("/cases?limit=5&region=US", None), &state)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(region.name, "us");
+
+        let region = Region::from_request_parts(&mut parts("/cases?region=eu", Some("us")), &state)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(region.name, "us");
+
+        assert!(Region::from_request_parts(&mut parts("/cases?region=apac", None), &state).await.is_err());
+        assert!(Region::from_request_parts(&mut parts("/cases?region=all", None), &state).await.is_err());
+
+        let regions = RegionSet::from_request_parts(&mut parts("/cases", None), &state).await.ok().unwrap();
+        assert_eq!(regions.0.len(), 1);
+
+        let regions = RegionSet::from_request_parts(&mut parts("/cases?region=all", None), &state)
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(regions.0.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["eu", "us"]);
+    }
+
+    #[tokio::test]
+    async fn test_cross_region_list_merges_newest_first() {
+        let at = |hour| Utc.with_ymd_and_hms(2026, 1, 1, hour, 0, 0).unwrap();
+        let regions = RegionSet(vec![
+            Region { name: "eu".to_string(), pool: lazy_pool(), read_pool: lazy_pool() },
+            Region { name: "us".to_string(), pool: lazy_pool(), read_pool: lazy_pool() },
+        ]);
+
+        let rows = |region: &str| match region {
+            "eu" => vec![("eu", at(9)), ("eu", at(5)), ("eu", at(1))],
+            _ => vec![("us", at(8)), ("us", at(7)), ("us", at(2))],
+        };
+
+        let page = regions
+            .list(Some(2), 1, |row: &(&str, DateTime<Utc>)| row.1, |region, limit, offset| async move {
+                assert_eq!((limit, offset), (Some(3), 0));
+                Ok(rows(&region.name))
+            })
+            .await
+            .unwrap();
+        assert_eq!(page, vec![("us", at(8)), ("us", at(7))]);
+
+        let total = regions.count(|_| async { Ok(3) }).await.unwrap();
+        assert_eq!(total, 6);
+    }
+}
@@ -1,41 +1,267 @@
 use axum::{
-    http::StatusCode,
+    extract::FromRequestParts,
+    http::{header::ACCEPT, request::Parts, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+
+use crate::middleware::request_id::current_request_id;
+use crate::models::ErrorCode;
 
 #[allow(dead_code)]
 pub type ApiResult<T = Value> = Result<Json<T>, ApiError>;
 
+/// An error answered as `{"error", "code", "request_id"}`, plus any details.
 pub struct ApiError {
     pub status: StatusCode,
     pub message: String,
+    code: Option<ErrorCode>,
+    details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            code: None,
+            details: None,
+        }
+    }
+
+    /// An error with a specific code, answered with that code's status.
+    pub fn from_code(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(status_for(code), message).with_code(code)
+    }
+
+    /// Replaces the code derived from the status, e.g. `NOT_FOUND`.
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Fields of `details` are added to the body next to `error`.
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// The status and body, for handlers that answer with tuples.
+    pub fn into_parts(self) -> (StatusCode, Json<Value>) {
+        let code = self.code.unwrap_or_else(|| code_for_status(self.status));
+        let mut body = json!({
+            "error": self.message,
+            "code": code,
+            "request_id": current_request_id(),
+        });
+
+        if let (Some(Value::Object(details)), Some(fields)) = (self.details, body.as_object_mut()) {
+            for (key, value) in details {
+                fields.entry(key).or_insert(value);
+            }
+        }
+        (self.status, Json(body))
+    }
+}
+
+/// The status answered with `code` when a handler does not pick one.
+fn status_for(code: ErrorCode) -> StatusCode {
+    use ErrorCode::*;
+    match code {
+        BadRequest | RegionInvalid | IdempotencyKeyInvalid | PhaseInvalid => StatusCode::BAD_REQUEST,
+        Unauthorized => StatusCode::UNAUTHORIZED,
+        Forbidden | RoleRequired | IpNotAllowed => StatusCode::FORBIDDEN,
+        NotFound | WorkflowNotFound | WorkflowVersionNotFound | CaseNotFound | CaseLinkNotFound | CaseAttachmentNotFound
+        | CaseRevisionNotFound | AutomationRunNotFound | FlowNotFound | EventNotFound | ExecutionNotFound | ServiceAccountNotFound
+        | WebhookSubscriptionNotFound | WebhookDeliveryNotFound | PortalLinkNotFound | SecretNotFound => StatusCode::NOT_FOUND,
+        Conflict | IdempotencyKeyInUse | WorkflowArchived | WorkflowHasCases | CaseAlreadyExists
+        | CaseStatusInvalid | VersionConflict | DataConflict | WipLimitReached | TransitionNotAllowed => {
+            StatusCode::CONFLICT
+        }
+        PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+        PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        UnprocessableEntity | ValidationFailed | IdempotencyKeyReused | WorkflowInvalid | SlaConfigInvalid
+        | TransitionInvalid | ConditionInvalid | WebhookTemplateInvalid
+        | WebhookHeaderInvalid => StatusCode::UNPROCESSABLE_ENTITY,
+        TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+        InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        ServiceUnavailable | StorageNotConfigured | SecretsNotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+/// The generic code for errors that were not given one.
+fn code_for_status(status: StatusCode) -> ErrorCode {
+    match status {
+        StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+        StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+        StatusCode::NOT_FOUND => ErrorCode::NotFound,
+        StatusCode::CONFLICT => ErrorCode::Conflict,
+        StatusCode::PRECONDITION_FAILED => ErrorCode::PreconditionFailed,
+        StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+        StatusCode::UNPROCESSABLE_ENTITY => ErrorCode::UnprocessableEntity,
+        StatusCode::TOO_MANY_REQUESTS => ErrorCode::TooManyRequests,
+        StatusCode::SERVICE_UNAVAILABLE => ErrorCode::ServiceUnavailable,
+        status if status.is_server_error() => ErrorCode::InternalError,
+        _ => ErrorCode::BadRequest,
+    }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        (self.status, self.message).into_response()
+        self.into_parts().into_response()
     }
 }
 
 impl From<StatusCode> for ApiError {
     fn from(status: StatusCode) -> Self {
-        Self {
-            status,
-            message: status
-                .canonical_reason()
-                .unwrap_or("Unknown error")
-                .to_string(),
-        }
+        Self::new(status, status.canonical_reason().unwrap_or("Unknown error"))
     }
 }
 
 impl From<serde_json::Error> for ApiError {
     fn from(err: serde_json::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Serialization error: {}", err))
+    }
+}
+
+/// Media type that asks a list endpoint for a [`Page`] instead of a bare array.
+pub const PAGE_MEDIA_TYPE: &str = "application/vnd.orchepy.page+json";
+
+/// Whether the client opted in to the [`Page`] envelope, either with
+/// `?envelope=true` or with `Accept: application/vnd.orchepy.page+json`.
+/// List endpoints keep returning bare arrays otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Envelope(pub bool);
+
+impl<S: Send + Sync> FromRequestParts<S> for Envelope {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let by_query = parts
+            .uri
+            .query()
+            .is_some_and(|query| query.split('&').any(|pair| matches!(pair, "envelope" | "envelope=true" | "envelope=1")));
+
+        let by_accept = parts
+            .headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|media| media.split(';').next().unwrap_or("").trim() == PAGE_MEDIA_TYPE);
+
+        Ok(Self(by_query || by_accept))
+    }
+}
+
+/// `limit`/`offset` for list endpoints without their own query type. A
+/// missing `limit` returns every item.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    /// `None` when the endpoint was asked for every item.
+    pub limit: Option<i64>,
+    pub offset: i64,
+    /// Offset of the next page, or `None` on the last one.
+    pub next_offset: Option<i64>,
+}
+
+impl<T: Serialize> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, limit: Option<i64>, offset: i64) -> Self {
+        let end = offset + items.len() as i64;
         Self {
-            status: StatusCode::INTERNAL_SERVER_ERROR,
-            message: format!("Serialization error: {}", err),
+            next_offset: (end < total && !items.is_empty()).then_some(end),
+            items,
+            total,
+            limit,
+            offset,
         }
     }
 }
+
+/// Renders a list as a bare array, or as a [`Page`] when the client opted in.
+/// `total` is only called for enveloped responses so existing clients don't
+/// pay for the count query.
+pub async fn list_response<T, F>(
+    envelope: Envelope,
+    items: Vec<T>,
+    limit: Option<i64>,
+    offset: i64,
+    total: F,
+) -> Result<Response, ApiError>
+where
+    T: Serialize,
+    F: std::future::Future<Output = anyhow::Result<i64>>,
+{
+    if !envelope.0 {
+        return Ok((StatusCode::OK, Json(items)).into_response());
+    }
+
+    let total = total.await.map_err(|err| {
+        tracing::error!("Failed to count list items: {}", err);
+        ApiError::from(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    Ok((StatusCode::OK, Json(Page::new(items, total, limit, offset))).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn envelope(uri: &str, accept: Option<&str>) -> bool {
+        let mut request = Request::builder().uri(uri);
+        if let Some(accept) = accept {
+            request = request.header(ACCEPT, accept);
+        }
+        let (mut parts, _) = request.body(()).unwrap().into_parts();
+        Envelope::from_request_parts(&mut parts, &()).await.unwrap().0
+    }
+
+    #[tokio::test]
+    async fn test_envelope_opt_in() {
+        assert!(!envelope("/cases", None).await);
+        assert!(!envelope("/cases?envelope=false", Some("application/json")).await);
+        assert!(envelope("/cases?limit=10&envelope=true", None).await);
+        assert!(envelope("/cases", Some("application/json, application/vnd.orchepy.page+json; q=0.9")).await);
+    }
+
+    #[test]
+    fn test_error_body() {
+        let (status, Json(body)) = ApiError::new(StatusCode::CONFLICT, "Taken")
+            .with_details(json!({"error": "ignored", "phase": "Done"}))
+            .into_parts();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body, json!({"error": "Taken", "code": "CONFLICT", "request_id": null, "phase": "Done"}));
+
+        let (status, Json(body)) = ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "WORKFLOW_NOT_FOUND");
+    }
+
+    #[test]
+    fn test_generic_codes_keep_their_status() {
+        for status in [StatusCode::NOT_FOUND, StatusCode::CONFLICT, StatusCode::INTERNAL_SERVER_ERROR] {
+            assert_eq!(status_for(code_for_status(status)), status);
+        }
+        assert_eq!(code_for_status(StatusCode::BAD_GATEWAY), ErrorCode::InternalError);
+    }
+
+    #[test]
+    fn test_page_next_offset() {
+        assert_eq!(Page::new(vec![1, 2], 5, Some(2), 0).next_offset, Some(2));
+        assert_eq!(Page::new(vec![5], 5, Some(2), 4).next_offset, None);
+        assert_eq!(Page::<i32>::new(vec![], 5, Some(2), 10).next_offset, None);
+    }
+}
@@ -0,0 +1,46 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::api::AppState;
+use crate::models::retention::PurgeRequest;
+use crate::services::retention::purge as purge_old_rows;
+
+/// `POST /admin/purge`: deletes the region's finished executions, events
+/// and logged webhook attempts older than the configured retention, or the
+/// days in the body, right away, and reports how many rows went. Without any retention it deletes
+/// nothing.
+pub async fn purge(
+    State(state): State<AppState>,
+    region: Region,
+    payload: Option<ValidatedJson<PurgeRequest>>,
+) -> impl IntoResponse {
+    let payload = payload.map(|ValidatedJson(payload)| payload).unwrap_or_default();
+    let retention = payload.retention(&state.retention);
+
+    match purge_old_rows(&region.pool, &retention, state.clock.now()).await {
+        Ok(report) => {
+            info!(
+                "Purged {} executions, {} events and {} webhook deliveries in region '{}'",
+                report.executions, report.events, report.webhook_deliveries, region.name
+            );
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "region": region.name,
+                    "execution_days": retention.execution_days,
+                    "event_days": retention.event_days,
+                    "webhook_delivery_days": retention.webhook_delivery_days,
+                    "deleted": report,
+                })),
+            )
+        }
+        Err(err) => {
+            error!("Failed to purge old rows: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to purge old rows").into_parts()
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::api::AppState;
+use crate::models::secret::{CreateSecret, Secret, UpdateSecret};
+use crate::models::ErrorCode;
+use crate::repositories::SecretRepository;
+use crate::services::{reencrypt_secrets, SecretCipher};
+
+fn not_found() -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::from_code(ErrorCode::SecretNotFound, "Secret not found").into_parts()
+}
+
+fn internal_error(message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message).into_parts()
+}
+
+fn cipher(state: &AppState) -> Result<&SecretCipher, (StatusCode, Json<serde_json::Value>)> {
+    state.secrets.as_ref().ok_or_else(|| {
+        ApiError::from_code(ErrorCode::SecretsNotConfigured, "Secrets need SECRETS_KEY to be set").into_parts()
+    })
+}
+
+/// Stores a secret's value encrypted. Only its name and description are
+/// ever returned.
+pub async fn create_secret(
+    State(state): State<AppState>,
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<CreateSecret>,
+) -> impl IntoResponse {
+    let cipher = match cipher(&state) {
+        Ok(cipher) => cipher,
+        Err(response) => return response,
+    };
+    let ciphertext = match cipher.encrypt(&payload.name, &payload.value) {
+        Ok(ciphertext) => ciphertext,
+        Err(err) => {
+            error!("{}", err);
+            return internal_error("Failed to encrypt secret");
+        }
+    };
+    let secret = Secret::new(payload.name, payload.description, ciphertext);
+
+    match SecretRepository::new(&region.pool).create(&secret).await {
+        Ok(true) => {
+            info!("Created secret '{}'", secret.name);
+            (StatusCode::CREATED, Json(json!(secret)))
+        }
+        Ok(false) => {
+            ApiError::new(StatusCode::CONFLICT, format!("Secret '{}' already exists", secret.name)).into_parts()
+        }
+        Err(err) => {
+            error!("Failed to create secret: {}", err);
+            internal_error("Failed to create secret")
+        }
+    }
+}
+
+pub async fn list_secrets(region: Region) -> impl IntoResponse {
+    match SecretRepository::new(&region.pool).list().await {
+        Ok(secrets) => (StatusCode::OK, Json(json!(secrets))),
+        Err(err) => {
+            error!("Failed to list secrets: {}", err);
+            internal_error("Failed to list secrets")
+        }
+    }
+}
+
+pub async fn get_secret(
+    region: Region,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match SecretRepository::new(&region.pool).find_by_name(&name).await {
+        Ok(Some(secret)) => (StatusCode::OK, Json(json!(secret))),
+        Ok(None) => not_found(),
+        Err(err) => {
+            error!("Failed to fetch secret: {}", err);
+            internal_error("Failed to fetch secret")
+        }
+    }
+}
+
+/// Replaces a secret's value, e.g. to rotate a token. Webhooks sent from
+/// then on use the new value.
+pub async fn update_secret(
+    State(state): State<AppState>,
+    region: Region,
+    Path(name): Path<String>,
+    ValidatedJson(payload): ValidatedJson<UpdateSecret>,
+) -> impl IntoResponse {
+    let cipher = match cipher(&state) {
+        Ok(cipher) => cipher,
+        Err(response) => return response,
+    };
+    let ciphertext = match cipher.encrypt(&name, &payload.value) {
+        Ok(ciphertext) => ciphertext,
+        Err(err) => {
+            error!("{}", err);
+            return internal_error("Failed to encrypt secret");
+        }
+    };
+
+    match SecretRepository::new(&region.pool).update(&name, payload.description.as_deref(), &ciphertext).await {
+        Ok(Some(secret)) => {
+            info!("Updated secret '{}'", name);
+            (StatusCode::OK, Json(json!(secret)))
+        }
+        Ok(None) => not_found(),
+        Err(err) => {
+            error!("Failed to update secret: {}", err);
+            internal_error("Failed to update secret")
+        }
+    }
+}
+
+/// Webhooks that still refer to a deleted secret fail until it is created
+/// again.
+pub async fn delete_secret(
+    region: Region,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match SecretRepository::new(&region.pool).delete(&name).await {
+        Ok(true) => {
+            info!("Deleted secret '{}'", name);
+            (StatusCode::NO_CONTENT, Json(json!({})))
+        }
+        Ok(false) => not_found(),
+        Err(err) => {
+            error!("Failed to delete secret: {}", err);
+            internal_error("Failed to delete secret")
+        }
+    }
+}
+
+/// `POST /admin/secrets/reencrypt`: re-encrypts the region's secrets still
+/// under `SECRETS_KEY_PREVIOUS` with `SECRETS_KEY`, and reports how many,
+/// plus any that neither key decrypts.
+pub async fn reencrypt(State(state): State<AppState>, region: Region) -> impl IntoResponse {
+    let cipher = match cipher(&state) {
+        Ok(cipher) => cipher,
+        Err(response) => return response,
+    };
+
+    match reencrypt_secrets(&region.pool, cipher).await {
+        Ok(report) => (StatusCode::OK, Json(json!({"region": region.name, "secrets": report}))),
+        Err(err) => {
+            error!("Failed to re-encrypt secrets: {}", err);
+            internal_error("Failed to re-encrypt secrets")
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::models::service_account::{CreateServiceAccount, ServiceAccount, UpdateServiceAccount};
+use crate::models::ErrorCode;
+use crate::repositories::ServiceAccountRepository;
+
+fn not_found() -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::from_code(ErrorCode::ServiceAccountNotFound, "Service account not found").into_parts()
+}
+
+fn internal_error(message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message).into_parts()
+}
+
+pub async fn create_service_account(
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<CreateServiceAccount>,
+) -> impl IntoResponse {
+    let account = ServiceAccount::new(payload);
+
+    match ServiceAccountRepository::new(&region.pool).create(&account).await {
+        Ok(true) => {
+            info!("Created service account '{}'", account.name);
+            (StatusCode::CREATED, Json(json!(account)))
+        }
+        Ok(false) => {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Service account '{}' already exists", account.name),
+            )
+            .into_parts()
+        }
+        Err(err) => {
+            error!("Failed to create service account: {}", err);
+            internal_error("Failed to create service account")
+        }
+    }
+}
+
+pub async fn list_service_accounts(region: Region) -> impl IntoResponse {
+    match ServiceAccountRepository::new(&region.pool).list().await {
+        Ok(accounts) => (StatusCode::OK, Json(json!(accounts))),
+        Err(err) => {
+            error!("Failed to list service accounts: {}", err);
+            internal_error("Failed to list service accounts")
+        }
+    }
+}
+
+pub async fn get_service_account(
+    region: Region,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match ServiceAccountRepository::new(&region.pool).find_by_name(&name).await {
+        Ok(Some(account)) => (StatusCode::OK, Json(json!(account))),
+        Ok(None) => not_found(),
+        Err(err) => {
+            error!("Failed to fetch service account: {}", err);
+            internal_error("Failed to fetch service account")
+        }
+    }
+}
+
+pub async fn update_service_account(
+    region: Region,
+    Path(name): Path<String>,
+    ValidatedJson(payload): ValidatedJson<UpdateServiceAccount>,
+) -> impl IntoResponse {
+    let repo = ServiceAccountRepository::new(&region.pool);
+
+    let mut account = match repo.find_by_name(&name).await {
+        Ok(Some(account)) => account,
+        Ok(None) => return not_found(),
+        Err(err) => {
+            error!("Failed to fetch service account: {}", err);
+            return internal_error("Failed to fetch service account");
+        }
+    };
+
+    account.apply(payload);
+
+    match repo.update(&account).await {
+        Ok(true) => (StatusCode::OK, Json(json!(account))),
+        Ok(false) => not_found(),
+        Err(err) => {
+            error!("Failed to update service account: {}", err);
+            internal_error("Failed to update service account")
+        }
+    }
+}
+
+/// Workflows that still `run_as` a deleted account fail their automation
+/// runs until they are pointed at another one.
+pub async fn delete_service_account(
+    region: Region,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match ServiceAccountRepository::new(&region.pool).delete(&name).await {
+        Ok(true) => {
+            info!("Deleted service account '{}'", name);
+            (StatusCode::NO_CONTENT, Json(json!({})))
+        }
+        Ok(false) => not_found(),
+        Err(err) => {
+            error!("Failed to delete service account: {}", err);
+            internal_error("Failed to delete service account")
+        }
+    }
+}
@@ -236,33 +236,74 @@ const DASHBOARD_HTML: &str = r#"
     <button class="refresh-btn" onclick="loadWorkflows()">Refresh</button>
 
     <script>
+        const workflows = new Map();
+        const casesByWorkflow = new Map();
+        const countsByWorkflow = new Map();
+        let socket = null;
+
         async function loadWorkflows() {
             const loading = document.getElementById('loading');
             const workflowsContainer = document.getElementById('workflows');
             loading.style.display = 'block';
             workflowsContainer.innerHTML = '';
+            workflows.clear();
+            casesByWorkflow.clear();
+            countsByWorkflow.clear();
 
             try {
                 const response = await fetch('/workflows');
-                const workflows = await response.json();
+                const list = await response.json();
                 loading.style.display = 'none';
 
-                if (workflows.length === 0) {
-                    workflowsContainer.innerHTML = '<div class="empty-state"><h2>No workflows found</h2></div>';
-                    return;
-                }
-
-                for (const workflow of workflows) {
-                    await renderWorkflowKanban(workflow, workflowsContainer);
+                for (const workflow of list) {
+                    workflows.set(workflow.id, workflow);
+                    try {
+                        const casesResponse = await fetch(`/cases?workflow_id=${workflow.id}`);
+                        casesByWorkflow.set(workflow.id, await casesResponse.json());
+                    } catch (err) {
+                        console.error('Failed to load cases for workflow:', workflow.id, err);
+                        casesByWorkflow.set(workflow.id, []);
+                    }
+                    await loadCounts(workflow.id);
+                    renderWorkflowKanban(workflow);
                 }
+                renderEmptyState();
+                subscribe([...workflows.keys()]);
             } catch (err) {
                 loading.innerHTML = 'Failed to load: ' + err.message;
             }
         }
 
-        async function renderWorkflowKanban(workflow, container) {
-            const section = document.createElement('div');
-            section.className = 'workflow-section';
+        async function loadCounts(workflowId) {
+            try {
+                const response = await fetch(`/workflows/${workflowId}/counts`);
+                const counts = await response.json();
+                countsByWorkflow.set(workflowId, new Map(counts.phases.map(p => [p.phase, p.cases])));
+            } catch (err) {
+                console.error('Failed to load counts for workflow:', workflowId, err);
+                countsByWorkflow.delete(workflowId);
+            }
+        }
+
+        function renderEmptyState() {
+            const workflowsContainer = document.getElementById('workflows');
+            const empty = workflowsContainer.querySelector('.empty-state');
+            if (workflows.size === 0 && !empty) {
+                workflowsContainer.innerHTML = '<div class="empty-state"><h2>No workflows found</h2></div>';
+            } else if (workflows.size > 0 && empty) {
+                empty.remove();
+            }
+        }
+
+        function renderWorkflowKanban(workflow) {
+            const container = document.getElementById('workflows');
+            let section = document.getElementById(`workflow-${workflow.id}`);
+            if (!section) {
+                section = document.createElement('div');
+                section.className = 'workflow-section';
+                section.id = `workflow-${workflow.id}`;
+                container.appendChild(section);
+            }
 
             const statusBadge = workflow.active
                 ? '<span class="badge badge-active">Active</span>'
@@ -281,45 +322,85 @@ const DASHBOARD_HTML: &str = r#"
                 <div class="kanban-board" id="kanban-${workflow.id}"></div>
             `;
 
-            container.appendChild(section);
+            const cases = casesByWorkflow.get(workflow.id) || [];
+            const kanbanBoard = document.getElementById(`kanban-${workflow.id}`);
+            const phases = workflow.phases || [];
 
-            try {
-                const casesResponse = await fetch(`/cases?workflow_id=${workflow.id}`);
-                const cases = await casesResponse.json();
+            phases.forEach(definition => {
+                const phase = typeof definition === 'string' ? definition : definition.name;
+                const wipLimit = typeof definition === 'string' ? null : definition.wip_limit;
+                const phaseCases = cases.filter(c => c.current_phase === phase);
+                const count = countsByWorkflow.get(workflow.id)?.get(phase) ?? phaseCases.length;
+                const column = document.createElement('div');
+                column.className = 'kanban-column';
+                if (definition.color) {
+                    column.style.borderTop = `3px solid ${definition.color}`;
+                }
 
-                const kanbanBoard = document.getElementById(`kanban-${workflow.id}`);
-                const phases = workflow.phases || [];
+                column.innerHTML = `
+                    <div class="column-header">
+                        <div class="column-title">${phase}</div>
+                        <div class="column-count">${count}${wipLimit ? ` / ${wipLimit}` : ''} ${count === 1 ? 'case' : 'cases'}</div>
+                    </div>
+                    <div class="column-cards"></div>
+                `;
 
-                phases.forEach(phase => {
-                    const phaseCases = cases.filter(c => c.current_phase === phase);
-                    const column = document.createElement('div');
-                    column.className = 'kanban-column';
+                kanbanBoard.appendChild(column);
 
-                    column.innerHTML = `
-                        <div class="column-header">
-                            <div class="column-title">${phase}</div>
-                            <div class="column-count">${phaseCases.length} ${phaseCases.length === 1 ? 'case' : 'cases'}</div>
-                        </div>
-                        <div class="column-cards" id="column-${workflow.id}-${phase}"></div>
-                    `;
+                const cardsContainer = column.querySelector('.column-cards');
+                if (phaseCases.length === 0) {
+                    cardsContainer.innerHTML = '<div class="empty-column">No cases in this phase</div>';
+                } else {
+                    phaseCases.forEach(caseItem => {
+                        const card = createCaseCard(caseItem);
+                        cardsContainer.appendChild(card);
+                    });
+                }
+            });
+        }
 
-                    kanbanBoard.appendChild(column);
+        function subscribe(workflowIds) {
+            if (socket && socket.readyState === WebSocket.OPEN && workflowIds.length > 0) {
+                socket.send(JSON.stringify({type: 'subscribe', workflow_ids: workflowIds}));
+            }
+        }
 
-                    const cardsContainer = document.getElementById(`column-${workflow.id}-${phase}`);
-                    if (phaseCases.length === 0) {
-                        cardsContainer.innerHTML = '<div class="empty-column">No cases in this phase</div>';
-                    } else {
-                        phaseCases.forEach(caseItem => {
-                            const card = createCaseCard(caseItem);
-                            cardsContainer.appendChild(card);
-                        });
-                    }
-                });
-            } catch (err) {
-                console.error('Failed to load cases for workflow:', workflow.id, err);
+        function applyChange(change) {
+            if (change.type === 'lagged') {
+                loadWorkflows();
+            } else if (change.type.startsWith('case.')) {
+                const caseItem = change.case;
+                const cases = (casesByWorkflow.get(caseItem.workflow_id) || []).filter(c => c.id !== caseItem.id);
+                cases.unshift(caseItem);
+                casesByWorkflow.set(caseItem.workflow_id, cases);
+                const workflow = workflows.get(caseItem.workflow_id);
+                if (workflow) loadCounts(workflow.id).then(() => renderWorkflowKanban(workflow));
+            } else if (change.type === 'workflow.deleted') {
+                workflows.delete(change.workflow_id);
+                casesByWorkflow.delete(change.workflow_id);
+                countsByWorkflow.delete(change.workflow_id);
+                const section = document.getElementById(`workflow-${change.workflow_id}`);
+                if (section) section.remove();
+                renderEmptyState();
+            } else if (change.type.startsWith('workflow.')) {
+                if (!workflows.has(change.workflow_id)) {
+                    casesByWorkflow.set(change.workflow_id, []);
+                    subscribe([change.workflow_id]);
+                }
+                workflows.set(change.workflow_id, change.workflow);
+                renderEmptyState();
+                renderWorkflowKanban(change.workflow);
             }
         }
 
+        function connect() {
+            const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+            socket = new WebSocket(`${protocol}//${location.host}/ws`);
+            socket.onopen = () => loadWorkflows();
+            socket.onmessage = message => applyChange(JSON.parse(message.data));
+            socket.onclose = () => setTimeout(connect, 3000);
+        }
+
         function createCaseCard(caseItem) {
             const card = document.createElement('div');
             card.className = 'case-card';
@@ -366,8 +447,7 @@ const DASHBOARD_HTML: &str = r#"
             return date.toLocaleDateString();
         }
 
-        setInterval(loadWorkflows, 30000);
-        loadWorkflows();
+        connect();
     </script>
 </body>
 </html>
@@ -0,0 +1,64 @@
+use axum::{
+    extract::{Query, State},
+    http::{Extensions, HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::api::{response::ApiError, AppState};
+use crate::repositories::UsageRepository;
+use crate::services::usage::client_id;
+
+#[derive(Deserialize)]
+pub struct UsageQuery {
+    days: Option<i64>,
+}
+
+pub async fn get_my_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let Some(key_id) = client_id(state.auth.is_some(), &extensions, &headers) else {
+        let message = if state.auth.is_some() { "Token has no subject" } else { "X-Api-Key header is required" };
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, message));
+    };
+
+    let days = query.days.unwrap_or(7).clamp(1, 90);
+    let since = Utc::now() - Duration::days(days);
+
+    let repo = UsageRepository::new(&state.pool);
+    let result = async {
+        let totals = repo.totals(&key_id, since).await?;
+        let daily = repo.daily(&key_id, since).await?;
+        let routes = repo.by_route(&key_id, since).await?;
+        anyhow::Ok((totals, daily, routes))
+    }
+    .await;
+
+    let (totals, daily, routes) = result.map_err(|err| {
+        error!("Failed to load API usage: {}", err);
+        ApiError::from(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    let errors = totals.client_errors + totals.server_errors;
+    let error_rate = if totals.requests > 0 {
+        errors as f64 / totals.requests as f64
+    } else {
+        0.0
+    };
+
+    Ok(Json(json!({
+        "key_id": key_id,
+        "since": since,
+        "days": days,
+        "totals": totals,
+        "error_rate": error_rate,
+        "daily": daily,
+        "routes": routes,
+    })))
+}
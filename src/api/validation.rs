@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest, OptionalFromRequest, Request},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+use crate::api::response::ApiError;
+use crate::models::ErrorCode;
+
+/// JSON body extractor that runs `Validate` on the payload and rejects it
+/// with a 422 listing the messages for each offending field.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(payload) = <Json<T> as FromRequest<S>>::from_request(req, state)
+            .await
+            .map_err(json_rejection)?;
+
+        payload
+            .validate()
+            .map_err(|errors| validation_response(&errors))?;
+
+        Ok(Self(payload))
+    }
+}
+
+/// `Option<ValidatedJson<T>>` accepts a request without a JSON body.
+impl<T, S> OptionalFromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        let payload = <Json<T> as OptionalFromRequest<S>>::from_request(req, state)
+            .await
+            .map_err(json_rejection)?;
+
+        let Some(Json(payload)) = payload else {
+            return Ok(None);
+        };
+
+        payload
+            .validate()
+            .map_err(|errors| validation_response(&errors))?;
+
+        Ok(Some(Self(payload)))
+    }
+}
+
+/// Malformed bodies get the same error envelope as invalid ones.
+fn json_rejection(rejection: JsonRejection) -> Response {
+    ApiError::new(rejection.status(), rejection.body_text()).into_response()
+}
+
+pub fn validation_response(errors: &ValidationErrors) -> Response {
+    ApiError::from_code(ErrorCode::ValidationFailed, "Validation failed")
+        .with_details(json!({"fields": field_messages(errors)}))
+        .into_response()
+}
+
+/// Flattens nested validation errors into `"trigger.event_type"` style keys.
+pub fn field_messages(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+    let mut fields = BTreeMap::new();
+    collect(errors, None, &mut fields);
+    fields
+}
+
+fn collect(errors: &ValidationErrors, prefix: Option<&str>, fields: &mut BTreeMap<String, Vec<String>>) {
+    for (field, kind) in errors.errors() {
+        let path = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, field),
+            None => field.to_string(),
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(errs) => {
+                fields.entry(path).or_default().extend(errs.iter().map(|err| {
+                    err.message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| format!("invalid ({})", err.code))
+                }));
+            }
+            ValidationErrorsKind::Struct(nested) => collect(nested, Some(&path), fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect(nested, Some(&format!("{}[{}]", path, index)), fields);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::flow::CreateFlow;
+
+    #[test]
+    fn test_field_messages_are_keyed_by_path() {
+        let payload: CreateFlow = serde_json::from_value(json!({
+            "name": "",
+            "trigger": {"event_type": ""},
+            "steps": [{"name": "wait", "type": "delay", "duration_ms": 999_999_999}],
+            "max_concurrent_executions": 0
+        }))
+        .unwrap();
+
+        let fields = field_messages(&payload.validate().unwrap_err());
+
+        assert_eq!(fields["name"], vec!["must be between 1 and 255 characters"]);
+        assert!(fields.contains_key("trigger.event_type"));
+        assert!(fields["steps"][0].contains("delay must be at most"));
+        assert_eq!(fields["max_concurrent_executions"], vec!["must be at least 1"]);
+    }
+}
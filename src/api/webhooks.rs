@@ -0,0 +1,350 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::validation::ValidatedJson;
+use crate::api::AppState;
+use crate::models::outbox::QueuedWebhookDeliveriesQuery;
+use crate::models::signing_key::{ManagedSigningKey, RotateSigningKey, SigningKeyStatus};
+use crate::models::webhook_delivery::WebhookDeliveryLogQuery;
+use crate::models::webhook_subscription::{CreateWebhookSubscription, WebhookSubscription};
+use crate::models::workflow::SetWebhookSecret;
+use crate::models::ErrorCode;
+use crate::repositories::{
+    OutboxRepository, SigningKeyRepository, WebhookDeliveryLogRepository, WebhookSubscriptionRepository,
+    WorkflowRepository,
+};
+use crate::workers::signing_keys::refresh_signing_keys;
+use crate::services::webhook_signing::{
+    test_vectors, DEFAULT_TOLERANCE, WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+};
+
+/// `GET /webhooks/signing-info`: how webhooks are signed, which keys sign
+/// them now (ids only) and test vectors for checking a verifier.
+pub async fn get_signing_info(State(state): State<AppState>) -> Json<Value> {
+    let keys: Vec<Value> = state
+        .webhook_sender
+        .signer()
+        .valid_keys(Utc::now())
+        .iter()
+        .map(|key| json!({"id": key.id, "expires_at": key.expires_at}))
+        .collect();
+
+    Json(json!({
+        "algorithm": "HMAC-SHA256",
+        "headers": {
+            "id": WEBHOOK_ID_HEADER,
+            "timestamp": WEBHOOK_TIMESTAMP_HEADER,
+            "signature": WEBHOOK_SIGNATURE_HEADER,
+        },
+        "signed_content": "{webhook_id}.{timestamp}.{body}",
+        "signature_format": "key_id=hex_signature, separated by \", \"; one per valid key",
+        "tolerance_secs": DEFAULT_TOLERANCE.as_secs(),
+        "signing_enabled": !keys.is_empty(),
+        "keys": keys,
+        "test_vectors": test_vectors(),
+    }))
+}
+
+/// `GET /admin/signing-keys`: the keys from `WEBHOOK_SIGNING_KEYS` and the
+/// ones rotated in through the API, with their status. Secrets are never
+/// listed.
+pub async fn list_signing_keys(State(state): State<AppState>) -> impl IntoResponse {
+    let managed = match SigningKeyRepository::new(&state.pool).list().await {
+        Ok(keys) => keys,
+        Err(err) => {
+            error!("Failed to list signing keys: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list signing keys").into_parts();
+        }
+    };
+
+    let now = Utc::now();
+    let configured = state.webhook_sender.signer().configured_keys().iter().map(|key| {
+        let status = SigningKeyStatus::at(key.expires_at, now);
+        (status, json!({"id": key.id, "source": "configured", "status": status, "retires_at": key.expires_at}))
+    });
+    let managed = managed.iter().map(|key| {
+        let status = key.status(now);
+        (
+            status,
+            json!({
+                "id": key.id,
+                "source": "managed",
+                "status": status,
+                "retires_at": key.retires_at,
+                "created_by": key.created_by,
+                "created_at": key.created_at,
+            }),
+        )
+    });
+    let (statuses, keys): (Vec<SigningKeyStatus>, Vec<Value>) = configured.chain(managed).unzip();
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "rotation_in_progress": statuses.contains(&SigningKeyStatus::Retiring),
+            "keys": keys,
+        })),
+    )
+}
+
+/// `POST /admin/signing-keys/rotate`: adds a signing key and has the
+/// managed keys still signing retire after the grace period. The new
+/// secret is only returned here.
+pub async fn rotate_signing_key(
+    State(state): State<AppState>,
+    payload: Option<ValidatedJson<RotateSigningKey>>,
+) -> impl IntoResponse {
+    let payload = payload.map(|ValidatedJson(payload)| payload).unwrap_or_default();
+    let key = ManagedSigningKey::new(payload.created_by.clone());
+    let retires_at = key.created_at + payload.grace_period();
+
+    let retiring = match SigningKeyRepository::new(&state.pool).rotate(&key, retires_at).await {
+        Ok(retiring) => retiring,
+        Err(err) => {
+            error!("Failed to rotate signing keys: {}", err);
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to rotate signing keys").into_parts();
+        }
+    };
+
+    if let Err(err) = refresh_signing_keys(&state.pool, state.webhook_sender.signer()).await {
+        error!("Failed to load signing keys after rotation: {}", err);
+    }
+
+    info!("Rotated in signing key {}; retiring {:?} at {}", key.id, retiring, retires_at);
+
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "key": key,
+            "secret": key.secret,
+            "retiring": retiring,
+            "retires_at": retires_at,
+        })),
+    )
+}
+
+/// `POST /workflows/{id}/webhook-secret`: sets the secret the webhooks to
+/// the workflow's `webhook_url` are signed with, instead of the signing
+/// keys, replacing any earlier one. The secret is only returned here.
+pub async fn set_workflow_webhook_secret(
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+    payload: Option<ValidatedJson<SetWebhookSecret>>,
+) -> impl IntoResponse {
+    let secret = payload.map(|ValidatedJson(payload)| payload).unwrap_or_default().into_secret();
+
+    match WorkflowRepository::new(&region.pool).set_webhook_secret(workflow_id, Some(&secret)).await {
+        Ok(true) => {
+            info!("Set webhook secret of workflow {}", workflow_id);
+            (
+                StatusCode::CREATED,
+                Json(json!({"workflow_id": workflow_id, "key_id": workflow_id, "secret": secret})),
+            )
+        }
+        Ok(false) => ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts(),
+        Err(err) => {
+            error!("Failed to set webhook secret: {}", err);
+            internal_error("Failed to set webhook secret")
+        }
+    }
+}
+
+/// `DELETE /workflows/{id}/webhook-secret`: the workflow's webhooks go back
+/// to being signed with the signing keys.
+pub async fn delete_workflow_webhook_secret(region: Region, Path(workflow_id): Path<Uuid>) -> impl IntoResponse {
+    match WorkflowRepository::new(&region.pool).set_webhook_secret(workflow_id, None).await {
+        Ok(true) => {
+            info!("Removed webhook secret of workflow {}", workflow_id);
+            (StatusCode::NO_CONTENT, Json(json!({})))
+        }
+        Ok(false) => ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts(),
+        Err(err) => {
+            error!("Failed to remove webhook secret: {}", err);
+            internal_error("Failed to remove webhook secret")
+        }
+    }
+}
+
+/// `GET /webhook-deliveries/queue`: webhooks waiting in the outbox for
+/// their next attempt and, with `?status=failed`, the ones given up on,
+/// newest first.
+pub async fn list_queued_webhook_deliveries(
+    region: Region,
+    Query(query): Query<QueuedWebhookDeliveriesQuery>,
+) -> impl IntoResponse {
+    let failed = match query.failed() {
+        Ok(failed) => failed,
+        Err(message) => return ApiError::new(StatusCode::BAD_REQUEST, message).into_parts(),
+    };
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match OutboxRepository::new(&region.read_pool).list_webhooks(failed, limit, offset).await {
+        Ok(deliveries) => (StatusCode::OK, Json(json!(deliveries))),
+        Err(err) => {
+            error!("Failed to list webhook deliveries: {}", err);
+            internal_error("Failed to list webhook deliveries")
+        }
+    }
+}
+
+/// `POST /webhook-deliveries/queue/{id}/retry`: queues a failed delivery
+/// again, with a fresh set of attempts, and sends it right away.
+pub async fn retry_queued_webhook_delivery(
+    State(state): State<AppState>,
+    region: Region,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match OutboxRepository::new(&region.pool).retry_webhook(id).await {
+        Ok(Some(delivery)) => {
+            info!("Retrying webhook delivery {}", id);
+            let sender = state.webhook_sender.clone();
+            let pool = region.pool.clone();
+            tokio::spawn(async move { sender.dispatch_queued(&pool, vec![id]).await });
+            (StatusCode::OK, Json(json!(delivery)))
+        }
+        Ok(None) => {
+            ApiError::from_code(ErrorCode::WebhookDeliveryNotFound, "No failed webhook delivery with that id").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to retry webhook delivery: {}", err);
+            internal_error("Failed to retry webhook delivery")
+        }
+    }
+}
+
+/// `GET /webhook-deliveries`: every webhook attempt, with the receiver's
+/// status, latency and error, newest first.
+pub async fn list_webhook_delivery_log(region: Region, Query(query): Query<WebhookDeliveryLogQuery>) -> impl IntoResponse {
+    let status = match query.status() {
+        Ok(status) => status,
+        Err(message) => return ApiError::new(StatusCode::BAD_REQUEST, message).into_parts(),
+    };
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match WebhookDeliveryLogRepository::new(&region.read_pool).list(&query, status, limit, offset).await {
+        Ok(entries) => (StatusCode::OK, Json(json!(entries))),
+        Err(err) => {
+            error!("Failed to list webhook delivery log: {}", err);
+            internal_error("Failed to list webhook delivery log")
+        }
+    }
+}
+
+/// `POST /webhook-deliveries/{id}/redeliver`: sends a logged webhook again,
+/// once, and returns the new attempt.
+pub async fn redeliver_webhook(State(state): State<AppState>, region: Region, Path(id): Path<i64>) -> impl IntoResponse {
+    let entry = match WebhookDeliveryLogRepository::new(&region.pool).find(id).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => {
+            return ApiError::from_code(ErrorCode::WebhookDeliveryNotFound, "Webhook delivery not found").into_parts()
+        }
+        Err(err) => {
+            error!("Failed to get webhook delivery {}: {}", id, err);
+            return internal_error("Failed to get webhook delivery");
+        }
+    };
+
+    match state.webhook_sender.redeliver(&region.pool, &entry).await {
+        Ok(Some(attempt)) => {
+            info!("Redelivered webhook delivery {} as {}", id, attempt.id);
+            (StatusCode::OK, Json(json!(attempt)))
+        }
+        Ok(None) => ApiError::new(StatusCode::CONFLICT, "The webhook's subscription is gone or inactive").into_parts(),
+        Err(err) => {
+            error!("Failed to redeliver webhook delivery {}: {}", id, err);
+            internal_error("Failed to redeliver webhook delivery")
+        }
+    }
+}
+
+fn subscription_not_found() -> (StatusCode, Json<Value>) {
+    ApiError::from_code(ErrorCode::WebhookSubscriptionNotFound, "Webhook subscription not found").into_parts()
+}
+
+fn internal_error(message: &str) -> (StatusCode, Json<Value>) {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message).into_parts()
+}
+
+/// `POST /webhooks`: subscribes a URL to event types, optionally only for
+/// one workflow's cases. The secret deliveries are signed with is only
+/// returned here.
+pub async fn create_webhook_subscription(
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<CreateWebhookSubscription>,
+) -> impl IntoResponse {
+    if let Some(workflow_id) = payload.workflow_id {
+        match WorkflowRepository::new(&region.pool).find_by_id(workflow_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts(),
+            Err(err) => {
+                error!("Failed to fetch workflow: {}", err);
+                return internal_error("Failed to fetch workflow");
+            }
+        }
+    }
+
+    let subscription = WebhookSubscription::new(payload);
+
+    match WebhookSubscriptionRepository::new(&region.pool).create(&subscription).await {
+        Ok(()) => {
+            info!(
+                "Created webhook subscription {} to {:?} for {}",
+                subscription.id, subscription.event_types, subscription.url
+            );
+            let mut body = json!(subscription);
+            body["secret"] = json!(subscription.secret);
+            (StatusCode::CREATED, Json(body))
+        }
+        Err(err) => {
+            error!("Failed to create webhook subscription: {}", err);
+            internal_error("Failed to create webhook subscription")
+        }
+    }
+}
+
+pub async fn list_webhook_subscriptions(region: Region) -> impl IntoResponse {
+    match WebhookSubscriptionRepository::new(&region.pool).list().await {
+        Ok(subscriptions) => (StatusCode::OK, Json(json!(subscriptions))),
+        Err(err) => {
+            error!("Failed to list webhook subscriptions: {}", err);
+            internal_error("Failed to list webhook subscriptions")
+        }
+    }
+}
+
+pub async fn get_webhook_subscription(region: Region, Path(id): Path<Uuid>) -> impl IntoResponse {
+    match WebhookSubscriptionRepository::new(&region.pool).find_by_id(id).await {
+        Ok(Some(subscription)) => (StatusCode::OK, Json(json!(subscription))),
+        Ok(None) => subscription_not_found(),
+        Err(err) => {
+            error!("Failed to fetch webhook subscription: {}", err);
+            internal_error("Failed to fetch webhook subscription")
+        }
+    }
+}
+
+pub async fn delete_webhook_subscription(region: Region, Path(id): Path<Uuid>) -> impl IntoResponse {
+    match WebhookSubscriptionRepository::new(&region.pool).delete(id).await {
+        Ok(true) => {
+            info!("Deleted webhook subscription {}", id);
+            (StatusCode::NO_CONTENT, Json(json!({})))
+        }
+        Ok(false) => subscription_not_found(),
+        Err(err) => {
+            error!("Failed to delete webhook subscription: {}", err);
+            internal_error("Failed to delete webhook subscription")
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::api::response::ApiError;
+use crate::api::AppState;
+use crate::middleware::Whitelist;
+
+fn describe(whitelist: &Whitelist) -> Value {
+    json!({
+        "config": *whitelist.config(),
+        "loaded_at": whitelist.loaded_at(),
+    })
+}
+
+/// The IP whitelist in effect and when it was loaded.
+pub async fn get_whitelist(State(state): State<AppState>) -> Json<Value> {
+    Json(describe(&state.whitelist))
+}
+
+/// Re-reads `WHITELIST_ENABLED` and `WHITELIST_IPS`, so the whitelist can
+/// change without a restart.
+pub async fn reload_whitelist(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    match state.whitelist.reload() {
+        Ok(config) => {
+            info!(
+                "Reloaded IP whitelist: enabled={}, {} range(s)",
+                config.enabled,
+                config.allowed_ips.len()
+            );
+            Ok(Json(describe(&state.whitelist)))
+        }
+        Err(err) => {
+            error!("Failed to reload IP whitelist: {}", err);
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to reload IP whitelist"))
+        }
+    }
+}
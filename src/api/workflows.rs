@@ -1,112 +1,181 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use serde_json::{json, to_value}; 
+use serde::Deserialize;
+use chrono::Duration;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
 use tracing::{error, info};
 use uuid::Uuid;
+use validator::Validate;
 
-use crate::api::{response::ApiError, AppState}; 
-use crate::models::workflow::{CreateWorkflow, UpdateWorkflow, Workflow};
+use crate::api::{
+    region::{Region, RegionSet},
+    response::{list_response, ApiError, Envelope, PageQuery},
+    validation::{field_messages, ValidatedJson},
+    AppState,
+};
+use crate::engine;
+use crate::models::validation::validate_phases;
+use crate::models::workflow::{
+    ArchiveWorkflow, CreateWorkflow, DefinitionError, DeleteWorkflowQuery, DuplicateWorkflow, PreviewAutomations,
+    RollbackWorkflow, UpdateWorkflow, Workflow, WorkflowListQuery,
+};
+use crate::models::case::CaseStatus;
+use crate::models::ErrorCode;
+use crate::repositories::{AnalyticsRepository, CaseRepository, WorkflowRepository};
+use crate::services::workflow_docs::{render_workflow_doc, DocFormat};
+use crate::services::WorkflowChangeKind;
+
+#[derive(Debug, Deserialize)]
+pub struct DocQuery {
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    days: Option<i64>,
+}
+
+/// A definition with one kind of problem, e.g. only `sla_config` entries
+/// naming unknown phases, is reported with that problem's code.
+fn invalid_definition(errors: Vec<DefinitionError>) -> (StatusCode, Json<serde_json::Value>) {
+    let code = match errors.first() {
+        Some(first) if errors.iter().all(|err| err.code == first.code) => first.code,
+        _ => ErrorCode::WorkflowInvalid,
+    };
+    ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "Invalid workflow definition")
+        .with_code(code)
+        .with_details(json!({"errors": errors}))
+        .into_parts()
+}
 
 pub async fn create_workflow(
     State(state): State<AppState>,
-    Json(payload): Json<CreateWorkflow>,
+    region: Region,
+    ValidatedJson(payload): ValidatedJson<CreateWorkflow>,
 ) -> Result<impl IntoResponse, ApiError> {
     
-    let pool = &state.pool;
-    let workflow = match Workflow::new(payload) {
+    let pool = &region.pool;
+    let mut workflow = match Workflow::new(payload) {
         Ok(wf) => wf,
         
-        Err(err) => return Ok((StatusCode::BAD_REQUEST, Json(json!({"error": err})))),
+        Err(err) => return Ok(ApiError::new(StatusCode::BAD_REQUEST, err).into_parts()),
     };
+    workflow.region = Some(region.name.clone());
 
-    match sqlx::query(
-        "INSERT INTO orchepy_workflows (id, name, phases, initial_phase, webhook_url, description, active, created_at, updated_at)
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"
-    )
-    .bind(workflow.id)
-    .bind(&workflow.name)
-    .bind(to_value(&workflow.phases)?) 
-    .bind(&workflow.initial_phase)
-    .bind(&workflow.webhook_url)
-    .bind(&workflow.description)
-    .bind(workflow.active)
-    .bind(workflow.created_at)
-    .bind(workflow.updated_at)
-    .execute(pool)
-    .await
-    {
-        Ok(_) => {
+    let errors = workflow.definition_errors();
+    if !errors.is_empty() {
+        return Ok(invalid_definition(errors));
+    }
+
+    match WorkflowRepository::new(pool).create(&workflow).await {
+        Ok(()) => {
             info!("Created workflow {} ({})", workflow.id, workflow.name);
+            state.workflow_stream.publish(WorkflowChangeKind::Created, &region.name, &workflow);
             Ok((StatusCode::CREATED, Json(json!(workflow)))) 
         }
         Err(err) => {
             error!("Failed to create workflow: {}", err);
-            Err(ApiError { 
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: format!("Failed to create workflow: {}", err),
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create workflow: {}", err)))
+        }
+    }
+}
+
+/// `POST /workflows/validate`: checks a definition without saving it and
+/// lists every problem found, field validation included.
+pub async fn validate_workflow(Json(payload): Json<CreateWorkflow>) -> impl IntoResponse {
+    let mut errors: Vec<DefinitionError> = match payload.validate() {
+        Ok(()) => Vec::new(),
+        Err(field_errors) => field_messages(&field_errors)
+            .into_iter()
+            .flat_map(|(path, messages)| {
+                messages
+                    .into_iter()
+                    .map(move |message| DefinitionError::new(ErrorCode::ValidationFailed, path.clone(), message))
             })
+            .collect(),
+    };
+
+    for error in Workflow::draft(payload).definition_errors() {
+        if !errors.contains(&error) {
+            errors.push(error);
         }
     }
+
+    (StatusCode::OK, Json(json!({"valid": errors.is_empty(), "errors": errors})))
 }
 
 pub async fn get_workflow(
-    State(state): State<AppState>,
+    region: Region,
     Path(workflow_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
     
-    let pool = &state.pool;
+    let pool = &region.read_pool;
     match sqlx::query_as::<_, Workflow>("SELECT * FROM orchepy_workflows WHERE id = $1")
         .bind(workflow_id)
         .fetch_optional(pool)
         .await
     {
         Ok(Some(workflow)) => Ok((StatusCode::OK, Json(json!(workflow)))), 
-        Ok(None) => Ok((
-            
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Workflow not found"})),
-        )),
+        Ok(None) => Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts()),
         Err(err) => {
             error!("Failed to fetch workflow: {}", err);
-            Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to fetch workflow".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow"))
         }
     }
 }
 
-pub async fn list_workflows(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
-    
-    let pool = &state.pool;
-    match sqlx::query_as::<_, Workflow>("SELECT * FROM orchepy_workflows ORDER BY created_at DESC")
-        .fetch_all(pool)
-        .await
-    {
-        Ok(workflows) => Ok((StatusCode::OK, Json(json!(workflows)))), 
+pub async fn list_workflows(
+    regions: RegionSet,
+    envelope: Envelope,
+    Query(page): Query<PageQuery>,
+    Query(query): Query<WorkflowListQuery>,
+) -> Result<Response, ApiError> {
+    let offset = page.offset.unwrap_or(0);
+    let archived = query.archived;
+
+    let workflows = regions
+        .list(page.limit, offset, |item: &Workflow| item.created_at, |region, limit, offset| async move {
+            Ok(sqlx::query_as::<_, Workflow>(
+                "SELECT * FROM orchepy_workflows WHERE ($1 OR archived_at IS NULL) ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            )
+            .bind(archived)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&region.read_pool)
+            .await?)
+        })
+        .await;
+
+    match workflows {
+        Ok(workflows) => {
+            let total = regions.count(|region| async move {
+                Ok(sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM orchepy_workflows WHERE ($1 OR archived_at IS NULL)")
+                    .bind(archived)
+                    .fetch_one(&region.read_pool)
+                    .await?)
+            });
+            list_response(envelope, workflows, page.limit, offset, total).await
+        }
         Err(err) => {
             error!("Failed to list workflows: {}", err);
-            Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to list workflows".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list workflows"))
         }
     }
 }
 
 pub async fn update_workflow(
     State(state): State<AppState>,
+    region: Region,
     Path(workflow_id): Path<Uuid>,
     Json(payload): Json<UpdateWorkflow>,
 ) -> Result<impl IntoResponse, ApiError> {
     
-    let pool = &state.pool;
+    let pool = &region.pool;
 
     let mut workflow = match sqlx::query_as::<_, Workflow>("SELECT * FROM orchepy_workflows WHERE id = $1")
         .bind(workflow_id)
@@ -115,19 +184,11 @@ pub async fn update_workflow(
     {
         Ok(Some(wf)) => wf,
         Ok(None) => {
-            return Ok((
-                
-                StatusCode::NOT_FOUND,
-                Json(json!({"error": "Workflow not found"})),
-            ));
+            return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts());
         }
         Err(err) => {
             error!("Failed to fetch workflow: {}", err);
-            return Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to fetch workflow".to_string(),
-            });
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow"));
         }
     };
 
@@ -136,29 +197,35 @@ pub async fn update_workflow(
     }
     if let Some(phases) = payload.phases {
         if phases.is_empty() {
-            return Ok((
-                
-                StatusCode::BAD_REQUEST,
-                Json(json!({"error": "Phases list cannot be empty"})),
-            ));
+            return Ok(ApiError::from_code(ErrorCode::PhaseInvalid, "Phases list cannot be empty").into_parts());
+        }
+        if let Err(err) = validate_phases(&phases) {
+            return Ok(ApiError::new(StatusCode::BAD_REQUEST, err.to_string()).into_parts());
         }
         workflow.phases = phases;
     }
     if let Some(initial_phase) = payload.initial_phase {
         if !workflow.has_phase(&initial_phase) {
-            return Ok((
-                
-                StatusCode::BAD_REQUEST,
-                Json(
-                    json!({"error": format!("Initial phase '{}' must be in phases list", initial_phase)}),
-                ),
-            ));
+            return Ok(ApiError::from_code(
+                ErrorCode::PhaseInvalid,
+                format!("Initial phase '{}' must be in phases list", initial_phase),
+            )
+            .into_parts());
         }
         workflow.initial_phase = initial_phase;
     }
     if let Some(webhook_url) = payload.webhook_url {
         workflow.webhook_url = Some(webhook_url);
     }
+    if let Some(webhook_template) = payload.webhook_template {
+        workflow.webhook_template = Some(webhook_template);
+    }
+    if let Some(webhook_headers) = payload.webhook_headers {
+        workflow.webhook_headers = webhook_headers;
+    }
+    if let Some(webhook_auth) = payload.webhook_auth {
+        workflow.webhook_auth = Some(webhook_auth.keeping_secret_of(workflow.webhook_auth.as_ref()));
+    }
     if let Some(description) = payload.description {
         workflow.description = Some(description);
     }
@@ -171,69 +238,479 @@ pub async fn update_workflow(
     if let Some(sla_config) = payload.sla_config {
         workflow.sla_config = Some(sla_config);
     }
+    if let Some(execution_limits) = payload.execution_limits {
+        workflow.execution_limits = execution_limits;
+    }
+    if let Some(data_conflicts) = payload.data_conflicts {
+        workflow.data_conflicts = data_conflicts;
+    }
+    if let Some(transitions) = payload.transitions {
+        workflow.transitions = transitions;
+    }
+
+    let errors = workflow.definition_errors();
+    if !errors.is_empty() {
+        return Ok(invalid_definition(errors));
+    }
 
+    workflow.updated_by = payload.updated_by;
     workflow.updated_at = chrono::Utc::now();
 
-    match sqlx::query(
-        "UPDATE orchepy_workflows SET name = $1, phases = $2, initial_phase = $3, webhook_url = $4, description = $5, active = $6, automations = $7, sla_config = $8, updated_at = $9 WHERE id = $10"
-    )
-    .bind(&workflow.name)
-    .bind(to_value(&workflow.phases)?)
-    .bind(&workflow.initial_phase)
-    .bind(&workflow.webhook_url)
-    .bind(&workflow.description)
-    .bind(workflow.active)
-    .bind(to_value(&workflow.automations)?)
-    .bind(to_value(&workflow.sla_config)?)
-    .bind(workflow.updated_at)
-    .bind(workflow_id)
-    .execute(pool)
-    .await
-    {
-        Ok(_) => {
-            info!("Updated workflow {}", workflow_id);
+    match WorkflowRepository::new(pool).update(&workflow).await {
+        Ok(version) => {
+            workflow.version = version;
+            info!("Updated workflow {} (version {})", workflow_id, version);
+            state.workflow_stream.publish(WorkflowChangeKind::Updated, &region.name, &workflow);
             Ok((StatusCode::OK, Json(json!(workflow)))) 
         }
         Err(err) => {
             error!("Failed to update workflow: {}", err);
-            Err(ApiError { 
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to update workflow".to_string(),
-            })
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update workflow"))
         }
     }
 }
 
+pub async fn list_workflow_versions(
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    match WorkflowRepository::new(&region.pool).list_versions(workflow_id).await {
+        Ok(versions) if versions.is_empty() => {
+            Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts())
+        }
+        Ok(versions) => Ok((StatusCode::OK, Json(json!(versions)))),
+        Err(err) => {
+            error!("Failed to list workflow versions: {}", err);
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list workflow versions"))
+        }
+    }
+}
+
+/// Restores the definition saved in `version` as a new version, so the
+/// rollback itself shows up in the history and can be undone.
+pub async fn rollback_workflow(
+    State(state): State<AppState>,
+    region: Region,
+    Path((workflow_id, version)): Path<(Uuid, i32)>,
+    payload: Option<ValidatedJson<RollbackWorkflow>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let repo = WorkflowRepository::new(&region.pool);
+    let internal_error = |message: &str| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message.to_string());
+
+    let mut workflow = match repo.find_by_id(workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts());
+        }
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return Err(internal_error("Failed to fetch workflow"));
+        }
+    };
+
+    let saved = match repo.find_version(workflow_id, version).await {
+        Ok(Some(saved)) => saved,
+        Ok(None) => {
+            return Ok(ApiError::from_code(
+                ErrorCode::WorkflowVersionNotFound,
+                format!("Workflow version {} not found", version),
+            )
+            .into_parts());
+        }
+        Err(err) => {
+            error!("Failed to fetch workflow version: {}", err);
+            return Err(internal_error("Failed to fetch workflow version"));
+        }
+    };
+
+    workflow.restore(&saved);
+    workflow.updated_by = payload.and_then(|ValidatedJson(payload)| payload.updated_by);
+    workflow.updated_at = chrono::Utc::now();
+
+    match repo.update(&workflow).await {
+        Ok(new_version) => {
+            workflow.version = new_version;
+            info!("Rolled workflow {} back to version {} (now version {})", workflow_id, version, new_version);
+            state.workflow_stream.publish(WorkflowChangeKind::Updated, &region.name, &workflow);
+            Ok((StatusCode::OK, Json(json!(workflow))))
+        }
+        Err(err) => {
+            error!("Failed to roll back workflow: {}", err);
+            Err(internal_error("Failed to roll back workflow"))
+        }
+    }
+}
+
+/// Refuses to delete a workflow that still has cases, since they would go
+/// with it, unless `force=true`; archiving keeps them instead.
 pub async fn delete_workflow(
     State(state): State<AppState>,
+    region: Region,
     Path(workflow_id): Path<Uuid>,
+    Query(query): Query<DeleteWorkflowQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
-    
-    let pool = &state.pool;
-    match sqlx::query("DELETE FROM orchepy_workflows WHERE id = $1")
-        .bind(workflow_id)
-        .execute(pool)
+    let repo = WorkflowRepository::new(&region.pool);
+    let internal_error = |err: anyhow::Error| {
+        error!("Failed to delete workflow: {}", err);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete workflow")
+    };
+
+    let deleted = if query.force {
+        repo.delete(workflow_id).await
+    } else {
+        repo.delete_if_unused(workflow_id).await
+    }
+    .map_err(internal_error)?;
+
+    if deleted {
+        info!("Deleted workflow {}", workflow_id);
+        state.workflow_stream.publish_deleted(&region.name, workflow_id);
+        return Ok((StatusCode::NO_CONTENT, Json(json!({}))));
+    }
+
+    if repo.find_by_id(workflow_id).await.map_err(internal_error)?.is_none() {
+        return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts());
+    }
+
+    let cases = repo.count_cases(workflow_id).await.map_err(internal_error)?;
+    Ok(ApiError::new(
+        StatusCode::CONFLICT,
+        "Workflow has cases; archive it to keep them, or pass force=true to delete them too",
+    )
+    .with_details(json!({"cases": cases}))
+    .into_parts())
+}
+
+/// Hides the workflow from listings and closes it to new cases. Its cases
+/// stay where they are and can still be moved and updated.
+pub async fn archive_workflow(
+    State(state): State<AppState>,
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+    payload: Option<ValidatedJson<ArchiveWorkflow>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let archived_by = payload.and_then(|ValidatedJson(payload)| payload.archived_by);
+
+    match WorkflowRepository::new(&region.pool).archive(workflow_id, archived_by.as_deref()).await {
+        Ok(Some(workflow)) => {
+            info!("Archived workflow {}", workflow_id);
+            state.workflow_stream.publish(WorkflowChangeKind::Updated, &region.name, &workflow);
+            Ok((StatusCode::OK, Json(json!(workflow))))
+        }
+        Ok(None) => Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts()),
+        Err(err) => {
+            error!("Failed to archive workflow: {}", err);
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to archive workflow"))
+        }
+    }
+}
+
+pub async fn unarchive_workflow(
+    State(state): State<AppState>,
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    match WorkflowRepository::new(&region.pool).unarchive(workflow_id).await {
+        Ok(Some(workflow)) => {
+            info!("Unarchived workflow {}", workflow_id);
+            state.workflow_stream.publish(WorkflowChangeKind::Updated, &region.name, &workflow);
+            Ok((StatusCode::OK, Json(json!(workflow))))
+        }
+        Ok(None) => Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts()),
+        Err(err) => {
+            error!("Failed to unarchive workflow: {}", err);
+            Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to unarchive workflow"))
+        }
+    }
+}
+
+/// Copies the workflow under a new name, without its cases, so changes can
+/// be tried out before touching the live one.
+pub async fn duplicate_workflow(
+    State(state): State<AppState>,
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<DuplicateWorkflow>,
+) -> Result<impl IntoResponse, ApiError> {
+    let repo = WorkflowRepository::new(&region.pool);
+
+    let workflow = match repo.find_by_id(workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts());
+        }
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow"));
+        }
+    };
+
+    let copy = workflow.duplicate(payload);
+    repo.create(&copy).await.map_err(|err| {
+        error!("Failed to duplicate workflow: {}", err);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to duplicate workflow")
+    })?;
+
+    info!("Duplicated workflow {} as {} ({})", workflow_id, copy.id, copy.name);
+    state.workflow_stream.publish(WorkflowChangeKind::Created, &region.name, &copy);
+    Ok((StatusCode::CREATED, Json(json!(copy))))
+}
+
+/// Evaluates proposed automations against the workflow's most recent cases
+/// without saving them or running any action.
+pub async fn preview_automations(
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<PreviewAutomations>,
+) -> Result<impl IntoResponse, ApiError> {
+    let pool = &region.pool;
+
+    match WorkflowRepository::new(pool).find_by_id(workflow_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts());
+        }
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow"));
+        }
+    }
+
+    let cases = CaseRepository::new(pool)
+        .list_by_workflow(workflow_id, payload.sample_size, 0)
         .await
-    {
-        Ok(result) => {
-            if result.rows_affected() > 0 {
-                info!("Deleted workflow {}", workflow_id);
-                Ok((StatusCode::NO_CONTENT, Json(json!({})))) 
+        .map_err(|err| {
+            error!("Failed to load sample cases: {}", err);
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load sample cases")
+        })?;
+
+    let automations = engine::preview_automations(&payload.automations, &cases);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "workflow_id": workflow_id,
+            "sampled_cases": cases.len(),
+            "automations": automations,
+        })),
+    ))
+}
+
+/// Workflow documentation rendered from its definition. Markdown unless
+/// `?format=html` is given or the client prefers `text/html`.
+pub async fn get_workflow_doc(
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+    Query(query): Query<DocQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let format = match query.format.as_deref() {
+        Some(format) => match DocFormat::parse(format) {
+            Some(format) => format,
+            None => {
+                return Ok(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Unsupported format '{}', expected markdown or html", format),
+                )
+                .into_response());
+            }
+        },
+        None => {
+            let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or_default();
+            if accept.contains("text/html") {
+                DocFormat::Html
             } else {
-                Ok((
-                    
-                    StatusCode::NOT_FOUND,
-                    Json(json!({"error": "Workflow not found"})),
-                ))
+                DocFormat::Markdown
             }
         }
+    };
+
+    let workflow = match WorkflowRepository::new(&region.pool).find_by_id(workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_response());
+        }
         Err(err) => {
-            error!("Failed to delete workflow: {}", err);
-            Err(ApiError {
-                
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                message: "Failed to delete workflow".to_string(),
+            error!("Failed to fetch workflow: {}", err);
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow"));
+        }
+    };
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, format.content_type())],
+        render_workflow_doc(&workflow, format),
+    )
+        .into_response())
+}
+
+fn breach_rate(breaches: i64, samples: i64) -> Option<f64> {
+    (samples > 0).then(|| breaches as f64 / samples as f64)
+}
+
+/// Cases per phase, time in phase, daily throughput and SLA breaches over
+/// the last `days` (default 30).
+pub async fn get_workflow_analytics(
+    State(state): State<AppState>,
+    region: Region,
+    Path(workflow_id): Path<Uuid>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let pool = &region.read_pool;
+
+    let workflow = match WorkflowRepository::new(pool).find_by_id(workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts());
+        }
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow"));
+        }
+    };
+
+    let days = query.days.unwrap_or(30).clamp(1, 365);
+    let now = state.clock.now();
+    let since = now - Duration::days(days);
+
+    let repo = AnalyticsRepository::new(pool);
+    let result = async {
+        let counts = repo.cases_per_phase(workflow_id).await?;
+        let timings = repo.phase_timings(workflow_id, since, now).await?;
+        let throughput = repo.daily_throughput(workflow_id, since, now).await?;
+        anyhow::Ok((counts, timings, throughput))
+    }
+    .await;
+
+    let (counts, timings, throughput) = result.map_err(|err| {
+        error!("Failed to load workflow analytics: {}", err);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load workflow analytics")
+    })?;
+
+    // Phases in definition order, then any that only old cases are still in.
+    let mut names: Vec<String> = workflow.phases.iter().map(|phase| phase.name.clone()).collect();
+    for phase in counts.iter().map(|count| &count.phase).chain(timings.iter().map(|timing| &timing.phase)) {
+        if !names.contains(phase) {
+            names.push(phase.clone());
+        }
+    }
+
+    let counts: HashMap<_, _> = counts.iter().map(|count| (count.phase.as_str(), count)).collect();
+    let timings: HashMap<_, _> = timings.iter().map(|timing| (timing.phase.as_str(), timing)).collect();
+    let (mut sla_samples, mut sla_breaches) = (0, 0);
+
+    let phases: Vec<Value> = names
+        .iter()
+        .map(|name| {
+            let count = counts.get(name.as_str());
+            let timing = timings.get(name.as_str());
+            let sla_hours = workflow
+                .sla_config
+                .as_ref()
+                .and_then(|config| config.phase_slas.get(name))
+                .map(|sla| sla.hours);
+
+            let sla = sla_hours.map(|hours| {
+                let (samples, breaches) = timing.map(|t| (t.sla_samples, t.sla_breaches)).unwrap_or_default();
+                sla_samples += samples;
+                sla_breaches += breaches;
+                json!({
+                    "hours": hours,
+                    "samples": samples,
+                    "breaches": breaches,
+                    "breach_rate": breach_rate(breaches, samples),
+                })
+            });
+
+            json!({
+                "phase": name,
+                "cases": count.map(|c| c.cases).unwrap_or(0),
+                "in_progress": count.map(|c| c.in_progress).unwrap_or(0),
+                "time_in_phase": {
+                    "samples": timing.map(|t| t.samples).unwrap_or(0),
+                    "avg_secs": timing.and_then(|t| t.avg_secs),
+                    "p50_secs": timing.and_then(|t| t.p50_secs),
+                    "p95_secs": timing.and_then(|t| t.p95_secs),
+                    "max_secs": timing.and_then(|t| t.max_secs),
+                },
+                "sla": sla,
             })
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "workflow_id": workflow_id,
+            "since": since,
+            "days": days,
+            "phases": phases,
+            "throughput": throughput,
+            "sla": {
+                "samples": sla_samples,
+                "breaches": sla_breaches,
+                "breach_rate": breach_rate(sla_breaches, sla_samples),
+            },
+        })),
+    ))
+}
+
+/// Live cases per phase and per status, counted in the database, for
+/// dashboard columns that shouldn't have to load every case.
+pub async fn get_workflow_counts(region: Region, Path(workflow_id): Path<Uuid>) -> Result<impl IntoResponse, ApiError> {
+    let pool = &region.read_pool;
+
+    let workflow = match WorkflowRepository::new(pool).find_by_id(workflow_id).await {
+        Ok(Some(workflow)) => workflow,
+        Ok(None) => {
+            return Ok(ApiError::from_code(ErrorCode::WorkflowNotFound, "Workflow not found").into_parts());
         }
+        Err(err) => {
+            error!("Failed to fetch workflow: {}", err);
+            return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch workflow"));
+        }
+    };
+
+    let repo = CaseRepository::new(pool);
+    let result = async {
+        let phases = repo.count_by_phase(workflow_id).await?;
+        let statuses = repo.count_by_status(workflow_id).await?;
+        anyhow::Ok((phases, statuses))
     }
+    .await;
+
+    let (phase_counts, status_counts) = result.map_err(|err| {
+        error!("Failed to count cases: {}", err);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to count cases")
+    })?;
+
+    // Phases in definition order, then any that only old cases are still in.
+    let mut phases: Vec<(String, i64)> = workflow.phases.iter().map(|phase| (phase.name.clone(), 0)).collect();
+    for (phase, cases) in &phase_counts {
+        match phases.iter_mut().find(|(name, _)| name == phase) {
+            Some(entry) => entry.1 = *cases,
+            None => phases.push((phase.clone(), *cases)),
+        }
+    }
+
+    let mut statuses: Map<String, Value> = [CaseStatus::Active, CaseStatus::Paused, CaseStatus::Completed, CaseStatus::Failed]
+        .iter()
+        .map(|status| (status.as_str().to_string(), json!(0)))
+        .collect();
+    for (status, cases) in &status_counts {
+        statuses.insert(status.as_str().to_string(), json!(cases));
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "workflow_id": workflow_id,
+            "total": phase_counts.iter().map(|(_, cases)| cases).sum::<i64>(),
+            "phases": phases
+                .iter()
+                .map(|(phase, cases)| json!({"phase": phase, "cases": cases}))
+                .collect::<Vec<_>>(),
+            "statuses": statuses,
+        })),
+    ))
 }
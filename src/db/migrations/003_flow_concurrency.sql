@@ -0,0 +1,3 @@
+ALTER TABLE orchepy_flows
+    ADD COLUMN IF NOT EXISTS max_concurrent_executions INTEGER
+    CHECK (max_concurrent_executions IS NULL OR max_concurrent_executions > 0);
@@ -0,0 +1,24 @@
+ALTER TABLE orchepy_flows ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE IF NOT EXISTS orchepy_flow_versions (
+    flow_id UUID NOT NULL REFERENCES orchepy_flows(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    trigger JSONB NOT NULL,
+    steps JSONB NOT NULL,
+    max_concurrent_executions INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (flow_id, version)
+);
+
+INSERT INTO orchepy_flow_versions (flow_id, version, name, trigger, steps, max_concurrent_executions, created_at)
+SELECT id, version, name, trigger, steps, max_concurrent_executions, updated_at
+FROM orchepy_flows
+ON CONFLICT DO NOTHING;
+
+ALTER TABLE orchepy_executions ADD COLUMN IF NOT EXISTS flow_version INTEGER;
+
+UPDATE orchepy_executions e
+SET flow_version = f.version
+FROM orchepy_flows f
+WHERE e.flow_id = f.id AND e.flow_version IS NULL;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS orchepy_api_usage (
+    key_id VARCHAR(64) NOT NULL,
+    bucket_start TIMESTAMPTZ NOT NULL,
+    method VARCHAR(16) NOT NULL,
+    route VARCHAR(255) NOT NULL,
+    request_count BIGINT NOT NULL DEFAULT 0,
+    client_error_count BIGINT NOT NULL DEFAULT 0,
+    server_error_count BIGINT NOT NULL DEFAULT 0,
+    total_latency_ms BIGINT NOT NULL DEFAULT 0,
+    max_latency_ms BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (key_id, bucket_start, method, route)
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_api_usage_bucket ON orchepy_api_usage (bucket_start DESC);
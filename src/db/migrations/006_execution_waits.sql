@@ -0,0 +1,9 @@
+ALTER TYPE execution_status ADD VALUE IF NOT EXISTS 'waiting';
+
+ALTER TABLE orchepy_executions
+    ADD COLUMN IF NOT EXISTS resume_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS resume_step INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_executions_resume_at
+    ON orchepy_executions (resume_at)
+    WHERE resume_at IS NOT NULL;
@@ -0,0 +1,25 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'automation_run_status') THEN
+        CREATE TYPE automation_run_status AS ENUM ('completed', 'failed', 'limit_exceeded');
+    END IF;
+END$$;
+
+ALTER TABLE orchepy_workflows
+    ADD COLUMN IF NOT EXISTS execution_limits JSONB NOT NULL DEFAULT '{}';
+
+CREATE TABLE IF NOT EXISTS orchepy_automation_runs (
+    id UUID PRIMARY KEY,
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    workflow_id UUID NOT NULL REFERENCES orchepy_workflows(id) ON DELETE CASCADE,
+    trigger VARCHAR(16) NOT NULL,
+    phase VARCHAR(255) NOT NULL,
+    status automation_run_status NOT NULL,
+    actions_executed INTEGER NOT NULL DEFAULT 0,
+    total_delay_ms BIGINT NOT NULL DEFAULT 0,
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_automation_runs_case ON orchepy_automation_runs (case_id, started_at DESC);
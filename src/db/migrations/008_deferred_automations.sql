@@ -0,0 +1,15 @@
+ALTER TYPE automation_run_status ADD VALUE IF NOT EXISTS 'deferred';
+
+CREATE TABLE IF NOT EXISTS orchepy_deferred_automations (
+    id UUID PRIMARY KEY,
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    workflow_id UUID NOT NULL REFERENCES orchepy_workflows(id) ON DELETE CASCADE,
+    trigger VARCHAR(16) NOT NULL,
+    phase VARCHAR(255) NOT NULL,
+    from_phase VARCHAR(255),
+    actions JSONB NOT NULL,
+    resume_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_deferred_automations_resume_at ON orchepy_deferred_automations (resume_at);
@@ -0,0 +1,27 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'message_direction') THEN
+        CREATE TYPE message_direction AS ENUM ('outbound', 'inbound');
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'message_channel') THEN
+        CREATE TYPE message_channel AS ENUM ('email', 'sms', 'whatsapp', 'slack');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS orchepy_case_messages (
+    id UUID PRIMARY KEY,
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    direction message_direction NOT NULL,
+    channel message_channel NOT NULL,
+    sender VARCHAR(320),
+    recipient VARCHAR(320),
+    subject TEXT,
+    body TEXT NOT NULL,
+    external_id VARCHAR(255),
+    reply_token VARCHAR(64) UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_messages_case ON orchepy_case_messages (case_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_messages_recipient ON orchepy_case_messages (channel, lower(recipient), created_at DESC)
+    WHERE direction = 'outbound';
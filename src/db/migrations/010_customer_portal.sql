@@ -0,0 +1,18 @@
+ALTER TYPE message_channel ADD VALUE IF NOT EXISTS 'portal';
+
+ALTER TABLE orchepy_case_messages
+    ADD COLUMN IF NOT EXISTS public BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS attachments JSONB NOT NULL DEFAULT '[]';
+
+CREATE TABLE IF NOT EXISTS orchepy_portal_tokens (
+    id UUID PRIMARY KEY,
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    fields JSONB NOT NULL DEFAULT '[]',
+    allow_replies BOOLEAN NOT NULL DEFAULT TRUE,
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_portal_tokens_case ON orchepy_portal_tokens (case_id);
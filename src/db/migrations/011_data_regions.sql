@@ -0,0 +1,3 @@
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS region VARCHAR(64);
+ALTER TABLE orchepy_cases ADD COLUMN IF NOT EXISTS region VARCHAR(64);
+ALTER TABLE orchepy_events ADD COLUMN IF NOT EXISTS region VARCHAR(64);
@@ -0,0 +1,3 @@
+ALTER TABLE orchepy_cases ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_cases_deleted ON orchepy_cases (deleted_at) WHERE deleted_at IS NOT NULL;
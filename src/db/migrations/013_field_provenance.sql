@@ -0,0 +1 @@
+ALTER TABLE orchepy_cases ADD COLUMN IF NOT EXISTS field_provenance JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+-- Serves equality filters of GET /cases/search (data @> ...).
+CREATE INDEX IF NOT EXISTS idx_orchepy_cases_data ON orchepy_cases USING GIN (data jsonb_path_ops);
@@ -0,0 +1,2 @@
+ALTER TABLE orchepy_case_history ADD COLUMN IF NOT EXISTS from_status case_status;
+ALTER TABLE orchepy_case_history ADD COLUMN IF NOT EXISTS to_status case_status;
@@ -0,0 +1 @@
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS conditions JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,10 @@
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS from_phase VARCHAR(255);
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS transient BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS attempt INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS retry_of UUID REFERENCES orchepy_automation_runs(id) ON DELETE CASCADE;
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS retried_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_automation_runs_retryable
+    ON orchepy_automation_runs (completed_at)
+    WHERE transient AND retried_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_orchepy_automation_runs_retry_of ON orchepy_automation_runs (retry_of) WHERE retry_of IS NOT NULL;
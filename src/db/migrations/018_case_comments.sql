@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS orchepy_case_comments (
+    id UUID PRIMARY KEY,
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    author VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_comments_case ON orchepy_case_comments (case_id, created_at);
+
+ALTER TABLE orchepy_cases ADD COLUMN IF NOT EXISTS comment_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS orchepy_service_accounts (
+    name VARCHAR(64) PRIMARY KEY,
+    description TEXT,
+    allowed_phases JSONB,
+    allowed_statuses JSONB,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS run_as VARCHAR(64);
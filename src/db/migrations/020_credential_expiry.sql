@@ -0,0 +1,5 @@
+ALTER TABLE orchepy_portal_tokens ADD COLUMN IF NOT EXISTS reminded_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_portal_tokens_expiring
+    ON orchepy_portal_tokens (expires_at)
+    WHERE revoked_at IS NULL AND expires_at IS NOT NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE orchepy_cases ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_cases_tags ON orchepy_cases USING GIN (tags);
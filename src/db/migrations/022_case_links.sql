@@ -0,0 +1,21 @@
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'case_relation') THEN
+        CREATE TYPE case_relation AS ENUM ('parent', 'child', 'related');
+    END IF;
+END$$;
+
+-- Links are stored one way round: `parent` rows point from the parent to
+-- the child, and `related` rows from the lower case id to the higher one.
+CREATE TABLE IF NOT EXISTS orchepy_case_links (
+    id UUID PRIMARY KEY,
+    source_case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    target_case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    relation case_relation NOT NULL,
+    created_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (source_case_id <> target_case_id),
+    UNIQUE (source_case_id, target_case_id, relation)
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_links_target ON orchepy_case_links (target_case_id);
@@ -0,0 +1,16 @@
+-- Workflows a case takes part in besides its own, each with its own phase.
+CREATE TABLE IF NOT EXISTS orchepy_case_workflows (
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    workflow_id UUID NOT NULL REFERENCES orchepy_workflows(id) ON DELETE CASCADE,
+    current_phase VARCHAR(255) NOT NULL,
+    previous_phase VARCHAR(255),
+    phase_entered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    joined_by VARCHAR(255),
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (case_id, workflow_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_workflows_phase ON orchepy_case_workflows (workflow_id, current_phase);
+
+-- Set on history entries for a move in one of those workflows.
+ALTER TABLE orchepy_case_history ADD COLUMN IF NOT EXISTS workflow_id UUID;
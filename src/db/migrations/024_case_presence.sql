@@ -0,0 +1,11 @@
+-- Who has a case open. Rows are kept alive by heartbeats and ignored once
+-- `expires_at` has passed.
+CREATE TABLE IF NOT EXISTS orchepy_case_presence (
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    user_name VARCHAR(255) NOT NULL,
+    editing BOOLEAN NOT NULL DEFAULT FALSE,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (case_id, user_name)
+);
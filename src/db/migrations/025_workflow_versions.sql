@@ -0,0 +1,45 @@
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS updated_by VARCHAR(255);
+
+CREATE TABLE IF NOT EXISTS orchepy_workflow_versions (
+    workflow_id UUID NOT NULL REFERENCES orchepy_workflows(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    phases JSONB NOT NULL,
+    initial_phase VARCHAR(255) NOT NULL,
+    webhook_url TEXT,
+    description TEXT,
+    automations JSONB,
+    sla_config JSONB,
+    execution_limits JSONB NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL,
+    created_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (workflow_id, version)
+);
+
+INSERT INTO orchepy_workflow_versions (workflow_id, version, name, phases, initial_phase, webhook_url, description, automations, sla_config, execution_limits, active, created_by, created_at)
+SELECT id, version, name, phases, initial_phase, webhook_url, description, automations, sla_config, execution_limits, active, updated_by, updated_at
+FROM orchepy_workflows
+ON CONFLICT DO NOTHING;
+
+-- The workflow version a transition ran under, taken from the workflow it
+-- happened in when the entry is written.
+ALTER TABLE orchepy_case_history ADD COLUMN IF NOT EXISTS workflow_version INTEGER;
+
+CREATE OR REPLACE FUNCTION set_case_history_workflow_version()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.workflow_version IS NULL THEN
+        SELECT w.version INTO NEW.workflow_version
+        FROM orchepy_workflows w
+        WHERE w.id = COALESCE(NEW.workflow_id, (SELECT c.workflow_id FROM orchepy_cases c WHERE c.id = NEW.case_id));
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER trigger_set_case_history_workflow_version
+    BEFORE INSERT ON orchepy_case_history
+    FOR EACH ROW
+    EXECUTE FUNCTION set_case_history_workflow_version();
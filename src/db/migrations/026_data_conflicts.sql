@@ -0,0 +1,2 @@
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS data_conflicts JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE orchepy_workflow_versions ADD COLUMN IF NOT EXISTS data_conflicts JSONB NOT NULL DEFAULT '{}';
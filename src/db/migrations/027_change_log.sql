@@ -0,0 +1,52 @@
+-- Every change to a case or execution, read by GET /changes. Rows are read
+-- in (xact_id, id) order and only once their transaction and every older
+-- one has finished, so a reader's cursor never passes a change that has yet
+-- to commit.
+CREATE TABLE IF NOT EXISTS orchepy_changes (
+    id BIGSERIAL PRIMARY KEY,
+    xact_id BIGINT NOT NULL DEFAULT pg_current_xact_id()::text::bigint,
+    entity VARCHAR(32) NOT NULL,
+    entity_id UUID NOT NULL,
+    workflow_id UUID,
+    flow_id UUID,
+    operation VARCHAR(16) NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_changes_cursor ON orchepy_changes (xact_id, id);
+CREATE INDEX IF NOT EXISTS idx_orchepy_changes_changed ON orchepy_changes (changed_at);
+
+CREATE OR REPLACE FUNCTION record_orchepy_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    new_row JSONB := CASE WHEN TG_OP = 'DELETE' THEN NULL ELSE to_jsonb(NEW) END;
+    old_row JSONB := CASE WHEN TG_OP = 'INSERT' THEN NULL ELSE to_jsonb(OLD) END;
+    row_data JSONB := COALESCE(new_row, old_row);
+    operation VARCHAR(16) := CASE TG_OP WHEN 'INSERT' THEN 'created' WHEN 'DELETE' THEN 'deleted' ELSE 'updated' END;
+BEGIN
+    -- Soft-deleted cases are gone as far as readers are concerned.
+    IF TG_OP = 'UPDATE' AND new_row ->> 'deleted_at' IS NOT NULL AND old_row ->> 'deleted_at' IS NULL THEN
+        operation := 'deleted';
+    END IF;
+
+    INSERT INTO orchepy_changes (entity, entity_id, workflow_id, flow_id, operation)
+    VALUES (
+        TG_ARGV[0],
+        (row_data ->> 'id')::uuid,
+        (row_data ->> 'workflow_id')::uuid,
+        (row_data ->> 'flow_id')::uuid,
+        operation
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER trigger_record_case_change
+    AFTER INSERT OR UPDATE OR DELETE ON orchepy_cases
+    FOR EACH ROW
+    EXECUTE FUNCTION record_orchepy_change('case');
+
+CREATE OR REPLACE TRIGGER trigger_record_execution_change
+    AFTER INSERT OR UPDATE OR DELETE ON orchepy_executions
+    FOR EACH ROW
+    EXECUTE FUNCTION record_orchepy_change('execution');
@@ -0,0 +1,4 @@
+-- Archived workflows are hidden from listings and take no new cases; their
+-- cases and history are kept.
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS archived_by VARCHAR(255);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS orchepy_signing_keys (
+    id TEXT PRIMARY KEY,
+    secret TEXT NOT NULL,
+    created_by TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    retires_at TIMESTAMPTZ
+);
@@ -0,0 +1,13 @@
+-- The exact flow or workflow definition each execution and automation run
+-- used, stored once per distinct content and referenced by its SHA-256.
+CREATE TABLE IF NOT EXISTS orchepy_definition_snapshots (
+    hash CHAR(64) PRIMARY KEY,
+    kind VARCHAR(16) NOT NULL,
+    definition JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE orchepy_executions ADD COLUMN IF NOT EXISTS definition_hash CHAR(64);
+
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS workflow_version INTEGER;
+ALTER TABLE orchepy_automation_runs ADD COLUMN IF NOT EXISTS definition_hash CHAR(64);
@@ -0,0 +1,17 @@
+-- Responses to POST requests sent with an Idempotency-Key header, replayed
+-- when a client retries with the same key. A row without a status is a
+-- request still being handled.
+CREATE TABLE IF NOT EXISTS orchepy_idempotency_keys (
+    client TEXT NOT NULL,
+    route TEXT NOT NULL,
+    key TEXT NOT NULL,
+    request_hash TEXT NOT NULL,
+    status_code SMALLINT,
+    content_type TEXT,
+    body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (client, route, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_idempotency_keys_expires ON orchepy_idempotency_keys (expires_at);
@@ -0,0 +1,21 @@
+-- Bumped on every change to a case, for If-Match checks on updates. A new
+-- comment alone (comment_count) is not a change to the case.
+ALTER TABLE orchepy_cases ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+CREATE OR REPLACE FUNCTION bump_orchepy_case_version()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF to_jsonb(NEW) - 'version' - 'updated_at' - 'comment_count'
+       IS DISTINCT FROM to_jsonb(OLD) - 'version' - 'updated_at' - 'comment_count' THEN
+        NEW.version := OLD.version + 1;
+    ELSE
+        NEW.version := OLD.version;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER trigger_bump_case_version
+    BEFORE UPDATE ON orchepy_cases
+    FOR EACH ROW
+    EXECUTE FUNCTION bump_orchepy_case_version();
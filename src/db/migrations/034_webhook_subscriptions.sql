@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS orchepy_webhook_subscriptions (
+    id UUID PRIMARY KEY,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    event_types TEXT[] NOT NULL,
+    workflow_id UUID,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_webhook_subscriptions_event_types
+    ON orchepy_webhook_subscriptions USING GIN (event_types)
+    WHERE active;
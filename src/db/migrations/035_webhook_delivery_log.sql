@@ -0,0 +1,18 @@
+-- Every webhook sent or attempted, for troubleshooting and manual redelivery.
+CREATE TABLE IF NOT EXISTS orchepy_webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    url TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    workflow_id UUID,
+    subscription_id UUID,
+    status TEXT NOT NULL,
+    status_code INTEGER,
+    latency_ms BIGINT NOT NULL,
+    error TEXT,
+    redelivery_of BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_created ON orchepy_webhook_deliveries (created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_workflow ON orchepy_webhook_deliveries (workflow_id) WHERE workflow_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_subscription ON orchepy_webhook_deliveries (subscription_id) WHERE subscription_id IS NOT NULL;
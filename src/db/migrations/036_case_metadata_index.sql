@@ -0,0 +1,4 @@
+-- Equality filters on `metadata.*` (GET /cases, GET /cases/search) are
+-- written as containment (`metadata @> '{"source": "zapier"}'`), which this
+-- index serves for any key. Ordering and `exists` filters are not indexed.
+CREATE INDEX IF NOT EXISTS idx_orchepy_cases_metadata ON orchepy_cases USING GIN (metadata jsonb_path_ops);
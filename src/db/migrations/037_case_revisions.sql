@@ -0,0 +1,21 @@
+-- Every change to a case's data, with the whole data after it, for
+-- GET /cases/{id}/revisions and restores. Revision 1 is the data the case
+-- was created with.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'data_write_source') THEN
+        CREATE TYPE data_write_source AS ENUM ('api', 'automation');
+    END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS orchepy_case_revisions (
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    data JSONB NOT NULL,
+    changed_fields TEXT[] NOT NULL DEFAULT '{}',
+    source data_write_source NOT NULL,
+    actor VARCHAR(255),
+    trigger VARCHAR(64),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (case_id, revision)
+);
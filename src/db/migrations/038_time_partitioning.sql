@@ -0,0 +1,103 @@
+-- Events, case history and executions are partitioned by month on the time
+-- each row was written, so old months can be dropped or archived without
+-- touching recent ones and time-bounded queries only scan the months they
+-- cover. Partitions are named <table>_YYYY_MM and cover UTC months; rows
+-- outside every monthly partition land in <table>_default.
+
+-- Creates the missing monthly partitions of `parent` from the month of
+-- `from_time` through the month of `to_time` and returns how many it
+-- created. A month that already has rows in the default partition is
+-- skipped with a warning, as Postgres can't attach it over them.
+CREATE OR REPLACE FUNCTION orchepy_create_monthly_partitions(parent TEXT, from_time TIMESTAMPTZ, to_time TIMESTAMPTZ)
+RETURNS INTEGER AS $$
+DECLARE
+    month_start TIMESTAMP := date_trunc('month', from_time AT TIME ZONE 'UTC');
+    last_month TIMESTAMP := date_trunc('month', to_time AT TIME ZONE 'UTC');
+    partition TEXT;
+    created INTEGER := 0;
+BEGIN
+    WHILE month_start <= last_month LOOP
+        partition := parent || '_' || to_char(month_start, 'YYYY_MM');
+        IF to_regclass(partition) IS NULL THEN
+            BEGIN
+                EXECUTE format(
+                    'CREATE TABLE %I PARTITION OF %I FOR VALUES FROM (%L) TO (%L)',
+                    partition,
+                    parent,
+                    month_start AT TIME ZONE 'UTC',
+                    (month_start + INTERVAL '1 month') AT TIME ZONE 'UTC'
+                );
+                created := created + 1;
+            EXCEPTION WHEN check_violation THEN
+                RAISE WARNING 'Partition % not created: %_default has rows for that month', partition, parent;
+            END;
+        END IF;
+        month_start := month_start + INTERVAL '1 month';
+    END LOOP;
+    RETURN created;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Replaces `parent` with a copy partitioned by month on `time_column`,
+-- with partitions for every month that has rows and the next three. The
+-- caller adds the keys, indexes and triggers afterwards.
+CREATE OR REPLACE FUNCTION orchepy_partition_by_month(parent TEXT, time_column TEXT)
+RETURNS VOID AS $$
+DECLARE
+    old_table TEXT := parent || '_unpartitioned';
+    oldest TIMESTAMPTZ;
+BEGIN
+    IF (SELECT relkind FROM pg_class WHERE oid = to_regclass(parent)) = 'p' THEN
+        RETURN;
+    END IF;
+
+    EXECUTE format('ALTER TABLE %I RENAME TO %I', parent, old_table);
+    EXECUTE format(
+        'CREATE TABLE %I (LIKE %I INCLUDING DEFAULTS INCLUDING CONSTRAINTS) PARTITION BY RANGE (%I)',
+        parent, old_table, time_column
+    );
+    EXECUTE format('CREATE TABLE %I PARTITION OF %I DEFAULT', parent || '_default', parent);
+
+    EXECUTE format('SELECT min(%I) FROM %I', time_column, old_table) INTO oldest;
+    PERFORM orchepy_create_monthly_partitions(parent, COALESCE(oldest, NOW()), NOW() + INTERVAL '3 months');
+
+    EXECUTE format('INSERT INTO %I SELECT * FROM %I', parent, old_table);
+    EXECUTE format('DROP TABLE %I CASCADE', old_table);
+END;
+$$ LANGUAGE plpgsql;
+
+-- A unique key on a partitioned table has to include the partition key, so
+-- executions can no longer reference events by id alone.
+ALTER TABLE orchepy_executions DROP CONSTRAINT IF EXISTS orchepy_executions_event_id_fkey;
+
+SELECT orchepy_partition_by_month('orchepy_events', 'received_at');
+ALTER TABLE orchepy_events ADD PRIMARY KEY (id, received_at);
+CREATE INDEX IF NOT EXISTS idx_orchepy_events_id ON orchepy_events (id);
+CREATE INDEX IF NOT EXISTS idx_orchepy_events_type_received ON orchepy_events (event_type, received_at DESC);
+
+SELECT orchepy_partition_by_month('orchepy_case_history', 'transitioned_at');
+ALTER TABLE orchepy_case_history ADD PRIMARY KEY (id, transitioned_at);
+ALTER TABLE orchepy_case_history
+    ADD CONSTRAINT orchepy_case_history_case_id_fkey FOREIGN KEY (case_id) REFERENCES orchepy_cases(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_history_case_id ON orchepy_case_history (case_id, transitioned_at DESC);
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_history_phases ON orchepy_case_history (from_phase, to_phase);
+
+CREATE OR REPLACE TRIGGER trigger_set_case_history_workflow_version
+    BEFORE INSERT ON orchepy_case_history
+    FOR EACH ROW
+    EXECUTE FUNCTION set_case_history_workflow_version();
+
+SELECT orchepy_partition_by_month('orchepy_executions', 'started_at');
+ALTER TABLE orchepy_executions ADD PRIMARY KEY (id, started_at);
+ALTER TABLE orchepy_executions
+    ADD CONSTRAINT orchepy_executions_flow_id_fkey FOREIGN KEY (flow_id) REFERENCES orchepy_flows(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS idx_orchepy_executions_id ON orchepy_executions (id);
+CREATE INDEX IF NOT EXISTS idx_orchepy_executions_event ON orchepy_executions (event_id);
+CREATE INDEX IF NOT EXISTS idx_orchepy_executions_flow ON orchepy_executions (flow_id, started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_orchepy_executions_resume_at ON orchepy_executions (resume_at) WHERE resume_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_orchepy_executions_status ON orchepy_executions (status, started_at DESC);
+
+CREATE OR REPLACE TRIGGER trigger_record_execution_change
+    AFTER INSERT OR UPDATE OR DELETE ON orchepy_executions
+    FOR EACH ROW
+    EXECUTE FUNCTION record_orchepy_change('execution');
@@ -0,0 +1,8 @@
+-- An integration's own id for a case (`POST /cases` with `external_id`),
+-- looked up with GET /cases/by-external-id/{id}. Unique within a workflow
+-- among cases that aren't deleted, so a deleted case's id can be reused.
+ALTER TABLE orchepy_cases ADD COLUMN IF NOT EXISTS external_id TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_orchepy_cases_external_id
+    ON orchepy_cases (workflow_id, external_id)
+    WHERE external_id IS NOT NULL AND deleted_at IS NULL;
@@ -0,0 +1,20 @@
+-- Events and webhooks that follow a case write (case.created, case.moved).
+-- They are inserted in the write's transaction and delivered once it has
+-- committed, so a crash in between no longer loses them. Messages of one
+-- case are delivered in order per kind; delivered ones are deleted and ones
+-- that kept failing stay with failed_at set.
+CREATE TABLE IF NOT EXISTS orchepy_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    case_id UUID NOT NULL,
+    kind TEXT NOT NULL,
+    delivery JSONB NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ,
+    last_error TEXT,
+    failed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_outbox_due ON orchepy_outbox (next_attempt_at) WHERE failed_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_orchepy_outbox_case ON orchepy_outbox (case_id, kind, id) WHERE failed_at IS NULL;
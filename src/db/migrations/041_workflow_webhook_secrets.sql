@@ -0,0 +1,4 @@
+-- A workflow's own secret for signing the webhooks sent to its webhook_url,
+-- used instead of the global signing keys when set. Kept out of workflow
+-- versions, as it isn't part of the definition.
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS webhook_secret TEXT;
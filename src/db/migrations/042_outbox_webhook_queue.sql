@@ -0,0 +1,5 @@
+-- The outbox now also queues status and credential webhooks and deliveries
+-- to webhook subscriptions, which aren't always about a case.
+ALTER TABLE orchepy_outbox ALTER COLUMN case_id DROP NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_outbox_failed ON orchepy_outbox (id DESC) WHERE failed_at IS NOT NULL;
@@ -0,0 +1,4 @@
+-- A workflow's own shape for the webhooks sent to its webhook_url, with
+-- ${case.*} placeholders, used instead of the default payload when set.
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS webhook_template JSONB;
+ALTER TABLE orchepy_workflow_versions ADD COLUMN IF NOT EXISTS webhook_template JSONB;
@@ -0,0 +1,6 @@
+-- Headers and credentials sent with the webhooks to a workflow's
+-- webhook_url, for receivers that reject unauthenticated requests.
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS webhook_headers JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE orchepy_workflows ADD COLUMN IF NOT EXISTS webhook_auth JSONB;
+ALTER TABLE orchepy_workflow_versions ADD COLUMN IF NOT EXISTS webhook_headers JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE orchepy_workflow_versions ADD COLUMN IF NOT EXISTS webhook_auth JSONB;
@@ -0,0 +1,14 @@
+-- Every email sent or attempted, for troubleshooting delivery.
+CREATE TABLE IF NOT EXISTS orchepy_email_log (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    case_id UUID,
+    recipients TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    status TEXT NOT NULL,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_log_created ON orchepy_email_log (created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_email_log_case ON orchepy_email_log (case_id) WHERE case_id IS NOT NULL;
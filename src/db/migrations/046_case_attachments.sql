@@ -0,0 +1,14 @@
+-- Files uploaded to a case. The content lives in object storage under
+-- `storage_key`; see `services::storage`.
+CREATE TABLE IF NOT EXISTS orchepy_case_attachments (
+    id UUID PRIMARY KEY,
+    case_id UUID NOT NULL REFERENCES orchepy_cases(id) ON DELETE CASCADE,
+    filename VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255),
+    size_bytes BIGINT NOT NULL,
+    storage_key TEXT NOT NULL,
+    uploaded_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_case_attachments_case ON orchepy_case_attachments (case_id, created_at);
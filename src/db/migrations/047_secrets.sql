@@ -0,0 +1,10 @@
+-- Credentials that webhook URLs and headers refer to as ${secrets.NAME}.
+-- `ciphertext` is the AES-256-GCM nonce followed by the encrypted value,
+-- under the key in SECRETS_KEY; see `services::secrets`.
+CREATE TABLE IF NOT EXISTS orchepy_secrets (
+    name VARCHAR(64) PRIMARY KEY,
+    description TEXT,
+    ciphertext BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
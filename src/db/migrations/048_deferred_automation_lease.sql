@@ -0,0 +1,8 @@
+-- Deferred automations are leased to a resume worker instead of deleted on
+-- claim, and deleted once their run has gone through, so a crash or failed
+-- run no longer loses them. total_delay_ms carries the time the automation
+-- has spent in delays so far, which counts toward max_total_delay_ms.
+ALTER TABLE orchepy_deferred_automations
+    ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS total_delay_ms BIGINT NOT NULL DEFAULT 0;
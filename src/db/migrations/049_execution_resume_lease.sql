@@ -0,0 +1,8 @@
+-- A resume worker leases the waiting executions it claims until
+-- locked_until. An execution still running when its lease runs out, because
+-- its worker crashed, is claimed again instead of staying running forever.
+ALTER TABLE orchepy_executions ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_orchepy_executions_locked_until
+    ON orchepy_executions (locked_until)
+    WHERE locked_until IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Portal tokens are stored as their SHA-256 hash, so the links can't be
+-- read back from the database or the API; only the request that creates a
+-- token returns it.
+ALTER TABLE orchepy_portal_tokens RENAME COLUMN token TO token_hash;
+UPDATE orchepy_portal_tokens SET token_hash = encode(sha256(convert_to(token_hash, 'UTF8')), 'hex');
@@ -1,46 +1,234 @@
-use crate::models::automation::{AutomationAction, AutomationResult, CaseModification, OnError, PhaseAutomation};
+use crate::models::automation::{
+    AutomationAction, AutomationLimits, AutomationResult, CaseModification, ComparisonTrace, Condition,
+    ConditionTrace, DeferredActions, LogicalOperator, OnError, PhaseAutomation,
+};
+use crate::models::message::{CaseMessage, MessageChannel};
 use crate::models::Case;
+use crate::services::clock::{system_clock, SharedClock};
+use crate::services::mailer::{Mailer, OutboundEmail};
+use crate::services::notification::{TwilioConfig, TwilioError, TWILIO_UNSUBSCRIBED};
+use crate::services::outbound_http::http_client;
+use crate::services::secrets::{referenced_secrets, Secrets};
+use crate::services::webhook_template::replace_placeholders;
 use anyhow::{anyhow, Result};
 use reqwest::Client;
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
+/// Delays up to this long are slept in-process; anything longer suspends the
+/// run and hands the remaining actions to the scheduler.
+pub const MAX_INLINE_DELAY_MS: u64 = 30_000;
+
+/// Raised when a run goes over one of the workflow's `AutomationLimits`.
+/// It aborts the whole run regardless of the action's `on_error` setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: &'static str,
+    pub max: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} exceeded: {} > {}", self.limit, self.actual, self.max)
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// A failure that may go away on its own: a webhook that timed out, could
+/// not connect, or answered 5xx/429. Runs that stop on one are picked up by
+/// the automation retry worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransientFailure(pub String);
+
+impl fmt::Display for TransientFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TransientFailure {}
+
+fn request_failure(err: reqwest::Error) -> anyhow::Error {
+    if err.is_timeout() || err.is_connect() {
+        TransientFailure(err.to_string()).into()
+    } else {
+        anyhow!(err)
+    }
+}
+
+#[derive(Default)]
+struct RunUsage {
+    actions: AtomicU32,
+    delay_ms: AtomicU64,
+    conditions: Mutex<Vec<ConditionTrace>>,
+}
+
+impl RunUsage {
+    fn record_condition(&self, trace: ConditionTrace) {
+        self.conditions.lock().expect("condition traces lock").push(trace);
+    }
+
+    fn charge_action(&self, limits: &AutomationLimits) -> Result<()> {
+        let actions = self.actions.fetch_add(1, Ordering::Relaxed) + 1;
+        if actions > limits.max_actions_per_run {
+            return Err(LimitExceeded {
+                limit: "max_actions_per_run",
+                max: limits.max_actions_per_run as u64,
+                actual: actions as u64,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn charge_delay(&self, duration_ms: u64, limits: &AutomationLimits) -> Result<()> {
+        let total = self.delay_ms.fetch_add(duration_ms, Ordering::Relaxed) + duration_ms;
+        if total > limits.max_total_delay_ms {
+            return Err(LimitExceeded {
+                limit: "max_total_delay_ms",
+                max: limits.max_total_delay_ms,
+                actual: total,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
 pub struct AutomationExecutor {
     http_client: Client,
+    limits: AutomationLimits,
+    twilio: Option<TwilioConfig>,
+    mailer: Option<Arc<dyn Mailer>>,
+    clock: SharedClock,
+    secrets: Secrets,
+    defers_external_actions: bool,
+    delay_spent_ms: u64,
 }
 
 impl AutomationExecutor {
     pub fn new() -> Self {
+        Self::with_limits(AutomationLimits::default())
+    }
+
+    pub fn with_limits(limits: AutomationLimits) -> Self {
         Self {
-            http_client: Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .expect("Failed to create HTTP client"),
+            http_client: http_client(Duration::from_secs(30)),
+            limits,
+            twilio: None,
+            mailer: None,
+            clock: system_clock(),
+            secrets: Secrets::default(),
+            defers_external_actions: false,
+            delay_spent_ms: 0,
         }
     }
 
+    /// Enables SMS and WhatsApp `send_message` actions; without it they fail
+    /// as unconfigured.
+    pub fn with_twilio(mut self, twilio: Option<TwilioConfig>) -> Self {
+        self.twilio = twilio;
+        self
+    }
+
+    /// Enables `send_message` actions on the email channel.
+    pub fn with_mailer(mut self, mailer: Option<Arc<dyn Mailer>>) -> Self {
+        self.mailer = mailer;
+        self
+    }
+
+    /// Substitutes `secrets` for the `${secrets.NAME}` placeholders in
+    /// webhook URLs and headers.
+    pub fn with_secrets(mut self, secrets: Secrets) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Stops at the first webhook, message or delay and hands it back, with
+    /// the actions after it, as deferred to now. A caller that holds a
+    /// transaction runs them once it has committed, rather than waiting on
+    /// the network with the case locked.
+    pub fn deferring_external_actions(mut self) -> Self {
+        self.defers_external_actions = true;
+        self
+    }
+
+    /// Continues a run that already spent `delay_ms` in delays before it was
+    /// deferred, so `max_total_delay_ms` covers the automation as a whole.
+    pub fn with_delay_spent(mut self, delay_ms: u64) -> Self {
+        self.delay_spent_ms = delay_ms;
+        self
+    }
+
+    /// Reads the time for deferred `delay` actions from `clock` and waits
+    /// on it for inline ones.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub async fn execute_automations(
         &self,
         automations: &[&PhaseAutomation],
         case: &Case,
         from_phase: Option<&str>,
+    ) -> Result<AutomationResult> {
+        self.execute_automations_traced(automations, case, from_phase).await.0
+    }
+
+    /// Like `execute_automations`, also returning how every condition reached
+    /// was decided, including when the run fails.
+    pub async fn execute_automations_traced(
+        &self,
+        automations: &[&PhaseAutomation],
+        case: &Case,
+        from_phase: Option<&str>,
+    ) -> (Result<AutomationResult>, Vec<ConditionTrace>) {
+        let usage = RunUsage::default();
+        usage.delay_ms.store(self.delay_spent_ms, Ordering::Relaxed);
+        let result = self.run_automations(automations, case, from_phase, &usage).await;
+        let conditions = usage.conditions.into_inner().expect("condition traces lock");
+        (result, conditions)
+    }
+
+    async fn run_automations(
+        &self,
+        automations: &[&PhaseAutomation],
+        case: &Case,
+        from_phase: Option<&str>,
+        usage: &RunUsage,
     ) -> Result<AutomationResult> {
         let mut result = AutomationResult::default();
-        for automation in automations {
+        for (idx, automation) in automations.iter().enumerate() {
             info!(
                 "Executing automation for phase '{}' (trigger: {:?})",
                 automation.phase, automation.trigger
             );
 
+            let path = format!("automations[{}].actions", idx);
             match self
-                .execute_actions(&automation.actions, case, from_phase)
+                .execute_actions(&automation.actions, &path, case, from_phase, usage)
                 .await
             {
                 Ok(action_result) => {
                     result.modifications.extend(action_result.modifications);
+                    if let Some(mut deferred) = action_result.deferred {
+                        deferred.actions.extend(
+                            automations[idx + 1..]
+                                .iter()
+                                .flat_map(|a| a.actions.iter().cloned()),
+                        );
+                        result.deferred = Some(deferred);
+                        break;
+                    }
                 }
                 Err(e) => {
                     error!(
@@ -52,14 +240,19 @@ impl AutomationExecutor {
             }
         }
 
+        result.actions_executed = usage.actions.load(Ordering::Relaxed);
+        result.total_delay_ms = usage.delay_ms.load(Ordering::Relaxed);
+
         Ok(result)
     }
 
     fn execute_actions<'a>(
         &'a self,
         actions: &'a [AutomationAction],
+        path: &'a str,
         case: &'a Case,
         from_phase: Option<&'a str>,
+        usage: &'a RunUsage,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AutomationResult>> + Send + 'a>> {
         Box::pin(async move {
         let mut action_responses: HashMap<String, Value> = HashMap::new();
@@ -71,25 +264,51 @@ impl AutomationExecutor {
                 .unwrap_or(&format!("action_{}", idx))
                 .to_string();
 
+            if self.defers_external_actions && action.is_external() {
+                info!("Handing over action '{}' and the ones after it", action_name);
+                result.deferred = Some(DeferredActions {
+                    resume_at: self.clock.now(),
+                    actions: actions[idx..].to_vec(),
+                });
+                return Ok(result);
+            }
+
             info!("Executing action: {}", action_name);
 
+            usage.charge_action(&self.limits)?;
+
+            let action_path = format!("{}[{}]", path, idx);
             let action_result = self
-                .execute_action(action, case, from_phase, &action_responses)
+                .execute_action(action, &action_path, case, from_phase, &action_responses, usage)
                 .await;
 
             match action_result {
-                Ok((response, modifications)) => {
+                Ok((response, modifications, deferred)) => {
                     if let Some(id) = action.id() {
                         action_responses.insert(id.to_string(), response);
                     }
                     result.modifications.extend(modifications);
+
+                    if let Some(mut deferred) = deferred {
+                        deferred.actions.extend(actions[idx + 1..].iter().cloned());
+                        result.deferred = Some(deferred);
+                        return Ok(result);
+                    }
+                }
+                Err(e) if e.is::<LimitExceeded>() => {
+                    error!("Action '{}' aborted the run: {}", action_name, e);
+                    return Err(e);
                 }
                 Err(e) => {
                     error!("Action '{}' failed: {}", action_name, e);
 
                     match action.on_error() {
                         OnError::Stop => {
-                            return Err(anyhow!("Action '{}' failed: {}", action_name, e));
+                            let message = format!("Action '{}' failed: {}", action_name, e);
+                            if e.is::<TransientFailure>() {
+                                return Err(TransientFailure(message).into());
+                            }
+                            return Err(anyhow!(message));
                         }
                         OnError::Continue => {
                             warn!("Action '{}' failed but continuing execution", action_name);
@@ -106,10 +325,12 @@ impl AutomationExecutor {
     async fn execute_action(
         &self,
         action: &AutomationAction,
+        path: &str,
         case: &Case,
         from_phase: Option<&str>,
         previous_responses: &HashMap<String, Value>,
-    ) -> Result<(Value, Vec<CaseModification>)> {
+        usage: &RunUsage,
+    ) -> Result<(Value, Vec<CaseModification>, Option<DeferredActions>)> {
         match action {
             AutomationAction::Webhook {
                 url,
@@ -129,6 +350,16 @@ impl AutomationExecutor {
                     self.build_webhook_body(case, from_phase, fields.as_ref())
                 };
 
+                let body_bytes = serde_json::to_vec(&body)?.len();
+                if body_bytes > self.limits.max_webhook_body_bytes {
+                    return Err(LimitExceeded {
+                        limit: "max_webhook_body_bytes",
+                        max: self.limits.max_webhook_body_bytes as u64,
+                        actual: body_bytes as u64,
+                    }
+                    .into());
+                }
+
                 let response = if retry.enabled {
                     self.execute_webhook_with_retry(
                         url,
@@ -148,43 +379,86 @@ impl AutomationExecutor {
                     )
                     .await?
                 };
-                Ok((response, vec![]))
+                Ok((response, vec![], None))
+            }
+
+            AutomationAction::Delay { duration_ms, .. } if *duration_ms > MAX_INLINE_DELAY_MS => {
+                usage.charge_delay(*duration_ms, &self.limits)?;
+                let resume_at = self.clock.now() + chrono::Duration::milliseconds(*duration_ms as i64);
+                info!("Deferring remaining actions until {} ({}ms delay)", resume_at, duration_ms);
+                Ok((
+                    json!({"deferred_until": resume_at}),
+                    vec![],
+                    Some(DeferredActions { resume_at, actions: vec![] }),
+                ))
             }
 
             AutomationAction::Delay { duration_ms, .. } => {
+                usage.charge_delay(*duration_ms, &self.limits)?;
                 debug!("Delaying for {}ms", duration_ms);
-                sleep(Duration::from_millis(*duration_ms)).await;
-                Ok((json!({"delayed_ms": duration_ms}), vec![]))
+                self.clock.sleep(Duration::from_millis(*duration_ms)).await;
+                Ok((json!({"delayed_ms": duration_ms}), vec![], None))
             }
 
             AutomationAction::Conditional {
+                name,
                 condition,
                 then,
                 r#else,
-                ..
             } => {
-                let condition_result = self.evaluate_condition(condition, case)?;
-
-                let mut modifications = vec![];
-
-                if condition_result {
+                let (outcome, comparisons) = self.explain_condition(condition, case);
+                usage.record_condition(ConditionTrace {
+                    path: path.to_string(),
+                    name: name.clone(),
+                    result: outcome.as_ref().ok().copied(),
+                    comparisons,
+                });
+                let condition_result = outcome?;
+
+                let (branch, branch_path) = if condition_result {
                     debug!("Condition evaluated to true, executing then branch");
-                    let result = self.execute_actions(then, case, from_phase).await?;
-                    modifications.extend(result.modifications);
-                } else if let Some(else_actions) = r#else {
+                    (Some(then), format!("{}.then", path))
+                } else {
                     debug!("Condition evaluated to false, executing else branch");
-                    let result = self.execute_actions(else_actions, case, from_phase).await?;
-                    modifications.extend(result.modifications);
-                }
+                    (r#else.as_ref(), format!("{}.else", path))
+                };
 
-                Ok((json!({"condition_result": condition_result}), modifications))
+                let result = match branch {
+                    Some(branch) => self.execute_actions(branch, &branch_path, case, from_phase, usage).await?,
+                    None => AutomationResult::default(),
+                };
+
+                Ok((
+                    json!({"condition_result": condition_result}),
+                    result.modifications,
+                    result.deferred,
+                ))
             }
 
             AutomationAction::MoveToPhase { phase, .. } => {
                 debug!("Queueing move to phase: {}", phase);
                 Ok((
                     json!({"action": "move_to_phase", "phase": phase}),
-                    vec![CaseModification::MoveToPhase { phase: phase.clone() }]
+                    vec![CaseModification::MoveToPhase { phase: phase.clone() }],
+                    None,
+                ))
+            }
+
+            AutomationAction::SetStatus { status, .. } => {
+                debug!("Queueing status change to {:?}", status);
+                Ok((
+                    json!({"action": "set_status", "status": status}),
+                    vec![CaseModification::SetStatus { status: status.clone() }],
+                    None,
+                ))
+            }
+
+            AutomationAction::AddTag { tag, .. } => {
+                debug!("Queueing tag '{}'", tag);
+                Ok((
+                    json!({"action": "add_tag", "tag": tag}),
+                    vec![CaseModification::AddTag { tag: tag.clone() }],
+                    None,
                 ))
             }
 
@@ -192,50 +466,199 @@ impl AutomationExecutor {
                 debug!("Queueing set field '{}' to {:?}", field, value);
                 Ok((
                     json!({"action": "set_field", "field": field, "value": value}),
-                    vec![CaseModification::SetField { field: field.clone(), value: value.clone() }]
+                    vec![CaseModification::SetField {
+                        field: field.clone(),
+                        value: value.clone(),
+                        action: action.name().map(str::to_string),
+                    }],
+                    None,
+                ))
+            }
+
+            AutomationAction::SendMessage {
+                channel,
+                to,
+                subject,
+                body,
+                opt_out_field,
+                ..
+            } => {
+                let opted_out = self.get_field_value(opt_out_field, case).ok() == Some(json!(true));
+                if opted_out {
+                    info!("Case {} has opted out of messages, skipping", case.id);
+                    return Ok((json!({"skipped": "opted_out"}), vec![], None));
+                }
+
+                let to = self.render_template(to, case)?;
+                let body = self.render_template(body, case)?;
+
+                if *channel == MessageChannel::Email {
+                    let subject = match subject {
+                        Some(subject) => self.render_template(subject, case)?,
+                        None => String::new(),
+                    };
+                    return self.send_email(case, to, subject, body).await;
+                }
+
+                let label = action.id().or(action.name());
+                self.send_text(case, *channel, to, body, opt_out_field, label).await
+            }
+        }
+    }
+
+    async fn send_email(
+        &self,
+        case: &Case,
+        to: String,
+        subject: String,
+        body: String,
+    ) -> Result<(Value, Vec<CaseModification>, Option<DeferredActions>)> {
+        let mailer = self
+            .mailer
+            .as_ref()
+            .ok_or_else(|| anyhow!("Email is not configured (SMTP_* settings missing)"))?;
+
+        let message = CaseMessage::outbound(case.id, MessageChannel::Email, Some(to.clone()), Some(subject.clone()), body.clone());
+        mailer
+            .send(&OutboundEmail {
+                to,
+                subject,
+                body,
+                reply_token: message.reply_token.clone(),
+                kind: "automation".to_string(),
+                case_id: Some(case.id),
+            })
+            .await?;
+
+        Ok((
+            json!({"sent": true, "reply_token": message.reply_token}),
+            vec![CaseModification::RecordMessage(message)],
+            None,
+        ))
+    }
+
+    async fn send_text(
+        &self,
+        case: &Case,
+        channel: MessageChannel,
+        to: String,
+        body: String,
+        opt_out_field: &str,
+        action: Option<&str>,
+    ) -> Result<(Value, Vec<CaseModification>, Option<DeferredActions>)> {
+        let twilio = self
+            .twilio
+            .as_ref()
+            .ok_or_else(|| anyhow!("Messaging is not configured (TWILIO_* settings missing)"))?;
+
+        let (from, address) = match channel {
+            MessageChannel::Sms => (twilio.from.clone(), to.clone()),
+            MessageChannel::Whatsapp => (
+                whatsapp_address(twilio.whatsapp_from.as_deref().unwrap_or(&twilio.from)),
+                whatsapp_address(&to),
+            ),
+            other => return Err(anyhow!("send_message does not support the {:?} channel", other)),
+        };
+
+        match twilio.send_message(&self.http_client, &from, &address, &body).await {
+            Ok(response) => {
+                let mut message = CaseMessage::outbound(case.id, channel, Some(to), None, body);
+                message.sender = Some(from);
+                message.external_id = response.get("sid").and_then(Value::as_str).map(str::to_string);
+                Ok((response, vec![CaseModification::RecordMessage(message)], None))
+            }
+            Err(e)
+                if e
+                    .downcast_ref::<TwilioError>()
+                    .is_some_and(|e| e.code == Some(TWILIO_UNSUBSCRIBED)) =>
+            {
+                info!("{} has unsubscribed, marking case {} as opted out", address, case.id);
+                Ok((
+                    json!({"skipped": "opted_out"}),
+                    vec![CaseModification::SetField {
+                        field: opt_out_field.to_string(),
+                        value: json!(true),
+                        action: action.map(str::to_string),
+                    }],
+                    None,
                 ))
             }
+            Err(e) => Err(e),
         }
     }
 
-    fn evaluate_condition(&self, condition: &crate::models::automation::Condition, case: &Case) -> Result<bool> {
-        use crate::models::automation::Condition;
+    /// Replaces `${data.customer.name}` style placeholders with case fields.
+    fn render_template(&self, template: &str, case: &Case) -> Result<String> {
+        replace_placeholders(template, |path| {
+            Ok(match self.get_field_value(path, case)? {
+                Value::String(text) => text,
+                other => other.to_string(),
+            })
+        })
+    }
+
+    /// Evaluates `condition` against the case, returning the comparisons made
+    /// along the way with the values they read.
+    pub(crate) fn explain_condition(&self, condition: &Condition, case: &Case) -> (Result<bool>, Vec<ComparisonTrace>) {
+        let mut comparisons = Vec::new();
 
-        match condition {
+        let result = match condition {
             Condition::Simple { field, operator, value } => {
-                self.evaluate_simple_condition(field, operator, value, case)
+                self.evaluate_comparison(field, operator, value, case, &mut comparisons)
             }
             Condition::Complex { operator, conditions } => {
-                use crate::models::automation::LogicalOperator;
-
-                match operator {
-                    LogicalOperator::And => {
-                        for cond in conditions {
-                            if !self.evaluate_simple_condition(&cond.field, &cond.operator, &cond.value, case)? {
-                                return Ok(false);
-                            }
+                // AND is decided by the first false comparison, OR by the first true one.
+                let decisive = matches!(operator, LogicalOperator::Or);
+                let mut result = Ok(!decisive);
+                for cond in conditions {
+                    match self.evaluate_comparison(&cond.field, &cond.operator, &cond.value, case, &mut comparisons) {
+                        Ok(outcome) if outcome == decisive => {
+                            result = Ok(outcome);
+                            break;
                         }
-                        Ok(true)
-                    }
-                    LogicalOperator::Or => {
-                        for cond in conditions {
-                            if self.evaluate_simple_condition(&cond.field, &cond.operator, &cond.value, case)? {
-                                return Ok(true);
-                            }
+                        Ok(_) => {}
+                        Err(e) => {
+                            result = Err(e);
+                            break;
                         }
-                        Ok(false)
                     }
                 }
+                result
             }
-        }
+        };
+
+        (result, comparisons)
     }
 
-    fn evaluate_simple_condition(&self, field: &str, operator: &str, expected: &Value, case: &Case) -> Result<bool> {
-        let actual_value = self.get_field_value(field, case)?;
+    fn evaluate_comparison(
+        &self,
+        field: &str,
+        operator: &str,
+        expected: &Value,
+        case: &Case,
+        out: &mut Vec<ComparisonTrace>,
+    ) -> Result<bool> {
+        let (outcome, actual) = match self.get_field_value(field, case) {
+            Ok(actual) => (Self::compare_values(operator, &actual, expected), Some(actual)),
+            Err(e) => (Err(e), None),
+        };
+
+        out.push(ComparisonTrace {
+            field: field.to_string(),
+            operator: operator.to_string(),
+            expected: expected.clone(),
+            actual,
+            outcome: outcome.as_ref().ok().copied(),
+            error: outcome.as_ref().err().map(ToString::to_string),
+        });
+
+        outcome
+    }
 
+    fn compare_values(operator: &str, actual_value: &Value, expected: &Value) -> Result<bool> {
         match operator {
-            "==" | "=" => Ok(actual_value == *expected),
-            "!=" => Ok(actual_value != *expected),
+            "==" | "=" => Ok(actual_value == expected),
+            "!=" => Ok(actual_value != expected),
             ">" => {
                 if let (Some(a), Some(b)) = (actual_value.as_f64(), expected.as_f64()) {
                     Ok(a > b)
@@ -265,7 +688,9 @@ impl AutomationExecutor {
                 }
             }
             "contains" => {
-                if let Some(s) = actual_value.as_str() {
+                if let Some(items) = actual_value.as_array() {
+                    Ok(items.contains(expected))
+                } else if let Some(s) = actual_value.as_str() {
                     if let Some(substr) = expected.as_str() {
                         Ok(s.contains(substr))
                     } else {
@@ -293,6 +718,7 @@ impl AutomationExecutor {
             Some(&"status") => Ok(json!(case.status)),
             Some(&"current_phase") => Ok(json!(case.current_phase)),
             Some(&"previous_phase") => Ok(json!(case.previous_phase)),
+            Some(&"tags") => Ok(json!(case.tags)),
             _ => Err(anyhow!("Unsupported field path: {}", field)),
         }
     }
@@ -357,6 +783,8 @@ impl AutomationExecutor {
         }
     }
 
+    /// Sends the webhook with the secrets `url` and `headers` refer to filled
+    /// in. Errors leave out a URL that had secrets.
     async fn execute_webhook(
         &self,
         url: &str,
@@ -364,26 +792,32 @@ impl AutomationExecutor {
         headers: Option<&HashMap<String, String>>,
         body: &Value,
     ) -> Result<Value> {
+        let resolved_url = self.secrets.resolve(url)?;
+        let url_has_secrets = !referenced_secrets(url).is_empty();
         let mut request = match method.to_uppercase().as_str() {
-            "GET" => self.http_client.get(url),
-            "POST" => self.http_client.post(url).json(body),
-            "PUT" => self.http_client.put(url).json(body),
-            "DELETE" => self.http_client.delete(url),
-            "PATCH" => self.http_client.patch(url).json(body),
+            "GET" => self.http_client.get(&resolved_url),
+            "POST" => self.http_client.post(&resolved_url).json(body),
+            "PUT" => self.http_client.put(&resolved_url).json(body),
+            "DELETE" => self.http_client.delete(&resolved_url),
+            "PATCH" => self.http_client.patch(&resolved_url).json(body),
             _ => return Err(anyhow!("Unsupported HTTP method: {}", method)),
         };
 
         if let Some(header_map) = headers {
             for (key, value) in header_map {
-                request = request.header(key, value);
+                request = request.header(key, self.secrets.resolve(value)?);
             }
         }
 
-        let response = request.send().await.map_err(|e| anyhow!(e))?;
+        let failure = |err: reqwest::Error| request_failure(if url_has_secrets { err.without_url() } else { err });
+        let response = request.send().await.map_err(failure)?;
 
         let status = response.status();
-        let body_text = response.text().await.map_err(|e| anyhow!(e))?;
+        let body_text = response.text().await.map_err(failure)?;
 
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(TransientFailure(format!("HTTP {} - {}", status, body_text)).into());
+        }
         if !status.is_success() {
             return Err(anyhow!("HTTP {} - {}", status, body_text));
         }
@@ -427,6 +861,14 @@ impl AutomationExecutor {
     }
 }
 
+fn whatsapp_address(number: &str) -> String {
+    if number.starts_with("whatsapp:") {
+        number.to_string()
+    } else {
+        format!("whatsapp:{}", number)
+    }
+}
+
 impl Default for AutomationExecutor {
     fn default() -> Self {
         Self::new()
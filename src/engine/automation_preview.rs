@@ -0,0 +1,214 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::engine::AutomationExecutor;
+use crate::models::automation::{AutomationAction, AutomationTrigger, ComparisonTrace, WorkflowAutomations};
+use crate::models::Case;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewWarning {
+    /// No sampled case took the `then` branch.
+    MatchesNone,
+    /// Every sampled case took the `then` branch.
+    MatchesAll,
+}
+
+/// How the sampled cases that reach one `conditional` action split between
+/// its branches. Cases whose condition fails to evaluate (a missing field, a
+/// non-numeric comparison) take neither branch and are counted as `errors`;
+/// like in a real run, they still reach the actions that follow.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConditionPreview {
+    /// Position in the automation, e.g. `actions[1].then[0]`.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub evaluated: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub errors: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<PreviewWarning>,
+    /// Why each evaluated case went the way it did.
+    pub cases: Vec<CaseExplanation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseExplanation {
+    pub case_id: Uuid,
+    /// Absent when the condition failed to evaluate.
+    pub result: Option<bool>,
+    pub comparisons: Vec<ComparisonTrace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutomationPreview {
+    pub trigger: AutomationTrigger,
+    pub phase: String,
+    pub conditions: Vec<ConditionPreview>,
+}
+
+/// Evaluates the conditions of `automations` against `cases` as they are
+/// now. Nothing is executed: webhooks are not sent and no case is changed.
+pub fn preview_automations(automations: &WorkflowAutomations, cases: &[Case]) -> Vec<AutomationPreview> {
+    let executor = AutomationExecutor::new();
+
+    automations
+        .automations
+        .iter()
+        .map(|automation| {
+            let mut conditions = Vec::new();
+            let cases = cases.iter().collect();
+            preview_actions(&executor, &automation.actions, "actions", cases, &mut conditions);
+
+            AutomationPreview {
+                trigger: automation.trigger.clone(),
+                phase: automation.phase.clone(),
+                conditions,
+            }
+        })
+        .collect()
+}
+
+/// Returns the cases that reach the end of `actions`.
+fn preview_actions<'a>(
+    executor: &AutomationExecutor,
+    actions: &[AutomationAction],
+    path: &str,
+    mut cases: Vec<&'a Case>,
+    out: &mut Vec<ConditionPreview>,
+) -> Vec<&'a Case> {
+    for (index, action) in actions.iter().enumerate() {
+        let AutomationAction::Conditional {
+            name,
+            condition,
+            then,
+            r#else,
+        } = action
+        else {
+            continue;
+        };
+
+        let path = format!("{}[{}]", path, index);
+        let evaluated = cases.len();
+        let mut matched = Vec::new();
+        let mut unmatched = Vec::new();
+        let mut errored = Vec::new();
+        let mut explanations = Vec::with_capacity(evaluated);
+
+        for case in cases {
+            let (outcome, comparisons) = executor.explain_condition(condition, case);
+            match outcome {
+                Ok(true) => matched.push(case),
+                Ok(false) => unmatched.push(case),
+                Err(_) => errored.push(case),
+            }
+            explanations.push(CaseExplanation {
+                case_id: case.id,
+                result: outcome.ok(),
+                comparisons,
+            });
+        }
+
+        let warning = match matched.len() {
+            _ if evaluated == 0 => None,
+            0 => Some(PreviewWarning::MatchesNone),
+            count if count == evaluated => Some(PreviewWarning::MatchesAll),
+            _ => None,
+        };
+
+        out.push(ConditionPreview {
+            path: path.clone(),
+            name: name.clone(),
+            evaluated,
+            matched: matched.len(),
+            unmatched: unmatched.len(),
+            errors: errored.len(),
+            warning,
+            cases: explanations,
+        });
+
+        cases = preview_actions(executor, then, &format!("{}.then", path), matched, out);
+        match r#else {
+            Some(otherwise) => cases.extend(preview_actions(executor, otherwise, &format!("{}.else", path), unmatched, out)),
+            None => cases.extend(unmatched),
+        }
+        cases.extend(errored);
+    }
+
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::automation::{Condition, PhaseAutomation};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn case(data: serde_json::Value) -> Case {
+        Case::new(Uuid::new_v4(), "New".to_string(), data, None)
+    }
+
+    fn conditional(field: &str, operator: &str, value: serde_json::Value, then: Vec<AutomationAction>) -> AutomationAction {
+        AutomationAction::Conditional {
+            name: None,
+            condition: Condition::Simple {
+                field: field.to_string(),
+                operator: operator.to_string(),
+                value,
+            },
+            then,
+            r#else: None,
+        }
+    }
+
+    #[test]
+    fn test_preview_counts_branches() {
+        let automations = WorkflowAutomations {
+            automations: vec![PhaseAutomation {
+                trigger: AutomationTrigger::OnEnter,
+                phase: "New".to_string(),
+                actions: vec![
+                    conditional(
+                        "data.amount",
+                        ">",
+                        json!(1000),
+                        vec![conditional("data.tier", "==", json!("gold"), vec![])],
+                    ),
+                    conditional("data.amount", ">", json!(0), vec![]),
+                ],
+            }],
+            run_as: None,
+        };
+        let cases = vec![
+            case(json!({"amount": 5000, "tier": "gold"})),
+            case(json!({"amount": 2000, "tier": "silver"})),
+            case(json!({"amount": 10})),
+            case(json!({})),
+        ];
+
+        let preview = preview_automations(&automations, &cases);
+        let conditions = &preview[0].conditions;
+
+        assert_eq!(conditions.len(), 3);
+        assert_eq!(conditions[0].path, "actions[0]");
+        assert_eq!((conditions[0].matched, conditions[0].unmatched, conditions[0].errors), (2, 1, 1));
+        assert_eq!(conditions[0].warning, None);
+        assert_eq!(conditions[0].cases[0].comparisons[0].actual, Some(json!(5000)));
+        assert_eq!(conditions[0].cases[3].result, None);
+        assert!(conditions[0].cases[3].comparisons[0].error.is_some());
+
+        assert_eq!(conditions[1].path, "actions[0].then[0]");
+        assert_eq!((conditions[1].evaluated, conditions[1].matched), (2, 1));
+
+        assert_eq!(conditions[2].path, "actions[1]");
+        assert_eq!(conditions[2].evaluated, 4);
+        assert_eq!((conditions[2].matched, conditions[2].errors), (3, 1));
+        assert_eq!(conditions[2].warning, None);
+
+        let preview = preview_automations(&automations, &cases[..3]);
+        assert_eq!(preview[0].conditions[2].warning, Some(PreviewWarning::MatchesAll));
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::models::Flow;
+
+type FlowSlots = HashMap<Uuid, (u32, Arc<Semaphore>)>;
+
+/// Caps how many executions of the same flow run at once. Work over the limit
+/// waits for a permit instead of hitting the downstream in parallel.
+#[derive(Clone, Default)]
+pub struct FlowConcurrencyLimiter {
+    slots: Arc<Mutex<FlowSlots>>,
+}
+
+impl FlowConcurrencyLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `None` when the flow has no limit configured.
+    pub async fn acquire(&self, flow: &Flow) -> Option<OwnedSemaphorePermit> {
+        let limit = flow.max_concurrent_executions.filter(|l| *l > 0)? as u32;
+        let semaphore = self.semaphore_for(flow.id, limit);
+
+        if semaphore.available_permits() == 0 {
+            debug!(
+                "Flow '{}' is at its limit of {} concurrent execution(s), queueing",
+                flow.name, limit
+            );
+        }
+
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// Like `acquire`, but fails instead of waiting when the flow is at its
+    /// limit.
+    pub fn try_acquire(&self, flow: &Flow) -> Result<Option<OwnedSemaphorePermit>, TryAcquireError> {
+        let Some(limit) = flow.max_concurrent_executions.filter(|l| *l > 0) else {
+            return Ok(None);
+        };
+
+        self.semaphore_for(flow.id, limit as u32).try_acquire_owned().map(Some)
+    }
+
+    fn semaphore_for(&self, flow_id: Uuid, limit: u32) -> Arc<Semaphore> {
+        let mut slots = self.slots.lock().expect("flow limiter lock poisoned");
+
+        match slots.get(&flow_id) {
+            Some((current_limit, semaphore)) if *current_limit == limit => semaphore.clone(),
+            _ => {
+                // Limit was added or changed: executions already holding permits
+                // on the old semaphore finish normally.
+                let semaphore = Arc::new(Semaphore::new(limit as usize));
+                slots.insert(flow_id, (limit, semaphore.clone()));
+                semaphore
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::flow::{CreateFlow, FlowTrigger};
+    use std::time::Duration;
+
+    fn flow_with_limit(limit: Option<i32>) -> Flow {
+        Flow::new(CreateFlow {
+            name: "Limited".to_string(),
+            trigger: FlowTrigger {
+                event_type: "test".to_string(),
+                filters: serde_json::Value::Null,
+                case: None,
+            },
+            steps: vec![],
+            max_concurrent_executions: limit,
+            active: true,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_flow_has_no_permit() {
+        let limiter = FlowConcurrencyLimiter::new();
+        assert!(limiter.acquire(&flow_with_limit(None)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_excess_work_waits_for_permit() {
+        let limiter = FlowConcurrencyLimiter::new();
+        let flow = flow_with_limit(Some(1));
+
+        let first = limiter.acquire(&flow).await;
+        assert!(first.is_some());
+
+        let second = tokio::time::timeout(Duration::from_millis(50), limiter.acquire(&flow)).await;
+        assert!(second.is_err(), "second execution should be queued");
+
+        drop(first);
+        let third = tokio::time::timeout(Duration::from_millis(50), limiter.acquire(&flow)).await;
+        assert!(third.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_try_acquire_does_not_wait() {
+        let limiter = FlowConcurrencyLimiter::new();
+        let flow = flow_with_limit(Some(1));
+
+        assert!(matches!(limiter.try_acquire(&flow_with_limit(None)), Ok(None)));
+        let first = limiter.try_acquire(&flow).unwrap();
+        assert!(first.is_some());
+        assert!(limiter.try_acquire(&flow).is_err());
+        drop(first);
+        assert!(limiter.try_acquire(&flow).unwrap().is_some());
+    }
+}
@@ -1,69 +1,193 @@
 use crate::engine::retry::RetryExecutor;
+use crate::engine::simulation::{SimulatedRequest, Simulation};
 use crate::models::{
     execution::{Execution, ExecutionStatus, StepExecutionStatus, StepStatus},
     step::{FailureAction, Step, StepType},
     Event, Flow,
 };
+use crate::services::clock::{system_clock, SharedClock, VirtualClock};
+use crate::services::outbound_http::http_client;
+use crate::services::secrets::{redact_secrets, referenced_secrets, Secrets};
 use anyhow::{anyhow, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
 use reqwest::Client;
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
+#[derive(Clone)]
 pub struct Executor {
     http_client: Client,
+    simulation: Option<Arc<Simulation>>,
+    clock: SharedClock,
+    secrets: Secrets,
 }
 
 impl Executor {
     pub fn new() -> Self {
         Self {
-            http_client: Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .expect("Failed to create HTTP client"),
+            http_client: http_client(Duration::from_secs(30)),
+            simulation: None,
+            clock: system_clock(),
+            secrets: Secrets::default(),
         }
     }
 
+    /// An executor that never leaves the process: webhook steps are answered
+    /// by `simulation` and recorded there instead of being sent. It runs on a
+    /// virtual clock starting now, so delays take no time.
+    pub fn simulated(simulation: Arc<Simulation>) -> Self {
+        Self {
+            simulation: Some(simulation),
+            clock: Arc::new(VirtualClock::new(Utc::now())),
+            ..Self::new()
+        }
+    }
+
+    /// Reads the time from `clock` for step timings and `delay_until`
+    /// deadlines, and waits on it for `delay` steps.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Substitutes `secrets` for the `${secrets.NAME}` placeholders in
+    /// webhook URLs and headers. Simulations only ever show them redacted.
+    pub fn with_secrets(mut self, secrets: Secrets) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
     pub async fn execute(&self, flow: &Flow, event: &Event) -> Result<Execution> {
         let mut execution = Execution::new(flow.id, event.id);
-        execution.status = ExecutionStatus::Running;
+        execution.flow_version = Some(flow.version);
+        execution.started_at = self.clock.now();
 
         info!(
             "Starting execution {} for flow '{}' (event: {})",
             execution.id, flow.name, event.event_type
         );
 
-        let mut steps_status: HashMap<String, StepStatus> = HashMap::new();
+        self.run_steps(flow, event, execution, 0).await
+    }
+
+    /// Continues a waiting execution from the step after the `delay_until`
+    /// that suspended it. `flow` must be the version the execution is pinned to.
+    pub async fn resume(&self, flow: &Flow, event: &Event, mut execution: Execution) -> Result<Execution> {
+        let start = execution.resume_step.unwrap_or(0).max(0) as usize;
+        execution.resume_at = None;
+        execution.resume_step = None;
+
+        // The `delay_until` step the execution waited at is done.
+        if let Some(waited) = start.checked_sub(1).and_then(|idx| flow.steps.get(idx)) {
+            let mut steps_status: HashMap<String, StepStatus> =
+                serde_json::from_value(execution.steps_status.clone()).unwrap_or_default();
+            if let Some(status) = steps_status.get_mut(&waited.name) {
+                if matches!(status.status, StepExecutionStatus::Waiting) {
+                    let completed_at = self.clock.now();
+                    status.status = StepExecutionStatus::Completed;
+                    status.completed_at = Some(completed_at);
+                    status.duration_ms = Some((completed_at - status.started_at).num_milliseconds().max(0) as u64);
+                    execution.steps_status = serde_json::to_value(&steps_status)?;
+                }
+            }
+        }
+
+        info!(
+            "Resuming execution {} for flow '{}' at step {}",
+            execution.id, flow.name, start
+        );
+
+        self.run_steps(flow, event, execution, start).await
+    }
+
+    async fn run_steps(
+        &self,
+        flow: &Flow,
+        event: &Event,
+        mut execution: Execution,
+        start: usize,
+    ) -> Result<Execution> {
+        execution.status = ExecutionStatus::Running;
+
+        let mut steps_status: HashMap<String, StepStatus> =
+            serde_json::from_value(execution.steps_status.clone()).unwrap_or_default();
         let mut flow_failed = false;
 
-        for step in &flow.steps {
+        for (idx, step) in flow.steps.iter().enumerate().skip(start) {
+            execution.current_step = Some(step.name.clone());
+
+            if let StepType::DelayUntil { until } = &step.step_type {
+                if let Ok(deadline) = self.resolve_deadline(until, event, &steps_status) {
+                    if deadline > self.clock.now() && self.simulation.is_none() {
+                        info!(
+                            "Execution {} waiting at step '{}' until {}",
+                            execution.id, step.name, deadline
+                        );
+
+                        steps_status.insert(
+                            step.name.clone(),
+                            StepStatus {
+                                status: StepExecutionStatus::Waiting,
+                                started_at: self.clock.now(),
+                                completed_at: None,
+                                attempts: 1,
+                                duration_ms: None,
+                                request: None,
+                                response: Some(json!({"wait_until": deadline})),
+                                error: None,
+                            },
+                        );
+
+                        execution.steps_status = serde_json::to_value(&steps_status)?;
+                        execution.status = ExecutionStatus::Waiting;
+                        execution.resume_at = Some(deadline);
+                        execution.resume_step = Some((idx + 1) as i32);
+                        return Ok(execution);
+                    }
+                }
+            }
+
             execution.current_step = Some(step.name.clone());
 
             info!("Executing step: {}", step.name);
 
-            let step_result = self.execute_step(step, event, &steps_status).await;
+            let trace = StepTrace::default();
+            let started_at = self.clock.now();
+            let step_result = self
+                .execute_step(step, event, &steps_status, None, &trace)
+                .await;
+            let completed_at = self.clock.now();
+            let attempts = trace.attempts.into_inner().max(1);
+            let request = trace.request.into_inner().expect("step request lock poisoned");
+            let duration_ms = Some((completed_at - started_at).num_milliseconds().max(0) as u64);
 
             let status = match &step_result {
                 Ok(response) => StepStatus {
                     status: StepExecutionStatus::Completed,
-                    started_at: Utc::now(),
-                    completed_at: Some(Utc::now()),
-                    attempts: 1,
+                    started_at,
+                    completed_at: Some(completed_at),
+                    attempts,
+                    duration_ms,
+                    request,
                     response: Some(response.clone()),
                     error: None,
                 },
                 Err(err) => {
                     let error_msg = err.to_string();
-                    warn!("Step '{}' failed: {}", step.name, error_msg);
+                    warn!("Step '{}' failed after {} attempt(s): {}", step.name, attempts, error_msg);
 
                     StepStatus {
                         status: StepExecutionStatus::Failed,
-                        started_at: Utc::now(),
-                        completed_at: Some(Utc::now()),
-                        attempts: 1,
+                        started_at,
+                        completed_at: Some(completed_at),
+                        attempts,
+                        duration_ms,
+                        request,
                         response: None,
                         error: Some(error_msg.clone()),
                     }
@@ -93,7 +217,7 @@ impl Executor {
         } else {
             ExecutionStatus::Completed
         };
-        execution.completed_at = Some(Utc::now());
+        execution.completed_at = Some(self.clock.now());
 
         info!(
             "Execution {} finished with status: {:?}",
@@ -108,8 +232,13 @@ impl Executor {
         step: &'a Step,
         event: &'a Event,
         previous_steps: &'a HashMap<String, StepStatus>,
+        item: Option<&'a ItemContext<'a>>,
+        trace: &'a StepTrace,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Value>> + Send + 'a>> {
-        Box::pin(async move { self.execute_step_inner(step, event, previous_steps).await })
+        Box::pin(async move {
+            self.execute_step_inner(step, event, previous_steps, item, trace)
+                .await
+        })
     }
 
     async fn execute_step_inner(
@@ -117,6 +246,8 @@ impl Executor {
         step: &Step,
         event: &Event,
         previous_steps: &HashMap<String, StepStatus>,
+        item: Option<&ItemContext<'_>>,
+        trace: &StepTrace,
     ) -> Result<Value> {
         match &step.step_type {
             StepType::Webhook {
@@ -127,6 +258,21 @@ impl Executor {
                 timeout_ms,
                 retry,
             } => {
+                if let Some(simulation) = &self.simulation {
+                    let body = self.interpolate_template(body_template, event, previous_steps, item)?;
+                    let url = self.interpolate_string(&redact_secrets(url), event, previous_steps, item)?;
+                    let headers = headers
+                        .iter()
+                        .map(|(key, value)| {
+                            Ok((key.clone(), self.interpolate_string(&redact_secrets(value), event, previous_steps, item)?))
+                        })
+                        .collect::<Result<HashMap<_, _>>>()?;
+                    if item.is_none() {
+                        trace.record_request(method, &url, &body);
+                    }
+                    return simulate_webhook(simulation, &step.name, method, url, headers, body, trace);
+                }
+
                 self.execute_webhook(
                     url,
                     method,
@@ -134,8 +280,10 @@ impl Executor {
                     body_template,
                     event,
                     previous_steps,
+                    item,
                     *timeout_ms,
                     retry.as_ref(),
+                    trace,
                 )
                 .await
             }
@@ -146,18 +294,181 @@ impl Executor {
                 if_false,
             } => {
                 let result = self.evaluate_condition(condition, event)?;
+                if let Some(simulation) = &self.simulation {
+                    simulation.record_branch(&step.name, result);
+                }
                 let branch = if result { if_true } else { if_false };
-                Box::pin(self.execute_step_inner(branch, event, previous_steps)).await
+                Box::pin(self.execute_step_inner(branch, event, previous_steps, item, trace)).await
             }
 
             StepType::Delay { duration_ms } => {
                 debug!("Delaying for {}ms", duration_ms);
-                sleep(Duration::from_millis(*duration_ms)).await;
+                self.clock.sleep(Duration::from_millis(*duration_ms)).await;
                 Ok(json!({"delayed_ms": duration_ms}))
             }
+
+            StepType::FanOut {
+                items,
+                step: child,
+                max_concurrency,
+            } => {
+                self.execute_fan_out(
+                    items,
+                    child,
+                    *max_concurrency,
+                    event,
+                    previous_steps,
+                    item,
+                    trace,
+                )
+                .await
+            }
+
+            StepType::DelayUntil { until } => {
+                let deadline = self.resolve_deadline(until, event, previous_steps)?;
+                if deadline > self.clock.now() && self.simulation.is_some() {
+                    return Ok(json!({"would_wait_until": deadline}));
+                }
+                if deadline > self.clock.now() {
+                    return Err(anyhow!(
+                        "delay_until can only wait as a top-level step (target {})",
+                        deadline
+                    ));
+                }
+                Ok(json!({"waited_until": deadline}))
+            }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_fan_out(
+        &self,
+        items_expr: &str,
+        child: &Step,
+        max_concurrency: Option<usize>,
+        event: &Event,
+        previous_steps: &HashMap<String, StepStatus>,
+        parent_item: Option<&ItemContext<'_>>,
+        trace: &StepTrace,
+    ) -> Result<Value> {
+        let items = match self.resolve_value(items_expr, event, parent_item) {
+            Value::Array(items) => items,
+            Value::Null => return Err(anyhow!("fan_out items '{}' not found", items_expr)),
+            other => {
+                return Err(anyhow!(
+                    "fan_out items '{}' must be an array, got {}",
+                    items_expr,
+                    other
+                ))
+            }
+        };
+
+        let concurrency = max_concurrency.unwrap_or(items.len()).max(1);
+        debug!(
+            "Fanning out step '{}' over {} item(s) (concurrency {})",
+            child.name,
+            items.len(),
+            concurrency
+        );
+
+        let total = items.len();
+        let results: Vec<Result<Value>> = stream::iter(items.into_iter().enumerate())
+            .map(|(index, value)| async move {
+                let context = ItemContext { index, value: &value };
+                self.execute_step(child, event, previous_steps, Some(&context), trace)
+                    .await
+            })
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        let mut failed = 0;
+        let item_results: Vec<Value> = results
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| match result {
+                Ok(response) => json!({"index": index, "status": "completed", "response": response}),
+                Err(err) => {
+                    failed += 1;
+                    json!({"index": index, "status": "failed", "error": err.to_string()})
+                }
+            })
+            .collect();
+
+        let summary = json!({
+            "total": item_results.len(),
+            "completed": item_results.len() - failed,
+            "failed": failed,
+            "items": item_results,
+        });
+
+        if failed > 0 && matches!(child.on_failure, FailureAction::Stop) {
+            return Err(anyhow!("{} of {} fan_out item(s) failed: {}", failed, total, summary));
+        }
+
+        Ok(summary)
+    }
+
+    /// Resolves `${event.data.a.b}`, `${item}`, `${item.a.b}` or `${item_index}`
+    /// to the raw JSON value, `Null` when missing.
+    fn resolve_value(&self, expr: &str, event: &Event, item: Option<&ItemContext<'_>>) -> Value {
+        let expr = expr.trim();
+        let path = expr
+            .strip_prefix("${")
+            .and_then(|s| s.strip_suffix('}'))
+            .unwrap_or(expr);
+
+        let (root, rest) = if let Some(rest) = path.strip_prefix("event.data") {
+            (&event.data, rest)
+        } else if path == "item_index" {
+            return item.map(|i| json!(i.index)).unwrap_or(Value::Null);
+        } else if let (Some(rest), Some(item)) = (path.strip_prefix("item"), item) {
+            (item.value, rest)
+        } else {
+            return Value::Null;
+        };
+
+        let mut current = root;
+        for part in rest.split('.').filter(|p| !p.is_empty()) {
+            current = match current {
+                Value::Object(map) => match map.get(part) {
+                    Some(v) => v,
+                    None => return Value::Null,
+                },
+                Value::Array(arr) => match part.parse::<usize>().ok().and_then(|i| arr.get(i)) {
+                    Some(v) => v,
+                    None => return Value::Null,
+                },
+                _ => return Value::Null,
+            };
+        }
+
+        current.clone()
+    }
+
+    fn resolve_deadline(
+        &self,
+        until: &str,
+        event: &Event,
+        previous_steps: &HashMap<String, StepStatus>,
+    ) -> Result<DateTime<Utc>> {
+        let value = self.interpolate_string(until, event, previous_steps, None)?;
+        let value = value.trim();
+
+        if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+            return Ok(timestamp.with_timezone(&Utc));
+        }
+
+        if let Ok(seconds) = value.parse::<i64>() {
+            if let Some(timestamp) = DateTime::from_timestamp(seconds, 0) {
+                return Ok(timestamp);
+            }
+        }
+
+        Err(anyhow!("Invalid delay_until timestamp: '{}'", value))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn execute_webhook(
         &self,
         url: &str,
@@ -166,14 +477,26 @@ impl Executor {
         body_template: &Value,
         event: &Event,
         previous_steps: &HashMap<String, StepStatus>,
+        item: Option<&ItemContext<'_>>,
         timeout_ms: Option<u64>,
         retry_config: Option<&crate::models::step::RetryConfig>,
+        trace: &StepTrace,
     ) -> Result<Value> {
-        let body = self.interpolate_template(body_template, event, previous_steps)?;
-
-        let interpolated_url = self.interpolate_string(url, event, previous_steps)?;
+        let body = self.interpolate_template(body_template, event, previous_steps, item)?;
+
+        // Secrets go in before the event's data, so data can't pull them in,
+        // and only ever in what is sent: the trace and errors show the URL
+        // with them redacted.
+        let interpolated_url = self.interpolate_string(&self.secrets.resolve(url)?, event, previous_steps, item)?;
+        let shown_url = self.interpolate_string(&redact_secrets(url), event, previous_steps, item)?;
+        let url_has_secrets = !referenced_secrets(url).is_empty();
+        if item.is_none() {
+            trace.record_request(method, &shown_url, &body);
+        }
 
         let operation = || async {
+            trace.attempts.fetch_add(1, Ordering::Relaxed);
+
             let mut request = match method.to_uppercase().as_str() {
                 "GET" => self.http_client.get(&interpolated_url),
                 "POST" => self.http_client.post(&interpolated_url).json(&body),
@@ -184,7 +507,7 @@ impl Executor {
             };
 
             for (key, value) in headers {
-                let interpolated_value = self.interpolate_string(value, event, previous_steps)?;
+                let interpolated_value = self.interpolate_string(&self.secrets.resolve(value)?, event, previous_steps, item)?;
                 request = request.header(key, interpolated_value);
             }
 
@@ -192,10 +515,11 @@ impl Executor {
                 request = request.timeout(Duration::from_millis(timeout));
             }
 
-            let response = request.send().await.map_err(|e| anyhow!(e))?;
+            let failure = |e: reqwest::Error| if url_has_secrets { anyhow!(e.without_url()) } else { anyhow!(e) };
+            let response = request.send().await.map_err(failure)?;
 
             let status = response.status();
-            let body = response.text().await.map_err(|e| anyhow!(e))?;
+            let body = response.text().await.map_err(failure)?;
 
             if !status.is_success() {
                 return Err(anyhow!("HTTP {} - {}", status, body));
@@ -250,23 +574,31 @@ impl Executor {
         template: &Value,
         event: &Event,
         _previous_steps: &HashMap<String, StepStatus>,
+        item: Option<&ItemContext<'_>>,
     ) -> Result<Value> {
         match template {
-            Value::String(s) => Ok(json!(self.interpolate_string(s, event, _previous_steps)?)),
+            Value::String(s) => {
+                // A template that is exactly one item placeholder keeps the
+                // item's JSON type so whole objects can be forwarded as-is.
+                if item.is_some() && is_single_item_placeholder(s) {
+                    return Ok(self.resolve_value(s, event, item));
+                }
+                Ok(json!(self.interpolate_string(s, event, _previous_steps, item)?))
+            }
             Value::Object(map) => {
                 let mut result = serde_json::Map::new();
                 for (key, value) in map {
                     result.insert(
                         key.clone(),
-                        self.interpolate_template(value, event, _previous_steps)?,
+                        self.interpolate_template(value, event, _previous_steps, item)?,
                     );
                 }
                 Ok(Value::Object(result))
             }
             Value::Array(arr) => {
                 let mut result = Vec::new();
-                for item in arr {
-                    result.push(self.interpolate_template(item, event, _previous_steps)?);
+                for entry in arr {
+                    result.push(self.interpolate_template(entry, event, _previous_steps, item)?);
                 }
                 Ok(Value::Array(result))
             }
@@ -279,6 +611,7 @@ impl Executor {
         template: &str,
         event: &Event,
         _previous_steps: &HashMap<String, StepStatus>,
+        item: Option<&ItemContext<'_>>,
     ) -> Result<String> {
         let mut result = template.to_string();
 
@@ -289,13 +622,10 @@ impl Executor {
                     event
                         .data
                         .get(field)
-                        .and_then(|v| match v {
-                            Value::String(s) => Some(s.clone()),
-                            Value::Number(n) => Some(n.to_string()),
-                            Value::Bool(b) => Some(b.to_string()),
-                            _ => None,
-                        })
+                        .and_then(scalar_to_string)
                         .unwrap_or_default()
+                } else if var == "item_index" || var == "item" || var.starts_with("item.") {
+                    scalar_to_string(&self.resolve_value(var, event, item)).unwrap_or_default()
                 } else {
                     String::new()
                 };
@@ -310,6 +640,79 @@ impl Executor {
     }
 }
 
+fn simulate_webhook(
+    simulation: &Simulation,
+    step: &str,
+    method: &str,
+    url: String,
+    headers: HashMap<String, String>,
+    body: Value,
+    trace: &StepTrace,
+) -> Result<Value> {
+    trace.attempts.fetch_add(1, Ordering::Relaxed);
+
+    let (source, response) = simulation.respond(step);
+    simulation.record(SimulatedRequest {
+        step: step.to_string(),
+        method: method.to_uppercase(),
+        url,
+        headers,
+        body,
+        source,
+        response: response.clone(),
+    });
+
+    if response.status >= 400 {
+        return Err(anyhow!("HTTP {} - {}", response.status, response.body));
+    }
+
+    Ok(response.body)
+}
+
+/// What a step did, filled in while it runs.
+#[derive(Default)]
+struct StepTrace {
+    /// Every HTTP request made on behalf of the step, including retries and
+    /// fan-out items.
+    attempts: AtomicU32,
+    /// The request a top-level webhook step sent. Headers are left out, as
+    /// they often carry credentials.
+    request: Mutex<Option<Value>>,
+}
+
+impl StepTrace {
+    fn record_request(&self, method: &str, url: &str, body: &Value) {
+        *self.request.lock().expect("step request lock poisoned") = Some(json!({
+            "method": method.to_uppercase(),
+            "url": url,
+            "body": body,
+        }));
+    }
+}
+
+/// The array element a `fan_out` child step is currently running for.
+struct ItemContext<'a> {
+    index: usize,
+    value: &'a Value,
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn is_single_item_placeholder(template: &str) -> bool {
+    template
+        .strip_prefix("${")
+        .and_then(|s| s.strip_suffix('}'))
+        .map(|var| (var == "item" || var.starts_with("item.")) && !var.contains("${"))
+        .unwrap_or(false)
+}
+
 impl Default for Executor {
     fn default() -> Self {
         Self::new()
@@ -1,5 +1,11 @@
-use crate::models::{Event, Flow};
+use crate::models::{
+    flow::{CaseTrigger, FlowTrigger},
+    Event, Flow,
+};
+use regex::Regex;
 use serde_json::Value;
+use std::cmp::Ordering;
+use uuid::Uuid;
 
 pub struct Matcher;
 
@@ -12,101 +18,143 @@ impl Matcher {
     }
 
     fn matches(event: &Event, flow: &Flow) -> bool {
-        if !flow.active {
+        flow.active && Self::matches_trigger(event, &flow.trigger)
+    }
+
+    /// Whether `event` satisfies the trigger, regardless of the flow being active.
+    pub fn matches_trigger(event: &Event, trigger: &FlowTrigger) -> bool {
+        if event.event_type != trigger.event_type {
             return false;
         }
 
-        if event.event_type != flow.trigger.event_type {
-            return false;
+        if let Some(scope) = &trigger.case {
+            if !Self::check_case_scope(&event.data, scope) {
+                return false;
+            }
         }
 
-        if !flow.trigger.filters.is_null() {
-            return Self::check_filters(&event.data, &flow.trigger.filters);
+        if !trigger.filters.is_null() {
+            return Self::check_filters(&event.data, &trigger.filters);
         }
 
         true
     }
 
+    /// Case lifecycle events carry `workflow_id`, `to_phase` and `from_phase`
+    /// in their data; `phase` is matched against the phase being entered.
+    fn check_case_scope(event_data: &Value, scope: &CaseTrigger) -> bool {
+        let workflow_id = event_data
+            .get("workflow_id")
+            .and_then(Value::as_str)
+            .and_then(|id| id.parse::<Uuid>().ok());
+        if workflow_id != Some(scope.workflow_id) {
+            return false;
+        }
+
+        let phase_matches = |field: &str, expected: &Option<String>| match expected {
+            Some(expected) => event_data.get(field).and_then(Value::as_str) == Some(expected.as_str()),
+            None => true,
+        };
+
+        phase_matches("to_phase", &scope.phase) && phase_matches("from_phase", &scope.from_phase)
+    }
+
     fn check_filters(event_data: &Value, filters: &Value) -> bool {
         let Some(filter_obj) = filters.as_object() else {
             return true;
         };
 
-        for (key, filter_value) in filter_obj {
-            if let Some(field_name) = key.strip_suffix("_gt") {
-                if !Self::check_gt(event_data, field_name, filter_value) {
-                    return false;
-                }
-            } else if let Some(field_name) = key.strip_suffix("_lt") {
-                if !Self::check_lt(event_data, field_name, filter_value) {
-                    return false;
-                }
-            } else if let Some(field_name) = key.strip_suffix("_gte") {
-                if !Self::check_gte(event_data, field_name, filter_value) {
-                    return false;
-                }
-            } else if let Some(field_name) = key.strip_suffix("_lte") {
-                if !Self::check_lte(event_data, field_name, filter_value) {
-                    return false;
-                }
-            } else if let Some(field_name) = key.strip_suffix("_ne") {
-                if !Self::check_ne(event_data, field_name, filter_value) {
-                    return false;
-                }
-            } else {
-                let event_value = event_data.get(key);
-                if event_value != Some(filter_value) {
-                    return false;
-                }
-            }
+        filter_obj
+            .iter()
+            .all(|(key, filter_value)| Self::check_filter(event_data, key, filter_value))
+    }
+
+    fn check_filter(data: &Value, key: &str, filter: &Value) -> bool {
+        if let Some(field) = key.strip_suffix("_gte") {
+            let value = lookup(data, field);
+            return matches!(
+                value.and_then(|v| compare_values(v, filter)),
+                Some(Ordering::Greater | Ordering::Equal)
+            );
         }
 
-        true
-    }
+        if let Some(field) = key.strip_suffix("_lte") {
+            let value = lookup(data, field);
+            return matches!(
+                value.and_then(|v| compare_values(v, filter)),
+                Some(Ordering::Less | Ordering::Equal)
+            );
+        }
 
-    fn check_gt(data: &Value, field: &str, filter: &Value) -> bool {
-        let Some(value) = data.get(field) else {
-            return false;
-        };
-        compare_values(value, filter) == Some(std::cmp::Ordering::Greater)
-    }
+        if let Some(field) = key.strip_suffix("_gt") {
+            let value = lookup(data, field);
+            return value.and_then(|v| compare_values(v, filter)) == Some(Ordering::Greater);
+        }
 
-    fn check_lt(data: &Value, field: &str, filter: &Value) -> bool {
-        let Some(value) = data.get(field) else {
-            return false;
-        };
-        compare_values(value, filter) == Some(std::cmp::Ordering::Less)
-    }
+        if let Some(field) = key.strip_suffix("_lt") {
+            let value = lookup(data, field);
+            return value.and_then(|v| compare_values(v, filter)) == Some(Ordering::Less);
+        }
 
-    fn check_gte(data: &Value, field: &str, filter: &Value) -> bool {
-        let Some(value) = data.get(field) else {
-            return false;
-        };
-        matches!(
-            compare_values(value, filter),
-            Some(std::cmp::Ordering::Greater | std::cmp::Ordering::Equal)
-        )
-    }
+        if let Some(field) = key.strip_suffix("_ne") {
+            return lookup(data, field).is_some_and(|value| value != filter);
+        }
 
-    fn check_lte(data: &Value, field: &str, filter: &Value) -> bool {
-        let Some(value) = data.get(field) else {
-            return false;
-        };
-        matches!(
-            compare_values(value, filter),
-            Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)
-        )
+        if let Some(field) = key.strip_suffix("_in") {
+            let Some(options) = filter.as_array() else {
+                return false;
+            };
+            return lookup(data, field).is_some_and(|value| options.contains(value));
+        }
+
+        if let Some(field) = key.strip_suffix("_contains") {
+            return match lookup(data, field) {
+                Some(Value::String(text)) => filter.as_str().is_some_and(|needle| text.contains(needle)),
+                Some(Value::Array(items)) => items.contains(filter),
+                _ => false,
+            };
+        }
+
+        if let Some(field) = key.strip_suffix("_exists") {
+            let exists = lookup(data, field).is_some_and(|value| !value.is_null());
+            return Some(exists) == filter.as_bool();
+        }
+
+        if let Some(field) = key.strip_suffix("_regex") {
+            let Some(Value::String(text)) = lookup(data, field) else {
+                return false;
+            };
+            let Some(pattern) = filter.as_str() else {
+                return false;
+            };
+            return match Regex::new(pattern) {
+                Ok(regex) => regex.is_match(text),
+                Err(e) => {
+                    tracing::warn!("Invalid regex filter '{}': {}", key, e);
+                    false
+                }
+            };
+        }
+
+        lookup(data, key) == Some(filter)
     }
+}
 
-    fn check_ne(data: &Value, field: &str, filter: &Value) -> bool {
-        let Some(value) = data.get(field) else {
-            return false;
-        };
-        value != filter
+/// Resolves `customer.tier` or `items.0.sku` against the event data. A key
+/// that literally contains dots takes precedence over the nested path.
+pub(crate) fn lookup<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+    if let Some(value) = data.get(path) {
+        return Some(value);
     }
+
+    path.split('.').try_fold(data, |current, segment| match current {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
 }
 
-fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
     match (a, b) {
         (Value::Number(a), Value::Number(b)) => {
             let a_f64 = a.as_f64()?;
@@ -117,3 +165,70 @@ fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data() -> Value {
+        json!({
+            "amount": 1500,
+            "status": "approved",
+            "customer": {"tier": "gold", "email": "ana@example.com", "phone": null},
+            "tags": ["vip", "eu"],
+            "items": [{"sku": "A-1"}]
+        })
+    }
+
+    #[test]
+    fn test_nested_paths() {
+        assert!(Matcher::check_filters(&data(), &json!({"customer.tier": "gold"})));
+        assert!(Matcher::check_filters(&data(), &json!({"items.0.sku": "A-1", "amount_gte": 1500})));
+        assert!(!Matcher::check_filters(&data(), &json!({"customer.tier_ne": "gold"})));
+        assert!(!Matcher::check_filters(&data(), &json!({"customer.region": "eu"})));
+    }
+
+    #[test]
+    fn test_membership_and_presence() {
+        assert!(Matcher::check_filters(&data(), &json!({"status_in": ["approved", "paid"]})));
+        assert!(!Matcher::check_filters(&data(), &json!({"status_in": ["rejected"]})));
+        assert!(Matcher::check_filters(&data(), &json!({"tags_contains": "vip"})));
+        assert!(Matcher::check_filters(&data(), &json!({"customer.email_contains": "@example.com"})));
+        assert!(!Matcher::check_filters(&data(), &json!({"tags_contains": "us"})));
+        assert!(Matcher::check_filters(&data(), &json!({"customer.email_exists": true})));
+        assert!(Matcher::check_filters(&data(), &json!({"customer.phone_exists": false})));
+        assert!(Matcher::check_filters(&data(), &json!({"coupon_exists": false})));
+    }
+
+    #[test]
+    fn test_case_scope() {
+        let workflow_id = Uuid::new_v4();
+        let moved = json!({
+            "case_id": Uuid::new_v4(),
+            "workflow_id": workflow_id,
+            "from_phase": "Review",
+            "to_phase": "Approved",
+            "case_data": {}
+        });
+        let scope = |workflow_id, phase: Option<&str>, from_phase: Option<&str>| CaseTrigger {
+            workflow_id,
+            phase: phase.map(str::to_string),
+            from_phase: from_phase.map(str::to_string),
+        };
+
+        assert!(Matcher::check_case_scope(&moved, &scope(workflow_id, None, None)));
+        assert!(Matcher::check_case_scope(&moved, &scope(workflow_id, Some("Approved"), Some("Review"))));
+        assert!(!Matcher::check_case_scope(&moved, &scope(workflow_id, Some("Review"), None)));
+        assert!(!Matcher::check_case_scope(&moved, &scope(workflow_id, None, Some("Draft"))));
+        assert!(!Matcher::check_case_scope(&moved, &scope(Uuid::new_v4(), Some("Approved"), None)));
+    }
+
+    #[test]
+    fn test_regex() {
+        assert!(Matcher::check_filters(&data(), &json!({"customer.email_regex": "@example\\.com$"})));
+        assert!(!Matcher::check_filters(&data(), &json!({"status_regex": "^rej"})));
+        assert!(!Matcher::check_filters(&data(), &json!({"amount_regex": "1500"})));
+        assert!(!Matcher::check_filters(&data(), &json!({"status_regex": "("})));
+    }
+}
@@ -1,8 +1,18 @@
 pub mod automation_executor;
+pub mod automation_preview;
+pub mod concurrency;
 pub mod executor;
 pub mod matcher;
+pub mod modifications;
+pub mod replay;
 pub mod retry;
+pub mod simulation;
 
-pub use automation_executor::AutomationExecutor;
+pub use automation_executor::{AutomationExecutor, LimitExceeded, TransientFailure};
+pub use automation_preview::{preview_automations, AutomationPreview};
+pub use concurrency::FlowConcurrencyLimiter;
 pub use executor::Executor;
 pub use matcher::Matcher;
+pub use modifications::apply_modifications;
+pub use replay::ReplayReport;
+pub use simulation::{MockResponse, Simulation};
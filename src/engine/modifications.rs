@@ -0,0 +1,244 @@
+use anyhow::Result;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::models::automation::AutomationResult;
+use crate::models::case::{CaseHistory, CaseLifecycleAction, FieldProvenance};
+use crate::models::conflict::FieldWrite;
+use crate::models::validation::MAX_CASE_TAGS;
+use crate::models::{CaseModification, Workflow};
+use crate::storage::CaseStore;
+
+/// Applies the modifications of an automation run to the case. A
+/// modification that is not allowed or fails is logged and skipped; only
+/// failing to read the case is an error.
+pub async fn apply_modifications<S: CaseStore + ?Sized>(
+    store: &mut S,
+    case_id: Uuid,
+    workflow: &Workflow,
+    automation_result: AutomationResult,
+    automation_type: &str,
+    triggered_by: &str,
+) -> Result<()> {
+    let mut current_phase = store
+        .find_case(case_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Case {} not found", case_id))?
+        .current_phase;
+
+    for modification in automation_result.modifications {
+        match modification {
+            CaseModification::MoveToPhase { phase } => {
+                if !workflow.has_phase(&phase) {
+                    error!("{} automation tried to move case {} to non-existent phase: {}", automation_type, case_id, phase);
+                    continue;
+                }
+
+                if !workflow.allows_transition(&current_phase, &phase) {
+                    warn!(
+                        "{} automation did not move case {} from '{}' to '{}': the workflow does not allow that transition",
+                        automation_type, case_id, current_phase, phase
+                    );
+                    continue;
+                }
+
+                if let Some(limit) = workflow.wip_limit(&phase) {
+                    match store.lock_phase_wip(workflow.id, &phase).await {
+                        Ok(in_progress) if in_progress >= i64::from(limit) => {
+                            warn!(
+                                "{} automation did not move case {} to '{}': the phase is at its WIP limit of {}",
+                                automation_type, case_id, phase, limit
+                            );
+                            continue;
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            error!("Failed to check the WIP limit of phase '{}': {}", phase, e);
+                            continue;
+                        }
+                    }
+                }
+
+                let from_phase = current_phase.clone();
+
+                if let Err(e) = store.move_case(case_id, &from_phase, &phase).await {
+                    error!("Failed to apply {} MoveToPhase automation for case {}: {}", automation_type, case_id, e);
+                } else {
+                    info!("{} automation moved case {} from '{}' to '{}'", automation_type, case_id, from_phase, phase);
+
+                    let history = CaseHistory::new(
+                        case_id,
+                        Some(from_phase),
+                        phase.clone(),
+                        Some(format!("{} automation", automation_type)),
+                        Some(triggered_by.to_string()),
+                    );
+
+                    if let Err(err) = store.create_history(&history).await {
+                        error!("Failed to create history entry for {} automation: {}", automation_type, err);
+                    }
+
+                    current_phase = phase;
+                }
+            }
+            CaseModification::SetStatus { status } => {
+                let mut case = match store.find_case(case_id).await {
+                    Ok(Some(case)) => case,
+                    Ok(None) => {
+                        error!("Case {} disappeared before its {} SetStatus automation", case_id, automation_type);
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to fetch case {} for {} SetStatus automation: {}", case_id, automation_type, e);
+                        continue;
+                    }
+                };
+
+                let from_status = case.status.clone();
+                let lifecycle = CaseLifecycleAction::to(&status);
+                if !lifecycle.allowed_from(&from_status) {
+                    warn!(
+                        "{} automation cannot {} case {} in status '{}'",
+                        automation_type, lifecycle.as_str(), case_id, from_status.as_str()
+                    );
+                    continue;
+                }
+                lifecycle.apply(&mut case);
+
+                if let Err(e) = store.update_status(&case).await {
+                    error!("Failed to apply {} SetStatus automation for case {}: {}", automation_type, case_id, e);
+                    continue;
+                }
+                info!("{} automation set case {} status from '{}' to '{}'", automation_type, case_id, from_status.as_str(), status.as_str());
+
+                let history = CaseHistory::status_change(
+                    &case,
+                    from_status,
+                    Some(format!("{} automation", automation_type)),
+                    Some(triggered_by.to_string()),
+                );
+
+                if let Err(err) = store.create_history(&history).await {
+                    error!("Failed to create history entry for {} automation: {}", automation_type, err);
+                }
+            }
+            CaseModification::SetField { field, value, action } => {
+                let parts: Vec<&str> = field.split('.').collect();
+                if parts.is_empty() {
+                    error!("Invalid field path: {}", field);
+                    continue;
+                }
+
+                match parts[0] {
+                    "data" => {
+                        let path = parts[1..].join(".");
+                        if path.is_empty() {
+                            error!("Invalid data field path: {}", field);
+                            continue;
+                        }
+
+                        let provenance = FieldProvenance::automation(action, automation_type);
+                        match store.set_field(case_id, &path, &value, &provenance, &workflow.data_conflicts).await {
+                            Ok(FieldWrite::Rejected) => warn!(
+                                "{} automation did not set field '{}' for case {}: it was changed concurrently",
+                                automation_type, field, case_id
+                            ),
+                            Ok(_) => info!("{} automation set field '{}' to {:?} for case {}", automation_type, field, value, case_id),
+                            Err(e) => error!("Failed to apply {} SetField automation for case {}: {}", automation_type, case_id, e),
+                        }
+                    }
+                    _ => {
+                        error!("Unsupported field path for automation: {}", field);
+                    }
+                }
+            }
+            CaseModification::AddTag { tag } => match store.add_tag(case_id, &tag, MAX_CASE_TAGS).await {
+                Ok(true) => info!("{} automation tagged case {} with '{}'", automation_type, case_id, tag),
+                Ok(false) => debug!(
+                    "{} automation left case {} untagged with '{}': already tagged or at the tag limit",
+                    automation_type, case_id, tag
+                ),
+                Err(e) => error!("Failed to apply {} AddTag automation for case {}: {}", automation_type, case_id, e),
+            },
+            CaseModification::RecordMessage(message) => {
+                if let Err(e) = store.record_message(&message).await {
+                    error!("Failed to record {} automation message for case {}: {}", automation_type, case_id, e);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::AutomationExecutor;
+    use crate::models::automation::PhaseAutomation;
+    use crate::models::case::CaseStatus;
+    use crate::models::Case;
+    use crate::storage::MemoryStore;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn workflow() -> Workflow {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "name": "Sales",
+            "phases": ["Lead", {"name": "Qualified", "wip_limit": 1}, "Won"],
+            "initial_phase": "Lead",
+            "active": true,
+            "execution_limits": {},
+            "transitions": {"Lead": ["Qualified"]},
+            "created_at": Utc::now(),
+            "updated_at": Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    async fn run(store: &mut MemoryStore, workflow: &Workflow, case: &Case, actions: serde_json::Value) {
+        let automation: PhaseAutomation =
+            serde_json::from_value(json!({"trigger": "on_enter", "phase": "Lead", "actions": actions})).unwrap();
+        let result = AutomationExecutor::new().execute_automations(&[&automation], case, None).await.unwrap();
+        apply_modifications(store, case.id, workflow, result, "on_enter", "automation").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_modifications_apply_to_store() {
+        let workflow = workflow();
+        let mut store = MemoryStore::new();
+        let case = Case::new(workflow.id, "Lead".to_string(), json!({"amount": 10}), None);
+        store.insert_case(case.clone());
+
+        run(&mut store, &workflow, &case, json!([
+            {"type": "move_to_phase", "phase": "Won"},
+            {"type": "set_field", "field": "data.score", "value": 7},
+            {"type": "add_tag", "tag": "vip"},
+            {"type": "add_tag", "tag": "vip"},
+            {"type": "move_to_phase", "phase": "Qualified"},
+            {"type": "set_status", "status": "completed"}
+        ]))
+        .await;
+
+        let stored = store.case(case.id).unwrap();
+        assert_eq!(stored.current_phase, "Qualified");
+        assert_eq!(stored.previous_phase.as_deref(), Some("Lead"));
+        assert_eq!(stored.data, json!({"amount": 10, "score": 7}));
+        assert!(stored.field_provenance.contains_key("score"));
+        assert_eq!(stored.tags, vec!["vip"]);
+        assert_eq!(stored.status, CaseStatus::Completed);
+
+        let history = store.history(case.id);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].to_phase, "Qualified");
+        assert_eq!(history[1].to_status, Some(CaseStatus::Completed));
+
+        // "Qualified" holds at most one case in progress.
+        let other = Case::new(workflow.id, "Lead".to_string(), json!({}), None);
+        store.insert_case(other.clone());
+        store.insert_case(Case::new(workflow.id, "Qualified".to_string(), json!({}), None));
+        run(&mut store, &workflow, &other, json!([{"type": "move_to_phase", "phase": "Qualified"}])).await;
+        assert_eq!(store.case(other.id).unwrap().current_phase, "Lead");
+    }
+}
@@ -0,0 +1,214 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::models::execution::{Execution, ExecutionStatus, StepExecutionStatus, StepStatus};
+use crate::models::flow::Flow;
+use crate::models::step::{Step, StepType};
+
+/// How often a top-level step was reached across the replayed events.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StepCoverage {
+    pub name: String,
+    pub completed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub waiting: usize,
+}
+
+/// Outcomes of a condition step. A branch that was never taken has no
+/// replayed traffic behind it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BranchCoverage {
+    pub step: String,
+    pub if_true: usize,
+    pub if_false: usize,
+}
+
+impl BranchCoverage {
+    pub fn fully_covered(&self) -> bool {
+        self.if_true > 0 && self.if_false > 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayFailure {
+    pub event_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Aggregate of a flow run in shadow mode against stored events.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub flow_id: Uuid,
+    pub sampled: usize,
+    pub trigger_matched: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub steps: Vec<StepCoverage>,
+    pub branches: Vec<BranchCoverage>,
+    pub failures: Vec<ReplayFailure>,
+}
+
+impl ReplayReport {
+    pub fn new(flow: &Flow) -> Self {
+        let mut branches = Vec::new();
+        collect_conditions(&flow.steps, &mut branches);
+
+        Self {
+            flow_id: flow.id,
+            sampled: 0,
+            trigger_matched: 0,
+            completed: 0,
+            failed: 0,
+            steps: flow
+                .steps
+                .iter()
+                .map(|step| StepCoverage {
+                    name: step.name.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            branches,
+            failures: Vec::new(),
+        }
+    }
+
+    /// An event the flow's trigger does not accept.
+    pub fn record_unmatched(&mut self) {
+        self.sampled += 1;
+    }
+
+    /// A shadow execution of one event and the condition outcomes the
+    /// simulation saw while running it.
+    pub fn record(&mut self, execution: &Execution, branches: &[(String, bool)]) {
+        self.sampled += 1;
+        self.trigger_matched += 1;
+
+        let steps_status: BTreeMap<String, StepStatus> =
+            serde_json::from_value(execution.steps_status.clone()).unwrap_or_default();
+
+        for coverage in &mut self.steps {
+            match steps_status.get(&coverage.name).map(|status| &status.status) {
+                Some(StepExecutionStatus::Completed) => coverage.completed += 1,
+                Some(StepExecutionStatus::Failed) => coverage.failed += 1,
+                Some(StepExecutionStatus::Skipped) => coverage.skipped += 1,
+                Some(StepExecutionStatus::Waiting) => coverage.waiting += 1,
+                Some(StepExecutionStatus::Running) | None => {}
+            }
+        }
+
+        for (step, result) in branches {
+            if let Some(coverage) = self.branches.iter_mut().find(|b| &b.step == step) {
+                if *result {
+                    coverage.if_true += 1;
+                } else {
+                    coverage.if_false += 1;
+                }
+            }
+        }
+
+        match execution.status {
+            ExecutionStatus::Failed => {
+                self.failed += 1;
+                let step = steps_status
+                    .iter()
+                    .find(|(_, status)| matches!(status.status, StepExecutionStatus::Failed))
+                    .map(|(name, _)| name.clone());
+                self.failures.push(ReplayFailure {
+                    event_id: execution.event_id,
+                    step,
+                    error: execution.error.clone(),
+                });
+            }
+            _ => self.completed += 1,
+        }
+    }
+}
+
+fn collect_conditions(steps: &[Step], out: &mut Vec<BranchCoverage>) {
+    for step in steps {
+        collect_condition(step, out);
+    }
+}
+
+fn collect_condition(step: &Step, out: &mut Vec<BranchCoverage>) {
+    match &step.step_type {
+        StepType::Condition { if_true, if_false, .. } => {
+            out.push(BranchCoverage {
+                step: step.name.clone(),
+                ..Default::default()
+            });
+            collect_condition(if_true, out);
+            collect_condition(if_false, out);
+        }
+        StepType::FanOut { step: child, .. } => collect_condition(child, out),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::flow::{CreateFlow, FlowTrigger};
+    use serde_json::json;
+
+    fn flow() -> Flow {
+        let steps = serde_json::from_value(json!([
+            {
+                "name": "route",
+                "type": "condition",
+                "condition": "${event.data.amount} > 100",
+                "if_true": {"name": "review", "type": "delay", "duration_ms": 0},
+                "if_false": {"name": "approve", "type": "delay", "duration_ms": 0}
+            },
+            {"name": "notify", "type": "delay", "duration_ms": 0}
+        ]))
+        .unwrap();
+
+        Flow::new(CreateFlow {
+            name: "Orders".to_string(),
+            trigger: FlowTrigger {
+                event_type: "order.created".to_string(),
+                filters: serde_json::Value::Null,
+                case: None,
+            },
+            steps,
+            max_concurrent_executions: None,
+            active: true,
+        })
+    }
+
+    #[test]
+    fn test_replay_report_aggregates_executions() {
+        let flow = flow();
+        let mut report = ReplayReport::new(&flow);
+        assert_eq!(report.branches.len(), 1);
+
+        let mut completed = Execution::new(flow.id, Uuid::new_v4());
+        completed.status = ExecutionStatus::Completed;
+        completed.steps_status = json!({
+            "route": {"status": "completed", "started_at": "2026-01-01T00:00:00Z", "completed_at": null, "attempts": 1, "response": null, "error": null},
+            "notify": {"status": "completed", "started_at": "2026-01-01T00:00:00Z", "completed_at": null, "attempts": 1, "response": null, "error": null}
+        });
+        report.record(&completed, &[("route".to_string(), true)]);
+
+        let mut failed = Execution::new(flow.id, Uuid::new_v4());
+        failed.status = ExecutionStatus::Failed;
+        failed.error = Some("boom".to_string());
+        failed.steps_status = json!({
+            "route": {"status": "failed", "started_at": "2026-01-01T00:00:00Z", "completed_at": null, "attempts": 1, "response": null, "error": "boom"}
+        });
+        report.record(&failed, &[]);
+        report.record_unmatched();
+
+        assert_eq!((report.sampled, report.trigger_matched, report.completed, report.failed), (3, 2, 1, 1));
+        assert_eq!((report.steps[0].completed, report.steps[0].failed), (1, 1));
+        assert_eq!(report.steps[1].completed, 1);
+        assert_eq!((report.branches[0].if_true, report.branches[0].if_false), (1, 0));
+        assert!(!report.branches[0].fully_covered());
+        assert_eq!(report.failures[0].step.as_deref(), Some("route"));
+    }
+}
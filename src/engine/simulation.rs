@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+fn default_status() -> u16 {
+    200
+}
+
+/// Canned response for a webhook step during a simulation. A status of 400
+/// or above makes the step fail the same way a real error response would.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockResponse {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub body: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MockSource {
+    /// Supplied by the caller for this simulation.
+    Provided,
+    /// Taken from the step's last successful execution.
+    Recorded,
+    /// Neither was available; an empty 200 was returned.
+    Default,
+}
+
+/// A request the flow would have sent, with the mock that answered it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedRequest {
+    pub step: String,
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Value,
+    pub source: MockSource,
+    pub response: MockResponse,
+}
+
+/// Stubbed HTTP for dry runs. Webhook steps are answered by step name
+/// instead of being sent, delays are not slept and `delay_until` does not
+/// suspend the execution.
+#[derive(Debug, Default)]
+pub struct Simulation {
+    provided: HashMap<String, MockResponse>,
+    recorded: HashMap<String, Value>,
+    requests: Mutex<Vec<SimulatedRequest>>,
+    branches: Mutex<Vec<(String, bool)>>,
+}
+
+impl Simulation {
+    pub fn new(provided: HashMap<String, MockResponse>, recorded: HashMap<String, Value>) -> Self {
+        Self {
+            provided,
+            recorded,
+            requests: Mutex::new(Vec::new()),
+            branches: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn respond(&self, step: &str) -> (MockSource, MockResponse) {
+        if let Some(mock) = self.provided.get(step) {
+            return (MockSource::Provided, mock.clone());
+        }
+
+        if let Some(body) = self.recorded.get(step) {
+            return (
+                MockSource::Recorded,
+                MockResponse {
+                    status: default_status(),
+                    body: body.clone(),
+                },
+            );
+        }
+
+        (
+            MockSource::Default,
+            MockResponse {
+                status: default_status(),
+                body: Value::Null,
+            },
+        )
+    }
+
+    pub(crate) fn record(&self, request: SimulatedRequest) {
+        self.requests
+            .lock()
+            .expect("simulation request log poisoned")
+            .push(request);
+    }
+
+    pub(crate) fn record_branch(&self, step: &str, result: bool) {
+        self.branches
+            .lock()
+            .expect("simulation branch log poisoned")
+            .push((step.to_string(), result));
+    }
+
+    /// Outcome of every condition step evaluated, in order.
+    pub fn branches(&self) -> Vec<(String, bool)> {
+        self.branches
+            .lock()
+            .expect("simulation branch log poisoned")
+            .clone()
+    }
+
+    /// Requests in the order the flow issued them.
+    pub fn requests(&self) -> Vec<SimulatedRequest> {
+        self.requests
+            .lock()
+            .expect("simulation request log poisoned")
+            .clone()
+    }
+}
@@ -0,0 +1,265 @@
+//! gRPC ingestion for internal services, served next to the REST API. Each
+//! call goes through the same handler or engine path as its REST endpoint.
+
+pub mod proto;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use tonic::{Request, Status, Streaming};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::api::cases::{create_case, move_case};
+use crate::api::events::internal_create_and_trigger_event;
+use crate::api::region::{Region, ALL_REGIONS, REGION_HEADER};
+use crate::api::validation::{field_messages, ValidatedJson};
+use crate::api::AppState;
+use crate::middleware::auth::bearer_token;
+use crate::middleware::load_middleware;
+use crate::models::case::{CreateCase, MoveCase};
+use crate::models::event::CreateEvent;
+use crate::services::auth::{AuthError, JwtAuth, Role};
+use proto::ingestion_server::{Ingestion, IngestionServer};
+use proto::{CaseReply, CreateCaseRequest, EventReply, EventRequest, IngestEventsReply, MoveCaseRequest};
+
+/// Events of one `IngestEvents` stream stored and run at the same time.
+const INGEST_CONCURRENCY: usize = 32;
+
+/// Rejections described in an `IngestEvents` reply; the rest are counted.
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// The gRPC service as a router, to be served on its own port. Calls count
+/// as API requests for load shedding, and need a bearer token with the
+/// `operator` role when JWT authentication is configured.
+pub fn router(state: AppState) -> axum::Router {
+    let shedder = state.load_shedder.clone();
+    let auth = state.auth.clone();
+    let service = IngestionServer::with_interceptor(IngestionService { state }, move |request| {
+        authenticate(auth.as_ref(), request)
+    });
+    tonic::service::Routes::new(service)
+        .into_axum_router()
+        .layer(middleware::from_fn_with_state(shedder, load_middleware))
+}
+
+/// Checks the bearer token in the `authorization` metadata, as the REST
+/// API does for the endpoints the calls stand in for. All of them write, so
+/// they need `operator`.
+fn authenticate(auth: Option<&JwtAuth>, request: Request<()>) -> Result<Request<()>, Status> {
+    let Some(auth) = auth else {
+        return Ok(request);
+    };
+
+    let token = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(bearer_token)
+        .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+
+    let principal = match auth.authenticate(token) {
+        Ok(principal) => principal,
+        Err(AuthError::NoRole) => return Err(Status::permission_denied("Token grants no Orchepy role")),
+        Err(err) => return Err(Status::unauthenticated(err.to_string())),
+    };
+    if principal.role < Role::Operator {
+        return Err(Status::permission_denied("This call needs the 'operator' role"));
+    }
+
+    Ok(request)
+}
+
+pub struct IngestionService {
+    state: AppState,
+}
+
+fn code_for(status: StatusCode) -> tonic::Code {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY | StatusCode::PAYLOAD_TOO_LARGE => {
+            tonic::Code::InvalidArgument
+        }
+        StatusCode::UNAUTHORIZED => tonic::Code::Unauthenticated,
+        StatusCode::FORBIDDEN => tonic::Code::PermissionDenied,
+        StatusCode::NOT_FOUND => tonic::Code::NotFound,
+        StatusCode::CONFLICT => tonic::Code::FailedPrecondition,
+        StatusCode::TOO_MANY_REQUESTS => tonic::Code::ResourceExhausted,
+        StatusCode::NOT_IMPLEMENTED => tonic::Code::Unimplemented,
+        StatusCode::SERVICE_UNAVAILABLE => tonic::Code::Unavailable,
+        _ => tonic::Code::Internal,
+    }
+}
+
+/// The JSON body of a REST handler's response, or its error as a status.
+async fn response_json(response: Response) -> Result<Value, Status> {
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|err| Status::internal(err.to_string()))?;
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    if status.is_success() {
+        return Ok(body);
+    }
+
+    let message = match body.get("error").and_then(Value::as_str) {
+        Some(message) => message.to_string(),
+        None => status.to_string(),
+    };
+    Err(Status::new(code_for(status), message))
+}
+
+/// `{}` when empty.
+fn parse_json(field: &str, text: &str) -> Result<Value, Status> {
+    if text.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(text).map_err(|err| Status::invalid_argument(format!("{} is not valid JSON: {}", field, err)))
+}
+
+fn parse_id(field: &str, text: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(text).map_err(|_| Status::invalid_argument(format!("{} must be a UUID", field)))
+}
+
+fn validate(payload: &impl Validate) -> Result<(), Status> {
+    payload.validate().map_err(|errors| {
+        Status::invalid_argument(format!("Validation failed: {}", json!(field_messages(&errors))))
+    })
+}
+
+fn case_reply(body: Value) -> CaseReply {
+    // A move to the phase the case is in answers with a message and the case.
+    let case = match body.get("case") {
+        Some(case) => case.clone(),
+        None => body,
+    };
+    let field = |name: &str| case.get(name).and_then(Value::as_str).unwrap_or_default().to_string();
+
+    CaseReply {
+        id: field("id"),
+        workflow_id: field("workflow_id"),
+        current_phase: field("current_phase"),
+        status: field("status"),
+        case_json: case.to_string(),
+    }
+}
+
+impl IngestionService {
+    fn region<T>(&self, request: &Request<T>) -> Result<Region, Status> {
+        let name = request
+            .metadata()
+            .get(REGION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_ascii_lowercase());
+
+        if name.as_deref() == Some(ALL_REGIONS) {
+            return Err(Status::invalid_argument("region=all is only supported when listing resources"));
+        }
+        Region::resolve(&self.state, name).map_err(|rejection| Status::invalid_argument(rejection.0))
+    }
+}
+
+async fn trigger_event(state: &AppState, region: &Region, request: EventRequest) -> Result<EventReply, Status> {
+    let payload = CreateEvent {
+        event_type: request.event_type,
+        data: parse_json("data_json", &request.data_json)?,
+        metadata: request.metadata_json.map(|text| parse_json("metadata_json", &text)).transpose()?,
+    };
+
+    let (event_id, execution_ids, matched_flows) = internal_create_and_trigger_event(state, region, payload)
+        .await
+        .map_err(|err| Status::new(code_for(err.status), err.message))?;
+
+    Ok(EventReply {
+        event_id: event_id.to_string(),
+        execution_ids: execution_ids.iter().map(Uuid::to_string).collect(),
+        matched_flows: matched_flows as u32,
+    })
+}
+
+#[tonic::async_trait]
+impl Ingestion for IngestionService {
+    async fn create_case(&self, request: Request<CreateCaseRequest>) -> Result<tonic::Response<CaseReply>, Status> {
+        let region = self.region(&request)?;
+        let request = request.into_inner();
+
+        let payload = CreateCase {
+            workflow_id: parse_id("workflow_id", &request.workflow_id)?,
+            data: parse_json("data_json", &request.data_json)?,
+            metadata: request.metadata_json.map(|text| parse_json("metadata_json", &text)).transpose()?,
+            initial_phase: request.initial_phase,
+            triggered_by: request.triggered_by,
+            tags: request.tags,
+            external_id: request.external_id,
+            upsert: request.upsert,
+        };
+        validate(&payload)?;
+
+        let response = create_case(State(self.state.clone()), region, ValidatedJson(payload))
+            .await
+            .into_response();
+        Ok(tonic::Response::new(case_reply(response_json(response).await?)))
+    }
+
+    async fn move_case(&self, request: Request<MoveCaseRequest>) -> Result<tonic::Response<CaseReply>, Status> {
+        let region = self.region(&request)?;
+        let request = request.into_inner();
+
+        let case_id = parse_id("case_id", &request.case_id)?;
+        let payload = MoveCase {
+            to_phase: request.to_phase,
+            reason: request.reason,
+            triggered_by: request.triggered_by,
+        };
+        validate(&payload)?;
+
+        let response = move_case(State(self.state.clone()), region, Path(case_id), HeaderMap::new(), ValidatedJson(payload))
+            .await
+            .into_response();
+        Ok(tonic::Response::new(case_reply(response_json(response).await?)))
+    }
+
+    async fn send_event(&self, request: Request<EventRequest>) -> Result<tonic::Response<EventReply>, Status> {
+        let region = self.region(&request)?;
+        let reply = trigger_event(&self.state, &region, request.into_inner()).await?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    async fn ingest_events(
+        &self,
+        request: Request<Streaming<EventRequest>>,
+    ) -> Result<tonic::Response<IngestEventsReply>, Status> {
+        let region = self.region(&request)?;
+
+        let mut outcomes = request
+            .into_inner()
+            .map(|message| {
+                let (state, region) = (&self.state, &region);
+                async move { Ok::<_, Status>(trigger_event(state, region, message?).await) }
+            })
+            .buffer_unordered(INGEST_CONCURRENCY);
+
+        let mut reply = IngestEventsReply::default();
+        while let Some(outcome) = outcomes.next().await {
+            match outcome? {
+                Ok(event) => {
+                    reply.accepted += 1;
+                    reply.event_ids.push(event.event_id);
+                }
+                Err(status) => {
+                    reply.rejected += 1;
+                    if reply.errors.len() < MAX_REPORTED_ERRORS {
+                        reply.errors.push(status.message().to_string());
+                    }
+                }
+            }
+        }
+
+        Ok(tonic::Response::new(reply))
+    }
+}
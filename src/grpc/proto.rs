@@ -0,0 +1,82 @@
+//! Messages of `proto/orchepy/v1/ingestion.proto`, with the service code
+//! generated by `build.rs`.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CreateCaseRequest {
+    #[prost(string, tag = "1")]
+    pub workflow_id: String,
+    #[prost(string, optional, tag = "2")]
+    pub initial_phase: Option<String>,
+    #[prost(string, tag = "3")]
+    pub data_json: String,
+    #[prost(string, optional, tag = "4")]
+    pub metadata_json: Option<String>,
+    #[prost(string, repeated, tag = "5")]
+    pub tags: Vec<String>,
+    #[prost(string, optional, tag = "6")]
+    pub triggered_by: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub external_id: Option<String>,
+    #[prost(bool, tag = "8")]
+    pub upsert: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MoveCaseRequest {
+    #[prost(string, tag = "1")]
+    pub case_id: String,
+    #[prost(string, tag = "2")]
+    pub to_phase: String,
+    #[prost(string, optional, tag = "3")]
+    pub reason: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub triggered_by: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CaseReply {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub workflow_id: String,
+    #[prost(string, tag = "3")]
+    pub current_phase: String,
+    #[prost(string, tag = "4")]
+    pub status: String,
+    #[prost(string, tag = "5")]
+    pub case_json: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EventRequest {
+    #[prost(string, tag = "1")]
+    pub event_type: String,
+    #[prost(string, tag = "2")]
+    pub data_json: String,
+    #[prost(string, optional, tag = "3")]
+    pub metadata_json: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EventReply {
+    #[prost(string, tag = "1")]
+    pub event_id: String,
+    #[prost(string, repeated, tag = "2")]
+    pub execution_ids: Vec<String>,
+    #[prost(uint32, tag = "3")]
+    pub matched_flows: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct IngestEventsReply {
+    #[prost(uint64, tag = "1")]
+    pub accepted: u64,
+    #[prost(uint64, tag = "2")]
+    pub rejected: u64,
+    #[prost(string, repeated, tag = "3")]
+    pub event_ids: Vec<String>,
+    #[prost(string, repeated, tag = "4")]
+    pub errors: Vec<String>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/orchepy.v1.Ingestion.rs"));
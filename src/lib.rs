@@ -3,4 +3,8 @@ pub mod middleware;
 pub mod models;
 pub mod repositories;
 pub mod services;
+pub mod storage;
 pub mod engine;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod workers;
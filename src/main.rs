@@ -1,9 +1,19 @@
 use orchepy::api;
-use orchepy::middleware::whitelist_middleware;
-use orchepy::services::WebhookSender;
+use orchepy::middleware::{capture_process_env, whitelist_middleware, Whitelist, WhitelistConfig};
+use orchepy::services::{
+    ConsumerConfig, DataRegions, DigestConfig, DigestService, EventPublisher, ExecutionPayloads, JwtAuth, LoadShedder,
+    LoadSheddingConfig, NotificationRegistry, OutboundHttpConfig, PoolConfig, RetentionConfig, SecretCipher,
+    WebhookSender, WorkerMonitor,
+};
+use orchepy::workers::{
+    spawn_automation_resume_worker, spawn_automation_retry_worker, spawn_case_event_forwarder,
+    spawn_change_log_prune_worker, spawn_credential_expiry_worker, spawn_digest_worker, spawn_event_consumer,
+    spawn_flow_resume_worker, spawn_idempotency_prune_worker, spawn_jwks_refresh_worker, spawn_outbox_dispatcher,
+    spawn_partition_maintenance_worker, spawn_retention_purge_worker, spawn_secret_reencrypt_job,
+    spawn_signing_key_refresh_worker, spawn_usage_flush_worker, AutomationRetryConfig, CredentialExpiryConfig,
+};
 
 use axum::middleware;
-use sqlx::postgres::PgPoolOptions;
 use std::env;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tracing::info;
@@ -11,6 +21,7 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    capture_process_env();
     dotenvy::dotenv().ok();
 
     tracing_subscriber::registry()
@@ -23,20 +34,330 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Starting Orchepy v{}", env!("CARGO_PKG_VERSION"));
 
+    // Before any HTTP client is built, so all of them use these settings.
+    let outbound_config = OutboundHttpConfig::from_env();
+    let outbound = outbound_config
+        .load()
+        .map_err(|err| anyhow::anyhow!("Invalid outbound HTTP settings: {:#}", err))?;
+    if outbound_config.http_proxy.is_some() || outbound_config.https_proxy.is_some() {
+        info!("Outbound requests go through the configured proxy");
+    }
+    if outbound_config.client_cert.is_some() {
+        info!("Outbound requests present a client certificate when asked");
+    }
+    outbound.install();
+
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
-    info!("Connecting to database...");
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
-        .await?;
+    let pool_config = PoolConfig::from_env();
+    info!(
+        "Connecting to database (pool of up to {} connections)...",
+        pool_config.max_connections
+    );
+    let pool = pool_config.connect(&database_url).await?;
 
     info!("Database connected");
 
+    let regions = DataRegions::from_env(pool.clone(), &pool_config).await?;
+    info!(
+        "Data regions: {} (default: {})",
+        regions.names().collect::<Vec<_>>().join(", "),
+        regions.default_region()
+    );
+
+    let migrate_only = env::args().skip(1).any(|arg| arg == "--migrate-only");
+    let run_migrations = env::var("RUN_MIGRATIONS")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+    if migrate_only || run_migrations {
+        regions.migrate().await?;
+        info!("Migrations are up to date");
+    }
+    if migrate_only {
+        return Ok(());
+    }
+
+    let notifications = NotificationRegistry::from_env(&pool);
+    let shedder = LoadShedder::new(LoadSheddingConfig::from_env());
+    let workers = WorkerMonitor::new();
+
+    let digest_config = DigestConfig::from_env();
+    if digest_config.enabled {
+        for (region, region_pool) in regions.iter() {
+            workers.track_in_region(
+                "digest",
+                region,
+                spawn_digest_worker(
+                    DigestService::new(region_pool.clone(), digest_config.clone(), notifications.clone()),
+                    shedder.clone(),
+                ),
+            );
+        }
+    }
+
     let webhook_sender = WebhookSender::new();
 
-    let app = api::build_router(pool, webhook_sender)
-        .layer(middleware::from_fn(whitelist_middleware))
+    let signing_key_refresh_secs = env::var("SIGNING_KEY_REFRESH_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(60);
+    workers.track(
+        "signing_key_refresh",
+        spawn_signing_key_refresh_worker(
+            pool.clone(),
+            webhook_sender.signer().clone(),
+            std::time::Duration::from_secs(signing_key_refresh_secs),
+        ),
+    );
+
+    let credential_expiry = CredentialExpiryConfig::from_env();
+    for (region, region_pool) in regions.iter() {
+        workers.track_in_region(
+            "credential_expiry",
+            region,
+            spawn_credential_expiry_worker(
+                region_pool.clone(),
+                webhook_sender.clone(),
+                shedder.clone(),
+                credential_expiry.clone(),
+            ),
+        );
+    }
+
+    let idempotency_ttl_hours = env::var("IDEMPOTENCY_KEY_TTL_HOURS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(24);
+
+    let state = api::AppState::new(pool.clone(), webhook_sender)
+        .with_regions(regions.clone())
+        .with_load_shedder(shedder.clone())
+        .with_workers(workers.clone())
+        .with_idempotency_ttl(std::time::Duration::from_secs(idempotency_ttl_hours * 3600))
+        .with_retention(RetentionConfig::from_env())
+        .with_whitelist(Whitelist::new(WhitelistConfig::from_env()));
+
+    let event_publisher = EventPublisher::from_env()
+        .await
+        .map_err(|err| anyhow::anyhow!("Invalid event broker settings: {}", err))?;
+    let state = if event_publisher.is_enabled() {
+        info!("Publishing events to the message broker");
+        workers.track(
+            "case_event_forwarder",
+            spawn_case_event_forwarder(state.case_stream.clone(), event_publisher.clone()),
+        );
+        state.with_event_publisher(event_publisher)
+    } else {
+        state
+    };
+
+    let storage = orchepy::services::storage::storage_from_env()
+        .await
+        .map_err(|err| anyhow::anyhow!("Invalid storage settings: {}", err))?;
+    let state = match storage {
+        Some(storage) => {
+            info!("Object storage enabled for attachments and execution payloads");
+            state.with_storage(storage.clone()).with_execution_payloads(ExecutionPayloads::from_env(storage))
+        }
+        None => state,
+    };
+
+    let state = match SecretCipher::from_env().map_err(|err| anyhow::anyhow!("Invalid secrets settings: {}", err))? {
+        Some(cipher) => {
+            info!("Secrets store enabled");
+            state.with_secrets(cipher)
+        }
+        None => state,
+    };
+
+    let jwks_refresh_secs = env::var("AUTH_JWKS_REFRESH_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(300);
+    let auth = JwtAuth::from_env().map_err(|err| anyhow::anyhow!("Invalid JWT settings: {}", err))?;
+    let state = match auth {
+        Some(auth) => {
+            info!("JWT authentication enabled");
+            if auth.jwks_url().is_some() {
+                workers.track(
+                    "jwks_refresh",
+                    spawn_jwks_refresh_worker(auth.clone(), std::time::Duration::from_secs(jwks_refresh_secs)),
+                );
+            }
+            state.with_auth(auth)
+        }
+        None => state,
+    };
+
+    let usage_flush_secs = env::var("USAGE_FLUSH_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(60);
+    workers.track(
+        "usage_flush",
+        spawn_usage_flush_worker(
+            state.usage.clone(),
+            pool.clone(),
+            shedder.clone(),
+            std::time::Duration::from_secs(usage_flush_secs),
+        ),
+    );
+
+    let flow_resume_secs = env::var("FLOW_RESUME_POLL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(15);
+    for (region, region_pool) in regions.iter() {
+        workers.track_in_region(
+            "flow_resume",
+            region,
+            spawn_flow_resume_worker(
+                region_pool.clone(),
+                state.flow_limiter.clone(),
+                state.webhook_sender.clone(),
+                state.event_publisher.clone(),
+                state.execution_payloads.clone(),
+                state.secrets.clone(),
+                shedder.clone(),
+                state.clock.clone(),
+                std::time::Duration::from_secs(flow_resume_secs),
+            ),
+        );
+    }
+
+    let automation_resume_secs = env::var("AUTOMATION_RESUME_POLL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(15);
+    for (region, region_pool) in regions.iter() {
+        workers.track_in_region(
+            "automation_resume",
+            region,
+            spawn_automation_resume_worker(
+                region_pool.clone(),
+                state.secrets.clone(),
+                shedder.clone(),
+                state.clock.clone(),
+                std::time::Duration::from_secs(automation_resume_secs),
+            ),
+        );
+    }
+
+    let automation_retry = AutomationRetryConfig::from_env();
+    if automation_retry.enabled() {
+        for (region, region_pool) in regions.iter() {
+            workers.track_in_region(
+                "automation_retry",
+                region,
+                spawn_automation_retry_worker(
+                    region_pool.clone(),
+                    state.secrets.clone(),
+                    shedder.clone(),
+                    automation_retry.clone(),
+                ),
+            );
+        }
+    }
+
+    let outbox_poll_secs = env::var("OUTBOX_POLL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(5);
+    for (region, _) in regions.iter() {
+        workers.track_in_region(
+            "outbox_dispatcher",
+            region,
+            spawn_outbox_dispatcher(
+                state.clone(),
+                region.to_string(),
+                std::time::Duration::from_secs(outbox_poll_secs),
+            ),
+        );
+    }
+
+    if let Some(consumer) =
+        ConsumerConfig::from_env().map_err(|err| anyhow::anyhow!("Invalid event consumer settings: {}", err))?
+    {
+        info!("Ingesting events from {} '{}'", consumer.broker, consumer.topic);
+        workers.track("event_consumer", spawn_event_consumer(state.clone(), consumer));
+    }
+
+    let change_log_retention_hours = env::var("CHANGE_LOG_RETENTION_HOURS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(72);
+    for (region, region_pool) in regions.iter() {
+        workers.track_in_region(
+            "change_log_prune",
+            region,
+            spawn_change_log_prune_worker(
+                region_pool.clone(),
+                shedder.clone(),
+                std::time::Duration::from_secs(change_log_retention_hours * 3600),
+            ),
+        );
+    }
+
+    let partition_months_ahead = env::var("PARTITION_MONTHS_AHEAD")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|months| *months > 0)
+        .unwrap_or(3);
+    for (region, region_pool) in regions.iter() {
+        workers.track_in_region(
+            "partition_maintenance",
+            region,
+            spawn_partition_maintenance_worker(region_pool.clone(), shedder.clone(), partition_months_ahead),
+        );
+    }
+
+    for (region, region_pool) in regions.iter() {
+        workers.track_in_region(
+            "idempotency_prune",
+            region,
+            spawn_idempotency_prune_worker(region_pool.clone(), shedder.clone(), state.clock.clone()),
+        );
+    }
+
+    if let Some(cipher) = state.secrets.as_ref().filter(|cipher| cipher.has_previous()) {
+        info!("SECRETS_KEY_PREVIOUS is set; re-encrypting secrets with SECRETS_KEY");
+        for (_, region_pool) in regions.iter() {
+            spawn_secret_reencrypt_job(region_pool.clone(), cipher.clone());
+        }
+    }
+
+    if state.retention.enabled() {
+        for (region, region_pool) in regions.iter() {
+            workers.track_in_region(
+                "retention_purge",
+                region,
+                spawn_retention_purge_worker(
+                    region_pool.clone(),
+                    shedder.clone(),
+                    state.clock.clone(),
+                    state.retention.clone(),
+                ),
+            );
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    let grpc_app = orchepy::grpc::router(state.clone())
+        .layer(middleware::from_fn_with_state(state.whitelist.clone(), whitelist_middleware))
+        .layer(TraceLayer::new_for_http());
+
+    let whitelist = state.whitelist.clone();
+    let app = api::build_router(state)
+        .layer(middleware::from_fn_with_state(whitelist, whitelist_middleware))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http());
 
@@ -47,6 +368,20 @@ async fn main() -> anyhow::Result<()> {
     info!("Starting server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = env::var("GRPC_PORT").ok().filter(|port| !port.trim().is_empty()) {
+        let grpc_addr = format!("{}:{}", host, grpc_port);
+        info!("Starting gRPC server on {}", grpc_addr);
+
+        let grpc_listener = tokio::net::TcpListener::bind(&grpc_addr).await?;
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(grpc_listener, grpc_app).await {
+                tracing::error!("gRPC server stopped: {}", e);
+            }
+        });
+    }
+
     axum::serve(listener, app).await?;
 
     Ok(())
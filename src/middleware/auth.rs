@@ -0,0 +1,136 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::api::response::ApiError;
+use crate::models::ErrorCode;
+use crate::services::auth::{AuthError, JwtAuth, Role};
+
+/// Routes that carry their own credential or none: the dashboard shell,
+/// health checks and portal links.
+const PUBLIC_ROUTES: &[&str] = &[
+    "/",
+    "/health",
+    "/webhooks/signing-info",
+    "/portal/{token}",
+    "/portal/{token}/replies",
+];
+
+/// Definitions and administration, which only admins may change.
+const ADMIN_PREFIXES: &[&str] =
+    &["/workflows", "/flows", "/service-accounts", "/secrets", "/webhooks", "/webhook-deliveries", "/admin"];
+
+/// Administration that only admins may even read.
+const ADMIN_ONLY_PREFIXES: &[&str] = &["/admin", "/service-accounts", "/secrets", "/webhooks", "/webhook-deliveries"];
+
+/// The role a request needs, or None for a public route. Reads need
+/// `viewer`, except under `/admin`, `/service-accounts`, `/secrets`, `/webhooks` and
+/// `/webhook-deliveries`; other requests
+/// need `operator`, or `admin` for workflows, flows and administration.
+pub fn required_role(method: &Method, route: &str) -> Option<Role> {
+    if PUBLIC_ROUTES.contains(&route) {
+        return None;
+    }
+
+    let under = |prefix: &&str| route == *prefix || route.starts_with(&format!("{}/", prefix));
+    let admin_only = ADMIN_ONLY_PREFIXES.iter().any(under);
+
+    if admin_only {
+        Some(Role::Admin)
+    } else if method == Method::GET || method == Method::HEAD {
+        Some(Role::Viewer)
+    } else if ADMIN_PREFIXES.iter().any(under) {
+        Some(Role::Admin)
+    } else {
+        Some(Role::Operator)
+    }
+}
+
+/// The token of an `Authorization: Bearer <token>` header value.
+pub(crate) fn bearer_token(authorization: &str) -> Option<&str> {
+    authorization
+        .strip_prefix("Bearer ")
+        .or_else(|| authorization.strip_prefix("bearer "))
+        .map(str::trim)
+}
+
+fn unauthorized(message: String) -> Response {
+    let mut response = ApiError::new(StatusCode::UNAUTHORIZED, message).into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+/// Checks the bearer token when JWT authentication is configured, and makes
+/// the [`Principal`](crate::services::auth::Principal) available to handlers
+/// as a request extension.
+pub async fn auth_middleware(State(auth): State<Option<JwtAuth>>, mut request: Request, next: Next) -> Response {
+    let Some(auth) = auth else {
+        return next.run(request).await;
+    };
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let Some(required) = required_role(request.method(), &route) else {
+        return next.run(request).await;
+    };
+
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(bearer_token);
+    let Some(token) = token else {
+        return unauthorized("Missing bearer token".to_string());
+    };
+
+    let principal = match auth.authenticate(token) {
+        Ok(principal) => principal,
+        Err(AuthError::NoRole) => {
+            return ApiError::from_code(ErrorCode::RoleRequired, "Token grants no Orchepy role").into_response()
+        }
+        Err(err) => return unauthorized(err.to_string()),
+    };
+
+    if principal.role < required {
+        return ApiError::from_code(ErrorCode::RoleRequired, format!("This request needs the '{}' role", required.as_str()))
+            .with_details(json!({"role": principal.role}))
+            .into_response();
+    }
+
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_roles() {
+        assert_eq!(required_role(&Method::GET, "/health"), None);
+        assert_eq!(required_role(&Method::POST, "/portal/{token}/replies"), None);
+        assert_eq!(required_role(&Method::GET, "/cases/{id}"), Some(Role::Viewer));
+        assert_eq!(required_role(&Method::GET, "/workflows"), Some(Role::Viewer));
+        assert_eq!(required_role(&Method::PUT, "/cases/{id}/move"), Some(Role::Operator));
+        assert_eq!(required_role(&Method::POST, "/events"), Some(Role::Operator));
+        assert_eq!(required_role(&Method::POST, "/messages/inbound"), Some(Role::Operator));
+        assert_eq!(required_role(&Method::POST, "/workflows"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::DELETE, "/flows/{id}"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::GET, "/admin/load"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::GET, "/service-accounts"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::GET, "/secrets/{name}"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::GET, "/webhooks/{id}"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::GET, "/webhooks/signing-info"), None);
+        assert_eq!(required_role(&Method::GET, "/webhook-deliveries"), Some(Role::Admin));
+        assert_eq!(required_role(&Method::POST, "/flowsheets"), Some(Role::Operator));
+    }
+}
@@ -0,0 +1,162 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{FromRequestParts, MatchedPath, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::api::region::Region;
+use crate::api::response::ApiError;
+use crate::api::AppState;
+use crate::models::ErrorCode;
+use crate::repositories::idempotency_repository::{IdempotencyClaim, IdempotencyKey, StoredResponse};
+use crate::repositories::IdempotencyRepository;
+use crate::services::usage::client_id;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Set on responses replayed from an earlier request with the same key.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const MAX_KEY_LEN: usize = 255;
+
+/// Bodies of idempotent requests are buffered to be compared on retries.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// A claim older than this whose request never answered, e.g. because the
+/// instance died, no longer blocks retries.
+const ABANDONED_AFTER: chrono::Duration = chrono::Duration::minutes(5);
+
+fn error_response(code: ErrorCode, message: &str) -> Response {
+    ApiError::from_code(code, message).into_response()
+}
+
+/// Replays the stored response when a request is retried with the same
+/// `Idempotency-Key`. Keys are kept per client (see [`client_id`]) and route in the region of
+/// the request for `AppState::idempotency_ttl`. Server errors aren't stored,
+/// so a retry after one runs the request again.
+pub async fn idempotency_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => key.to_string(),
+        _ => {
+            return error_response(
+                ErrorCode::IdempotencyKeyInvalid,
+                "Idempotency-Key must be 1 to 255 visible ASCII characters",
+            )
+        }
+    };
+
+    let (mut parts, body) = request.into_parts();
+
+    // An unknown region is left for the handler to reject.
+    let Ok(region) = Region::from_request_parts(&mut parts, &state).await else {
+        return next.run(Request::from_parts(parts, body)).await;
+    };
+
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => return error_response(ErrorCode::PayloadTooLarge, "Request body is too large"),
+    };
+
+    let route = parts
+        .extensions
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| parts.uri.path().to_string());
+    let key = IdempotencyKey {
+        client: client_id(state.auth.is_some(), &parts.extensions, &parts.headers).unwrap_or_default(),
+        route: format!("{} {}", parts.method, route),
+        key,
+    };
+
+    let mut hasher = Sha256::new();
+    hasher.update(parts.uri.query().unwrap_or_default().as_bytes());
+    hasher.update([0]);
+    hasher.update(&body);
+    let request_hash = hex::encode(hasher.finalize());
+
+    let now = state.clock.now();
+    let ttl = chrono::Duration::from_std(state.idempotency_ttl).unwrap_or(chrono::Duration::MAX);
+    let repo = IdempotencyRepository::new(&region.pool);
+
+    match repo
+        .claim(&key, &request_hash, now, now + ttl, now - ABANDONED_AFTER)
+        .await
+    {
+        Ok(IdempotencyClaim::Claimed) => {}
+        Ok(IdempotencyClaim::InProgress) => {
+            return error_response(
+                ErrorCode::IdempotencyKeyInUse,
+                "A request with this Idempotency-Key is still being processed",
+            )
+        }
+        Ok(IdempotencyClaim::Mismatch) => {
+            return error_response(
+                ErrorCode::IdempotencyKeyReused,
+                "Idempotency-Key was already used for a different request",
+            )
+        }
+        Ok(IdempotencyClaim::Completed(stored)) => return replay(stored),
+        Err(e) => {
+            error!("Failed to claim idempotency key: {}", e);
+            return error_response(ErrorCode::ServiceUnavailable, "Idempotency-Key could not be checked");
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let status = response.status();
+
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        if let Err(e) = repo.release(&key).await {
+            error!("Failed to release idempotency key: {}", e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Failed to read response for idempotency key: {}", e);
+            if let Err(e) = repo.release(&key).await {
+                error!("Failed to release idempotency key: {}", e);
+            }
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let stored = StoredResponse {
+        status_code: status.as_u16() as i16,
+        content_type: parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        body: body.to_vec(),
+    };
+    if let Err(e) = repo.complete(&key, &stored).await {
+        error!("Failed to store idempotent response: {}", e);
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status_code as u16).unwrap_or(StatusCode::OK);
+    let mut response = (status, stored.body).into_response();
+
+    let headers = response.headers_mut();
+    headers.remove(header::CONTENT_TYPE);
+    if let Some(content_type) = stored.content_type.and_then(|value| HeaderValue::from_str(&value).ok()) {
+        headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+
+    response
+}
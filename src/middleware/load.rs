@@ -0,0 +1,18 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::services::LoadShedder;
+
+/// Counts the request as in flight while it runs, so background work can
+/// make way for it.
+pub async fn load_middleware(
+    State(shedder): State<LoadShedder>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let _in_flight = shedder.begin_request();
+    next.run(request).await
+}
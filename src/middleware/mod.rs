@@ -1,3 +1,13 @@
+pub mod auth;
+pub mod idempotency;
+pub mod load;
+pub mod request_id;
+pub mod usage;
 pub mod whitelist;
 
-pub use whitelist::{whitelist_middleware, WhitelistConfig};
+pub use auth::auth_middleware;
+pub use idempotency::idempotency_middleware;
+pub use load::load_middleware;
+pub use request_id::request_id_middleware;
+pub use usage::{usage_middleware, UNMATCHED_ROUTE};
+pub use whitelist::{capture_process_env, whitelist_middleware, IpRange, Whitelist, WhitelistConfig};
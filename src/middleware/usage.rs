@@ -0,0 +1,43 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use std::time::Instant;
+
+use crate::api::AppState;
+use crate::services::usage::client_id;
+
+/// The route recorded for requests that match none, such as 404s for
+/// made-up paths, so they don't each get a row of their own.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+pub async fn usage_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(key_id) = client_id(state.auth.is_some(), request.extensions(), request.headers()) else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or(UNMATCHED_ROUTE)
+        .to_string();
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let latency_ms = started.elapsed().as_millis() as i64;
+
+    state.usage.record(
+        &key_id,
+        &method,
+        &route,
+        response.status().as_u16(),
+        latency_ms,
+        Utc::now(),
+    );
+
+    response
+}
@@ -1,32 +1,137 @@
-use axum::{extract::Request, http::StatusCode, middleware::Next, response::Response};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
 use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
 use tracing::{debug, warn};
 
-#[derive(Clone)]
+use crate::api::response::ApiError;
+use crate::models::ErrorCode;
+
+/// `WHITELIST_ENABLED` and `WHITELIST_IPS` as the process was started
+/// with, before `.env` was loaded.
+static PROCESS_ENV: OnceLock<(Option<String>, Option<String>)> = OnceLock::new();
+
+/// Records the whitelist settings of the process environment. Call it
+/// before loading `.env`, so [`Whitelist::reload`] can tell them apart from
+/// the ones `.env` added.
+pub fn capture_process_env() {
+    PROCESS_ENV.get_or_init(|| (std::env::var("WHITELIST_ENABLED").ok(), std::env::var("WHITELIST_IPS").ok()));
+}
+
+/// An address or CIDR range from `WHITELIST_IPS`, e.g. `10.0.0.50`,
+/// `10.0.0.0/8` or `2001:db8::/32`. A plain address is a range of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        // Dual-stack listeners see IPv4 clients as `::ffff:a.b.c.d`.
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                masked(u32::from(ip) as u128, 32, self.prefix_len) == u32::from(network) as u128
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => masked(u128::from(ip), 128, self.prefix_len) == u128::from(network),
+            _ => false,
+        }
+    }
+}
+
+/// `bits` of an address `width` bits long, with all but the first
+/// `prefix_len` cleared.
+fn masked(bits: u128, width: u8, prefix_len: u8) -> u128 {
+    match width - prefix_len {
+        0 => bits,
+        host_bits if host_bits >= 128 => 0,
+        host_bits => bits & !((1u128 << host_bits) - 1),
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    /// Host bits set below the prefix are ignored, so `10.1.2.3/8` is
+    /// `10.0.0.0/8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid IP address or CIDR range '{}'", s);
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len.parse::<u8>().map_err(|_| invalid())?)),
+            None => (s, None),
+        };
+        let ip = address.parse::<IpAddr>().map_err(|_| invalid())?.to_canonical();
+        let width = if ip.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) if prefix_len > width => return Err(invalid()),
+            Some(prefix_len) => prefix_len,
+            None => width,
+        };
+
+        let network = match ip {
+            IpAddr::V4(ip) => IpAddr::V4((masked(u32::from(ip) as u128, 32, prefix_len) as u32).into()),
+            IpAddr::V6(ip) => IpAddr::V6(masked(u128::from(ip), 128, prefix_len).into()),
+        };
+        Ok(Self { network, prefix_len })
+    }
+}
+
+impl fmt::Display for IpRange {
+    /// A single address is shown without its prefix length.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = if self.network.is_ipv4() { 32 } else { 128 };
+        if self.prefix_len == width {
+            write!(f, "{}", self.network)
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix_len)
+        }
+    }
+}
+
+impl Serialize for IpRange {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct WhitelistConfig {
     pub enabled: bool,
-    pub allowed_ips: Vec<IpAddr>,
+    pub allowed_ips: Vec<IpRange>,
+    /// Entries of `WHITELIST_IPS` that aren't addresses or ranges, and so
+    /// allow nothing.
+    pub invalid_entries: Vec<String>,
 }
 
 impl WhitelistConfig {
+    /// From `WHITELIST_ENABLED` and `WHITELIST_IPS`, a comma-separated list
+    /// of addresses and CIDR ranges. Invalid entries are skipped with a
+    /// warning.
     pub fn from_env() -> Self {
-        let enabled = std::env::var("WHITELIST_ENABLED")
-            .unwrap_or_else(|_| "false".to_string())
-            .parse()
-            .unwrap_or(false);
+        Self::from_values(
+            std::env::var("WHITELIST_ENABLED").ok().as_deref(),
+            std::env::var("WHITELIST_IPS").ok().as_deref(),
+        )
+    }
 
-        let allowed_ips = std::env::var("WHITELIST_IPS")
-            .unwrap_or_default()
-            .split(',')
-            .filter_map(|s| s.trim().parse::<IpAddr>().ok())
-            .collect();
+    fn from_values(enabled: Option<&str>, ips: Option<&str>) -> Self {
+        let enabled = enabled.and_then(|value| value.trim().parse().ok()).unwrap_or(false);
+        let (allowed_ips, invalid_entries) = parse_ranges(ips.unwrap_or_default());
 
         debug!("Whitelist enabled: {}", enabled);
-        debug!("Allowed IPs: {:?}", allowed_ips);
+        debug!("Allowed IPs: {:?}", allowed_ips.iter().map(IpRange::to_string).collect::<Vec<_>>());
 
         Self {
             enabled,
             allowed_ips,
+            invalid_entries,
         }
     }
 
@@ -35,24 +140,120 @@ impl WhitelistConfig {
             return true;
         }
 
-        if ip.is_loopback() {
+        if ip.to_canonical().is_loopback() {
             return true;
         }
 
-        self.allowed_ips.contains(ip)
+        self.allowed_ips.iter().any(|range| range.contains(ip))
+    }
+}
+
+/// The ranges in `list`, and the entries that aren't ranges.
+fn parse_ranges(list: &str) -> (Vec<IpRange>, Vec<String>) {
+    let mut ranges = Vec::new();
+    let mut invalid = Vec::new();
+    for entry in list.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        match entry.parse::<IpRange>() {
+            Ok(range) => ranges.push(range),
+            Err(err) => {
+                warn!("Ignoring WHITELIST_IPS entry: {}", err);
+                invalid.push(entry.to_string());
+            }
+        }
+    }
+    (ranges, invalid)
+}
+
+/// The whitelist in effect, loaded at startup and replaced by
+/// `POST /admin/whitelist/reload`. Clones share it, so a reload reaches
+/// every router at once.
+#[derive(Clone)]
+pub struct Whitelist {
+    current: Arc<RwLock<LoadedWhitelist>>,
+}
+
+#[derive(Clone)]
+struct LoadedWhitelist {
+    config: Arc<WhitelistConfig>,
+    loaded_at: DateTime<Utc>,
+}
+
+impl Whitelist {
+    pub fn new(config: WhitelistConfig) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(LoadedWhitelist { config: Arc::new(config), loaded_at: Utc::now() })),
+        }
+    }
+
+    pub fn config(&self) -> Arc<WhitelistConfig> {
+        self.current.read().expect("whitelist lock poisoned").config.clone()
+    }
+
+    /// When the config in effect was loaded.
+    pub fn loaded_at(&self) -> DateTime<Utc> {
+        self.current.read().expect("whitelist lock poisoned").loaded_at
+    }
+
+    pub fn replace(&self, config: WhitelistConfig) -> Arc<WhitelistConfig> {
+        let config = Arc::new(config);
+        *self.current.write().expect("whitelist lock poisoned") =
+            LoadedWhitelist { config: config.clone(), loaded_at: Utc::now() };
+        config
+    }
+
+    /// Reads `WHITELIST_ENABLED` and `WHITELIST_IPS` again. As at startup,
+    /// the process environment comes first and the `.env` file fills in
+    /// what it doesn't set, so only changes to `.env` can take effect. The
+    /// config stays as it was when `.env` can't be read.
+    pub fn reload(&self) -> anyhow::Result<Arc<WhitelistConfig>> {
+        let (process_enabled, process_ips) = PROCESS_ENV
+            .get()
+            .cloned()
+            .unwrap_or_else(|| (std::env::var("WHITELIST_ENABLED").ok(), std::env::var("WHITELIST_IPS").ok()));
+        let (mut enabled, mut ips) = (None, None);
+        match dotenvy::dotenv_iter() {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry? {
+                        (key, value) if key == "WHITELIST_ENABLED" => enabled = Some(value),
+                        (key, value) if key == "WHITELIST_IPS" => ips = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+            Err(err) if err.not_found() => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        let enabled = process_enabled.or(enabled);
+        let ips = process_ips.or(ips);
+        Ok(self.replace(WhitelistConfig::from_values(enabled.as_deref(), ips.as_deref())))
+    }
+}
+
+impl Default for Whitelist {
+    /// Disabled: every client is let through.
+    fn default() -> Self {
+        Self::new(WhitelistConfig::default())
     }
 }
 
 pub async fn whitelist_middleware(
+    State(whitelist): State<Whitelist>,
     request: Request,
     next: Next,
-) -> Result<Response, (StatusCode, String)> {
-    let config = WhitelistConfig::from_env();
+) -> Result<Response, ApiError> {
+    let config = whitelist.config();
 
     if !config.enabled {
         return Ok(next.run(request).await);
     }
 
+    // Portal links are handed to external requesters; the token is the credential.
+    if request.uri().path().starts_with("/portal/") {
+        return Ok(next.run(request).await);
+    }
+
     let ip = extract_client_ip(&request);
 
     match ip {
@@ -62,18 +263,12 @@ pub async fn whitelist_middleware(
                 Ok(next.run(request).await)
             } else {
                 warn!("Blocked request from unauthorized IP: {}", client_ip);
-                Err((
-                    StatusCode::FORBIDDEN,
-                    format!("Access denied from IP: {}", client_ip),
-                ))
+                Err(ApiError::from_code(ErrorCode::IpNotAllowed, format!("Access denied from IP: {}", client_ip)))
             }
         }
         None => {
             warn!("Could not extract client IP from request");
-            Err((
-                StatusCode::FORBIDDEN,
-                "Could not determine client IP".to_string(),
-            ))
+            Err(ApiError::from_code(ErrorCode::IpNotAllowed, "Could not determine client IP"))
         }
     }
 }
@@ -102,3 +297,52 @@ fn extract_client_ip(request: &Request) -> Option<IpAddr> {
         .get::<std::net::SocketAddr>()
         .map(|addr| addr.ip())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ranges() {
+        let private: IpRange = "10.0.0.0/8".parse().unwrap();
+        assert!(private.contains(&ip("10.255.0.1")));
+        assert!(!private.contains(&ip("11.0.0.1")));
+        assert!(private.contains(&ip("::ffff:10.1.2.3")));
+        assert!(!private.contains(&ip("2001:db8::1")));
+
+        let single: IpRange = "192.168.1.100".parse().unwrap();
+        assert!(single.contains(&ip("192.168.1.100")));
+        assert!(!single.contains(&ip("192.168.1.101")));
+
+        let v6: IpRange = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(&ip("2001:db8:ffff::1")));
+        assert!(!v6.contains(&ip("2001:db9::1")));
+
+        assert_eq!("10.1.2.3/8".parse::<IpRange>().unwrap().to_string(), "10.0.0.0/8");
+        assert_eq!("192.168.1.100/32".parse::<IpRange>().unwrap().to_string(), "192.168.1.100");
+        assert!("0.0.0.0/0".parse::<IpRange>().unwrap().contains(&ip("203.0.113.9")));
+        assert!("::/0".parse::<IpRange>().unwrap().contains(&ip("2001:db8::1")));
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("10.0.0/8".parse::<IpRange>().is_err());
+        assert!("10.0.0.0/x".parse::<IpRange>().is_err());
+    }
+
+    #[test]
+    fn test_allowed_ips() {
+        let config = WhitelistConfig::from_values(Some("true"), Some(" 10.0.0.0/8, 192.168.1.0/24,nonsense,, fd00::/8 "));
+        assert_eq!(config.allowed_ips.len(), 3);
+        assert_eq!(config.invalid_entries, vec!["nonsense".to_string()]);
+
+        assert!(config.is_allowed(&ip("10.20.30.40")));
+        assert!(config.is_allowed(&ip("192.168.1.7")));
+        assert!(config.is_allowed(&ip("fd12::1")));
+        assert!(config.is_allowed(&ip("::1")));
+        assert!(config.is_allowed(&ip("::ffff:127.0.0.1")));
+        assert!(!config.is_allowed(&ip("192.168.2.7")));
+        assert!(!config.is_allowed(&ip("2001:db8::1")));
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// A file uploaded to a case. Its content is in object storage under
+/// `storage_key`, which is never shown.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CaseAttachment {
+    pub id: Uuid,
+    pub case_id: Uuid,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub size_bytes: i64,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub uploaded_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query string of `POST /cases/{id}/attachments`; the file is the body.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UploadAttachment {
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1 and 255 characters"),
+        custom(function = "crate::models::validation::validate_filename")
+    )]
+    pub filename: String,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub uploaded_by: Option<String>,
+}
+
+impl CaseAttachment {
+    pub fn new(case_id: Uuid, upload: UploadAttachment, content_type: Option<String>) -> Self {
+        let id = Uuid::new_v4();
+        Self {
+            id,
+            case_id,
+            filename: upload.filename,
+            content_type,
+            size_bytes: 0,
+            storage_key: format!("cases/{}/attachments/{}", case_id, id),
+            uploaded_by: upload.uploaded_by,
+            created_at: Utc::now(),
+        }
+    }
+}
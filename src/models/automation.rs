@@ -1,15 +1,200 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
 use std::collections::HashMap;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::case::CaseStatus;
+use super::message::{CaseMessage, MessageChannel};
+use crate::services::clock::Clock;
 
 #[derive(Debug, Clone)]
 pub enum CaseModification {
     MoveToPhase { phase: String },
-    SetField { field: String, value: serde_json::Value },
+    SetField {
+        field: String,
+        value: serde_json::Value,
+        /// Id or name of the action that produced the write, if it has one.
+        action: Option<String>,
+    },
+    SetStatus { status: CaseStatus },
+    AddTag { tag: String },
+    RecordMessage(CaseMessage),
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct AutomationResult {
     pub modifications: Vec<CaseModification>,
+    pub actions_executed: u32,
+    pub total_delay_ms: u64,
+    pub deferred: Option<DeferredActions>,
+}
+
+/// Actions left over when a run hits a delay too long to wait for inline;
+/// they are persisted and picked up by the scheduler at `resume_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredActions {
+    pub resume_at: DateTime<Utc>,
+    pub actions: Vec<AutomationAction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DeferredAutomation {
+    pub id: Uuid,
+    pub case_id: Uuid,
+    pub workflow_id: Uuid,
+    pub trigger: String,
+    pub phase: String,
+    pub from_phase: Option<String>,
+
+    #[sqlx(json)]
+    pub actions: Vec<AutomationAction>,
+
+    pub resume_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+
+    /// Time the automation has spent in delays before these actions, inline
+    /// or deferred. It counts toward `max_total_delay_ms` when they run.
+    #[serde(default)]
+    pub total_delay_ms: i64,
+
+    /// Times a resume worker has claimed it.
+    #[serde(default)]
+    pub attempts: i32,
+}
+
+/// Per-workflow guard rails for a single automation run, so a pathological
+/// definition cannot keep a worker busy indefinitely.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct AutomationLimits {
+    #[serde(default = "default_max_actions_per_run")]
+    #[validate(range(min = 1, max = 1000, message = "must be between 1 and 1000"))]
+    pub max_actions_per_run: u32,
+
+    #[serde(default = "default_max_total_delay_ms")]
+    #[validate(range(max = 2_592_000_000u64, message = "must be at most 2592000000"))]
+    pub max_total_delay_ms: u64,
+
+    #[serde(default = "default_max_webhook_body_bytes")]
+    #[validate(range(min = 1, max = 10_485_760, message = "must be between 1 and 10485760"))]
+    pub max_webhook_body_bytes: usize,
+}
+
+fn default_max_actions_per_run() -> u32 {
+    100
+}
+
+fn default_max_total_delay_ms() -> u64 {
+    300_000
+}
+
+fn default_max_webhook_body_bytes() -> usize {
+    1_048_576
+}
+
+impl Default for AutomationLimits {
+    fn default() -> Self {
+        Self {
+            max_actions_per_run: default_max_actions_per_run(),
+            max_total_delay_ms: default_max_total_delay_ms(),
+            max_webhook_body_bytes: default_max_webhook_body_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "automation_run_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationRunStatus {
+    Completed,
+    Deferred,
+    Failed,
+    LimitExceeded,
+}
+
+/// One entry of the automation run log: a single on_enter/on_exit pass over
+/// a case.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AutomationRun {
+    pub id: Uuid,
+    pub case_id: Uuid,
+    pub workflow_id: Uuid,
+    pub trigger: String,
+    pub phase: String,
+    pub status: AutomationRunStatus,
+    pub actions_executed: i32,
+    pub total_delay_ms: i64,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+
+    /// How each `conditional` reached during the run was decided.
+    #[sqlx(json)]
+    pub conditions: Vec<ConditionTrace>,
+
+    pub from_phase: Option<String>,
+
+    /// The run failed on a timeout, connection error or 5xx/429 response
+    /// and is eligible for an automatic retry.
+    pub transient: bool,
+
+    /// 1 for the original run, incremented on each automatic retry.
+    pub attempt: i32,
+
+    /// The original run this one retries.
+    pub retry_of: Option<Uuid>,
+
+    /// When the retry worker picked the run up.
+    pub retried_at: Option<DateTime<Utc>>,
+
+    /// Service account the run acted as, if the workflow sets `run_as`.
+    pub run_as: Option<String>,
+
+    /// The workflow version the run used.
+    #[serde(default)]
+    pub workflow_version: Option<i32>,
+
+    /// Hash of the workflow definition the run used; see
+    /// [`DefinitionSnapshot`](super::snapshot::DefinitionSnapshot).
+    #[serde(default)]
+    pub definition_hash: Option<String>,
+}
+
+/// One comparison of a condition: the value read from the case and what
+/// comparing it with `expected` produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComparisonTrace {
+    pub field: String,
+    pub operator: String,
+    pub expected: serde_json::Value,
+
+    /// Absent when the field could not be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<serde_json::Value>,
+
+    /// Absent when the comparison failed, see `error`.
+    pub outcome: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A `conditional` action evaluated during a run. Comparisons of AND/OR
+/// conditions stop at the first one that decides the result, so only the
+/// evaluated ones are listed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConditionTrace {
+    /// Position of the action, e.g. `automations[0].actions[1].then[0]`.
+    pub path: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Absent when the condition failed to evaluate.
+    pub result: Option<bool>,
+
+    pub comparisons: Vec<ComparisonTrace>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -23,27 +208,37 @@ pub struct PhaseSla {
     pub hours: u32,
 }
 
+impl WorkflowSlaConfig {
+    /// When a case that entered `phase` at `entered_at` is due to leave it,
+    /// or `None` for phases without an SLA.
+    pub fn due_at(&self, phase: &str, entered_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let sla = self.phase_slas.get(phase)?;
+        Some(entered_at + chrono::Duration::hours(sla.hours as i64))
+    }
+
+    pub fn is_breached(&self, phase: &str, entered_at: DateTime<Utc>, clock: &dyn Clock) -> bool {
+        self.due_at(phase, entered_at).is_some_and(|due| clock.now() > due)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum AutomationTrigger {
     OnEnter,
     OnExit,
+    /// An inbound message was routed to a case that is in the phase.
+    OnReply,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum OnError {
+    #[default]
     Stop,
     Continue,
 }
 
-impl Default for OnError {
-    fn default() -> Self {
-        Self::Stop
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RetryConfig {
     #[serde(default)]
     pub enabled: bool,
@@ -73,7 +268,11 @@ impl Default for RetryConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_opt_out_field() -> String {
+    "data.sms_opt_out".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AutomationAction {
     Webhook {
@@ -139,9 +338,60 @@ pub enum AutomationAction {
 
         value: serde_json::Value,
     },
+
+    /// Completes, fails, pauses or resumes the case, following the same
+    /// rules as the lifecycle endpoints.
+    SetStatus {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+
+        status: CaseStatus,
+    },
+
+    /// Adds `tag` to the case's tags; adding a tag it already has is a no-op.
+    AddTag {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+
+        tag: String,
+    },
+
+    /// Message to the case's customer: SMS or WhatsApp through the Twilio
+    /// Messages API, or email over SMTP with a reply address that routes
+    /// replies back to the case. `to`, `subject` and `body` accept
+    /// `${data.phone}` style placeholders. Cases whose `opt_out_field` is
+    /// true are skipped, and the field is set when Twilio reports that the
+    /// recipient has unsubscribed.
+    SendMessage {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+
+        #[serde(default)]
+        channel: MessageChannel,
+
+        to: String,
+
+        /// Email only; SMS and WhatsApp ignore it.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        subject: Option<String>,
+
+        body: String,
+
+        #[serde(default = "default_opt_out_field")]
+        opt_out_field: String,
+
+        #[serde(default)]
+        on_error: OnError,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Operators a condition can compare with.
+pub const CONDITION_OPERATORS: &[&str] = &["==", "=", "!=", ">", "<", ">=", "<=", "contains"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Condition {
     Simple {
@@ -155,14 +405,14 @@ pub enum Condition {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum LogicalOperator {
     And,
     Or,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SimpleCondition {
     pub field: String,
     #[serde(rename = "op")]
@@ -174,6 +424,7 @@ impl AutomationAction {
     pub fn id(&self) -> Option<&str> {
         match self {
             Self::Webhook { id, .. } => id.as_deref(),
+            Self::SendMessage { id, .. } => id.as_deref(),
             _ => None,
         }
     }
@@ -185,15 +436,25 @@ impl AutomationAction {
             Self::Conditional { name, .. } => name.as_deref(),
             Self::MoveToPhase { name, .. } => name.as_deref(),
             Self::SetField { name, .. } => name.as_deref(),
+            Self::SetStatus { name, .. } => name.as_deref(),
+            Self::AddTag { name, .. } => name.as_deref(),
+            Self::SendMessage { name, .. } => name.as_deref(),
         }
     }
 
     pub fn on_error(&self) -> OnError {
         match self {
             Self::Webhook { on_error, .. } => on_error.clone(),
+            Self::SendMessage { on_error, .. } => on_error.clone(),
             _ => OnError::Continue,
         }
     }
+
+    /// Webhooks, messages and delays, which wait on something other than
+    /// the database.
+    pub fn is_external(&self) -> bool {
+        matches!(self, Self::Webhook { .. } | Self::SendMessage { .. } | Self::Delay { .. })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -209,6 +470,12 @@ pub struct PhaseAutomation {
 pub struct WorkflowAutomations {
     #[serde(default)]
     pub automations: Vec<PhaseAutomation>,
+
+    /// Service account the automations act as. Its name is recorded as
+    /// `triggered_by` in history and in the run log, and its permissions
+    /// limit the phases and statuses the actions may set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<String>,
 }
 
 impl WorkflowAutomations {
@@ -230,6 +497,13 @@ impl WorkflowAutomations {
             .collect()
     }
 
+    pub fn get_on_reply_automations(&self, phase: &str) -> Vec<&PhaseAutomation> {
+        self.automations
+            .iter()
+            .filter(|a| a.trigger == AutomationTrigger::OnReply && a.phase == phase)
+            .collect()
+    }
+
     pub fn get_actions(&self, trigger: AutomationTrigger, phase: &str) -> Vec<&AutomationAction> {
         self.automations
             .iter()
@@ -270,7 +544,16 @@ mod tests {
                         duration_ms: 1000,
                     }],
                 },
+                PhaseAutomation {
+                    trigger: AutomationTrigger::OnReply,
+                    phase: "Qualified".to_string(),
+                    actions: vec![AutomationAction::Delay {
+                        name: None,
+                        duration_ms: 1000,
+                    }],
+                },
             ],
+            run_as: None,
         };
 
         let on_enter = automations.get_on_enter_automations("Qualified");
@@ -278,6 +561,10 @@ mod tests {
 
         let on_exit = automations.get_on_exit_automations("Qualified");
         assert_eq!(on_exit.len(), 1);
+
+        let on_reply = automations.get_on_reply_automations("Qualified");
+        assert_eq!(on_reply.len(), 1);
+        assert!(automations.get_on_reply_automations("Won").is_empty());
     }
 
     #[test]
@@ -447,4 +734,20 @@ mod tests {
         assert_eq!(deserialized.phase_slas.get("Review").unwrap().hours, 24);
         assert_eq!(deserialized.phase_slas.get("Approval").unwrap().hours, 48);
     }
+
+    #[test]
+    fn test_sla_breached_on_virtual_clock() {
+        use crate::services::clock::VirtualClock;
+
+        let config: WorkflowSlaConfig = serde_json::from_value(serde_json::json!({"Lead": {"hours": 4}})).unwrap();
+        let entered_at = Utc::now();
+        let clock = VirtualClock::new(entered_at);
+
+        assert_eq!(config.due_at("Lead", entered_at), Some(entered_at + chrono::Duration::hours(4)));
+        assert!(!config.is_breached("Lead", entered_at, &clock));
+
+        clock.advance(chrono::Duration::hours(4) + chrono::Duration::seconds(1));
+        assert!(config.is_breached("Lead", entered_at, &clock));
+        assert!(!config.is_breached("Won", entered_at, &clock));
+    }
 }
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::case::{Case, CaseStatus};
+
+pub const DEFAULT_ASSIGNEE_FIELD: &str = "assignee";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoardGrouping {
+    #[default]
+    Status,
+    Assignee,
+}
+
+/// `GET /cases/board`: cases from every workflow in columns by status or
+/// assignee. The assignee is read from a top-level `data` field.
+#[derive(Debug, Deserialize)]
+pub struct BoardQuery {
+    #[serde(default)]
+    pub group_by: BoardGrouping,
+    pub assignee: Option<String>,
+    pub assignee_field: Option<String>,
+    pub status: Option<CaseStatus>,
+    /// Cases per column.
+    pub limit: Option<i64>,
+}
+
+impl BoardQuery {
+    pub fn assignee_field(&self) -> &str {
+        self.assignee_field.as_deref().unwrap_or(DEFAULT_ASSIGNEE_FIELD)
+    }
+}
+
+/// A board column: `key` is the status or assignee, `None` for unassigned
+/// cases, and `count` includes cases beyond the ones listed.
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardColumn {
+    pub key: Option<String>,
+    pub count: i64,
+    pub cases: Vec<Case>,
+}
+
+/// Merges columns read from several regions, keeping the `limit` most
+/// recently updated cases of each. Columns come out by key, unassigned last.
+pub fn merge_columns(columns: impl IntoIterator<Item = BoardColumn>, limit: usize) -> Vec<BoardColumn> {
+    let mut merged: BTreeMap<(bool, Option<String>), BoardColumn> = BTreeMap::new();
+
+    for column in columns {
+        let entry = merged
+            .entry((column.key.is_none(), column.key.clone()))
+            .or_insert_with(|| BoardColumn { key: column.key, count: 0, cases: Vec::new() });
+        entry.count += column.count;
+        entry.cases.extend(column.cases);
+    }
+
+    merged
+        .into_values()
+        .map(|mut column| {
+            column.cases.sort_by_key(|case| std::cmp::Reverse(case.updated_at));
+            column.cases.truncate(limit);
+            column
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn column(key: Option<&str>, count: i64, ages: &[i64]) -> BoardColumn {
+        let cases = ages
+            .iter()
+            .map(|age| {
+                let mut case = Case::new(Uuid::new_v4(), "New".to_string(), json!({}), None);
+                case.updated_at = Utc::now() - Duration::hours(*age);
+                case
+            })
+            .collect();
+        BoardColumn { key: key.map(str::to_string), count, cases }
+    }
+
+    #[test]
+    fn test_merge_columns_across_regions() {
+        let merged = merge_columns(
+            vec![
+                column(None, 1, &[1]),
+                column(Some("bob"), 3, &[2, 5]),
+                column(Some("alice"), 1, &[4]),
+                column(Some("bob"), 2, &[1, 3]),
+            ],
+            2,
+        );
+
+        let keys: Vec<_> = merged.iter().map(|column| column.key.as_deref()).collect();
+        assert_eq!(keys, vec![Some("alice"), Some("bob"), None]);
+
+        let bob = &merged[1];
+        assert_eq!((bob.count, bob.cases.len()), (5, 2));
+        assert!(bob.cases[0].updated_at > bob.cases[1].updated_at);
+        assert!(bob.cases[1].updated_at > Utc::now() - Duration::hours(2) - Duration::minutes(1));
+    }
+}
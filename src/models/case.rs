@@ -1,7 +1,14 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use uuid::Uuid;
+use validator::Validate;
+
+use super::conflict::{DataConflictPolicy, FieldWrite};
+use super::workflow::WorkflowSummary;
+use crate::services::clock::{Clock, SystemClock};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Case {
@@ -23,6 +30,112 @@ pub struct Case {
     pub completed_at: Option<DateTime<Utc>>,
 
     pub phase_entered_at: DateTime<Utc>,
+
+    #[serde(default)]
+    pub region: Option<String>,
+
+    /// Set when the case is soft-deleted; such cases are hidden from
+    /// listings and can no longer be moved or updated.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// Who last wrote each top-level `data` field.
+    #[serde(default)]
+    pub field_provenance: BTreeMap<String, FieldProvenance>,
+
+    #[serde(default)]
+    pub comment_count: i32,
+
+    /// Labels for filtering, e.g. `vip`; kept in the order they were added.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// The id an integration knows the case by, unique within its workflow.
+    #[serde(default)]
+    pub external_id: Option<String>,
+
+    /// Incremented on every change to the case; sent back in `If-Match`
+    /// to update only the version the caller saw.
+    #[serde(default)]
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "data_write_source", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ProvenanceSource {
+    Api,
+    Automation,
+}
+
+/// The last write to a `data` field. `actor` is the caller's `triggered_by`
+/// for API writes, or the action's id, name or type for automations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldProvenance {
+    pub source: ProvenanceSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// Automation trigger that ran the action, e.g. `on_enter`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    pub updated_at: DateTime<Utc>,
+    /// The write this one replaced when the two were concurrent; see
+    /// [`DataConflictPolicy`](super::conflict::DataConflictPolicy).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overwrote: Option<Box<FieldProvenance>>,
+}
+
+impl FieldProvenance {
+    pub fn api(actor: Option<String>) -> Self {
+        Self {
+            source: ProvenanceSource::Api,
+            actor,
+            trigger: None,
+            updated_at: Utc::now(),
+            overwrote: None,
+        }
+    }
+
+    pub fn automation(action: Option<String>, trigger: &str) -> Self {
+        Self {
+            source: ProvenanceSource::Automation,
+            actor: action,
+            trigger: Some(trigger.to_string()),
+            updated_at: Utc::now(),
+            overwrote: None,
+        }
+    }
+
+    /// This write, recording that it replaced the concurrent `previous` one.
+    pub fn overwriting(&self, previous: &FieldProvenance) -> Self {
+        Self {
+            overwrote: Some(Box::new(FieldProvenance { overwrote: None, ..previous.clone() })),
+            ..self.clone()
+        }
+    }
+}
+
+/// Updates `provenance` for `data` replacing `previous`: fields that were
+/// added or changed are attributed to `writer`, removed fields are dropped
+/// and unchanged fields keep their last writer.
+pub fn track_data_writes(
+    provenance: &mut BTreeMap<String, FieldProvenance>,
+    previous: &serde_json::Value,
+    data: &serde_json::Value,
+    writer: &FieldProvenance,
+) {
+    let Some(fields) = data.as_object() else {
+        provenance.clear();
+        return;
+    };
+
+    provenance.retain(|field, _| fields.contains_key(field));
+
+    for (field, value) in fields {
+        if previous.get(field) != Some(value) || !provenance.contains_key(field) {
+            provenance.insert(field.clone(), writer.clone());
+        }
+    }
 }
 
 impl<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> for Case {
@@ -41,6 +154,15 @@ impl<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> for Case {
             updated_at: row.try_get("updated_at")?,
             completed_at: row.try_get("completed_at")?,
             phase_entered_at: row.try_get("phase_entered_at")?,
+            region: row.try_get("region")?,
+            deleted_at: row.try_get("deleted_at")?,
+            field_provenance: row
+                .try_get::<sqlx::types::Json<BTreeMap<String, FieldProvenance>>, _>("field_provenance")?
+                .0,
+            comment_count: row.try_get("comment_count")?,
+            tags: row.try_get("tags")?,
+            external_id: row.try_get("external_id")?,
+            version: row.try_get("version")?,
         })
     }
 }
@@ -55,6 +177,22 @@ pub enum CaseStatus {
     Paused,
 }
 
+impl CaseStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Paused => "paused",
+        }
+    }
+
+    /// Active and paused cases count towards phase WIP limits.
+    pub fn is_in_progress(&self) -> bool {
+        matches!(self, Self::Active | Self::Paused)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct CaseHistory {
     pub id: Uuid,
@@ -69,9 +207,27 @@ pub struct CaseHistory {
     pub triggered_by: Option<String>,
 
     pub transitioned_at: DateTime<Utc>,
+
+    /// Set on entries recorded for a status change rather than a move.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_status: Option<CaseStatus>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_status: Option<CaseStatus>,
+
+    /// Set on moves in another workflow the case takes part in; `None` for
+    /// its own workflow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow_id: Option<Uuid>,
+
+    /// Version of the workflow definition the transition ran under; filled
+    /// in by the database when the entry is written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow_version: Option<i32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "crate::models::validation::validate_create_case_upsert"))]
 pub struct CreateCase {
     pub workflow_id: Uuid,
 
@@ -79,32 +235,523 @@ pub struct CreateCase {
 
     pub metadata: Option<serde_json::Value>,
 
+    #[validate(custom(function = "crate::models::validation::validate_phase_reference"))]
     pub initial_phase: Option<String>,
+
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub triggered_by: Option<String>,
+
+    #[serde(default)]
+    #[validate(custom(function = "crate::models::validation::validate_tags"))]
+    pub tags: Vec<String>,
+
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub external_id: Option<String>,
+
+    /// With `external_id`, updates the workflow's case with that id instead
+    /// of rejecting the request when there is one.
+    #[serde(default)]
+    pub upsert: bool,
+}
+
+/// Body of `POST /cases/{id}/tags`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddCaseTags {
+    #[validate(
+        length(min = 1, message = "must not be empty"),
+        custom(function = "crate::models::validation::validate_tags")
+    )]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateCaseData {
     pub data: serde_json::Value,
+
+    /// Recorded as the writer of the fields this update changes.
+    pub triggered_by: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct MoveCase {
+    #[validate(custom(function = "crate::models::validation::validate_phase_reference"))]
     pub to_phase: String,
 
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub reason: Option<String>,
+
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub triggered_by: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct ChangeCaseStatus {
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
     pub reason: Option<String>,
 
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
     pub triggered_by: Option<String>,
 }
 
+/// A status change requested through the case lifecycle endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseLifecycleAction {
+    Complete,
+    Fail,
+    Pause,
+    Resume,
+}
+
+impl CaseLifecycleAction {
+    /// The action that puts a case in `status`.
+    pub fn to(status: &CaseStatus) -> Self {
+        match status {
+            CaseStatus::Active => Self::Resume,
+            CaseStatus::Completed => Self::Complete,
+            CaseStatus::Failed => Self::Fail,
+            CaseStatus::Paused => Self::Pause,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Complete => "complete",
+            Self::Fail => "fail",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+        }
+    }
+
+    /// Name of the event and webhook action fired after the change.
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::Complete => "case.completed",
+            Self::Fail => "case.failed",
+            Self::Pause => "case.paused",
+            Self::Resume => "case.resumed",
+        }
+    }
+
+    /// Completed and failed cases are final; paused cases can only be
+    /// resumed or closed.
+    pub fn allowed_from(self, status: &CaseStatus) -> bool {
+        match self {
+            Self::Complete | Self::Fail => matches!(status, CaseStatus::Active | CaseStatus::Paused),
+            Self::Pause => *status == CaseStatus::Active,
+            Self::Resume => *status == CaseStatus::Paused,
+        }
+    }
+
+    pub fn apply(self, case: &mut Case) {
+        match self {
+            Self::Complete => case.complete(),
+            Self::Fail => case.fail(),
+            Self::Pause => case.pause(),
+            Self::Resume => case.resume(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ListCasesQuery {
     pub workflow_id: Option<Uuid>,
     pub current_phase: Option<String>,
     pub status: Option<CaseStatus>,
+    pub tag: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub include_deleted: bool,
+    #[serde(default)]
+    pub sort: CaseSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub embed: Option<CaseEmbed>,
+    /// A JSON object the case's `data` must contain, e.g. `{"vip":true}`;
+    /// see [`ListCasesQuery::data_containment`].
+    pub data_contains: Option<String>,
+    /// `metadata.*` query string filters; see [`ListCasesQuery::metadata_filters`].
+    #[serde(skip)]
+    pub metadata: Vec<DataFilter>,
+    /// `data_contains` parsed, matched with `@>` against the GIN index on
+    /// `data`.
+    #[serde(skip)]
+    pub containment: Option<serde_json::Value>,
+}
+
+impl ListCasesQuery {
+    /// The `metadata.*` filters among the query string parameters, written
+    /// as for `GET /cases/search`, e.g. `metadata.source=zapier`.
+    pub fn metadata_filters(params: &[(String, String)]) -> Result<Vec<DataFilter>, String> {
+        let mut filters = Vec::new();
+        for (key, value) in params {
+            if let Some(filter) = DataFilter::parse(key, value)? {
+                if filter.column == JsonColumn::Metadata {
+                    filters.push(filter);
+                }
+            }
+        }
+
+        if filters.len() > MAX_SEARCH_FILTERS {
+            return Err(format!("At most {} metadata filters are allowed", MAX_SEARCH_FILTERS));
+        }
+        Ok(filters)
+    }
+
+    /// `data_contains` as a JSON object. Nested objects match at any depth
+    /// and arrays match when they hold every listed element, as with `@>`.
+    pub fn data_containment(&self) -> Result<Option<serde_json::Value>, String> {
+        let Some(text) = &self.data_contains else {
+            return Ok(None);
+        };
+
+        match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(value @ serde_json::Value::Object(_)) => Ok(Some(value)),
+            _ => Err("data_contains must be a JSON object, e.g. {\"vip\":true}".to_string()),
+        }
+    }
+}
+
+/// `?embed=` on `GET /cases`: related records to include with each case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseEmbed {
+    Workflow,
+}
+
+/// A case in a `GET /cases` page, with its workflow's summary when the
+/// list asked for `embed=workflow`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListedCase {
+    #[serde(flatten)]
+    pub case: Case,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<WorkflowSummary>,
+}
+
+/// `?sort=` on `GET /cases`: one of the allowed timestamp columns or a
+/// `data.*` path. Ties go newest first.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CaseSort {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    PhaseEnteredAt,
+    Data(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_sql(self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+impl TryFrom<String> for CaseSort {
+    type Error = String;
+
+    fn try_from(sort: String) -> Result<Self, String> {
+        match sort.as_str() {
+            "created_at" => Ok(Self::CreatedAt),
+            "updated_at" => Ok(Self::UpdatedAt),
+            "phase_entered_at" => Ok(Self::PhaseEnteredAt),
+            other => other.strip_prefix("data.").and_then(parse_json_path).map(Self::Data).ok_or_else(|| {
+                format!(
+                    "Invalid sort '{}': expected created_at, updated_at, phase_entered_at or data.<field>",
+                    sort
+                )
+            }),
+        }
+    }
+}
+
+impl CaseSort {
+    /// Orders two cases the way the SQL query does, for merging pages from
+    /// several regions: cases without the `data` field come last, and JSON
+    /// values of different types order as Postgres orders `jsonb`.
+    pub fn compare(&self, order: SortOrder, a: &Case, b: &Case) -> Ordering {
+        let ordering = match self {
+            Self::CreatedAt => directed(order, a.created_at.cmp(&b.created_at)),
+            Self::UpdatedAt => directed(order, a.updated_at.cmp(&b.updated_at)),
+            Self::PhaseEnteredAt => directed(order, a.phase_entered_at.cmp(&b.phase_entered_at)),
+            Self::Data(path) => {
+                let value = |case: &'_ Case| path.iter().try_fold(&case.data, |value, key| value.get(key)).cloned();
+                match (value(a), value(b)) {
+                    (Some(a), Some(b)) => directed(order, compare_json(&a, &b)),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            }
+        };
+        ordering.then(b.created_at.cmp(&a.created_at)).then(a.id.cmp(&b.id))
+    }
+}
+
+fn directed(order: SortOrder, ordering: Ordering) -> Ordering {
+    match order {
+        SortOrder::Asc => ordering,
+        SortOrder::Desc => ordering.reverse(),
+    }
+}
+
+/// `jsonb` ordering for scalars: null < string < number < boolean < array
+/// < object. Arrays and objects are not compared further.
+fn compare_json(a: &serde_json::Value, b: &serde_json::Value) -> Ordering {
+    use serde_json::Value;
+
+    let rank = |value: &Value| match value {
+        Value::Null => 0,
+        Value::String(_) => 1,
+        Value::Number(_) => 2,
+        Value::Bool(_) => 3,
+        Value::Array(_) => 4,
+        Value::Object(_) => 5,
+    };
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => {
+            a.as_f64().unwrap_or_default().total_cmp(&b.as_f64().unwrap_or_default())
+        }
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// A dotted JSON path such as `customer.tier`, if every key is made of
+/// letters, digits, `_` and `-`.
+fn parse_json_path(path: &str) -> Option<Vec<String>> {
+    let path: Vec<String> = path.split('.').map(str::to_string).collect();
+    let valid_segment = |segment: &String| {
+        !segment.is_empty() && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+    };
+    (path.len() <= MAX_SEARCH_PATH_DEPTH && path.iter().all(valid_segment)).then_some(path)
+}
+
+/// `GET /search/cases`: free text matched against every workflow's cases.
+#[derive(Debug, Deserialize)]
+pub struct GlobalSearchQuery {
+    #[serde(default)]
+    pub q: String,
+    pub status: Option<CaseStatus>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub const MAX_SEARCH_TEXT_LENGTH: usize = 200;
+
+impl GlobalSearchQuery {
+    /// The trimmed text as an `ILIKE` pattern matching it anywhere.
+    pub fn pattern(&self) -> Result<String, String> {
+        let text = self.q.trim();
+        if text.is_empty() || text.chars().count() > MAX_SEARCH_TEXT_LENGTH {
+            return Err(format!("q must be between 1 and {} characters", MAX_SEARCH_TEXT_LENGTH));
+        }
+
+        let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        Ok(format!("%{}%", escaped))
+    }
+}
+
+/// `GET /cases/by-external-id/{id}?workflow_id=...`.
+#[derive(Debug, Deserialize)]
+pub struct ExternalIdQuery {
+    pub workflow_id: Uuid,
+}
+
+/// `?include_deleted=true` on single-case reads.
+#[derive(Debug, Default, Deserialize)]
+pub struct IncludeDeletedQuery {
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+pub const MAX_SEARCH_FILTERS: usize = 20;
+const MAX_SEARCH_PATH_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Contains,
+    Exists,
+}
+
+impl DataFilterOp {
+    fn parse(op: &str) -> Option<Self> {
+        Some(match op {
+            "eq" => Self::Eq,
+            "ne" => Self::Ne,
+            "gt" => Self::Gt,
+            "gte" => Self::Gte,
+            "lt" => Self::Lt,
+            "lte" => Self::Lte,
+            "contains" => Self::Contains,
+            "exists" => Self::Exists,
+            _ => return None,
+        })
+    }
+}
+
+/// The JSON column a [`DataFilter`] looks into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonColumn {
+    Data,
+    Metadata,
+}
+
+impl JsonColumn {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Data => "data",
+            Self::Metadata => "metadata",
+        }
+    }
+}
+
+/// A condition on one `data` or `metadata` path, written in the query
+/// string as `data.customer.tier=gold`, `data.amount[gte]=1000` or
+/// `metadata.source=zapier`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFilter {
+    pub column: JsonColumn,
+    pub path: Vec<String>,
+    pub op: DataFilterOp,
+    pub value: String,
+}
+
+impl DataFilter {
+    /// Returns `Ok(None)` for parameters that are not `data.*` or
+    /// `metadata.*` filters.
+    pub fn parse(key: &str, value: &str) -> Result<Option<Self>, String> {
+        let (column, filter) = match (key.strip_prefix("data."), key.strip_prefix("metadata.")) {
+            (Some(filter), _) => (JsonColumn::Data, filter),
+            (_, Some(filter)) => (JsonColumn::Metadata, filter),
+            _ => return Ok(None),
+        };
+
+        let (path, op) = match filter.strip_suffix(']').and_then(|f| f.split_once('[')) {
+            Some((path, op)) => (
+                path,
+                DataFilterOp::parse(op).ok_or_else(|| format!("Unsupported operator '{}' in '{}'", op, key))?,
+            ),
+            None => (filter, DataFilterOp::Eq),
+        };
+
+        let path = parse_json_path(path).ok_or_else(|| format!("Invalid {} path in '{}'", column.as_str(), key))?;
+
+        if op == DataFilterOp::Exists && !matches!(value, "true" | "false") {
+            return Err(format!("'{}' must be true or false", key));
+        }
+
+        Ok(Some(Self {
+            column,
+            path,
+            op,
+            value: value.to_string(),
+        }))
+    }
+
+    /// The values an equality filter accepts: the string itself and, when it
+    /// reads as a number, boolean or null, that JSON value too, each nested
+    /// under the filter's path so it can be matched with `@>`.
+    pub fn containment_candidates(&self) -> Vec<serde_json::Value> {
+        let mut values = vec![serde_json::Value::String(self.value.clone())];
+        if let Ok(typed) = serde_json::from_str::<serde_json::Value>(&self.value) {
+            if matches!(typed, serde_json::Value::Number(_) | serde_json::Value::Bool(_) | serde_json::Value::Null) {
+                values.push(typed);
+            }
+        }
+
+        values
+            .into_iter()
+            .map(|value| {
+                self.path
+                    .iter()
+                    .rev()
+                    .fold(value, |inner, key| serde_json::json!({ key: inner }))
+            })
+            .collect()
+    }
+
+    /// Whether an ordering filter compares numbers; otherwise it compares
+    /// strings, which also covers ISO 8601 dates.
+    pub fn is_numeric(&self) -> bool {
+        self.value.trim().parse::<f64>().is_ok_and(f64::is_finite)
+    }
+}
+
+/// Parsed `GET /cases/search` query. Besides the `data.*` filters it takes
+/// the same `workflow_id`, `current_phase`, `status`, `tag`, `limit` and
+/// `offset` parameters as `GET /cases`; `tag` may repeat, and cases must
+/// have every tag given.
+#[derive(Debug, Default)]
+pub struct CaseSearch {
+    pub workflow_id: Option<Uuid>,
+    pub current_phase: Option<String>,
+    pub status: Option<CaseStatus>,
+    pub tags: Vec<String>,
+    pub filters: Vec<DataFilter>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
 }
 
+impl CaseSearch {
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, String> {
+        let mut search = Self::default();
+
+        for (key, value) in params {
+            match key.as_str() {
+                "workflow_id" => {
+                    search.workflow_id = Some(value.parse().map_err(|_| format!("Invalid workflow_id '{}'", value))?)
+                }
+                "current_phase" => search.current_phase = Some(value.clone()),
+                "status" => {
+                    search.status = Some(
+                        serde_json::from_value(serde_json::Value::String(value.clone()))
+                            .map_err(|_| format!("Invalid status '{}'", value))?,
+                    )
+                }
+                "tag" => search.tags.push(value.clone()),
+                "limit" => search.limit = Some(value.parse().map_err(|_| format!("Invalid limit '{}'", value))?),
+                "offset" => search.offset = Some(value.parse().map_err(|_| format!("Invalid offset '{}'", value))?),
+                _ => {
+                    if let Some(filter) = DataFilter::parse(key, value)? {
+                        search.filters.push(filter);
+                    }
+                }
+            }
+        }
+
+        if search.filters.len() > MAX_SEARCH_FILTERS {
+            return Err(format!("At most {} data filters are allowed", MAX_SEARCH_FILTERS));
+        }
+
+        Ok(search)
+    }
+}
+
+/// `?triggered_by=` on data updates, for patch bodies that have no room for it.
+#[derive(Debug, Default, Deserialize)]
+pub struct TriggeredByQuery {
+    pub triggered_by: Option<String>,
+}
+
 impl Case {
     pub fn new(
         workflow_id: Uuid,
@@ -112,7 +759,18 @@ impl Case {
         data: serde_json::Value,
         metadata: Option<serde_json::Value>,
     ) -> Self {
-        let now = Utc::now();
+        Self::with_clock(workflow_id, initial_phase, data, metadata, &SystemClock)
+    }
+
+    /// A new case stamped with `clock`'s time instead of the wall clock.
+    pub fn with_clock(
+        workflow_id: Uuid,
+        initial_phase: String,
+        data: serde_json::Value,
+        metadata: Option<serde_json::Value>,
+        clock: &dyn Clock,
+    ) -> Self {
+        let now = clock.now();
         Self {
             id: Uuid::new_v4(),
             workflow_id,
@@ -125,6 +783,13 @@ impl Case {
             updated_at: now,
             completed_at: None,
             phase_entered_at: now,
+            region: None,
+            deleted_at: None,
+            field_provenance: BTreeMap::new(),
+            comment_count: 0,
+            tags: Vec::new(),
+            external_id: None,
+            version: 1,
         }
     }
 
@@ -146,6 +811,52 @@ impl Case {
         self.updated_at = Utc::now();
     }
 
+    /// Writes a top-level `data` field under `policy` and records `writer`
+    /// as its provenance; see [`DataConflictPolicy::resolve`].
+    pub fn write_field(
+        &mut self,
+        field: &str,
+        value: &serde_json::Value,
+        writer: &FieldProvenance,
+        policy: &DataConflictPolicy,
+    ) -> FieldWrite {
+        let last = self.field_provenance.get(field).cloned();
+        let write = policy.resolve(field, last.as_ref(), self.data.get(field), value.clone(), writer);
+        let Some(resolved) = write.value() else {
+            return write;
+        };
+
+        let provenance = match &last {
+            Some(last) if write.replaced_concurrent() => writer.overwriting(last),
+            _ => writer.clone(),
+        };
+        if let Some(data) = self.data.as_object_mut() {
+            data.insert(field.to_string(), resolved.clone());
+        }
+        self.field_provenance.insert(field.to_string(), provenance);
+        self.updated_at = Utc::now();
+        write
+    }
+
+    /// Adds `tag` unless the case already has it or has `max_tags` tags.
+    pub fn add_tag(&mut self, tag: &str, max_tags: usize) -> bool {
+        if self.tags.iter().any(|existing| existing == tag) || self.tags.len() >= max_tags {
+            return false;
+        }
+        self.tags.push(tag.to_string());
+        self.updated_at = Utc::now();
+        true
+    }
+
+    /// Appends the tags the case does not have yet.
+    pub fn add_tags(&mut self, tags: impl IntoIterator<Item = String>) {
+        for tag in tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+    }
+
     pub fn complete(&mut self) {
         self.status = CaseStatus::Completed;
         self.completed_at = Some(Utc::now());
@@ -157,6 +868,17 @@ impl Case {
         self.completed_at = Some(Utc::now());
         self.updated_at = Utc::now();
     }
+
+    pub fn pause(&mut self) {
+        self.status = CaseStatus::Paused;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn resume(&mut self) {
+        self.status = CaseStatus::Active;
+        self.completed_at = None;
+        self.updated_at = Utc::now();
+    }
 }
 
 impl CaseHistory {
@@ -175,6 +897,161 @@ impl CaseHistory {
             reason,
             triggered_by,
             transitioned_at: Utc::now(),
+            from_status: None,
+            to_status: None,
+            workflow_id: None,
+            workflow_version: None,
         }
     }
+
+    /// An entry for a status change. The case stays in its phase, so both
+    /// phase columns hold the current one.
+    pub fn status_change(
+        case: &Case,
+        from_status: CaseStatus,
+        reason: Option<String>,
+        triggered_by: Option<String>,
+    ) -> Self {
+        Self {
+            from_status: Some(from_status),
+            to_status: Some(case.status.clone()),
+            ..Self::new(
+                case.id,
+                Some(case.current_phase.clone()),
+                case.current_phase.clone(),
+                reason,
+                triggered_by,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_data_filter_parsing() {
+        let filter = DataFilter::parse("data.customer.tier", "gold").unwrap().unwrap();
+        assert_eq!(filter.path, vec!["customer", "tier"]);
+        assert_eq!(filter.op, DataFilterOp::Eq);
+        assert_eq!(filter.containment_candidates(), vec![json!({"customer": {"tier": "gold"}})]);
+
+        let filter = DataFilter::parse("data.amount[gte]", "1000").unwrap().unwrap();
+        assert_eq!(filter.op, DataFilterOp::Gte);
+        assert!(filter.is_numeric());
+        assert_eq!(
+            filter.containment_candidates(),
+            vec![json!({"amount": "1000"}), json!({"amount": 1000})]
+        );
+
+        assert_eq!(DataFilter::parse("workflow_id", "x"), Ok(None));
+        assert!(DataFilter::parse("data.amount[between]", "1").is_err());
+        assert!(DataFilter::parse("data.a..b", "1").is_err());
+        assert!(DataFilter::parse("data.a'b", "1").is_err());
+        assert!(DataFilter::parse("data.flag[exists]", "yes").is_err());
+
+        let filter = DataFilter::parse("metadata.source", "zapier").unwrap().unwrap();
+        assert_eq!((filter.column, filter.path.as_slice()), (JsonColumn::Metadata, ["source".to_string()].as_slice()));
+        assert!(DataFilter::parse("metadata.", "x").is_err());
+    }
+
+    #[test]
+    fn test_case_sort() {
+        assert_eq!(CaseSort::try_from("updated_at".to_string()), Ok(CaseSort::UpdatedAt));
+        assert_eq!(
+            CaseSort::try_from("data.customer.tier".to_string()),
+            Ok(CaseSort::Data(vec!["customer".to_string(), "tier".to_string()]))
+        );
+        assert!(CaseSort::try_from("priority".to_string()).is_err());
+        assert!(CaseSort::try_from("data.".to_string()).is_err());
+
+        let workflow_id = Uuid::new_v4();
+        let case = |data| Case::new(workflow_id, "New".to_string(), data, None);
+        let (small, large, missing, text) =
+            (case(json!({"amount": 5})), case(json!({"amount": 20})), case(json!({})), case(json!({"amount": "x"})));
+        let sort = CaseSort::Data(vec!["amount".to_string()]);
+        assert_eq!(sort.compare(SortOrder::Asc, &small, &large), Ordering::Less);
+        assert_eq!(sort.compare(SortOrder::Desc, &small, &large), Ordering::Greater);
+        assert_eq!(sort.compare(SortOrder::Asc, &missing, &small), Ordering::Greater);
+        assert_eq!(sort.compare(SortOrder::Desc, &missing, &small), Ordering::Greater);
+        assert_eq!(sort.compare(SortOrder::Asc, &text, &small), Ordering::Less);
+    }
+
+    #[test]
+    fn test_list_cases_metadata_filters() {
+        let params = vec![
+            ("workflow_id".to_string(), Uuid::new_v4().to_string()),
+            ("data.amount".to_string(), "10".to_string()),
+            ("metadata.region".to_string(), "eu".to_string()),
+        ];
+        let filters = ListCasesQuery::metadata_filters(&params).unwrap();
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].path, vec!["region"]);
+
+        assert!(ListCasesQuery::metadata_filters(&[("metadata.a[between]".to_string(), "1".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_list_cases_data_containment() {
+        let query = |params: serde_json::Value| serde_json::from_value::<ListCasesQuery>(params).unwrap();
+        let data_contains = |text: &str| query(json!({"data_contains": text}));
+
+        assert_eq!(data_contains(r#"{"vip":true}"#).data_containment().unwrap(), Some(json!({"vip": true})));
+        assert_eq!(query(json!({})).data_containment().unwrap(), None);
+        assert!(data_contains("[1]").data_containment().is_err());
+        assert!(data_contains("{vip").data_containment().is_err());
+    }
+
+    #[test]
+    fn test_case_search_params() {
+        let params = vec![
+            ("status".to_string(), "active".to_string()),
+            ("data.customer.tier".to_string(), "gold".to_string()),
+            ("limit".to_string(), "10".to_string()),
+            ("envelope".to_string(), "true".to_string()),
+        ];
+        let search = CaseSearch::from_params(&params).unwrap();
+        assert_eq!(search.status, Some(CaseStatus::Active));
+        assert_eq!(search.filters.len(), 1);
+        assert_eq!(search.limit, Some(10));
+
+        assert!(CaseSearch::from_params(&[("status".to_string(), "bogus".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_track_data_writes() {
+        let mut provenance = BTreeMap::new();
+        let api = FieldProvenance::api(Some("alice".to_string()));
+        track_data_writes(&mut provenance, &serde_json::Value::Null, &json!({"a": 1, "b": 2}), &api);
+        assert_eq!(provenance.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+
+        let automation = FieldProvenance::automation(Some("score".to_string()), "on_enter");
+        track_data_writes(&mut provenance, &json!({"a": 1, "b": 2}), &json!({"a": 1, "c": 3}), &automation);
+        assert_eq!(provenance.keys().collect::<Vec<_>>(), vec!["a", "c"]);
+        assert_eq!(provenance["a"].actor.as_deref(), Some("alice"));
+        assert_eq!(provenance["c"].source, ProvenanceSource::Automation);
+        assert_eq!(provenance["c"].trigger.as_deref(), Some("on_enter"));
+    }
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        let mut case = Case::new(Uuid::new_v4(), "New".to_string(), json!({}), None);
+        assert!(!CaseLifecycleAction::Resume.allowed_from(&case.status));
+
+        CaseLifecycleAction::Pause.apply(&mut case);
+        assert_eq!(case.status, CaseStatus::Paused);
+        assert!(!CaseLifecycleAction::Pause.allowed_from(&case.status));
+
+        CaseLifecycleAction::Complete.apply(&mut case);
+        assert!(case.completed_at.is_some());
+        for action in [CaseLifecycleAction::Complete, CaseLifecycleAction::Fail, CaseLifecycleAction::Pause, CaseLifecycleAction::Resume] {
+            assert!(!action.allowed_from(&CaseStatus::Completed));
+        }
+
+        let history = CaseHistory::status_change(&case, CaseStatus::Paused, None, None);
+        assert_eq!(history.to_status, Some(CaseStatus::Completed));
+        assert_eq!(history.from_phase.as_deref(), Some("New"));
+    }
 }
@@ -0,0 +1,93 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+pub const DEFAULT_CHANGES_LIMIT: i64 = 100;
+pub const MAX_CHANGES_LIMIT: i64 = 1000;
+pub const DEFAULT_CHANGES_WAIT_SECS: u64 = 25;
+pub const MAX_CHANGES_WAIT_SECS: u64 = 60;
+
+/// A case or execution that was created, updated or deleted.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Change {
+    /// Pass as `since` to get the changes after this one.
+    pub cursor: String,
+    /// `case` or `execution`.
+    pub entity: String,
+    pub entity_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_id: Option<Uuid>,
+    /// `created`, `updated` or `deleted`.
+    pub operation: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// A position in the change log: the writing transaction and the row,
+/// written as `"<xact_id>-<id>"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChangeCursor {
+    pub xact_id: i64,
+    pub id: i64,
+}
+
+impl fmt::Display for ChangeCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.xact_id, self.id)
+    }
+}
+
+impl FromStr for ChangeCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not a change cursor", s);
+        let (xact_id, id) = s.split_once('-').ok_or_else(invalid)?;
+
+        Ok(Self {
+            xact_id: xact_id.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Query of `GET /changes`.
+#[derive(Debug, Deserialize)]
+pub struct ChangesQuery {
+    /// Without it, the response only carries the current cursor.
+    pub since: Option<String>,
+    pub limit: Option<i64>,
+    /// How long to wait for a change before answering with none.
+    pub wait_secs: Option<u64>,
+}
+
+impl ChangesQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_CHANGES_LIMIT).clamp(1, MAX_CHANGES_LIMIT)
+    }
+
+    pub fn wait(&self) -> Duration {
+        Duration::from_secs(self.wait_secs.unwrap_or(DEFAULT_CHANGES_WAIT_SECS).min(MAX_CHANGES_WAIT_SECS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_cursor_round_trip() {
+        let cursor = ChangeCursor { xact_id: 7412, id: 98 };
+        assert_eq!(cursor.to_string(), "7412-98");
+        assert_eq!("7412-98".parse::<ChangeCursor>(), Ok(cursor));
+        assert!("7412".parse::<ChangeCursor>().is_err());
+        assert!("a-1".parse::<ChangeCursor>().is_err());
+        assert!(ChangeCursor { xact_id: 7, id: 100 } < ChangeCursor { xact_id: 8, id: 1 });
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// A note left on a case by someone working it. Unlike messages, comments
+/// are internal and never shown to the requester.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CaseComment {
+    pub id: Uuid,
+    pub case_id: Uuid,
+    pub author: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCaseComment {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub author: String,
+
+    #[validate(length(min = 1, max = 10000, message = "must be between 1 and 10000 characters"))]
+    pub body: String,
+}
+
+impl CaseComment {
+    pub fn new(case_id: Uuid, payload: CreateCaseComment) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            case_id,
+            author: payload.author,
+            body: payload.body,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use validator::Validate;
+
+use super::case::FieldProvenance;
+
+/// What happens when a data field is written by someone else shortly after
+/// another writer changed it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// The later write applies and its provenance records the one it
+    /// overwrote.
+    #[default]
+    LastWriteWins,
+    /// The later write is refused.
+    Reject,
+    /// Arrays are combined, keeping existing items first; other values
+    /// fall back to last-write-wins.
+    MergeArrays,
+}
+
+/// A workflow's `data_conflicts` setting, applied to writes to its cases'
+/// top-level `data` fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct DataConflictPolicy {
+    #[serde(default)]
+    pub strategy: ConflictStrategy,
+
+    /// Writes by different writers this close together are concurrent.
+    #[serde(default = "default_window_secs")]
+    #[validate(range(max = 3600, message = "must be at most 3600"))]
+    pub window_secs: u64,
+
+    /// Per-field strategies overriding `strategy`.
+    #[serde(default)]
+    pub fields: BTreeMap<String, ConflictStrategy>,
+}
+
+fn default_window_secs() -> u64 {
+    5
+}
+
+impl Default for DataConflictPolicy {
+    fn default() -> Self {
+        Self {
+            strategy: ConflictStrategy::default(),
+            window_secs: default_window_secs(),
+            fields: BTreeMap::new(),
+        }
+    }
+}
+
+/// How a write to one field is carried out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldWrite {
+    /// No concurrent write: the value applies as given.
+    Write(Value),
+    /// Applies over a concurrent write.
+    Overwrite(Value),
+    /// The concurrent write's array combined with this one.
+    Merged(Value),
+    Rejected,
+}
+
+impl FieldWrite {
+    /// The value to store, unless the write was rejected.
+    pub fn value(&self) -> Option<&Value> {
+        match self {
+            Self::Write(value) | Self::Overwrite(value) | Self::Merged(value) => Some(value),
+            Self::Rejected => None,
+        }
+    }
+
+    /// Whether the write landed on top of a concurrent one.
+    pub fn replaced_concurrent(&self) -> bool {
+        matches!(self, Self::Overwrite(_) | Self::Merged(_))
+    }
+}
+
+impl DataConflictPolicy {
+    pub fn strategy_for(&self, field: &str) -> ConflictStrategy {
+        self.fields.get(field).copied().unwrap_or(self.strategy)
+    }
+
+    /// Whether `writer` follows another writer's `last` write within the window.
+    pub fn is_concurrent(&self, last: &FieldProvenance, writer: &FieldProvenance) -> bool {
+        let same_writer = (last.source, &last.actor, &last.trigger) == (writer.source, &writer.actor, &writer.trigger);
+        let elapsed = (writer.updated_at - last.updated_at).num_milliseconds().abs();
+
+        !same_writer && elapsed < (self.window_secs as i64).saturating_mul(1000)
+    }
+
+    /// Decides how `writer` writes `value` over `current`, where `last` is
+    /// the provenance of the top-level `field` being written. Writing the
+    /// value a field already has is never a conflict.
+    pub fn resolve(
+        &self,
+        field: &str,
+        last: Option<&FieldProvenance>,
+        current: Option<&Value>,
+        value: Value,
+        writer: &FieldProvenance,
+    ) -> FieldWrite {
+        let concurrent = current != Some(&value) && last.is_some_and(|last| self.is_concurrent(last, writer));
+        if !concurrent {
+            return FieldWrite::Write(value);
+        }
+
+        match (self.strategy_for(field), current, value) {
+            (ConflictStrategy::Reject, _, _) => FieldWrite::Rejected,
+            (ConflictStrategy::MergeArrays, Some(Value::Array(existing)), Value::Array(items)) => {
+                let mut merged = existing.clone();
+                for item in items {
+                    if !merged.contains(&item) {
+                        merged.push(item);
+                    }
+                }
+                FieldWrite::Merged(Value::Array(merged))
+            }
+            (_, _, value) => FieldWrite::Overwrite(value),
+        }
+    }
+
+    /// Applies the policy to a whole new `data` replacing `previous`, field
+    /// by field. Returns the data to store and the provenance of each field
+    /// written over a concurrent write, or the fields that were rejected.
+    pub fn resolve_data(
+        &self,
+        provenance: &BTreeMap<String, FieldProvenance>,
+        previous: &Value,
+        data: Value,
+        writer: &FieldProvenance,
+    ) -> Result<(Value, BTreeMap<String, FieldProvenance>), Vec<String>> {
+        let Some(before) = previous.as_object() else {
+            return Ok((data, BTreeMap::new()));
+        };
+        let fields = match data {
+            Value::Object(fields) => fields,
+            data => return Ok((data, BTreeMap::new())),
+        };
+
+        let mut resolved = serde_json::Map::new();
+        let mut overwritten = BTreeMap::new();
+        let mut rejected = Vec::new();
+
+        for (field, value) in fields {
+            let last = provenance.get(&field);
+            let write = self.resolve(&field, last, before.get(&field), value, writer);
+            if write.replaced_concurrent() {
+                overwritten.extend(last.map(|last| (field.clone(), last.clone())));
+            }
+            match write {
+                FieldWrite::Write(value) | FieldWrite::Overwrite(value) | FieldWrite::Merged(value) => {
+                    resolved.insert(field, value);
+                }
+                FieldWrite::Rejected => rejected.push(field),
+            }
+        }
+
+        let removals_rejected: Vec<String> = before
+            .keys()
+            .filter(|field| !resolved.contains_key(*field) && !rejected.contains(*field))
+            .filter(|field| {
+                self.strategy_for(field) == ConflictStrategy::Reject
+                    && provenance.get(*field).is_some_and(|last| self.is_concurrent(last, writer))
+            })
+            .cloned()
+            .collect();
+        rejected.extend(removals_rejected);
+
+        if rejected.is_empty() {
+            Ok((Value::Object(resolved), overwritten))
+        } else {
+            rejected.sort();
+            Err(rejected)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    fn writer(actor: &str, seconds_ago: i64) -> FieldProvenance {
+        FieldProvenance {
+            updated_at: Utc::now() - Duration::seconds(seconds_ago),
+            ..FieldProvenance::api(Some(actor.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_resolve_field_strategies() {
+        let policy = DataConflictPolicy {
+            fields: BTreeMap::from([
+                ("owner".to_string(), ConflictStrategy::Reject),
+                ("labels".to_string(), ConflictStrategy::MergeArrays),
+            ]),
+            ..Default::default()
+        };
+        let (alice, bob) = (writer("alice", 1), writer("bob", 0));
+
+        let write = |field, current: Value, value: Value, last: &FieldProvenance| {
+            policy.resolve(field, Some(last), Some(&current), value, &bob)
+        };
+
+        assert_eq!(write("amount", json!(1), json!(2), &alice), FieldWrite::Overwrite(json!(2)));
+        assert_eq!(write("amount", json!(1), json!(2), &writer("alice", 60)), FieldWrite::Write(json!(2)));
+        assert_eq!(write("amount", json!(1), json!(2), &writer("bob", 1)), FieldWrite::Write(json!(2)));
+        assert_eq!(write("owner", json!("ana"), json!("bob"), &alice), FieldWrite::Rejected);
+        assert_eq!(write("owner", json!("bob"), json!("bob"), &alice), FieldWrite::Write(json!("bob")));
+        assert_eq!(
+            write("labels", json!(["a", "b"]), json!(["b", "c"]), &alice),
+            FieldWrite::Merged(json!(["a", "b", "c"]))
+        );
+        assert_eq!(write("labels", json!("a"), json!(["c"]), &alice), FieldWrite::Overwrite(json!(["c"])));
+    }
+
+    #[test]
+    fn test_resolve_data_reports_rejected_fields() {
+        let policy = DataConflictPolicy {
+            strategy: ConflictStrategy::Reject,
+            fields: BTreeMap::from([("notes".to_string(), ConflictStrategy::LastWriteWins)]),
+            ..Default::default()
+        };
+        let provenance = BTreeMap::from([
+            ("owner".to_string(), writer("alice", 1)),
+            ("notes".to_string(), writer("alice", 1)),
+            ("stage".to_string(), writer("alice", 1)),
+        ]);
+        let previous = json!({"owner": "ana", "notes": "x", "stage": 1, "amount": 5});
+        let bob = writer("bob", 0);
+
+        let rejected = policy
+            .resolve_data(&provenance, &previous, json!({"owner": "bob", "notes": "y", "amount": 6}), &bob)
+            .unwrap_err();
+        assert_eq!(rejected, vec!["owner", "stage"]);
+
+        let (data, overwritten) = policy
+            .resolve_data(&provenance, &previous, json!({"owner": "ana", "notes": "y", "stage": 1, "amount": 6}), &bob)
+            .unwrap();
+        assert_eq!(data, json!({"owner": "ana", "notes": "y", "stage": 1, "amount": 6}));
+        assert_eq!(overwritten.keys().collect::<Vec<_>>(), vec!["notes"]);
+    }
+}
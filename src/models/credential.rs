@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::portal::PortalToken;
+
+/// Credentials Orchepy issues and can revoke. `X-Api-Key` values are not
+/// among them: they are supplied by callers and only fingerprinted for usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    PortalToken,
+}
+
+/// A credential in the expiry report and reminder webhooks. The secret
+/// itself is left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiringCredential {
+    pub kind: CredentialKind,
+    pub id: Uuid,
+    pub case_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub expired: bool,
+    pub reminded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ExpiringCredential {
+    /// `None` for tokens that never expire.
+    pub fn from_portal_token(token: &PortalToken, now: DateTime<Utc>) -> Option<Self> {
+        let expires_at = token.expires_at?;
+        Some(Self {
+            kind: CredentialKind::PortalToken,
+            id: token.id,
+            case_id: token.case_id,
+            expires_at,
+            expired: expires_at <= now,
+            reminded_at: token.reminded_at,
+            created_at: token.created_at,
+        })
+    }
+}
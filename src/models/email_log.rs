@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// An email the mailer sent or tried to send.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct EmailLogEntry {
+    pub id: i64,
+    /// What sent it: `automation` for a send_message action, otherwise the
+    /// notification kind, e.g. `digest`.
+    pub kind: String,
+    pub case_id: Option<Uuid>,
+    pub recipients: String,
+    pub subject: String,
+    /// `sent` or `failed`.
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query of `GET /admin/emails`.
+#[derive(Debug, Default, Deserialize)]
+pub struct EmailLogQuery {
+    /// `sent` or `failed`; both when omitted.
+    pub status: Option<String>,
+    pub case_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl EmailLogQuery {
+    pub fn status(&self) -> Result<Option<&str>, String> {
+        match self.status.as_deref() {
+            None => Ok(None),
+            Some(status @ ("sent" | "failed")) => Ok(Some(status)),
+            Some(other) => Err(format!("Unknown status '{}'; expected sent or failed", other)),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+/// Why a request failed, for clients that branch on the reason rather than
+/// parse the message. Serialized as `WORKFLOW_NOT_FOUND` etc. New codes may
+/// be added; clients should fall back on the HTTP status for unknown ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // Used when nothing more specific applies.
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    PreconditionFailed,
+    PayloadTooLarge,
+    UnprocessableEntity,
+    TooManyRequests,
+    InternalError,
+    ServiceUnavailable,
+
+    ValidationFailed,
+    RegionInvalid,
+    RoleRequired,
+    IpNotAllowed,
+    IdempotencyKeyInvalid,
+    IdempotencyKeyInUse,
+    IdempotencyKeyReused,
+
+    WorkflowNotFound,
+    WorkflowVersionNotFound,
+    WorkflowInvalid,
+    WorkflowArchived,
+    WorkflowHasCases,
+    PhaseInvalid,
+    SlaConfigInvalid,
+    TransitionInvalid,
+    ConditionInvalid,
+    WebhookTemplateInvalid,
+    WebhookHeaderInvalid,
+
+    CaseNotFound,
+    CaseAlreadyExists,
+    CaseStatusInvalid,
+    VersionConflict,
+    DataConflict,
+    WipLimitReached,
+    TransitionNotAllowed,
+    CaseLinkNotFound,
+    CaseRevisionNotFound,
+    AutomationRunNotFound,
+
+    FlowNotFound,
+    EventNotFound,
+    ExecutionNotFound,
+    ServiceAccountNotFound,
+    WebhookSubscriptionNotFound,
+    WebhookDeliveryNotFound,
+    PortalLinkNotFound,
+    CaseAttachmentNotFound,
+    SecretNotFound,
+
+    StorageNotConfigured,
+    SecretsNotConfigured,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_serialize_in_screaming_snake_case() {
+        assert_eq!(serde_json::to_value(ErrorCode::SlaConfigInvalid).unwrap(), "SLA_CONFIG_INVALID");
+        assert_eq!(serde_json::to_value(ErrorCode::WorkflowNotFound).unwrap(), "WORKFLOW_NOT_FOUND");
+    }
+}
@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::case::{DataFilter, MAX_SEARCH_FILTERS};
+use super::execution::Execution;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Event {
     pub id: Uuid,
@@ -13,19 +16,85 @@ pub struct Event {
     #[sqlx(json)]
     pub data: serde_json::Value,
 
-    #[sqlx(json)]
+    #[sqlx(json(nullable))]
     pub metadata: Option<serde_json::Value>,
 
     pub received_at: DateTime<Utc>,
+
+    #[serde(default)]
+    pub region: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct CreateEvent {
     pub event_type: String,
     pub data: serde_json::Value,
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Selects stored events, newest first. `data` is matched by containment,
+/// e.g. `{"customer": {"tier": "gold"}}`.
+#[derive(Debug, Default, Deserialize)]
+pub struct EventFilter {
+    pub event_type: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub data: Option<serde_json::Value>,
+    /// `data.*` query string filters; see [`EventSearch`].
+    #[serde(skip)]
+    pub data_filters: Vec<DataFilter>,
+}
+
+/// Parsed `GET /events` query: `event_type`, `since` and `until` on
+/// `received_at`, `limit`, `offset` and `data.*` filters written as for
+/// `GET /cases/search`.
+#[derive(Debug, Default)]
+pub struct EventSearch {
+    pub filter: EventFilter,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl EventSearch {
+    pub fn from_params(params: &[(String, String)]) -> Result<Self, String> {
+        let mut search = Self::default();
+        let time = |name: &str, value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|_| format!("Invalid {} '{}': expected an RFC 3339 time", name, value))
+        };
+
+        for (key, value) in params {
+            match key.as_str() {
+                "event_type" => search.filter.event_type = Some(value.clone()),
+                "since" => search.filter.since = Some(time("since", value)?),
+                "until" => search.filter.until = Some(time("until", value)?),
+                "limit" => search.limit = Some(value.parse().map_err(|_| format!("Invalid limit '{}'", value))?),
+                "offset" => search.offset = Some(value.parse().map_err(|_| format!("Invalid offset '{}'", value))?),
+                _ => {
+                    if let Some(filter) = DataFilter::parse(key, value)? {
+                        search.filter.data_filters.push(filter);
+                    }
+                }
+            }
+        }
+
+        if search.filter.data_filters.len() > MAX_SEARCH_FILTERS {
+            return Err(format!("At most {} data filters are allowed", MAX_SEARCH_FILTERS));
+        }
+
+        Ok(search)
+    }
+}
+
+/// `GET /events/{id}`: the event and the executions it started.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventDetails {
+    #[serde(flatten)]
+    pub event: Event,
+    pub executions: Vec<Execution>,
+}
+
 impl Event {
     pub fn new(create: CreateEvent) -> Self {
         Self {
@@ -34,6 +103,39 @@ impl Event {
             data: create.data,
             metadata: create.metadata,
             received_at: Utc::now(),
+            region: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::case::DataFilterOp;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_event_search_params() {
+        let search = EventSearch::from_params(&params(&[
+            ("event_type", "order.created"),
+            ("since", "2026-01-01T00:00:00Z"),
+            ("data.customer.tier", "gold"),
+            ("data.total[gte]", "100"),
+            ("limit", "10"),
+            ("region", "eu"),
+        ]))
+        .unwrap();
+
+        assert_eq!(search.filter.event_type.as_deref(), Some("order.created"));
+        assert_eq!(search.filter.since.unwrap().to_rfc3339(), "2026-01-01T00:00:00+00:00");
+        assert_eq!(search.filter.data_filters.len(), 2);
+        assert_eq!(search.filter.data_filters[1].op, DataFilterOp::Gte);
+        assert_eq!(search.limit, Some(10));
+
+        assert!(EventSearch::from_params(&params(&[("until", "yesterday")])).is_err());
+        assert!(EventSearch::from_params(&params(&[("data.total[near]", "1")])).is_err());
+    }
+}
@@ -1,8 +1,14 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+use super::step::Step;
+
+/// Sent to webhook subscriptions when a flow execution fails.
+pub const EXECUTION_FAILED_EVENT: &str = "execution.failed";
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Execution {
     pub id: Uuid,
@@ -10,6 +16,13 @@ pub struct Execution {
     pub flow_id: Uuid,
     pub event_id: Uuid,
 
+    pub flow_version: Option<i32>,
+
+    /// Hash of the flow definition the execution ran; see
+    /// [`DefinitionSnapshot`](super::snapshot::DefinitionSnapshot).
+    #[serde(default)]
+    pub definition_hash: Option<String>,
+
     pub status: ExecutionStatus,
 
     pub current_step: Option<String>,
@@ -21,6 +34,21 @@ pub struct Execution {
     pub completed_at: Option<DateTime<Utc>>,
 
     pub error: Option<String>,
+
+    pub resume_at: Option<DateTime<Utc>>,
+    pub resume_step: Option<i32>,
+}
+
+/// `GET /executions` filters. `since` and `until` bound `started_at`;
+/// `has_error` selects executions with or without an `error`.
+#[derive(Debug, Default, Deserialize)]
+pub struct ExecutionFilter {
+    pub status: Option<ExecutionStatus>,
+    pub flow_id: Option<Uuid>,
+    pub event_id: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub has_error: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
@@ -36,6 +64,8 @@ pub enum ExecutionStatus {
     Failed,
 
     Retrying,
+
+    Waiting,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,10 +74,29 @@ pub struct StepStatus {
     pub started_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
     pub attempts: u32,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// Method, URL and body sent by a webhook step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<serde_json::Value>,
     pub response: Option<serde_json::Value>,
     pub error: Option<String>,
 }
 
+/// An entry of `GET /executions/{id}/steps`: a step of the flow the
+/// execution ran, with how it went. Steps the execution has not reached
+/// only have their position, name and type.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionStep {
+    pub position: usize,
+    pub name: String,
+    /// Absent when the flow definition is no longer known.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub step_type: Option<&'static str>,
+    #[serde(flatten)]
+    pub outcome: Option<StepStatus>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum StepExecutionStatus {
@@ -55,6 +104,7 @@ pub enum StepExecutionStatus {
     Completed,
     Failed,
     Skipped,
+    Waiting,
 }
 
 impl Execution {
@@ -63,12 +113,84 @@ impl Execution {
             id: Uuid::new_v4(),
             flow_id,
             event_id,
+            flow_version: None,
+            definition_hash: None,
             status: ExecutionStatus::Pending,
             current_step: None,
             steps_status: serde_json::json!({}),
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            resume_at: None,
+            resume_step: None,
+        }
+    }
+
+    /// `steps_status` in the order of `steps`, the definition the execution
+    /// ran. Recorded steps the definition does not list follow, oldest first.
+    pub fn steps(&self, steps: &[Step]) -> Vec<ExecutionStep> {
+        let mut recorded: HashMap<String, StepStatus> =
+            serde_json::from_value(self.steps_status.clone()).unwrap_or_default();
+
+        let mut listed: Vec<ExecutionStep> = steps
+            .iter()
+            .map(|step| ExecutionStep {
+                position: 0,
+                name: step.name.clone(),
+                step_type: Some(step.step_type.kind()),
+                outcome: recorded.remove(&step.name),
+            })
+            .collect();
+
+        let mut unlisted: Vec<(String, StepStatus)> = recorded.into_iter().collect();
+        unlisted.sort_by(|a, b| a.1.started_at.cmp(&b.1.started_at).then_with(|| a.0.cmp(&b.0)));
+        listed.extend(unlisted.into_iter().map(|(name, outcome)| ExecutionStep {
+            position: 0,
+            name,
+            step_type: None,
+            outcome: Some(outcome),
+        }));
+
+        for (position, step) in listed.iter_mut().enumerate() {
+            step.position = position;
         }
+        listed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_steps_follow_the_definition() {
+        let steps: Vec<Step> = serde_json::from_value(json!([
+            {"name": "notify", "type": "webhook", "url": "https://example.com", "method": "POST"},
+            {"name": "wait", "type": "delay", "duration_ms": 10},
+            {"name": "close", "type": "delay", "duration_ms": 10}
+        ]))
+        .unwrap();
+
+        let mut execution = Execution::new(Uuid::new_v4(), Uuid::new_v4());
+        execution.steps_status = json!({
+            "wait": {"status": "completed", "started_at": "2026-01-01T00:00:02Z", "attempts": 1, "response": null, "error": null},
+            "removed": {"status": "failed", "started_at": "2026-01-01T00:00:03Z", "attempts": 2, "response": null, "error": "HTTP 500"},
+            "notify": {
+                "status": "completed", "started_at": "2026-01-01T00:00:00Z", "attempts": 1, "duration_ms": 40,
+                "request": {"method": "POST", "url": "https://example.com", "body": {}},
+                "response": {"ok": true}, "error": null
+            }
+        });
+
+        let listed = serde_json::to_value(execution.steps(&steps)).unwrap();
+        assert_eq!(listed[0]["name"], "notify");
+        assert_eq!(listed[0]["type"], "webhook");
+        assert_eq!(listed[0]["request"]["method"], "POST");
+        assert_eq!(listed[1]["status"], "completed");
+        assert_eq!(listed[2], json!({"position": 2, "name": "close", "type": "delay"}));
+        assert_eq!(listed[3]["name"], "removed");
+        assert_eq!((listed[3]["position"].clone(), listed[3]["error"].clone()), (json!(3), json!("HTTP 500")));
+        assert!(listed[3].get("type").is_none());
     }
 }
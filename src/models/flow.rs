@@ -1,14 +1,36 @@
 use crate::models::step::Step;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub const CASE_EVENT_TYPES: [&str; 2] = ["case.created", "case.moved"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "crate::models::validation::validate_case_trigger_event"))]
 pub struct FlowTrigger {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
     pub event_type: String,
     #[serde(default)]
+    #[validate(custom(function = "crate::models::validation::validate_trigger_filters"))]
     pub filters: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(nested)]
+    pub case: Option<CaseTrigger>,
+}
+
+/// Scopes a `case.created` / `case.moved` trigger to one workflow and,
+/// optionally, to the phase the case enters or leaves.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CaseTrigger {
+    pub workflow_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "crate::models::validation::validate_phase_name"))]
+    pub phase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(custom(function = "crate::models::validation::validate_phase_name"))]
+    pub from_phase: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -22,16 +44,43 @@ pub struct Flow {
     #[sqlx(json)]
     pub steps: Vec<Step>,
 
+    pub max_concurrent_executions: Option<i32>,
+
+    pub version: i32,
+
     pub active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FlowVersion {
+    pub flow_id: Uuid,
+    pub version: i32,
+    pub name: String,
+
+    #[sqlx(json)]
+    pub trigger: FlowTrigger,
+
+    #[sqlx(json)]
+    pub steps: Vec<Step>,
+
+    pub max_concurrent_executions: Option<i32>,
+
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateFlow {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
     pub name: String,
+    #[validate(nested)]
     pub trigger: FlowTrigger,
+    #[validate(custom(function = "crate::models::validation::validate_steps"))]
     pub steps: Vec<Step>,
+    #[serde(default)]
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub max_concurrent_executions: Option<i32>,
     #[serde(default = "default_active")]
     pub active: bool,
 }
@@ -45,9 +94,21 @@ pub struct UpdateFlow {
     pub name: Option<String>,
     pub trigger: Option<FlowTrigger>,
     pub steps: Option<Vec<Step>>,
+    /// `Some(None)` when the request sends `null`, which clears the limit.
+    #[serde(default, deserialize_with = "explicit_null")]
+    pub max_concurrent_executions: Option<Option<i32>>,
     pub active: Option<bool>,
 }
 
+/// Keeps an explicit `null` apart from a missing field.
+fn explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 impl Flow {
     pub fn new(create: CreateFlow) -> Self {
         let now = Utc::now();
@@ -56,9 +117,40 @@ impl Flow {
             name: create.name,
             trigger: create.trigger,
             steps: create.steps,
+            max_concurrent_executions: create.max_concurrent_executions,
+            version: 1,
             active: create.active,
             created_at: now,
             updated_at: now,
         }
     }
 }
+
+impl FlowVersion {
+    /// Rebuilds the flow definition as it was at this version.
+    pub fn to_flow(&self) -> Flow {
+        Flow {
+            id: self.flow_id,
+            name: self.name.clone(),
+            trigger: self.trigger.clone(),
+            steps: self.steps.clone(),
+            max_concurrent_executions: self.max_concurrent_executions,
+            version: self.version,
+            active: true,
+            created_at: self.created_at,
+            updated_at: self.created_at,
+        }
+    }
+
+    pub fn snapshot(flow: &Flow) -> Self {
+        Self {
+            flow_id: flow.id,
+            version: flow.version,
+            name: flow.name.clone(),
+            trigger: flow.trigger.clone(),
+            steps: flow.steps.clone(),
+            max_concurrent_executions: flow.max_concurrent_executions,
+            created_at: flow.updated_at,
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use super::case::{track_data_writes, Case, CaseHistory, CaseStatus, FieldProvenance};
+use super::validation::validate_tags;
+use super::Workflow;
+
+/// Body of `POST /admin/cases/import`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ImportCases {
+    pub workflow_id: Uuid,
+
+    #[validate(length(min = 1, max = 1000, message = "must contain between 1 and 1000 cases"))]
+    pub cases: Vec<ImportCase>,
+
+    /// Default `triggered_by` for timeline entries and data provenance.
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub triggered_by: Option<String>,
+}
+
+/// A historical case. `timeline` lists the phases it entered, oldest first;
+/// the first entry is where it was created and the last is where it is now.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportCase {
+    /// Keeps the case's id from the source system when given.
+    pub id: Option<Uuid>,
+
+    #[serde(default)]
+    pub data: serde_json::Value,
+
+    pub metadata: Option<serde_json::Value>,
+
+    #[serde(default = "default_status")]
+    pub status: CaseStatus,
+
+    /// Defaults to when the first phase was entered.
+    pub created_at: Option<DateTime<Utc>>,
+
+    /// Defaults to when the last phase was entered, for completed and
+    /// failed cases.
+    pub completed_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    pub timeline: Vec<ImportedPhase>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedPhase {
+    pub phase: String,
+    pub entered_at: DateTime<Utc>,
+    pub reason: Option<String>,
+    pub triggered_by: Option<String>,
+}
+
+fn default_status() -> CaseStatus {
+    CaseStatus::Active
+}
+
+impl ImportCase {
+    /// Builds the case and its history, checking that the timeline only uses
+    /// the workflow's phases and runs forward in time without reaching into
+    /// the future.
+    pub fn into_case(
+        self,
+        workflow: &Workflow,
+        triggered_by: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<(Case, Vec<CaseHistory>), String> {
+        let (Some(first), Some(last)) = (self.timeline.first(), self.timeline.last()) else {
+            return Err("timeline must contain at least one phase".to_string());
+        };
+
+        validate_tags(&self.tags).map_err(|err| err.to_string())?;
+        if let Some(unknown) = self.timeline.iter().find(|entry| !workflow.has_phase(&entry.phase)) {
+            return Err(format!("phase '{}' not found in workflow", unknown.phase));
+        }
+
+        let created_at = self.created_at.unwrap_or(first.entered_at);
+        if first.entered_at < created_at {
+            return Err("the first phase was entered before the case was created".to_string());
+        }
+        if let Some(pair) = self.timeline.windows(2).find(|pair| pair[1].entered_at < pair[0].entered_at) {
+            return Err(format!(
+                "phase '{}' was entered at {}, before the previous phase at {}",
+                pair[1].phase, pair[1].entered_at, pair[0].entered_at
+            ));
+        }
+        if last.entered_at > now {
+            return Err("timeline entries cannot be in the future".to_string());
+        }
+
+        let completed_at = match self.status {
+            CaseStatus::Completed | CaseStatus::Failed => Some(self.completed_at.unwrap_or(last.entered_at)),
+            CaseStatus::Active | CaseStatus::Paused if self.completed_at.is_some() => {
+                return Err("only completed and failed cases can have completed_at".to_string());
+            }
+            CaseStatus::Active | CaseStatus::Paused => None,
+        };
+        if completed_at.is_some_and(|completed_at| completed_at < last.entered_at || completed_at > now) {
+            return Err("completed_at must be after the last phase was entered and not in the future".to_string());
+        }
+
+        let mut case = Case::new(workflow.id, last.phase.clone(), self.data, self.metadata);
+        if let Some(id) = self.id {
+            case.id = id;
+        }
+        case.status = self.status;
+        case.created_at = created_at;
+        case.updated_at = completed_at.unwrap_or(last.entered_at);
+        case.completed_at = completed_at;
+        case.phase_entered_at = last.entered_at;
+        case.previous_phase = self.timeline.iter().rev().nth(1).map(|entry| entry.phase.clone());
+        case.add_tags(self.tags);
+
+        let writer = FieldProvenance {
+            updated_at: created_at,
+            ..FieldProvenance::api(triggered_by.map(str::to_string))
+        };
+        track_data_writes(&mut case.field_provenance, &serde_json::Value::Null, &case.data, &writer);
+
+        let mut from_phase = None;
+        let history = self
+            .timeline
+            .into_iter()
+            .map(|entry| {
+                let mut history = CaseHistory::new(
+                    case.id,
+                    from_phase.replace(entry.phase.clone()),
+                    entry.phase,
+                    entry.reason,
+                    entry.triggered_by.or_else(|| triggered_by.map(str::to_string)),
+                );
+                history.transitioned_at = entry.entered_at;
+                history
+            })
+            .collect();
+
+        Ok((case, history))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn workflow() -> Workflow {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "name": "Sales",
+            "phases": ["Lead", "Qualified", "Won"],
+            "initial_phase": "Lead",
+            "active": true,
+            "execution_limits": {},
+            "created_at": Utc::now(),
+            "updated_at": Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    fn import(timeline: serde_json::Value, extra: serde_json::Value) -> ImportCase {
+        let mut case = json!({"data": {"amount": 10}, "timeline": timeline});
+        case.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(case).unwrap()
+    }
+
+    #[test]
+    fn test_import_builds_case_and_history() {
+        let workflow = workflow();
+        let now = Utc::now();
+        let (t0, t1) = (now - Duration::days(10), now - Duration::days(3));
+
+        let (case, history) = import(
+            json!([{"phase": "Lead", "entered_at": t0}, {"phase": "Won", "entered_at": t1, "triggered_by": "ana"}]),
+            json!({"status": "completed"}),
+        )
+        .into_case(&workflow, Some("import"), now)
+        .unwrap();
+
+        assert_eq!(case.current_phase, "Won");
+        assert_eq!(case.previous_phase.as_deref(), Some("Lead"));
+        assert_eq!((case.created_at, case.phase_entered_at, case.completed_at), (t0, t1, Some(t1)));
+        assert_eq!(case.field_provenance["amount"].actor.as_deref(), Some("import"));
+
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].from_phase.as_deref(), history[0].transitioned_at), (None, t0));
+        assert_eq!(history[1].from_phase.as_deref(), Some("Lead"));
+        assert_eq!(history[1].triggered_by.as_deref(), Some("ana"));
+        assert_eq!(history[0].triggered_by.as_deref(), Some("import"));
+    }
+
+    #[test]
+    fn test_import_rejects_bad_chronology() {
+        let workflow = workflow();
+        let now = Utc::now();
+        let (t0, t1) = (now - Duration::days(10), now - Duration::days(3));
+        let check = |timeline, extra| import(timeline, extra).into_case(&workflow, None, now);
+
+        assert!(check(json!([]), json!({})).is_err());
+        assert!(check(json!([{"phase": "Lost", "entered_at": t0}]), json!({})).is_err());
+        assert!(check(json!([{"phase": "Lead", "entered_at": t1}, {"phase": "Won", "entered_at": t0}]), json!({})).is_err());
+        assert!(check(json!([{"phase": "Lead", "entered_at": t0}]), json!({"created_at": t1})).is_err());
+        assert!(check(json!([{"phase": "Lead", "entered_at": now + Duration::days(1)}]), json!({})).is_err());
+        assert!(check(json!([{"phase": "Lead", "entered_at": t0}]), json!({"completed_at": t1})).is_err());
+        assert!(check(json!([{"phase": "Won", "entered_at": t1}]), json!({"status": "failed", "completed_at": t0})).is_err());
+    }
+}
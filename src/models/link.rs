@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// How a linked case relates to the case it is seen from: `parent` means
+/// the other case is this one's parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "case_relation", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CaseRelation {
+    Parent,
+    Child,
+    Related,
+}
+
+impl CaseRelation {
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::Parent => Self::Child,
+            Self::Child => Self::Parent,
+            Self::Related => Self::Related,
+        }
+    }
+}
+
+/// A stored link, normalized so each pair of cases has one row per
+/// relation; see [`CaseLink::new`].
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CaseLink {
+    pub id: Uuid,
+    pub source_case_id: Uuid,
+    pub target_case_id: Uuid,
+    pub relation: CaseRelation,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of `POST /cases/{id}/links`: `relation` is what `case_id` is to
+/// the case in the URL.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCaseLink {
+    pub case_id: Uuid,
+
+    pub relation: CaseRelation,
+
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub created_by: Option<String>,
+}
+
+/// A link as seen from one of its cases.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkedCase {
+    pub link_id: Uuid,
+    pub case_id: Uuid,
+    pub relation: CaseRelation,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CaseLink {
+    /// Links `case_id` to `payload.case_id`. Parent links are stored from
+    /// the parent and related links from the lower id, so linking A to B and
+    /// B to A give the same row.
+    pub fn new(case_id: Uuid, payload: CreateCaseLink) -> Self {
+        let other = payload.case_id;
+        let (source_case_id, target_case_id, relation) = match payload.relation {
+            CaseRelation::Parent => (other, case_id, CaseRelation::Parent),
+            CaseRelation::Child => (case_id, other, CaseRelation::Parent),
+            CaseRelation::Related => (case_id.min(other), case_id.max(other), CaseRelation::Related),
+        };
+
+        Self {
+            id: Uuid::new_v4(),
+            source_case_id,
+            target_case_id,
+            relation,
+            created_by: payload.created_by,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// The other case and what it is to `case_id`.
+    pub fn seen_from(&self, case_id: Uuid) -> LinkedCase {
+        // A stored relation is what the source is to the target.
+        let (other, relation) = if self.source_case_id == case_id {
+            (self.target_case_id, self.relation.inverse())
+        } else {
+            (self.source_case_id, self.relation)
+        };
+
+        LinkedCase {
+            link_id: self.id,
+            case_id: other,
+            relation,
+            created_by: self.created_by.clone(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(case_id: Uuid, other: Uuid, relation: CaseRelation) -> CaseLink {
+        CaseLink::new(case_id, CreateCaseLink { case_id: other, relation, created_by: None })
+    }
+
+    #[test]
+    fn test_links_are_normalized() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let parent = link(a, b, CaseRelation::Child);
+        let same = link(b, a, CaseRelation::Parent);
+        assert_eq!(
+            (parent.source_case_id, parent.target_case_id, parent.relation),
+            (same.source_case_id, same.target_case_id, same.relation)
+        );
+        assert_eq!(parent.seen_from(a).relation, CaseRelation::Child);
+        assert_eq!(parent.seen_from(b).relation, CaseRelation::Parent);
+        assert_eq!(parent.seen_from(b).case_id, a);
+
+        let related = link(a, b, CaseRelation::Related);
+        assert_eq!(related.source_case_id, link(b, a, CaseRelation::Related).source_case_id);
+        assert_eq!(related.seen_from(a).relation, CaseRelation::Related);
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::case::{Case, CaseHistory};
+
+/// A case's place in a workflow. Besides the workflow it was created in, a
+/// case can join others, e.g. a deal tracked on both the Legal and Finance
+/// boards, and moves through each one's phases independently.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CaseWorkflow {
+    pub case_id: Uuid,
+    pub workflow_id: Uuid,
+    pub current_phase: String,
+    pub previous_phase: Option<String>,
+    pub phase_entered_at: DateTime<Utc>,
+    pub joined_by: Option<String>,
+    pub joined_at: DateTime<Utc>,
+
+    /// True for the workflow the case was created in, which it cannot leave.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// Body of `POST /cases/{id}/workflows`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct JoinWorkflow {
+    pub workflow_id: Uuid,
+
+    /// Defaults to the workflow's initial phase.
+    #[validate(custom(function = "crate::models::validation::validate_phase_name"))]
+    pub phase: Option<String>,
+
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub triggered_by: Option<String>,
+}
+
+impl CaseWorkflow {
+    pub fn new(case_id: Uuid, workflow_id: Uuid, phase: String, joined_by: Option<String>) -> Self {
+        let now = Utc::now();
+
+        Self {
+            case_id,
+            workflow_id,
+            current_phase: phase,
+            previous_phase: None,
+            phase_entered_at: now,
+            joined_by,
+            joined_at: now,
+            primary: false,
+        }
+    }
+
+    /// The case's own workflow, as a membership.
+    pub fn primary(case: &Case) -> Self {
+        Self {
+            case_id: case.id,
+            workflow_id: case.workflow_id,
+            current_phase: case.current_phase.clone(),
+            previous_phase: case.previous_phase.clone(),
+            phase_entered_at: case.phase_entered_at,
+            joined_by: None,
+            joined_at: case.created_at,
+            primary: true,
+        }
+    }
+
+    pub fn move_to_phase(&mut self, new_phase: String) {
+        self.previous_phase = Some(std::mem::replace(&mut self.current_phase, new_phase));
+        self.phase_entered_at = Utc::now();
+    }
+
+    /// A history entry for a move from `from_phase` (`None` on joining) to
+    /// the current phase.
+    pub fn history(&self, from_phase: Option<String>, reason: Option<String>, triggered_by: Option<String>) -> CaseHistory {
+        CaseHistory {
+            workflow_id: Some(self.workflow_id),
+            ..CaseHistory::new(self.case_id, from_phase, self.current_phase.clone(), reason, triggered_by)
+        }
+    }
+
+    /// The case as seen on this workflow's board: its workflow and phase
+    /// fields are this membership's.
+    pub fn scope(&self, mut case: Case) -> Case {
+        case.workflow_id = self.workflow_id;
+        case.current_phase = self.current_phase.clone();
+        case.previous_phase = self.previous_phase.clone();
+        case.phase_entered_at = self.phase_entered_at;
+        case
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_membership_moves_independently_of_case() {
+        let case = Case::new(Uuid::new_v4(), "Lead".to_string(), json!({}), None);
+        let legal = Uuid::new_v4();
+        let mut membership = CaseWorkflow::new(case.id, legal, "Review".to_string(), Some("ana".to_string()));
+
+        membership.move_to_phase("Signed".to_string());
+        let history = membership.history(Some("Review".to_string()), None, None);
+        assert_eq!((history.workflow_id, history.to_phase.as_str()), (Some(legal), "Signed"));
+
+        let scoped = membership.scope(case.clone());
+        assert_eq!((scoped.workflow_id, scoped.current_phase.as_str()), (legal, "Signed"));
+        assert_eq!(scoped.previous_phase.as_deref(), Some("Review"));
+        assert_eq!(case.current_phase, "Lead");
+        assert!(CaseWorkflow::primary(&case).primary);
+    }
+}
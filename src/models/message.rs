@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Plus-addresses a reply token onto `base`: `cases@reply.example.com`
+/// becomes `cases+<token>@reply.example.com`.
+pub fn reply_address(base: &str, token: &str) -> Option<String> {
+    let (local, domain) = base.trim().split_once('@')?;
+    Some(format!("{}+{}@{}", local, token, domain))
+}
+
+/// Recovers the token from a reply address, accepting the display-name
+/// form (`Cases <cases+token@reply.example.com>`) that inbound email
+/// services usually forward.
+pub fn reply_token_from_address(address: &str) -> Option<String> {
+    let address = match (address.rfind('<'), address.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &address[start + 1..end],
+        _ => address,
+    };
+    let (local, _) = address.trim().split_once('@')?;
+    let (_, token) = local.split_once('+')?;
+
+    (!token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric())).then(|| token.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "message_channel", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MessageChannel {
+    Email,
+    #[default]
+    Sms,
+    Whatsapp,
+    Slack,
+    /// Posted by the requester through the customer portal.
+    Portal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(type_name = "message_direction", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum MessageDirection {
+    Outbound,
+    Inbound,
+}
+
+/// A file shared in a conversation. Orchepy stores the reference only; the
+/// file itself lives wherever `url` points.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct MessageAttachment {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub filename: String,
+    #[validate(url(message = "must be a valid URL"))]
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<i64>,
+}
+
+/// One entry in a case's conversation. Outbound messages carry a
+/// `reply_token` that lets an inbound reply be matched back to the case.
+/// `public` messages are shown to the requester in the customer portal.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CaseMessage {
+    pub id: Uuid,
+    pub case_id: Uuid,
+    pub direction: MessageDirection,
+    pub channel: MessageChannel,
+    pub sender: Option<String>,
+    pub recipient: Option<String>,
+    pub subject: Option<String>,
+    pub body: String,
+    pub external_id: Option<String>,
+    pub reply_token: Option<String>,
+    pub public: bool,
+    #[sqlx(json)]
+    pub attachments: Vec<MessageAttachment>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CaseMessage {
+    pub fn outbound(
+        case_id: Uuid,
+        channel: MessageChannel,
+        recipient: Option<String>,
+        subject: Option<String>,
+        body: String,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            case_id,
+            direction: MessageDirection::Outbound,
+            channel,
+            sender: None,
+            recipient,
+            subject,
+            body,
+            external_id: None,
+            reply_token: Some(Uuid::new_v4().simple().to_string()),
+            public: false,
+            attachments: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn inbound(case_id: Uuid, message: InboundMessage) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            case_id,
+            direction: MessageDirection::Inbound,
+            channel: message.channel,
+            sender: Some(message.from),
+            recipient: message.to,
+            subject: message.subject,
+            body: message.body,
+            external_id: message.external_id,
+            reply_token: None,
+            public: false,
+            attachments: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// An outbound message sent outside Orchepy that should appear in the
+/// case's conversation.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCaseMessage {
+    pub channel: MessageChannel,
+    #[validate(length(min = 1, max = 320, message = "must be between 1 and 320 characters"))]
+    pub to: String,
+    pub subject: Option<String>,
+    #[validate(length(min = 1, message = "cannot be empty"))]
+    pub body: String,
+    pub external_id: Option<String>,
+    /// Show the message to the requester in the customer portal.
+    #[serde(default)]
+    pub public: bool,
+}
+
+/// A reply received on any channel. It is matched to a case by
+/// `reply_token` when present, otherwise by the last outbound message sent
+/// to `from` on the same channel.
+#[derive(Debug, Deserialize, Validate)]
+pub struct InboundMessage {
+    pub channel: MessageChannel,
+    #[validate(length(min = 1, max = 320, message = "must be between 1 and 320 characters"))]
+    pub from: String,
+    pub to: Option<String>,
+    pub subject: Option<String>,
+    #[validate(length(min = 1, message = "cannot be empty"))]
+    pub body: String,
+    pub reply_token: Option<String>,
+    pub external_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_address_round_trip() {
+        let address = reply_address("cases@reply.example.com", "abc123").unwrap();
+        assert_eq!(address, "cases+abc123@reply.example.com");
+        assert_eq!(reply_token_from_address(&address).as_deref(), Some("abc123"));
+        assert_eq!(
+            reply_token_from_address("Orchepy <cases+abc123@reply.example.com>").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(reply_token_from_address("cases@reply.example.com"), None);
+        assert_eq!(reply_token_from_address("cases+@reply.example.com"), None);
+        assert_eq!(reply_address("not-an-address", "abc123"), None);
+    }
+}
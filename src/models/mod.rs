@@ -1,13 +1,42 @@
+pub mod attachment;
 pub mod automation;
+pub mod board;
 pub mod case;
+pub mod change;
+pub mod comment;
+pub mod conflict;
+pub mod credential;
+pub mod email_log;
+pub mod error_code;
 pub mod event;
 pub mod execution;
 pub mod flow;
+pub mod import;
+pub mod link;
+pub mod membership;
+pub mod message;
+pub mod outbox;
+pub mod patch;
+pub mod phase;
+pub mod portal;
+pub mod retention;
+pub mod presence;
+pub mod revision;
+pub mod secret;
+pub mod service_account;
+pub mod signing_key;
+pub mod snapshot;
 pub mod step;
+pub mod validation;
+pub mod webhook_auth;
+pub mod webhook_delivery;
+pub mod webhook_subscription;
 pub mod workflow;
 
 pub use automation::{AutomationAction, AutomationResult, AutomationTrigger, CaseModification, PhaseAutomation, WorkflowAutomations, WorkflowSlaConfig};
 pub use case::Case;
+pub use error_code::ErrorCode;
 pub use event::Event;
 pub use flow::Flow;
+pub use phase::Phase;
 pub use workflow::Workflow;
@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::models::automation::DeferredAutomation;
+use crate::models::workflow::WorkflowWebhook;
+use crate::models::Case;
+use crate::services::webhook::CaseWebhookPayload;
+use crate::services::webhook_template;
+
+/// Something to send once the write it belongs to has committed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OutboxDelivery {
+    /// Stored and run through the active flows, like `POST /events`.
+    Event {
+        event_type: String,
+        data: Value,
+        metadata: Option<Value>,
+    },
+    /// Posted to a workflow's `webhook_url`, signed with the workflow's
+    /// webhook secret as it is at delivery.
+    Webhook {
+        url: String,
+        payload: Value,
+        #[serde(default)]
+        workflow_id: Option<Uuid>,
+    },
+    /// Posted to a webhook subscription, signed with its secret. Dropped if
+    /// the subscription is deleted or deactivated before it goes out.
+    Subscription { subscription_id: Uuid, payload: Value },
+    /// The rest of a case's automation, from its first webhook, message or
+    /// delay on, run once the change that triggered it has committed.
+    Automation(DeferredAutomation),
+}
+
+impl OutboxDelivery {
+    /// Messages of one case are delivered in order per kind, so a failing
+    /// webhook doesn't hold back the case's events. Each subscription is a
+    /// kind of its own, so one failing receiver doesn't hold back another.
+    pub fn kind(&self) -> String {
+        match self {
+            Self::Event { .. } => "event".to_string(),
+            Self::Webhook { .. } => "webhook".to_string(),
+            Self::Subscription { subscription_id, .. } => format!("subscription:{}", subscription_id),
+            Self::Automation(_) => "automation".to_string(),
+        }
+    }
+}
+
+/// A message written with a change, delivered after it commits. Messages
+/// without a case are delivered in no particular order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxMessage {
+    pub case_id: Option<Uuid>,
+    pub delivery: OutboxDelivery,
+}
+
+impl OutboxMessage {
+    /// `case.created` (without `from_phase`) or `case.moved` for `case` in
+    /// its current phase, followed by the `case.moved` webhook to `webhook`
+    /// if there is one.
+    pub fn case_moved(case: &Case, from_phase: Option<&str>, webhook: Option<WorkflowWebhook>) -> Vec<Self> {
+        let event_type = if from_phase.is_some() { "case.moved" } else { "case.created" };
+        let mut messages = vec![Self {
+            case_id: Some(case.id),
+            delivery: OutboxDelivery::Event {
+                event_type: event_type.to_string(),
+                data: json!({
+                    "case_id": case.id,
+                    "workflow_id": case.workflow_id,
+                    "to_phase": case.current_phase,
+                    "from_phase": from_phase,
+                    "case_data": case.data,
+                }),
+                metadata: case.metadata.clone(),
+            },
+        }];
+
+        if let Some(webhook) = webhook {
+            let payload = CaseWebhookPayload::moved(case, from_phase.map(str::to_string));
+            messages.push(Self::webhook(case, webhook, &payload.action, &payload));
+        }
+        messages
+    }
+
+    /// `payload` about `case` for the workflow's `webhook_url`, or the
+    /// workflow's template filled in for `action` and `case` if it has one.
+    pub fn webhook(case: &Case, webhook: WorkflowWebhook, action: &str, payload: &impl Serialize) -> Self {
+        let payload = match webhook.template {
+            Some(template) => webhook_template::render(template, &webhook_template::context(action, case)),
+            None => serde_json::to_value(payload).unwrap_or(Value::Null),
+        };
+
+        Self {
+            case_id: Some(case.id),
+            delivery: OutboxDelivery::Webhook {
+                url: webhook.url.to_string(),
+                payload,
+                workflow_id: Some(webhook.workflow_id),
+            },
+        }
+    }
+
+    pub fn automation(automation: DeferredAutomation) -> Self {
+        Self {
+            case_id: Some(automation.case_id),
+            delivery: OutboxDelivery::Automation(automation),
+        }
+    }
+
+    pub fn subscription(case_id: Option<Uuid>, subscription_id: Uuid, payload: &impl Serialize) -> Self {
+        Self {
+            case_id,
+            delivery: OutboxDelivery::Subscription {
+                subscription_id,
+                payload: serde_json::to_value(payload).unwrap_or(Value::Null),
+            },
+        }
+    }
+}
+
+/// Which due messages a dispatch delivers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutboxScope {
+    All,
+    Case(Uuid),
+    /// Messages just queued, by id.
+    Messages(Vec<i64>),
+}
+
+/// A stored message claimed for delivery.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub case_id: Option<Uuid>,
+    pub delivery: sqlx::types::Json<OutboxDelivery>,
+    /// Delivery attempts so far, this one included.
+    pub attempts: i32,
+}
+
+/// A queued webhook delivery as `GET /webhook-deliveries/queue` lists it:
+/// `pending` while it is being tried, `failed` once it was given up on.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OutboxRecord {
+    pub id: i64,
+    pub case_id: Option<Uuid>,
+    pub status: String,
+    pub delivery: sqlx::types::Json<OutboxDelivery>,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub failed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query of `GET /webhook-deliveries/queue`.
+#[derive(Debug, Default, Deserialize)]
+pub struct QueuedWebhookDeliveriesQuery {
+    /// `pending` or `failed`; both when omitted.
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl QueuedWebhookDeliveriesQuery {
+    /// Whether to list failed deliveries (`Some(true)`), pending ones
+    /// (`Some(false)`) or both.
+    pub fn failed(&self) -> Result<Option<bool>, String> {
+        match self.status.as_deref() {
+            None => Ok(None),
+            Some("pending") => Ok(Some(false)),
+            Some("failed") => Ok(Some(true)),
+            Some(other) => Err(format!("Unknown status '{}'; expected pending or failed", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_moved_messages() {
+        let case = Case::new(Uuid::new_v4(), "Review".to_string(), json!({"amount": 10}), None);
+
+        let created = OutboxMessage::case_moved(&case, None, None);
+        assert_eq!(created.len(), 1);
+        let OutboxDelivery::Event { event_type, data, .. } = &created[0].delivery else {
+            panic!("expected an event");
+        };
+        assert_eq!(event_type, "case.created");
+        assert_eq!(data["from_phase"], Value::Null);
+        assert_eq!(data["case_data"], json!({"amount": 10}));
+
+        let webhook = WorkflowWebhook { workflow_id: case.workflow_id, url: "https://example.com/hook", template: None };
+        let moved = OutboxMessage::case_moved(&case, Some("New"), Some(webhook));
+        assert_eq!(moved.iter().map(|m| m.delivery.kind()).collect::<Vec<_>>(), vec!["event", "webhook"]);
+        let OutboxDelivery::Webhook { url, payload, workflow_id } = &moved[1].delivery else {
+            panic!("expected a webhook");
+        };
+        assert_eq!(url, "https://example.com/hook");
+        assert_eq!(*workflow_id, Some(case.workflow_id));
+        assert_eq!(payload["action"], "case.moved");
+        assert_eq!(payload["data"]["from_phase"], "New");
+        assert_eq!(payload["data"]["to_phase"], "Review");
+
+        let stored = serde_json::to_value(&moved[0].delivery).unwrap();
+        assert_eq!(stored["kind"], "event");
+        assert_eq!(serde_json::from_value::<OutboxDelivery>(stored).unwrap(), moved[0].delivery);
+
+        let template = json!({"ref": "${case.id}", "stage": "${case.current_phase}", "event": "${action}"});
+        let templated = OutboxMessage::case_moved(&case, Some("New"), Some(WorkflowWebhook { template: Some(&template), ..webhook }));
+        let OutboxDelivery::Webhook { payload, .. } = &templated[1].delivery else {
+            panic!("expected a webhook");
+        };
+        assert_eq!(*payload, json!({"ref": case.id, "stage": "Review", "event": "case.moved"}));
+
+        let subscription_id = Uuid::new_v4();
+        let notified = OutboxMessage::subscription(None, subscription_id, &json!({"action": "case.moved"}));
+        assert_eq!(notified.delivery.kind(), format!("subscription:{}", subscription_id));
+    }
+}
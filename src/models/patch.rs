@@ -0,0 +1,269 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+
+/// One RFC 6902 operation. Paths are JSON Pointers (RFC 6901).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// A change to case `data`. Whichever form is used, it is applied to the
+/// current data while the case row is locked, so concurrent updates are
+/// applied one after the other instead of overwriting each other.
+#[derive(Debug, Clone)]
+pub enum DataPatch {
+    /// Replaces the whole document.
+    Replace(Value),
+    /// RFC 7386 JSON Merge Patch.
+    Merge(Value),
+    /// RFC 6902 JSON Patch; all operations apply or none do.
+    Json(Vec<PatchOperation>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// A `test` operation did not match the current data.
+    TestFailed(String),
+    /// The operation cannot be applied, e.g. its path does not exist.
+    Invalid(String),
+}
+
+/// Result of applying a [`DataPatch`] to a stored case.
+#[derive(Debug)]
+pub enum PatchOutcome {
+    /// The new `data`.
+    Applied(Value),
+    NotFound,
+    Rejected(PatchError),
+    /// Fields another writer changed moments before, under a `reject`
+    /// conflict strategy.
+    Conflict(Vec<String>),
+    /// The case is no longer at the version the caller expected; it is at
+    /// `version` now.
+    Stale { version: i32 },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TestFailed(message) | Self::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl PatchError {
+    fn at(self, index: usize) -> Self {
+        match self {
+            Self::TestFailed(message) => Self::TestFailed(format!("operation {}: {}", index, message)),
+            Self::Invalid(message) => Self::Invalid(format!("operation {}: {}", index, message)),
+        }
+    }
+}
+
+impl DataPatch {
+    pub fn apply(&self, data: &Value) -> Result<Value, PatchError> {
+        match self {
+            Self::Replace(value) => Ok(value.clone()),
+            Self::Merge(patch) => {
+                let mut data = data.clone();
+                merge_patch(&mut data, patch);
+                Ok(data)
+            }
+            Self::Json(operations) => {
+                let mut data = data.clone();
+                for (index, operation) in operations.iter().enumerate() {
+                    apply_operation(&mut data, operation).map_err(|err| err.at(index))?;
+                }
+                Ok(data)
+            }
+        }
+    }
+}
+
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().expect("target is an object");
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+fn apply_operation(data: &mut Value, operation: &PatchOperation) -> Result<(), PatchError> {
+    match operation {
+        PatchOperation::Add { path, value } => add(data, &parse_pointer(path)?, value.clone()),
+        PatchOperation::Remove { path } => remove(data, &parse_pointer(path)?).map(|_| ()),
+        PatchOperation::Replace { path, value } => {
+            let target = data
+                .pointer_mut(path)
+                .ok_or_else(|| PatchError::Invalid(format!("path '{}' does not exist", path)))?;
+            *target = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(PatchError::Invalid(format!("cannot move '{}' into its own child '{}'", from, path)));
+            }
+            let value = remove(data, &parse_pointer(from)?)?;
+            add(data, &parse_pointer(path)?, value)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = data
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| PatchError::Invalid(format!("path '{}' does not exist", from)))?;
+            add(data, &parse_pointer(path)?, value)
+        }
+        PatchOperation::Test { path, value } => match data.pointer(path) {
+            Some(actual) if actual == value => Ok(()),
+            Some(actual) => Err(PatchError::TestFailed(format!("'{}' is {}, expected {}", path, actual, value))),
+            None => Err(PatchError::TestFailed(format!("path '{}' does not exist", path))),
+        },
+    }
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, PatchError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PatchError::Invalid(format!("'{}' is not a JSON pointer", pointer)));
+    };
+
+    Ok(rest.split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+fn parent<'a>(data: &'a mut Value, tokens: &[String]) -> Result<&'a mut Value, PatchError> {
+    let mut current = data;
+    for token in tokens {
+        current = match current {
+            Value::Object(map) => map.get_mut(token),
+            Value::Array(items) => token.parse::<usize>().ok().and_then(move |i| items.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| PatchError::Invalid(format!("path '/{}' does not exist", tokens.join("/"))))?;
+    }
+    Ok(current)
+}
+
+fn add(data: &mut Value, tokens: &[String], value: Value) -> Result<(), PatchError> {
+    let Some((last, parents)) = tokens.split_last() else {
+        *data = value;
+        return Ok(());
+    };
+
+    match parent(data, parents)? {
+        Value::Object(map) => {
+            map.insert(last.clone(), value);
+            Ok(())
+        }
+        Value::Array(items) if last == "-" => {
+            items.push(value);
+            Ok(())
+        }
+        Value::Array(items) => match last.parse::<usize>() {
+            Ok(index) if index <= items.len() => {
+                items.insert(index, value);
+                Ok(())
+            }
+            _ => Err(PatchError::Invalid(format!("invalid array index '{}'", last))),
+        },
+        _ => Err(PatchError::Invalid(format!("cannot add '{}' to a scalar value", last))),
+    }
+}
+
+fn remove(data: &mut Value, tokens: &[String]) -> Result<Value, PatchError> {
+    let Some((last, parents)) = tokens.split_last() else {
+        return Err(PatchError::Invalid("cannot remove the whole document".to_string()));
+    };
+
+    let removed = match parent(data, parents)? {
+        Value::Object(map) => map.remove(last),
+        Value::Array(items) => match last.parse::<usize>() {
+            Ok(index) if index < items.len() => Some(items.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    removed.ok_or_else(|| PatchError::Invalid(format!("path '/{}' does not exist", tokens.join("/"))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn json_patch(operations: Value) -> DataPatch {
+        DataPatch::Json(serde_json::from_value(operations).unwrap())
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let data = json!({"title": "Goodbye!", "author": {"givenName": "John", "familyName": "Doe"}, "tags": ["example", "sample"]});
+        let patch = json!({"title": "Hello!", "phoneNumber": "+01-234", "author": {"familyName": null}, "tags": ["example"]});
+
+        assert_eq!(
+            DataPatch::Merge(patch).apply(&data).unwrap(),
+            json!({"title": "Hello!", "author": {"givenName": "John"}, "tags": ["example"], "phoneNumber": "+01-234"})
+        );
+    }
+
+    #[test]
+    fn test_json_patch_operations() {
+        let data = json!({"customer": {"tier": "silver"}, "items": ["a", "c"], "a/b": 1});
+        let patch = json_patch(json!([
+            {"op": "test", "path": "/customer/tier", "value": "silver"},
+            {"op": "replace", "path": "/customer/tier", "value": "gold"},
+            {"op": "add", "path": "/items/1", "value": "b"},
+            {"op": "add", "path": "/items/-", "value": "d"},
+            {"op": "copy", "from": "/customer/tier", "path": "/tier"},
+            {"op": "move", "from": "/a~1b", "path": "/count"},
+            {"op": "remove", "path": "/items/0"}
+        ]));
+
+        assert_eq!(
+            patch.apply(&data).unwrap(),
+            json!({"customer": {"tier": "gold"}, "items": ["b", "c", "d"], "tier": "gold", "count": 1})
+        );
+    }
+
+    #[test]
+    fn test_json_patch_is_all_or_nothing() {
+        let data = json!({"amount": 100});
+
+        let failed_test = json_patch(json!([
+            {"op": "replace", "path": "/amount", "value": 200},
+            {"op": "test", "path": "/amount", "value": 100}
+        ]));
+        assert!(matches!(failed_test.apply(&data), Err(PatchError::TestFailed(_))));
+
+        let missing = json_patch(json!([{"op": "remove", "path": "/missing"}]));
+        assert!(matches!(missing.apply(&data), Err(PatchError::Invalid(_))));
+
+        let no_parent = json_patch(json!([{"op": "add", "path": "/a/b", "value": 1}]));
+        assert!(matches!(no_parent.apply(&data), Err(PatchError::Invalid(_))));
+    }
+}
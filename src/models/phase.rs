@@ -0,0 +1,134 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A workflow phase. Definitions may list phases as plain names or as
+/// objects with metadata; a phase without metadata is written back as its
+/// name, so string-only workflows read the same as before.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Phase {
+    pub name: String,
+    pub description: Option<String>,
+    /// `#rgb` or `#rrggbb`, for boards.
+    pub color: Option<String>,
+    /// Most cases that may be in progress in the phase at once.
+    pub wip_limit: Option<u32>,
+    /// Marks phases where a case's work is done, for boards and clients.
+    pub terminal: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PhaseObject {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wip_limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    terminal: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a phase name or an object with a name")]
+enum PhaseDefinition {
+    Name(String),
+    Object(PhaseObject),
+}
+
+impl Phase {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..Default::default() }
+    }
+
+    fn has_metadata(&self) -> bool {
+        self.description.is_some() || self.color.is_some() || self.wip_limit.is_some() || self.terminal
+    }
+}
+
+impl From<&str> for Phase {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Phase {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl Serialize for Phase {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.has_metadata() {
+            return serializer.serialize_str(&self.name);
+        }
+
+        PhaseObject {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            color: self.color.clone(),
+            wip_limit: self.wip_limit,
+            terminal: self.terminal,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Phase {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match PhaseDefinition::deserialize(deserializer)? {
+            PhaseDefinition::Name(name) => Self::new(name),
+            PhaseDefinition::Object(phase) => Self {
+                name: phase.name,
+                description: phase.description,
+                color: phase.color,
+                wip_limit: phase.wip_limit,
+                terminal: phase.terminal,
+            },
+        })
+    }
+}
+
+/// How moving a case into a phase with a WIP limit went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhaseMove {
+    Moved,
+    /// The phase already holds `in_progress` cases, at or over its `limit`.
+    AtWipLimit { limit: u32, in_progress: i64 },
+    NotFound,
+    /// The case is no longer at the version the caller expected; it is at
+    /// `version` now.
+    Stale { version: i32 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_phases_read_from_strings_and_objects() {
+        let phases: Vec<Phase> = serde_json::from_value(json!([
+            "Lead",
+            {"name": "Working", "color": "#3366ff", "wip_limit": 5},
+            {"name": "Won", "terminal": true},
+            {"name": "Lost"}
+        ]))
+        .unwrap();
+
+        assert_eq!(phases[0], Phase::new("Lead"));
+        assert_eq!((phases[1].wip_limit, phases[1].color.as_deref()), (Some(5), Some("#3366ff")));
+        assert!(phases[2].terminal && !phases[1].terminal);
+
+        assert_eq!(
+            serde_json::to_value(&phases).unwrap(),
+            json!([
+                "Lead",
+                {"name": "Working", "color": "#3366ff", "wip_limit": 5},
+                {"name": "Won", "terminal": true},
+                "Lost"
+            ])
+        );
+        assert!(serde_json::from_value::<Phase>(json!({"color": "#fff"})).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::case::CaseStatus;
+use super::message::{CaseMessage, MessageAttachment, MessageDirection};
+
+pub const MAX_PORTAL_ATTACHMENTS: u64 = 10;
+
+fn default_allow_replies() -> bool {
+    true
+}
+
+/// Access to one case for its external requester. The token is the only
+/// credential, so it is generated from two random UUIDs and only its hash
+/// is stored; `fields` lists the `data` paths the requester may see.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PortalToken {
+    pub id: Uuid,
+    pub case_id: Uuid,
+    /// Only known to the request that created the token, which returns it
+    /// once.
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub token_hash: String,
+    #[sqlx(json)]
+    pub fields: Vec<String>,
+    pub allow_replies: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// When the expiry reminder went out, if it has.
+    pub reminded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PortalToken {
+    pub fn new(case_id: Uuid, request: CreatePortalToken) -> Self {
+        let now = Utc::now();
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        Self {
+            id: Uuid::new_v4(),
+            case_id,
+            token_hash: hash_token(&token),
+            token: Some(token),
+            fields: request.fields,
+            allow_replies: request.allow_replies,
+            expires_at: request.expires_in_hours.map(|hours| now + Duration::hours(hours)),
+            revoked_at: None,
+            reminded_at: None,
+            created_at: now,
+        }
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// What the database keeps of a portal token, and looks it up by.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePortalToken {
+    /// Case `data` paths shown in the portal, e.g. `order.number`.
+    #[serde(default)]
+    #[validate(custom(function = "crate::models::validation::validate_portal_fields"))]
+    pub fields: Vec<String>,
+    #[serde(default = "default_allow_replies")]
+    pub allow_replies: bool,
+    /// Omitted for links that stay valid until revoked.
+    #[validate(range(min = 1, max = 8760, message = "must be between 1 and 8760 hours"))]
+    pub expires_in_hours: Option<i64>,
+}
+
+/// What the requester sees: the case's progress, the whitelisted fields and
+/// the public part of the conversation.
+#[derive(Debug, Serialize)]
+pub struct PortalView {
+    pub case_id: Uuid,
+    pub workflow: String,
+    pub current_phase: String,
+    pub status: CaseStatus,
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    pub messages: Vec<PortalMessage>,
+    pub allow_replies: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A public message stripped of addresses and delivery details.
+#[derive(Debug, Serialize)]
+pub struct PortalMessage {
+    pub id: Uuid,
+    pub from_requester: bool,
+    pub subject: Option<String>,
+    pub body: String,
+    pub attachments: Vec<MessageAttachment>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<CaseMessage> for PortalMessage {
+    fn from(message: CaseMessage) -> Self {
+        Self {
+            id: message.id,
+            from_requester: message.direction == MessageDirection::Inbound,
+            subject: message.subject,
+            body: message.body,
+            attachments: message.attachments,
+            created_at: message.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PortalReply {
+    #[validate(length(min = 1, max = 10000, message = "must be between 1 and 10000 characters"))]
+    pub body: String,
+    #[serde(default)]
+    #[validate(length(max = MAX_PORTAL_ATTACHMENTS, message = "at most 10 attachments"), nested)]
+    pub attachments: Vec<MessageAttachment>,
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Someone who has a case open, as long as their heartbeats keep coming.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CasePresence {
+    pub case_id: Uuid,
+    #[sqlx(rename = "user_name")]
+    pub user: String,
+    /// True while they are changing the case, not just looking at it.
+    pub editing: bool,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Body of `PUT /cases/{id}/presence`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct PresenceHeartbeat {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub user: String,
+
+    #[serde(default)]
+    pub editing: bool,
+}
+
+/// How long a heartbeat keeps someone present, from `CASE_PRESENCE_TTL_SECS`.
+pub fn presence_ttl() -> Duration {
+    let secs = std::env::var("CASE_PRESENCE_TTL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(60);
+
+    Duration::from_secs(secs)
+}
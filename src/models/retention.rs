@@ -0,0 +1,29 @@
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::services::RetentionConfig;
+
+/// Body of `POST /admin/purge`. Days left out fall back to the configured
+/// retention.
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct PurgeRequest {
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub execution_days: Option<u32>,
+
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub event_days: Option<u32>,
+
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub webhook_delivery_days: Option<u32>,
+}
+
+impl PurgeRequest {
+    pub fn retention(&self, configured: &RetentionConfig) -> RetentionConfig {
+        RetentionConfig {
+            execution_days: self.execution_days.or(configured.execution_days),
+            event_days: self.event_days.or(configured.event_days),
+            webhook_delivery_days: self.webhook_delivery_days.or(configured.webhook_delivery_days),
+            batch_size: configured.batch_size,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::case::ProvenanceSource;
+
+/// One change to a case's `data`: the whole data after it, the top-level
+/// fields it added, changed or removed, and who made it, attributed as in
+/// [`FieldProvenance`](super::case::FieldProvenance).
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CaseRevision {
+    pub case_id: Uuid,
+    pub revision: i32,
+    pub data: serde_json::Value,
+    pub changed_fields: Vec<String>,
+    pub source: ProvenanceSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The top-level fields that differ between `previous` and `data`, sorted.
+/// Every field of `data` counts as changed when there is no `previous`.
+pub fn changed_fields(previous: Option<&serde_json::Value>, data: &serde_json::Value) -> Vec<String> {
+    let empty = serde_json::Map::new();
+    let before = previous.and_then(|previous| previous.as_object()).unwrap_or(&empty);
+    let after = data.as_object().unwrap_or(&empty);
+
+    let mut fields: Vec<String> = after
+        .iter()
+        .filter(|(field, value)| before.get(*field) != Some(value))
+        .map(|(field, _)| field.clone())
+        .chain(before.keys().filter(|field| !after.contains_key(*field)).cloned())
+        .collect();
+    fields.sort();
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_changed_fields() {
+        let previous = json!({"amount": 10, "tier": "gold", "notes": "x"});
+        let data = json!({"amount": 20, "tier": "gold", "owner": "ana"});
+        assert_eq!(changed_fields(Some(&previous), &data), vec!["amount", "notes", "owner"]);
+        assert_eq!(changed_fields(None, &data), vec!["amount", "owner", "tier"]);
+        assert!(changed_fields(Some(&data), &data).is_empty());
+    }
+}
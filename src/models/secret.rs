@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+/// A credential that webhook URLs and headers refer to as
+/// `${secrets.NAME}`. Its value is stored encrypted and never returned.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Secret {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(skip)]
+    pub ciphertext: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateSecret {
+    #[validate(custom(function = "crate::models::validation::validate_secret_name"))]
+    pub name: String,
+
+    #[validate(length(min = 1, max = 10000, message = "must be between 1 and 10000 characters"))]
+    pub value: String,
+
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub description: Option<String>,
+}
+
+/// Replaces a secret's value and description.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateSecret {
+    #[validate(length(min = 1, max = 10000, message = "must be between 1 and 10000 characters"))]
+    pub value: String,
+
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub description: Option<String>,
+}
+
+impl Secret {
+    pub fn new(name: String, description: Option<String>, ciphertext: Vec<u8>) -> Self {
+        let now = Utc::now();
+        Self {
+            name,
+            description,
+            ciphertext,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
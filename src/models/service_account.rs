@@ -0,0 +1,128 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+use super::automation::CaseModification;
+use super::case::CaseStatus;
+
+/// A named identity for automations. Workflows opt in with
+/// `automations.run_as`; the account's name then shows up as `triggered_by`
+/// instead of `system`, and its allow-lists bound what the actions may do.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ServiceAccount {
+    pub name: String,
+    pub description: Option<String>,
+
+    /// Phases its automations may move cases to; `None` allows any.
+    #[sqlx(json(nullable))]
+    pub allowed_phases: Option<Vec<String>>,
+
+    /// Statuses its automations may set; `None` allows any.
+    #[sqlx(json(nullable))]
+    pub allowed_statuses: Option<Vec<CaseStatus>>,
+
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateServiceAccount {
+    #[validate(custom(function = "crate::models::validation::validate_service_account_name"))]
+    pub name: String,
+
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub description: Option<String>,
+
+    #[validate(custom(function = "crate::models::validation::validate_allowed_phases"))]
+    pub allowed_phases: Option<Vec<String>>,
+
+    pub allowed_statuses: Option<Vec<CaseStatus>>,
+}
+
+/// Replaces an account's settings; omitted allow-lists allow anything.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateServiceAccount {
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    pub description: Option<String>,
+
+    #[validate(custom(function = "crate::models::validation::validate_allowed_phases"))]
+    pub allowed_phases: Option<Vec<String>>,
+
+    pub allowed_statuses: Option<Vec<CaseStatus>>,
+
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl ServiceAccount {
+    pub fn new(payload: CreateServiceAccount) -> Self {
+        let now = Utc::now();
+        Self {
+            name: payload.name,
+            description: payload.description,
+            allowed_phases: payload.allowed_phases,
+            allowed_statuses: payload.allowed_statuses,
+            active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn apply(&mut self, payload: UpdateServiceAccount) {
+        self.description = payload.description;
+        self.allowed_phases = payload.allowed_phases;
+        self.allowed_statuses = payload.allowed_statuses;
+        self.active = payload.active;
+        self.updated_at = Utc::now();
+    }
+
+    /// Why the account may not make `modification`, if it may not.
+    pub fn denies(&self, modification: &CaseModification) -> Option<String> {
+        match modification {
+            CaseModification::MoveToPhase { phase } => self
+                .allowed_phases
+                .as_ref()
+                .filter(|phases| !phases.contains(phase))
+                .map(|_| format!("Service account '{}' may not move cases to '{}'", self.name, phase)),
+            CaseModification::SetStatus { status } => self
+                .allowed_statuses
+                .as_ref()
+                .filter(|statuses| !statuses.contains(status))
+                .map(|_| format!("Service account '{}' may not set status '{}'", self.name, status.as_str())),
+            CaseModification::SetField { .. } | CaseModification::AddTag { .. } | CaseModification::RecordMessage(_) => {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_account_permissions() {
+        let account = ServiceAccount::new(CreateServiceAccount {
+            name: "billing-bot".to_string(),
+            description: None,
+            allowed_phases: Some(vec!["Invoiced".to_string()]),
+            allowed_statuses: None,
+        });
+
+        assert!(account.denies(&CaseModification::MoveToPhase { phase: "Invoiced".to_string() }).is_none());
+        assert!(account.denies(&CaseModification::MoveToPhase { phase: "Closed".to_string() }).is_some());
+        assert!(account.denies(&CaseModification::SetStatus { status: CaseStatus::Completed }).is_none());
+
+        let restricted = ServiceAccount {
+            allowed_statuses: Some(vec![CaseStatus::Paused]),
+            ..account
+        };
+        assert!(restricted.denies(&CaseModification::SetStatus { status: CaseStatus::Failed }).is_some());
+    }
+}
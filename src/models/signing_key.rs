@@ -0,0 +1,108 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::services::webhook_signing::SigningKey;
+
+pub const DEFAULT_ROTATION_GRACE_SECS: i64 = 86_400;
+
+/// A webhook signing key created through `POST /admin/signing-keys/rotate`.
+/// The secret is only returned when the key is created, and left out of
+/// `Debug` output.
+#[derive(Clone, Serialize, Deserialize, FromRow)]
+pub struct ManagedSigningKey {
+    pub id: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the key stops signing; set when a newer key replaces it.
+    pub retires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningKeyStatus {
+    Active,
+    /// Still signing alongside its replacement until `retires_at`.
+    Retiring,
+    Retired,
+}
+
+impl SigningKeyStatus {
+    /// The status at `now` of a key that stops signing at `retires_at`.
+    pub fn at(retires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Self {
+        match retires_at {
+            None => Self::Active,
+            Some(retires_at) if now < retires_at => Self::Retiring,
+            Some(_) => Self::Retired,
+        }
+    }
+}
+
+/// Body of `POST /admin/signing-keys/rotate`.
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct RotateSigningKey {
+    /// How long the current keys keep signing next to the new one, so
+    /// receivers have time to switch. Defaults to a day.
+    #[validate(range(min = 0, max = 2_592_000, message = "must be between 0 and 2592000 seconds"))]
+    pub grace_period_secs: Option<i64>,
+
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub created_by: Option<String>,
+}
+
+impl RotateSigningKey {
+    pub fn grace_period(&self) -> Duration {
+        Duration::seconds(self.grace_period_secs.unwrap_or(DEFAULT_ROTATION_GRACE_SECS))
+    }
+}
+
+impl ManagedSigningKey {
+    pub fn new(created_by: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: format!("{}-{}", now.format("%Y%m%d"), &Uuid::new_v4().simple().to_string()[..8]),
+            secret: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+            created_by,
+            created_at: now,
+            retires_at: None,
+        }
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> SigningKeyStatus {
+        SigningKeyStatus::at(self.retires_at, now)
+    }
+
+    pub fn signing_key(&self) -> SigningKey {
+        let key = SigningKey::new(self.id.clone(), self.secret.as_bytes());
+        match self.retires_at {
+            Some(retires_at) => key.expiring(retires_at),
+            None => key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_status() {
+        let now = Utc::now();
+        let mut key = ManagedSigningKey::new(Some("ops".to_string()));
+        assert_eq!(key.status(now), SigningKeyStatus::Active);
+        assert_eq!(key.secret.len(), 64);
+
+        key.retires_at = Some(now + Duration::hours(1));
+        assert_eq!(key.status(now), SigningKeyStatus::Retiring);
+        assert!(key.signing_key().is_valid_at(now));
+
+        key.retires_at = Some(now);
+        assert_eq!(key.status(now), SigningKeyStatus::Retired);
+        assert!(!key.signing_key().is_valid_at(now));
+        assert_eq!(RotateSigningKey::default().grace_period(), Duration::days(1));
+    }
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+
+use super::{Flow, Workflow};
+
+/// Fields that identify or describe a definition's lifecycle rather than
+/// its logic; they are left out of snapshots so the hash only changes when
+/// what runs changes.
+const FLOW_BOOKKEEPING: &[&str] = &["id", "version", "active", "created_at", "updated_at"];
+const WORKFLOW_BOOKKEEPING: &[&str] = &[
+    "id",
+    "version",
+    "active",
+    "region",
+    "updated_by",
+    "archived_at",
+    "archived_by",
+    "created_at",
+    "updated_at",
+];
+
+/// The definition an execution or automation run used, keyed by the
+/// SHA-256 of its canonical JSON. Object keys serialize sorted, so equal
+/// definitions always hash the same.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DefinitionSnapshot {
+    pub hash: String,
+    /// `flow` or `workflow`.
+    pub kind: String,
+    pub definition: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DefinitionSnapshot {
+    pub fn of_flow(flow: &Flow) -> Self {
+        Self::new("flow", serde_json::to_value(flow).unwrap_or_default(), FLOW_BOOKKEEPING)
+    }
+
+    pub fn of_workflow(workflow: &Workflow) -> Self {
+        Self::new("workflow", serde_json::to_value(workflow).unwrap_or_default(), WORKFLOW_BOOKKEEPING)
+    }
+
+    fn new(kind: &str, mut definition: Value, bookkeeping: &[&str]) -> Self {
+        if let Some(fields) = definition.as_object_mut() {
+            fields.retain(|field, _| !bookkeeping.contains(&field.as_str()));
+        }
+
+        let digest = Sha256::digest(definition.to_string().as_bytes());
+        Self {
+            hash: hex::encode(digest),
+            kind: kind.to_string(),
+            definition,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// An execution or automation run together with the definition it used;
+/// `definition` is absent for runs recorded before snapshots were kept.
+#[derive(Debug, Clone, Serialize)]
+pub struct WithDefinition<T> {
+    #[serde(flatten)]
+    pub run: T,
+    pub definition: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::flow::FlowTrigger;
+    use uuid::Uuid;
+
+    fn flow(event_type: &str) -> Flow {
+        Flow {
+            id: Uuid::new_v4(),
+            name: "Notify".to_string(),
+            trigger: serde_json::from_value::<FlowTrigger>(serde_json::json!({"event_type": event_type})).unwrap(),
+            steps: Vec::new(),
+            max_concurrent_executions: None,
+            version: 1,
+            active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_hash_follows_logic_only() {
+        let original = flow("order.created");
+        let mut republished = flow("order.created");
+        republished.version = 7;
+        republished.active = false;
+
+        let snapshot = DefinitionSnapshot::of_flow(&original);
+        assert_eq!(snapshot.hash.len(), 64);
+        assert_eq!(snapshot.hash, DefinitionSnapshot::of_flow(&republished).hash);
+        assert_ne!(snapshot.hash, DefinitionSnapshot::of_flow(&flow("order.paid")).hash);
+        assert!(snapshot.definition.get("id").is_none());
+        assert_eq!(snapshot.definition["trigger"]["event_type"], "order.created");
+    }
+}
@@ -28,6 +28,39 @@ pub enum StepType {
     Delay {
         duration_ms: u64,
     },
+
+    /// Runs `step` once per element of the `items` array (e.g.
+    /// `${event.data.line_items}`); the child can reference `${item}`,
+    /// `${item.field}` and `${item_index}`.
+    #[serde(rename = "fan_out")]
+    FanOut {
+        items: String,
+
+        step: Box<Step>,
+
+        #[serde(default)]
+        max_concurrency: Option<usize>,
+    },
+
+    /// Waits until an RFC 3339 timestamp or unix seconds, either literal or
+    /// interpolated from the event (e.g. `${event.data.send_at}`).
+    #[serde(rename = "delay_until")]
+    DelayUntil {
+        until: String,
+    },
+}
+
+impl StepType {
+    /// The step's `type` as written in flow definitions.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Webhook { .. } => "webhook",
+            Self::Condition { .. } => "condition",
+            Self::Delay { .. } => "delay",
+            Self::FanOut { .. } => "fan_out",
+            Self::DelayUntil { .. } => "delay_until",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,597 @@
+use std::borrow::Cow;
+
+use validator::{ValidateUrl, ValidationError};
+
+use super::automation::{AutomationAction, WorkflowAutomations};
+use super::case::CreateCase;
+use super::flow::{FlowTrigger, CASE_EVENT_TYPES};
+use super::message::MessageChannel;
+use super::phase::Phase;
+use super::step::{Step, StepType};
+
+pub const MAX_NAME_LENGTH: usize = 255;
+pub const MAX_DELAY_MS: u64 = 3_600_000;
+pub const MAX_AUTOMATION_DELAY_MS: u64 = 30 * 24 * 3_600_000;
+pub const MAX_WEBHOOK_TIMEOUT_MS: u64 = 300_000;
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+pub const MAX_MESSAGE_LENGTH: usize = 1600;
+pub const MAX_PORTAL_FIELDS: usize = 50;
+pub const MAX_SERVICE_ACCOUNT_NAME_LENGTH: usize = 64;
+pub const MAX_SECRET_NAME_LENGTH: usize = 64;
+pub const MAX_TAG_LENGTH: usize = 64;
+pub const MAX_CASE_TAGS: usize = 50;
+pub const MAX_PHASE_DESCRIPTION_LENGTH: usize = 2000;
+pub const MAX_SUBSCRIPTION_EVENT_TYPES: usize = 50;
+
+fn error(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+fn is_hex_color(value: &str) -> bool {
+    value
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn is_http_url(value: &str) -> bool {
+    (value.starts_with("http://") || value.starts_with("https://")) && value.validate_url()
+}
+
+/// Phase names end up in URLs, automation definitions and Kanban columns, so
+/// they are limited to letters, digits, spaces and a few punctuation marks.
+pub fn validate_phase_name(phase: &str) -> Result<(), ValidationError> {
+    if phase.trim().is_empty() {
+        return Err(error("phase_name", "phase name cannot be blank"));
+    }
+
+    if phase.chars().count() > MAX_NAME_LENGTH {
+        return Err(error(
+            "phase_name",
+            format!("phase name must be at most {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+
+    if let Some(c) = phase
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '&' | '/' | '(' | ')')))
+    {
+        return Err(error(
+            "phase_name",
+            format!("phase name '{}' contains invalid character '{}'", phase, c),
+        ));
+    }
+
+    Ok(())
+}
+
+/// A phase a request refers to rather than defines. The workflow decides
+/// whether it exists, so only blank and overlong names are refused here;
+/// phases named before the character rules stay reachable.
+pub fn validate_phase_reference(phase: &str) -> Result<(), ValidationError> {
+    if phase.trim().is_empty() {
+        return Err(error("phase_name", "phase name cannot be blank"));
+    }
+
+    if phase.chars().count() > MAX_NAME_LENGTH {
+        return Err(error(
+            "phase_name",
+            format!("phase name must be at most {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Service account names appear in URLs and in `triggered_by`, so they
+/// are kept to lowercase letters, digits, `-`, `_` and `.`.
+pub fn validate_service_account_name(name: &str) -> Result<(), ValidationError> {
+    if name.is_empty() || name.len() > MAX_SERVICE_ACCOUNT_NAME_LENGTH {
+        return Err(error(
+            "service_account",
+            format!("service account name must be between 1 and {} characters", MAX_SERVICE_ACCOUNT_NAME_LENGTH),
+        ));
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.')))
+    {
+        return Err(error(
+            "service_account",
+            format!("service account name '{}' contains invalid character '{}'", name, c),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Secret names are written in `${secrets.NAME}` placeholders, so they are
+/// kept to ASCII letters, digits and `_`.
+pub fn validate_secret_name(name: &str) -> Result<(), ValidationError> {
+    if name.is_empty() || name.len() > MAX_SECRET_NAME_LENGTH {
+        return Err(error(
+            "secret_name",
+            format!("secret name must be between 1 and {} characters", MAX_SECRET_NAME_LENGTH),
+        ));
+    }
+
+    if let Some(c) = name.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '_')) {
+        return Err(error(
+            "secret_name",
+            format!("secret name '{}' contains invalid character '{}'", name, c),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Tags are matched exactly, so they are kept to one spelling: lowercase
+/// letters, digits, `-`, `_`, `.` and `:`.
+pub fn validate_tag(tag: &str) -> Result<(), ValidationError> {
+    if tag.is_empty() || tag.len() > MAX_TAG_LENGTH {
+        return Err(error("tag", format!("tag must be between 1 and {} characters", MAX_TAG_LENGTH)));
+    }
+
+    if let Some(c) = tag
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.' | ':')))
+    {
+        return Err(error("tag", format!("tag '{}' contains invalid character '{}'", tag, c)));
+    }
+
+    Ok(())
+}
+
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    if tags.len() > MAX_CASE_TAGS {
+        return Err(error("tags", format!("at most {} tags are allowed", MAX_CASE_TAGS)));
+    }
+
+    tags.iter().try_for_each(|tag| validate_tag(tag))
+}
+
+/// Phase names where an empty list is allowed.
+pub fn validate_allowed_phases(phases: &[String]) -> Result<(), ValidationError> {
+    phases.iter().try_for_each(|phase| validate_phase_name(phase))
+}
+
+pub fn validate_phases(phases: &[Phase]) -> Result<(), ValidationError> {
+    if phases.is_empty() {
+        return Err(error("phases", "phases list cannot be empty"));
+    }
+
+    for phase in phases {
+        validate_phase_name(&phase.name)?;
+
+        if phase.description.as_ref().is_some_and(|d| d.chars().count() > MAX_PHASE_DESCRIPTION_LENGTH) {
+            return Err(error(
+                "phase_description",
+                format!(
+                    "description of phase '{}' must be at most {} characters",
+                    phase.name, MAX_PHASE_DESCRIPTION_LENGTH
+                ),
+            ));
+        }
+        if let Some(color) = phase.color.as_deref().filter(|color| !is_hex_color(color)) {
+            return Err(error(
+                "phase_color",
+                format!("color '{}' of phase '{}' must be #rgb or #rrggbb", color, phase.name),
+            ));
+        }
+        if phase.wip_limit == Some(0) {
+            return Err(error(
+                "phase_wip_limit",
+                format!("WIP limit of phase '{}' must be at least 1", phase.name),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_automations(automations: &WorkflowAutomations) -> Result<(), ValidationError> {
+    if let Some(run_as) = &automations.run_as {
+        validate_service_account_name(run_as)?;
+    }
+
+    for automation in &automations.automations {
+        validate_phase_name(&automation.phase)?;
+        validate_actions(&automation.actions)?;
+    }
+
+    Ok(())
+}
+
+fn validate_actions(actions: &[AutomationAction]) -> Result<(), ValidationError> {
+    for action in actions {
+        match action {
+            AutomationAction::Webhook { url, .. } => {
+                if !url.contains("${") && !is_http_url(url) {
+                    return Err(error("url", format!("'{}' is not a valid http(s) URL", url)));
+                }
+            }
+            AutomationAction::Delay { duration_ms, .. } => {
+                if *duration_ms > MAX_AUTOMATION_DELAY_MS {
+                    return Err(error(
+                        "duration",
+                        format!("delay must be at most {} ms", MAX_AUTOMATION_DELAY_MS),
+                    ));
+                }
+            }
+            AutomationAction::Conditional { then, r#else, .. } => {
+                validate_actions(then)?;
+                if let Some(otherwise) = r#else {
+                    validate_actions(otherwise)?;
+                }
+            }
+            AutomationAction::MoveToPhase { phase, .. } => validate_phase_name(phase)?,
+            AutomationAction::AddTag { tag, .. } => validate_tag(tag)?,
+            AutomationAction::SetField { .. } | AutomationAction::SetStatus { .. } => {}
+            AutomationAction::SendMessage { channel, to, body, opt_out_field, .. } => {
+                if matches!(channel, MessageChannel::Slack | MessageChannel::Portal) {
+                    return Err(error("channel", "send_message supports the email, sms and whatsapp channels"));
+                }
+                if to.trim().is_empty() || body.trim().is_empty() {
+                    return Err(error("message", "send_message requires both to and body"));
+                }
+                if body.chars().count() > MAX_MESSAGE_LENGTH {
+                    return Err(error(
+                        "message",
+                        format!("message body must be at most {} characters", MAX_MESSAGE_LENGTH),
+                    ));
+                }
+                if opt_out_field.strip_prefix("data.").is_none_or(str::is_empty) {
+                    return Err(error("opt_out_field", "opt_out_field must be a data.* path"));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Catches filter operands the Matcher could never satisfy, such as a
+/// malformed `_regex` pattern, when the flow is saved rather than silently
+/// never triggering.
+pub fn validate_trigger_filters(filters: &serde_json::Value) -> Result<(), ValidationError> {
+    let Some(filters) = filters.as_object() else {
+        return if filters.is_null() {
+            Ok(())
+        } else {
+            Err(error("filters", "filters must be an object"))
+        };
+    };
+
+    for (key, value) in filters {
+        if key.ends_with("_regex") {
+            let pattern = value
+                .as_str()
+                .ok_or_else(|| error("filters", format!("'{}' must be a string", key)))?;
+            regex::Regex::new(pattern)
+                .map_err(|e| error("filters", format!("'{}' is not a valid regex: {}", key, e)))?;
+        } else if key.ends_with("_in") && !value.is_array() {
+            return Err(error("filters", format!("'{}' must be an array", key)));
+        } else if key.ends_with("_exists") && !value.is_boolean() {
+            return Err(error("filters", format!("'{}' must be true or false", key)));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_case_trigger_event(trigger: &FlowTrigger) -> Result<(), ValidationError> {
+    if trigger.case.is_some() && !CASE_EVENT_TYPES.contains(&trigger.event_type.as_str()) {
+        return Err(error(
+            "case",
+            format!("case scope requires event_type {}", CASE_EVENT_TYPES.join(" or ")),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_create_case_upsert(case: &CreateCase) -> Result<(), ValidationError> {
+    if case.upsert && case.external_id.is_none() {
+        return Err(error("upsert", "upsert requires external_id"));
+    }
+
+    Ok(())
+}
+
+/// Portal fields are dotted `data` paths such as `order.number`.
+pub fn validate_portal_fields(fields: &[String]) -> Result<(), ValidationError> {
+    if fields.len() > MAX_PORTAL_FIELDS {
+        return Err(error(
+            "portal_fields",
+            format!("at most {} fields can be exposed", MAX_PORTAL_FIELDS),
+        ));
+    }
+
+    for field in fields {
+        let valid = !field.is_empty()
+            && field
+                .split('.')
+                .all(|segment| !segment.is_empty() && segment.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-')));
+
+        if !valid {
+            return Err(error("portal_fields", format!("'{}' is not a valid data path", field)));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_http_url(url: &str) -> Result<(), ValidationError> {
+    if !is_http_url(url) {
+        return Err(error("url", format!("'{}' is not a valid http(s) URL", url)));
+    }
+
+    Ok(())
+}
+
+/// Event types a webhook subscription listens to; `*` stands for all.
+pub fn validate_subscription_event_types(event_types: &[String]) -> Result<(), ValidationError> {
+    if event_types.is_empty() || event_types.len() > MAX_SUBSCRIPTION_EVENT_TYPES {
+        return Err(error(
+            "event_types",
+            format!("between 1 and {} event types are allowed", MAX_SUBSCRIPTION_EVENT_TYPES),
+        ));
+    }
+
+    if let Some(event_type) = event_types
+        .iter()
+        .find(|event_type| event_type.trim().is_empty() || event_type.chars().count() > MAX_NAME_LENGTH)
+    {
+        return Err(error(
+            "event_types",
+            format!("event type '{}' must be between 1 and {} characters", event_type, MAX_NAME_LENGTH),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_steps(steps: &[Step]) -> Result<(), ValidationError> {
+    if steps.is_empty() {
+        return Err(error("steps", "flow must have at least one step"));
+    }
+
+    steps.iter().try_for_each(validate_step)
+}
+
+fn validate_step(step: &Step) -> Result<(), ValidationError> {
+    if step.name.trim().is_empty() || step.name.chars().count() > MAX_NAME_LENGTH {
+        return Err(error(
+            "step_name",
+            format!("step names must be between 1 and {} characters", MAX_NAME_LENGTH),
+        ));
+    }
+
+    match &step.step_type {
+        StepType::Webhook { url, timeout_ms, retry, .. } => {
+            if !url.contains("${") && !is_http_url(url) {
+                return Err(error(
+                    "url",
+                    format!("step '{}': '{}' is not a valid http(s) URL", step.name, url),
+                ));
+            }
+
+            if let Some(timeout_ms) = timeout_ms {
+                if *timeout_ms == 0 || *timeout_ms > MAX_WEBHOOK_TIMEOUT_MS {
+                    return Err(error(
+                        "duration",
+                        format!(
+                            "step '{}': timeout_ms must be between 1 and {}",
+                            step.name, MAX_WEBHOOK_TIMEOUT_MS
+                        ),
+                    ));
+                }
+            }
+
+            if let Some(retry) = retry {
+                if retry.max_attempts == 0 || retry.max_attempts > MAX_RETRY_ATTEMPTS {
+                    return Err(error(
+                        "retry",
+                        format!(
+                            "step '{}': retry.max_attempts must be between 1 and {}",
+                            step.name, MAX_RETRY_ATTEMPTS
+                        ),
+                    ));
+                }
+
+                if retry.initial_delay_ms > MAX_DELAY_MS {
+                    return Err(error(
+                        "duration",
+                        format!(
+                            "step '{}': retry.initial_delay_ms must be at most {}",
+                            step.name, MAX_DELAY_MS
+                        ),
+                    ));
+                }
+            }
+        }
+        StepType::Condition { if_true, if_false, .. } => {
+            validate_step(if_true)?;
+            validate_step(if_false)?;
+        }
+        StepType::Delay { duration_ms } => {
+            if *duration_ms > MAX_DELAY_MS {
+                return Err(error(
+                    "duration",
+                    format!(
+                        "step '{}': delay must be at most {} ms, use delay_until for longer waits",
+                        step.name, MAX_DELAY_MS
+                    ),
+                ));
+            }
+        }
+        StepType::FanOut { step: child, max_concurrency, .. } => {
+            if *max_concurrency == Some(0) {
+                return Err(error(
+                    "max_concurrency",
+                    format!("step '{}': max_concurrency must be at least 1", step.name),
+                ));
+            }
+            validate_step(child)?;
+        }
+        StepType::DelayUntil { until } => {
+            if until.trim().is_empty() {
+                return Err(error(
+                    "until",
+                    format!("step '{}': until cannot be empty", step.name),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Attachment filenames end up in `Content-Disposition` headers, so they
+/// can't carry path separators or control characters.
+pub fn validate_filename(filename: &str) -> Result<(), ValidationError> {
+    if filename.contains(['/', '\\', '"']) || filename.chars().any(char::is_control) {
+        return Err(error("filename", "must not contain slashes, quotes or control characters"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::step::FailureAction;
+
+    fn step(step_type: StepType) -> Step {
+        Step {
+            name: "step".to_string(),
+            step_type,
+            on_failure: FailureAction::Stop,
+        }
+    }
+
+    #[test]
+    fn test_phase_name_characters() {
+        assert!(validate_phase_name("Em Análise").is_ok());
+        assert!(validate_phase_name("Sales & Ops (EU)").is_ok());
+        assert!(validate_phase_name("  ").is_err());
+        assert!(validate_phase_name("drop;table").is_err());
+        assert!(validate_phase_name(&"x".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn test_phase_reference_only_checks_length() {
+        assert!(validate_phase_reference("Won: paid").is_ok());
+        assert!(validate_phase_reference("  ").is_err());
+        assert!(validate_phase_reference(&"x".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn test_phase_metadata() {
+        let phase = |color: Option<&str>, wip_limit| Phase {
+            color: color.map(str::to_string),
+            wip_limit,
+            ..Phase::new("Review")
+        };
+
+        assert!(validate_phases(&[phase(Some("#3366ff"), Some(3)), phase(Some("#FFF"), None)]).is_ok());
+        assert!(validate_phases(&[phase(Some("blue"), None)]).is_err());
+        assert!(validate_phases(&[phase(Some("#12345"), None)]).is_err());
+        assert!(validate_phases(&[phase(None, Some(0))]).is_err());
+        assert!(validate_phases(&[]).is_err());
+    }
+
+    #[test]
+    fn test_service_account_name() {
+        assert!(validate_service_account_name("billing-bot.v2").is_ok());
+        assert!(validate_service_account_name("Billing Bot").is_err());
+        assert!(validate_service_account_name("").is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        assert!(validate_tags(&["vip".to_string(), "region:eu".to_string()]).is_ok());
+        assert!(validate_tag("VIP").is_err());
+        assert!(validate_tag("needs review").is_err());
+        assert!(validate_tags(&vec!["x".to_string(); MAX_CASE_TAGS + 1]).is_err());
+    }
+
+    #[test]
+    fn test_filename() {
+        assert!(validate_filename("Relatório final (v2).pdf").is_ok());
+        assert!(validate_filename("../invoice.pdf").is_err());
+        assert!(validate_filename("a\\b.txt").is_err());
+        assert!(validate_filename("say \"hi\".txt").is_err());
+        assert!(validate_filename("line\nbreak.txt").is_err());
+    }
+
+    #[test]
+    fn test_step_bounds() {
+        assert!(validate_steps(&[]).is_err());
+        assert!(validate_steps(&[step(StepType::Delay { duration_ms: 1000 })]).is_ok());
+        assert!(validate_steps(&[step(StepType::Delay { duration_ms: MAX_DELAY_MS + 1 })]).is_err());
+
+        let webhook = |url: &str, timeout_ms| {
+            step(StepType::Webhook {
+                url: url.to_string(),
+                method: "POST".to_string(),
+                headers: Default::default(),
+                body_template: serde_json::Value::Null,
+                timeout_ms,
+                retry: None,
+            })
+        };
+        assert!(validate_steps(&[webhook("https://example.com", Some(5000))]).is_ok());
+        assert!(validate_steps(&[webhook("${event.data.callback}", None)]).is_ok());
+        assert!(validate_steps(&[webhook("not a url", None)]).is_err());
+        assert!(validate_steps(&[webhook("https://example.com", Some(0))]).is_err());
+    }
+
+    #[test]
+    fn test_trigger_filters() {
+        use serde_json::json;
+
+        assert!(validate_trigger_filters(&serde_json::Value::Null).is_ok());
+        assert!(validate_trigger_filters(&json!({"status_in": ["a"], "email_regex": "^a"})).is_ok());
+        assert!(validate_trigger_filters(&json!({"email_regex": "("})).is_err());
+        assert!(validate_trigger_filters(&json!({"status_in": "a"})).is_err());
+        assert!(validate_trigger_filters(&json!({"email_exists": "yes"})).is_err());
+        assert!(validate_trigger_filters(&json!(["status"])).is_err());
+    }
+
+    #[test]
+    fn test_portal_fields() {
+        let fields = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        assert!(validate_portal_fields(&fields(&["order.number", "eta", "items.0.sku"])).is_ok());
+        assert!(validate_portal_fields(&fields(&["order..number"])).is_err());
+        assert!(validate_portal_fields(&fields(&["order number"])).is_err());
+        assert!(validate_portal_fields(&vec!["x".to_string(); MAX_PORTAL_FIELDS + 1]).is_err());
+    }
+
+    #[test]
+    fn test_case_scope_requires_case_event() {
+        use crate::models::flow::CaseTrigger;
+
+        let trigger = |event_type: &str| FlowTrigger {
+            event_type: event_type.to_string(),
+            filters: serde_json::Value::Null,
+            case: Some(CaseTrigger {
+                workflow_id: uuid::Uuid::new_v4(),
+                phase: Some("Approved".to_string()),
+                from_phase: None,
+            }),
+        };
+        assert!(validate_case_trigger_event(&trigger("case.moved")).is_ok());
+        assert!(validate_case_trigger_event(&trigger("invoice.approved")).is_err());
+    }
+
+    #[test]
+    fn test_nested_steps_are_checked() {
+        let fan_out = step(StepType::FanOut {
+            items: "${event.data.items}".to_string(),
+            step: Box::new(step(StepType::Delay { duration_ms: MAX_DELAY_MS * 2 })),
+            max_concurrency: None,
+        });
+        assert!(validate_steps(&[fan_out]).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::services::webhook_signing::{WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER};
+
+/// What API responses show instead of a credential. Sending it back in an
+/// update keeps the stored one.
+pub const REDACTED: &str = "********";
+
+/// Headers set on every webhook, which a workflow's `webhook_headers`
+/// can't replace.
+const RESERVED_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "host",
+    WEBHOOK_ID_HEADER,
+    WEBHOOK_TIMESTAMP_HEADER,
+    WEBHOOK_SIGNATURE_HEADER,
+];
+
+fn default_api_key_header() -> String {
+    "X-API-Key".to_string()
+}
+
+/// How webhooks to a workflow's `webhook_url` authenticate with the
+/// receiver. The credential is redacted when serialized; use
+/// [`WebhookAuth::stored`] to keep it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookAuth {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+    ApiKey {
+        #[serde(default = "default_api_key_header")]
+        header: String,
+        key: String,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StoredAuth<'a> {
+    Basic { username: &'a str, password: &'a str },
+    Bearer { token: &'a str },
+    ApiKey { header: &'a str, key: &'a str },
+}
+
+impl WebhookAuth {
+    fn with_secret<'a>(&'a self, secret: &'a str) -> StoredAuth<'a> {
+        match self {
+            Self::Basic { username, .. } => StoredAuth::Basic { username, password: secret },
+            Self::Bearer { .. } => StoredAuth::Bearer { token: secret },
+            Self::ApiKey { header, .. } => StoredAuth::ApiKey { header, key: secret },
+        }
+    }
+
+    fn secret(&self) -> &str {
+        match self {
+            Self::Basic { password, .. } => password,
+            Self::Bearer { token } => token,
+            Self::ApiKey { key, .. } => key,
+        }
+    }
+
+    fn secret_mut(&mut self) -> &mut String {
+        match self {
+            Self::Basic { password, .. } => password,
+            Self::Bearer { token } => token,
+            Self::ApiKey { key, .. } => key,
+        }
+    }
+
+    /// The auth with its credential, as saved in the database.
+    pub fn stored(&self) -> Value {
+        serde_json::to_value(self.with_secret(self.secret())).unwrap_or(Value::Null)
+    }
+
+    /// This auth sent back with its credential redacted keeps `current`'s,
+    /// as long as the kind of auth is the same.
+    pub fn keeping_secret_of(mut self, current: Option<&WebhookAuth>) -> Self {
+        if let Some(current) = current.filter(|current| std::mem::discriminant(*current) == std::mem::discriminant(&self)) {
+            if self.secret() == REDACTED {
+                *self.secret_mut() = current.secret().to_string();
+            }
+        }
+        self
+    }
+
+    /// The header this auth is sent in.
+    pub fn header_name(&self) -> &str {
+        match self {
+            Self::ApiKey { header, .. } => header,
+            _ => AUTHORIZATION.as_str(),
+        }
+    }
+
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Self::Basic { username, password } => request.basic_auth(username, Some(password)),
+            Self::Bearer { token } => request.bearer_auth(token),
+            Self::ApiKey { header, key } => request.header(header.as_str(), key.as_str()),
+        }
+    }
+}
+
+impl Serialize for WebhookAuth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_secret(REDACTED).serialize(serializer)
+    }
+}
+
+/// Problems with a workflow's `webhook_headers` and `webhook_auth`, as
+/// paths such as `webhook_headers.X-Tenant` with a message.
+pub fn webhook_request_errors(headers: &BTreeMap<String, String>, auth: Option<&WebhookAuth>) -> Vec<(String, String)> {
+    let mut errors = Vec::new();
+
+    for (name, value) in headers {
+        let path = format!("webhook_headers.{}", name);
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            errors.push((path, format!("'{}' is not a valid header name", name)));
+        } else if RESERVED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            errors.push((path, format!("'{}' is set by Orchepy and can't be replaced", name)));
+        } else if auth.is_some_and(|auth| auth.header_name().eq_ignore_ascii_case(name)) {
+            errors.push((path, format!("'{}' is already set by webhook_auth", name)));
+        } else if HeaderValue::from_str(value).is_err() {
+            errors.push((path, "value must be visible ASCII characters".to_string()));
+        }
+    }
+
+    if let Some(WebhookAuth::ApiKey { header, key }) = auth {
+        if HeaderName::from_bytes(header.as_bytes()).is_err() || RESERVED_HEADERS.contains(&header.to_ascii_lowercase().as_str()) {
+            errors.push(("webhook_auth.header".to_string(), format!("'{}' can't be used as the API key header", header)));
+        }
+        if HeaderValue::from_str(key).is_err() {
+            errors.push(("webhook_auth.key".to_string(), "must be visible ASCII characters".to_string()));
+        }
+    }
+    if let Some(WebhookAuth::Bearer { token }) = auth {
+        if HeaderValue::from_str(&format!("Bearer {}", token)).is_err() {
+            errors.push(("webhook_auth.token".to_string(), "must be visible ASCII characters".to_string()));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_credentials_are_redacted_but_stored() {
+        let auth: WebhookAuth = serde_json::from_value(json!({"type": "api_key", "key": "s3cr3t"})).unwrap();
+
+        assert_eq!(serde_json::to_value(&auth).unwrap(), json!({"type": "api_key", "header": "X-API-Key", "key": REDACTED}));
+        assert_eq!(auth.stored(), json!({"type": "api_key", "header": "X-API-Key", "key": "s3cr3t"}));
+        assert_eq!(serde_json::from_value::<WebhookAuth>(auth.stored()).unwrap(), auth);
+
+        let resent: WebhookAuth = serde_json::from_value(json!({"type": "api_key", "header": "X-Key", "key": REDACTED})).unwrap();
+        assert_eq!(
+            resent.clone().keeping_secret_of(Some(&auth)),
+            WebhookAuth::ApiKey { header: "X-Key".to_string(), key: "s3cr3t".to_string() }
+        );
+        let bearer = WebhookAuth::Bearer { token: "t".to_string() };
+        assert_eq!(resent.clone().keeping_secret_of(Some(&bearer)), resent);
+    }
+
+    #[test]
+    fn test_webhook_request_errors() {
+        let headers = BTreeMap::from([
+            ("X-Tenant".to_string(), "acme".to_string()),
+            ("Content-Type".to_string(), "text/plain".to_string()),
+            ("Authorization".to_string(), "Token abc".to_string()),
+            ("Bad Header".to_string(), "x".to_string()),
+        ]);
+        let auth = WebhookAuth::Bearer { token: "abc".to_string() };
+
+        let paths: Vec<String> = webhook_request_errors(&headers, Some(&auth)).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            paths,
+            vec!["webhook_headers.Authorization", "webhook_headers.Bad Header", "webhook_headers.Content-Type"]
+        );
+        assert!(webhook_request_errors(&headers, None).iter().all(|(path, _)| path != "webhook_headers.Authorization"));
+    }
+}
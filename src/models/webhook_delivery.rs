@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::outbox::OutboxDelivery;
+
+/// One attempt at sending a webhook, before it is logged.
+#[derive(Debug, Clone)]
+pub struct WebhookAttempt {
+    pub url: String,
+    pub payload: Value,
+    pub workflow_id: Option<Uuid>,
+    pub subscription_id: Option<Uuid>,
+    /// The receiver's response status; `None` when no response came back.
+    pub status_code: Option<i32>,
+    pub latency_ms: i64,
+    pub error: Option<String>,
+    /// The logged delivery this one replays.
+    pub redelivery_of: Option<i64>,
+}
+
+impl WebhookAttempt {
+    /// `Err` with the attempt's error when it failed, for the outbox to
+    /// retry it.
+    pub fn result(&self) -> anyhow::Result<()> {
+        match &self.error {
+            Some(error) => Err(anyhow::anyhow!(error.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A webhook Orchepy sent or tried to send.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct WebhookDeliveryLogEntry {
+    pub id: i64,
+    pub url: String,
+    pub payload: Value,
+    pub workflow_id: Option<Uuid>,
+    pub subscription_id: Option<Uuid>,
+    /// `delivered` or `failed`.
+    pub status: String,
+    pub status_code: Option<i32>,
+    pub latency_ms: i64,
+    pub error: Option<String>,
+    pub redelivery_of: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebhookDeliveryLogEntry {
+    /// The same payload to the same receiver: the subscription as it is
+    /// now, or else the logged URL with the workflow's current headers and
+    /// secret.
+    pub fn delivery(&self) -> OutboxDelivery {
+        match self.subscription_id {
+            Some(subscription_id) => OutboxDelivery::Subscription { subscription_id, payload: self.payload.clone() },
+            None => OutboxDelivery::Webhook {
+                url: self.url.clone(),
+                payload: self.payload.clone(),
+                workflow_id: self.workflow_id,
+            },
+        }
+    }
+}
+
+/// Query of `GET /webhook-deliveries`.
+#[derive(Debug, Default, Deserialize)]
+pub struct WebhookDeliveryLogQuery {
+    /// `delivered` or `failed`; both when omitted.
+    pub status: Option<String>,
+    pub workflow_id: Option<Uuid>,
+    pub subscription_id: Option<Uuid>,
+    /// Bounds on when the attempt was made; `until` is exclusive.
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl WebhookDeliveryLogQuery {
+    pub fn status(&self) -> Result<Option<&str>, String> {
+        match self.status.as_deref() {
+            None => Ok(None),
+            Some(status @ ("delivered" | "failed")) => Ok(Some(status)),
+            Some(other) => Err(format!("Unknown status '{}'; expected delivered or failed", other)),
+        }
+    }
+}
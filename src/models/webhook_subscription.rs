@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Matches every event type.
+pub const ALL_EVENT_TYPES: &str = "*";
+
+/// A URL registered through `POST /webhooks` for the events it lists, in
+/// addition to a workflow's own `webhook_url`. Deliveries are signed with
+/// the subscription's secret, which is only returned when it is created.
+#[derive(Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub event_types: Vec<String>,
+    /// Only events of this workflow's cases are sent; `None` sends all.
+    pub workflow_id: Option<Uuid>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for WebhookSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookSubscription")
+            .field("id", &self.id)
+            .field("url", &self.url)
+            .field("event_types", &self.event_types)
+            .field("workflow_id", &self.workflow_id)
+            .field("active", &self.active)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWebhookSubscription {
+    #[validate(custom(function = "crate::models::validation::validate_http_url"))]
+    pub url: String,
+
+    /// Generated when omitted.
+    #[validate(length(min = 16, max = 255, message = "must be between 16 and 255 characters"))]
+    pub secret: Option<String>,
+
+    /// E.g. `case.created`, `case.moved`, `execution.failed`, or `*`.
+    #[validate(custom(function = "crate::models::validation::validate_subscription_event_types"))]
+    pub event_types: Vec<String>,
+
+    pub workflow_id: Option<Uuid>,
+}
+
+impl WebhookSubscription {
+    pub fn new(payload: CreateWebhookSubscription) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            url: payload.url,
+            secret: payload
+                .secret
+                .unwrap_or_else(|| format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())),
+            event_types: payload.event_types,
+            workflow_id: payload.workflow_id,
+            active: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Whether an event of `event_type`, about `workflow_id` if it concerns
+    /// a workflow, is sent to this subscription.
+    pub fn matches(&self, event_type: &str, workflow_id: Option<Uuid>) -> bool {
+        let listens = self
+            .event_types
+            .iter()
+            .any(|listened| listened == event_type || listened == ALL_EVENT_TYPES);
+
+        self.active && listens && self.workflow_id.is_none_or(|filter| workflow_id == Some(filter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(event_types: &[&str], workflow_id: Option<Uuid>) -> WebhookSubscription {
+        WebhookSubscription::new(CreateWebhookSubscription {
+            url: "https://example.com/hooks".to_string(),
+            secret: None,
+            event_types: event_types.iter().map(|event_type| event_type.to_string()).collect(),
+            workflow_id,
+        })
+    }
+
+    #[test]
+    fn test_subscription_matching() {
+        let workflow_id = Uuid::new_v4();
+
+        let moves = subscription(&["case.moved"], None);
+        assert!(moves.matches("case.moved", Some(workflow_id)));
+        assert!(moves.matches("case.moved", None));
+        assert!(!moves.matches("case.created", Some(workflow_id)));
+
+        let scoped = subscription(&["*"], Some(workflow_id));
+        assert!(scoped.matches("execution.failed", Some(workflow_id)));
+        assert!(!scoped.matches("case.created", Some(Uuid::new_v4())));
+        assert!(!scoped.matches("order.created", None));
+
+        let inactive = WebhookSubscription { active: false, ..moves };
+        assert!(!inactive.matches("case.moved", None));
+    }
+}
@@ -1,9 +1,21 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::services::webhook_template;
 
-use super::automation::{WorkflowAutomations, WorkflowSlaConfig};
+use super::automation::{
+    AutomationAction, AutomationLimits, Condition, WorkflowAutomations, WorkflowSlaConfig, CONDITION_OPERATORS,
+};
+use super::conflict::DataConflictPolicy;
+use super::error_code::ErrorCode;
+use super::phase::Phase;
+use super::webhook_auth::{self, WebhookAuth};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Workflow {
@@ -11,12 +23,27 @@ pub struct Workflow {
     pub name: String,
 
     #[sqlx(json)]
-    pub phases: Vec<String>,
+    pub phases: Vec<Phase>,
 
     pub initial_phase: String,
 
     pub webhook_url: Option<String>,
 
+    /// The body of the webhooks sent to `webhook_url`, with `${case.*}`
+    /// placeholders, instead of the default payload.
+    #[serde(default)]
+    pub webhook_template: Option<Value>,
+
+    /// Extra headers sent with the webhooks to `webhook_url`.
+    #[sqlx(json)]
+    #[serde(default)]
+    pub webhook_headers: BTreeMap<String, String>,
+
+    /// Credentials sent with the webhooks to `webhook_url`.
+    #[sqlx(json(nullable))]
+    #[serde(default)]
+    pub webhook_auth: Option<WebhookAuth>,
+
     pub active: bool,
 
     pub description: Option<String>,
@@ -27,21 +54,140 @@ pub struct Workflow {
     #[sqlx(json)]
     pub sla_config: Option<WorkflowSlaConfig>,
 
+    #[sqlx(json)]
+    pub execution_limits: AutomationLimits,
+
+    /// How concurrent writes to the same case data field are resolved.
+    #[sqlx(json)]
+    #[serde(default)]
+    pub data_conflicts: DataConflictPolicy,
+
+    /// The phases each phase may move to. A phase without an entry can
+    /// move to any phase.
+    #[sqlx(json)]
+    #[serde(default)]
+    pub transitions: BTreeMap<String, Vec<String>>,
+
+    /// Data residency region whose database stores the workflow and its cases.
+    #[serde(default)]
+    pub region: Option<String>,
+
+    /// Goes up by one with every change; see [`WorkflowVersion`].
+    #[serde(default = "first_version")]
+    pub version: i32,
+
+    /// Who made the latest change, when the caller said.
+    #[serde(default)]
+    pub updated_by: Option<String>,
+
+    /// Set while the workflow is archived: hidden from listings and closed
+    /// to new cases.
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub archived_by: Option<String>,
+
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+fn first_version() -> i32 {
+    1
+}
+
+/// The workflow definition as it was after one change.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WorkflowVersion {
+    pub workflow_id: Uuid,
+    pub version: i32,
+    pub name: String,
+
+    #[sqlx(json)]
+    pub phases: Vec<Phase>,
+
+    pub initial_phase: String,
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub webhook_template: Option<Value>,
+    #[sqlx(json)]
+    #[serde(default)]
+    pub webhook_headers: BTreeMap<String, String>,
+    #[sqlx(json(nullable))]
+    #[serde(default)]
+    pub webhook_auth: Option<WebhookAuth>,
+    pub description: Option<String>,
+
+    #[sqlx(json)]
+    pub automations: Option<WorkflowAutomations>,
+
+    #[sqlx(json)]
+    pub sla_config: Option<WorkflowSlaConfig>,
+
+    #[sqlx(json)]
+    pub execution_limits: AutomationLimits,
+
+    #[sqlx(json)]
+    #[serde(default)]
+    pub data_conflicts: DataConflictPolicy,
+
+    #[sqlx(json)]
+    #[serde(default)]
+    pub transitions: BTreeMap<String, Vec<String>>,
+
+    pub active: bool,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A workflow's `webhook_url` and the template its webhooks are shaped by.
+#[derive(Debug, Clone, Copy)]
+pub struct WorkflowWebhook<'a> {
+    pub workflow_id: Uuid,
+    pub url: &'a str,
+    pub template: Option<&'a Value>,
+}
+
+/// The parts of a workflow a case list needs to show its cases, embedded
+/// with `GET /cases?embed=workflow`.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WorkflowSummary {
+    pub id: Uuid,
+    pub name: String,
+
+    #[sqlx(json)]
+    pub phases: Vec<Phase>,
+
+    pub initial_phase: String,
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateWorkflow {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
     pub name: String,
-    pub phases: Vec<String>,
+    #[validate(custom(function = "crate::models::validation::validate_phases"))]
+    pub phases: Vec<Phase>,
+    #[validate(custom(function = "crate::models::validation::validate_phase_name"))]
     pub initial_phase: String,
+    #[validate(url(message = "must be a valid URL"))]
     pub webhook_url: Option<String>,
+    pub webhook_template: Option<Value>,
+    pub webhook_headers: Option<BTreeMap<String, String>>,
+    pub webhook_auth: Option<WebhookAuth>,
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
     pub description: Option<String>,
+    #[validate(custom(function = "crate::models::validation::validate_automations"))]
     pub automations: Option<WorkflowAutomations>,
     pub sla_config: Option<WorkflowSlaConfig>,
+    #[validate(nested)]
+    pub execution_limits: Option<AutomationLimits>,
+    #[validate(nested)]
+    pub data_conflicts: Option<DataConflictPolicy>,
+    pub transitions: Option<BTreeMap<String, Vec<String>>>,
     #[serde(default = "default_active")]
     pub active: bool,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub created_by: Option<String>,
 }
 
 fn default_active() -> bool {
@@ -51,18 +197,112 @@ fn default_active() -> bool {
 #[derive(Debug, Deserialize)]
 pub struct UpdateWorkflow {
     pub name: Option<String>,
-    pub phases: Option<Vec<String>>,
+    pub phases: Option<Vec<Phase>>,
     pub initial_phase: Option<String>,
     pub webhook_url: Option<String>,
+    pub webhook_template: Option<Value>,
+    pub webhook_headers: Option<BTreeMap<String, String>>,
+    /// A credential sent back as it is redacted keeps the stored one.
+    pub webhook_auth: Option<WebhookAuth>,
     pub description: Option<String>,
     pub automations: Option<WorkflowAutomations>,
     pub sla_config: Option<WorkflowSlaConfig>,
+    pub execution_limits: Option<AutomationLimits>,
+    pub data_conflicts: Option<DataConflictPolicy>,
+    pub transitions: Option<BTreeMap<String, Vec<String>>>,
     pub active: Option<bool>,
+    pub updated_by: Option<String>,
+}
+
+/// Body of `POST /workflows/{id}/rollback/{version}`; optional.
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct RollbackWorkflow {
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub updated_by: Option<String>,
+}
+
+/// Body of `POST /workflows/{id}/archive`; optional.
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct ArchiveWorkflow {
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub archived_by: Option<String>,
+}
+
+/// Body of `POST /workflows/{id}/webhook-secret`; optional.
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct SetWebhookSecret {
+    /// Generated when omitted.
+    #[validate(length(min = 16, max = 255, message = "must be between 16 and 255 characters"))]
+    pub secret: Option<String>,
+}
+
+impl SetWebhookSecret {
+    pub fn into_secret(self) -> String {
+        self.secret
+            .unwrap_or_else(|| format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()))
+    }
+}
+
+/// Query of `DELETE /workflows/{id}`.
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteWorkflowQuery {
+    /// Deletes the workflow's cases along with it.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Query of `GET /workflows`.
+#[derive(Debug, Default, Deserialize)]
+pub struct WorkflowListQuery {
+    /// Lists archived workflows too.
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// Body of `POST /workflows/{id}/duplicate`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct DuplicateWorkflow {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub name: String,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub created_by: Option<String>,
+}
+
+/// A problem in a workflow definition, at a path such as
+/// `automations[0].actions[1].then[0].phase`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DefinitionError {
+    pub code: ErrorCode,
+    pub path: String,
+    pub message: String,
+}
+
+impl DefinitionError {
+    pub fn new(code: ErrorCode, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { code, path: path.into(), message: message.into() }
+    }
+}
+
+fn default_preview_sample_size() -> i64 {
+    100
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PreviewAutomations {
+    #[validate(custom(function = "crate::models::validation::validate_automations"))]
+    pub automations: WorkflowAutomations,
+
+    /// How many of the workflow's most recent cases to evaluate.
+    #[serde(default = "default_preview_sample_size")]
+    #[validate(range(min = 1, max = 1000, message = "must be between 1 and 1000"))]
+    pub sample_size: i64,
 }
 
 impl Workflow {
     pub fn new(create: CreateWorkflow) -> Result<Self, String> {
-        if !create.phases.contains(&create.initial_phase) {
+        if !create.phases.iter().any(|phase| phase.name == create.initial_phase) {
             return Err(format!(
                 "Initial phase '{}' must be in phases list",
                 create.initial_phase
@@ -73,34 +313,233 @@ impl Workflow {
             return Err("Phases list cannot be empty".to_string());
         }
 
+        Ok(Self::draft(create))
+    }
+
+    /// The workflow `create` describes, without checking it; see
+    /// [`Workflow::definition_errors`].
+    pub fn draft(create: CreateWorkflow) -> Self {
         let now = Utc::now();
-        Ok(Self {
+        Self {
             id: Uuid::new_v4(),
             name: create.name,
             phases: create.phases,
             initial_phase: create.initial_phase,
             webhook_url: create.webhook_url,
+            webhook_template: create.webhook_template,
+            webhook_headers: create.webhook_headers.unwrap_or_default(),
+            webhook_auth: create.webhook_auth,
             description: create.description,
             automations: create.automations,
             sla_config: create.sla_config,
+            execution_limits: create.execution_limits.unwrap_or_default(),
+            data_conflicts: create.data_conflicts.unwrap_or_default(),
+            transitions: create.transitions.unwrap_or_default(),
             active: create.active,
+            region: None,
+            version: 1,
+            updated_by: create.created_by,
+            archived_at: None,
+            archived_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// A copy with its own id and the given name, keeping everything else:
+    /// phases, webhook, automations, SLA and execution limits.
+    pub fn duplicate(&self, payload: DuplicateWorkflow) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: Uuid::new_v4(),
+            name: payload.name,
+            active: payload.active,
+            version: 1,
+            updated_by: payload.created_by,
+            archived_at: None,
+            archived_by: None,
             created_at: now,
             updated_at: now,
+            ..self.clone()
+        }
+    }
+
+    /// Puts back the definition saved in `version`. The id, region, version
+    /// number and timestamps are kept.
+    pub fn restore(&mut self, version: &WorkflowVersion) {
+        self.name = version.name.clone();
+        self.phases = version.phases.clone();
+        self.initial_phase = version.initial_phase.clone();
+        self.webhook_url = version.webhook_url.clone();
+        self.webhook_template = version.webhook_template.clone();
+        self.webhook_headers = version.webhook_headers.clone();
+        self.webhook_auth = version.webhook_auth.clone();
+        self.description = version.description.clone();
+        self.automations = version.automations.clone();
+        self.sla_config = version.sla_config.clone();
+        self.execution_limits = version.execution_limits.clone();
+        self.data_conflicts = version.data_conflicts.clone();
+        self.transitions = version.transitions.clone();
+        self.active = version.active;
+    }
+
+    /// Checks that the definition only refers to its own phases and to
+    /// condition operators the engine supports. Field-level rules such as
+    /// phase name syntax are `CreateWorkflow`'s validators.
+    pub fn definition_errors(&self) -> Vec<DefinitionError> {
+        let mut errors = Vec::new();
+
+        if self.phases.is_empty() {
+            errors.push(DefinitionError::new(ErrorCode::PhaseInvalid, "phases", "phases list cannot be empty"));
+        }
+        if !self.has_phase(&self.initial_phase) {
+            errors.push(DefinitionError::new(
+                ErrorCode::PhaseInvalid,
+                "initial_phase",
+                format!("initial phase '{}' must be in phases list", self.initial_phase),
+            ));
+        }
+
+        for (idx, automation) in self.automations.iter().flat_map(|config| &config.automations).enumerate() {
+            let path = format!("automations[{}]", idx);
+            if !self.has_phase(&automation.phase) {
+                errors.push(DefinitionError::new(
+                    ErrorCode::PhaseInvalid,
+                    format!("{}.phase", path),
+                    format!("phase '{}' not found in workflow", automation.phase),
+                ));
+            }
+            self.check_actions(&automation.actions, &format!("{}.actions", path), &mut errors);
+        }
+
+        for (from, targets) in &self.transitions {
+            if !self.has_phase(from) {
+                errors.push(DefinitionError::new(
+                    ErrorCode::TransitionInvalid,
+                    format!("transitions.{}", from),
+                    format!("phase '{}' not found in workflow", from),
+                ));
+            }
+            for (idx, to) in targets.iter().enumerate().filter(|(_, to)| !self.has_phase(to)) {
+                errors.push(DefinitionError::new(
+                    ErrorCode::TransitionInvalid,
+                    format!("transitions.{}[{}]", from, idx),
+                    format!("phase '{}' not found in workflow", to),
+                ));
+            }
+        }
+
+        for (path, placeholder) in self.webhook_template.iter().flat_map(webhook_template::unknown_placeholders) {
+            let path = if path.is_empty() { "webhook_template".to_string() } else { format!("webhook_template.{}", path) };
+            errors.push(DefinitionError::new(
+                ErrorCode::WebhookTemplateInvalid,
+                path,
+                format!("unknown placeholder '${{{}}}', expected ${{action}} or ${{case.*}}", placeholder),
+            ));
+        }
+
+        for (path, message) in webhook_auth::webhook_request_errors(&self.webhook_headers, self.webhook_auth.as_ref()) {
+            errors.push(DefinitionError::new(ErrorCode::WebhookHeaderInvalid, path, message));
+        }
+
+        let mut sla_phases: Vec<_> = self.sla_config.iter().flat_map(|config| config.phase_slas.keys()).collect();
+        sla_phases.sort();
+        for phase in sla_phases.into_iter().filter(|phase| !self.has_phase(phase)) {
+            errors.push(DefinitionError::new(
+                ErrorCode::SlaConfigInvalid,
+                format!("sla_config.{}", phase),
+                format!("phase '{}' not found in workflow", phase),
+            ));
+        }
+
+        errors
+    }
+
+    fn check_actions(&self, actions: &[AutomationAction], path: &str, errors: &mut Vec<DefinitionError>) {
+        for (idx, action) in actions.iter().enumerate() {
+            let path = format!("{}[{}]", path, idx);
+            match action {
+                AutomationAction::MoveToPhase { phase, .. } if !self.has_phase(phase) => {
+                    errors.push(DefinitionError::new(
+                        ErrorCode::PhaseInvalid,
+                        format!("{}.phase", path),
+                        format!("phase '{}' not found in workflow", phase),
+                    ));
+                }
+                AutomationAction::Conditional { condition, then, r#else, .. } => {
+                    let operators: Vec<(String, &str)> = match condition {
+                        Condition::Simple { operator, .. } => vec![(format!("{}.operator", path), operator.as_str())],
+                        Condition::Complex { conditions, .. } => conditions
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, cond)| (format!("{}.conditions[{}].op", path, idx), cond.operator.as_str()))
+                            .collect(),
+                    };
+                    for (operator_path, operator) in operators {
+                        if !CONDITION_OPERATORS.contains(&operator) {
+                            errors.push(DefinitionError::new(
+                                ErrorCode::ConditionInvalid,
+                                operator_path,
+                                format!("unsupported operator '{}', expected one of {}", operator, CONDITION_OPERATORS.join(", ")),
+                            ));
+                        }
+                    }
+
+                    self.check_actions(then, &format!("{}.then", path), errors);
+                    if let Some(otherwise) = r#else {
+                        self.check_actions(otherwise, &format!("{}.else", path), errors);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Where the workflow's webhooks go, if it has a `webhook_url`.
+    pub fn webhook(&self) -> Option<WorkflowWebhook<'_>> {
+        Some(WorkflowWebhook {
+            workflow_id: self.id,
+            url: self.webhook_url.as_deref()?,
+            template: self.webhook_template.as_ref(),
         })
     }
 
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    pub fn phase(&self, phase_name: &str) -> Option<&Phase> {
+        self.phases.iter().find(|p| p.name == phase_name)
+    }
+
     pub fn has_phase(&self, phase_name: &str) -> bool {
-        self.phases.iter().any(|p| p == phase_name)
+        self.phase(phase_name).is_some()
     }
 
     pub fn phase_index(&self, phase_name: &str) -> Option<usize> {
-        self.phases.iter().position(|p| p == phase_name)
+        self.phases.iter().position(|p| p.name == phase_name)
+    }
+
+    /// The phases `from` may move to, when the workflow restricts it.
+    pub fn allowed_transitions(&self, from: &str) -> Option<&[String]> {
+        self.transitions.get(from).map(Vec::as_slice)
+    }
+
+    /// Staying in the same phase is always allowed.
+    pub fn allows_transition(&self, from: &str, to: &str) -> bool {
+        from == to || self.allowed_transitions(from).is_none_or(|allowed| allowed.iter().any(|phase| phase == to))
+    }
+
+    /// The phase's WIP limit, if it has one.
+    pub fn wip_limit(&self, phase_name: &str) -> Option<u32> {
+        self.phase(phase_name).and_then(|p| p.wip_limit)
     }
 
     pub fn next_phase(&self, current_phase: &str) -> Option<String> {
         self.phase_index(current_phase)
             .and_then(|idx| self.phases.get(idx + 1))
-            .cloned()
+            .map(|p| p.name.clone())
     }
 
     pub fn previous_phase(&self, current_phase: &str) -> Option<String> {
@@ -112,7 +551,7 @@ impl Workflow {
                     None
                 }
             })
-            .cloned()
+            .map(|p| p.name.clone())
     }
 }
 
@@ -125,17 +564,24 @@ mod tests {
         let create = CreateWorkflow {
             name: "Invoice Processing".to_string(),
             phases: vec![
-                "OCR".to_string(),
-                "Validation".to_string(),
-                "SAP".to_string(),
-                "Approved".to_string(),
+                "OCR".into(),
+                "Validation".into(),
+                "SAP".into(),
+                "Approved".into(),
             ],
             initial_phase: "OCR".to_string(),
             webhook_url: Some("https://backend.com/webhook".to_string()),
+            webhook_template: None,
+            webhook_headers: None,
+            webhook_auth: None,
             description: Some("Invoice workflow".to_string()),
             automations: None,
             sla_config: None,
+            execution_limits: None,
+            data_conflicts: None,
+            transitions: None,
             active: true,
+            created_by: None,
         };
 
         let workflow = Workflow::new(create).unwrap();
@@ -144,17 +590,56 @@ mod tests {
         assert_eq!(workflow.initial_phase, "OCR");
     }
 
+    #[test]
+    fn test_workflow_duplicate() {
+        let create = CreateWorkflow {
+            name: "Invoice Processing".to_string(),
+            phases: vec!["OCR".into(), "Approved".into()],
+            initial_phase: "OCR".to_string(),
+            webhook_url: None,
+            webhook_template: None,
+            webhook_headers: None,
+            webhook_auth: None,
+            description: Some("Invoice workflow".to_string()),
+            automations: None,
+            sla_config: None,
+            execution_limits: None,
+            data_conflicts: None,
+            transitions: None,
+            active: true,
+            created_by: None,
+        };
+        let workflow = Workflow::new(create).unwrap();
+
+        let copy = workflow.duplicate(DuplicateWorkflow {
+            name: "Invoice Processing v2".to_string(),
+            active: false,
+            created_by: Some("ana".to_string()),
+        });
+        assert_ne!(copy.id, workflow.id);
+        assert_eq!((copy.name.as_str(), copy.active), ("Invoice Processing v2", false));
+        assert_eq!((copy.version, copy.updated_by.as_deref()), (1, Some("ana")));
+        assert_eq!((copy.phases, copy.description), (workflow.phases, workflow.description));
+    }
+
     #[test]
     fn test_invalid_initial_phase() {
         let create = CreateWorkflow {
             name: "Test".to_string(),
-            phases: vec!["A".to_string(), "B".to_string()],
+            phases: vec!["A".into(), "B".into()],
             initial_phase: "C".to_string(),
             webhook_url: None,
+            webhook_template: None,
+            webhook_headers: None,
+            webhook_auth: None,
             description: None,
             automations: None,
             sla_config: None,
+            execution_limits: None,
+            data_conflicts: None,
+            transitions: None,
             active: true,
+            created_by: None,
         };
 
         let result = Workflow::new(create);
@@ -167,16 +652,27 @@ mod tests {
             id: Uuid::new_v4(),
             name: "Test".to_string(),
             phases: vec![
-                "First".to_string(),
-                "Second".to_string(),
-                "Third".to_string(),
+                "First".into(),
+                "Second".into(),
+                "Third".into(),
             ],
             initial_phase: "First".to_string(),
             webhook_url: None,
+            webhook_template: None,
+            webhook_headers: BTreeMap::new(),
+            webhook_auth: None,
             active: true,
             description: None,
             automations: None,
             sla_config: None,
+            execution_limits: AutomationLimits::default(),
+            data_conflicts: DataConflictPolicy::default(),
+            transitions: BTreeMap::new(),
+            region: None,
+            version: 1,
+            updated_by: None,
+            archived_at: None,
+            archived_by: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -189,4 +685,72 @@ mod tests {
         assert_eq!(workflow.previous_phase("Second"), Some("First".to_string()));
         assert_eq!(workflow.previous_phase("First"), None);
     }
+
+    #[test]
+    fn test_definition_errors() {
+        let workflow: Workflow = serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "name": "Sales",
+            "phases": ["Lead", "Won"],
+            "initial_phase": "Lead",
+            "active": true,
+            "execution_limits": {},
+            "automations": {"automations": [
+                {"trigger": "on_enter", "phase": "Lead", "actions": [
+                    {"type": "conditional", "field": "data.amount", "operator": ">", "value": 10,
+                     "then": [{"type": "move_to_phase", "phase": "Won"}],
+                     "else": [{"type": "move_to_phase", "phase": "Lost"}]},
+                    {"type": "conditional", "operator": "AND",
+                     "conditions": [{"field": "data.a", "op": "==", "value": 1}, {"field": "data.b", "op": "~", "value": 2}],
+                     "then": []}
+                ]},
+                {"trigger": "on_exit", "phase": "Qualified", "actions": []}
+            ]},
+            "sla_config": {"Lead": {"hours": 4}, "Closed": {"hours": 1}},
+            "created_at": Utc::now(),
+            "updated_at": Utc::now(),
+        }))
+        .unwrap();
+
+        let paths: Vec<_> = workflow.definition_errors().into_iter().map(|error| error.path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "automations[0].actions[0].else[0].phase",
+                "automations[0].actions[1].conditions[1].op",
+                "automations[1].phase",
+                "sla_config.Closed",
+            ]
+        );
+
+        let valid = Workflow { automations: None, sla_config: None, ..workflow };
+        assert!(valid.definition_errors().is_empty());
+    }
+
+    #[test]
+    fn test_transitions() {
+        let mut workflow: Workflow = serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "name": "Approvals",
+            "phases": ["Draft", "Review", "Approved", "Rejected"],
+            "initial_phase": "Draft",
+            "active": true,
+            "execution_limits": {},
+            "transitions": {"Review": ["Approved", "Rejected"], "Approved": []},
+            "created_at": Utc::now(),
+            "updated_at": Utc::now(),
+        }))
+        .unwrap();
+
+        assert!(workflow.allows_transition("Review", "Rejected"));
+        assert!(!workflow.allows_transition("Review", "Draft"));
+        assert!(!workflow.allows_transition("Approved", "Review"));
+        assert!(workflow.allows_transition("Approved", "Approved"));
+        assert!(workflow.allows_transition("Draft", "Approved"));
+        assert!(workflow.definition_errors().is_empty());
+
+        workflow.transitions.insert("Closed".to_string(), vec!["Draft".to_string(), "Archived".to_string()]);
+        let paths: Vec<_> = workflow.definition_errors().into_iter().map(|error| error.path).collect();
+        assert_eq!(paths, vec!["transitions.Closed", "transitions.Closed[1]"]);
+    }
 }
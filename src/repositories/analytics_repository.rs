@@ -0,0 +1,155 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PhaseCount {
+    pub phase: String,
+    pub cases: i64,
+    /// Active and paused cases.
+    pub in_progress: i64,
+}
+
+/// How long cases stayed in a phase, and how often they overran its SLA.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PhaseTiming {
+    pub phase: String,
+    /// Stints in the phase that ended in the period.
+    pub samples: i64,
+    pub avg_secs: Option<f64>,
+    pub p50_secs: Option<f64>,
+    pub p95_secs: Option<f64>,
+    pub max_secs: Option<f64>,
+    /// Stints measured against the phase's SLA: those that ended in the
+    /// period, plus open ones already past the SLA.
+    pub sla_samples: i64,
+    pub sla_breaches: i64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DailyThroughput {
+    /// A UTC day.
+    pub day: NaiveDate,
+    pub created: i64,
+    pub completed: i64,
+}
+
+/// Aggregates over the cases of a workflow. Only moves in the workflow the
+/// cases were created in count; deleted cases are left out.
+pub struct AnalyticsRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> AnalyticsRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn cases_per_phase(&self, workflow_id: Uuid) -> Result<Vec<PhaseCount>> {
+        let counts = sqlx::query_as::<_, PhaseCount>(
+            "SELECT current_phase AS phase,
+                    COUNT(*) AS cases,
+                    COUNT(*) FILTER (WHERE status IN ('active', 'paused')) AS in_progress
+             FROM orchepy_cases
+             WHERE workflow_id = $1 AND deleted_at IS NULL
+             GROUP BY current_phase
+             ORDER BY current_phase"
+        )
+        .bind(workflow_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Time in each phase from the case history, for stints that ended
+    /// between `since` and `now`. SLAs are those currently configured on
+    /// the workflow; an open stint of a case no longer in progress never
+    /// counts as a breach.
+    pub async fn phase_timings(
+        &self,
+        workflow_id: Uuid,
+        since: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<PhaseTiming>> {
+        let timings = sqlx::query_as::<_, PhaseTiming>(
+            "WITH stints AS (
+                SELECT h.to_phase AS phase,
+                       h.transitioned_at AS entered_at,
+                       LEAD(h.transitioned_at) OVER (PARTITION BY h.case_id ORDER BY h.transitioned_at) AS left_at,
+                       c.status IN ('active', 'paused') AS in_progress
+                FROM orchepy_case_history h
+                JOIN orchepy_cases c ON c.id = h.case_id
+                WHERE c.workflow_id = $1 AND c.deleted_at IS NULL
+                  AND h.workflow_id IS NULL AND h.to_status IS NULL
+             ), s AS (
+                SELECT st.phase,
+                       EXTRACT(EPOCH FROM (st.left_at - st.entered_at))::float8 AS secs,
+                       EXTRACT(EPOCH FROM (COALESCE(st.left_at, $3) - st.entered_at))::float8 AS elapsed,
+                       st.left_at IS NULL AND NOT st.in_progress AS abandoned,
+                       (w.sla_config -> st.phase ->> 'hours')::float8 * 3600 AS sla_secs
+                FROM stints st, orchepy_workflows w
+                WHERE w.id = $1 AND COALESCE(st.left_at, $3) >= $2 AND st.entered_at <= $3
+             )
+             SELECT phase,
+                    COUNT(secs) AS samples,
+                    AVG(secs) AS avg_secs,
+                    percentile_cont(0.5) WITHIN GROUP (ORDER BY secs) AS p50_secs,
+                    percentile_cont(0.95) WITHIN GROUP (ORDER BY secs) AS p95_secs,
+                    MAX(secs) AS max_secs,
+                    COUNT(*) FILTER (WHERE secs IS NOT NULL AND sla_secs IS NOT NULL
+                                        OR secs IS NULL AND NOT abandoned AND elapsed > sla_secs) AS sla_samples,
+                    COUNT(*) FILTER (WHERE NOT abandoned AND elapsed > sla_secs) AS sla_breaches
+             FROM s
+             GROUP BY phase
+             ORDER BY phase"
+        )
+        .bind(workflow_id)
+        .bind(since)
+        .bind(now)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(timings)
+    }
+
+    /// Cases created and completed on each UTC day from `since` to `now`,
+    /// including days with neither.
+    pub async fn daily_throughput(
+        &self,
+        workflow_id: Uuid,
+        since: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<DailyThroughput>> {
+        let days = sqlx::query_as::<_, DailyThroughput>(
+            "WITH days AS (
+                SELECT generate_series(
+                    date_trunc('day', $2 AT TIME ZONE 'UTC'),
+                    date_trunc('day', $3 AT TIME ZONE 'UTC'),
+                    interval '1 day'
+                ) AS day
+             ), cases AS (
+                SELECT created_at AT TIME ZONE 'UTC' AS created_at,
+                       CASE WHEN status = 'completed' THEN completed_at AT TIME ZONE 'UTC' END AS completed_at
+                FROM orchepy_cases
+                WHERE workflow_id = $1 AND deleted_at IS NULL
+             )
+             SELECT d.day::date AS day,
+                    (SELECT COUNT(*) FROM cases c
+                     WHERE c.created_at >= d.day AND c.created_at < d.day + interval '1 day') AS created,
+                    (SELECT COUNT(*) FROM cases c
+                     WHERE c.completed_at >= d.day AND c.completed_at < d.day + interval '1 day') AS completed
+             FROM days d
+             ORDER BY d.day"
+        )
+        .bind(workflow_id)
+        .bind(since)
+        .bind(now)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(days)
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::automation::AutomationRun;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WorkflowFailureCount {
+    pub workflow_id: Uuid,
+    pub workflow_name: String,
+    pub failures: i64,
+}
+
+pub struct AutomationRunRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> AutomationRunRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, run: &AutomationRun) -> Result<()> {
+        create_run_in(&mut *self.pool.acquire().await?, run).await
+    }
+
+    pub async fn list_by_case(&self, case_id: Uuid, limit: i64) -> Result<Vec<AutomationRun>> {
+        let runs = sqlx::query_as::<_, AutomationRun>(
+            "SELECT * FROM orchepy_automation_runs WHERE case_id = $1 ORDER BY started_at DESC LIMIT $2"
+        )
+        .bind(case_id)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    pub async fn find_by_case(&self, case_id: Uuid, run_id: Uuid) -> Result<Option<AutomationRun>> {
+        let run = sqlx::query_as::<_, AutomationRun>(
+            "SELECT * FROM orchepy_automation_runs WHERE id = $1 AND case_id = $2"
+        )
+        .bind(run_id)
+        .bind(case_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(run)
+    }
+
+    /// Marks up to `limit` transiently failed runs as retried and returns
+    /// them. A run is due once `backoff_secs` times its attempt number has
+    /// passed since it finished; runs older than `since` or already at
+    /// `max_attempts` are left alone.
+    pub async fn claim_retryable(
+        &self,
+        since: DateTime<Utc>,
+        max_attempts: i32,
+        backoff_secs: i64,
+        limit: i64,
+    ) -> Result<Vec<AutomationRun>> {
+        let runs = sqlx::query_as::<_, AutomationRun>(
+            "UPDATE orchepy_automation_runs SET retried_at = NOW()
+             WHERE id IN (
+                 SELECT id FROM orchepy_automation_runs
+                 WHERE transient AND retried_at IS NULL
+                   AND attempt < $1
+                   AND completed_at >= $2
+                   AND completed_at <= NOW() - make_interval(secs => $3 * attempt)
+                 ORDER BY completed_at
+                 LIMIT $4
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING *"
+        )
+        .bind(max_attempts)
+        .bind(since)
+        .bind(backoff_secs as f64)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    /// Runs that failed or hit a limit since `since`.
+    pub async fn count_failed_since(&self, since: DateTime<Utc>) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM orchepy_automation_runs
+             WHERE status IN ('failed', 'limit_exceeded') AND started_at >= $1"
+        )
+        .bind(since)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    pub async fn list_failed_since(&self, since: DateTime<Utc>, limit: i64) -> Result<Vec<AutomationRun>> {
+        let runs = sqlx::query_as::<_, AutomationRun>(
+            "SELECT * FROM orchepy_automation_runs
+             WHERE status IN ('failed', 'limit_exceeded') AND started_at >= $1
+             ORDER BY started_at DESC
+             LIMIT $2"
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+
+    pub async fn top_failing_workflows(&self, since: DateTime<Utc>, limit: i64) -> Result<Vec<WorkflowFailureCount>> {
+        let workflows = sqlx::query_as::<_, WorkflowFailureCount>(
+            "SELECT r.workflow_id, w.name AS workflow_name, COUNT(*) AS failures
+             FROM orchepy_automation_runs r
+             JOIN orchepy_workflows w ON w.id = r.workflow_id
+             WHERE r.status IN ('failed', 'limit_exceeded') AND r.started_at >= $1
+             GROUP BY r.workflow_id, w.name
+             ORDER BY failures DESC, w.name
+             LIMIT $2"
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(workflows)
+    }
+
+    pub async fn list_retries(&self, run_id: Uuid) -> Result<Vec<AutomationRun>> {
+        let runs = sqlx::query_as::<_, AutomationRun>(
+            "SELECT * FROM orchepy_automation_runs WHERE retry_of = $1 ORDER BY attempt"
+        )
+        .bind(run_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(runs)
+    }
+}
+
+pub(crate) async fn create_run_in(conn: &mut PgConnection, run: &AutomationRun) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO orchepy_automation_runs (id, case_id, workflow_id, trigger, phase, status, actions_executed, total_delay_ms, error, started_at, completed_at, conditions, from_phase, transient, attempt, retry_of, run_as, workflow_version, definition_hash)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)"
+    )
+    .bind(run.id)
+    .bind(run.case_id)
+    .bind(run.workflow_id)
+    .bind(&run.trigger)
+    .bind(&run.phase)
+    .bind(&run.status)
+    .bind(run.actions_executed)
+    .bind(run.total_delay_ms)
+    .bind(&run.error)
+    .bind(run.started_at)
+    .bind(run.completed_at)
+    .bind(sqlx::types::Json(&run.conditions))
+    .bind(&run.from_phase)
+    .bind(run.transient)
+    .bind(run.attempt)
+    .bind(run.retry_of)
+    .bind(&run.run_as)
+    .bind(run.workflow_version)
+    .bind(&run.definition_hash)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::attachment::CaseAttachment;
+
+pub struct CaseAttachmentRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> CaseAttachmentRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns false when the case does not exist or is deleted.
+    pub async fn create(&self, attachment: &CaseAttachment) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO orchepy_case_attachments (id, case_id, filename, content_type, size_bytes, storage_key, uploaded_by, created_at)
+             SELECT $1, $2, $3, $4, $5, $6, $7, $8
+             WHERE EXISTS (SELECT 1 FROM orchepy_cases WHERE id = $2 AND deleted_at IS NULL)"
+        )
+        .bind(attachment.id)
+        .bind(attachment.case_id)
+        .bind(&attachment.filename)
+        .bind(&attachment.content_type)
+        .bind(attachment.size_bytes)
+        .bind(&attachment.storage_key)
+        .bind(&attachment.uploaded_by)
+        .bind(attachment.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The case's attachments, oldest first.
+    pub async fn list_by_case(&self, case_id: Uuid) -> Result<Vec<CaseAttachment>> {
+        let attachments = sqlx::query_as::<_, CaseAttachment>(
+            "SELECT * FROM orchepy_case_attachments WHERE case_id = $1 ORDER BY created_at, id"
+        )
+        .bind(case_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(attachments)
+    }
+
+    pub async fn find(&self, case_id: Uuid, attachment_id: Uuid) -> Result<Option<CaseAttachment>> {
+        let attachment = sqlx::query_as::<_, CaseAttachment>(
+            "SELECT * FROM orchepy_case_attachments WHERE id = $1 AND case_id = $2"
+        )
+        .bind(attachment_id)
+        .bind(case_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(attachment)
+    }
+
+    /// The deleted attachment, or `None` when the case has no such one.
+    pub async fn delete(&self, case_id: Uuid, attachment_id: Uuid) -> Result<Option<CaseAttachment>> {
+        let attachment = sqlx::query_as::<_, CaseAttachment>(
+            "DELETE FROM orchepy_case_attachments WHERE id = $1 AND case_id = $2 RETURNING *"
+        )
+        .bind(attachment_id)
+        .bind(case_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(attachment)
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::comment::CaseComment;
+
+pub struct CaseCommentRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> CaseCommentRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores the comment and bumps the case's `comment_count`. Returns false
+    /// when the case does not exist or is deleted.
+    pub async fn create(&self, comment: &CaseComment) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query(
+            "UPDATE orchepy_cases SET comment_count = comment_count + 1 WHERE id = $1 AND deleted_at IS NULL"
+        )
+        .bind(comment.case_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO orchepy_case_comments (id, case_id, author, body, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(comment.id)
+        .bind(comment.case_id)
+        .bind(&comment.author)
+        .bind(&comment.body)
+        .bind(comment.created_at)
+        .bind(comment.updated_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn list_by_case(&self, case_id: Uuid, limit: i64) -> Result<Vec<CaseComment>> {
+        let comments = sqlx::query_as::<_, CaseComment>(
+            "SELECT * FROM orchepy_case_comments WHERE case_id = $1 ORDER BY created_at, id LIMIT $2"
+        )
+        .bind(case_id)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(comments)
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::link::CaseLink;
+
+pub struct CaseLinkRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> CaseLinkRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns false when the cases are already linked this way, or when
+    /// the link would make two cases each other's parent.
+    pub async fn create(&self, link: &CaseLink) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO orchepy_case_links (id, source_case_id, target_case_id, relation, created_by, created_at)
+             SELECT $1, $2, $3, $4, $5, $6
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM orchepy_case_links
+                 WHERE source_case_id = $3 AND target_case_id = $2 AND relation = $4
+             )
+             ON CONFLICT (source_case_id, target_case_id, relation) DO NOTHING"
+        )
+        .bind(link.id)
+        .bind(link.source_case_id)
+        .bind(link.target_case_id)
+        .bind(link.relation)
+        .bind(&link.created_by)
+        .bind(link.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Links on either side of the case, oldest first.
+    pub async fn list_by_case(&self, case_id: Uuid) -> Result<Vec<CaseLink>> {
+        let links = sqlx::query_as::<_, CaseLink>(
+            "SELECT * FROM orchepy_case_links
+             WHERE source_case_id = $1 OR target_case_id = $1
+             ORDER BY created_at"
+        )
+        .bind(case_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(links)
+    }
+
+    /// Returns false when the case has no such link.
+    pub async fn delete(&self, case_id: Uuid, link_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM orchepy_case_links
+             WHERE id = $1 AND (source_case_id = $2 OR target_case_id = $2)"
+        )
+        .bind(link_id)
+        .bind(case_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
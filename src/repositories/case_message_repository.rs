@@ -0,0 +1,92 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::message::{CaseMessage, MessageChannel};
+
+pub struct CaseMessageRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> CaseMessageRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, message: &CaseMessage) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO orchepy_case_messages (id, case_id, direction, channel, sender, recipient, subject, body, external_id, reply_token, public, attachments, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"
+        )
+        .bind(message.id)
+        .bind(message.case_id)
+        .bind(message.direction)
+        .bind(message.channel)
+        .bind(&message.sender)
+        .bind(&message.recipient)
+        .bind(&message.subject)
+        .bind(&message.body)
+        .bind(&message.external_id)
+        .bind(&message.reply_token)
+        .bind(message.public)
+        .bind(sqlx::types::Json(&message.attachments))
+        .bind(message.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_by_case(&self, case_id: Uuid, limit: i64) -> Result<Vec<CaseMessage>> {
+        let messages = sqlx::query_as::<_, CaseMessage>(
+            "SELECT * FROM orchepy_case_messages WHERE case_id = $1 ORDER BY created_at, id LIMIT $2"
+        )
+        .bind(case_id)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// The part of the conversation shown in the customer portal.
+    pub async fn list_public_by_case(&self, case_id: Uuid, limit: i64) -> Result<Vec<CaseMessage>> {
+        let messages = sqlx::query_as::<_, CaseMessage>(
+            "SELECT * FROM orchepy_case_messages WHERE case_id = $1 AND public ORDER BY created_at, id LIMIT $2"
+        )
+        .bind(case_id)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    pub async fn find_case_by_reply_token(&self, token: &str) -> Result<Option<Uuid>> {
+        let case_id = sqlx::query_scalar::<_, Uuid>(
+            "SELECT case_id FROM orchepy_case_messages WHERE reply_token = $1"
+        )
+        .bind(token)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(case_id)
+    }
+
+    /// The case of the most recent outbound message sent to `address` on
+    /// `channel`, for replies that carry no token (SMS, WhatsApp).
+    pub async fn find_case_by_last_recipient(&self, channel: MessageChannel, address: &str) -> Result<Option<Uuid>> {
+        let case_id = sqlx::query_scalar::<_, Uuid>(
+            "SELECT case_id FROM orchepy_case_messages
+             WHERE direction = 'outbound' AND channel = $1 AND lower(recipient) = lower($2)
+             ORDER BY created_at DESC
+             LIMIT 1"
+        )
+        .bind(channel)
+        .bind(address)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(case_id)
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::presence::CasePresence;
+
+pub struct CasePresenceRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> CasePresenceRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Marks `user` present on the case for `ttl` and clears the case's
+    /// expired entries. Someone whose presence had expired starts a new visit.
+    pub async fn heartbeat(&self, case_id: Uuid, user: &str, editing: bool, ttl: Duration) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM orchepy_case_presence WHERE case_id = $1 AND expires_at <= NOW()")
+            .bind(case_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO orchepy_case_presence (case_id, user_name, editing, first_seen_at, last_seen_at, expires_at)
+             VALUES ($1, $2, $3, NOW(), NOW(), NOW() + make_interval(secs => $4))
+             ON CONFLICT (case_id, user_name) DO UPDATE
+             SET editing = EXCLUDED.editing, last_seen_at = NOW(), expires_at = EXCLUDED.expires_at"
+        )
+        .bind(case_id)
+        .bind(user)
+        .bind(editing)
+        .bind(ttl.as_secs_f64())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Who is on the case now, longest present first.
+    pub async fn list_active(&self, case_id: Uuid) -> Result<Vec<CasePresence>> {
+        let presence = sqlx::query_as::<_, CasePresence>(
+            "SELECT * FROM orchepy_case_presence
+             WHERE case_id = $1 AND expires_at > NOW()
+             ORDER BY first_seen_at, user_name"
+        )
+        .bind(case_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(presence)
+    }
+
+    /// Returns false when the user wasn't present.
+    pub async fn leave(&self, case_id: Uuid, user: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM orchepy_case_presence WHERE case_id = $1 AND user_name = $2 AND expires_at > NOW()"
+        )
+        .bind(case_id)
+        .bind(user)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
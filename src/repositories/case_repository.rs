@@ -1,8 +1,30 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 
-use crate::models::case::{Case, CaseHistory, CaseStatus};
+use crate::models::board::{BoardColumn, BoardGrouping, BoardQuery};
+use crate::models::case::{
+    track_data_writes, Case, CaseHistory, CaseSearch, CaseStatus, DataFilter, DataFilterOp, FieldProvenance,
+};
+use crate::models::conflict::{DataConflictPolicy, FieldWrite};
+use crate::models::patch::{DataPatch, PatchOutcome};
+use crate::models::phase::PhaseMove;
+
+use super::case_revision_repository::{record_first_revisions_in, record_revision_in};
+
+/// Most rows a batch insert puts in one statement, keeping it well under
+/// Postgres' 65535 bind parameters.
+pub(crate) const BATCH_ROWS: usize = 500;
+
+#[derive(sqlx::FromRow)]
+struct BoardRow {
+    #[sqlx(flatten)]
+    case: Case,
+    board_key: Option<String>,
+    board_count: i64,
+}
 
 pub struct CaseRepository<'a> {
     pool: &'a PgPool,
@@ -14,27 +36,79 @@ impl<'a> CaseRepository<'a> {
     }
 
     pub async fn create(&self, case: &Case) -> Result<()> {
-        sqlx::query(
-            "INSERT INTO orchepy_cases (id, workflow_id, current_phase, previous_phase, data, status, metadata, created_at, updated_at, phase_entered_at)
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
-        )
-        .bind(case.id)
-        .bind(case.workflow_id)
-        .bind(&case.current_phase)
-        .bind(&case.previous_phase)
-        .bind(&case.data)
-        .bind(&case.status)
-        .bind(&case.metadata)
-        .bind(case.created_at)
-        .bind(case.updated_at)
-        .bind(case.phase_entered_at)
-        .execute(self.pool)
-        .await?;
+        let mut tx = self.pool.begin().await?;
+        create_in(&mut tx, case).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Inserts `cases` with their first data revisions in one transaction,
+    /// a few hundred rows per statement. Fails without inserting any if a
+    /// case id is already taken.
+    pub async fn create_batch(&self, cases: &[Case]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        if let Some(taken) = create_batch_in(&mut tx, &cases.iter().collect::<Vec<_>>()).await? {
+            anyhow::bail!("Case {} already exists", taken);
+        }
+        tx.commit().await?;
 
         Ok(())
     }
 
+    /// Inserts historical cases with their history in one transaction,
+    /// keeping their timestamps. If a case id is already taken nothing is
+    /// imported and that id is returned.
+    pub async fn import(&self, cases: &[(Case, Vec<CaseHistory>)]) -> Result<Option<Uuid>> {
+        let mut tx = self.pool.begin().await?;
+
+        let (new_cases, history): (Vec<&Case>, Vec<&Vec<CaseHistory>>) =
+            cases.iter().map(|(case, history)| (case, history)).unzip();
+        if let Some(taken) = create_batch_in(&mut tx, &new_cases).await? {
+            return Ok(Some(taken));
+        }
+
+        let history: Vec<&CaseHistory> = history.into_iter().flatten().collect();
+        create_history_batch_in(&mut tx, &history).await?;
+
+        tx.commit().await?;
+        Ok(None)
+    }
+
+    /// Soft-deleted cases are treated as missing; see [`Self::find_by_id_with_deleted`].
     pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Case>> {
+        let case = sqlx::query_as::<_, Case>("SELECT * FROM orchepy_cases WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(case)
+    }
+
+    /// The current version of a case that is not deleted.
+    pub async fn version(&self, id: Uuid) -> Result<Option<i32>> {
+        let version = sqlx::query_scalar("SELECT version FROM orchepy_cases WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(version)
+    }
+
+    /// The case in `workflow_id` with `external_id` that is not deleted.
+    pub async fn find_by_external_id(&self, workflow_id: Uuid, external_id: &str) -> Result<Option<Case>> {
+        let case = sqlx::query_as::<_, Case>(
+            "SELECT * FROM orchepy_cases WHERE workflow_id = $1 AND external_id = $2 AND deleted_at IS NULL",
+        )
+        .bind(workflow_id)
+        .bind(external_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(case)
+    }
+
+    pub async fn find_by_id_with_deleted(&self, id: Uuid) -> Result<Option<Case>> {
         let case = sqlx::query_as::<_, Case>("SELECT * FROM orchepy_cases WHERE id = $1")
             .bind(id)
             .fetch_optional(self.pool)
@@ -45,7 +119,7 @@ impl<'a> CaseRepository<'a> {
 
     pub async fn list_by_workflow(&self, workflow_id: Uuid, limit: i64, offset: i64) -> Result<Vec<Case>> {
         let cases = sqlx::query_as::<_, Case>(
-            "SELECT * FROM orchepy_cases WHERE workflow_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3"
+            "SELECT * FROM orchepy_cases WHERE workflow_id = $1 AND deleted_at IS NULL ORDER BY created_at DESC LIMIT $2 OFFSET $3"
         )
         .bind(workflow_id)
         .bind(limit)
@@ -64,7 +138,7 @@ impl<'a> CaseRepository<'a> {
         offset: i64,
     ) -> Result<Vec<Case>> {
         let cases = sqlx::query_as::<_, Case>(
-            "SELECT * FROM orchepy_cases WHERE workflow_id = $1 AND current_phase = $2 ORDER BY created_at DESC LIMIT $3 OFFSET $4"
+            "SELECT * FROM orchepy_cases WHERE workflow_id = $1 AND current_phase = $2 AND deleted_at IS NULL ORDER BY created_at DESC LIMIT $3 OFFSET $4"
         )
         .bind(workflow_id)
         .bind(phase)
@@ -84,7 +158,7 @@ impl<'a> CaseRepository<'a> {
         offset: i64,
     ) -> Result<Vec<Case>> {
         let cases = sqlx::query_as::<_, Case>(
-            "SELECT * FROM orchepy_cases WHERE workflow_id = $1 AND status = $2 ORDER BY created_at DESC LIMIT $3 OFFSET $4"
+            "SELECT * FROM orchepy_cases WHERE workflow_id = $1 AND status = $2 AND deleted_at IS NULL ORDER BY created_at DESC LIMIT $3 OFFSET $4"
         )
         .bind(workflow_id)
         .bind(status)
@@ -114,14 +188,97 @@ impl<'a> CaseRepository<'a> {
         Ok(())
     }
 
+    /// Like `update_phase`, but with a `wip_limit` the move is refused while
+    /// `current_phase` already holds that many cases in progress, and with
+    /// an `expected_version` while the case is at another version.
+    pub async fn update_phase_within_limit(
+        &self,
+        id: Uuid,
+        workflow_id: Uuid,
+        current_phase: &str,
+        previous_phase: Option<&str>,
+        wip_limit: Option<u32>,
+        expected_version: Option<i32>,
+    ) -> Result<PhaseMove> {
+        let mut tx = self.pool.begin().await?;
+
+        let moved = move_phase_in(&mut tx, id, workflow_id, current_phase, previous_phase, wip_limit, expected_version).await?;
+        if matches!(moved, PhaseMove::Moved) {
+            tx.commit().await?;
+        }
+
+        Ok(moved)
+    }
+
     pub async fn update_data(&self, id: Uuid, data: &serde_json::Value) -> Result<()> {
-        sqlx::query("UPDATE orchepy_cases SET data = $1, updated_at = NOW() WHERE id = $2")
-            .bind(data)
+        self.update_data_by(id, data, &FieldProvenance::api(None)).await?;
+        Ok(())
+    }
+
+    /// Replaces `data` and attributes the top-level fields it changes to
+    /// `writer`. Returns false if the case does not exist or is deleted.
+    pub async fn update_data_by(&self, id: Uuid, data: &serde_json::Value, writer: &FieldProvenance) -> Result<bool> {
+        let outcome = self.patch_data(id, &DataPatch::Replace(data.clone()), writer).await?;
+        Ok(matches!(outcome, PatchOutcome::Applied(_)))
+    }
+
+    /// Applies `patch` to the current `data` and attributes the top-level
+    /// fields it changes to `writer`. The row is locked for the whole
+    /// read-modify-write, so concurrent patches apply one after the other,
+    /// and fields another writer changed moments before are resolved with
+    /// the workflow's `data_conflicts` policy.
+    pub async fn patch_data(&self, id: Uuid, patch: &DataPatch, writer: &FieldProvenance) -> Result<PatchOutcome> {
+        self.patch_data_at_version(id, patch, writer, None).await
+    }
+
+    /// `patch_data`, applied only while the case is at `expected_version`.
+    pub async fn patch_data_at_version(
+        &self,
+        id: Uuid,
+        patch: &DataPatch,
+        writer: &FieldProvenance,
+        expected_version: Option<i32>,
+    ) -> Result<PatchOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let Some(mut case) = sqlx::query_as::<_, Case>("SELECT * FROM orchepy_cases WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
             .bind(id)
-            .execute(self.pool)
+            .fetch_optional(&mut *tx)
+            .await?
+        else {
+            return Ok(PatchOutcome::NotFound);
+        };
+
+        if expected_version.is_some_and(|expected| expected != case.version) {
+            return Ok(PatchOutcome::Stale { version: case.version });
+        }
+
+        let data = match patch.apply(&case.data) {
+            Ok(data) => data,
+            Err(err) => return Ok(PatchOutcome::Rejected(err)),
+        };
+
+        let policy = conflict_policy(&mut tx, id).await?;
+        let (data, overwritten) = match policy.resolve_data(&case.field_provenance, &case.data, data, writer) {
+            Ok(resolved) => resolved,
+            Err(fields) => return Ok(PatchOutcome::Conflict(fields)),
+        };
+
+        track_data_writes(&mut case.field_provenance, &case.data, &data, writer);
+        for (field, last) in overwritten {
+            case.field_provenance.insert(field, writer.overwriting(&last));
+        }
+
+        sqlx::query("UPDATE orchepy_cases SET data = $1, field_provenance = $2, updated_at = NOW() WHERE id = $3")
+            .bind(&data)
+            .bind(sqlx::types::Json(&case.field_provenance))
+            .bind(id)
+            .execute(&mut *tx)
             .await?;
+        record_revision_in(&mut tx, id, Some(&case.data), &data, writer).await?;
 
-        Ok(())
+        tx.commit().await?;
+        Ok(PatchOutcome::Applied(data))
     }
 
     pub async fn update_status(&self, id: Uuid, status: &CaseStatus) -> Result<()> {
@@ -134,34 +291,82 @@ impl<'a> CaseRepository<'a> {
         Ok(())
     }
 
+    /// Writes the case's status and `completed_at` if its status is still
+    /// `expected`. Returns false when the case is missing or was changed in
+    /// the meantime.
+    pub async fn transition_status(&self, case: &Case, expected: &CaseStatus) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE orchepy_cases SET status = $1, completed_at = $2, updated_at = NOW()
+             WHERE id = $3 AND status = $4 AND deleted_at IS NULL",
+        )
+        .bind(&case.status)
+        .bind(case.completed_at)
+        .bind(case.id)
+        .bind(expected)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn set_field(&self, id: Uuid, path: &str, value: &serde_json::Value) -> Result<()> {
-        let query = format!(
-            "UPDATE orchepy_cases SET data = jsonb_set(data, '{{{}}}', $1, true), updated_at = NOW() WHERE id = $2",
-            path
-        );
-        sqlx::query(&query)
-            .bind(value)
+        self.set_field_by(id, path, value, &FieldProvenance::api(None)).await?;
+        Ok(())
+    }
+
+    /// Sets the top-level `data` field `path` under the workflow's
+    /// `data_conflicts` policy. Returns false if the write was rejected.
+    pub async fn set_field_by(
+        &self,
+        id: Uuid,
+        path: &str,
+        value: &serde_json::Value,
+        writer: &FieldProvenance,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let policy = conflict_policy(&mut tx, id).await?;
+        let write = set_field_in(&mut tx, id, path, value, writer, &policy).await?;
+
+        tx.commit().await?;
+        Ok(write != FieldWrite::Rejected)
+    }
+
+    /// Hides the case from listings and further changes. Returns false if
+    /// it does not exist or was already deleted.
+    pub async fn soft_delete(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE orchepy_cases SET deleted_at = NOW(), updated_at = NOW() WHERE id = $1 AND deleted_at IS NULL"
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Permanently removes a soft-deleted case together with its history,
+    /// messages, automation runs and portal tokens. Returns false if there
+    /// is no soft-deleted case with this id.
+    pub async fn purge(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM orchepy_cases WHERE id = $1 AND deleted_at IS NOT NULL")
             .bind(id)
             .execute(self.pool)
             .await?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
     pub async fn create_history(&self, history: &CaseHistory) -> Result<()> {
-        sqlx::query(
-            "INSERT INTO orchepy_case_history (id, case_id, from_phase, to_phase, reason, triggered_by, transitioned_at)
-             VALUES ($1, $2, $3, $4, $5, $6, $7)"
-        )
-        .bind(history.id)
-        .bind(history.case_id)
-        .bind(&history.from_phase)
-        .bind(&history.to_phase)
-        .bind(&history.reason)
-        .bind(&history.triggered_by)
-        .bind(history.transitioned_at)
-        .execute(self.pool)
-        .await?;
+        create_history_in(&mut *self.pool.acquire().await?, history).await
+    }
+
+    /// Inserts history entries a few hundred rows per statement, in one
+    /// transaction.
+    pub async fn create_history_batch(&self, history: &[CaseHistory]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        create_history_batch_in(&mut tx, &history.iter().collect::<Vec<_>>()).await?;
+        tx.commit().await?;
 
         Ok(())
     }
@@ -179,7 +384,7 @@ impl<'a> CaseRepository<'a> {
 
     pub async fn count_by_workflow(&self, workflow_id: Uuid) -> Result<i64> {
         let (count,): (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM orchepy_cases WHERE workflow_id = $1"
+            "SELECT COUNT(*) FROM orchepy_cases WHERE workflow_id = $1 AND deleted_at IS NULL"
         )
         .bind(workflow_id)
         .fetch_one(self.pool)
@@ -187,4 +392,556 @@ impl<'a> CaseRepository<'a> {
 
         Ok(count)
     }
+
+    /// Live cases of the workflow per current phase, by phase name.
+    pub async fn count_by_phase(&self, workflow_id: Uuid) -> Result<Vec<(String, i64)>> {
+        let counts = sqlx::query_as(
+            "SELECT current_phase, COUNT(*) FROM orchepy_cases
+             WHERE workflow_id = $1 AND deleted_at IS NULL
+             GROUP BY current_phase
+             ORDER BY current_phase"
+        )
+        .bind(workflow_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Live cases of the workflow per status; statuses without cases are
+    /// left out.
+    pub async fn count_by_status(&self, workflow_id: Uuid) -> Result<Vec<(CaseStatus, i64)>> {
+        let counts = sqlx::query_as(
+            "SELECT status, COUNT(*) FROM orchepy_cases
+             WHERE workflow_id = $1 AND deleted_at IS NULL
+             GROUP BY status
+             ORDER BY status"
+        )
+        .bind(workflow_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Adds `tags` to a non-deleted case, skipping ones it already has.
+    /// Returns the case's tags, or `None` if the case is missing or would end
+    /// up with more than `max` tags.
+    pub async fn add_tags(&self, id: Uuid, tags: &[String], max: usize) -> Result<Option<Vec<String>>> {
+        let tags = sqlx::query_scalar::<_, Vec<String>>(
+            "WITH merged AS (
+                 SELECT ARRAY(
+                     SELECT tag FROM unnest(c.tags || $2::text[]) WITH ORDINALITY AS t(tag, position)
+                     GROUP BY tag ORDER BY MIN(position)
+                 ) AS tags
+                 FROM orchepy_cases c WHERE c.id = $1
+             )
+             UPDATE orchepy_cases SET tags = merged.tags, updated_at = NOW()
+             FROM merged
+             WHERE id = $1 AND deleted_at IS NULL AND cardinality(merged.tags) <= $3
+             RETURNING orchepy_cases.tags"
+        )
+        .bind(id)
+        .bind(tags)
+        .bind(max as i32)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(tags)
+    }
+
+    /// Returns the remaining tags, or `None` if the case is missing.
+    pub async fn remove_tag(&self, id: Uuid, tag: &str) -> Result<Option<Vec<String>>> {
+        let tags = sqlx::query_scalar::<_, Vec<String>>(
+            "UPDATE orchepy_cases SET tags = array_remove(tags, $2), updated_at = NOW()
+             WHERE id = $1 AND deleted_at IS NULL
+             RETURNING tags"
+        )
+        .bind(id)
+        .bind(tag)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(tags)
+    }
+
+    /// Non-deleted cases matching `search`, newest first. Equality filters
+    /// use `@>` so they can be served by the GIN index on `data`.
+    pub async fn search(&self, search: &CaseSearch, limit: i64, offset: i64) -> Result<Vec<Case>> {
+        let mut query = QueryBuilder::new("SELECT * FROM orchepy_cases WHERE deleted_at IS NULL");
+        push_search_filters(&mut query, search);
+
+        query.push(" ORDER BY created_at DESC LIMIT ");
+        query.push_bind(limit);
+        query.push(" OFFSET ");
+        query.push_bind(offset);
+
+        Ok(query.build_query_as::<Case>().fetch_all(self.pool).await?)
+    }
+
+    pub async fn count_search(&self, search: &CaseSearch) -> Result<i64> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM orchepy_cases WHERE deleted_at IS NULL");
+        push_search_filters(&mut query, search);
+
+        Ok(query.build_query_scalar::<i64>().fetch_one(self.pool).await?)
+    }
+
+    /// Cases in any workflow whose data, phase, tags or id contain the text
+    /// matched by `pattern`, most recently updated first.
+    pub async fn search_text(&self, pattern: &str, status: Option<&CaseStatus>, limit: i64, offset: i64) -> Result<Vec<Case>> {
+        let mut query = QueryBuilder::new("SELECT * FROM orchepy_cases WHERE deleted_at IS NULL");
+        push_text_filters(&mut query, pattern, status);
+
+        query.push(" ORDER BY updated_at DESC LIMIT ");
+        query.push_bind(limit);
+        query.push(" OFFSET ");
+        query.push_bind(offset);
+
+        Ok(query.build_query_as::<Case>().fetch_all(self.pool).await?)
+    }
+
+    pub async fn count_text(&self, pattern: &str, status: Option<&CaseStatus>) -> Result<i64> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM orchepy_cases WHERE deleted_at IS NULL");
+        push_text_filters(&mut query, pattern, status);
+
+        Ok(query.build_query_scalar::<i64>().fetch_one(self.pool).await?)
+    }
+
+    /// Board columns with each one's `limit` most recently updated cases.
+    pub async fn board(&self, board: &BoardQuery, limit: i64) -> Result<Vec<BoardColumn>> {
+        let mut query = QueryBuilder::new("SELECT * FROM (SELECT c.*, ");
+        push_board_key(&mut query, board);
+        query.push(" AS board_key, COUNT(*) OVER (PARTITION BY ");
+        push_board_key(&mut query, board);
+        query.push(") AS board_count, ROW_NUMBER() OVER (PARTITION BY ");
+        push_board_key(&mut query, board);
+        query.push(" ORDER BY c.updated_at DESC) AS board_rank FROM orchepy_cases c WHERE c.deleted_at IS NULL");
+
+        if let Some(assignee) = &board.assignee {
+            query.push(" AND c.data ->> ");
+            query.push_bind(board.assignee_field());
+            query.push(" = ");
+            query.push_bind(assignee);
+        }
+
+        if let Some(status) = &board.status {
+            query.push(" AND c.status = ");
+            query.push_bind(status);
+        }
+
+        query.push(") ranked WHERE board_rank <= ");
+        query.push_bind(limit);
+        query.push(" ORDER BY board_key, board_rank");
+
+        let rows = query.build_query_as::<BoardRow>().fetch_all(self.pool).await?;
+
+        let mut columns: Vec<BoardColumn> = Vec::new();
+        for row in rows {
+            match columns.last_mut() {
+                Some(column) if column.key == row.board_key => column.cases.push(row.case),
+                _ => columns.push(BoardColumn {
+                    key: row.board_key,
+                    count: row.board_count,
+                    cases: vec![row.case],
+                }),
+            }
+        }
+
+        Ok(columns)
+    }
+}
+
+fn push_text_filters<'q>(query: &mut QueryBuilder<'q, Postgres>, pattern: &'q str, status: Option<&'q CaseStatus>) {
+    query.push(" AND (data::text ILIKE ");
+    query.push_bind(pattern);
+    query.push(" OR current_phase ILIKE ");
+    query.push_bind(pattern);
+    query.push(" OR array_to_string(tags, ' ') ILIKE ");
+    query.push_bind(pattern);
+    query.push(" OR id::text ILIKE ");
+    query.push_bind(pattern);
+    query.push(")");
+
+    if let Some(status) = status {
+        query.push(" AND status = ");
+        query.push_bind(status);
+    }
+}
+
+fn push_board_key<'q>(query: &mut QueryBuilder<'q, Postgres>, board: &'q BoardQuery) {
+    match board.group_by {
+        BoardGrouping::Status => query.push("c.status::text"),
+        BoardGrouping::Assignee => query.push("c.data ->> ").push_bind(board.assignee_field()),
+    };
+}
+
+fn push_search_filters<'q>(query: &mut QueryBuilder<'q, Postgres>, search: &'q CaseSearch) {
+    push_workflow_filters(query, search.workflow_id, search.current_phase.as_ref());
+
+    if let Some(status) = &search.status {
+        query.push(" AND status = ");
+        query.push_bind(status);
+    }
+
+    if !search.tags.is_empty() {
+        query.push(" AND tags @> ");
+        query.push_bind(&search.tags);
+    }
+
+    for filter in &search.filters {
+        query.push(" AND ");
+        push_data_filter(query, filter);
+    }
+}
+
+/// Inserts `case` with its first data revision.
+pub(crate) async fn create_in(conn: &mut PgConnection, case: &Case) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO orchepy_cases (id, workflow_id, current_phase, previous_phase, data, status, metadata, created_at, updated_at, phase_entered_at, region, field_provenance, tags, external_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"
+    )
+    .bind(case.id)
+    .bind(case.workflow_id)
+    .bind(&case.current_phase)
+    .bind(&case.previous_phase)
+    .bind(&case.data)
+    .bind(&case.status)
+    .bind(&case.metadata)
+    .bind(case.created_at)
+    .bind(case.updated_at)
+    .bind(case.phase_entered_at)
+    .bind(&case.region)
+    .bind(sqlx::types::Json(&case.field_provenance))
+    .bind(&case.tags)
+    .bind(&case.external_id)
+    .execute(&mut *conn)
+    .await?;
+
+    record_revision_in(conn, case.id, None, &case.data, &creator(case)).await
+}
+
+pub(crate) async fn create_history_in(conn: &mut PgConnection, history: &CaseHistory) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO orchepy_case_history (id, case_id, from_phase, to_phase, reason, triggered_by, transitioned_at, from_status, to_status, workflow_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+    )
+    .bind(history.id)
+    .bind(history.case_id)
+    .bind(&history.from_phase)
+    .bind(&history.to_phase)
+    .bind(&history.reason)
+    .bind(&history.triggered_by)
+    .bind(history.transitioned_at)
+    .bind(&history.from_status)
+    .bind(&history.to_status)
+    .bind(history.workflow_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Inserts `cases` as they are, timestamps included, with their first data
+/// revisions, in multi-row INSERTs of up to [`BATCH_ROWS`] rows. If a case id
+/// is already taken, or repeated in `cases`, returns it without recording
+/// revisions; the caller rolls back.
+pub(crate) async fn create_batch_in(conn: &mut PgConnection, cases: &[&Case]) -> Result<Option<Uuid>> {
+    let mut inserted = HashSet::with_capacity(cases.len());
+    for chunk in cases.chunks(BATCH_ROWS) {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO orchepy_cases (id, workflow_id, current_phase, previous_phase, data, status, metadata, created_at, updated_at, completed_at, phase_entered_at, region, field_provenance, tags, external_id) "
+        );
+        query.push_values(chunk, |mut row, case| {
+            row.push_bind(case.id)
+                .push_bind(case.workflow_id)
+                .push_bind(&case.current_phase)
+                .push_bind(&case.previous_phase)
+                .push_bind(&case.data)
+                .push_bind(&case.status)
+                .push_bind(&case.metadata)
+                .push_bind(case.created_at)
+                .push_bind(case.updated_at)
+                .push_bind(case.completed_at)
+                .push_bind(case.phase_entered_at)
+                .push_bind(&case.region)
+                .push_bind(sqlx::types::Json(&case.field_provenance))
+                .push_bind(&case.tags)
+                .push_bind(&case.external_id);
+        });
+        query.push(" ON CONFLICT (id) DO NOTHING RETURNING id");
+        inserted.extend(query.build_query_scalar::<Uuid>().fetch_all(&mut *conn).await?);
+    }
+
+    let mut pending = inserted;
+    if let Some(case) = cases.iter().find(|case| !pending.remove(&case.id)) {
+        return Ok(Some(case.id));
+    }
+
+    let revisions: Vec<_> = cases.iter().map(|case| (case.id, &case.data, creator(case))).collect();
+    record_first_revisions_in(conn, &revisions).await?;
+
+    Ok(None)
+}
+
+/// Inserts history entries in multi-row INSERTs of up to [`BATCH_ROWS`] rows.
+pub(crate) async fn create_history_batch_in(conn: &mut PgConnection, history: &[&CaseHistory]) -> Result<()> {
+    for chunk in history.chunks(BATCH_ROWS) {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO orchepy_case_history (id, case_id, from_phase, to_phase, reason, triggered_by, transitioned_at, from_status, to_status, workflow_id) "
+        );
+        query.push_values(chunk, |mut row, entry| {
+            row.push_bind(entry.id)
+                .push_bind(entry.case_id)
+                .push_bind(&entry.from_phase)
+                .push_bind(&entry.to_phase)
+                .push_bind(&entry.reason)
+                .push_bind(&entry.triggered_by)
+                .push_bind(entry.transitioned_at)
+                .push_bind(&entry.from_status)
+                .push_bind(&entry.to_status)
+                .push_bind(entry.workflow_id);
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+/// `CaseRepository::update_phase_within_limit` on `conn`, for moves that
+/// commit together with other writes.
+pub(crate) async fn move_phase_in(
+    conn: &mut PgConnection,
+    id: Uuid,
+    workflow_id: Uuid,
+    current_phase: &str,
+    previous_phase: Option<&str>,
+    wip_limit: Option<u32>,
+    expected_version: Option<i32>,
+) -> Result<PhaseMove> {
+    if let Some(limit) = wip_limit {
+        let in_progress = lock_phase_wip(&mut *conn, workflow_id, current_phase).await?;
+        if in_progress >= i64::from(limit) {
+            return Ok(PhaseMove::AtWipLimit { limit, in_progress });
+        }
+    }
+
+    if let Some(expected) = expected_version {
+        match lock_version(&mut *conn, id).await? {
+            None => return Ok(PhaseMove::NotFound),
+            Some(version) if version != expected => return Ok(PhaseMove::Stale { version }),
+            Some(_) => {}
+        }
+    }
+
+    let result = sqlx::query(
+        "UPDATE orchepy_cases SET current_phase = $1, previous_phase = $2, phase_entered_at = NOW(), updated_at = NOW() WHERE id = $3"
+    )
+    .bind(current_phase)
+    .bind(previous_phase)
+    .bind(id)
+    .execute(conn)
+    .await?;
+
+    Ok(if result.rows_affected() > 0 { PhaseMove::Moved } else { PhaseMove::NotFound })
+}
+
+/// Serializes moves into `phase` of `workflow_id` until the transaction
+/// ends and returns how many cases are in progress there, counting those
+/// that joined the workflow. Active and paused cases are in progress.
+pub(crate) async fn lock_phase_wip(conn: &mut PgConnection, workflow_id: Uuid, phase: &str) -> Result<i64> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::text || '/' || $2, 0))")
+        .bind(workflow_id)
+        .bind(phase)
+        .execute(&mut *conn)
+        .await?;
+
+    let in_progress: i64 = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM orchepy_cases
+                 WHERE workflow_id = $1 AND current_phase = $2
+                   AND deleted_at IS NULL AND status IN ('active', 'paused'))
+              + (SELECT COUNT(*) FROM orchepy_case_workflows m
+                 JOIN orchepy_cases c ON c.id = m.case_id
+                 WHERE m.workflow_id = $1 AND m.current_phase = $2
+                   AND c.deleted_at IS NULL AND c.status IN ('active', 'paused'))"
+    )
+    .bind(workflow_id)
+    .bind(phase)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(in_progress)
+}
+
+/// Locks the case row until the transaction ends and returns its version,
+/// or None if the case is missing or deleted.
+async fn lock_version(conn: &mut PgConnection, case_id: Uuid) -> Result<Option<i32>> {
+    let version = sqlx::query_scalar("SELECT version FROM orchepy_cases WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(case_id)
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(version)
+}
+
+/// The `data_conflicts` policy of the case's workflow.
+async fn conflict_policy(conn: &mut PgConnection, case_id: Uuid) -> Result<DataConflictPolicy> {
+    let policy: Option<sqlx::types::Json<DataConflictPolicy>> = sqlx::query_scalar(
+        "SELECT w.data_conflicts FROM orchepy_cases c JOIN orchepy_workflows w ON w.id = c.workflow_id WHERE c.id = $1",
+    )
+    .bind(case_id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(policy.map(|policy| policy.0).unwrap_or_default())
+}
+
+/// Writes `value` to the top-level `data` field `field` under `policy`,
+/// locking the case row. The field's provenance records the write it
+/// replaced when the two were concurrent.
+pub(crate) async fn set_field_in(
+    conn: &mut PgConnection,
+    case_id: Uuid,
+    field: &str,
+    value: &serde_json::Value,
+    writer: &FieldProvenance,
+    policy: &DataConflictPolicy,
+) -> Result<FieldWrite> {
+    let (data, last): (Option<serde_json::Value>, Option<sqlx::types::Json<FieldProvenance>>) = sqlx::query_as(
+        "SELECT data, field_provenance -> $1 FROM orchepy_cases WHERE id = $2 FOR UPDATE",
+    )
+    .bind(field)
+    .bind(case_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or_default();
+    let current = data.as_ref().and_then(|data| data.get(field)).cloned();
+    let last = last.map(|last| last.0);
+
+    let write = policy.resolve(field, last.as_ref(), current.as_ref(), value.clone(), writer);
+    let Some(resolved) = write.value() else {
+        return Ok(write);
+    };
+    let provenance = match &last {
+        Some(last) if write.replaced_concurrent() => writer.overwriting(last),
+        _ => writer.clone(),
+    };
+
+    let updated: Option<serde_json::Value> = sqlx::query_scalar(
+        "UPDATE orchepy_cases SET data = jsonb_set(data, ARRAY[$1], $2, true), \
+         field_provenance = jsonb_set(field_provenance, ARRAY[$1], $3, true), updated_at = NOW() WHERE id = $4 \
+         RETURNING data",
+    )
+    .bind(field)
+    .bind(resolved)
+    .bind(sqlx::types::Json(&provenance))
+    .bind(case_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(updated) = &updated {
+        record_revision_in(conn, case_id, data.as_ref(), updated, writer).await?;
+    }
+
+    Ok(write)
+}
+
+/// Whether `err` is [`CaseRepository::create`] failing because the
+/// workflow already has a case with the new case's `external_id`.
+pub fn is_external_id_taken(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<sqlx::Error>()
+        .and_then(sqlx::Error::as_database_error)
+        .is_some_and(|err| err.constraint() == Some("idx_orchepy_cases_external_id"))
+}
+
+/// Who wrote the data a case was created with: the writer its fields are
+/// attributed to, at the case's creation time.
+fn creator(case: &Case) -> FieldProvenance {
+    let mut writer = case.field_provenance.values().next().cloned().unwrap_or_else(|| FieldProvenance::api(None));
+    writer.overwrote = None;
+    writer.updated_at = case.created_at;
+    writer
+}
+
+/// Matches cases in `workflow_id`, including ones that joined it, and in
+/// `current_phase` of that workflow when both are given.
+pub(crate) fn push_workflow_filters<'q>(
+    query: &mut QueryBuilder<'q, Postgres>,
+    workflow_id: Option<Uuid>,
+    current_phase: Option<&'q String>,
+) {
+    let Some(workflow_id) = workflow_id else {
+        if let Some(current_phase) = current_phase {
+            query.push(" AND current_phase = ");
+            query.push_bind(current_phase);
+        }
+        return;
+    };
+
+    query.push(" AND ((workflow_id = ");
+    query.push_bind(workflow_id);
+    if let Some(current_phase) = current_phase {
+        query.push(" AND current_phase = ");
+        query.push_bind(current_phase);
+    }
+    query.push(") OR id IN (SELECT case_id FROM orchepy_case_workflows WHERE workflow_id = ");
+    query.push_bind(workflow_id);
+    if let Some(current_phase) = current_phase {
+        query.push(" AND current_phase = ");
+        query.push_bind(current_phase);
+    }
+    query.push("))");
+}
+
+/// Pushes one `data.*` or `metadata.*` filter on its column, for any table
+/// that has one.
+pub(crate) fn push_data_filter<'q>(query: &mut QueryBuilder<'q, Postgres>, filter: &'q DataFilter) {
+    let column = filter.column.as_str();
+    let comparison = match filter.op {
+        DataFilterOp::Eq => return push_containment(query, filter),
+        DataFilterOp::Ne => {
+            query.push("NOT ");
+            return push_containment(query, filter);
+        }
+        DataFilterOp::Contains => {
+            query.push(format!("strpos({} #>> ", column));
+            query.push_bind(&filter.path);
+            query.push(", ");
+            query.push_bind(&filter.value);
+            query.push(") > 0");
+            return;
+        }
+        DataFilterOp::Exists => {
+            query.push(format!("{} #> ", column));
+            query.push_bind(&filter.path);
+            query.push(if filter.value == "true" { " IS NOT NULL" } else { " IS NULL" });
+            return;
+        }
+        DataFilterOp::Gt => ">",
+        DataFilterOp::Gte => ">=",
+        DataFilterOp::Lt => "<",
+        DataFilterOp::Lte => "<=",
+    };
+
+    // The CASE keeps the cast away from values of other JSON types.
+    let (json_type, cast) = if filter.is_numeric() { ("number", "::numeric") } else { ("string", "") };
+    query.push(format!("CASE WHEN jsonb_typeof({} #> ", column));
+    query.push_bind(&filter.path);
+    query.push(format!(") = '{}' THEN ({} #>> ", json_type, column));
+    query.push_bind(&filter.path);
+    query.push(format!("){} END {} ", cast, comparison));
+    query.push_bind(filter.value.trim());
+    query.push(cast);
+}
+
+/// Equality as containment, so it can use a GIN index on the column.
+fn push_containment<'q>(query: &mut QueryBuilder<'q, Postgres>, filter: &'q DataFilter) {
+    query.push("(");
+    for (i, candidate) in filter.containment_candidates().into_iter().enumerate() {
+        if i > 0 {
+            query.push(" OR ");
+        }
+        query.push(format!("{} @> ", filter.column.as_str()));
+        query.push_bind(candidate);
+    }
+    query.push(")");
 }
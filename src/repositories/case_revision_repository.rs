@@ -0,0 +1,100 @@
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::case::FieldProvenance;
+use crate::models::revision::{changed_fields, CaseRevision};
+
+use super::case_repository::BATCH_ROWS;
+
+pub struct CaseRevisionRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> CaseRevisionRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The case's revisions, newest first.
+    pub async fn list_by_case(&self, case_id: Uuid, limit: i64) -> Result<Vec<CaseRevision>> {
+        let revisions = sqlx::query_as::<_, CaseRevision>(
+            "SELECT * FROM orchepy_case_revisions WHERE case_id = $1 ORDER BY revision DESC LIMIT $2"
+        )
+        .bind(case_id)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(revisions)
+    }
+
+    pub async fn find(&self, case_id: Uuid, revision: i32) -> Result<Option<CaseRevision>> {
+        let revision = sqlx::query_as::<_, CaseRevision>(
+            "SELECT * FROM orchepy_case_revisions WHERE case_id = $1 AND revision = $2"
+        )
+        .bind(case_id)
+        .bind(revision)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(revision)
+    }
+}
+
+/// Records `data` as the case's next revision, unless it equals `previous`.
+/// Callers hold the case row's lock, or have just inserted it, so revision
+/// numbers don't collide.
+pub(crate) async fn record_revision_in(
+    conn: &mut PgConnection,
+    case_id: Uuid,
+    previous: Option<&serde_json::Value>,
+    data: &serde_json::Value,
+    writer: &FieldProvenance,
+) -> Result<()> {
+    if previous == Some(data) {
+        return Ok(());
+    }
+
+    sqlx::query(
+        "INSERT INTO orchepy_case_revisions (case_id, revision, data, changed_fields, source, actor, trigger, created_at)
+         SELECT $1, COALESCE(MAX(revision), 0) + 1, $2, $3, $4, $5, $6, $7
+         FROM orchepy_case_revisions WHERE case_id = $1"
+    )
+    .bind(case_id)
+    .bind(data)
+    .bind(changed_fields(previous, data))
+    .bind(writer.source)
+    .bind(&writer.actor)
+    .bind(&writer.trigger)
+    .bind(writer.updated_at)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Records each case's data as its first revision, in multi-row INSERTs. The cases were just inserted and have no revisions yet.
+pub(crate) async fn record_first_revisions_in(
+    conn: &mut PgConnection,
+    revisions: &[(Uuid, &serde_json::Value, FieldProvenance)],
+) -> Result<()> {
+    for chunk in revisions.chunks(BATCH_ROWS) {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO orchepy_case_revisions (case_id, revision, data, changed_fields, source, actor, trigger, created_at) "
+        );
+        query.push_values(chunk, |mut row, (case_id, data, writer)| {
+            row.push_bind(*case_id)
+                .push_bind(1)
+                .push_bind(*data)
+                .push_bind(changed_fields(None, data))
+                .push_bind(writer.source)
+                .push_bind(&writer.actor)
+                .push_bind(&writer.trigger)
+                .push_bind(writer.updated_at);
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::membership::CaseWorkflow;
+use crate::models::outbox::OutboxMessage;
+use crate::models::phase::PhaseMove;
+use crate::models::Case;
+
+use super::case_repository::lock_phase_wip;
+use super::outbox_repository::enqueue_in;
+
+pub struct CaseWorkflowRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> CaseWorkflowRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns false when the case already takes part in the workflow.
+    pub async fn create(&self, membership: &CaseWorkflow) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO orchepy_case_workflows (case_id, workflow_id, current_phase, previous_phase, phase_entered_at, joined_by, joined_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (case_id, workflow_id) DO NOTHING"
+        )
+        .bind(membership.case_id)
+        .bind(membership.workflow_id)
+        .bind(&membership.current_phase)
+        .bind(&membership.previous_phase)
+        .bind(membership.phase_entered_at)
+        .bind(&membership.joined_by)
+        .bind(membership.joined_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn find(&self, case_id: Uuid, workflow_id: Uuid) -> Result<Option<CaseWorkflow>> {
+        let membership = sqlx::query_as::<_, CaseWorkflow>(
+            "SELECT * FROM orchepy_case_workflows WHERE case_id = $1 AND workflow_id = $2"
+        )
+        .bind(case_id)
+        .bind(workflow_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(membership)
+    }
+
+    /// The workflows the case joined, oldest first; not its own.
+    pub async fn list_by_case(&self, case_id: Uuid) -> Result<Vec<CaseWorkflow>> {
+        let memberships = sqlx::query_as::<_, CaseWorkflow>(
+            "SELECT * FROM orchepy_case_workflows WHERE case_id = $1 ORDER BY joined_at"
+        )
+        .bind(case_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(memberships)
+    }
+
+    pub async fn update_phase(&self, membership: &CaseWorkflow) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE orchepy_case_workflows
+             SET current_phase = $3, previous_phase = $4, phase_entered_at = $5
+             WHERE case_id = $1 AND workflow_id = $2"
+        )
+        .bind(membership.case_id)
+        .bind(membership.workflow_id)
+        .bind(&membership.current_phase)
+        .bind(&membership.previous_phase)
+        .bind(membership.phase_entered_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Like `update_phase`, but with a `wip_limit` the move is refused while
+    /// the membership's new phase already holds that many cases in progress.
+    pub async fn update_phase_within_limit(&self, membership: &CaseWorkflow, wip_limit: Option<u32>) -> Result<PhaseMove> {
+        self.update_phase_and_enqueue(membership, wip_limit, &[]).await
+    }
+
+    /// `update_phase_within_limit`, queuing `outbox` in the same transaction
+    /// when the move is made.
+    pub async fn update_phase_and_enqueue(
+        &self,
+        membership: &CaseWorkflow,
+        wip_limit: Option<u32>,
+        outbox: &[OutboxMessage],
+    ) -> Result<PhaseMove> {
+        let mut tx = self.pool.begin().await?;
+
+        if let Some(limit) = wip_limit {
+            let in_progress = lock_phase_wip(&mut tx, membership.workflow_id, &membership.current_phase).await?;
+            if in_progress >= i64::from(limit) {
+                return Ok(PhaseMove::AtWipLimit { limit, in_progress });
+            }
+        }
+
+        let result = sqlx::query(
+            "UPDATE orchepy_case_workflows
+             SET current_phase = $3, previous_phase = $4, phase_entered_at = $5
+             WHERE case_id = $1 AND workflow_id = $2"
+        )
+        .bind(membership.case_id)
+        .bind(membership.workflow_id)
+        .bind(&membership.current_phase)
+        .bind(&membership.previous_phase)
+        .bind(membership.phase_entered_at)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(PhaseMove::NotFound);
+        }
+
+        enqueue_in(&mut tx, outbox).await?;
+        tx.commit().await?;
+
+        Ok(PhaseMove::Moved)
+    }
+
+    pub async fn delete(&self, case_id: Uuid, workflow_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM orchepy_case_workflows WHERE case_id = $1 AND workflow_id = $2")
+            .bind(case_id)
+            .bind(workflow_id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Shows cases listed for `workflow_id` at their phase in it: cases that
+    /// joined the workflow get their membership's workflow and phase fields.
+    pub async fn scope(&self, workflow_id: Uuid, cases: Vec<Case>) -> Result<Vec<Case>> {
+        let joined: Vec<Uuid> = cases
+            .iter()
+            .filter(|case| case.workflow_id != workflow_id)
+            .map(|case| case.id)
+            .collect();
+        if joined.is_empty() {
+            return Ok(cases);
+        }
+
+        let memberships: HashMap<Uuid, CaseWorkflow> = sqlx::query_as::<_, CaseWorkflow>(
+            "SELECT * FROM orchepy_case_workflows WHERE workflow_id = $1 AND case_id = ANY($2)"
+        )
+        .bind(workflow_id)
+        .bind(&joined)
+        .fetch_all(self.pool)
+        .await?
+        .into_iter()
+        .map(|membership| (membership.case_id, membership))
+        .collect();
+
+        Ok(cases
+            .into_iter()
+            .map(|case| match memberships.get(&case.id) {
+                Some(membership) => membership.scope(case),
+                None => case,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::change::{Change, ChangeCursor};
+
+/// Transactions older than this have all finished; their changes are final.
+const SETTLED: &str = "pg_snapshot_xmin(pg_current_snapshot())::text::bigint";
+
+pub struct ChangeRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> ChangeRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Settled changes after `cursor`, oldest first.
+    pub async fn since(&self, cursor: ChangeCursor, limit: i64) -> Result<Vec<Change>> {
+        let changes = sqlx::query_as::<_, Change>(&format!(
+            "SELECT xact_id || '-' || id AS cursor, entity, entity_id, workflow_id, flow_id, operation, changed_at
+             FROM orchepy_changes
+             WHERE (xact_id, id) > ($1, $2) AND xact_id < {SETTLED}
+             ORDER BY xact_id, id
+             LIMIT $3"
+        ))
+        .bind(cursor.xact_id)
+        .bind(cursor.id)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(changes)
+    }
+
+    /// The cursor of the last settled change, from which a new reader starts.
+    pub async fn latest(&self) -> Result<ChangeCursor> {
+        let latest: Option<(i64, i64)> = sqlx::query_as(&format!(
+            "SELECT xact_id, id FROM orchepy_changes WHERE xact_id < {SETTLED} ORDER BY xact_id DESC, id DESC LIMIT 1"
+        ))
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(latest.map(|(xact_id, id)| ChangeCursor { xact_id, id }).unwrap_or_default())
+    }
+
+    /// Removes changes recorded before `before`. Returns how many.
+    pub async fn prune(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM orchepy_changes WHERE changed_at < $1")
+            .bind(before)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
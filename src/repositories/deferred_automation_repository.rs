@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::automation::DeferredAutomation;
+
+pub struct DeferredAutomationRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> DeferredAutomationRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, deferred: &DeferredAutomation) -> Result<()> {
+        create_deferred_in(&mut *self.pool.acquire().await?, deferred).await
+    }
+
+    /// Leases up to `limit` deferred automations due to resume at `now` or
+    /// earlier for `lease`. Rows leased by another worker are skipped; ones
+    /// whose lease ran out, because their worker crashed, are handed out
+    /// again.
+    pub async fn claim_due(&self, now: DateTime<Utc>, limit: i64, lease: Duration) -> Result<Vec<DeferredAutomation>> {
+        let deferred = sqlx::query_as::<_, DeferredAutomation>(
+            "UPDATE orchepy_deferred_automations
+             SET attempts = attempts + 1, locked_until = $1 + make_interval(secs => $3)
+             WHERE id IN (
+                SELECT id FROM orchepy_deferred_automations
+                WHERE resume_at <= $1 AND (locked_until IS NULL OR locked_until <= $1)
+                ORDER BY resume_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+             )
+             RETURNING *"
+        )
+        .bind(now)
+        .bind(limit)
+        .bind(lease.as_secs_f64())
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deferred)
+    }
+
+    /// Removes a deferred automation once its actions have run, or been
+    /// given up on.
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM orchepy_deferred_automations WHERE id = $1")
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Releases a deferred automation whose run failed, to be claimed again
+    /// at `resume_at`.
+    pub async fn release(&self, id: Uuid, resume_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE orchepy_deferred_automations SET locked_until = NULL, resume_at = $2 WHERE id = $1")
+            .bind(id)
+            .bind(resume_at)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+pub(crate) async fn create_deferred_in(conn: &mut PgConnection, deferred: &DeferredAutomation) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO orchepy_deferred_automations
+            (id, case_id, workflow_id, trigger, phase, from_phase, actions, resume_at, created_at, total_delay_ms)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+    )
+    .bind(deferred.id)
+    .bind(deferred.case_id)
+    .bind(deferred.workflow_id)
+    .bind(&deferred.trigger)
+    .bind(&deferred.phase)
+    .bind(&deferred.from_phase)
+    .bind(serde_json::to_value(&deferred.actions)?)
+    .bind(deferred.resume_at)
+    .bind(deferred.created_at)
+    .bind(deferred.total_delay_ms)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
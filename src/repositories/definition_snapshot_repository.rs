@@ -0,0 +1,46 @@
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool};
+
+use crate::models::snapshot::DefinitionSnapshot;
+
+pub struct DefinitionSnapshotRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> DefinitionSnapshotRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores the snapshot unless one with the same hash already exists.
+    pub async fn save(&self, snapshot: &DefinitionSnapshot) -> Result<()> {
+        save_snapshot_in(&mut *self.pool.acquire().await?, snapshot).await
+    }
+
+    pub async fn find(&self, hash: &str) -> Result<Option<DefinitionSnapshot>> {
+        let snapshot = sqlx::query_as::<_, DefinitionSnapshot>(
+            "SELECT * FROM orchepy_definition_snapshots WHERE hash = $1"
+        )
+        .bind(hash)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(snapshot)
+    }
+}
+
+pub(crate) async fn save_snapshot_in(conn: &mut PgConnection, snapshot: &DefinitionSnapshot) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO orchepy_definition_snapshots (hash, kind, definition, created_at)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (hash) DO NOTHING"
+    )
+    .bind(&snapshot.hash)
+    .bind(&snapshot.kind)
+    .bind(&snapshot.definition)
+    .bind(snapshot.created_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::email_log::EmailLogEntry;
+
+pub struct EmailLogRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> EmailLogRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        kind: &str,
+        case_id: Option<Uuid>,
+        recipients: &str,
+        subject: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO orchepy_email_log (kind, case_id, recipients, subject, status, error)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(kind)
+        .bind(case_id)
+        .bind(recipients)
+        .bind(subject)
+        .bind(if error.is_some() { "failed" } else { "sent" })
+        .bind(error)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Newest first.
+    pub async fn list(
+        &self,
+        status: Option<&str>,
+        case_id: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<EmailLogEntry>> {
+        let entries = sqlx::query_as::<_, EmailLogEntry>(
+            "SELECT * FROM orchepy_email_log
+             WHERE ($1::text IS NULL OR status = $1) AND ($2::uuid IS NULL OR case_id = $2)
+             ORDER BY id DESC LIMIT $3 OFFSET $4",
+        )
+        .bind(status)
+        .bind(case_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
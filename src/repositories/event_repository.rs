@@ -0,0 +1,120 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::event::EventFilter;
+use crate::models::Event;
+
+use super::case_repository::push_data_filter;
+
+pub struct EventRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> EventRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, event: &Event) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO orchepy_events (id, event_type, data, metadata, received_at, region)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(event.id)
+        .bind(&event.event_type)
+        .bind(&event.data)
+        .bind(&event.metadata)
+        .bind(event.received_at)
+        .bind(&event.region)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Event>> {
+        let event = sqlx::query_as::<_, Event>("SELECT * FROM orchepy_events WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(event)
+    }
+
+    pub async fn list_filtered(&self, filter: &EventFilter, limit: i64) -> Result<Vec<Event>> {
+        self.search(filter, Some(limit), 0).await
+    }
+
+    /// Matching events, newest first; a `None` limit returns all of them.
+    pub async fn search(&self, filter: &EventFilter, limit: Option<i64>, offset: i64) -> Result<Vec<Event>> {
+        let mut query = QueryBuilder::new("SELECT * FROM orchepy_events WHERE 1=1");
+        push_event_filters(&mut query, filter);
+
+        query.push(" ORDER BY received_at DESC LIMIT ");
+        query.push_bind(limit);
+        query.push(" OFFSET ");
+        query.push_bind(offset);
+
+        Ok(query.build_query_as::<Event>().fetch_all(self.pool).await?)
+    }
+
+    /// Deletes up to `limit` events received before `before`. Events with
+    /// an execution that hasn't finished are kept, as resuming it needs
+    /// them. Returns how many were deleted.
+    pub async fn purge(&self, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM orchepy_events
+             WHERE (id, received_at) IN (
+                SELECT e.id, e.received_at FROM orchepy_events e
+                WHERE e.received_at < $1
+                  AND NOT EXISTS (
+                    SELECT 1 FROM orchepy_executions x
+                    WHERE x.event_id = e.id AND x.status NOT IN ('completed', 'failed')
+                  )
+                LIMIT $2
+             )",
+        )
+        .bind(before)
+        .bind(limit)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn count(&self, filter: &EventFilter) -> Result<i64> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM orchepy_events WHERE 1=1");
+        push_event_filters(&mut query, filter);
+
+        Ok(query.build_query_scalar::<i64>().fetch_one(self.pool).await?)
+    }
+}
+
+fn push_event_filters<'q>(query: &mut QueryBuilder<'q, Postgres>, filter: &'q EventFilter) {
+    if let Some(event_type) = &filter.event_type {
+        query.push(" AND event_type = ");
+        query.push_bind(event_type);
+    }
+
+    if let Some(since) = filter.since {
+        query.push(" AND received_at >= ");
+        query.push_bind(since);
+    }
+
+    if let Some(until) = filter.until {
+        query.push(" AND received_at < ");
+        query.push_bind(until);
+    }
+
+    if let Some(data) = &filter.data {
+        query.push(" AND data @> ");
+        query.push_bind(data);
+    }
+
+    for data_filter in &filter.data_filters {
+        query.push(" AND ");
+        push_data_filter(query, data_filter);
+    }
+}
@@ -0,0 +1,347 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::models::execution::{Execution, ExecutionFilter};
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct FlowFailureCount {
+    pub flow_id: Uuid,
+    pub flow_name: String,
+    pub failures: i64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LatencyStats {
+    pub samples: i64,
+    pub avg_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct StepLatency {
+    pub step: String,
+    pub samples: i64,
+    pub failures: i64,
+    pub avg_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub avg_attempts: Option<f64>,
+}
+
+pub struct ExecutionRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> ExecutionRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn count_since(&self, since: DateTime<Utc>) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM orchepy_executions WHERE started_at >= $1"
+        )
+        .bind(since)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    pub async fn count_failed_since(&self, since: DateTime<Utc>) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM orchepy_executions WHERE status = 'failed' AND started_at >= $1"
+        )
+        .bind(since)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Matching executions, newest first; a `None` limit returns all of them.
+    pub async fn list(&self, filter: &ExecutionFilter, limit: Option<i64>, offset: i64) -> Result<Vec<Execution>> {
+        let mut query = QueryBuilder::new("SELECT * FROM orchepy_executions WHERE 1=1");
+        push_execution_filters(&mut query, filter);
+
+        query.push(" ORDER BY started_at DESC LIMIT ");
+        query.push_bind(limit);
+        query.push(" OFFSET ");
+        query.push_bind(offset);
+
+        Ok(query.build_query_as::<Execution>().fetch_all(self.pool).await?)
+    }
+
+    pub async fn count(&self, filter: &ExecutionFilter) -> Result<i64> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM orchepy_executions WHERE 1=1");
+        push_execution_filters(&mut query, filter);
+
+        Ok(query.build_query_scalar::<i64>().fetch_one(self.pool).await?)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Execution>> {
+        let execution = sqlx::query_as::<_, Execution>("SELECT * FROM orchepy_executions WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(execution)
+    }
+
+    /// The executions an event started, oldest first.
+    pub async fn list_by_event(&self, event_id: Uuid) -> Result<Vec<Execution>> {
+        let executions = sqlx::query_as::<_, Execution>(
+            "SELECT * FROM orchepy_executions WHERE event_id = $1 ORDER BY started_at ASC"
+        )
+        .bind(event_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(executions)
+    }
+
+    pub async fn list_failed_since(&self, since: DateTime<Utc>, limit: i64) -> Result<Vec<Execution>> {
+        let executions = sqlx::query_as::<_, Execution>(
+            "SELECT * FROM orchepy_executions WHERE status = 'failed' AND started_at >= $1 ORDER BY started_at DESC LIMIT $2"
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(executions)
+    }
+
+    pub async fn top_failing_flows(&self, since: DateTime<Utc>, limit: i64) -> Result<Vec<FlowFailureCount>> {
+        let flows = sqlx::query_as::<_, FlowFailureCount>(
+            "SELECT e.flow_id, f.name AS flow_name, COUNT(*) AS failures
+             FROM orchepy_executions e
+             JOIN orchepy_flows f ON f.id = e.flow_id
+             WHERE e.status = 'failed' AND e.started_at >= $1
+             GROUP BY e.flow_id, f.name
+             ORDER BY failures DESC, f.name
+             LIMIT $2"
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(flows)
+    }
+
+    /// End-to-end latency of finished executions of a flow. Time spent
+    /// waiting on `delay_until` steps is included.
+    pub async fn flow_latency(&self, flow_id: Uuid, since: DateTime<Utc>) -> Result<LatencyStats> {
+        let stats = sqlx::query_as::<_, LatencyStats>(
+            "WITH d AS (
+                SELECT EXTRACT(EPOCH FROM (completed_at - started_at))::float8 * 1000 AS ms
+                FROM orchepy_executions
+                WHERE flow_id = $1 AND started_at >= $2 AND completed_at IS NOT NULL
+             )
+             SELECT COUNT(*) AS samples,
+                    AVG(ms) AS avg_ms,
+                    percentile_cont(0.5) WITHIN GROUP (ORDER BY ms) AS p50_ms,
+                    percentile_cont(0.95) WITHIN GROUP (ORDER BY ms) AS p95_ms,
+                    MAX(ms) AS max_ms
+             FROM d"
+        )
+        .bind(flow_id)
+        .bind(since)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    /// Per-step latency and attempt counts taken from `steps_status`. Steps
+    /// recorded before durations were tracked are ignored.
+    pub async fn step_latency(&self, flow_id: Uuid, since: DateTime<Utc>) -> Result<Vec<StepLatency>> {
+        let steps = sqlx::query_as::<_, StepLatency>(
+            "WITH s AS (
+                SELECT step.key AS step,
+                       (step.value->>'duration_ms')::float8 AS ms,
+                       (step.value->>'attempts')::float8 AS attempts,
+                       step.value->>'status' AS status
+                FROM orchepy_executions e, jsonb_each(e.steps_status) AS step
+                WHERE e.flow_id = $1 AND e.started_at >= $2
+                  AND jsonb_typeof(step.value->'duration_ms') = 'number'
+             )
+             SELECT step,
+                    COUNT(*) AS samples,
+                    COUNT(*) FILTER (WHERE status = 'failed') AS failures,
+                    AVG(ms) AS avg_ms,
+                    percentile_cont(0.5) WITHIN GROUP (ORDER BY ms) AS p50_ms,
+                    percentile_cont(0.95) WITHIN GROUP (ORDER BY ms) AS p95_ms,
+                    MAX(ms) AS max_ms,
+                    AVG(attempts) AS avg_attempts
+             FROM s
+             GROUP BY step
+             ORDER BY p95_ms DESC NULLS LAST, step"
+        )
+        .bind(flow_id)
+        .bind(since)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(steps)
+    }
+
+    /// Marks up to `limit` waiting executions whose resume time has passed as
+    /// running and returns them. Rows locked by another worker are skipped.
+    /// The most recent successful response of each step of a flow, used as
+    /// fixtures when simulating it. Responses moved to object storage are
+    /// skipped.
+    pub async fn recorded_responses(&self, flow_id: Uuid) -> Result<HashMap<String, Value>> {
+        let rows = sqlx::query_as::<_, (String, Value)>(
+            "SELECT DISTINCT ON (step.key) step.key, step.value->'response'
+             FROM orchepy_executions e, jsonb_each(e.steps_status) AS step
+             WHERE e.flow_id = $1
+               AND step.value->>'status' = 'completed'
+               AND jsonb_typeof(step.value->'response') <> 'null'
+               AND NOT step.value->'response' ? '$offloaded'
+             ORDER BY step.key, e.started_at DESC"
+        )
+        .bind(flow_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Marks up to `limit` executions waiting until `now` or earlier as
+    /// running and returns them. Rows locked by another worker are skipped.
+    /// Marks up to `limit` waiting executions due at `now` as running and
+    /// leases them for `lease`. Executions whose lease ran out while they
+    /// were running are claimed again; `update` ends the lease.
+    pub async fn claim_due_waiting(&self, now: DateTime<Utc>, limit: i64, lease: Duration) -> Result<Vec<Execution>> {
+        let executions = sqlx::query_as::<_, Execution>(
+            "UPDATE orchepy_executions SET status = 'running', locked_until = $1 + make_interval(secs => $3)
+             WHERE (id, started_at) IN (
+                SELECT id, started_at FROM orchepy_executions
+                WHERE (status = 'waiting' AND resume_at <= $1)
+                   OR (status = 'running' AND locked_until <= $1)
+                ORDER BY resume_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+             )
+             RETURNING *"
+        )
+        .bind(now)
+        .bind(limit)
+        .bind(lease.as_secs_f64())
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(executions)
+    }
+
+    pub async fn create(&self, execution: &Execution) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO orchepy_executions
+             (id, flow_id, event_id, flow_version, definition_hash, status, current_step, steps_status, started_at, completed_at, error, resume_at, resume_step)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"
+        )
+        .bind(execution.id)
+        .bind(execution.flow_id)
+        .bind(execution.event_id)
+        .bind(execution.flow_version)
+        .bind(&execution.definition_hash)
+        .bind(&execution.status)
+        .bind(&execution.current_step)
+        .bind(&execution.steps_status)
+        .bind(execution.started_at)
+        .bind(execution.completed_at)
+        .bind(&execution.error)
+        .bind(execution.resume_at)
+        .bind(execution.resume_step)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes up to `limit` completed or failed executions that finished
+    /// before `before`. Returns how many.
+    pub async fn purge_finished(&self, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM orchepy_executions
+             WHERE (id, started_at) IN (
+                SELECT id, started_at FROM orchepy_executions
+                WHERE started_at < $1 AND completed_at < $1 AND status IN ('completed', 'failed')
+                LIMIT $2
+             )"
+        )
+        .bind(before)
+        .bind(limit)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Matches on `started_at` as well as the id, so only the month's
+    /// partition is scanned.
+    pub async fn update(&self, execution: &Execution) -> Result<()> {
+        sqlx::query(
+            "UPDATE orchepy_executions SET status = $1, current_step = $2, steps_status = $3, completed_at = $4, error = $5, resume_at = $6, resume_step = $7,
+                 locked_until = NULL
+             WHERE id = $8 AND started_at = $9"
+        )
+        .bind(&execution.status)
+        .bind(&execution.current_step)
+        .bind(&execution.steps_status)
+        .bind(execution.completed_at)
+        .bind(&execution.error)
+        .bind(execution.resume_at)
+        .bind(execution.resume_step)
+        .bind(execution.id)
+        .bind(execution.started_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn push_execution_filters<'q>(query: &mut QueryBuilder<'q, Postgres>, filter: &'q ExecutionFilter) {
+    if let Some(status) = &filter.status {
+        query.push(" AND status = ");
+        query.push_bind(status);
+    }
+
+    if let Some(flow_id) = filter.flow_id {
+        query.push(" AND flow_id = ");
+        query.push_bind(flow_id);
+    }
+
+    if let Some(event_id) = filter.event_id {
+        query.push(" AND event_id = ");
+        query.push_bind(event_id);
+    }
+
+    if let Some(since) = filter.since {
+        query.push(" AND started_at >= ");
+        query.push_bind(since);
+    }
+
+    if let Some(until) = filter.until {
+        query.push(" AND started_at < ");
+        query.push_bind(until);
+    }
+
+    match filter.has_error {
+        Some(true) => query.push(" AND error IS NOT NULL"),
+        Some(false) => query.push(" AND error IS NULL"),
+        None => query,
+    };
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::models::flow::FlowVersion;
+use crate::models::Flow;
+
+pub struct FlowRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> FlowRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Inserts the flow and saves it as its first version.
+    pub async fn create(&self, flow: &Flow) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO orchepy_flows (id, name, trigger, steps, max_concurrent_executions, version, active, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(flow.id)
+        .bind(&flow.name)
+        .bind(serde_json::to_value(&flow.trigger)?)
+        .bind(serde_json::to_value(&flow.steps)?)
+        .bind(flow.max_concurrent_executions)
+        .bind(flow.version)
+        .bind(flow.active)
+        .bind(flow.created_at)
+        .bind(flow.updated_at)
+        .execute(&mut *tx)
+        .await?;
+
+        save_version(&mut tx, &FlowVersion::snapshot(flow)).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Flow>> {
+        let flow = sqlx::query_as::<_, Flow>("SELECT * FROM orchepy_flows WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(flow)
+    }
+
+    /// Newest first; a `None` limit returns all of them.
+    pub async fn list(&self, limit: Option<i64>, offset: i64) -> Result<Vec<Flow>> {
+        let flows = sqlx::query_as::<_, Flow>("SELECT * FROM orchepy_flows ORDER BY created_at DESC LIMIT $1 OFFSET $2")
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(flows)
+    }
+
+    pub async fn list_active(&self) -> Result<Vec<Flow>> {
+        let flows = sqlx::query_as::<_, Flow>("SELECT * FROM orchepy_flows WHERE active = true")
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(flows)
+    }
+
+    pub async fn count(&self) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM orchepy_flows")
+            .fetch_one(self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// Writes the flow as it is, including its already bumped version, and
+    /// saves that version. Returns false, writing nothing, when the stored
+    /// flow is no longer at the version before, as when another update got
+    /// there first.
+    pub async fn update(&self, flow: &Flow) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE orchepy_flows SET name = $1, trigger = $2, steps = $3, max_concurrent_executions = $4, version = $5, active = $6, updated_at = $7 WHERE id = $8 AND version = $5 - 1"
+        )
+        .bind(&flow.name)
+        .bind(serde_json::to_value(&flow.trigger)?)
+        .bind(serde_json::to_value(&flow.steps)?)
+        .bind(flow.max_concurrent_executions)
+        .bind(flow.version)
+        .bind(flow.active)
+        .bind(flow.updated_at)
+        .bind(flow.id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        save_version(&mut tx, &FlowVersion::snapshot(flow)).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM orchepy_flows WHERE id = $1")
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Newest first.
+    pub async fn list_versions(&self, flow_id: Uuid) -> Result<Vec<FlowVersion>> {
+        let versions = sqlx::query_as::<_, FlowVersion>(
+            "SELECT * FROM orchepy_flow_versions WHERE flow_id = $1 ORDER BY version DESC"
+        )
+        .bind(flow_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(versions)
+    }
+
+    pub async fn find_version(&self, flow_id: Uuid, version: i32) -> Result<Option<FlowVersion>> {
+        let flow_version = sqlx::query_as::<_, FlowVersion>(
+            "SELECT * FROM orchepy_flow_versions WHERE flow_id = $1 AND version = $2"
+        )
+        .bind(flow_id)
+        .bind(version)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(flow_version)
+    }
+}
+
+async fn save_version(tx: &mut Transaction<'_, Postgres>, version: &FlowVersion) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO orchepy_flow_versions (flow_id, version, name, trigger, steps, max_concurrent_executions, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(version.flow_id)
+    .bind(version.version)
+    .bind(&version.name)
+    .bind(serde_json::to_value(&version.trigger)?)
+    .bind(serde_json::to_value(&version.steps)?)
+    .bind(version.max_concurrent_executions)
+    .bind(version.created_at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::migrate::Migrator;
+use sqlx::{FromRow, PgPool};
+
+/// The migrations this build expects the database to have.
+pub static MIGRATOR: Migrator = sqlx::migrate!("src/db/migrations");
+
+/// Work a worker should already have picked up.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct QueueBacklog {
+    pub queue: String,
+    pub due: i64,
+    pub oldest_due_at: Option<DateTime<Utc>>,
+}
+
+pub struct HealthRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> HealthRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(self.pool).await?;
+        Ok(())
+    }
+
+    /// Versions of the migrations applied successfully, or none when the
+    /// database was never migrated.
+    pub async fn applied_migrations(&self) -> Result<Vec<i64>> {
+        let migrated: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(self.pool)
+            .await?;
+        if !migrated {
+            return Ok(Vec::new());
+        }
+
+        let versions = sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version")
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(versions)
+    }
+
+    /// Suspended executions and deferred automations due to resume by `now`.
+    pub async fn backlog(&self, now: DateTime<Utc>) -> Result<Vec<QueueBacklog>> {
+        let backlog = sqlx::query_as::<_, QueueBacklog>(
+            "SELECT 'flow_resume' AS queue, COUNT(*) AS due, MIN(resume_at) AS oldest_due_at
+             FROM orchepy_executions
+             WHERE status = 'waiting' AND resume_at <= $1
+             UNION ALL
+             SELECT 'automation_resume', COUNT(*), MIN(resume_at)
+             FROM orchepy_deferred_automations
+             WHERE resume_at <= $1 AND (locked_until IS NULL OR locked_until <= $1)"
+        )
+        .bind(now)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(backlog)
+    }
+}
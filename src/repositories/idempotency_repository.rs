@@ -0,0 +1,155 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+
+/// A key as sent by one client to one route. Clients are told apart by
+/// their API key fingerprint; requests without one share the empty client.
+#[derive(Debug, Clone)]
+pub struct IdempotencyKey {
+    pub client: String,
+    pub route: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct StoredResponse {
+    pub status_code: i16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum IdempotencyClaim {
+    /// The key is new, expired or abandoned; the request should run.
+    Claimed,
+    /// A request with the key is still being handled.
+    InProgress,
+    /// The key was used for a request with a different body.
+    Mismatch,
+    Completed(StoredResponse),
+}
+
+#[derive(FromRow)]
+struct KeyRow {
+    request_hash: String,
+    status_code: Option<i16>,
+    content_type: Option<String>,
+    body: Option<Vec<u8>>,
+}
+
+pub struct IdempotencyRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> IdempotencyRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Claims `key` for a request until `expires_at`, unless it is held by
+    /// an unexpired request. A claim whose request never finished is taken
+    /// over once it is older than `abandoned_before`.
+    pub async fn claim(
+        &self,
+        key: &IdempotencyKey,
+        request_hash: &str,
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+        abandoned_before: DateTime<Utc>,
+    ) -> Result<IdempotencyClaim> {
+        let claimed = sqlx::query(
+            "INSERT INTO orchepy_idempotency_keys (client, route, key, request_hash, created_at, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (client, route, key) DO UPDATE
+             SET request_hash = EXCLUDED.request_hash, status_code = NULL, content_type = NULL, body = NULL,
+                 created_at = EXCLUDED.created_at, expires_at = EXCLUDED.expires_at
+             WHERE orchepy_idempotency_keys.expires_at <= EXCLUDED.created_at
+                OR (orchepy_idempotency_keys.status_code IS NULL AND orchepy_idempotency_keys.created_at <= $7)
+             RETURNING key",
+        )
+        .bind(&key.client)
+        .bind(&key.route)
+        .bind(&key.key)
+        .bind(request_hash)
+        .bind(now)
+        .bind(expires_at)
+        .bind(abandoned_before)
+        .fetch_optional(self.pool)
+        .await?;
+
+        if claimed.is_some() {
+            return Ok(IdempotencyClaim::Claimed);
+        }
+
+        let row = sqlx::query_as::<_, KeyRow>(
+            "SELECT request_hash, status_code, content_type, body FROM orchepy_idempotency_keys
+             WHERE client = $1 AND route = $2 AND key = $3",
+        )
+        .bind(&key.client)
+        .bind(&key.route)
+        .bind(&key.key)
+        .fetch_optional(self.pool)
+        .await?;
+
+        // Pruned between the two statements; the client can try again.
+        let Some(row) = row else {
+            return Ok(IdempotencyClaim::InProgress);
+        };
+
+        if row.request_hash != request_hash {
+            return Ok(IdempotencyClaim::Mismatch);
+        }
+
+        Ok(match row.status_code {
+            Some(status_code) => IdempotencyClaim::Completed(StoredResponse {
+                status_code,
+                content_type: row.content_type,
+                body: row.body.unwrap_or_default(),
+            }),
+            None => IdempotencyClaim::InProgress,
+        })
+    }
+
+    /// Stores the response of a claimed request for replays.
+    pub async fn complete(&self, key: &IdempotencyKey, response: &StoredResponse) -> Result<()> {
+        sqlx::query(
+            "UPDATE orchepy_idempotency_keys SET status_code = $4, content_type = $5, body = $6
+             WHERE client = $1 AND route = $2 AND key = $3",
+        )
+        .bind(&key.client)
+        .bind(&key.route)
+        .bind(&key.key)
+        .bind(response.status_code)
+        .bind(&response.content_type)
+        .bind(&response.body)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gives up a claim so the request can be retried with the same key.
+    pub async fn release(&self, key: &IdempotencyKey) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM orchepy_idempotency_keys
+             WHERE client = $1 AND route = $2 AND key = $3 AND status_code IS NULL",
+        )
+        .bind(&key.client)
+        .bind(&key.route)
+        .bind(&key.key)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes keys that expired before `now`. Returns how many.
+    pub async fn prune(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM orchepy_idempotency_keys WHERE expires_at <= $1")
+            .bind(now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
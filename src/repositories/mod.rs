@@ -1,5 +1,59 @@
+pub mod analytics_repository;
+pub mod automation_run_repository;
+pub mod case_attachment_repository;
+pub mod case_comment_repository;
+pub mod case_link_repository;
+pub mod case_message_repository;
+pub mod case_presence_repository;
 pub mod case_repository;
+pub mod case_revision_repository;
+pub mod case_workflow_repository;
+pub mod change_repository;
+pub mod deferred_automation_repository;
+pub mod definition_snapshot_repository;
+pub mod email_log_repository;
+pub mod event_repository;
+pub mod execution_repository;
+pub mod flow_repository;
+pub mod health_repository;
+pub mod idempotency_repository;
+pub mod outbox_repository;
+pub mod partition_repository;
+pub mod portal_token_repository;
+pub mod secret_repository;
+pub mod service_account_repository;
+pub mod signing_key_repository;
+pub mod usage_repository;
+pub mod webhook_delivery_log_repository;
+pub mod webhook_subscription_repository;
 pub mod workflow_repository;
 
+pub use analytics_repository::AnalyticsRepository;
+pub use automation_run_repository::AutomationRunRepository;
+pub use case_attachment_repository::CaseAttachmentRepository;
+pub use case_comment_repository::CaseCommentRepository;
+pub use case_link_repository::CaseLinkRepository;
+pub use case_message_repository::CaseMessageRepository;
+pub use case_presence_repository::CasePresenceRepository;
 pub use case_repository::CaseRepository;
+pub use case_revision_repository::CaseRevisionRepository;
+pub use case_workflow_repository::CaseWorkflowRepository;
+pub use change_repository::ChangeRepository;
+pub use deferred_automation_repository::DeferredAutomationRepository;
+pub use definition_snapshot_repository::DefinitionSnapshotRepository;
+pub use email_log_repository::EmailLogRepository;
+pub use event_repository::EventRepository;
+pub use execution_repository::ExecutionRepository;
+pub use flow_repository::FlowRepository;
+pub use health_repository::HealthRepository;
+pub use idempotency_repository::IdempotencyRepository;
+pub use outbox_repository::OutboxRepository;
+pub use partition_repository::PartitionRepository;
+pub use portal_token_repository::PortalTokenRepository;
+pub use secret_repository::SecretRepository;
+pub use service_account_repository::ServiceAccountRepository;
+pub use signing_key_repository::SigningKeyRepository;
+pub use usage_repository::UsageRepository;
+pub use webhook_delivery_log_repository::WebhookDeliveryLogRepository;
+pub use webhook_subscription_repository::WebhookSubscriptionRepository;
 pub use workflow_repository::WorkflowRepository;
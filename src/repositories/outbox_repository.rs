@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool};
+
+use crate::models::outbox::{OutboxEntry, OutboxMessage, OutboxRecord, OutboxScope};
+
+const RECORD_COLUMNS: &str = "id, case_id, CASE WHEN failed_at IS NULL THEN 'pending' ELSE 'failed' END AS status,
+    delivery, attempts, next_attempt_at, last_error, failed_at, created_at";
+
+pub struct OutboxRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> OutboxRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the ids of the queued messages.
+    pub async fn enqueue(&self, messages: &[OutboxMessage]) -> Result<Vec<i64>> {
+        enqueue_in(&mut *self.pool.acquire().await?, messages).await
+    }
+
+    /// Leases up to `limit` due messages in `scope` for `lease`. Only the
+    /// oldest undelivered message of each case and kind is handed out, so a
+    /// case's messages go out one after the other; rows leased by another
+    /// dispatcher are skipped.
+    pub async fn claim(&self, scope: &OutboxScope, limit: i64, lease: Duration) -> Result<Vec<OutboxEntry>> {
+        let (case_id, ids) = match scope {
+            OutboxScope::All => (None, None),
+            OutboxScope::Case(case_id) => (Some(*case_id), None),
+            OutboxScope::Messages(ids) => (None, Some(ids.as_slice())),
+        };
+
+        let mut entries = sqlx::query_as::<_, OutboxEntry>(
+            "UPDATE orchepy_outbox
+             SET attempts = attempts + 1, locked_until = NOW() + make_interval(secs => $3)
+             WHERE id IN (
+                SELECT m.id FROM orchepy_outbox m
+                WHERE m.failed_at IS NULL
+                  AND m.next_attempt_at <= NOW()
+                  AND (m.locked_until IS NULL OR m.locked_until <= NOW())
+                  AND ($1::uuid IS NULL OR m.case_id = $1)
+                  AND ($4::bigint[] IS NULL OR m.id = ANY($4))
+                  AND NOT EXISTS (
+                      SELECT 1 FROM orchepy_outbox earlier
+                      WHERE earlier.case_id = m.case_id AND earlier.kind = m.kind
+                        AND earlier.id < m.id AND earlier.failed_at IS NULL
+                  )
+                ORDER BY m.id
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+             )
+             RETURNING id, case_id, delivery, attempts"
+        )
+        .bind(case_id)
+        .bind(limit)
+        .bind(lease.as_secs_f64())
+        .bind(ids)
+        .fetch_all(self.pool)
+        .await?;
+
+        entries.sort_by_key(|entry| entry.id);
+        Ok(entries)
+    }
+
+    pub async fn delivered(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM orchepy_outbox WHERE id = $1")
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Webhook deliveries, newest first: the failed ones with `failed`, the
+    /// pending ones without, both when `None`.
+    pub async fn list_webhooks(&self, failed: Option<bool>, limit: i64, offset: i64) -> Result<Vec<OutboxRecord>> {
+        let records = sqlx::query_as::<_, OutboxRecord>(&format!(
+            "SELECT {} FROM orchepy_outbox
+             WHERE kind <> 'event' AND ($1::boolean IS NULL OR (failed_at IS NOT NULL) = $1)
+             ORDER BY id DESC LIMIT $2 OFFSET $3",
+            RECORD_COLUMNS
+        ))
+        .bind(failed)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Queues a failed webhook delivery again with a fresh set of attempts.
+    /// Returns `None` unless `id` is a webhook delivery that was given up on.
+    pub async fn retry_webhook(&self, id: i64) -> Result<Option<OutboxRecord>> {
+        let record = sqlx::query_as::<_, OutboxRecord>(&format!(
+            "UPDATE orchepy_outbox SET failed_at = NULL, attempts = 0, next_attempt_at = NOW(), locked_until = NULL
+             WHERE id = $1 AND kind <> 'event' AND failed_at IS NOT NULL
+             RETURNING {}",
+            RECORD_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Releases the message to be attempted again after `delay`, or for
+    /// good without one. A message given up on no longer holds back the
+    /// ones after it.
+    pub async fn failed(&self, id: i64, error: &str, delay: Option<Duration>) -> Result<()> {
+        sqlx::query(
+            "UPDATE orchepy_outbox
+             SET locked_until = NULL, last_error = $2,
+                 next_attempt_at = NOW() + make_interval(secs => COALESCE($3, 0)),
+                 failed_at = CASE WHEN $3 IS NULL THEN NOW() END
+             WHERE id = $1"
+        )
+        .bind(id)
+        .bind(error)
+        .bind(delay.map(|delay| delay.as_secs_f64()))
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+pub(crate) async fn enqueue_in(conn: &mut PgConnection, messages: &[OutboxMessage]) -> Result<Vec<i64>> {
+    let mut ids = Vec::with_capacity(messages.len());
+    for message in messages {
+        let id = sqlx::query_scalar("INSERT INTO orchepy_outbox (case_id, kind, delivery) VALUES ($1, $2, $3) RETURNING id")
+            .bind(message.case_id)
+            .bind(message.delivery.kind())
+            .bind(sqlx::types::Json(&message.delivery))
+            .fetch_one(&mut *conn)
+            .await?;
+        ids.push(id);
+    }
+
+    Ok(ids)
+}
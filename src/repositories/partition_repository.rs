@@ -0,0 +1,50 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Tables partitioned by month on the time their rows were written:
+/// `received_at`, `transitioned_at` and `started_at`.
+pub const PARTITIONED_TABLES: &[&str] = &["orchepy_events", "orchepy_case_history", "orchepy_executions"];
+
+pub struct PartitionRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> PartitionRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Creates the monthly partitions of every partitioned table from the
+    /// month of `from` through the month of `until` that don't exist yet.
+    /// Returns how many were created.
+    pub async fn create_monthly(&self, from: DateTime<Utc>, until: DateTime<Utc>) -> Result<i64> {
+        let mut created = 0;
+        for table in PARTITIONED_TABLES {
+            let count: i32 = sqlx::query_scalar("SELECT orchepy_create_monthly_partitions($1, $2, $3)")
+                .bind(table)
+                .bind(from)
+                .bind(until)
+                .fetch_one(self.pool)
+                .await?;
+            created += i64::from(count);
+        }
+        Ok(created)
+    }
+
+    /// The monthly partitions of `table`, oldest first; the default
+    /// partition is left out.
+    pub async fn list(&self, table: &str) -> Result<Vec<String>> {
+        let partitions = sqlx::query_scalar(
+            "SELECT child.relname::text FROM pg_inherits
+             JOIN pg_class child ON child.oid = pg_inherits.inhrelid
+             WHERE pg_inherits.inhparent = to_regclass($1) AND child.relname <> $1 || '_default'
+             ORDER BY child.relname",
+        )
+        .bind(table)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(partitions)
+    }
+}
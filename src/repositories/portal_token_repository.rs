@@ -0,0 +1,133 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::portal::{hash_token, PortalToken};
+
+pub struct PortalTokenRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> PortalTokenRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, token: &PortalToken) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO orchepy_portal_tokens (id, case_id, token_hash, fields, allow_replies, expires_at, revoked_at, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+        )
+        .bind(token.id)
+        .bind(token.case_id)
+        .bind(&token.token_hash)
+        .bind(sqlx::types::Json(&token.fields))
+        .bind(token.allow_replies)
+        .bind(token.expires_at)
+        .bind(token.revoked_at)
+        .bind(token.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_by_case(&self, case_id: Uuid) -> Result<Vec<PortalToken>> {
+        let tokens = sqlx::query_as::<_, PortalToken>(
+            "SELECT * FROM orchepy_portal_tokens WHERE case_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(case_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    /// The token if it exists and has neither expired nor been revoked.
+    pub async fn find_active(&self, token: &str) -> Result<Option<PortalToken>> {
+        let token = sqlx::query_as::<_, PortalToken>(
+            "SELECT * FROM orchepy_portal_tokens WHERE token_hash = $1"
+        )
+        .bind(hash_token(token))
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(token.filter(|token| token.is_active(Utc::now())))
+    }
+
+    /// Returns false when the case has no such token or it was already revoked.
+    pub async fn revoke(&self, case_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE orchepy_portal_tokens SET revoked_at = NOW()
+             WHERE id = $1 AND case_id = $2 AND revoked_at IS NULL"
+        )
+        .bind(id)
+        .bind(case_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Unrevoked tokens that expire before `before`, including ones that
+    /// already have, soonest first.
+    pub async fn list_expiring(&self, before: DateTime<Utc>, limit: i64) -> Result<Vec<PortalToken>> {
+        let tokens = sqlx::query_as::<_, PortalToken>(
+            "SELECT * FROM orchepy_portal_tokens
+             WHERE revoked_at IS NULL AND expires_at IS NOT NULL AND expires_at <= $1
+             ORDER BY expires_at
+             LIMIT $2"
+        )
+        .bind(before)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    /// Marks expired tokens as revoked and returns them, so the revocation
+    /// is recorded even though expired tokens are already refused.
+    pub async fn revoke_expired(&self, limit: i64) -> Result<Vec<PortalToken>> {
+        let tokens = sqlx::query_as::<_, PortalToken>(
+            "UPDATE orchepy_portal_tokens SET revoked_at = NOW()
+             WHERE id IN (
+                 SELECT id FROM orchepy_portal_tokens
+                 WHERE revoked_at IS NULL AND expires_at <= NOW()
+                 ORDER BY expires_at
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING *"
+        )
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    /// Marks up to `limit` live tokens expiring before `before` as reminded
+    /// and returns them. Each token is claimed once.
+    pub async fn claim_reminders(&self, before: DateTime<Utc>, limit: i64) -> Result<Vec<PortalToken>> {
+        let tokens = sqlx::query_as::<_, PortalToken>(
+            "UPDATE orchepy_portal_tokens SET reminded_at = NOW()
+             WHERE id IN (
+                 SELECT id FROM orchepy_portal_tokens
+                 WHERE revoked_at IS NULL AND reminded_at IS NULL
+                   AND expires_at > NOW() AND expires_at <= $1
+                 ORDER BY expires_at
+                 LIMIT $2
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING *"
+        )
+        .bind(before)
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use sqlx::PgPool;
+
+use crate::models::secret::Secret;
+
+pub struct SecretRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> SecretRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns false when a secret with the same name already exists.
+    pub async fn create(&self, secret: &Secret) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO orchepy_secrets (name, description, ciphertext, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (name) DO NOTHING"
+        )
+        .bind(&secret.name)
+        .bind(&secret.description)
+        .bind(&secret.ciphertext)
+        .bind(secret.created_at)
+        .bind(secret.updated_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list(&self) -> Result<Vec<Secret>> {
+        let secrets = sqlx::query_as::<_, Secret>("SELECT * FROM orchepy_secrets ORDER BY name")
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(secrets)
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Option<Secret>> {
+        let secret = sqlx::query_as::<_, Secret>("SELECT * FROM orchepy_secrets WHERE name = $1")
+            .bind(name)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(secret)
+    }
+
+    /// The secrets among `names` that exist.
+    pub async fn find_many(&self, names: &BTreeSet<String>) -> Result<Vec<Secret>> {
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let secrets = sqlx::query_as::<_, Secret>("SELECT * FROM orchepy_secrets WHERE name = ANY($1)")
+            .bind(names)
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(secrets)
+    }
+
+    /// Replaces the value and description; `None` when there is no secret
+    /// with that name.
+    pub async fn update(&self, name: &str, description: Option<&str>, ciphertext: &[u8]) -> Result<Option<Secret>> {
+        let secret = sqlx::query_as::<_, Secret>(
+            "UPDATE orchepy_secrets
+             SET description = $2, ciphertext = $3, updated_at = NOW()
+             WHERE name = $1
+             RETURNING *"
+        )
+        .bind(name)
+        .bind(description)
+        .bind(ciphertext)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(secret)
+    }
+
+    /// Swaps the ciphertext of `name` for one encrypted under another key,
+    /// unless the secret changed since `current` was read.
+    pub async fn replace_ciphertext(&self, name: &str, current: &[u8], ciphertext: &[u8]) -> Result<bool> {
+        let result = sqlx::query("UPDATE orchepy_secrets SET ciphertext = $3 WHERE name = $1 AND ciphertext = $2")
+            .bind(name)
+            .bind(current)
+            .bind(ciphertext)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM orchepy_secrets WHERE name = $1")
+            .bind(name)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool};
+
+use crate::models::service_account::ServiceAccount;
+
+pub struct ServiceAccountRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> ServiceAccountRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns false when an account with the same name already exists.
+    pub async fn create(&self, account: &ServiceAccount) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO orchepy_service_accounts (name, description, allowed_phases, allowed_statuses, active, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (name) DO NOTHING"
+        )
+        .bind(&account.name)
+        .bind(&account.description)
+        .bind(account.allowed_phases.as_ref().map(sqlx::types::Json))
+        .bind(account.allowed_statuses.as_ref().map(sqlx::types::Json))
+        .bind(account.active)
+        .bind(account.created_at)
+        .bind(account.updated_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list(&self) -> Result<Vec<ServiceAccount>> {
+        let accounts = sqlx::query_as::<_, ServiceAccount>(
+            "SELECT * FROM orchepy_service_accounts ORDER BY name"
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Option<ServiceAccount>> {
+        find_service_account_in(&mut *self.pool.acquire().await?, name).await
+    }
+
+    /// Returns false when there is no account with that name.
+    pub async fn update(&self, account: &ServiceAccount) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE orchepy_service_accounts
+             SET description = $2, allowed_phases = $3, allowed_statuses = $4, active = $5, updated_at = NOW()
+             WHERE name = $1"
+        )
+        .bind(&account.name)
+        .bind(&account.description)
+        .bind(account.allowed_phases.as_ref().map(sqlx::types::Json))
+        .bind(account.allowed_statuses.as_ref().map(sqlx::types::Json))
+        .bind(account.active)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM orchepy_service_accounts WHERE name = $1")
+            .bind(name)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+pub(crate) async fn find_service_account_in(conn: &mut PgConnection, name: &str) -> Result<Option<ServiceAccount>> {
+    let account = sqlx::query_as::<_, ServiceAccount>(
+        "SELECT * FROM orchepy_service_accounts WHERE name = $1"
+    )
+    .bind(name)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(account)
+}
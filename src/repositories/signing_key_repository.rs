@@ -0,0 +1,82 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::signing_key::ManagedSigningKey;
+
+pub struct SigningKeyRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> SigningKeyRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Newest first.
+    pub async fn list(&self) -> Result<Vec<ManagedSigningKey>> {
+        let keys = sqlx::query_as::<_, ManagedSigningKey>("SELECT * FROM orchepy_signing_keys ORDER BY created_at DESC, id")
+            .fetch_all(self.pool)
+            .await?;
+
+        Ok(keys)
+    }
+
+    /// Keys that have not retired by `now`.
+    pub async fn list_signing(&self, now: DateTime<Utc>) -> Result<Vec<ManagedSigningKey>> {
+        let keys = sqlx::query_as::<_, ManagedSigningKey>(
+            "SELECT * FROM orchepy_signing_keys WHERE retires_at IS NULL OR retires_at > $1 ORDER BY created_at DESC, id",
+        )
+        .bind(now)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(keys)
+    }
+
+    /// Adds `key` and has every other key still signing retire at
+    /// `retire_at`, or earlier if it was already due to. Returns the ids of
+    /// the keys being replaced.
+    pub async fn rotate(&self, key: &ManagedSigningKey, retire_at: DateTime<Utc>) -> Result<Vec<String>> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("LOCK TABLE orchepy_signing_keys IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+
+        let retiring: Vec<String> = sqlx::query_scalar(
+            "UPDATE orchepy_signing_keys SET retires_at = LEAST(COALESCE(retires_at, $1), $1)
+             WHERE retires_at IS NULL OR retires_at > NOW()
+             RETURNING id",
+        )
+        .bind(retire_at)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO orchepy_signing_keys (id, secret, created_by, created_at, retires_at)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(&key.id)
+        .bind(&key.secret)
+        .bind(&key.created_by)
+        .bind(key.created_at)
+        .bind(key.retires_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(retiring)
+    }
+
+    /// Deletes keys that retired before `before`.
+    pub async fn prune(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM orchepy_signing_keys WHERE retires_at < $1")
+            .bind(before)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
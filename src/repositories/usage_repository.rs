@@ -0,0 +1,135 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+use crate::services::usage::{UsageCounters, UsageKey};
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct UsageTotals {
+    pub requests: i64,
+    pub client_errors: i64,
+    pub server_errors: i64,
+    pub avg_latency_ms: Option<f64>,
+    pub max_latency_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DailyUsage {
+    pub day: NaiveDate,
+    pub requests: i64,
+    pub client_errors: i64,
+    pub server_errors: i64,
+    pub avg_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct RouteUsage {
+    pub method: String,
+    pub route: String,
+    pub requests: i64,
+    pub client_errors: i64,
+    pub server_errors: i64,
+    pub avg_latency_ms: Option<f64>,
+    pub max_latency_ms: i64,
+}
+
+pub struct UsageRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> UsageRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn upsert(&self, entries: &[(UsageKey, UsageCounters)]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for (key, counters) in entries {
+            sqlx::query(
+                "INSERT INTO orchepy_api_usage (key_id, bucket_start, method, route, request_count, client_error_count, server_error_count, total_latency_ms, max_latency_ms)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 ON CONFLICT (key_id, bucket_start, method, route) DO UPDATE SET
+                    request_count = orchepy_api_usage.request_count + EXCLUDED.request_count,
+                    client_error_count = orchepy_api_usage.client_error_count + EXCLUDED.client_error_count,
+                    server_error_count = orchepy_api_usage.server_error_count + EXCLUDED.server_error_count,
+                    total_latency_ms = orchepy_api_usage.total_latency_ms + EXCLUDED.total_latency_ms,
+                    max_latency_ms = GREATEST(orchepy_api_usage.max_latency_ms, EXCLUDED.max_latency_ms)"
+            )
+            .bind(&key.key_id)
+            .bind(key.bucket_start)
+            .bind(&key.method)
+            .bind(&key.route)
+            .bind(counters.requests)
+            .bind(counters.client_errors)
+            .bind(counters.server_errors)
+            .bind(counters.total_latency_ms)
+            .bind(counters.max_latency_ms)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn totals(&self, key_id: &str, since: DateTime<Utc>) -> Result<UsageTotals> {
+        let totals = sqlx::query_as::<_, UsageTotals>(
+            "SELECT COALESCE(SUM(request_count), 0)::BIGINT AS requests,
+                    COALESCE(SUM(client_error_count), 0)::BIGINT AS client_errors,
+                    COALESCE(SUM(server_error_count), 0)::BIGINT AS server_errors,
+                    (SUM(total_latency_ms)::FLOAT8 / NULLIF(SUM(request_count), 0)) AS avg_latency_ms,
+                    MAX(max_latency_ms) AS max_latency_ms
+             FROM orchepy_api_usage
+             WHERE key_id = $1 AND bucket_start >= $2"
+        )
+        .bind(key_id)
+        .bind(since)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(totals)
+    }
+
+    pub async fn daily(&self, key_id: &str, since: DateTime<Utc>) -> Result<Vec<DailyUsage>> {
+        let days = sqlx::query_as::<_, DailyUsage>(
+            "SELECT (bucket_start AT TIME ZONE 'UTC')::DATE AS day,
+                    SUM(request_count)::BIGINT AS requests,
+                    SUM(client_error_count)::BIGINT AS client_errors,
+                    SUM(server_error_count)::BIGINT AS server_errors,
+                    (SUM(total_latency_ms)::FLOAT8 / NULLIF(SUM(request_count), 0)) AS avg_latency_ms
+             FROM orchepy_api_usage
+             WHERE key_id = $1 AND bucket_start >= $2
+             GROUP BY day
+             ORDER BY day"
+        )
+        .bind(key_id)
+        .bind(since)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(days)
+    }
+
+    pub async fn by_route(&self, key_id: &str, since: DateTime<Utc>) -> Result<Vec<RouteUsage>> {
+        let routes = sqlx::query_as::<_, RouteUsage>(
+            "SELECT method, route,
+                    SUM(request_count)::BIGINT AS requests,
+                    SUM(client_error_count)::BIGINT AS client_errors,
+                    SUM(server_error_count)::BIGINT AS server_errors,
+                    (SUM(total_latency_ms)::FLOAT8 / NULLIF(SUM(request_count), 0)) AS avg_latency_ms,
+                    MAX(max_latency_ms) AS max_latency_ms
+             FROM orchepy_api_usage
+             WHERE key_id = $1 AND bucket_start >= $2
+             GROUP BY method, route
+             ORDER BY client_errors DESC, requests DESC"
+        )
+        .bind(key_id)
+        .bind(since)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(routes)
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::webhook_delivery::{WebhookAttempt, WebhookDeliveryLogEntry, WebhookDeliveryLogQuery};
+
+pub struct WebhookDeliveryLogRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> WebhookDeliveryLogRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(&self, attempt: &WebhookAttempt) -> Result<WebhookDeliveryLogEntry> {
+        let entry = sqlx::query_as::<_, WebhookDeliveryLogEntry>(
+            "INSERT INTO orchepy_webhook_deliveries
+                (url, payload, workflow_id, subscription_id, status, status_code, latency_ms, error, redelivery_of)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING *",
+        )
+        .bind(&attempt.url)
+        .bind(&attempt.payload)
+        .bind(attempt.workflow_id)
+        .bind(attempt.subscription_id)
+        .bind(if attempt.error.is_some() { "failed" } else { "delivered" })
+        .bind(attempt.status_code)
+        .bind(attempt.latency_ms)
+        .bind(&attempt.error)
+        .bind(attempt.redelivery_of)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(entry)
+    }
+
+    pub async fn find(&self, id: i64) -> Result<Option<WebhookDeliveryLogEntry>> {
+        let entry = sqlx::query_as::<_, WebhookDeliveryLogEntry>("SELECT * FROM orchepy_webhook_deliveries WHERE id = $1")
+            .bind(id)
+            .fetch_optional(self.pool)
+            .await?;
+
+        Ok(entry)
+    }
+
+    /// Deletes up to `limit` attempts logged before `before`.
+    pub async fn purge(&self, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM orchepy_webhook_deliveries
+             WHERE id IN (SELECT id FROM orchepy_webhook_deliveries WHERE created_at < $1 LIMIT $2)",
+        )
+        .bind(before)
+        .bind(limit)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Newest first. `status` is the query's, already checked.
+    pub async fn list(
+        &self,
+        query: &WebhookDeliveryLogQuery,
+        status: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDeliveryLogEntry>> {
+        let entries = sqlx::query_as::<_, WebhookDeliveryLogEntry>(
+            "SELECT * FROM orchepy_webhook_deliveries
+             WHERE ($1::text IS NULL OR status = $1)
+               AND ($2::uuid IS NULL OR workflow_id = $2)
+               AND ($3::uuid IS NULL OR subscription_id = $3)
+               AND ($4::timestamptz IS NULL OR created_at >= $4)
+               AND ($5::timestamptz IS NULL OR created_at < $5)
+             ORDER BY id DESC LIMIT $6 OFFSET $7",
+        )
+        .bind(status)
+        .bind(query.workflow_id)
+        .bind(query.subscription_id)
+        .bind(query.since)
+        .bind(query.until)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::webhook_subscription::{WebhookSubscription, ALL_EVENT_TYPES};
+
+pub struct WebhookSubscriptionRepository<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> WebhookSubscriptionRepository<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, subscription: &WebhookSubscription) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO orchepy_webhook_subscriptions (id, url, secret, event_types, workflow_id, active, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        )
+        .bind(subscription.id)
+        .bind(&subscription.url)
+        .bind(&subscription.secret)
+        .bind(&subscription.event_types)
+        .bind(subscription.workflow_id)
+        .bind(subscription.active)
+        .bind(subscription.created_at)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Result<Vec<WebhookSubscription>> {
+        let subscriptions = sqlx::query_as::<_, WebhookSubscription>(
+            "SELECT * FROM orchepy_webhook_subscriptions ORDER BY created_at"
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<WebhookSubscription>> {
+        let subscription = sqlx::query_as::<_, WebhookSubscription>(
+            "SELECT * FROM orchepy_webhook_subscriptions WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(subscription)
+    }
+
+    /// Active subscriptions to `event_type`, directly or through `*`, whose
+    /// workflow filter admits `workflow_id`.
+    pub async fn find_matching(&self, event_type: &str, workflow_id: Option<Uuid>) -> Result<Vec<WebhookSubscription>> {
+        let subscriptions = sqlx::query_as::<_, WebhookSubscription>(
+            "SELECT * FROM orchepy_webhook_subscriptions
+             WHERE active
+               AND event_types && ARRAY[$1, $2]
+               AND (workflow_id IS NULL OR workflow_id = $3)
+             ORDER BY created_at"
+        )
+        .bind(event_type)
+        .bind(ALL_EVENT_TYPES)
+        .bind(workflow_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(subscriptions)
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM orchepy_webhook_subscriptions WHERE id = $1")
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
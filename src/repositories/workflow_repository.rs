@@ -1,7 +1,9 @@
 use anyhow::Result;
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::models::webhook_auth::WebhookAuth;
+use crate::models::workflow::{WorkflowSummary, WorkflowVersion};
 use crate::models::Workflow;
 
 pub struct WorkflowRepository<'a> {
@@ -13,25 +15,40 @@ impl<'a> WorkflowRepository<'a> {
         Self { pool }
     }
 
+    /// Inserts the workflow and saves it as its first version.
     pub async fn create(&self, workflow: &Workflow) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
         sqlx::query(
-            "INSERT INTO orchepy_workflows (id, name, phases, initial_phase, webhook_url, description, automations, sla_config, active, created_at, updated_at)
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
+            "INSERT INTO orchepy_workflows (id, name, phases, initial_phase, webhook_url, webhook_template, webhook_headers, webhook_auth, description, automations, sla_config, execution_limits, data_conflicts, transitions, active, region, version, updated_by, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)"
         )
         .bind(workflow.id)
         .bind(&workflow.name)
         .bind(serde_json::to_value(&workflow.phases)?)
         .bind(&workflow.initial_phase)
         .bind(&workflow.webhook_url)
+        .bind(&workflow.webhook_template)
+        .bind(serde_json::to_value(&workflow.webhook_headers)?)
+        .bind(workflow.webhook_auth.as_ref().map(WebhookAuth::stored))
         .bind(&workflow.description)
         .bind(serde_json::to_value(&workflow.automations)?)
         .bind(serde_json::to_value(&workflow.sla_config)?)
+        .bind(serde_json::to_value(&workflow.execution_limits)?)
+        .bind(serde_json::to_value(&workflow.data_conflicts)?)
+        .bind(serde_json::to_value(&workflow.transitions)?)
         .bind(workflow.active)
+        .bind(&workflow.region)
+        .bind(workflow.version)
+        .bind(&workflow.updated_by)
         .bind(workflow.created_at)
         .bind(workflow.updated_at)
-        .execute(self.pool)
+        .execute(&mut *tx)
         .await?;
 
+        save_version(&mut tx, workflow.id).await?;
+        tx.commit().await?;
+
         Ok(())
     }
 
@@ -48,7 +65,7 @@ impl<'a> WorkflowRepository<'a> {
 
     pub async fn find_active_by_id(&self, id: Uuid) -> Result<Option<Workflow>> {
         let workflow = sqlx::query_as::<_, Workflow>(
-            "SELECT * FROM orchepy_workflows WHERE id = $1 AND active = true"
+            "SELECT * FROM orchepy_workflows WHERE id = $1 AND active = true AND archived_at IS NULL"
         )
         .bind(id)
         .fetch_optional(self.pool)
@@ -57,6 +74,17 @@ impl<'a> WorkflowRepository<'a> {
         Ok(workflow)
     }
 
+    pub async fn find_summaries(&self, ids: &[Uuid]) -> Result<Vec<WorkflowSummary>> {
+        let summaries = sqlx::query_as::<_, WorkflowSummary>(
+            "SELECT id, name, phases, initial_phase, archived_at FROM orchepy_workflows WHERE id = ANY($1)"
+        )
+        .bind(ids)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(summaries)
+    }
+
     pub async fn list_all(&self) -> Result<Vec<Workflow>> {
         let workflows = sqlx::query_as::<_, Workflow>(
             "SELECT * FROM orchepy_workflows ORDER BY created_at DESC"
@@ -69,7 +97,7 @@ impl<'a> WorkflowRepository<'a> {
 
     pub async fn list_active(&self) -> Result<Vec<Workflow>> {
         let workflows = sqlx::query_as::<_, Workflow>(
-            "SELECT * FROM orchepy_workflows WHERE active = true ORDER BY created_at DESC"
+            "SELECT * FROM orchepy_workflows WHERE active = true AND archived_at IS NULL ORDER BY created_at DESC"
         )
         .fetch_all(self.pool)
         .await?;
@@ -77,24 +105,62 @@ impl<'a> WorkflowRepository<'a> {
         Ok(workflows)
     }
 
-    pub async fn update(&self, workflow: &Workflow) -> Result<()> {
-        sqlx::query(
-            "UPDATE orchepy_workflows SET name = $1, phases = $2, initial_phase = $3, webhook_url = $4, description = $5, automations = $6, sla_config = $7, active = $8, updated_at = $9 WHERE id = $10"
+    /// Saves the definition as a new version and returns its number.
+    pub async fn update(&self, workflow: &Workflow) -> Result<i32> {
+        let mut tx = self.pool.begin().await?;
+
+        let version = sqlx::query_scalar::<_, i32>(
+            "UPDATE orchepy_workflows SET name = $1, phases = $2, initial_phase = $3, webhook_url = $4, webhook_template = $5, webhook_headers = $6, webhook_auth = $7, description = $8, automations = $9, sla_config = $10, execution_limits = $11, data_conflicts = $12, transitions = $13, active = $14, updated_by = $15, updated_at = $16, version = version + 1 WHERE id = $17
+             RETURNING version"
         )
         .bind(&workflow.name)
         .bind(serde_json::to_value(&workflow.phases)?)
         .bind(&workflow.initial_phase)
         .bind(&workflow.webhook_url)
+        .bind(&workflow.webhook_template)
+        .bind(serde_json::to_value(&workflow.webhook_headers)?)
+        .bind(workflow.webhook_auth.as_ref().map(WebhookAuth::stored))
         .bind(&workflow.description)
         .bind(serde_json::to_value(&workflow.automations)?)
         .bind(serde_json::to_value(&workflow.sla_config)?)
+        .bind(serde_json::to_value(&workflow.execution_limits)?)
+        .bind(serde_json::to_value(&workflow.data_conflicts)?)
+        .bind(serde_json::to_value(&workflow.transitions)?)
         .bind(workflow.active)
+        .bind(&workflow.updated_by)
         .bind(workflow.updated_at)
         .bind(workflow.id)
-        .execute(self.pool)
+        .fetch_one(&mut *tx)
         .await?;
 
-        Ok(())
+        save_version(&mut tx, workflow.id).await?;
+        tx.commit().await?;
+
+        Ok(version)
+    }
+
+    /// Newest first.
+    pub async fn list_versions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVersion>> {
+        let versions = sqlx::query_as::<_, WorkflowVersion>(
+            "SELECT * FROM orchepy_workflow_versions WHERE workflow_id = $1 ORDER BY version DESC"
+        )
+        .bind(workflow_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(versions)
+    }
+
+    pub async fn find_version(&self, workflow_id: Uuid, version: i32) -> Result<Option<WorkflowVersion>> {
+        let version = sqlx::query_as::<_, WorkflowVersion>(
+            "SELECT * FROM orchepy_workflow_versions WHERE workflow_id = $1 AND version = $2"
+        )
+        .bind(workflow_id)
+        .bind(version)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(version)
     }
 
     pub async fn delete(&self, id: Uuid) -> Result<bool> {
@@ -106,13 +172,110 @@ impl<'a> WorkflowRepository<'a> {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Deletes the workflow unless it has cases, its own or ones that
+    /// joined it. Returns false if it was not deleted.
+    pub async fn delete_if_unused(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM orchepy_workflows w WHERE w.id = $1
+             AND NOT EXISTS (SELECT 1 FROM orchepy_cases c WHERE c.workflow_id = w.id)
+             AND NOT EXISTS (SELECT 1 FROM orchepy_case_workflows m WHERE m.workflow_id = w.id)"
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Cases deleting the workflow would remove, soft-deleted ones included.
+    pub async fn count_cases(&self, id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT (SELECT COUNT(*) FROM orchepy_cases WHERE workflow_id = $1)
+                  + (SELECT COUNT(*) FROM orchepy_case_workflows WHERE workflow_id = $1)"
+        )
+        .bind(id)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Archives the workflow, keeping the original time and author if it
+    /// already was. Returns `None` if it does not exist.
+    pub async fn archive(&self, id: Uuid, archived_by: Option<&str>) -> Result<Option<Workflow>> {
+        let workflow = sqlx::query_as::<_, Workflow>(
+            "UPDATE orchepy_workflows
+             SET archived_by = CASE WHEN archived_at IS NULL THEN $2 ELSE archived_by END,
+                 archived_at = COALESCE(archived_at, NOW())
+             WHERE id = $1 RETURNING *"
+        )
+        .bind(id)
+        .bind(archived_by)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(workflow)
+    }
+
+    pub async fn unarchive(&self, id: Uuid) -> Result<Option<Workflow>> {
+        let workflow = sqlx::query_as::<_, Workflow>(
+            "UPDATE orchepy_workflows SET archived_at = NULL, archived_by = NULL WHERE id = $1 RETURNING *"
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(workflow)
+    }
+
+    /// Sets or, with `None`, removes the secret the workflow's webhooks are
+    /// signed with. Returns false if the workflow does not exist.
+    pub async fn set_webhook_secret(&self, id: Uuid, secret: Option<&str>) -> Result<bool> {
+        let result = sqlx::query("UPDATE orchepy_workflows SET webhook_secret = $1 WHERE id = $2")
+            .bind(secret)
+            .bind(id)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn webhook_secret(&self, id: Uuid) -> Result<Option<String>> {
+        let secret: Option<Option<String>> =
+            sqlx::query_scalar("SELECT webhook_secret FROM orchepy_workflows WHERE id = $1")
+                .bind(id)
+                .fetch_optional(self.pool)
+                .await?;
+
+        Ok(secret.flatten())
+    }
+
     pub async fn set_active(&self, id: Uuid, active: bool) -> Result<()> {
-        sqlx::query("UPDATE orchepy_workflows SET active = $1, updated_at = NOW() WHERE id = $2")
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE orchepy_workflows SET active = $1, updated_by = NULL, updated_at = NOW(), version = version + 1 WHERE id = $2")
             .bind(active)
             .bind(id)
-            .execute(self.pool)
+            .execute(&mut *tx)
             .await?;
 
+        save_version(&mut tx, id).await?;
+        tx.commit().await?;
+
         Ok(())
     }
 }
+
+/// Copies the workflow's current definition into its version history.
+async fn save_version(tx: &mut Transaction<'_, Postgres>, workflow_id: Uuid) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO orchepy_workflow_versions (workflow_id, version, name, phases, initial_phase, webhook_url, webhook_template, webhook_headers, webhook_auth, description, automations, sla_config, execution_limits, data_conflicts, transitions, active, created_by, created_at)
+         SELECT id, version, name, phases, initial_phase, webhook_url, webhook_template, webhook_headers, webhook_auth, description, automations, sla_config, execution_limits, data_conflicts, transitions, active, updated_by, updated_at
+         FROM orchepy_workflows WHERE id = $1"
+    )
+    .bind(workflow_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
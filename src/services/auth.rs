@@ -0,0 +1,382 @@
+//! JWT authentication for the REST API.
+//!
+//! Tokens are sent as `Authorization: Bearer <jwt>` and checked against a
+//! shared secret (HS256/384/512) or the keys published at a JWKS URL, plus
+//! the configured issuer and audience. The roles claim decides what the
+//! caller may do: `viewer` reads, `operator` also works cases and events,
+//! and `admin` also manages definitions and the admin endpoints.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+
+pub const DEFAULT_ROLES_CLAIM: &str = "roles";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Operator => "operator",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Ok(Self::Viewer),
+            "operator" => Ok(Self::Operator),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("unknown role '{}'", other)),
+        }
+    }
+}
+
+/// The caller of an authenticated request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// The token's `sub` claim.
+    pub subject: Option<String>,
+    /// The highest role the token grants.
+    pub role: Role,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    InvalidToken(String),
+    /// The token was signed with a key that is not (or no longer) published.
+    UnknownKey,
+    /// The token is valid but grants none of the roles.
+    NoRole,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidToken(reason) => write!(f, "invalid token: {}", reason),
+            Self::UnknownKey => f.write_str("token was signed with an unknown key"),
+            Self::NoRole => f.write_str("token grants no role"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+struct VerifyingKey {
+    key: DecodingKey,
+    algorithm: Algorithm,
+}
+
+/// Checks bearer tokens. Clones share the keys loaded from the JWKS URL.
+#[derive(Clone)]
+pub struct JwtAuth {
+    secret: Option<Arc<DecodingKey>>,
+    jwks_url: Option<String>,
+    /// JWKS keys by `kid`; keys without one are stored under "".
+    jwks: Arc<RwLock<HashMap<String, VerifyingKey>>>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    roles_claim: String,
+    /// Claim values that stand for a role, e.g. an IdP group name.
+    role_mapping: HashMap<String, Role>,
+}
+
+impl fmt::Debug for JwtAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtAuth")
+            .field("jwks_url", &self.jwks_url)
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("roles_claim", &self.roles_claim)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JwtAuth {
+    fn new(secret: Option<&[u8]>, jwks_url: Option<String>) -> Self {
+        Self {
+            secret: secret.map(|secret| Arc::new(DecodingKey::from_secret(secret))),
+            jwks_url,
+            jwks: Arc::default(),
+            issuer: None,
+            audience: None,
+            roles_claim: DEFAULT_ROLES_CLAIM.to_string(),
+            role_mapping: HashMap::new(),
+        }
+    }
+
+    /// Tokens signed with an HMAC `secret`.
+    pub fn with_secret(secret: impl AsRef<[u8]>) -> Self {
+        Self::new(Some(secret.as_ref()), None)
+    }
+
+    /// Tokens signed with the keys published at `url`, loaded with
+    /// [`refresh_jwks`](Self::refresh_jwks).
+    pub fn with_jwks_url(url: impl Into<String>) -> Self {
+        Self::new(None, Some(url.into()))
+    }
+
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
+
+    /// The claim holding the roles, as a dot-separated path into the
+    /// claims, e.g. `realm_access.roles`.
+    pub fn roles_claim(mut self, claim: impl Into<String>) -> Self {
+        self.roles_claim = claim.into();
+        self
+    }
+
+    pub fn map_role(mut self, value: impl Into<String>, role: Role) -> Self {
+        self.role_mapping.insert(value.into(), role);
+        self
+    }
+
+    /// Authentication configured through `AUTH_JWT_*`, or None when neither
+    /// `AUTH_JWT_SECRET` nor `AUTH_JWT_JWKS_URL` is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |name: &str| std::env::var(name).ok().map(|value| value.trim().to_string()).filter(|v| !v.is_empty());
+
+        let mut auth = match (var("AUTH_JWT_SECRET"), var("AUTH_JWT_JWKS_URL")) {
+            (None, None) => return Ok(None),
+            (Some(_), Some(_)) => return Err("set either AUTH_JWT_SECRET or AUTH_JWT_JWKS_URL, not both".to_string()),
+            (Some(secret), None) => Self::with_secret(secret),
+            (None, Some(url)) => Self::with_jwks_url(url),
+        };
+
+        if let Some(issuer) = var("AUTH_JWT_ISSUER") {
+            auth = auth.issuer(issuer);
+        }
+        if let Some(audience) = var("AUTH_JWT_AUDIENCE") {
+            auth = auth.audience(audience);
+        }
+        if let Some(claim) = var("AUTH_JWT_ROLES_CLAIM") {
+            auth = auth.roles_claim(claim);
+        }
+        for entry in var("AUTH_JWT_ROLE_MAPPING").unwrap_or_default().split(',').map(str::trim) {
+            if entry.is_empty() {
+                continue;
+            }
+            let (value, role) = entry
+                .rsplit_once('=')
+                .ok_or_else(|| format!("role mapping '{}' must be written as value=role", entry))?;
+            let role = role.parse().map_err(|err| format!("role mapping '{}': {}", entry, err))?;
+            auth = auth.map_role(value.trim(), role);
+        }
+
+        Ok(Some(auth))
+    }
+
+    pub fn jwks_url(&self) -> Option<&str> {
+        self.jwks_url.as_deref()
+    }
+
+    /// Replaces the JWKS keys with the usable ones in `jwks`. Returns how
+    /// many were loaded.
+    pub fn set_jwks(&self, jwks: &JwkSet) -> usize {
+        let keys: HashMap<String, VerifyingKey> = jwks
+            .keys
+            .iter()
+            .filter_map(|jwk| match verifying_key(jwk) {
+                Ok(key) => Some((jwk.common.key_id.clone().unwrap_or_default(), key)),
+                Err(err) => {
+                    warn!("Skipping JWKS key {:?}: {}", jwk.common.key_id, err);
+                    None
+                }
+            })
+            .collect();
+
+        let count = keys.len();
+        *self.jwks.write().expect("JWKS lock poisoned") = keys;
+        count
+    }
+
+    /// Fetches the keys from the JWKS URL. Returns how many were loaded.
+    pub async fn refresh_jwks(&self, client: &reqwest::Client) -> anyhow::Result<usize> {
+        let Some(url) = &self.jwks_url else {
+            return Ok(0);
+        };
+
+        let jwks: JwkSet = client.get(url).send().await?.error_for_status()?.json().await?;
+        Ok(self.set_jwks(&jwks))
+    }
+
+    pub fn authenticate(&self, token: &str) -> Result<Principal, AuthError> {
+        let header = decode_header(token).map_err(|err| AuthError::InvalidToken(err.to_string()))?;
+
+        let claims = match &self.secret {
+            Some(secret) => self.decode(token, secret, &[Algorithm::HS256, Algorithm::HS384, Algorithm::HS512])?,
+            None => {
+                let keys = self.jwks.read().expect("JWKS lock poisoned");
+                let key = keys.get(header.kid.as_deref().unwrap_or_default()).ok_or(AuthError::UnknownKey)?;
+                self.decode(token, &key.key, &[key.algorithm])?
+            }
+        };
+
+        let role = self.role(&claims).ok_or(AuthError::NoRole)?;
+        let subject = claims.get("sub").and_then(Value::as_str).map(str::to_string);
+        Ok(Principal { subject, role })
+    }
+
+    fn decode(&self, token: &str, key: &DecodingKey, algorithms: &[Algorithm]) -> Result<Value, AuthError> {
+        let mut validation = Validation::new(algorithms[0]);
+        validation.algorithms = algorithms.to_vec();
+        validation.set_required_spec_claims(&["exp"]);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        decode::<Value>(token, key, &validation)
+            .map(|data| data.claims)
+            .map_err(|err| AuthError::InvalidToken(err.to_string()))
+    }
+
+    /// The highest role among the values of the roles claim, which may be a
+    /// list or a space- or comma-separated string.
+    fn role(&self, claims: &Value) -> Option<Role> {
+        let claim = self.roles_claim.split('.').try_fold(claims, |value, key| value.get(key))?;
+
+        let values: Vec<&str> = match claim {
+            Value::String(values) => values.split([' ', ',']).collect(),
+            Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+            _ => return None,
+        };
+
+        values
+            .into_iter()
+            .filter_map(|value| self.role_mapping.get(value).copied().or_else(|| value.parse().ok()))
+            .max()
+    }
+}
+
+/// A JWKS key with the one algorithm tokens signed with it may use: the
+/// key's `alg`, or the usual one for its type.
+fn verifying_key(jwk: &Jwk) -> Result<VerifyingKey, String> {
+    let algorithm = match jwk.common.key_algorithm {
+        Some(algorithm) => Algorithm::from_str(&algorithm.to_string()).map_err(|err| err.to_string())?,
+        None => match &jwk.algorithm {
+            AlgorithmParameters::RSA(_) => Algorithm::RS256,
+            AlgorithmParameters::EllipticCurve(params) if params.curve == EllipticCurve::P384 => Algorithm::ES384,
+            AlgorithmParameters::EllipticCurve(_) => Algorithm::ES256,
+            AlgorithmParameters::OctetKeyPair(_) => Algorithm::EdDSA,
+            AlgorithmParameters::OctetKey(_) => Algorithm::HS256,
+        },
+    };
+
+    let key = DecodingKey::from_jwk(jwk).map_err(|err| err.to_string())?;
+    Ok(VerifyingKey { key, algorithm })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    fn token(secret: &str, kid: Option<&str>, claims: Value) -> String {
+        let header = Header { kid: kid.map(str::to_string), ..Header::default() };
+        encode(&header, &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    fn exp() -> i64 {
+        chrono::Utc::now().timestamp() + 600
+    }
+
+    #[test]
+    fn test_roles_from_claims() {
+        let auth = JwtAuth::with_secret("s3cret").issuer("https://idp.example.com").map_role("support", Role::Operator);
+
+        let claims = |roles: Value| json!({"sub": "ana", "iss": "https://idp.example.com", "exp": exp(), "roles": roles});
+        let role = |roles| auth.authenticate(&token("s3cret", None, claims(roles))).map(|p| p.role);
+
+        assert_eq!(role(json!(["viewer"])), Ok(Role::Viewer));
+        assert_eq!(role(json!(["viewer", "admin"])), Ok(Role::Admin));
+        assert_eq!(role(json!("viewer support")), Ok(Role::Operator));
+        assert_eq!(role(json!(["billing"])), Err(AuthError::NoRole));
+
+        let principal = auth.authenticate(&token("s3cret", None, claims(json!(["viewer"])))).unwrap();
+        assert_eq!(principal.subject.as_deref(), Some("ana"));
+
+        let nested = JwtAuth::with_secret("s3cret").roles_claim("realm_access.roles");
+        let claims = json!({"exp": exp(), "realm_access": {"roles": ["operator"]}});
+        assert_eq!(nested.authenticate(&token("s3cret", None, claims)).unwrap().role, Role::Operator);
+    }
+
+    #[test]
+    fn test_rejects_bad_tokens() {
+        let auth = JwtAuth::with_secret("s3cret").issuer("https://idp.example.com").audience("orchepy");
+        let claims = |iss: &str, aud: &str, exp: i64| json!({"iss": iss, "aud": aud, "exp": exp, "roles": ["admin"]});
+
+        let valid = token("s3cret", None, claims("https://idp.example.com", "orchepy", exp()));
+        assert!(auth.authenticate(&valid).is_ok());
+
+        for bad in [
+            token("other", None, claims("https://idp.example.com", "orchepy", exp())),
+            token("s3cret", None, claims("https://evil.example.com", "orchepy", exp())),
+            token("s3cret", None, claims("https://idp.example.com", "other", exp())),
+            token("s3cret", None, claims("https://idp.example.com", "orchepy", exp() - 7200)),
+            token("s3cret", None, json!({"iss": "https://idp.example.com", "aud": "orchepy", "roles": ["admin"]})),
+            "not-a-jwt".to_string(),
+        ] {
+            assert!(matches!(auth.authenticate(&bad), Err(AuthError::InvalidToken(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_jwks_keys_are_picked_by_kid() {
+        let auth = JwtAuth::with_jwks_url("https://idp.example.com/jwks.json");
+        let jwks: JwkSet = serde_json::from_value(json!({"keys": [
+            {"kty": "oct", "kid": "2026-01", "k": "czNjcmV0"},
+            {"kty": "oct", "kid": "2026-02", "alg": "HS512", "k": "bmV3ZXI"}
+        ]}))
+        .unwrap();
+        assert_eq!(auth.set_jwks(&jwks), 2);
+
+        let claims = json!({"exp": exp(), "roles": ["viewer"]});
+        assert!(auth.authenticate(&token("s3cret", Some("2026-01"), claims.clone())).is_ok());
+        assert_eq!(
+            auth.authenticate(&token("s3cret", Some("2026-03"), claims.clone())),
+            Err(AuthError::UnknownKey)
+        );
+        // 2026-02 only verifies HS512 tokens.
+        assert!(matches!(
+            auth.authenticate(&token("newer", Some("2026-02"), claims)),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[test]
+    fn test_role_names() {
+        assert_eq!("Operator".parse::<Role>(), Ok(Role::Operator));
+        assert!("owner".parse::<Role>().is_err());
+        assert!(Role::Admin > Role::Operator && Role::Operator > Role::Viewer);
+    }
+}
@@ -0,0 +1,77 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::models::Case;
+
+/// Changes a subscriber may fall behind by before it misses some.
+const DEFAULT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CaseChangeKind {
+    #[serde(rename = "case.created")]
+    Created,
+    #[serde(rename = "case.moved")]
+    Moved,
+    #[serde(rename = "case.updated")]
+    Updated,
+}
+
+impl CaseChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "case.created",
+            Self::Moved => "case.moved",
+            Self::Updated => "case.updated",
+        }
+    }
+}
+
+/// A case as a handler left it. Moves in a joined workflow carry the case
+/// scoped to that workflow.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseChange {
+    #[serde(rename = "type")]
+    pub kind: CaseChangeKind,
+    pub region: String,
+    pub case: Case,
+}
+
+/// Fans case changes out to the open `GET /cases/stream` connections of
+/// this instance. Publishing without subscribers is free.
+#[derive(Clone)]
+pub struct CaseStream {
+    sender: broadcast::Sender<CaseChange>,
+}
+
+impl CaseStream {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, kind: CaseChangeKind, region: &str, case: &Case) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        // Only fails when the last subscriber left in the meantime.
+        let _ = self.sender.send(CaseChange {
+            kind,
+            region: region.to_string(),
+            case: case.clone(),
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<CaseChange> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for CaseStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
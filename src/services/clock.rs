@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The source of the current time for time-based features: case
+/// timestamps, SLA deadlines, delays and the resume schedulers.
+#[async_trait]
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Waits for `duration` to pass on this clock.
+    async fn sleep(&self, duration: Duration);
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall-clock time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to. Sleeping advances it at once
+/// instead of waiting, so delays and deadlines can be tested in no time.
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    now: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl VirtualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().expect("virtual clock lock poisoned") = now;
+    }
+
+    pub fn advance(&self, by: chrono::Duration) {
+        *self.now.lock().expect("virtual clock lock poisoned") += by;
+    }
+}
+
+#[async_trait]
+impl Clock for VirtualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().expect("virtual clock lock poisoned")
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        if let Ok(duration) = chrono::Duration::from_std(duration) {
+            self.advance(duration);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_virtual_clock_moves_only_when_told() {
+        let start = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let clock = VirtualClock::new(start);
+        let shared: SharedClock = Arc::new(clock.clone());
+        assert_eq!(shared.now(), start);
+
+        shared.sleep(Duration::from_secs(90)).await;
+        clock.advance(chrono::Duration::minutes(1));
+        assert_eq!(shared.now(), start + chrono::Duration::seconds(150));
+
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+}
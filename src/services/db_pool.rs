@@ -0,0 +1,136 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Connection pool settings, applied to every region's database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    /// Connections kept open even when idle.
+    pub min_connections: u32,
+    /// How long a query waits for a free connection before failing.
+    pub acquire_timeout: Duration,
+    /// How long an idle connection above `min_connections` is kept; `None`
+    /// keeps it until the pool closes.
+    pub idle_timeout: Option<Duration>,
+    /// `statement_timeout` of every connection; `None` leaves the server's.
+    pub statement_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+            statement_timeout: None,
+        }
+    }
+}
+
+impl PoolConfig {
+    pub fn from_env() -> Self {
+        fn var<T: FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|s| s.parse().ok())
+        }
+
+        let defaults = Self::default();
+        let max_connections = var("DB_MAX_CONNECTIONS").filter(|max| *max > 0).unwrap_or(defaults.max_connections);
+        Self {
+            max_connections,
+            min_connections: var("DB_MIN_CONNECTIONS").unwrap_or(defaults.min_connections).min(max_connections),
+            acquire_timeout: var("DB_ACQUIRE_TIMEOUT_SECS")
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.acquire_timeout),
+            idle_timeout: match var::<u64>("DB_IDLE_TIMEOUT_SECS") {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => defaults.idle_timeout,
+            },
+            statement_timeout: var::<u64>("DB_STATEMENT_TIMEOUT_MS")
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+        }
+    }
+
+    pub async fn connect(&self, url: &str) -> Result<PgPool> {
+        self.connect_with(PgConnectOptions::from_str(url)?).await
+    }
+
+    pub async fn connect_with(&self, mut options: PgConnectOptions) -> Result<PgPool> {
+        if let Some(timeout) = self.statement_timeout {
+            options = options.options([("statement_timeout", format!("{}ms", timeout.as_millis()))]);
+        }
+
+        let pool = PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .idle_timeout(self.idle_timeout)
+            .connect_with(options)
+            .await?;
+
+        Ok(pool)
+    }
+}
+
+/// How much of a pool is in use right now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PoolStats {
+    pub max_connections: u32,
+    /// Open connections, idle or not.
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+}
+
+impl PoolStats {
+    pub fn of(pool: &PgPool) -> Self {
+        let size = pool.size();
+        let idle = pool.num_idle() as u32;
+        Self {
+            max_connections: pool.options().get_max_connections(),
+            size,
+            idle,
+            in_use: size.saturating_sub(idle),
+        }
+    }
+}
+
+/// A database's primary pool and, when one is configured, a read replica's.
+/// Writes and reads that must see them go to the primary; lists, lookups
+/// and analytics can go to [`DbPools::read`].
+#[derive(Clone)]
+pub struct DbPools {
+    primary: PgPool,
+    replica: Option<PgPool>,
+}
+
+impl DbPools {
+    pub fn new(primary: PgPool) -> Self {
+        Self { primary, replica: None }
+    }
+
+    pub fn with_replica(mut self, replica: PgPool) -> Self {
+        self.replica = Some(replica);
+        self
+    }
+
+    pub fn primary(&self) -> &PgPool {
+        &self.primary
+    }
+
+    pub fn replica(&self) -> Option<&PgPool> {
+        self.replica.as_ref()
+    }
+
+    /// The replica, or the primary without one.
+    pub fn read(&self) -> &PgPool {
+        self.replica.as_ref().unwrap_or(&self.primary)
+    }
+}
@@ -0,0 +1,393 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc, Weekday};
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::repositories::automation_run_repository::WorkflowFailureCount;
+use crate::repositories::execution_repository::FlowFailureCount;
+use crate::repositories::{AutomationRunRepository, ExecutionRepository};
+use crate::services::notification::{Notification, NotificationRegistry, SlackChannel};
+
+const TOP_FAILING_LIMIT: i64 = 5;
+const RECENT_FAILURES_LIMIT: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+impl DigestPeriod {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "daily" => Some(Self::Daily),
+            "weekly" => Some(Self::Weekly),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Daily => Duration::days(1),
+            Self::Weekly => Duration::weeks(1),
+        }
+    }
+
+    /// Next delivery time strictly after `now`: every day at `hour` UTC for
+    /// daily digests, every Monday at `hour` UTC for weekly ones.
+    pub fn next_run_after(&self, now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+        let today = Utc
+            .with_ymd_and_hms(now.year(), now.month(), now.day(), hour.min(23), 0, 0)
+            .single()
+            .unwrap_or(now);
+
+        let mut candidate = if today > now { today } else { today + Duration::days(1) };
+
+        if *self == Self::Weekly {
+            while candidate.weekday() != Weekday::Mon {
+                candidate += Duration::days(1);
+            }
+        }
+
+        candidate
+    }
+}
+
+#[derive(Clone)]
+pub struct DigestConfig {
+    pub enabled: bool,
+    pub period: DigestPeriod,
+    pub hour_utc: u32,
+    pub slack_webhook_url: Option<String>,
+    pub channels: Option<Vec<String>>,
+    pub dashboard_url: String,
+}
+
+impl DigestConfig {
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("DIGEST_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+
+        let period = std::env::var("DIGEST_PERIOD")
+            .ok()
+            .and_then(|p| DigestPeriod::parse(&p))
+            .unwrap_or(DigestPeriod::Daily);
+
+        let hour_utc = std::env::var("DIGEST_HOUR_UTC")
+            .ok()
+            .and_then(|h| h.parse().ok())
+            .unwrap_or(8);
+
+        let slack_webhook_url = std::env::var("DIGEST_SLACK_WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty());
+
+        let channels = std::env::var("DIGEST_CHANNELS")
+            .ok()
+            .map(|list| {
+                list.split(',')
+                    .map(|c| c.trim().to_lowercase())
+                    .filter(|c| !c.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|list| !list.is_empty());
+
+        let dashboard_url = std::env::var("DASHBOARD_URL")
+            .unwrap_or_else(|_| "http://localhost:3296".to_string())
+            .trim_end_matches('/')
+            .to_string();
+
+        debug!("Digest enabled: {} ({:?} at {}:00 UTC)", enabled, period, hour_utc);
+
+        Self {
+            enabled,
+            period,
+            hour_utc,
+            slack_webhook_url,
+            channels,
+            dashboard_url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedExecutionSummary {
+    pub execution_id: Uuid,
+    pub flow_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub link: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedAutomationRunSummary {
+    pub run_id: Uuid,
+    pub case_id: Uuid,
+    pub workflow_id: Uuid,
+    pub phase: String,
+    pub started_at: DateTime<Utc>,
+    pub error: Option<String>,
+    pub link: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperatorDigest {
+    pub period: DigestPeriod,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub total_executions: i64,
+    pub failed_executions: i64,
+    pub top_failing_flows: Vec<FlowFailureCount>,
+    pub recent_failures: Vec<FailedExecutionSummary>,
+    pub failed_executions_link: String,
+    /// Automation runs that failed or hit a limit.
+    pub failed_automation_runs: i64,
+    pub top_failing_workflows: Vec<WorkflowFailureCount>,
+    pub recent_automation_failures: Vec<FailedAutomationRunSummary>,
+}
+
+impl OperatorDigest {
+    pub fn render_text(&self) -> String {
+        let mut text = format!(
+            "*Orchepy {} digest* ({} – {})\n{} of {} executions failed\n",
+            self.period.label(),
+            self.since.format("%Y-%m-%d %H:%M UTC"),
+            self.until.format("%Y-%m-%d %H:%M UTC"),
+            self.failed_executions,
+            self.total_executions,
+        );
+
+        if !self.top_failing_flows.is_empty() {
+            text.push_str("\n*Top failing flows*\n");
+            for flow in &self.top_failing_flows {
+                text.push_str(&format!("• {} — {} failure(s)\n", flow.flow_name, flow.failures));
+            }
+        }
+
+        if !self.recent_failures.is_empty() {
+            text.push_str("\n*Recent failures*\n");
+            for failure in &self.recent_failures {
+                text.push_str(&format!(
+                    "• <{}|{}> {}\n",
+                    failure.link,
+                    failure.execution_id,
+                    failure.error.as_deref().unwrap_or("unknown error")
+                ));
+            }
+        }
+
+        text.push_str(&format!("\n{} automation run(s) failed\n", self.failed_automation_runs));
+
+        if !self.top_failing_workflows.is_empty() {
+            text.push_str("\n*Top failing workflow automations*\n");
+            for workflow in &self.top_failing_workflows {
+                text.push_str(&format!("• {} — {} failure(s)\n", workflow.workflow_name, workflow.failures));
+            }
+        }
+
+        if !self.recent_automation_failures.is_empty() {
+            text.push_str("\n*Recent automation failures*\n");
+            for failure in &self.recent_automation_failures {
+                text.push_str(&format!(
+                    "• <{}|{}> {}: {}\n",
+                    failure.link,
+                    failure.run_id,
+                    failure.phase,
+                    failure.error.as_deref().unwrap_or("unknown error")
+                ));
+            }
+        }
+
+        text.push_str(&format!("\nAll failed executions: {}", self.failed_executions_link));
+        text
+    }
+}
+
+#[derive(Clone)]
+pub struct DigestService {
+    pool: PgPool,
+    notifications: NotificationRegistry,
+    config: DigestConfig,
+}
+
+impl DigestService {
+    /// `DIGEST_SLACK_WEBHOOK_URL` is still honoured as a Slack channel for
+    /// the digest when no deployment-wide Slack channel is configured.
+    pub fn new(pool: PgPool, config: DigestConfig, mut notifications: NotificationRegistry) -> Self {
+        if let Some(url) = &config.slack_webhook_url {
+            if !notifications.has("slack") {
+                notifications.register(SlackChannel::new(url.clone()));
+            }
+        }
+
+        Self {
+            pool,
+            notifications,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &DigestConfig {
+        &self.config
+    }
+
+    pub async fn build(&self, until: DateTime<Utc>) -> Result<OperatorDigest> {
+        let since = until - self.config.period.duration();
+        let repo = ExecutionRepository::new(&self.pool);
+
+        let total_executions = repo.count_since(since).await?;
+        let failed_executions = repo.count_failed_since(since).await?;
+        let top_failing_flows = repo.top_failing_flows(since, TOP_FAILING_LIMIT).await?;
+        let recent_failures = repo
+            .list_failed_since(since, RECENT_FAILURES_LIMIT)
+            .await?
+            .into_iter()
+            .map(|execution| FailedExecutionSummary {
+                link: format!("{}/executions/{}", self.config.dashboard_url, execution.id),
+                execution_id: execution.id,
+                flow_id: execution.flow_id,
+                started_at: execution.started_at,
+                error: execution.error,
+            })
+            .collect();
+
+        let runs = AutomationRunRepository::new(&self.pool);
+        let failed_automation_runs = runs.count_failed_since(since).await?;
+        let top_failing_workflows = runs.top_failing_workflows(since, TOP_FAILING_LIMIT).await?;
+        let recent_automation_failures = runs
+            .list_failed_since(since, RECENT_FAILURES_LIMIT)
+            .await?
+            .into_iter()
+            .map(|run| FailedAutomationRunSummary {
+                link: format!("{}/cases/{}/automation-runs/{}", self.config.dashboard_url, run.case_id, run.id),
+                run_id: run.id,
+                case_id: run.case_id,
+                workflow_id: run.workflow_id,
+                phase: run.phase,
+                started_at: run.started_at,
+                error: run.error,
+            })
+            .collect();
+
+        Ok(OperatorDigest {
+            period: self.config.period,
+            since,
+            until,
+            total_executions,
+            failed_executions,
+            top_failing_flows,
+            recent_failures,
+            failed_executions_link: format!("{}/executions?status=failed", self.config.dashboard_url),
+            failed_automation_runs,
+            top_failing_workflows,
+            recent_automation_failures,
+        })
+    }
+
+    pub async fn deliver(&self, digest: &OperatorDigest) -> Result<()> {
+        let notification = Notification::new(
+            "digest",
+            format!("Orchepy {} digest", digest.period.label()),
+            digest.render_text(),
+        )
+        .with_link(digest.failed_executions_link.clone());
+
+        match &self.config.channels {
+            Some(channels) => self.notifications.notify_channels(channels, &notification).await?,
+            None => self.notifications.notify(&notification).await?,
+        }
+
+        info!(
+            "Delivered {:?} digest: {} failed execution(s), {} failed automation run(s)",
+            digest.period, digest.failed_executions, digest.failed_automation_runs
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_run_daily() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 5, 9, 30, 0).unwrap();
+        let next = DigestPeriod::Daily.next_run_after(now, 8);
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 3, 6, 8, 0, 0).unwrap());
+
+        let early = Utc.with_ymd_and_hms(2025, 3, 5, 7, 0, 0).unwrap();
+        let next = DigestPeriod::Daily.next_run_after(early, 8);
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 3, 5, 8, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_next_run_weekly() {
+        // 2025-03-05 is a Wednesday
+        let now = Utc.with_ymd_and_hms(2025, 3, 5, 9, 30, 0).unwrap();
+        let next = DigestPeriod::Weekly.next_run_after(now, 8);
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 3, 10, 8, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_render_text() {
+        let flow_id = Uuid::new_v4();
+        let execution_id = Uuid::new_v4();
+        let (workflow_id, case_id, run_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let digest = OperatorDigest {
+            period: DigestPeriod::Daily,
+            since: Utc.with_ymd_and_hms(2025, 3, 4, 8, 0, 0).unwrap(),
+            until: Utc.with_ymd_and_hms(2025, 3, 5, 8, 0, 0).unwrap(),
+            total_executions: 10,
+            failed_executions: 2,
+            top_failing_flows: vec![FlowFailureCount {
+                flow_id,
+                flow_name: "Sync CRM".to_string(),
+                failures: 2,
+            }],
+            recent_failures: vec![FailedExecutionSummary {
+                execution_id,
+                flow_id,
+                started_at: Utc::now(),
+                error: Some("HTTP 502".to_string()),
+                link: format!("http://localhost:3296/executions/{}", execution_id),
+            }],
+            failed_executions_link: "http://localhost:3296/executions?status=failed".to_string(),
+            failed_automation_runs: 3,
+            top_failing_workflows: vec![WorkflowFailureCount {
+                workflow_id,
+                workflow_name: "Deals".to_string(),
+                failures: 3,
+            }],
+            recent_automation_failures: vec![FailedAutomationRunSummary {
+                run_id,
+                case_id,
+                workflow_id,
+                phase: "Review".to_string(),
+                started_at: Utc::now(),
+                error: Some("Webhook returned status 503".to_string()),
+                link: format!("http://localhost:3296/cases/{}/automation-runs/{}", case_id, run_id),
+            }],
+        };
+
+        let text = digest.render_text();
+        assert!(text.contains("2 of 10 executions failed"));
+        assert!(text.contains("Sync CRM — 2 failure(s)"));
+        assert!(text.contains(&format!("/executions/{}", execution_id)));
+        assert!(text.contains("3 automation run(s) failed"));
+        assert!(text.contains("Deals — 3 failure(s)"));
+        assert!(text.contains(&format!("/cases/{}/automation-runs/{}|{}> Review: Webhook returned status 503", case_id, run_id, run_id)));
+    }
+}
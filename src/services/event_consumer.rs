@@ -0,0 +1,296 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::models::event::CreateEvent;
+
+const DEFAULT_GROUP: &str = "orchepy";
+
+/// A message read from the broker: the Kafka topic, NATS subject or
+/// RabbitMQ routing key it came with, and its body.
+#[derive(Debug, Clone)]
+pub struct BrokerDelivery {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// A subscription to a NATS subject, Kafka topic or RabbitMQ queue.
+#[async_trait]
+pub trait MessageSource: Send {
+    /// The next message, or `None` once the subscription has ended.
+    async fn next(&mut self) -> Result<Option<BrokerDelivery>>;
+
+    /// Acknowledges the message `next` returned last, so the broker doesn't
+    /// deliver it again. A no-op for NATS, which doesn't redeliver.
+    async fn ack(&mut self) -> Result<()>;
+}
+
+/// Where `EVENT_CONSUMER` reads events from.
+#[derive(Debug, Clone)]
+pub struct ConsumerConfig {
+    /// `nats`, `kafka` or `rabbitmq`.
+    pub broker: String,
+    pub url: String,
+    /// NATS subject, comma-separated Kafka topics or RabbitMQ queue.
+    pub topic: String,
+    /// NATS queue group or Kafka consumer group shared by the instances,
+    /// so each message is ingested once.
+    pub group: String,
+    /// Region the events are stored in; the default region when `None`.
+    pub region: Option<String>,
+}
+
+impl ConsumerConfig {
+    /// From `EVENT_CONSUMER`, `EVENT_CONSUMER_URL`, `EVENT_CONSUMER_TOPIC`,
+    /// `EVENT_CONSUMER_GROUP` (default `orchepy`) and `EVENT_CONSUMER_REGION`.
+    /// `None` when `EVENT_CONSUMER` is unset.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(broker) = env_value("EVENT_CONSUMER").map(|broker| broker.to_ascii_lowercase()) else {
+            return Ok(None);
+        };
+        let built = match broker.as_str() {
+            "nats" => cfg!(feature = "nats"),
+            "kafka" => cfg!(feature = "kafka"),
+            "rabbitmq" => cfg!(feature = "rabbitmq"),
+            other => anyhow::bail!("Unknown EVENT_CONSUMER '{}', expected nats, kafka or rabbitmq", other),
+        };
+        if !built {
+            anyhow::bail!("EVENT_CONSUMER={} needs Orchepy built with the `{}` feature", broker, broker);
+        }
+
+        let url = env_value("EVENT_CONSUMER_URL")
+            .ok_or_else(|| anyhow::anyhow!("EVENT_CONSUMER_URL must be set when EVENT_CONSUMER is"))?;
+        let topic = env_value("EVENT_CONSUMER_TOPIC")
+            .ok_or_else(|| anyhow::anyhow!("EVENT_CONSUMER_TOPIC must be set when EVENT_CONSUMER is"))?;
+
+        Ok(Some(Self {
+            broker,
+            url,
+            topic,
+            group: env_value("EVENT_CONSUMER_GROUP").unwrap_or_else(|| DEFAULT_GROUP.to_string()),
+            region: env_value("EVENT_CONSUMER_REGION").map(|region| region.to_ascii_lowercase()),
+        }))
+    }
+
+    /// Subscribes to the configured topic.
+    pub async fn connect(&self) -> Result<Box<dyn MessageSource>> {
+        match self.broker.as_str() {
+            #[cfg(feature = "nats")]
+            "nats" => Ok(Box::new(NatsSource::connect(self).await?)),
+            #[cfg(feature = "kafka")]
+            "kafka" => Ok(Box::new(KafkaSource::connect(self)?)),
+            #[cfg(feature = "rabbitmq")]
+            "rabbitmq" => Ok(Box::new(RabbitMqSource::connect(self).await?)),
+            other => anyhow::bail!("EVENT_CONSUMER={} isn't available in this build", other),
+        }
+    }
+}
+
+/// The event a message stands for. A JSON object with an `event_type` is
+/// taken as a `POST /events` body; any other JSON becomes the `data` of an
+/// event named after the message's topic. Either way `metadata.broker`
+/// records where the message came from.
+pub fn to_event(broker: &str, delivery: &BrokerDelivery) -> Result<CreateEvent, String> {
+    let body: Value =
+        serde_json::from_slice(&delivery.payload).map_err(|err| format!("message is not valid JSON: {}", err))?;
+
+    let mut event = match body.get("event_type") {
+        Some(Value::String(_)) => {
+            serde_json::from_value::<CreateEvent>(body).map_err(|err| format!("invalid event: {}", err))?
+        }
+        Some(_) => return Err("event_type must be a string".to_string()),
+        None => CreateEvent {
+            event_type: delivery.topic.clone(),
+            data: body,
+            metadata: None,
+        },
+    };
+
+    if event.event_type.trim().is_empty() {
+        return Err("event_type must not be empty".to_string());
+    }
+
+    let source = json!({"type": broker, "topic": delivery.topic});
+    match &mut event.metadata {
+        Some(Value::Object(metadata)) => {
+            metadata.insert("broker".to_string(), source);
+        }
+        Some(_) => {}
+        None => event.metadata = Some(json!({"broker": source})),
+    }
+    Ok(event)
+}
+
+#[cfg(feature = "nats")]
+struct NatsSource {
+    subscriber: async_nats::Subscriber,
+}
+
+#[cfg(feature = "nats")]
+impl NatsSource {
+    async fn connect(config: &ConsumerConfig) -> Result<Self> {
+        let client = async_nats::connect(&config.url).await?;
+        let subscriber = client.queue_subscribe(config.topic.clone(), config.group.clone()).await?;
+        Ok(Self { subscriber })
+    }
+}
+
+#[cfg(feature = "nats")]
+#[async_trait]
+impl MessageSource for NatsSource {
+    async fn next(&mut self) -> Result<Option<BrokerDelivery>> {
+        use futures::StreamExt;
+
+        Ok(self.subscriber.next().await.map(|message| BrokerDelivery {
+            topic: message.subject.to_string(),
+            payload: message.payload.to_vec(),
+        }))
+    }
+
+    async fn ack(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Commits a message's offset only once it has been acknowledged.
+#[cfg(feature = "kafka")]
+struct KafkaSource {
+    consumer: rdkafka::consumer::StreamConsumer,
+    pending: Option<(String, i32, i64)>,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaSource {
+    fn connect(config: &ConsumerConfig) -> Result<Self> {
+        use rdkafka::consumer::Consumer;
+
+        let consumer: rdkafka::consumer::StreamConsumer = rdkafka::ClientConfig::new()
+            .set("bootstrap.servers", &config.url)
+            .set("group.id", &config.group)
+            .set("enable.auto.commit", "true")
+            .set("enable.auto.offset.store", "false")
+            .create()?;
+        let topics: Vec<&str> = config.topic.split(',').map(str::trim).filter(|t| !t.is_empty()).collect();
+        consumer.subscribe(&topics)?;
+        Ok(Self { consumer, pending: None })
+    }
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait]
+impl MessageSource for KafkaSource {
+    async fn next(&mut self) -> Result<Option<BrokerDelivery>> {
+        use rdkafka::Message;
+
+        let message = self.consumer.recv().await?;
+        self.pending = Some((message.topic().to_string(), message.partition(), message.offset()));
+        Ok(Some(BrokerDelivery {
+            topic: message.topic().to_string(),
+            payload: message.payload().unwrap_or_default().to_vec(),
+        }))
+    }
+
+    async fn ack(&mut self) -> Result<()> {
+        use rdkafka::consumer::Consumer;
+
+        if let Some((topic, partition, offset)) = self.pending.take() {
+            self.consumer.store_offset(&topic, partition, offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads one message at a time from an existing queue.
+#[cfg(feature = "rabbitmq")]
+struct RabbitMqSource {
+    _connection: lapin::Connection,
+    consumer: lapin::Consumer,
+    queue: String,
+    pending: Option<lapin::acker::Acker>,
+}
+
+#[cfg(feature = "rabbitmq")]
+impl RabbitMqSource {
+    async fn connect(config: &ConsumerConfig) -> Result<Self> {
+        use lapin::options::{BasicConsumeOptions, BasicQosOptions};
+        use lapin::types::FieldTable;
+
+        let connection = lapin::Connection::connect(&config.url, lapin::ConnectionProperties::default()).await?;
+        let channel = connection.create_channel().await?;
+        channel.basic_qos(1, BasicQosOptions::default()).await?;
+        let consumer = channel
+            .basic_consume(&config.topic, &config.group, BasicConsumeOptions::default(), FieldTable::default())
+            .await?;
+        Ok(Self {
+            _connection: connection,
+            consumer,
+            queue: config.topic.clone(),
+            pending: None,
+        })
+    }
+}
+
+#[cfg(feature = "rabbitmq")]
+#[async_trait]
+impl MessageSource for RabbitMqSource {
+    async fn next(&mut self) -> Result<Option<BrokerDelivery>> {
+        use futures::StreamExt;
+
+        let Some(delivery) = self.consumer.next().await.transpose()? else {
+            return Ok(None);
+        };
+        let topic = match delivery.routing_key.as_str() {
+            "" => self.queue.clone(),
+            routing_key => routing_key.to_string(),
+        };
+        self.pending = Some(delivery.acker);
+        Ok(Some(BrokerDelivery { topic, payload: delivery.data }))
+    }
+
+    async fn ack(&mut self) -> Result<()> {
+        if let Some(acker) = self.pending.take() {
+            acker.ack(lapin::options::BasicAckOptions::default()).await?;
+        }
+        Ok(())
+    }
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery(topic: &str, payload: Value) -> BrokerDelivery {
+        BrokerDelivery { topic: topic.to_string(), payload: payload.to_string().into_bytes() }
+    }
+
+    #[test]
+    fn test_message_to_event() {
+        let event = to_event(
+            "kafka",
+            &delivery("orders", json!({"event_type": "order.created", "data": {"id": 42}, "metadata": {"tenant": "acme"}})),
+        )
+        .unwrap();
+        assert_eq!(event.event_type, "order.created");
+        assert_eq!(event.data, json!({"id": 42}));
+        assert_eq!(event.metadata, Some(json!({"tenant": "acme", "broker": {"type": "kafka", "topic": "orders"}})));
+
+        let event = to_event("nats", &delivery("payments.settled", json!({"amount": 10}))).unwrap();
+        assert_eq!(event.event_type, "payments.settled");
+        assert_eq!(event.data, json!({"amount": 10}));
+        assert_eq!(event.metadata, Some(json!({"broker": {"type": "nats", "topic": "payments.settled"}})));
+    }
+
+    #[test]
+    fn test_invalid_messages_are_rejected() {
+        let not_json = BrokerDelivery { topic: "orders".to_string(), payload: b"<order/>".to_vec() };
+
+        assert!(to_event("kafka", &not_json).is_err());
+        assert!(to_event("kafka", &delivery("orders", json!({"event_type": 7, "data": {}}))).is_err());
+        assert!(to_event("kafka", &delivery("orders", json!({"event_type": "order.created"}))).is_err());
+        assert!(to_event("kafka", &delivery("orders", json!({"event_type": " ", "data": {}}))).is_err());
+    }
+}
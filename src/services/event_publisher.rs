@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::models::execution::{Execution, ExecutionStatus};
+use crate::models::Event;
+use crate::services::case_stream::CaseChange;
+
+/// Messages waiting for the broker before new ones are dropped.
+const QUEUE_CAPACITY: usize = 4096;
+
+const DEFAULT_TOPIC: &str = "orchepy.events";
+
+const DEFAULT_EVENTS: &[&str] = &["case.created", "case.moved", "execution.completed", "execution.failed"];
+
+/// A NATS server or Kafka cluster messages are published to.
+#[async_trait]
+pub trait EventBroker: Send + Sync {
+    /// Publishes `payload` on `topic`, a Kafka topic or NATS subject. Kafka
+    /// partitions by `key`, so messages about one case stay in order.
+    async fn publish(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()>;
+}
+
+/// What is published: an internal event with where and when it happened.
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokerMessage {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub region: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+struct Outgoing {
+    topic: String,
+    key: String,
+    message: BrokerMessage,
+}
+
+/// Mirrors case and execution events to a message broker for analytics
+/// pipelines. Publishing only queues the message, so handlers never wait
+/// on the broker; messages it refuses are logged and dropped. The default
+/// publisher is disabled and publishes nothing.
+#[derive(Clone, Default)]
+pub struct EventPublisher {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    topic: String,
+    events: Vec<String>,
+    queue: mpsc::Sender<Outgoing>,
+}
+
+impl EventPublisher {
+    /// Publishes `events` to `topic`, which may contain `{event_type}` to
+    /// give each type its own topic or subject. Needs a Tokio runtime.
+    pub fn new(broker: Arc<dyn EventBroker>, topic: impl Into<String>, events: Vec<String>) -> Self {
+        let (queue, mut outgoing) = mpsc::channel::<Outgoing>(QUEUE_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(Outgoing { topic, key, message }) = outgoing.recv().await {
+                let payload = match serde_json::to_vec(&message) {
+                    Ok(payload) => payload,
+                    Err(err) => {
+                        error!("Failed to serialize {} event: {}", message.event_type, err);
+                        continue;
+                    }
+                };
+                if let Err(err) = broker.publish(&topic, &key, payload).await {
+                    error!("Failed to publish {} event to '{}': {}", message.event_type, topic, err);
+                }
+            }
+        });
+
+        Self {
+            inner: Some(Arc::new(Inner { topic: topic.into(), events, queue })),
+        }
+    }
+
+    /// From `EVENT_BROKER` (`nats` or `kafka`), `EVENT_BROKER_URL`,
+    /// `EVENT_BROKER_TOPIC` and `EVENT_BROKER_EVENTS`, a comma-separated
+    /// list of event types. Disabled when `EVENT_BROKER` is unset; an error
+    /// when the broker can't be reached or this build doesn't include its
+    /// feature.
+    pub async fn from_env() -> Result<Self> {
+        let Some(kind) = env_value("EVENT_BROKER") else {
+            return Ok(Self::default());
+        };
+        let url = env_value("EVENT_BROKER_URL")
+            .ok_or_else(|| anyhow::anyhow!("EVENT_BROKER_URL must be set when EVENT_BROKER is"))?;
+        let broker = connect(&kind.to_ascii_lowercase(), &url).await?;
+
+        let topic = env_value("EVENT_BROKER_TOPIC").unwrap_or_else(|| DEFAULT_TOPIC.to_string());
+        let events = env_value("EVENT_BROKER_EVENTS")
+            .map(|events| events.split(',').map(|e| e.trim().to_string()).filter(|e| !e.is_empty()).collect())
+            .unwrap_or_else(|| DEFAULT_EVENTS.iter().map(|e| e.to_string()).collect());
+
+        Ok(Self::new(broker, topic, events))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Whether events of `event_type` are published.
+    pub fn publishes(&self, event_type: &str) -> bool {
+        self.inner.as_ref().is_some_and(|inner| inner.events.iter().any(|e| e == event_type))
+    }
+
+    /// Queues `data` as an `event_type` message keyed by `key`.
+    pub fn publish(&self, event_type: &str, region: Option<&str>, key: Uuid, data: Value) {
+        let Some(inner) = self.inner.as_ref().filter(|_| self.publishes(event_type)) else {
+            return;
+        };
+
+        let outgoing = Outgoing {
+            topic: inner.topic.replace("{event_type}", event_type),
+            key: key.to_string(),
+            message: BrokerMessage {
+                id: Uuid::new_v4(),
+                event_type: event_type.to_string(),
+                region: region.map(str::to_string),
+                occurred_at: Utc::now(),
+                data,
+            },
+        };
+        if let Err(err) = inner.queue.try_send(outgoing) {
+            warn!("Dropped {} event for the broker: {}", event_type, err);
+        }
+    }
+
+    /// Publishes a case change as `case.created`, `case.moved` or
+    /// `case.updated`, with the case as its data.
+    pub fn publish_case_change(&self, change: &CaseChange) {
+        self.publish(change.kind.as_str(), Some(&change.region), change.case.id, json!(change.case));
+    }
+
+    /// Publishes `execution.completed` or `execution.failed` for a finished
+    /// execution of `flow_name` triggered by `event`; other statuses aren't
+    /// published. Keyed by the case the event is about, if any.
+    pub fn publish_execution(&self, execution: &Execution, flow_name: &str, event: &Event) {
+        let event_type = match execution.status {
+            ExecutionStatus::Completed => "execution.completed",
+            ExecutionStatus::Failed => "execution.failed",
+            _ => return,
+        };
+
+        let case_id = event.data.get("case_id").and_then(Value::as_str).and_then(|id| id.parse::<Uuid>().ok());
+        let data = json!({
+            "execution_id": execution.id,
+            "flow_id": execution.flow_id,
+            "flow_name": flow_name,
+            "flow_version": execution.flow_version,
+            "status": execution.status,
+            "error": execution.error,
+            "started_at": execution.started_at,
+            "completed_at": execution.completed_at,
+            "event_id": event.id,
+            "event_type": event.event_type,
+            "workflow_id": event.data.get("workflow_id"),
+            "case_id": case_id,
+        });
+        self.publish(event_type, event.region.as_deref(), case_id.unwrap_or(execution.id), data);
+    }
+}
+
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(unused_variables))]
+async fn connect(kind: &str, url: &str) -> Result<Arc<dyn EventBroker>> {
+    match kind {
+        #[cfg(feature = "nats")]
+        "nats" => Ok(Arc::new(NatsBroker::connect(url).await?)),
+        #[cfg(feature = "kafka")]
+        "kafka" => Ok(Arc::new(KafkaBroker::connect(url)?)),
+        #[cfg(not(feature = "nats"))]
+        "nats" => anyhow::bail!("EVENT_BROKER=nats needs Orchepy built with the `nats` feature"),
+        #[cfg(not(feature = "kafka"))]
+        "kafka" => anyhow::bail!("EVENT_BROKER=kafka needs Orchepy built with the `kafka` feature"),
+        other => anyhow::bail!("Unknown EVENT_BROKER '{}', expected nats or kafka", other),
+    }
+}
+
+/// Publishes to NATS subjects; the key isn't used.
+#[cfg(feature = "nats")]
+pub struct NatsBroker {
+    client: async_nats::Client,
+}
+
+#[cfg(feature = "nats")]
+impl NatsBroker {
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self { client: async_nats::connect(url).await? })
+    }
+}
+
+#[cfg(feature = "nats")]
+#[async_trait]
+impl EventBroker for NatsBroker {
+    async fn publish(&self, topic: &str, _key: &str, payload: Vec<u8>) -> Result<()> {
+        self.client.publish(topic.to_string(), payload.into()).await?;
+        Ok(())
+    }
+}
+
+/// Publishes to Kafka topics, `url` being the bootstrap servers.
+#[cfg(feature = "kafka")]
+pub struct KafkaBroker {
+    producer: rdkafka::producer::FutureProducer,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaBroker {
+    pub fn connect(url: &str) -> Result<Self> {
+        let producer = rdkafka::ClientConfig::new()
+            .set("bootstrap.servers", url)
+            .set("message.timeout.ms", "10000")
+            .create()?;
+        Ok(Self { producer })
+    }
+}
+
+#[cfg(feature = "kafka")]
+#[async_trait]
+impl EventBroker for KafkaBroker {
+    async fn publish(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()> {
+        let record = rdkafka::producer::FutureRecord::to(topic).key(key).payload(&payload);
+        self.producer
+            .send(record, rdkafka::util::Timeout::After(std::time::Duration::from_secs(10)))
+            .await
+            .map_err(|(err, _)| err)?;
+        Ok(())
+    }
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::event::CreateEvent;
+
+    struct RecordingBroker {
+        sent: mpsc::UnboundedSender<(String, String, Value)>,
+    }
+
+    #[async_trait]
+    impl EventBroker for RecordingBroker {
+        async fn publish(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()> {
+            let payload = serde_json::from_slice(&payload)?;
+            self.sent.send((topic.to_string(), key.to_string(), payload))?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publishes_selected_events() {
+        let (sent, mut received) = mpsc::unbounded_channel();
+        let events = vec!["execution.failed".to_string(), "case.created".to_string()];
+        let publisher = EventPublisher::new(Arc::new(RecordingBroker { sent }), "orchepy.{event_type}", events);
+
+        let case_id = Uuid::new_v4();
+        let mut event = Event::new(CreateEvent {
+            event_type: "case.status_changed".to_string(),
+            data: json!({"case_id": case_id, "workflow_id": Uuid::new_v4()}),
+            metadata: None,
+        });
+        event.region = Some("eu".to_string());
+        let mut execution = Execution::new(Uuid::new_v4(), event.id);
+        execution.status = ExecutionStatus::Completed;
+        publisher.publish_execution(&execution, "Notify", &event);
+
+        execution.status = ExecutionStatus::Failed;
+        execution.error = Some("Step 'post' failed".to_string());
+        publisher.publish_execution(&execution, "Notify", &event);
+
+        let (topic, key, message) = received.recv().await.unwrap();
+        assert_eq!(topic, "orchepy.execution.failed");
+        assert_eq!(key, case_id.to_string());
+        assert_eq!(message["type"], "execution.failed");
+        assert_eq!(message["region"], "eu");
+        assert_eq!(message["data"]["flow_name"], "Notify");
+        assert_eq!(message["data"]["error"], "Step 'post' failed");
+        assert_eq!(message["data"]["case_id"], json!(case_id));
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_disabled_publisher_publishes_nothing() {
+        let publisher = EventPublisher::default();
+
+        assert!(!publisher.is_enabled());
+        assert!(!publisher.publishes("case.created"));
+        publisher.publish("case.created", None, Uuid::new_v4(), json!({}));
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::Result;
+use bytes::Bytes;
+use serde_json::{json, Value};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::models::execution::{Execution, ExecutionStatus};
+use crate::services::storage::SharedStorage;
+
+/// Key of the object that replaces an offloaded payload in `steps_status`:
+/// `{"$offloaded": {"key": "executions/...", "size_bytes": 81234}}`.
+pub const OFFLOADED: &str = "$offloaded";
+
+const DEFAULT_THRESHOLD_BYTES: usize = 64 * 1024;
+
+/// Step fields that can hold large payloads.
+const PAYLOAD_FIELDS: [&str; 2] = ["request", "response"];
+
+/// Moves the large request and response bodies of finished executions to
+/// object storage, so `orchepy_executions` keeps only timings and statuses
+/// for them, and loads them back when an execution is looked at.
+#[derive(Clone)]
+pub struct ExecutionPayloads {
+    storage: SharedStorage,
+    threshold_bytes: usize,
+}
+
+impl ExecutionPayloads {
+    /// Offloads payloads whose JSON is larger than `threshold_bytes`.
+    pub fn new(storage: SharedStorage, threshold_bytes: usize) -> Self {
+        Self { storage, threshold_bytes }
+    }
+
+    /// With `EXECUTION_PAYLOAD_OFFLOAD_BYTES` as the threshold (default
+    /// 64 KiB). `0` stops offloading; payloads already offloaded are still
+    /// restored.
+    pub fn from_env(storage: SharedStorage) -> Self {
+        let threshold_bytes = std::env::var("EXECUTION_PAYLOAD_OFFLOAD_BYTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_THRESHOLD_BYTES);
+        Self::new(storage, threshold_bytes)
+    }
+
+    /// Replaces the oversized payloads of a completed or failed execution
+    /// by references to stored copies, and returns how many were moved.
+    /// Executions that may still resume are left alone.
+    pub async fn offload(&self, execution: &mut Execution) -> Result<usize> {
+        let finished = matches!(execution.status, ExecutionStatus::Completed | ExecutionStatus::Failed);
+        if !finished || self.threshold_bytes == 0 {
+            return Ok(0);
+        }
+
+        let execution_id = execution.id;
+        let mut offloaded = 0;
+        let Some(steps) = execution.steps_status.as_object_mut() else {
+            return Ok(0);
+        };
+        for step in steps.values_mut() {
+            for field in PAYLOAD_FIELDS {
+                let Some(payload) = step.get_mut(field).filter(|payload| !payload.is_null()) else {
+                    continue;
+                };
+                let bytes = serde_json::to_vec(payload)?;
+                if bytes.len() <= self.threshold_bytes {
+                    continue;
+                }
+
+                let key = format!("executions/{}/{}.json", execution_id, Uuid::new_v4().simple());
+                let size_bytes = self.storage.put_bytes(&key, Bytes::from(bytes), Some("application/json")).await?;
+                *payload = json!({OFFLOADED: {"key": key, "size_bytes": size_bytes}});
+                offloaded += 1;
+            }
+        }
+        Ok(offloaded)
+    }
+
+    /// Puts the offloaded payloads of `execution` back in place. Payloads
+    /// that are no longer in storage keep their reference.
+    pub async fn restore(&self, execution: &mut Execution) -> Result<()> {
+        let execution_id = execution.id;
+        let Some(steps) = execution.steps_status.as_object_mut() else {
+            return Ok(());
+        };
+        for step in steps.values_mut() {
+            for field in PAYLOAD_FIELDS {
+                let Some(payload) = step.get_mut(field) else {
+                    continue;
+                };
+                let Some(key) = offloaded_key(payload) else {
+                    continue;
+                };
+                match self.storage.get_bytes(&key).await? {
+                    Some(bytes) => *payload = serde_json::from_slice(&bytes)?,
+                    None => warn!("Offloaded payload {} of execution {} is missing", key, execution_id),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `execution` as it should be saved: with its large payloads offloaded
+/// when `payloads` is set, or as is when offloading fails.
+pub async fn stored_execution(payloads: Option<&ExecutionPayloads>, execution: &Execution) -> Execution {
+    let mut stored = execution.clone();
+    if let Some(payloads) = payloads {
+        if let Err(err) = payloads.offload(&mut stored).await {
+            warn!("Failed to offload payloads of execution {}: {}", execution.id, err);
+            return execution.clone();
+        }
+    }
+    stored
+}
+
+fn offloaded_key(payload: &Value) -> Option<String> {
+    payload.get(OFFLOADED)?.get("key")?.as_str().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::storage::LocalStorage;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_offload_and_restore_payloads() {
+        let root = std::env::temp_dir().join(format!("orchepy-payloads-{}", Uuid::new_v4().simple()));
+        let payloads = ExecutionPayloads::new(Arc::new(LocalStorage::new(&root)), 100);
+
+        let mut execution = Execution::new(Uuid::new_v4(), Uuid::new_v4());
+        let large = json!({"items": vec!["x".repeat(20); 10]});
+        execution.steps_status = json!({
+            "fetch": {"status": "completed", "attempts": 1, "duration_ms": 12, "request": {"url": "http://localhost"}, "response": large},
+            "notify": {"status": "completed", "attempts": 1, "response": null}
+        });
+
+        assert_eq!(payloads.offload(&mut execution).await.unwrap(), 0);
+
+        execution.status = ExecutionStatus::Completed;
+        let original = execution.steps_status.clone();
+        assert_eq!(payloads.offload(&mut execution).await.unwrap(), 1);
+        assert_eq!(execution.steps_status["fetch"]["request"], json!({"url": "http://localhost"}));
+        assert_eq!(execution.steps_status["fetch"]["duration_ms"], 12);
+        let key = offloaded_key(&execution.steps_status["fetch"]["response"]).unwrap();
+        assert!(key.starts_with(&format!("executions/{}/", execution.id)));
+
+        payloads.restore(&mut execution).await.unwrap();
+        assert_eq!(execution.steps_status, original);
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,269 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// How often deferred work checks whether the pressure has dropped.
+const RECHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Kinds of work, most important first. Under pressure, background tiers
+/// wait in reverse order, so API requests are the last to slow down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkTier {
+    /// API requests such as case moves; never deferred.
+    Interactive,
+    /// Time-bound background work: resuming delayed flows and automations,
+    /// retrying failed runs, delivering the outbox, expiring credentials and
+    /// ingesting broker messages.
+    Sla,
+    /// Digests and API usage rollups.
+    Analytics,
+    /// Pruning old data and creating partitions for new data.
+    Retention,
+}
+
+impl WorkTier {
+    pub const ALL: [WorkTier; 4] = [Self::Interactive, Self::Sla, Self::Analytics, Self::Retention];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// In-flight API requests at which each background tier starts waiting.
+/// `None` never sheds the tier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadSheddingConfig {
+    pub sla_at: Option<usize>,
+    pub analytics_at: Option<usize>,
+    pub retention_at: Option<usize>,
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            sla_at: Some(64),
+            analytics_at: Some(32),
+            retention_at: Some(16),
+        }
+    }
+}
+
+impl LoadSheddingConfig {
+    /// No tier is ever shed.
+    pub fn disabled() -> Self {
+        Self {
+            sla_at: None,
+            analytics_at: None,
+            retention_at: None,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        fn var(name: &str, default: Option<usize>) -> Option<usize> {
+            match std::env::var(name).ok().and_then(|s| s.parse::<usize>().ok()) {
+                Some(0) => None,
+                Some(at) => Some(at),
+                None => default,
+            }
+        }
+
+        let defaults = Self::default();
+        Self {
+            sla_at: var("LOAD_SHED_SLA_AT", defaults.sla_at),
+            analytics_at: var("LOAD_SHED_ANALYTICS_AT", defaults.analytics_at),
+            retention_at: var("LOAD_SHED_RETENTION_AT", defaults.retention_at),
+        }
+    }
+
+    /// The threshold actually applied to `tier`: never above the one of a
+    /// more important tier, so a lower tier cannot keep running while a
+    /// higher one waits.
+    pub fn shed_at(&self, tier: WorkTier) -> Option<usize> {
+        let own = match tier {
+            WorkTier::Interactive => return None,
+            WorkTier::Sla => self.sla_at,
+            WorkTier::Analytics => self.analytics_at,
+            WorkTier::Retention => self.retention_at,
+        };
+
+        WorkTier::ALL[1..tier.index()]
+            .iter()
+            .filter_map(|higher| self.shed_at(*higher))
+            .chain(own)
+            .min()
+    }
+}
+
+#[derive(Default)]
+struct TierCounters {
+    deferred: AtomicU64,
+    deferred_ms: AtomicU64,
+    waiting: AtomicUsize,
+}
+
+/// Tracks in-flight API requests and holds background work back while
+/// they exceed its tier's threshold.
+#[derive(Clone)]
+pub struct LoadShedder {
+    config: Arc<LoadSheddingConfig>,
+    in_flight: Arc<AtomicUsize>,
+    tiers: Arc<[TierCounters; 4]>,
+}
+
+/// Counts a request as in flight until dropped.
+pub struct InFlight {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// `GET /admin/load`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadStats {
+    pub in_flight: usize,
+    pub tiers: Vec<TierStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TierStats {
+    pub tier: WorkTier,
+    pub shed_at: Option<usize>,
+    pub shedding: bool,
+    /// Times work of the tier had to wait since the process started.
+    pub deferred: u64,
+    pub deferred_ms: u64,
+    /// Work of the tier waiting right now.
+    pub waiting: usize,
+}
+
+impl Default for LoadShedder {
+    fn default() -> Self {
+        Self::new(LoadSheddingConfig::default())
+    }
+}
+
+impl LoadShedder {
+    pub fn new(config: LoadSheddingConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            tiers: Arc::new(Default::default()),
+        }
+    }
+
+    pub fn begin_request(&self) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight {
+            in_flight: self.in_flight.clone(),
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn is_shedding(&self, tier: WorkTier) -> bool {
+        self.config.shed_at(tier).is_some_and(|at| self.in_flight() >= at)
+    }
+
+    /// Waits until `tier` is no longer shed. Returns at once when it isn't.
+    pub async fn wait_for_turn(&self, tier: WorkTier, work: &str) {
+        if !self.is_shedding(tier) {
+            return;
+        }
+
+        let counters = &self.tiers[tier.index()];
+        counters.deferred.fetch_add(1, Ordering::Relaxed);
+        counters.waiting.fetch_add(1, Ordering::Relaxed);
+        debug!("Deferring {} while {} API requests are in flight", work, self.in_flight());
+
+        let started = Instant::now();
+        while self.is_shedding(tier) {
+            tokio::time::sleep(RECHECK_INTERVAL).await;
+        }
+
+        counters.waiting.fetch_sub(1, Ordering::Relaxed);
+        counters.deferred_ms.fetch_add(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+        debug!("Resuming {} after {:?}", work, started.elapsed());
+    }
+
+    pub fn stats(&self) -> LoadStats {
+        LoadStats {
+            in_flight: self.in_flight(),
+            tiers: WorkTier::ALL
+                .iter()
+                .map(|tier| {
+                    let counters = &self.tiers[tier.index()];
+                    TierStats {
+                        tier: *tier,
+                        shed_at: self.config.shed_at(*tier),
+                        shedding: self.is_shedding(*tier),
+                        deferred: counters.deferred.load(Ordering::Relaxed),
+                        deferred_ms: counters.deferred_ms.load(Ordering::Relaxed),
+                        waiting: counters.waiting.load(Ordering::Relaxed),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lower_tiers_never_outlast_higher_ones() {
+        let config = LoadSheddingConfig {
+            sla_at: Some(10),
+            analytics_at: Some(20),
+            retention_at: None,
+        };
+
+        assert_eq!(config.shed_at(WorkTier::Interactive), None);
+        assert_eq!(config.shed_at(WorkTier::Sla), Some(10));
+        assert_eq!(config.shed_at(WorkTier::Analytics), Some(10));
+        assert_eq!(config.shed_at(WorkTier::Retention), Some(10));
+        assert_eq!(LoadSheddingConfig::disabled().shed_at(WorkTier::Retention), None);
+    }
+
+    #[tokio::test]
+    async fn test_background_work_waits_for_requests() {
+        let shedder = LoadShedder::new(LoadSheddingConfig {
+            sla_at: Some(3),
+            analytics_at: Some(2),
+            retention_at: Some(1),
+        });
+
+        let first = shedder.begin_request();
+        let second = shedder.begin_request();
+        assert!(shedder.is_shedding(WorkTier::Analytics));
+        assert!(!shedder.is_shedding(WorkTier::Sla));
+
+        shedder.wait_for_turn(WorkTier::Sla, "sla check").await;
+
+        let waiting = tokio::spawn({
+            let shedder = shedder.clone();
+            async move { shedder.wait_for_turn(WorkTier::Analytics, "digest").await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(shedder.stats().tiers[2].waiting, 1);
+
+        drop(second);
+        waiting.await.unwrap();
+        drop(first);
+
+        let stats = shedder.stats();
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!((stats.tiers[1].deferred, stats.tiers[2].deferred), (0, 1));
+        assert_eq!(stats.tiers[2].waiting, 0);
+        assert!(!stats.tiers[3].shedding);
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use lettre::message::{Mailbox, Mailboxes};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::models::message::reply_address;
+use crate::repositories::EmailLogRepository;
+use crate::services::webhook_template::render_text;
+
+/// An email to one or more comma-separated recipients. When `reply_token`
+/// is set and the mailer has a reply address, replies are routed back
+/// through that token.
+#[derive(Debug, Clone)]
+pub struct OutboundEmail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub reply_token: Option<String>,
+    /// What is sending it, e.g. `automation` or `digest`, for the send log.
+    pub kind: String,
+    pub case_id: Option<Uuid>,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &OutboundEmail) -> Result<()>;
+}
+
+#[derive(Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    reply_address: Option<String>,
+    log: Option<PgPool>,
+}
+
+impl SmtpMailer {
+    pub fn new(host: &str, port: u16, credentials: Option<(String, String)>, from: &str) -> Result<Self> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?.port(port);
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: from.parse()?,
+            reply_address: None,
+            log: None,
+        })
+    }
+
+    /// Base address (`cases@reply.example.com`) that tokens are plus-addressed onto.
+    pub fn with_reply_address(mut self, reply_address: Option<String>) -> Self {
+        self.reply_address = reply_address;
+        self
+    }
+
+    /// Records every send, and why it failed, in `orchepy_email_log`.
+    pub fn with_log(mut self, pool: PgPool) -> Self {
+        self.log = Some(pool);
+        self
+    }
+
+    /// From `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`, `SMTP_PASSWORD` and
+    /// `SMTP_FROM`; `None` unless the host and sender are set.
+    pub fn from_env() -> Option<Self> {
+        let (host, from) = env_value("SMTP_HOST").zip(env_value("SMTP_FROM"))?;
+        let port = env_value("SMTP_PORT").and_then(|p| p.parse().ok()).unwrap_or(587);
+        let credentials = env_value("SMTP_USERNAME").zip(env_value("SMTP_PASSWORD"));
+
+        match Self::new(&host, port, credentials, &from) {
+            Ok(mailer) => Some(mailer.with_reply_address(env_value("CASE_REPLY_ADDRESS"))),
+            Err(err) => {
+                warn!("SMTP disabled: {}", err);
+                None
+            }
+        }
+    }
+
+    async fn deliver(&self, email: &OutboundEmail) -> Result<()> {
+        let mut builder = Message::builder().from(self.from.clone()).subject(email.subject.clone());
+        for recipient in email.to.parse::<Mailboxes>()? {
+            builder = builder.to(recipient);
+        }
+
+        let reply_to = email
+            .reply_token
+            .as_deref()
+            .zip(self.reply_address.as_deref())
+            .and_then(|(token, base)| reply_address(base, token));
+        if let Some(reply_to) = reply_to {
+            builder = builder.reply_to(reply_to.parse()?);
+        }
+
+        self.transport.send(builder.body(email.body.clone())?).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &OutboundEmail) -> Result<()> {
+        let result = self.deliver(email).await;
+
+        if let Some(pool) = &self.log {
+            let error = result.as_ref().err().map(ToString::to_string);
+            if let Err(err) = EmailLogRepository::new(pool)
+                .record(&email.kind, email.case_id, &email.to, &email.subject, error.as_deref())
+                .await
+            {
+                warn!("Failed to log email to {}: {}", email.to, err);
+            }
+        }
+
+        result
+    }
+}
+
+/// A subject and body with `${...}` placeholders, filled in from a JSON
+/// context; see [`EmailTemplate::render`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+impl EmailTemplate {
+    pub fn new(subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self { subject: subject.into(), body: body.into() }
+    }
+
+    /// For notification emails: `NOTIFY_EMAIL_SUBJECT` and
+    /// `NOTIFY_EMAIL_BODY`, which can use `${kind}`, `${subject}`,
+    /// `${text}` and `${link}`.
+    pub fn notification_from_env() -> Self {
+        Self::new(
+            env_value("NOTIFY_EMAIL_SUBJECT").unwrap_or_else(|| "${subject}".to_string()),
+            env_value("NOTIFY_EMAIL_BODY").map_or_else(|| "${text}\n\n${link}".to_string(), |body| body.replace("\\n", "\n")),
+        )
+    }
+
+    /// Subject and body with each placeholder replaced by the value at its
+    /// path in `context`, or by nothing. Trailing blank lines are dropped
+    /// from the body.
+    pub fn render(&self, context: &Value) -> (String, String) {
+        let subject = render_text(&self.subject, context).replace(['\r', '\n'], " ");
+        let body = render_text(&self.body, context).trim_end().to_string();
+        (subject, body)
+    }
+
+    pub fn render_notification(&self, kind: &str, subject: &str, text: &str, link: Option<&str>) -> (String, String) {
+        self.render(&json!({"kind": kind, "subject": subject, "text": text, "link": link}))
+    }
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_email_template() {
+        let template = EmailTemplate::new("[Orchepy] ${subject}", "${text}\n\n${link}");
+
+        let (subject, body) = template.render_notification("digest", "Daily\ndigest", "2 failed", None);
+        assert_eq!(subject, "[Orchepy] Daily digest");
+        assert_eq!(body, "2 failed");
+
+        let (_, body) = template.render_notification("digest", "Daily digest", "2 failed", Some("http://localhost/x"));
+        assert_eq!(body, "2 failed\n\nhttp://localhost/x");
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_recipients_fail_before_sending() {
+        let mailer = SmtpMailer::new("localhost", 2525, None, "orchepy@example.com").unwrap();
+        let email = OutboundEmail {
+            to: "not an address".to_string(),
+            subject: "Hi".to_string(),
+            body: "Hello".to_string(),
+            reply_token: None,
+            kind: "test".to_string(),
+            case_id: None,
+        };
+
+        assert!(mailer.send(&email).await.is_err());
+    }
+}
@@ -1,3 +1,45 @@
+pub mod auth;
+pub mod case_stream;
+pub mod clock;
+pub mod db_pool;
+pub mod digest;
+pub mod event_consumer;
+pub mod event_publisher;
+pub mod execution_payloads;
+pub mod load_shedding;
+pub mod mailer;
+pub mod notification;
+pub mod outbound_http;
+pub mod outbox;
+pub mod regions;
+pub mod retention;
+pub mod secrets;
+pub mod storage;
+pub mod usage;
 pub mod webhook;
+pub mod webhook_signing;
+pub mod webhook_template;
+pub mod worker_monitor;
+pub mod workflow_stream;
+pub mod workflow_docs;
 
+pub use auth::{JwtAuth, Principal, Role};
+pub use case_stream::{CaseChange, CaseChangeKind, CaseStream};
+pub use clock::{Clock, SharedClock, SystemClock, VirtualClock};
+pub use db_pool::{DbPools, PoolConfig, PoolStats};
+pub use digest::{DigestConfig, DigestService};
+pub use event_consumer::{ConsumerConfig, MessageSource};
+pub use event_publisher::{EventBroker, EventPublisher};
+pub use execution_payloads::ExecutionPayloads;
+pub use load_shedding::{LoadShedder, LoadSheddingConfig, WorkTier};
+pub use notification::{Notification, NotificationChannel, NotificationRegistry};
+pub use outbound_http::{OutboundHttp, OutboundHttpConfig};
+pub use regions::DataRegions;
+pub use retention::{PurgeReport, RetentionConfig};
+pub use secrets::{reencrypt_secrets, SecretCipher, Secrets};
+pub use storage::{LocalStorage, SharedStorage, StorageService};
+pub use usage::UsageRecorder;
 pub use webhook::WebhookSender;
+pub use webhook_signing::{WebhookSigner, WebhookVerifier};
+pub use worker_monitor::{WorkerMonitor, WorkerStatus};
+pub use workflow_stream::{WorkflowChange, WorkflowChangeKind, WorkflowStream};
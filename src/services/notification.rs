@@ -0,0 +1,508 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lettre::message::Mailboxes;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{debug, info, warn};
+
+use crate::services::mailer::{EmailTemplate, Mailer, OutboundEmail, SmtpMailer};
+use crate::services::outbound_http;
+
+const SMS_MAX_CHARS: usize = 1600;
+
+/// A message for humans, independent of where it ends up. `text` may contain
+/// Slack-style markup (`*bold*`, `<url|label>`); channels that can't render
+/// it send it as-is.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub kind: String,
+    pub subject: String,
+    pub text: String,
+    pub link: Option<String>,
+}
+
+impl Notification {
+    pub fn new(kind: &str, subject: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            subject: subject.into(),
+            text: text.into(),
+            link: None,
+        }
+    }
+
+    pub fn with_link(mut self, link: impl Into<String>) -> Self {
+        self.link = Some(link.into());
+        self
+    }
+}
+
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    /// Stable identifier used to route notifications (`slack`, `email`, ...).
+    fn name(&self) -> &str;
+
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+fn http_client() -> Client {
+    outbound_http::http_client(Duration::from_secs(10))
+}
+
+async fn post_json(client: &Client, url: &str, body: &serde_json::Value, channel: &str) -> Result<()> {
+    let response = client.post(url).json(body).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} returned status {}", channel, response.status()));
+    }
+    Ok(())
+}
+
+pub struct SlackChannel {
+    client: Client,
+    webhook_url: String,
+}
+
+impl SlackChannel {
+    pub fn new(webhook_url: String) -> Self {
+        Self { client: http_client(), webhook_url }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SlackChannel {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        post_json(&self.client, &self.webhook_url, &json!({"text": notification.text}), "Slack").await
+    }
+}
+
+pub struct TeamsChannel {
+    client: Client,
+    webhook_url: String,
+}
+
+impl TeamsChannel {
+    pub fn new(webhook_url: String) -> Self {
+        Self { client: http_client(), webhook_url }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for TeamsChannel {
+    fn name(&self) -> &str {
+        "teams"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let mut card = json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": notification.subject,
+            "title": notification.subject,
+            "text": notification.text,
+        });
+        if let Some(link) = &notification.link {
+            card["potentialAction"] = json!([{
+                "@type": "OpenUri",
+                "name": "Open in Orchepy",
+                "targets": [{"os": "default", "uri": link}],
+            }]);
+        }
+        post_json(&self.client, &self.webhook_url, &card, "Teams").await
+    }
+}
+
+/// Posts the notification as JSON to an arbitrary endpoint.
+pub struct WebhookChannel {
+    client: Client,
+    url: String,
+}
+
+impl WebhookChannel {
+    pub fn new(url: String) -> Self {
+        Self { client: http_client(), url }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        post_json(&self.client, &self.url, &json!(notification), "Webhook").await
+    }
+}
+
+/// Twilio error code for a recipient who replied STOP to the sender.
+pub const TWILIO_UNSUBSCRIBED: u64 = 21610;
+
+#[derive(Clone)]
+pub struct TwilioConfig {
+    pub account_sid: String,
+    pub auth_token: String,
+    pub from: String,
+    pub whatsapp_from: Option<String>,
+    pub api_base: String,
+}
+
+/// A rejected Twilio request, with Twilio's own error `code` when the
+/// response carried one.
+#[derive(Debug)]
+pub struct TwilioError {
+    pub status: u16,
+    pub code: Option<u64>,
+    pub message: String,
+}
+
+impl std::fmt::Display for TwilioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "Twilio returned status {} (code {}): {}", self.status, code, self.message),
+            None => write!(f, "Twilio returned status {}: {}", self.status, self.message),
+        }
+    }
+}
+
+impl std::error::Error for TwilioError {}
+
+pub struct SmsChannel {
+    client: Client,
+    config: TwilioConfig,
+    recipients: Vec<String>,
+}
+
+impl SmsChannel {
+    pub fn new(config: TwilioConfig, recipients: Vec<String>) -> Self {
+        Self { client: http_client(), config, recipients }
+    }
+
+    fn body(notification: &Notification) -> String {
+        let detail = notification.link.as_deref().unwrap_or(&notification.text);
+        format!("{}\n{}", notification.subject, detail)
+            .chars()
+            .take(SMS_MAX_CHARS)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for SmsChannel {
+    fn name(&self) -> &str {
+        "sms"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let body = Self::body(notification);
+
+        for to in &self.recipients {
+            self.config
+                .send_message(&self.client, &self.config.from, to, &body)
+                .await
+                .map_err(|e| anyhow!("{} for {}", e, to))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct EmailChannel {
+    mailer: SmtpMailer,
+    recipients: String,
+    template: EmailTemplate,
+}
+
+impl EmailChannel {
+    pub fn new(mailer: SmtpMailer, recipients: &[String], template: EmailTemplate) -> Result<Self> {
+        let recipients = recipients.join(", ");
+        recipients.parse::<Mailboxes>()?;
+        Ok(Self { mailer, recipients, template })
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let (subject, body) = self.template.render_notification(
+            &notification.kind,
+            &notification.subject,
+            &notification.text,
+            notification.link.as_deref(),
+        );
+
+        self.mailer
+            .send(&OutboundEmail {
+                to: self.recipients.clone(),
+                subject,
+                body,
+                reply_token: None,
+                kind: notification.kind.clone(),
+                case_id: None,
+            })
+            .await
+    }
+}
+
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// The notification channels configured for this deployment. Callers send
+/// through the registry and never talk to a channel directly.
+#[derive(Clone, Default)]
+pub struct NotificationRegistry {
+    channels: Vec<Arc<dyn NotificationChannel>>,
+}
+
+impl NotificationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emails are logged to `pool`.
+    pub fn from_env(pool: &PgPool) -> Self {
+        let mut registry = Self::new();
+
+        if let Some(url) = env_value("NOTIFY_SLACK_WEBHOOK_URL") {
+            registry.register(SlackChannel::new(url));
+        }
+
+        if let Some(url) = env_value("NOTIFY_TEAMS_WEBHOOK_URL") {
+            registry.register(TeamsChannel::new(url));
+        }
+
+        if let Some(url) = env_value("NOTIFY_WEBHOOK_URL") {
+            registry.register(WebhookChannel::new(url));
+        }
+
+        if let Some(twilio) = TwilioConfig::from_env() {
+            let recipients = env_list("NOTIFY_SMS_TO");
+            if !recipients.is_empty() {
+                registry.register(SmsChannel::new(twilio, recipients));
+            }
+        }
+
+        let email_to = env_list("NOTIFY_EMAIL_TO");
+        if !email_to.is_empty() {
+            if let Some(mailer) = SmtpMailer::from_env() {
+                match EmailChannel::new(mailer.with_log(pool.clone()), &email_to, EmailTemplate::notification_from_env()) {
+                    Ok(channel) => registry.register(channel),
+                    Err(err) => warn!("Email notifications disabled: {}", err),
+                }
+            }
+        }
+
+        info!("Notification channels: {:?}", registry.names());
+        registry
+    }
+
+    pub fn register(&mut self, channel: impl NotificationChannel + 'static) {
+        self.channels.retain(|c| c.name() != channel.name());
+        self.channels.push(Arc::new(channel));
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.channels.iter().any(|c| c.name() == name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.channels.iter().map(|c| c.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Sends to every registered channel.
+    pub async fn notify(&self, notification: &Notification) -> Result<()> {
+        self.deliver(self.channels.iter(), notification).await
+    }
+
+    /// Sends only to the named channels; unknown names are ignored.
+    pub async fn notify_channels(&self, names: &[String], notification: &Notification) -> Result<()> {
+        self.deliver(
+            self.channels.iter().filter(|c| names.iter().any(|n| n == c.name())),
+            notification,
+        )
+        .await
+    }
+
+    /// Every channel is attempted even if an earlier one fails; the first
+    /// error is returned.
+    async fn deliver<'a>(
+        &self,
+        channels: impl Iterator<Item = &'a Arc<dyn NotificationChannel>>,
+        notification: &Notification,
+    ) -> Result<()> {
+        let mut first_error = None;
+        let mut delivered = 0;
+
+        for channel in channels {
+            match channel.send(notification).await {
+                Ok(()) => {
+                    delivered += 1;
+                    debug!("Sent {} notification via {}", notification.kind, channel.name());
+                }
+                Err(err) => {
+                    warn!("Failed to send {} notification via {}: {}", notification.kind, channel.name(), err);
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        if delivered == 0 && first_error.is_none() {
+            warn!("No notification channel configured for {} notification", notification.kind);
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+impl TwilioConfig {
+    /// Sends one message through the Messages API and returns Twilio's
+    /// response (which includes the message `sid`).
+    pub async fn send_message(&self, client: &Client, from: &str, to: &str, body: &str) -> Result<serde_json::Value> {
+        let url = format!(
+            "{}/2010-04-01/Accounts/{}/Messages.json",
+            self.api_base.trim_end_matches('/'),
+            self.account_sid
+        );
+
+        let response = client
+            .post(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("To", to), ("From", from), ("Body", body)])
+            .send()
+            .await?;
+
+        let status = response.status();
+        let payload: serde_json::Value = response.json().await.unwrap_or_default();
+
+        if !status.is_success() {
+            return Err(TwilioError {
+                status: status.as_u16(),
+                code: payload.get("code").and_then(|c| c.as_u64()),
+                message: payload
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            }
+            .into());
+        }
+
+        Ok(payload)
+    }
+
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            account_sid: env_value("TWILIO_ACCOUNT_SID")?,
+            auth_token: env_value("TWILIO_AUTH_TOKEN")?,
+            from: env_value("TWILIO_FROM_NUMBER")?,
+            whatsapp_from: env_value("TWILIO_WHATSAPP_FROM"),
+            api_base: env_value("TWILIO_API_BASE").unwrap_or_else(|| "https://api.twilio.com".to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct RecordingChannel {
+        name: &'static str,
+        fail: bool,
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl NotificationChannel for RecordingChannel {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn send(&self, notification: &Notification) -> Result<()> {
+            self.sent.lock().unwrap().push(format!("{}:{}", self.name, notification.subject));
+            if self.fail {
+                return Err(anyhow!("{} is down", self.name));
+            }
+            Ok(())
+        }
+    }
+
+    fn registry(sent: &Arc<Mutex<Vec<String>>>, failing: &str) -> NotificationRegistry {
+        let mut registry = NotificationRegistry::new();
+        for name in ["slack", "email", "sms"] {
+            registry.register(RecordingChannel {
+                name,
+                fail: name == failing,
+                sent: sent.clone(),
+            });
+        }
+        registry
+    }
+
+    #[tokio::test]
+    async fn test_notify_reaches_every_channel_despite_failures() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let registry = registry(&sent, "email");
+
+        let result = registry.notify(&Notification::new("test", "Hello", "body")).await;
+
+        assert!(result.unwrap_err().to_string().contains("email is down"));
+        assert_eq!(*sent.lock().unwrap(), vec!["slack:Hello", "email:Hello", "sms:Hello"]);
+    }
+
+    #[tokio::test]
+    async fn test_notify_channels_routes_by_name() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let registry = registry(&sent, "");
+
+        registry
+            .notify_channels(&["sms".to_string(), "teams".to_string()], &Notification::new("test", "Hi", "body"))
+            .await
+            .unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), vec!["sms:Hi"]);
+    }
+
+    #[test]
+    fn test_register_replaces_same_name() {
+        let mut registry = NotificationRegistry::new();
+        registry.register(SlackChannel::new("http://a".to_string()));
+        registry.register(SlackChannel::new("http://b".to_string()));
+        assert_eq!(registry.names(), vec!["slack"]);
+    }
+
+    #[test]
+    fn test_sms_body_prefers_link_and_is_truncated() {
+        let notification = Notification::new("test", "Digest", "x".repeat(5000));
+        assert_eq!(SmsChannel::body(&notification).chars().count(), SMS_MAX_CHARS);
+
+        let notification = notification.with_link("http://localhost/executions");
+        assert_eq!(SmsChannel::body(&notification), "Digest\nhttp://localhost/executions");
+    }
+}
@@ -0,0 +1,67 @@
+//! The delivery loop of the outbox: due messages are leased, handed to a
+//! delivery function and then deleted, scheduled for a retry with
+//! exponential backoff or, after the last attempt, kept as failed.
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use sqlx::PgPool;
+use tracing::{error, warn};
+
+use crate::models::outbox::{OutboxDelivery, OutboxScope};
+use crate::repositories::OutboxRepository;
+
+const CLAIM_BATCH_SIZE: i64 = 50;
+/// Longer than a delivery can take, so a message isn't sent twice while
+/// the first attempt is still running.
+const LEASE: Duration = Duration::from_secs(300);
+/// With the backoff below, a message is given up on about an hour after
+/// its first attempt.
+const MAX_DELIVERY_ATTEMPTS: i32 = 12;
+
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::from_secs(2_u64.pow(attempts.clamp(1, 12) as u32))
+}
+
+/// Delivers the due messages in `scope` with `deliver` until none is left
+/// to claim, and returns how many went out.
+pub async fn dispatch<F, Fut>(pool: &PgPool, scope: &OutboxScope, mut deliver: F) -> Result<usize>
+where
+    F: FnMut(OutboxDelivery) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let repo = OutboxRepository::new(pool);
+    let mut delivered = 0;
+
+    loop {
+        let entries = repo.claim(scope, CLAIM_BATCH_SIZE, LEASE).await?;
+        if entries.is_empty() {
+            return Ok(delivered);
+        }
+
+        for entry in entries {
+            match deliver(entry.delivery.0).await {
+                Ok(()) => {
+                    repo.delivered(entry.id).await?;
+                    delivered += 1;
+                }
+                Err(err) if entry.attempts < MAX_DELIVERY_ATTEMPTS => {
+                    let delay = retry_delay(entry.attempts);
+                    warn!(
+                        "Outbox message {} for case {:?} failed (attempt {}), retrying in {}s: {}",
+                        entry.id, entry.case_id, entry.attempts, delay.as_secs(), err
+                    );
+                    repo.failed(entry.id, &err.to_string(), Some(delay)).await?;
+                }
+                Err(err) => {
+                    error!(
+                        "Outbox message {} for case {:?} failed after {} attempts, giving up: {}",
+                        entry.id, entry.case_id, entry.attempts, err
+                    );
+                    repo.failed(entry.id, &err.to_string(), None).await?;
+                }
+            }
+        }
+    }
+}
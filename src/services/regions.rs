@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Result};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::Arc;
+use tracing::info;
+
+use super::{DbPools, PoolConfig};
+use crate::repositories::health_repository::MIGRATOR;
+
+pub const DEFAULT_REGION: &str = "default";
+
+/// Normalises a region name from configuration or a request. Names are
+/// case-insensitive and limited to letters, digits, `-` and `_` so they can
+/// be used in env var names (`DATABASE_URL_EU_WEST`).
+pub fn normalize_region(name: &str) -> Option<String> {
+    let name = name.trim().to_ascii_lowercase();
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    valid.then_some(name)
+}
+
+fn region_env_var(prefix: &str, region: &str) -> String {
+    format!("{}_{}", prefix, region.to_ascii_uppercase().replace('-', "_"))
+}
+
+/// Database per data-residency region. `DATABASE_URL` is the default
+/// region (named by `DATA_REGION`); every name in `DATA_REGIONS` needs its
+/// own `DATABASE_URL_<REGION>`. Workflows, their cases and the events they
+/// raise are stored in exactly one of these databases. A region may also
+/// have a read replica (`DATABASE_READ_URL`, `DATABASE_READ_URL_<REGION>`).
+#[derive(Clone)]
+pub struct DataRegions {
+    default: String,
+    pools: Arc<BTreeMap<String, DbPools>>,
+}
+
+impl DataRegions {
+    /// A deployment with one database, which is also the default region.
+    pub fn single(pool: PgPool) -> Self {
+        Self::single_named(DEFAULT_REGION, pool)
+    }
+
+    pub fn single_named(name: &str, pool: PgPool) -> Self {
+        Self {
+            default: name.to_string(),
+            pools: Arc::new(BTreeMap::from([(name.to_string(), DbPools::new(pool))])),
+        }
+    }
+
+    pub fn with_region(mut self, name: &str, pool: PgPool) -> Self {
+        Arc::make_mut(&mut self.pools).insert(name.to_string(), DbPools::new(pool));
+        self
+    }
+
+    /// Sends the region's read-only queries to `replica`. Unknown regions
+    /// are ignored.
+    pub fn with_replica(mut self, name: &str, replica: PgPool) -> Self {
+        if let Some(pools) = Arc::make_mut(&mut self.pools).get_mut(name) {
+            *pools = pools.clone().with_replica(replica);
+        }
+        self
+    }
+
+    pub async fn from_env(default_pool: PgPool, pool_config: &PoolConfig) -> Result<Self> {
+        let default = match env::var("DATA_REGION") {
+            Ok(name) => normalize_region(&name).ok_or_else(|| anyhow!("Invalid DATA_REGION '{}'", name))?,
+            Err(_) => DEFAULT_REGION.to_string(),
+        };
+
+        let mut regions = Self::single_named(&default, default_pool);
+        if let Ok(url) = env::var("DATABASE_READ_URL") {
+            info!("Connecting to read replica for region '{}'...", default);
+            regions = regions.with_replica(&default, pool_config.connect(&url).await?);
+        }
+
+        for name in env::var("DATA_REGIONS").unwrap_or_default().split(',').filter(|s| !s.trim().is_empty()) {
+            let region = normalize_region(name).ok_or_else(|| anyhow!("Invalid region '{}' in DATA_REGIONS", name))?;
+            if region == default {
+                continue;
+            }
+
+            let var = region_env_var("DATABASE_URL", &region);
+            let url = env::var(&var).map_err(|_| anyhow!("{} must be set for region '{}'", var, region))?;
+
+            info!("Connecting to database for region '{}'...", region);
+            let pool = pool_config.connect(&url).await?;
+            regions = regions.with_region(&region, pool);
+
+            if let Ok(url) = env::var(region_env_var("DATABASE_READ_URL", &region)) {
+                info!("Connecting to read replica for region '{}'...", region);
+                regions = regions.with_replica(&region, pool_config.connect(&url).await?);
+            }
+        }
+
+        Ok(regions)
+    }
+
+    pub fn default_region(&self) -> &str {
+        &self.default
+    }
+
+    pub fn default_pool(&self) -> &PgPool {
+        self.pools[&self.default].primary()
+    }
+
+    pub fn pool(&self, region: &str) -> Option<&PgPool> {
+        self.pools.get(region).map(DbPools::primary)
+    }
+
+    pub fn pools(&self, region: &str) -> Option<&DbPools> {
+        self.pools.get(region)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.pools.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PgPool)> {
+        self.pools.iter().map(|(name, pools)| (name.as_str(), pools.primary()))
+    }
+
+    /// Applies the migrations embedded in this build to every region's
+    /// database. Instances starting together take turns, as the migrator
+    /// holds a database lock while it runs.
+    pub async fn migrate(&self) -> Result<()> {
+        for (region, pool) in self.iter() {
+            info!("Running migrations for region '{}'...", region);
+            MIGRATOR
+                .run(pool)
+                .await
+                .map_err(|err| anyhow!("Migrations failed for region '{}': {}", region, err))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_names() {
+        assert_eq!(normalize_region(" EU-West ").as_deref(), Some("eu-west"));
+        assert_eq!(normalize_region("us_east_1").as_deref(), Some("us_east_1"));
+        assert_eq!(normalize_region(""), None);
+        assert_eq!(normalize_region("eu west"), None);
+        assert_eq!(region_env_var("DATABASE_URL", "eu-west"), "DATABASE_URL_EU_WEST");
+        assert_eq!(region_env_var("DATABASE_READ_URL", "eu"), "DATABASE_READ_URL_EU");
+    }
+}
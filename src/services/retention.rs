@@ -0,0 +1,101 @@
+//! Deleting old executions, events and webhook attempts, by a background
+//! task and `POST /admin/purge`.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::repositories::{EventRepository, ExecutionRepository, WebhookDeliveryLogRepository};
+
+/// How long finished executions, events and logged webhook attempts are
+/// kept; `None` keeps them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionConfig {
+    pub execution_days: Option<u32>,
+    pub event_days: Option<u32>,
+    pub webhook_delivery_days: Option<u32>,
+    /// Rows deleted per statement, so a large purge doesn't hold locks for
+    /// long.
+    pub batch_size: i64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            execution_days: None,
+            event_days: None,
+            webhook_delivery_days: None,
+            batch_size: 1000,
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|s| s.parse().ok())
+        }
+
+        Self {
+            execution_days: var("EXECUTION_RETENTION_DAYS").filter(|days| *days > 0),
+            event_days: var("EVENT_RETENTION_DAYS").filter(|days| *days > 0),
+            webhook_delivery_days: var("WEBHOOK_DELIVERY_RETENTION_DAYS").filter(|days| *days > 0),
+            batch_size: var("PURGE_BATCH_SIZE").filter(|size| *size > 0).unwrap_or(1000),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.execution_days.is_some() || self.event_days.is_some() || self.webhook_delivery_days.is_some()
+    }
+}
+
+/// Rows deleted by a purge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PurgeReport {
+    pub executions: u64,
+    pub events: u64,
+    pub webhook_deliveries: u64,
+}
+
+/// Deletes executions that finished, events received and webhook attempts
+/// logged more than the configured days before `now`, a batch at a time.
+/// Executions go first, so the events only they held on to go in the same
+/// purge.
+pub async fn purge(pool: &PgPool, config: &RetentionConfig, now: DateTime<Utc>) -> Result<PurgeReport> {
+    let mut report = PurgeReport::default();
+    let cutoff = |days: u32| now - Duration::days(i64::from(days));
+
+    if let Some(days) = config.execution_days {
+        let repo = ExecutionRepository::new(pool);
+        report.executions = in_batches(config.batch_size, || repo.purge_finished(cutoff(days), config.batch_size)).await?;
+    }
+
+    if let Some(days) = config.event_days {
+        let repo = EventRepository::new(pool);
+        report.events = in_batches(config.batch_size, || repo.purge(cutoff(days), config.batch_size)).await?;
+    }
+
+    if let Some(days) = config.webhook_delivery_days {
+        let repo = WebhookDeliveryLogRepository::new(pool);
+        report.webhook_deliveries =
+            in_batches(config.batch_size, || repo.purge(cutoff(days), config.batch_size)).await?;
+    }
+
+    Ok(report)
+}
+
+async fn in_batches<F, Fut>(batch_size: i64, mut delete: F) -> Result<u64>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<u64>>,
+{
+    let mut total = 0;
+    loop {
+        let deleted = delete().await?;
+        total += deleted;
+        if deleted < batch_size as u64 {
+            return Ok(total);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, LazyLock};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{error, info, warn};
+
+use crate::models::webhook_auth::REDACTED;
+use crate::repositories::SecretRepository;
+
+const NONCE_LEN: usize = 12;
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\{\s*secrets\.([A-Za-z0-9_]+)\s*\}").expect("valid secret placeholder regex")
+});
+
+/// Encrypts secret values with AES-256-GCM. Each value gets a random nonce,
+/// stored in front of its ciphertext, and is bound to the secret's name, so
+/// a value copied to another secret's row doesn't decrypt.
+///
+/// While the key is being rotated, values still encrypted under the
+/// previous key decrypt with it until they are re-encrypted.
+#[derive(Clone)]
+pub struct SecretCipher {
+    cipher: Arc<Aes256Gcm>,
+    previous: Option<Arc<Aes256Gcm>>,
+}
+
+impl SecretCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: Arc::new(Aes256Gcm::new(key.into())),
+            previous: None,
+        }
+    }
+
+    /// Also decrypts values encrypted under `key`.
+    pub fn with_previous(mut self, key: &[u8; 32]) -> Self {
+        self.previous = Some(Arc::new(Aes256Gcm::new(key.into())));
+        self
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// From `SECRETS_KEY`, 32 bytes as 64 hex characters (e.g. from
+    /// `openssl rand -hex 32`), and `SECRETS_KEY_PREVIOUS`, the key it
+    /// replaced, if any. `None` when `SECRETS_KEY` is unset.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(key) = key_from_env("SECRETS_KEY")? else {
+            return Ok(None);
+        };
+        let cipher = Self::new(&key);
+        Ok(Some(match key_from_env("SECRETS_KEY_PREVIOUS")? {
+            Some(previous) => cipher.with_previous(&previous),
+            None => cipher,
+        }))
+    }
+
+    pub fn encrypt(&self, name: &str, value: &str) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload { msg: value.as_bytes(), aad: name.as_bytes() })
+            .map_err(|_| anyhow!("Failed to encrypt secret '{}'", name))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Fails when the value was encrypted under another key or name.
+    pub fn decrypt(&self, name: &str, sealed: &[u8]) -> Result<String> {
+        self.open(name, sealed).map(|(value, _)| value)
+    }
+
+    /// The value, and whether it was encrypted under the previous key.
+    fn open(&self, name: &str, sealed: &[u8]) -> Result<(String, bool)> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("Secret '{}' is malformed", name));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let payload = || Payload { msg: ciphertext, aad: name.as_bytes() };
+        let nonce = Nonce::from_slice(nonce);
+
+        let (value, previous) = match self.cipher.decrypt(nonce, payload()) {
+            Ok(value) => (value, false),
+            Err(_) => {
+                let value = self
+                    .previous
+                    .as_ref()
+                    .and_then(|previous| previous.decrypt(nonce, payload()).ok())
+                    .ok_or_else(|| anyhow!("Secret '{}' can't be decrypted with SECRETS_KEY", name))?;
+                (value, true)
+            }
+        };
+        Ok((String::from_utf8(value)?, previous))
+    }
+}
+
+fn key_from_env(var: &str) -> Result<Option<[u8; 32]>> {
+    let Some(hex_key) = std::env::var(var).ok().filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+    hex::decode(hex_key.trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .map(Some)
+        .ok_or_else(|| anyhow!("{} must be 64 hex characters", var))
+}
+
+/// Outcome of [`reencrypt_secrets`].
+#[derive(Debug, Default, Serialize)]
+pub struct ReencryptReport {
+    pub reencrypted: usize,
+    /// Secrets neither key decrypts; they stay as they are.
+    pub failed: Vec<String>,
+}
+
+/// Re-encrypts the secrets in `pool` still under the previous key with the
+/// current one, so the previous key can be dropped. A secret replaced
+/// meanwhile keeps its new value.
+pub async fn reencrypt_secrets(pool: &PgPool, cipher: &SecretCipher) -> Result<ReencryptReport> {
+    let repo = SecretRepository::new(pool);
+    let mut report = ReencryptReport::default();
+
+    for secret in repo.list().await? {
+        match cipher.open(&secret.name, &secret.ciphertext) {
+            Ok((value, true)) => {
+                let ciphertext = cipher.encrypt(&secret.name, &value)?;
+                if repo.replace_ciphertext(&secret.name, &secret.ciphertext, &ciphertext).await? {
+                    report.reencrypted += 1;
+                }
+            }
+            Ok((_, false)) => {}
+            Err(err) => {
+                error!("{}", err);
+                report.failed.push(secret.name);
+            }
+        }
+    }
+
+    if report.reencrypted > 0 {
+        info!("Re-encrypted {} secrets with the current SECRETS_KEY", report.reencrypted);
+    }
+    Ok(report)
+}
+
+/// Decrypted secret values for a run, substituted for `${secrets.NAME}` in
+/// webhook URLs and headers.
+#[derive(Clone, Default)]
+pub struct Secrets {
+    values: Arc<HashMap<String, String>>,
+}
+
+impl Secrets {
+    pub fn new(values: HashMap<String, String>) -> Self {
+        Self { values: Arc::new(values) }
+    }
+
+    /// The secrets called `names` in `pool`. Ones that don't exist or can't
+    /// be decrypted are left out, so only the requests using them fail.
+    pub async fn load(pool: &PgPool, cipher: Option<&SecretCipher>, names: &BTreeSet<String>) -> Result<Self> {
+        if names.is_empty() {
+            return Ok(Self::default());
+        }
+        let Some(cipher) = cipher else {
+            warn!("Secrets are referenced but SECRETS_KEY is not set");
+            return Ok(Self::default());
+        };
+
+        let mut values = HashMap::new();
+        for secret in SecretRepository::new(pool).find_many(names).await? {
+            match cipher.decrypt(&secret.name, &secret.ciphertext) {
+                Ok(value) => {
+                    values.insert(secret.name, value);
+                }
+                Err(err) => error!("{}", err),
+            }
+        }
+        Ok(Self::new(values))
+    }
+
+    /// The secrets `definition`, such as a flow's steps or a workflow's
+    /// automations, refers to. Failing to load them is logged, and leaves
+    /// the webhooks that use them to fail.
+    pub async fn for_definition(pool: &PgPool, cipher: Option<&SecretCipher>, definition: &impl Serialize) -> Self {
+        let names = serde_json::to_string(definition).map(|json| referenced_secrets(&json)).unwrap_or_default();
+
+        Self::load(pool, cipher, &names).await.unwrap_or_else(|err| {
+            error!("Failed to load secrets: {}", err);
+            Self::default()
+        })
+    }
+
+    /// `text` with its `${secrets.NAME}` placeholders replaced by their
+    /// values; an error names the first secret that isn't available.
+    pub fn resolve(&self, text: &str) -> Result<String> {
+        let mut missing = None;
+        let resolved = PLACEHOLDER.replace_all(text, |captures: &regex::Captures| {
+            let name = &captures[1];
+            self.values.get(name).cloned().unwrap_or_else(|| {
+                missing.get_or_insert_with(|| name.to_string());
+                String::new()
+            })
+        });
+
+        match missing {
+            Some(name) => Err(anyhow!("Secret '{}' is not available", name)),
+            None => Ok(resolved.into_owned()),
+        }
+    }
+}
+
+/// Names of the secrets `text` refers to.
+pub fn referenced_secrets(text: &str) -> BTreeSet<String> {
+    PLACEHOLDER.captures_iter(text).map(|captures| captures[1].to_string()).collect()
+}
+
+/// `text` with its `${secrets.NAME}` placeholders shown as redacted, for
+/// traces and simulations.
+pub fn redact_secrets(text: &str) -> String {
+    PLACEHOLDER.replace_all(text, REDACTED).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_values_are_bound_to_key_and_name() {
+        let cipher = SecretCipher::new(&[7; 32]);
+        let sealed = cipher.encrypt("STRIPE_KEY", "sk_live_123").unwrap();
+
+        assert!(!sealed.windows(11).any(|window| window == b"sk_live_123"));
+        assert_eq!(cipher.decrypt("STRIPE_KEY", &sealed).unwrap(), "sk_live_123");
+        assert_ne!(cipher.encrypt("STRIPE_KEY", "sk_live_123").unwrap(), sealed);
+        assert!(cipher.decrypt("OTHER_KEY", &sealed).is_err());
+        assert!(SecretCipher::new(&[8; 32]).decrypt("STRIPE_KEY", &sealed).is_err());
+        assert!(cipher.decrypt("STRIPE_KEY", &sealed[..5]).is_err());
+    }
+
+    #[test]
+    fn test_previous_key_still_decrypts() {
+        let old = SecretCipher::new(&[7; 32]);
+        let sealed = old.encrypt("STRIPE_KEY", "sk_live_123").unwrap();
+
+        let rotated = SecretCipher::new(&[8; 32]).with_previous(&[7; 32]);
+        assert_eq!(rotated.open("STRIPE_KEY", &sealed).unwrap(), ("sk_live_123".to_string(), true));
+
+        let resealed = rotated.encrypt("STRIPE_KEY", "sk_live_123").unwrap();
+        assert_eq!(rotated.open("STRIPE_KEY", &resealed).unwrap(), ("sk_live_123".to_string(), false));
+        assert!(old.decrypt("STRIPE_KEY", &resealed).is_err());
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let secrets = Secrets::new(HashMap::from([("TOKEN".to_string(), "abc".to_string())]));
+        let url = "https://api.example.com/hooks?token=${secrets.TOKEN}&id=${event.data.id}";
+
+        assert_eq!(secrets.resolve(url).unwrap(), "https://api.example.com/hooks?token=abc&id=${event.data.id}");
+        assert_eq!(secrets.resolve("Bearer ${ secrets.TOKEN }").unwrap(), "Bearer abc");
+        assert!(secrets.resolve("${secrets.MISSING}").is_err());
+        assert_eq!(redact_secrets(url), "https://api.example.com/hooks?token=********&id=${event.data.id}");
+        assert_eq!(
+            referenced_secrets("${secrets.A} ${secrets.B} ${secrets.A}"),
+            BTreeSet::from(["A".to_string(), "B".to_string()])
+        );
+    }
+}
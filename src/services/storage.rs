@@ -0,0 +1,295 @@
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt, TryStreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+/// Bytes streamed to or from storage, so files never have to fit in memory.
+pub type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+pub type SharedStorage = Arc<dyn StorageService>;
+
+/// An object read back from storage.
+pub struct StoredObject {
+    pub size_bytes: u64,
+    pub body: ByteStream,
+}
+
+/// Where case attachments and offloaded execution payloads are kept. Keys
+/// are `/`-separated paths such as `cases/{id}/attachments/{id}`.
+#[async_trait]
+pub trait StorageService: Send + Sync {
+    /// Stores `body` under `key`, replacing what was there, and returns its
+    /// size. A failed stream stores nothing.
+    async fn put(&self, key: &str, body: ByteStream, content_type: Option<&str>) -> Result<u64>;
+
+    /// The object under `key`, or `None` if there is none.
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>>;
+
+    /// Removes the object under `key`; removing a missing one succeeds.
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// A URL that downloads the object without going through Orchepy, valid
+    /// for `expires_in`, or `None` when the backend can't sign one.
+    async fn signed_url(&self, _key: &str, _expires_in: Duration) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    async fn put_bytes(&self, key: &str, bytes: Bytes, content_type: Option<&str>) -> Result<u64> {
+        self.put(key, Box::pin(futures::stream::once(async { Ok(bytes) })), content_type).await
+    }
+
+    /// The whole object under `key`, for small ones such as JSON payloads.
+    async fn get_bytes(&self, key: &str) -> Result<Option<Bytes>> {
+        let Some(object) = self.get(key).await? else {
+            return Ok(None);
+        };
+        let mut bytes = BytesMut::with_capacity(object.size_bytes as usize);
+        let mut body = object.body;
+        while let Some(chunk) = body.try_next().await? {
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(Some(bytes.freeze()))
+    }
+}
+
+/// From `STORAGE_BACKEND`: `local` keeps objects under `STORAGE_PATH`
+/// (default `./data/storage`); `s3` uses `S3_BUCKET`, optionally below
+/// `S3_PREFIX`, with the usual AWS credentials and region settings, and
+/// `S3_ENDPOINT` with `S3_FORCE_PATH_STYLE` for S3-compatible stores.
+/// `None` when `STORAGE_BACKEND` is unset.
+pub async fn storage_from_env() -> Result<Option<SharedStorage>> {
+    let Some(backend) = env_value("STORAGE_BACKEND").map(|backend| backend.to_ascii_lowercase()) else {
+        return Ok(None);
+    };
+
+    match backend.as_str() {
+        "local" => {
+            let root = env_value("STORAGE_PATH").unwrap_or_else(|| "./data/storage".to_string());
+            Ok(Some(Arc::new(LocalStorage::new(root))))
+        }
+        #[cfg(feature = "s3")]
+        "s3" => Ok(Some(Arc::new(S3Storage::from_env().await?))),
+        #[cfg(not(feature = "s3"))]
+        "s3" => anyhow::bail!("STORAGE_BACKEND=s3 needs Orchepy built with the `s3` feature"),
+        other => anyhow::bail!("Unknown STORAGE_BACKEND '{}', expected local or s3", other),
+    }
+}
+
+/// Objects as files below a directory, which is created as needed. Writes
+/// go to a temporary file that is renamed into place once complete.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        let plain = relative.components().all(|component| matches!(component, Component::Normal(_)));
+        if key.is_empty() || !plain {
+            anyhow::bail!("Invalid storage key '{}'", key);
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl StorageService for LocalStorage {
+    async fn put(&self, key: &str, mut body: ByteStream, _content_type: Option<&str>) -> Result<u64> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let partial = path.with_extension(format!("partial-{}", Uuid::new_v4().simple()));
+        let written = async {
+            let mut file = tokio::fs::File::create(&partial).await?;
+            let mut size = 0u64;
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk?;
+                file.write_all(&chunk).await?;
+                size += chunk.len() as u64;
+            }
+            file.sync_all().await?;
+            Ok::<_, std::io::Error>(size)
+        }
+        .await;
+
+        match written {
+            Ok(size) => {
+                tokio::fs::rename(&partial, &path).await?;
+                Ok(size)
+            }
+            Err(err) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                Err(err.into())
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>> {
+        let file = match tokio::fs::File::open(self.path(key)?).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let size_bytes = file.metadata().await?.len();
+        Ok(Some(StoredObject { size_bytes, body: Box::pin(ReaderStream::new(file)) }))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Objects in an S3 bucket. Uploads are spooled to a temporary file first,
+/// since S3 needs their length up front.
+#[cfg(feature = "s3")]
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+}
+
+#[cfg(feature = "s3")]
+impl S3Storage {
+    pub fn new(client: aws_sdk_s3::Client, bucket: impl Into<String>, prefix: impl Into<String>) -> Self {
+        let prefix = prefix.into().trim_matches('/').to_string();
+        Self { client, bucket: bucket.into(), prefix }
+    }
+
+    pub async fn from_env() -> Result<Self> {
+        let bucket = env_value("S3_BUCKET").ok_or_else(|| anyhow::anyhow!("S3_BUCKET must be set for STORAGE_BACKEND=s3"))?;
+        let shared = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let mut config = aws_sdk_s3::config::Builder::from(&shared);
+        if let Some(endpoint) = env_value("S3_ENDPOINT") {
+            config = config.endpoint_url(endpoint);
+        }
+        if env_value("S3_FORCE_PATH_STYLE").is_some_and(|value| value.eq_ignore_ascii_case("true")) {
+            config = config.force_path_style(true);
+        }
+
+        let client = aws_sdk_s3::Client::from_conf(config.build());
+        Ok(Self::new(client, bucket, env_value("S3_PREFIX").unwrap_or_default()))
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.prefix, key)
+        }
+    }
+}
+
+#[cfg(feature = "s3")]
+#[async_trait]
+impl StorageService for S3Storage {
+    async fn put(&self, key: &str, mut body: ByteStream, content_type: Option<&str>) -> Result<u64> {
+        let spool = std::env::temp_dir().join(format!("orchepy-upload-{}", Uuid::new_v4().simple()));
+        let result = async {
+            let mut file = tokio::fs::File::create(&spool).await?;
+            let mut size = 0u64;
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk?;
+                file.write_all(&chunk).await?;
+                size += chunk.len() as u64;
+            }
+            file.flush().await?;
+
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(self.object_key(key))
+                .set_content_type(content_type.map(str::to_string))
+                .body(aws_sdk_s3::primitives::ByteStream::from_path(&spool).await?)
+                .send()
+                .await?;
+            Ok(size)
+        }
+        .await;
+
+        let _ = tokio::fs::remove_file(&spool).await;
+        result
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredObject>> {
+        let output = match self.client.get_object().bucket(&self.bucket).key(self.object_key(key)).send().await {
+            Ok(output) => output,
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_no_such_key()) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let size_bytes = output.content_length().unwrap_or_default().max(0) as u64;
+        let body = ReaderStream::new(output.body.into_async_read());
+        Ok(Some(StoredObject { size_bytes, body: Box::pin(body) }))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.client.delete_object().bucket(&self.bucket).key(self.object_key(key)).send().await?;
+        Ok(())
+    }
+
+    async fn signed_url(&self, key: &str, expires_in: Duration) -> Result<Option<String>> {
+        let presigning = aws_sdk_s3::presigning::PresigningConfig::expires_in(expires_in)?;
+        let request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.object_key(key))
+            .presigned(presigning)
+            .await?;
+        Ok(Some(request.uri().to_string()))
+    }
+}
+
+fn env_value(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_storage_round_trip() {
+        let root = std::env::temp_dir().join(format!("orchepy-storage-{}", Uuid::new_v4().simple()));
+        let storage = LocalStorage::new(&root);
+
+        let chunks = futures::stream::iter(vec![Ok(Bytes::from_static(b"hello ")), Ok(Bytes::from_static(b"world"))]);
+        assert_eq!(storage.put("cases/1/a.txt", Box::pin(chunks), None).await.unwrap(), 11);
+        assert_eq!(storage.get_bytes("cases/1/a.txt").await.unwrap(), Some(Bytes::from_static(b"hello world")));
+        assert_eq!(storage.signed_url("cases/1/a.txt", Duration::from_secs(60)).await.unwrap(), None);
+
+        storage.delete("cases/1/a.txt").await.unwrap();
+        storage.delete("cases/1/a.txt").await.unwrap();
+        assert!(storage.get("cases/1/a.txt").await.unwrap().is_none());
+
+        let failing = futures::stream::iter(vec![Ok(Bytes::from_static(b"part")), Err(std::io::Error::other("reset"))]);
+        assert!(storage.put("cases/1/b.txt", Box::pin(failing), None).await.is_err());
+        assert!(storage.get("cases/1/b.txt").await.unwrap().is_none());
+        assert_eq!(std::fs::read_dir(root.join("cases/1")).unwrap().count(), 0);
+
+        for key in ["", "../escape", "/etc/passwd", "cases/../../x"] {
+            assert!(storage.put_bytes(key, Bytes::new(), None).await.is_err(), "{}", key);
+        }
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,148 @@
+use axum::http::{Extensions, HeaderMap};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::services::auth::Principal;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Stable, non-reversible identifier for an API key so raw keys never reach
+/// the database or logs.
+pub fn key_fingerprint(api_key: &str) -> String {
+    let digest = Sha256::digest(api_key.as_bytes());
+    hex::encode(&digest[..8])
+}
+
+/// Who a request is attributed to for usage and idempotency keys: the
+/// subject of its [`Principal`], or the `X-Api-Key` header when
+/// authentication is disabled. The header can't be trusted otherwise.
+pub fn client_id(auth_enabled: bool, extensions: &Extensions, headers: &HeaderMap) -> Option<String> {
+    if let Some(principal) = extensions.get::<Principal>() {
+        return principal.subject.as_deref().map(key_fingerprint);
+    }
+    if auth_enabled {
+        return None;
+    }
+
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|api_key| !api_key.is_empty())
+        .map(key_fingerprint)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UsageKey {
+    pub key_id: String,
+    pub bucket_start: DateTime<Utc>,
+    pub method: String,
+    pub route: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageCounters {
+    pub requests: i64,
+    pub client_errors: i64,
+    pub server_errors: i64,
+    pub total_latency_ms: i64,
+    pub max_latency_ms: i64,
+}
+
+/// In-memory rollup of API traffic per key, route and hour. Counters are
+/// drained periodically by the usage flush worker.
+#[derive(Clone, Default)]
+pub struct UsageRecorder {
+    buckets: Arc<Mutex<HashMap<UsageKey, UsageCounters>>>,
+}
+
+impl UsageRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(
+        &self,
+        key_id: &str,
+        method: &str,
+        route: &str,
+        status: u16,
+        latency_ms: i64,
+        at: DateTime<Utc>,
+    ) {
+        let key = UsageKey {
+            key_id: key_id.to_string(),
+            bucket_start: at.duration_trunc(TimeDelta::hours(1)).unwrap_or(at),
+            method: method.to_string(),
+            route: route.to_string(),
+        };
+
+        let mut buckets = self.buckets.lock().expect("usage lock poisoned");
+        let counters = buckets.entry(key).or_default();
+        counters.requests += 1;
+        if (400..500).contains(&status) {
+            counters.client_errors += 1;
+        } else if status >= 500 {
+            counters.server_errors += 1;
+        }
+        counters.total_latency_ms += latency_ms;
+        counters.max_latency_ms = counters.max_latency_ms.max(latency_ms);
+    }
+
+    pub fn drain(&self) -> Vec<(UsageKey, UsageCounters)> {
+        let mut buckets = self.buckets.lock().expect("usage lock poisoned");
+        buckets.drain().collect()
+    }
+
+    /// Puts counters back after a failed flush so they are retried next time.
+    pub fn restore(&self, entries: Vec<(UsageKey, UsageCounters)>) {
+        let mut buckets = self.buckets.lock().expect("usage lock poisoned");
+        for (key, counters) in entries {
+            let existing = buckets.entry(key).or_default();
+            existing.requests += counters.requests;
+            existing.client_errors += counters.client_errors;
+            existing.server_errors += counters.server_errors;
+            existing.total_latency_ms += counters.total_latency_ms;
+            existing.max_latency_ms = existing.max_latency_ms.max(counters.max_latency_ms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_fingerprint_is_stable_and_short() {
+        let a = key_fingerprint("secret-key");
+        assert_eq!(a, key_fingerprint("secret-key"));
+        assert_ne!(a, key_fingerprint("other-key"));
+        assert_eq!(a.len(), 16);
+    }
+
+    #[test]
+    fn test_record_aggregates_per_hour() {
+        let recorder = UsageRecorder::new();
+        let at = Utc.with_ymd_and_hms(2025, 3, 5, 9, 15, 0).unwrap();
+
+        recorder.record("k1", "GET", "/cases", 200, 10, at);
+        recorder.record("k1", "GET", "/cases", 404, 30, at + TimeDelta::minutes(20));
+        recorder.record("k1", "GET", "/cases", 500, 20, at + TimeDelta::hours(1));
+
+        let mut entries = recorder.drain();
+        entries.sort_by_key(|(key, _)| key.bucket_start);
+        assert_eq!(entries.len(), 2);
+
+        let (key, counters) = &entries[0];
+        assert_eq!(key.bucket_start, Utc.with_ymd_and_hms(2025, 3, 5, 9, 0, 0).unwrap());
+        assert_eq!(counters.requests, 2);
+        assert_eq!(counters.client_errors, 1);
+        assert_eq!(counters.total_latency_ms, 40);
+        assert_eq!(counters.max_latency_ms, 30);
+
+        assert_eq!(entries[1].1.server_errors, 1);
+        assert!(recorder.drain().is_empty());
+    }
+}
@@ -1,10 +1,31 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Instant;
+
 use anyhow::Result;
-use reqwest::Client;
+use chrono::Utc;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use crate::models::case::CaseStatus;
+use crate::models::credential::ExpiringCredential;
+use crate::models::execution::{Execution, EXECUTION_FAILED_EVENT};
+use crate::models::outbox::{OutboxDelivery, OutboxMessage, OutboxScope};
+use crate::models::Event;
+use crate::models::webhook_auth::WebhookAuth;
+use crate::models::webhook_delivery::{WebhookAttempt, WebhookDeliveryLogEntry};
+use crate::models::webhook_subscription::WebhookSubscription;
 use crate::models::Case;
+use crate::repositories::{
+    OutboxRepository, WebhookDeliveryLogRepository, WebhookSubscriptionRepository, WorkflowRepository,
+};
+use crate::services::outbound_http::http_client;
+use crate::services::outbox;
+use crate::services::webhook_signing::{SigningKey, WebhookSigner};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CaseWebhookPayload {
@@ -28,45 +49,260 @@ pub struct CaseWebhookData {
     pub metadata: Option<serde_json::Value>,
 }
 
+impl CaseWebhookPayload {
+    /// `case.moved` for `case` in its current phase; without `from_phase`
+    /// for a case that was just created.
+    pub fn moved(case: &Case, from_phase: Option<String>) -> Self {
+        Self {
+            action: "case.moved".to_string(),
+            data: CaseWebhookData {
+                case_id: case.id,
+                workflow_id: case.workflow_id,
+                from_phase,
+                to_phase: case.current_phase.clone(),
+                case_data: case.data.clone(),
+                metadata: case.metadata.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseStatusWebhookPayload {
+    pub action: String,
+
+    pub data: CaseStatusWebhookData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseStatusWebhookData {
+    pub case_id: Uuid,
+
+    pub workflow_id: Uuid,
+
+    pub phase: String,
+
+    pub from_status: CaseStatus,
+
+    pub to_status: CaseStatus,
+
+    pub reason: Option<String>,
+
+    pub case_data: serde_json::Value,
+
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl CaseStatusWebhookPayload {
+    /// `action` (e.g. `case.completed`) for `case`, now in its new status.
+    pub fn changed(action: &str, case: &Case, from_status: CaseStatus, reason: Option<String>) -> Self {
+        Self {
+            action: action.to_string(),
+            data: CaseStatusWebhookData {
+                case_id: case.id,
+                workflow_id: case.workflow_id,
+                phase: case.current_phase.clone(),
+                from_status,
+                to_status: case.status.clone(),
+                reason,
+                case_data: case.data.clone(),
+                metadata: case.metadata.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialWebhookPayload {
+    pub action: String,
+
+    pub data: ExpiringCredential,
+}
+
+/// What a webhook subscription receives: the event's type as `action` and
+/// its data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionWebhookPayload {
+    pub action: String,
+
+    pub event_id: Uuid,
+
+    pub subscription_id: Uuid,
+
+    pub data: serde_json::Value,
+}
+
+/// A webhook the receiver answered with a non-2xx status.
+#[derive(Debug)]
+struct Rejected(StatusCode);
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Webhook returned status {}", self.0)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
 #[derive(Clone)]
 pub struct WebhookSender {
     client: Client,
+    signer: WebhookSigner,
+    /// Sent with every webhook, e.g. a workflow's `webhook_headers`.
+    headers: BTreeMap<String, String>,
+    auth: Option<WebhookAuth>,
 }
 
 impl WebhookSender {
+    /// Signs with the keys in `WEBHOOK_SIGNING_KEYS`, if any.
     pub fn new() -> Self {
         Self {
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(10))
-                .build()
-                .expect("Failed to create HTTP client"),
+            client: http_client(std::time::Duration::from_secs(10)),
+            signer: WebhookSigner::from_env(),
+            headers: BTreeMap::new(),
+            auth: None,
         }
     }
 
-    pub async fn send_case_moved(
-        &self,
-        webhook_url: &str,
-        case: &Case,
-        from_phase: Option<String>,
-    ) -> Result<()> {
-        let payload = CaseWebhookPayload {
-            action: "case.moved".to_string(),
-            data: CaseWebhookData {
-                case_id: case.id,
-                workflow_id: case.workflow_id,
-                from_phase,
-                to_phase: case.current_phase.clone(),
-                case_data: case.data.clone(),
-                metadata: case.metadata.clone(),
-            },
+    pub fn with_signer(self, signer: WebhookSigner) -> Self {
+        Self { signer, ..self }
+    }
+
+    pub fn signer(&self) -> &WebhookSigner {
+        &self.signer
+    }
+
+    /// A sender for webhooks to the workflow's `webhook_url`: signed with
+    /// the workflow's webhook secret rather than the signing keys when it
+    /// has one, and carrying its `webhook_headers` and `webhook_auth`.
+    pub async fn for_workflow(&self, pool: &PgPool, workflow_id: Uuid) -> Result<Self> {
+        let repo = WorkflowRepository::new(pool);
+        let mut sender = match repo.webhook_secret(workflow_id).await? {
+            Some(secret) => self.clone().with_signer(WebhookSigner::new(vec![SigningKey::new(
+                workflow_id.to_string(),
+                secret.as_bytes(),
+            )])),
+            None => self.clone(),
         };
+        if let Some(workflow) = repo.find_by_id(workflow_id).await? {
+            sender.headers = workflow.webhook_headers;
+            sender.auth = workflow.webhook_auth;
+        }
+
+        Ok(sender)
+    }
+
+    /// Posts a payload built beforehand, e.g. one stored in the outbox, and
+    /// returns the receiver's status.
+    pub async fn send_payload(&self, webhook_url: &str, payload: &serde_json::Value) -> Result<StatusCode> {
+        info!("Sending webhook to {}: {}", webhook_url, payload["action"]);
+
+        self.post(webhook_url, payload).await
+    }
+
+    /// Signed with the subscription's secret rather than the signing keys.
+    pub async fn send_to_subscription(
+        &self,
+        subscription: &WebhookSubscription,
+        payload: &SubscriptionWebhookPayload,
+    ) -> Result<StatusCode> {
+        let signer = WebhookSigner::new(vec![SigningKey::new(
+            subscription.id.to_string(),
+            subscription.secret.as_bytes(),
+        )]);
 
         info!(
-            "Sending webhook to {}: case {} moved to phase '{}'",
-            webhook_url, case.id, case.current_phase
+            "Sending webhook to {}: {} for subscription {}",
+            subscription.url, payload.action, subscription.id
         );
 
-        match self.client.post(webhook_url).json(&payload).send().await {
+        self.post_signed(&subscription.url, payload, &signer).await
+    }
+
+    /// Queues `action` for every active subscription listening for it and
+    /// delivers it, in the background. `data.workflow_id`, when present, is
+    /// checked against the subscriptions' workflow filters; `data.case_id`
+    /// keeps each subscription's deliveries about a case in order.
+    pub fn notify_subscribers(&self, pool: PgPool, action: String, event_id: Uuid, data: serde_json::Value) {
+        let sender = self.clone();
+        tokio::spawn(async move {
+            let id_of = |field: &str| data.get(field).and_then(|id| id.as_str()).and_then(|id| Uuid::parse_str(id).ok());
+            let (workflow_id, case_id) = (id_of("workflow_id"), id_of("case_id"));
+
+            let subscriptions = match WebhookSubscriptionRepository::new(&pool)
+                .find_matching(&action, workflow_id)
+                .await
+            {
+                Ok(subscriptions) => subscriptions,
+                Err(err) => {
+                    error!("Failed to load webhook subscriptions for {}: {}", action, err);
+                    return;
+                }
+            };
+            if subscriptions.is_empty() {
+                return;
+            }
+
+            let messages: Vec<OutboxMessage> = subscriptions
+                .iter()
+                .map(|subscription| {
+                    let payload = SubscriptionWebhookPayload {
+                        action: action.clone(),
+                        event_id,
+                        subscription_id: subscription.id,
+                        data: data.clone(),
+                    };
+                    OutboxMessage::subscription(case_id, subscription.id, &payload)
+                })
+                .collect();
+
+            match OutboxRepository::new(&pool).enqueue(&messages).await {
+                Ok(ids) => sender.dispatch_queued(&pool, ids).await,
+                Err(err) => error!("Failed to queue {} for webhook subscriptions: {}", action, err),
+            }
+        });
+    }
+
+    /// `execution.failed` for an execution of `flow_name` triggered by
+    /// `event`, carrying the event's `workflow_id` and `case_id` if any.
+    pub fn notify_execution_failed(&self, pool: PgPool, execution: &Execution, flow_name: &str, event: &Event) {
+        let data = serde_json::json!({
+            "execution_id": execution.id,
+            "flow_id": execution.flow_id,
+            "flow_name": flow_name,
+            "flow_version": execution.flow_version,
+            "error": execution.error,
+            "event_type": event.event_type,
+            "workflow_id": event.data.get("workflow_id"),
+            "case_id": event.data.get("case_id"),
+        });
+        self.notify_subscribers(pool, EXECUTION_FAILED_EVENT.to_string(), event.id, data);
+    }
+
+    async fn post<T: Serialize>(&self, webhook_url: &str, payload: &T) -> Result<StatusCode> {
+        self.post_signed(webhook_url, payload, &self.signer).await
+    }
+
+    async fn post_signed<T: Serialize>(
+        &self,
+        webhook_url: &str,
+        payload: &T,
+        signer: &WebhookSigner,
+    ) -> Result<StatusCode> {
+        let body = serde_json::to_vec(payload)?;
+        let mut request = self.client.post(webhook_url);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(auth) = &self.auth {
+            request = auth.apply(request);
+        }
+        request = request.header(CONTENT_TYPE, "application/json");
+        for (name, value) in signer.headers(&Uuid::new_v4().to_string(), &body, Utc::now()) {
+            request = request.header(name, value);
+        }
+
+        match request.body(body).send().await {
             Ok(response) => {
                 if response.status().is_success() {
                     info!(
@@ -74,17 +310,14 @@ impl WebhookSender {
                         webhook_url,
                         response.status()
                     );
-                    Ok(())
+                    Ok(response.status())
                 } else {
                     warn!(
                         "Webhook failed with status {}: {}",
                         response.status(),
                         webhook_url
                     );
-                    Err(anyhow::anyhow!(
-                        "Webhook returned status {}",
-                        response.status()
-                    ))
+                    Err(Rejected(response.status()).into())
                 }
             }
             Err(err) => {
@@ -94,38 +327,85 @@ impl WebhookSender {
         }
     }
 
-    pub async fn send_case_moved_with_retry(
-        &self,
-        webhook_url: &str,
-        case: &Case,
-        from_phase: Option<String>,
-        max_retries: u32,
-    ) -> Result<()> {
-        let mut attempts = 0;
+    /// Sends a webhook queued in the outbox and logs the attempt. Events
+    /// are delivered by the API, not here.
+    pub async fn deliver(&self, pool: &PgPool, delivery: OutboxDelivery) -> Result<()> {
+        let Some(attempt) = self.send(pool, delivery).await? else {
+            return Ok(());
+        };
+        if let Err(err) = WebhookDeliveryLogRepository::new(pool).record(&attempt).await {
+            error!("Failed to log webhook delivery to {}: {}", attempt.url, err);
+        }
+        attempt.result()
+    }
 
-        loop {
-            attempts += 1;
+    /// Sends a logged webhook again and logs the new attempt, whether it
+    /// succeeded or not. `None` when it was for a subscription that is gone
+    /// or inactive.
+    pub async fn redeliver(&self, pool: &PgPool, logged: &WebhookDeliveryLogEntry) -> Result<Option<WebhookDeliveryLogEntry>> {
+        let Some(mut attempt) = self.send(pool, logged.delivery()).await? else {
+            return Ok(None);
+        };
+        attempt.redelivery_of = Some(logged.id);
+        WebhookDeliveryLogRepository::new(pool).record(&attempt).await.map(Some)
+    }
 
-            match self
-                .send_case_moved(webhook_url, case, from_phase.clone())
-                .await
-            {
-                Ok(_) => return Ok(()),
-                Err(err) => {
-                    if attempts >= max_retries {
-                        error!("Webhook failed after {} attempts: {}", max_retries, err);
-                        return Err(err);
+    /// Sends `delivery` and times it. `None` when it is for a subscription
+    /// that is gone or inactive, which is dropped.
+    async fn send(&self, pool: &PgPool, delivery: OutboxDelivery) -> Result<Option<WebhookAttempt>> {
+        let started = Instant::now();
+        let (result, url, payload, workflow_id, subscription_id) = match delivery {
+            OutboxDelivery::Webhook { url, payload, workflow_id } => {
+                let sender = match workflow_id {
+                    Some(workflow_id) => self.for_workflow(pool, workflow_id).await?,
+                    None => self.clone(),
+                };
+                let result = sender.send_payload(&url, &payload).await;
+                (result, url, payload, workflow_id, None)
+            }
+            OutboxDelivery::Subscription { subscription_id, payload } => {
+                match WebhookSubscriptionRepository::new(pool).find_by_id(subscription_id).await? {
+                    Some(subscription) if subscription.active => {
+                        let typed: SubscriptionWebhookPayload = serde_json::from_value(payload.clone())?;
+                        let result = self.send_to_subscription(&subscription, &typed).await;
+                        (result, subscription.url, payload, subscription.workflow_id, Some(subscription_id))
+                    }
+                    _ => {
+                        info!("Dropping webhook for subscription {}, which is gone or inactive", subscription_id);
+                        return Ok(None);
                     }
-
-                    warn!(
-                        "Webhook attempt {}/{} failed, retrying in {}s: {}",
-                        attempts, max_retries, attempts, err
-                    );
-
-                    tokio::time::sleep(std::time::Duration::from_secs(2_u64.pow(attempts - 1)))
-                        .await;
                 }
             }
+            OutboxDelivery::Event { event_type, .. } => {
+                anyhow::bail!("Event {} cannot be delivered as a webhook", event_type)
+            }
+            OutboxDelivery::Automation(automation) => {
+                anyhow::bail!("Automation {} cannot be delivered as a webhook", automation.id)
+            }
+        };
+
+        let status_code = match &result {
+            Ok(status) => Some(status.as_u16()),
+            Err(err) => err.downcast_ref::<Rejected>().map(|rejected| rejected.0.as_u16()),
+        };
+        Ok(Some(WebhookAttempt {
+            url,
+            payload,
+            workflow_id,
+            subscription_id,
+            status_code: status_code.map(i32::from),
+            latency_ms: started.elapsed().as_millis() as i64,
+            error: result.err().map(|err| err.to_string()),
+            redelivery_of: None,
+        }))
+    }
+
+    /// Delivers webhooks just queued in the outbox, by id, without waiting
+    /// for the dispatcher. Ones that fail are left to its retries.
+    pub async fn dispatch_queued(&self, pool: &PgPool, ids: Vec<i64>) {
+        let scope = OutboxScope::Messages(ids);
+        if let Err(err) = outbox::dispatch(pool, &scope, |delivery| self.deliver(pool, delivery)).await {
+            error!("Failed to deliver queued webhooks: {}", err);
         }
     }
 }
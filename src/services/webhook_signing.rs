@@ -0,0 +1,382 @@
+//! Signatures on the webhooks Orchepy sends, and the helpers receivers use
+//! to check them.
+//!
+//! Every webhook carries three headers:
+//!
+//! - `Orchepy-Webhook-Id`: unique per request.
+//! - `Orchepy-Webhook-Timestamp`: when it was sent, in Unix seconds.
+//! - `Orchepy-Signature`: `key_id=signature` pairs separated by `", "`, one
+//!   for each signing key valid at that time.
+//!
+//! A signature is the lowercase hex HMAC-SHA256 of
+//! `"{webhook_id}.{timestamp}.{body}"`, keyed with the key's secret. While
+//! keys are being rotated both the old and the new one sign, so a receiver
+//! holding either secret accepts the webhook.
+
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tracing::error;
+
+pub const WEBHOOK_ID_HEADER: &str = "orchepy-webhook-id";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "orchepy-webhook-timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "orchepy-signature";
+
+/// How far a webhook's timestamp may be from the receiver's clock.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// A secret webhooks are signed with, until `expires_at` if set.
+#[derive(Clone)]
+pub struct SigningKey {
+    pub id: String,
+    secret: Vec<u8>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("id", &self.id)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    pub fn new(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self { id: id.into(), secret: secret.into(), expires_at: None }
+    }
+
+    pub fn expiring(self, expires_at: DateTime<Utc>) -> Self {
+        Self { expires_at: Some(expires_at), ..self }
+    }
+
+    pub fn is_valid_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+
+    /// Parses `WEBHOOK_SIGNING_KEYS`: comma-separated `id:secret` entries,
+    /// each optionally followed by `@` and the RFC 3339 time it stops
+    /// signing, e.g. `2026-02:new-secret,2026-01:old-secret@2026-03-01T00:00:00Z`.
+    pub fn parse_list(spec: &str) -> Result<Vec<Self>, String> {
+        spec.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (id, rest) = entry
+                    .split_once(':')
+                    .filter(|(id, _)| !id.is_empty())
+                    .ok_or_else(|| format!("signing key '{}' must be written as id:secret", entry))?;
+                let (secret, expires_at) = match rest.rsplit_once('@') {
+                    Some((secret, expires_at)) => {
+                        let expires_at = DateTime::parse_from_rfc3339(expires_at)
+                            .map_err(|err| format!("signing key '{}' has an invalid expiry: {}", id, err))?;
+                        (secret, Some(expires_at.with_timezone(&Utc)))
+                    }
+                    None => (rest, None),
+                };
+                if secret.is_empty() {
+                    return Err(format!("signing key '{}' has an empty secret", id));
+                }
+
+                Ok(Self { id: id.to_string(), secret: secret.as_bytes().to_vec(), expires_at })
+            })
+            .collect()
+    }
+}
+
+/// The hex HMAC-SHA256 signature of a webhook under `secret`.
+pub fn signature(secret: &[u8], webhook_id: &str, timestamp: i64, body: &[u8]) -> String {
+    hex::encode(mac(secret, webhook_id, timestamp, body).finalize().into_bytes())
+}
+
+fn mac(secret: &[u8], webhook_id: &str, timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(format!("{}.{}.", webhook_id, timestamp).as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// Signs outgoing webhooks with the configured keys and the ones rotated
+/// in through the admin API.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookSigner {
+    keys: Vec<SigningKey>,
+    /// Shared by every clone, so a rotation reaches all senders at once.
+    managed: Arc<RwLock<Vec<SigningKey>>>,
+}
+
+impl WebhookSigner {
+    pub fn new(keys: Vec<SigningKey>) -> Self {
+        Self { keys, managed: Arc::default() }
+    }
+
+    /// Replaces the keys managed through the admin API; the configured
+    /// ones stay.
+    pub fn set_managed_keys(&self, keys: Vec<SigningKey>) {
+        *self.managed.write().expect("signing keys lock poisoned") = keys;
+    }
+
+    /// Keys from `WEBHOOK_SIGNING_KEYS`. Without any, webhooks go unsigned.
+    pub fn from_env() -> Self {
+        let spec = std::env::var("WEBHOOK_SIGNING_KEYS").unwrap_or_default();
+        match SigningKey::parse_list(&spec) {
+            Ok(keys) => Self::new(keys),
+            Err(err) => {
+                error!("Ignoring WEBHOOK_SIGNING_KEYS: {}", err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Keys that sign webhooks sent at `now`: the configured ones, then the
+    /// managed ones.
+    pub fn valid_keys(&self, now: DateTime<Utc>) -> Vec<SigningKey> {
+        let managed = self.managed.read().expect("signing keys lock poisoned");
+        self.keys
+            .iter()
+            .chain(managed.iter())
+            .filter(|key| key.is_valid_at(now))
+            .cloned()
+            .collect()
+    }
+
+    /// The keys from `WEBHOOK_SIGNING_KEYS`, valid or not.
+    pub fn configured_keys(&self) -> &[SigningKey] {
+        &self.keys
+    }
+
+    /// The headers for a webhook with `body` sent at `now`, or none when no
+    /// key is valid.
+    pub fn headers(&self, webhook_id: &str, body: &[u8], now: DateTime<Utc>) -> Vec<(&'static str, String)> {
+        let timestamp = now.timestamp();
+        let signatures: Vec<String> = self
+            .valid_keys(now)
+            .iter()
+            .map(|key| format!("{}={}", key.id, signature(&key.secret, webhook_id, timestamp, body)))
+            .collect();
+        if signatures.is_empty() {
+            return Vec::new();
+        }
+
+        vec![
+            (WEBHOOK_ID_HEADER, webhook_id.to_string()),
+            (WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string()),
+            (WEBHOOK_SIGNATURE_HEADER, signatures.join(", ")),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    MissingHeader(&'static str),
+    InvalidTimestamp,
+    /// The timestamp is further from now than the tolerance allows.
+    OutsideTolerance,
+    /// None of the signatures was made with one of the receiver's secrets.
+    NoMatchingSignature,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHeader(header) => write!(f, "missing {} header", header),
+            Self::InvalidTimestamp => f.write_str("webhook timestamp is not a Unix time"),
+            Self::OutsideTolerance => f.write_str("webhook timestamp is outside the allowed tolerance"),
+            Self::NoMatchingSignature => f.write_str("no webhook signature matches"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Checks webhooks on the receiving side. Give it every secret you accept:
+/// during a rotation, both the old and the new one.
+#[derive(Clone)]
+pub struct WebhookVerifier {
+    secrets: Vec<Vec<u8>>,
+    tolerance: Duration,
+}
+
+impl WebhookVerifier {
+    pub fn new<S: Into<Vec<u8>>>(secrets: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            secrets: secrets.into_iter().map(Into::into).collect(),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    pub fn with_tolerance(self, tolerance: Duration) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8]) -> Result<(), VerifyError> {
+        self.verify_at(headers, body, Utc::now())
+    }
+
+    /// Like `verify`, with the receiver's clock reading `now`.
+    pub fn verify_at(&self, headers: &HeaderMap, body: &[u8], now: DateTime<Utc>) -> Result<(), VerifyError> {
+        let header = |name: &'static str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(VerifyError::MissingHeader(name))
+        };
+        let webhook_id = header(WEBHOOK_ID_HEADER)?;
+        let timestamp: i64 = header(WEBHOOK_TIMESTAMP_HEADER)?
+            .trim()
+            .parse()
+            .map_err(|_| VerifyError::InvalidTimestamp)?;
+        let signatures = header(WEBHOOK_SIGNATURE_HEADER)?;
+
+        if now.timestamp().abs_diff(timestamp) > self.tolerance.as_secs() {
+            return Err(VerifyError::OutsideTolerance);
+        }
+
+        let candidates: Vec<Vec<u8>> = signatures
+            .split(',')
+            .filter_map(|entry| entry.trim().split_once('='))
+            .filter_map(|(_, signature)| hex::decode(signature).ok())
+            .collect();
+
+        let matches = self.secrets.iter().any(|secret| {
+            candidates
+                .iter()
+                .any(|candidate| mac(secret, webhook_id, timestamp, body).verify_slice(candidate).is_ok())
+        });
+        if matches {
+            Ok(())
+        } else {
+            Err(VerifyError::NoMatchingSignature)
+        }
+    }
+}
+
+/// A worked example for checking an implementation of the algorithm.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestVector {
+    pub description: &'static str,
+    /// `id: secret` of each key, as UTF-8.
+    pub keys: Vec<(String, String)>,
+    pub webhook_id: &'static str,
+    pub timestamp: i64,
+    pub body: &'static str,
+    pub signature_header: String,
+}
+
+/// Examples signed with published secrets, never the configured ones.
+pub fn test_vectors() -> Vec<TestVector> {
+    const WEBHOOK_ID: &str = "0b7c4a1e-8f2d-4c3b-9a6e-5d1f2e3c4b5a";
+    const BODY: &str = r#"{"action":"case.moved"}"#;
+
+    let old = SigningKey::new("2026-01", "orchepy-example-old")
+        .expiring(DateTime::from_timestamp(1_767_312_000, 0).expect("valid timestamp"));
+    let new = SigningKey::new("2026-02", "orchepy-example-new");
+    let signer = WebhookSigner::new(vec![old.clone(), new.clone()]);
+
+    [
+        ("Both keys valid during a rotation", 1_767_225_600),
+        ("After the old key expired", 1_767_398_400),
+    ]
+    .into_iter()
+    .map(|(description, timestamp)| {
+        let now = DateTime::from_timestamp(timestamp, 0).expect("valid timestamp");
+        let headers = signer.headers(WEBHOOK_ID, BODY.as_bytes(), now);
+
+        TestVector {
+            description,
+            keys: signer
+                .valid_keys(now)
+                .iter()
+                .map(|key| (key.id.clone(), String::from_utf8_lossy(&key.secret).into_owned()))
+                .collect(),
+            webhook_id: WEBHOOK_ID,
+            timestamp,
+            body: BODY,
+            signature_header: headers
+                .into_iter()
+                .find(|(name, _)| *name == WEBHOOK_SIGNATURE_HEADER)
+                .map(|(_, value)| value)
+                .unwrap_or_default(),
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn header_map(headers: Vec<(&'static str, String)>) -> HeaderMap {
+        headers
+            .into_iter()
+            .map(|(name, value)| (name.parse().unwrap(), HeaderValue::from_str(&value).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_vectors_match_reference_hmac() {
+        let vectors = test_vectors();
+        assert_eq!(
+            vectors[0].signature_header,
+            "2026-01=967b411e3aa35c4fdd6596ab068df9ac440daf00a8b6c94db07629f05557aa54, \
+             2026-02=7084dd1bbf1b42aec1aff87ae8bca0d8f8b6262bf25720085cc896cc33276622"
+        );
+        assert!(vectors[1].signature_header.starts_with("2026-02="));
+        assert_eq!(vectors[1].keys.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_across_key_rotation() {
+        let now = Utc::now();
+        let signer = WebhookSigner::new(vec![
+            SigningKey::new("old", "old-secret").expiring(now + chrono::Duration::hours(1)),
+            SigningKey::new("new", "new-secret"),
+            SigningKey::new("retired", "retired-secret").expiring(now - chrono::Duration::hours(1)),
+        ]);
+        let body = br#"{"action":"case.moved"}"#;
+        let headers = header_map(signer.headers("wh_1", body, now));
+
+        assert_eq!(WebhookVerifier::new(["old-secret"]).verify_at(&headers, body, now), Ok(()));
+        assert_eq!(WebhookVerifier::new(["new-secret"]).verify_at(&headers, body, now), Ok(()));
+        assert_eq!(
+            WebhookVerifier::new(["retired-secret"]).verify_at(&headers, body, now),
+            Err(VerifyError::NoMatchingSignature)
+        );
+        assert_eq!(
+            WebhookVerifier::new(["new-secret"]).verify_at(&headers, b"{}", now),
+            Err(VerifyError::NoMatchingSignature)
+        );
+        assert_eq!(
+            WebhookVerifier::new(["new-secret"]).verify_at(&headers, body, now + chrono::Duration::minutes(10)),
+            Err(VerifyError::OutsideTolerance)
+        );
+        assert_eq!(
+            WebhookVerifier::new(["new-secret"]).verify_at(&HeaderMap::new(), body, now),
+            Err(VerifyError::MissingHeader(WEBHOOK_ID_HEADER))
+        );
+        assert!(WebhookSigner::default().headers("wh_1", body, now).is_empty());
+    }
+
+    #[test]
+    fn test_parse_signing_keys() {
+        let keys = SigningKey::parse_list("2026-02:new, 2026-01:old@2026-03-01T00:00:00Z").unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!((keys[0].id.as_str(), keys[0].expires_at), ("2026-02", None));
+        assert_eq!(keys[1].expires_at.unwrap().to_rfc3339(), "2026-03-01T00:00:00+00:00");
+
+        assert!(SigningKey::parse_list("").unwrap().is_empty());
+        assert!(SigningKey::parse_list("no-secret").is_err());
+        assert!(SigningKey::parse_list("k:").is_err());
+        assert!(SigningKey::parse_list("k:s@tomorrow").is_err());
+    }
+}
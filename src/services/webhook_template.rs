@@ -0,0 +1,157 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::models::Case;
+
+/// Placeholders a workflow's `webhook_template` may use: `${action}` and
+/// the case's fields as serialized, e.g. `${case.data.customer.name}`.
+const PLACEHOLDER_ROOTS: &[&str] = &["action", "case"];
+
+/// `${path}` placeholders, in webhook templates and automation actions.
+pub(crate) static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{\s*([^}]+?)\s*\}").expect("valid placeholder regex"));
+
+/// Replaces each placeholder in `text` by what `value` gives for its path,
+/// stopping at the first error.
+pub(crate) fn replace_placeholders<E>(text: &str, mut value: impl FnMut(&str) -> Result<String, E>) -> Result<String, E> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut last = 0;
+    for captures in PLACEHOLDER.captures_iter(text) {
+        let whole = captures.get(0).expect("capture 0 always exists");
+        rendered.push_str(&text[last..whole.start()]);
+        rendered.push_str(&value(&captures[1])?);
+        last = whole.end();
+    }
+    rendered.push_str(&text[last..]);
+    Ok(rendered)
+}
+
+/// What the placeholders of a template read when sending `action` about `case`.
+pub fn context(action: &str, case: &Case) -> Value {
+    json!({"action": action, "case": case})
+}
+
+/// Fills in the placeholders of every string in `template`. A string that
+/// is a single placeholder takes the value as is, numbers and objects
+/// included, or `null` if the case has no such field; placeholders inside
+/// longer strings are replaced by the value's text, or nothing.
+pub fn render(template: &Value, context: &Value) -> Value {
+    match template {
+        Value::String(text) => render_string(text, context),
+        Value::Array(items) => Value::Array(items.iter().map(|item| render(item, context)).collect()),
+        Value::Object(fields) => {
+            Value::Object(fields.iter().map(|(key, value)| (key.clone(), render(value, context))).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+fn render_string(text: &str, context: &Value) -> Value {
+    if let Some(captures) = PLACEHOLDER.captures(text) {
+        if captures.get(0).is_some_and(|whole| whole.as_str() == text) {
+            return lookup(context, &captures[1]).cloned().unwrap_or(Value::Null);
+        }
+    }
+
+    Value::String(render_text(text, context))
+}
+
+/// Replaces the placeholders in `text` by the text of their values, or by
+/// nothing for values `context` doesn't have.
+pub fn render_text(text: &str, context: &Value) -> String {
+    let Ok(rendered) = replace_placeholders(text, |path| {
+        Ok::<_, std::convert::Infallible>(match lookup(context, path) {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(other) => other.to_string(),
+        })
+    });
+    rendered
+}
+
+fn lookup<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(context, |current, part| match current {
+        Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+        _ => current.get(part),
+    })
+}
+
+/// The placeholders in `template` that don't start with `action` or
+/// `case`, with the path of the string they are in, e.g. `order.items[0]`.
+pub fn unknown_placeholders(template: &Value) -> Vec<(String, String)> {
+    let mut unknown = Vec::new();
+    collect_unknown(template, String::new(), &mut unknown);
+    unknown
+}
+
+fn collect_unknown(template: &Value, path: String, out: &mut Vec<(String, String)>) {
+    match template {
+        Value::String(text) => {
+            for captures in PLACEHOLDER.captures_iter(text) {
+                let root = captures[1].split('.').next().unwrap_or_default();
+                if !PLACEHOLDER_ROOTS.contains(&root) {
+                    out.push((path.clone(), captures[1].to_string()));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (idx, item) in items.iter().enumerate() {
+                collect_unknown(item, format!("{}[{}]", path, idx), out);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                collect_unknown(value, path, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_render_template() {
+        let case = Case::new(
+            Uuid::new_v4(),
+            "Review".to_string(),
+            json!({"customer": {"name": "Ana", "id": 42}, "lines": [{"sku": "A-1"}]}),
+            None,
+        );
+        let template = json!({
+            "DocType": "ORDER",
+            "Customer": "${case.data.customer.id}",
+            "Reference": "ORD-${case.data.customer.id}/${ case.current_phase }",
+            "Note": "${case.data.customer.name} (${case.data.missing})",
+            "FirstSku": "${case.data.lines.0.sku}",
+            "Missing": "${case.data.missing}",
+            "Event": ["${action}", 1, true]
+        });
+
+        let rendered = render(&template, &context("case.moved", &case));
+
+        assert_eq!(rendered["DocType"], "ORDER");
+        assert_eq!(rendered["Customer"], 42);
+        assert_eq!(rendered["Reference"], "ORD-42/Review");
+        assert_eq!(rendered["Note"], "Ana ()");
+        assert_eq!(rendered["FirstSku"], "A-1");
+        assert_eq!(rendered["Missing"], Value::Null);
+        assert_eq!(rendered["Event"], json!(["case.moved", 1, true]));
+    }
+
+    #[test]
+    fn test_unknown_placeholders() {
+        let template = json!({"id": "${case.id}", "lines": [{"qty": "${event.data.qty}"}], "kind": "${action}"});
+
+        assert_eq!(
+            unknown_placeholders(&template),
+            vec![("lines[0].qty".to_string(), "event.data.qty".to_string())]
+        );
+    }
+}
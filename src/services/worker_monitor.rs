@@ -0,0 +1,81 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+/// The background workers started by this process, so the deep health check
+/// can tell whether any of them stopped. Workers loop until shutdown; one
+/// that finished has crashed or given up.
+#[derive(Clone, Default)]
+pub struct WorkerMonitor {
+    workers: Arc<Mutex<Vec<TrackedWorker>>>,
+}
+
+struct TrackedWorker {
+    name: &'static str,
+    region: Option<String>,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WorkerStatus {
+    pub name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub running: bool,
+}
+
+impl WorkerMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks a worker that is not tied to a region.
+    pub fn track(&self, name: &'static str, handle: JoinHandle<()>) {
+        self.push(TrackedWorker { name, region: None, handle });
+    }
+
+    pub fn track_in_region(&self, name: &'static str, region: &str, handle: JoinHandle<()>) {
+        self.push(TrackedWorker { name, region: Some(region.to_string()), handle });
+    }
+
+    fn push(&self, worker: TrackedWorker) {
+        self.workers.lock().expect("worker monitor lock poisoned").push(worker);
+    }
+
+    pub fn statuses(&self) -> Vec<WorkerStatus> {
+        self.workers
+            .lock()
+            .expect("worker monitor lock poisoned")
+            .iter()
+            .map(|worker| WorkerStatus {
+                name: worker.name,
+                region: worker.region.clone(),
+                running: !worker.handle.is_finished(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_finished_workers_are_reported_stopped() {
+        let monitor = WorkerMonitor::new();
+        monitor.track("forever", tokio::spawn(std::future::pending()));
+        let stopped = tokio::spawn(async {});
+        while !stopped.is_finished() {
+            tokio::task::yield_now().await;
+        }
+        monitor.track_in_region("stopped", "eu", stopped);
+
+        let statuses = monitor.statuses();
+        assert!(statuses[0].running);
+        assert_eq!(
+            statuses[1],
+            WorkerStatus { name: "stopped", region: Some("eu".to_string()), running: false }
+        );
+    }
+}
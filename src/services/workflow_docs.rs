@@ -0,0 +1,543 @@
+use serde_json::Value;
+
+use crate::models::automation::{
+    AutomationAction, AutomationTrigger, Condition, LogicalOperator, OnError, PhaseAutomation, SimpleCondition,
+};
+use crate::models::message::MessageChannel;
+use crate::models::Workflow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl DocFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+/// Text may contain `code` spans in backticks; everything else is plain.
+struct ListItem {
+    text: String,
+    children: Vec<ListItem>,
+}
+
+impl ListItem {
+    fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            children: Vec::new(),
+        }
+    }
+}
+
+enum Block {
+    Heading(usize, String),
+    Paragraph(String),
+    List(Vec<ListItem>),
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+}
+
+/// Documentation generated from the workflow definition itself, so it is
+/// always in sync with what the engine does.
+pub fn render_workflow_doc(workflow: &Workflow, format: DocFormat) -> String {
+    let blocks = document(workflow);
+    match format {
+        DocFormat::Markdown => render_markdown(&blocks),
+        DocFormat::Html => render_html(&workflow.name, &blocks),
+    }
+}
+
+fn document(workflow: &Workflow) -> Vec<Block> {
+    let mut blocks = vec![Block::Heading(1, workflow.name.clone())];
+
+    if let Some(description) = workflow.description.as_deref().filter(|d| !d.trim().is_empty()) {
+        blocks.push(Block::Paragraph(description.to_string()));
+    }
+
+    let mut summary = format!(
+        "This workflow is {}. New cases start in `{}`.",
+        if workflow.active { "active" } else { "inactive" },
+        workflow.initial_phase
+    );
+    if let Some(url) = &workflow.webhook_url {
+        summary.push_str(&format!(" Phase changes are posted to `{}`.", url));
+    }
+    blocks.push(Block::Paragraph(summary));
+
+    blocks.push(Block::Heading(2, "Phases".to_string()));
+    blocks.push(Block::List(
+        workflow
+            .phases
+            .iter()
+            .map(|phase| {
+                let mut notes = Vec::new();
+                if phase.name == workflow.initial_phase {
+                    notes.push("initial".to_string());
+                }
+                if phase.terminal {
+                    notes.push("terminal".to_string());
+                }
+                if let Some(limit) = phase.wip_limit {
+                    notes.push(format!("at most {} in progress", plural(limit as u64, "case")));
+                }
+                let mut item = format!("`{}`", phase.name);
+                if !notes.is_empty() {
+                    item.push_str(&format!(" ({})", notes.join(", ")));
+                }
+                if let Some(description) = &phase.description {
+                    item.push_str(&format!(": {}", description));
+                }
+                ListItem::new(item)
+            })
+            .collect(),
+    ));
+
+    let automations: Vec<&PhaseAutomation> = workflow
+        .automations
+        .as_ref()
+        .map(|a| a.automations.iter().collect())
+        .unwrap_or_default();
+
+    blocks.push(Block::Heading(2, "Transitions".to_string()));
+    if workflow.transitions.is_empty() {
+        blocks.push(Block::Paragraph(
+            "Cases can be moved to any phase of the workflow through the API.".to_string(),
+        ));
+    } else {
+        blocks.push(Block::Paragraph(
+            "Cases can be moved through the API as follows; phases not listed can move to any phase.".to_string(),
+        ));
+        blocks.push(Block::List(
+            workflow
+                .phases
+                .iter()
+                .filter_map(|phase| {
+                    let allowed = workflow.allowed_transitions(&phase.name)?;
+                    Some(ListItem::new(if allowed.is_empty() {
+                        format!("From `{}`: nowhere", phase.name)
+                    } else {
+                        let targets: Vec<String> = allowed.iter().map(|to| format!("`{}`", to)).collect();
+                        format!("From `{}` to {}", phase.name, targets.join(", "))
+                    }))
+                })
+                .collect(),
+        ));
+    }
+    let mut transitions = Vec::new();
+    for automation in &automations {
+        collect_transitions(automation, &automation.actions, &mut Vec::new(), &mut transitions);
+    }
+    if transitions.is_empty() {
+        blocks.push(Block::Paragraph("No automation moves cases between phases.".to_string()));
+    } else {
+        blocks.push(Block::Paragraph("Automations move cases as follows:".to_string()));
+        blocks.push(Block::List(transitions));
+    }
+
+    blocks.push(Block::Heading(2, "Automations".to_string()));
+    if automations.is_empty() {
+        blocks.push(Block::Paragraph("This workflow has no automations.".to_string()));
+    } else if let Some(run_as) = workflow.automations.as_ref().and_then(|a| a.run_as.as_deref()) {
+        blocks.push(Block::Paragraph(format!("Automations run as the service account `{}`.", run_as)));
+    }
+    for automation in sorted_by_phase(workflow, &automations) {
+        blocks.push(Block::Heading(3, trigger_heading(automation)));
+        blocks.push(Block::List(automation.actions.iter().map(describe_action).collect()));
+    }
+
+    blocks.push(Block::Heading(2, "SLAs".to_string()));
+    match workflow.sla_config.as_ref().filter(|sla| !sla.phase_slas.is_empty()) {
+        Some(sla) => {
+            let rows = workflow
+                .phases
+                .iter()
+                .filter_map(|phase| {
+                    let target = sla.phase_slas.get(&phase.name)?;
+                    Some(vec![format!("`{}`", phase.name), plural(target.hours as u64, "hour")])
+                })
+                .collect();
+            blocks.push(Block::Table(vec!["Phase", "Target time in phase"], rows));
+        }
+        None => blocks.push(Block::Paragraph("No SLAs are configured.".to_string())),
+    }
+
+    let limits = &workflow.execution_limits;
+    blocks.push(Block::Heading(2, "Limits".to_string()));
+    blocks.push(Block::Table(
+        vec!["Limit", "Value"],
+        vec![
+            vec!["Actions per automation run".to_string(), limits.max_actions_per_run.to_string()],
+            vec!["Total delay per run".to_string(), duration(limits.max_total_delay_ms)],
+            vec!["Webhook body size".to_string(), format!("{} bytes", limits.max_webhook_body_bytes)],
+        ],
+    ));
+
+    blocks.push(Block::Paragraph(format!(
+        "Generated from the workflow definition last updated {}.",
+        workflow.updated_at.format("%Y-%m-%d %H:%M UTC")
+    )));
+
+    blocks
+}
+
+/// Automations in phase order, on_enter before on_reply before on_exit.
+fn sorted_by_phase<'a>(workflow: &Workflow, automations: &[&'a PhaseAutomation]) -> Vec<&'a PhaseAutomation> {
+    let phase_index = |phase: &str| workflow.phase_index(phase).unwrap_or(usize::MAX);
+    let trigger_index = |trigger: &AutomationTrigger| match trigger {
+        AutomationTrigger::OnEnter => 0,
+        AutomationTrigger::OnReply => 1,
+        AutomationTrigger::OnExit => 2,
+    };
+
+    let mut sorted = automations.to_vec();
+    sorted.sort_by_key(|a| (phase_index(&a.phase), trigger_index(&a.trigger)));
+    sorted
+}
+
+fn trigger_heading(automation: &PhaseAutomation) -> String {
+    let when = match automation.trigger {
+        AutomationTrigger::OnEnter => "When a case enters",
+        AutomationTrigger::OnExit => "When a case leaves",
+        AutomationTrigger::OnReply => "When a reply arrives for a case in",
+    };
+    format!("{} `{}`", when, automation.phase)
+}
+
+fn collect_transitions(
+    automation: &PhaseAutomation,
+    actions: &[AutomationAction],
+    conditions: &mut Vec<String>,
+    out: &mut Vec<ListItem>,
+) {
+    for action in actions {
+        match action {
+            AutomationAction::MoveToPhase { phase, .. } => {
+                let mut text = format!("{}: moved to `{}`", trigger_heading(automation), phase);
+                if !conditions.is_empty() {
+                    text.push_str(&format!(" if {}", conditions.join(" and ")));
+                }
+                out.push(ListItem::new(text));
+            }
+            AutomationAction::Conditional {
+                condition, then, r#else, ..
+            } => {
+                conditions.push(describe_condition(condition));
+                collect_transitions(automation, then, conditions, out);
+                conditions.pop();
+
+                if let Some(otherwise) = r#else {
+                    conditions.push(format!("not ({})", describe_condition(condition)));
+                    collect_transitions(automation, otherwise, conditions, out);
+                    conditions.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn describe_action(action: &AutomationAction) -> ListItem {
+    let mut item = match action {
+        AutomationAction::Webhook { method, url, .. } => ListItem::new(format!(
+            "Call `{} {}`",
+            method.as_deref().unwrap_or("POST").to_uppercase(),
+            url
+        )),
+        AutomationAction::Delay { duration_ms, .. } => ListItem::new(format!("Wait {}", duration(*duration_ms))),
+        AutomationAction::Conditional {
+            condition, then, r#else, ..
+        } => {
+            let mut item = ListItem::new(format!("If {}:", describe_condition(condition)));
+            item.children = then.iter().map(describe_action).collect();
+            if let Some(otherwise) = r#else {
+                let mut otherwise_item = ListItem::new("Otherwise:");
+                otherwise_item.children = otherwise.iter().map(describe_action).collect();
+                item.children.push(otherwise_item);
+            }
+            item
+        }
+        AutomationAction::MoveToPhase { phase, .. } => ListItem::new(format!("Move the case to `{}`", phase)),
+        AutomationAction::AddTag { tag, .. } => ListItem::new(format!("Tag the case `{}`", tag)),
+        AutomationAction::SetStatus { status, .. } => {
+            ListItem::new(format!("Mark the case as `{}`", status.as_str()))
+        }
+        AutomationAction::SetField { field, value, .. } => {
+            ListItem::new(format!("Set {} to {}", describe_field(field), describe_value(value)))
+        }
+        AutomationAction::SendMessage {
+            channel, to, subject, ..
+        } => {
+            let kind = match channel {
+                MessageChannel::Email => "an email",
+                MessageChannel::Whatsapp => "a WhatsApp message",
+                MessageChannel::Slack => "a Slack message",
+                MessageChannel::Portal => "a portal message",
+                MessageChannel::Sms => "an SMS",
+            };
+            let mut text = format!("Send {} to `{}`", kind, to);
+            if let Some(subject) = subject {
+                text.push_str(&format!(" with subject \"{}\"", subject));
+            }
+            ListItem::new(text)
+        }
+    };
+
+    if let Some(name) = action.name() {
+        item.text = format!("{}: {}", name, item.text);
+    }
+    let can_fail = matches!(action, AutomationAction::Webhook { .. } | AutomationAction::SendMessage { .. });
+    if can_fail && action.on_error() == OnError::Continue {
+        item.text.push_str(" (failures are ignored)");
+    }
+
+    item
+}
+
+fn describe_condition(condition: &Condition) -> String {
+    match condition {
+        Condition::Simple { field, operator, value } => describe_comparison(field, operator, value),
+        Condition::Complex { operator, conditions } => {
+            let joiner = match operator {
+                LogicalOperator::And => " and ",
+                LogicalOperator::Or => " or ",
+            };
+            conditions
+                .iter()
+                .map(|SimpleCondition { field, operator, value }| describe_comparison(field, operator, value))
+                .collect::<Vec<_>>()
+                .join(joiner)
+        }
+    }
+}
+
+fn describe_comparison(field: &str, operator: &str, value: &Value) -> String {
+    let relation = match operator {
+        "==" | "=" => "is",
+        "!=" => "is not",
+        ">" => "is greater than",
+        ">=" => "is at least",
+        "<" => "is less than",
+        "<=" => "is at most",
+        "contains" => "contains",
+        other => other,
+    };
+    format!("{} {} {}", describe_field(field), relation, describe_value(value))
+}
+
+fn describe_field(field: &str) -> String {
+    match field {
+        "current_phase" => "the current phase".to_string(),
+        "previous_phase" => "the previous phase".to_string(),
+        "status" => "the case status".to_string(),
+        "tags" => "the case tags".to_string(),
+        _ => format!("`{}`", field.strip_prefix("data.").unwrap_or(field)),
+    }
+}
+
+fn describe_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        other => format!("`{}`", other),
+    }
+}
+
+fn duration(ms: u64) -> String {
+    const MINUTE: u64 = 60_000;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+
+    match ms {
+        ms if ms >= DAY && ms % DAY == 0 => plural(ms / DAY, "day"),
+        ms if ms >= HOUR && ms % HOUR == 0 => plural(ms / HOUR, "hour"),
+        ms if ms >= MINUTE && ms % MINUTE == 0 => plural(ms / MINUTE, "minute"),
+        ms if ms >= 1000 && ms % 1000 == 0 => plural(ms / 1000, "second"),
+        ms => format!("{} ms", ms),
+    }
+}
+
+fn plural(count: u64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+fn render_markdown(blocks: &[Block]) -> String {
+    let mut out = String::new();
+
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => out.push_str(&format!("{} {}\n\n", "#".repeat(*level), text)),
+            Block::Paragraph(text) => out.push_str(&format!("{}\n\n", text)),
+            Block::List(items) => {
+                markdown_list(items, 0, &mut out);
+                out.push('\n');
+            }
+            Block::Table(headers, rows) => {
+                out.push_str(&format!("| {} |\n", headers.join(" | ")));
+                out.push_str(&format!("|{}\n", "---|".repeat(headers.len())));
+                for row in rows {
+                    let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+                    out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                }
+                out.push('\n');
+            }
+        }
+    }
+
+    out.trim_end().to_string() + "\n"
+}
+
+fn markdown_list(items: &[ListItem], depth: usize, out: &mut String) {
+    for item in items {
+        out.push_str(&format!("{}- {}\n", "  ".repeat(depth), item.text));
+        markdown_list(&item.children, depth + 1, out);
+    }
+}
+
+fn render_html(title: &str, blocks: &[Block]) -> String {
+    let mut body = String::new();
+
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => body.push_str(&format!("<h{0}>{1}</h{0}>\n", level, inline_html(text))),
+            Block::Paragraph(text) => body.push_str(&format!("<p>{}</p>\n", inline_html(text))),
+            Block::List(items) => html_list(items, &mut body),
+            Block::Table(headers, rows) => {
+                body.push_str("<table>\n<tr>");
+                for header in headers {
+                    body.push_str(&format!("<th>{}</th>", escape_html(header)));
+                }
+                body.push_str("</tr>\n");
+                for row in rows {
+                    body.push_str("<tr>");
+                    for cell in row {
+                        body.push_str(&format!("<td>{}</td>", inline_html(cell)));
+                    }
+                    body.push_str("</tr>\n");
+                }
+                body.push_str("</table>\n");
+            }
+        }
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"UTF-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Arial, sans-serif; max-width: 860px; margin: 32px auto; padding: 0 16px; color: #1a202c; }}\n\
+         code {{ background: #edf2f7; padding: 1px 4px; border-radius: 3px; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #e2e8f0; padding: 6px 12px; text-align: left; }}\n\
+         </style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        body
+    )
+}
+
+fn html_list(items: &[ListItem], out: &mut String) {
+    out.push_str("<ul>\n");
+    for item in items {
+        out.push_str(&format!("<li>{}", inline_html(&item.text)));
+        if !item.children.is_empty() {
+            out.push('\n');
+            html_list(&item.children, out);
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+/// Escapes `text` and turns backtick spans into `<code>`.
+fn inline_html(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<code>{}</code>", escape_html(part))
+            } else {
+                escape_html(part)
+            }
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::workflow::CreateWorkflow;
+    use serde_json::json;
+
+    fn workflow() -> Workflow {
+        let create: CreateWorkflow = serde_json::from_value(json!({
+            "name": "Sales <pipeline>",
+            "phases": ["Lead", "Qualified", "Won"],
+            "initial_phase": "Lead",
+            "automations": {"automations": [{
+                "trigger": "on_enter",
+                "phase": "Lead",
+                "actions": [
+                    {"type": "delay", "duration_ms": 300000},
+                    {
+                        "type": "conditional",
+                        "field": "data.amount",
+                        "operator": ">=",
+                        "value": 1000,
+                        "then": [{"type": "move_to_phase", "phase": "Qualified"}],
+                        "else": [{"type": "set_field", "field": "data.tier", "value": "small"}]
+                    },
+                    {"type": "webhook", "name": "Notify CRM", "url": "https://crm.example.com/hook", "on_error": "continue"}
+                ]
+            }]},
+            "sla_config": {"Qualified": {"hours": 48}},
+            "transitions": {"Qualified": ["Won"]}
+        }))
+        .unwrap();
+        Workflow::new(create).unwrap()
+    }
+
+    #[test]
+    fn test_markdown_doc() {
+        let doc = render_workflow_doc(&workflow(), DocFormat::Markdown);
+
+        assert!(doc.starts_with("# Sales <pipeline>\n"));
+        assert!(doc.contains("- `Lead` (initial)\n"));
+        assert!(doc.contains("- From `Qualified` to `Won`\n"));
+        assert!(doc.contains("- When a case enters `Lead`: moved to `Qualified` if `amount` is at least `1000`\n"));
+        assert!(doc.contains("### When a case enters `Lead`\n"));
+        assert!(doc.contains("- Wait 5 minutes\n"));
+        assert!(doc.contains("- If `amount` is at least `1000`:\n  - Move the case to `Qualified`\n  - Otherwise:\n    - Set `tier` to \"small\"\n"));
+        assert!(doc.contains("- Notify CRM: Call `POST https://crm.example.com/hook` (failures are ignored)\n"));
+        assert!(doc.contains("| `Qualified` | 48 hours |\n"));
+    }
+
+    #[test]
+    fn test_html_doc_escapes() {
+        let doc = render_workflow_doc(&workflow(), DocFormat::Html);
+
+        assert!(doc.contains("<h1>Sales &lt;pipeline&gt;</h1>"));
+        assert!(doc.contains("<li><code>Lead</code> (initial)</li>"));
+        assert!(doc.contains("<td><code>Qualified</code></td><td>48 hours</td>"));
+        assert_eq!(DocFormat::parse("MD"), Some(DocFormat::Markdown));
+    }
+}
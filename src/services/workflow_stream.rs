@@ -0,0 +1,77 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::models::Workflow;
+
+/// Changes a subscriber may fall behind by before it misses some.
+const DEFAULT_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum WorkflowChangeKind {
+    #[serde(rename = "workflow.created")]
+    Created,
+    #[serde(rename = "workflow.updated")]
+    Updated,
+    #[serde(rename = "workflow.deleted")]
+    Deleted,
+}
+
+/// A workflow definition as a handler left it; `None` once deleted.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowChange {
+    #[serde(rename = "type")]
+    pub kind: WorkflowChangeKind,
+    pub region: String,
+    pub workflow_id: Uuid,
+    pub workflow: Option<Workflow>,
+}
+
+/// Fans workflow changes out to the dashboards connected to `/ws` on this
+/// instance, like [`CaseStream`](super::CaseStream) does for cases.
+#[derive(Clone)]
+pub struct WorkflowStream {
+    sender: broadcast::Sender<WorkflowChange>,
+}
+
+impl WorkflowStream {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(DEFAULT_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, kind: WorkflowChangeKind, region: &str, workflow: &Workflow) {
+        self.send(WorkflowChange {
+            kind,
+            region: region.to_string(),
+            workflow_id: workflow.id,
+            workflow: Some(workflow.clone()),
+        });
+    }
+
+    pub fn publish_deleted(&self, region: &str, workflow_id: Uuid) {
+        self.send(WorkflowChange {
+            kind: WorkflowChangeKind::Deleted,
+            region: region.to_string(),
+            workflow_id,
+            workflow: None,
+        });
+    }
+
+    fn send(&self, change: WorkflowChange) {
+        if self.sender.receiver_count() > 0 {
+            // Only fails when the last subscriber left in the meantime.
+            let _ = self.sender.send(change);
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WorkflowChange> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for WorkflowStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
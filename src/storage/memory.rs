@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::{CaseStore, ExecutionStore};
+use crate::models::case::{Case, CaseHistory, FieldProvenance};
+use crate::models::conflict::{DataConflictPolicy, FieldWrite};
+use crate::models::execution::Execution;
+use crate::models::message::CaseMessage;
+
+#[derive(Default)]
+struct State {
+    cases: HashMap<Uuid, Case>,
+    history: Vec<CaseHistory>,
+    messages: Vec<CaseMessage>,
+    executions: HashMap<Uuid, Execution>,
+}
+
+/// Keeps cases, history, messages and executions in memory, for testing
+/// the engine without a database. Writes apply at once, so there is no
+/// commit or rollback. Clones share the same data.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    state: Arc<Mutex<State>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("memory store lock poisoned")
+    }
+
+    pub fn insert_case(&self, case: Case) {
+        self.state().cases.insert(case.id, case);
+    }
+
+    pub fn case(&self, case_id: Uuid) -> Option<Case> {
+        self.state().cases.get(&case_id).cloned()
+    }
+
+    /// The case's history, oldest first.
+    pub fn history(&self, case_id: Uuid) -> Vec<CaseHistory> {
+        self.state().history.iter().filter(|entry| entry.case_id == case_id).cloned().collect()
+    }
+
+    pub fn messages(&self, case_id: Uuid) -> Vec<CaseMessage> {
+        self.state().messages.iter().filter(|message| message.case_id == case_id).cloned().collect()
+    }
+
+    pub fn execution(&self, id: Uuid) -> Option<Execution> {
+        self.state().executions.get(&id).cloned()
+    }
+
+    fn with_case<T>(&self, case_id: Uuid, update: impl FnOnce(&mut Case) -> T) -> Result<T> {
+        let mut state = self.state();
+        let case = state
+            .cases
+            .get_mut(&case_id)
+            .ok_or_else(|| anyhow::anyhow!("Case {} not found", case_id))?;
+        Ok(update(case))
+    }
+}
+
+#[async_trait]
+impl CaseStore for MemoryStore {
+    async fn find_case(&mut self, case_id: Uuid) -> Result<Option<Case>> {
+        Ok(self.case(case_id))
+    }
+
+    async fn lock_phase_wip(&mut self, workflow_id: Uuid, phase: &str) -> Result<i64> {
+        let in_progress = self
+            .state()
+            .cases
+            .values()
+            .filter(|case| case.workflow_id == workflow_id && case.current_phase == phase)
+            .filter(|case| case.deleted_at.is_none() && case.status.is_in_progress())
+            .count();
+
+        Ok(in_progress as i64)
+    }
+
+    async fn move_case(&mut self, case_id: Uuid, from_phase: &str, to_phase: &str) -> Result<()> {
+        self.with_case(case_id, |case| {
+            let now = Utc::now();
+            case.previous_phase = Some(from_phase.to_string());
+            case.current_phase = to_phase.to_string();
+            case.phase_entered_at = now;
+            case.updated_at = now;
+        })
+    }
+
+    async fn update_status(&mut self, updated: &Case) -> Result<()> {
+        self.with_case(updated.id, |case| {
+            case.status = updated.status.clone();
+            case.completed_at = updated.completed_at;
+            case.updated_at = Utc::now();
+        })
+    }
+
+    async fn set_field(
+        &mut self,
+        case_id: Uuid,
+        field: &str,
+        value: &Value,
+        writer: &FieldProvenance,
+        policy: &DataConflictPolicy,
+    ) -> Result<FieldWrite> {
+        self.with_case(case_id, |case| case.write_field(field, value, writer, policy))
+    }
+
+    async fn add_tag(&mut self, case_id: Uuid, tag: &str, max_tags: usize) -> Result<bool> {
+        self.with_case(case_id, |case| case.add_tag(tag, max_tags))
+    }
+
+    async fn record_message(&mut self, message: &CaseMessage) -> Result<()> {
+        self.state().messages.push(message.clone());
+        Ok(())
+    }
+
+    async fn create_history(&mut self, history: &CaseHistory) -> Result<()> {
+        self.state().history.push(history.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExecutionStore for MemoryStore {
+    async fn create_execution(&self, execution: &Execution) -> Result<()> {
+        self.state().executions.insert(execution.id, execution.clone());
+        Ok(())
+    }
+
+    async fn update_execution(&self, execution: &Execution) -> Result<()> {
+        match self.state().executions.get_mut(&execution.id) {
+            Some(stored) => *stored = execution.clone(),
+            None => anyhow::bail!("Execution {} not found", execution.id),
+        }
+        Ok(())
+    }
+
+    async fn find_execution(&self, id: Uuid) -> Result<Option<Execution>> {
+        Ok(self.execution(id))
+    }
+}
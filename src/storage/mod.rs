@@ -0,0 +1,66 @@
+//! Storage seams for the engine. Case modifications and executions go
+//! through these traits, so the engine paths can run against Postgres or,
+//! with the `memory-store` feature, an in-memory store in tests.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::case::{Case, CaseHistory, FieldProvenance};
+use crate::models::conflict::{DataConflictPolicy, FieldWrite};
+use crate::models::execution::Execution;
+use crate::models::message::CaseMessage;
+
+#[cfg(any(test, feature = "memory-store"))]
+pub mod memory;
+pub mod postgres;
+
+#[cfg(any(test, feature = "memory-store"))]
+pub use memory::MemoryStore;
+pub use postgres::PgCaseStore;
+
+/// Writes to cases within one unit of work, a transaction for Postgres.
+#[async_trait]
+pub trait CaseStore: Send {
+    /// Reads the case and holds it until the unit of work ends, so that
+    /// concurrent transitions of one case run one after the other.
+    async fn find_case(&mut self, case_id: Uuid) -> Result<Option<Case>>;
+
+    /// Serializes moves into `phase` of `workflow_id` until the unit of
+    /// work ends and returns how many cases are in progress there.
+    async fn lock_phase_wip(&mut self, workflow_id: Uuid, phase: &str) -> Result<i64>;
+
+    async fn move_case(&mut self, case_id: Uuid, from_phase: &str, to_phase: &str) -> Result<()>;
+
+    /// Stores the case's `status` and `completed_at`.
+    async fn update_status(&mut self, case: &Case) -> Result<()>;
+
+    /// Writes a top-level `data` field under `policy`; see
+    /// [`DataConflictPolicy::resolve`].
+    async fn set_field(
+        &mut self,
+        case_id: Uuid,
+        field: &str,
+        value: &Value,
+        writer: &FieldProvenance,
+        policy: &DataConflictPolicy,
+    ) -> Result<FieldWrite>;
+
+    /// Adds `tag` unless the case already has it or has `max_tags` tags.
+    /// Returns whether the tag was added.
+    async fn add_tag(&mut self, case_id: Uuid, tag: &str, max_tags: usize) -> Result<bool>;
+
+    async fn record_message(&mut self, message: &CaseMessage) -> Result<()>;
+
+    async fn create_history(&mut self, history: &CaseHistory) -> Result<()>;
+}
+
+#[async_trait]
+pub trait ExecutionStore: Send + Sync {
+    async fn create_execution(&self, execution: &Execution) -> Result<()>;
+
+    async fn update_execution(&self, execution: &Execution) -> Result<()>;
+
+    async fn find_execution(&self, id: Uuid) -> Result<Option<Execution>>;
+}
@@ -0,0 +1,208 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{CaseStore, ExecutionStore};
+use crate::models::automation::AutomationRun;
+use crate::models::case::{Case, CaseHistory, FieldProvenance};
+use crate::models::conflict::{DataConflictPolicy, FieldWrite};
+use crate::models::execution::Execution;
+use crate::models::message::CaseMessage;
+use crate::models::outbox::OutboxMessage;
+use crate::models::phase::PhaseMove;
+use crate::models::service_account::ServiceAccount;
+use crate::models::snapshot::DefinitionSnapshot;
+use crate::repositories::automation_run_repository::create_run_in;
+use crate::repositories::case_repository::{create_history_in, create_in, lock_phase_wip, move_phase_in, set_field_in};
+use crate::repositories::definition_snapshot_repository::save_snapshot_in;
+use crate::repositories::outbox_repository::enqueue_in;
+use crate::repositories::service_account_repository::find_service_account_in;
+use crate::repositories::ExecutionRepository;
+
+/// A [`CaseStore`] over one Postgres transaction; nothing is visible to
+/// others until [`commit`](Self::commit).
+pub struct PgCaseStore<'c> {
+    tx: Transaction<'c, Postgres>,
+    failed: bool,
+}
+
+impl PgCaseStore<'static> {
+    pub async fn begin(pool: &PgPool) -> Result<Self> {
+        Ok(Self { tx: pool.begin().await?, failed: false })
+    }
+}
+
+impl PgCaseStore<'_> {
+    /// Fails without committing anything if a write failed before, even
+    /// one the caller logged and went on from: Postgres aborts the whole
+    /// transaction on an error and turns its commit into a rollback.
+    pub async fn commit(self) -> Result<()> {
+        if self.failed {
+            self.tx.rollback().await?;
+            anyhow::bail!("a write failed, so the transaction was rolled back");
+        }
+        Ok(self.tx.commit().await?)
+    }
+
+    fn track<T>(&mut self, result: Result<T>) -> Result<T> {
+        self.failed |= result.is_err();
+        result
+    }
+
+    pub async fn create_case(&mut self, case: &Case) -> Result<()> {
+        let result = create_in(&mut self.tx, case).await;
+        self.track(result)
+    }
+
+    /// Queues events and webhooks to deliver once the transaction commits.
+    pub async fn enqueue(&mut self, messages: &[OutboxMessage]) -> Result<()> {
+        let result = enqueue_in(&mut self.tx, messages).await.map(|_| ());
+        self.track(result)
+    }
+
+    /// See [`CaseRepository::update_phase_within_limit`]; the move commits
+    /// with the rest of the unit of work.
+    ///
+    /// [`CaseRepository::update_phase_within_limit`]: crate::repositories::CaseRepository::update_phase_within_limit
+    pub async fn move_case_within_limit(
+        &mut self,
+        case_id: Uuid,
+        workflow_id: Uuid,
+        to_phase: &str,
+        from_phase: Option<&str>,
+        wip_limit: Option<u32>,
+        expected_version: Option<i32>,
+    ) -> Result<PhaseMove> {
+        let result =
+            move_phase_in(&mut self.tx, case_id, workflow_id, to_phase, from_phase, wip_limit, expected_version).await;
+        self.track(result)
+    }
+
+    pub async fn record_automation_run(&mut self, run: &AutomationRun) -> Result<()> {
+        let result = create_run_in(&mut self.tx, run).await;
+        self.track(result)
+    }
+
+    pub async fn save_snapshot(&mut self, snapshot: &DefinitionSnapshot) -> Result<()> {
+        let result = save_snapshot_in(&mut self.tx, snapshot).await;
+        self.track(result)
+    }
+
+    pub async fn find_service_account(&mut self, name: &str) -> Result<Option<ServiceAccount>> {
+        let result = find_service_account_in(&mut self.tx, name).await;
+        self.track(result)
+    }
+}
+
+#[async_trait]
+impl CaseStore for PgCaseStore<'_> {
+    async fn find_case(&mut self, case_id: Uuid) -> Result<Option<Case>> {
+        let result = sqlx::query_as::<_, Case>("SELECT * FROM orchepy_cases WHERE id = $1 FOR UPDATE")
+            .bind(case_id)
+            .fetch_optional(&mut *self.tx)
+            .await;
+
+        self.track(result.map_err(Into::into))
+    }
+
+    async fn lock_phase_wip(&mut self, workflow_id: Uuid, phase: &str) -> Result<i64> {
+        let result = lock_phase_wip(&mut self.tx, workflow_id, phase).await;
+        self.track(result)
+    }
+
+    async fn move_case(&mut self, case_id: Uuid, from_phase: &str, to_phase: &str) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE orchepy_cases SET current_phase = $1, previous_phase = $2, phase_entered_at = NOW(), updated_at = NOW() WHERE id = $3"
+        )
+        .bind(to_phase)
+        .bind(from_phase)
+        .bind(case_id)
+        .execute(&mut *self.tx)
+        .await;
+
+        self.track(result.map(|_| ()).map_err(Into::into))
+    }
+
+    async fn update_status(&mut self, case: &Case) -> Result<()> {
+        let result = sqlx::query("UPDATE orchepy_cases SET status = $1, completed_at = $2, updated_at = NOW() WHERE id = $3")
+            .bind(&case.status)
+            .bind(case.completed_at)
+            .bind(case.id)
+            .execute(&mut *self.tx)
+            .await;
+
+        self.track(result.map(|_| ()).map_err(Into::into))
+    }
+
+    async fn set_field(
+        &mut self,
+        case_id: Uuid,
+        field: &str,
+        value: &Value,
+        writer: &FieldProvenance,
+        policy: &DataConflictPolicy,
+    ) -> Result<FieldWrite> {
+        let result = set_field_in(&mut self.tx, case_id, field, value, writer, policy).await;
+        self.track(result)
+    }
+
+    async fn add_tag(&mut self, case_id: Uuid, tag: &str, max_tags: usize) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE orchepy_cases SET tags = array_append(tags, $1), updated_at = NOW()
+             WHERE id = $2 AND NOT (tags @> ARRAY[$1]) AND cardinality(tags) < $3"
+        )
+        .bind(tag)
+        .bind(case_id)
+        .bind(max_tags as i32)
+        .execute(&mut *self.tx)
+        .await;
+
+        self.track(result.map(|result| result.rows_affected() > 0).map_err(Into::into))
+    }
+
+    async fn record_message(&mut self, message: &CaseMessage) -> Result<()> {
+        let result = sqlx::query(
+            "INSERT INTO orchepy_case_messages (id, case_id, direction, channel, sender, recipient, subject, body, external_id, reply_token, public, attachments, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"
+        )
+        .bind(message.id)
+        .bind(message.case_id)
+        .bind(message.direction)
+        .bind(message.channel)
+        .bind(&message.sender)
+        .bind(&message.recipient)
+        .bind(&message.subject)
+        .bind(&message.body)
+        .bind(&message.external_id)
+        .bind(&message.reply_token)
+        .bind(message.public)
+        .bind(sqlx::types::Json(&message.attachments))
+        .bind(message.created_at)
+        .execute(&mut *self.tx)
+        .await;
+
+        self.track(result.map(|_| ()).map_err(Into::into))
+    }
+
+    async fn create_history(&mut self, history: &CaseHistory) -> Result<()> {
+        let result = create_history_in(&mut self.tx, history).await;
+        self.track(result)
+    }
+}
+
+#[async_trait]
+impl ExecutionStore for ExecutionRepository<'_> {
+    async fn create_execution(&self, execution: &Execution) -> Result<()> {
+        self.create(execution).await
+    }
+
+    async fn update_execution(&self, execution: &Execution) -> Result<()> {
+        self.update(execution).await
+    }
+
+    async fn find_execution(&self, id: Uuid) -> Result<Option<Execution>> {
+        self.find_by_id(id).await
+    }
+}
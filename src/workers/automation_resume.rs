@@ -0,0 +1,132 @@
+use std::time::Duration;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+use crate::api::cases::resume_deferred_automation;
+use crate::models::automation::{AutomationTrigger, DeferredAutomation, PhaseAutomation};
+use crate::repositories::{CaseRepository, DeferredAutomationRepository, WorkflowRepository};
+use crate::services::clock::SharedClock;
+use crate::services::secrets::SecretCipher;
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+
+const CLAIM_BATCH_SIZE: i64 = 50;
+/// Longer than a resumed run normally takes, so it isn't started twice
+/// while the first one is still going.
+const LEASE: Duration = Duration::from_secs(300);
+/// A deferred automation whose run keeps failing is dropped after this
+/// many claims.
+const MAX_RESUME_ATTEMPTS: i32 = 5;
+/// Wait before a failed run is claimed again, multiplied by the attempt.
+const RETRY_BACKOFF_SECS: i64 = 60;
+
+/// Runs the actions left over by automations that hit a delay longer than
+/// the inline limit, once that delay has elapsed.
+pub fn spawn_automation_resume_worker(
+    pool: PgPool,
+    secrets: Option<SecretCipher>,
+    shedder: LoadShedder,
+    clock: SharedClock,
+    poll_interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Sla, "automation resume").await;
+
+            let repo = DeferredAutomationRepository::new(&pool);
+            let due = match repo.claim_due(clock.now(), CLAIM_BATCH_SIZE, LEASE).await {
+                Ok(due) => due,
+                Err(err) => {
+                    error!("Failed to claim deferred automations: {}", err);
+                    continue;
+                }
+            };
+
+            for deferred in due {
+                let (id, attempts) = (deferred.id, deferred.attempts);
+                let settled = match resume_automation(&pool, secrets.as_ref(), deferred).await {
+                    Ok(()) => repo.delete(id).await,
+                    Err(err) if attempts >= MAX_RESUME_ATTEMPTS => {
+                        error!("Dropping deferred automation {} after {} attempts: {}", id, attempts, err);
+                        repo.delete(id).await
+                    }
+                    Err(err) => {
+                        warn!("Failed to resume deferred automation {} (attempt {}): {}", id, attempts, err);
+                        let retry_at = clock.now() + chrono::Duration::seconds(RETRY_BACKOFF_SECS * attempts as i64);
+                        repo.release(id, retry_at).await
+                    }
+                };
+                if let Err(err) = settled {
+                    error!("Failed to settle deferred automation {}: {}", id, err);
+                }
+            }
+        }
+    })
+}
+
+async fn resume_automation(
+    pool: &PgPool,
+    secrets: Option<&SecretCipher>,
+    deferred: DeferredAutomation,
+) -> anyhow::Result<()> {
+    run_deferred(pool, secrets, deferred, true).await
+}
+
+/// Runs the actions an automation handed over to the outbox when it
+/// reached a webhook, message or delay inside a case transaction. Unlike
+/// after a long delay, they run even if the case has left the phase since,
+/// as they would have inline.
+pub(crate) async fn continue_automation(
+    pool: &PgPool,
+    secrets: Option<&SecretCipher>,
+    automation: DeferredAutomation,
+) -> anyhow::Result<()> {
+    run_deferred(pool, secrets, automation, false).await
+}
+
+async fn run_deferred(
+    pool: &PgPool,
+    secrets: Option<&SecretCipher>,
+    deferred: DeferredAutomation,
+    in_phase_only: bool,
+) -> anyhow::Result<()> {
+    let case = CaseRepository::new(pool).find_by_id(deferred.case_id).await?;
+    let workflow = WorkflowRepository::new(pool).find_by_id(deferred.workflow_id).await?;
+
+    let (Some(case), Some(workflow)) = (case, workflow) else {
+        warn!("Deferred automation {} dropped: case or workflow no longer exists", deferred.id);
+        return Ok(());
+    };
+
+    let trigger = match deferred.trigger.as_str() {
+        "on_exit" => AutomationTrigger::OnExit,
+        "on_reply" => AutomationTrigger::OnReply,
+        _ => AutomationTrigger::OnEnter,
+    };
+
+    if in_phase_only && trigger != AutomationTrigger::OnExit && case.current_phase != deferred.phase {
+        info!(
+            "Deferred automation {} skipped: case {} left phase '{}'",
+            deferred.id, case.id, deferred.phase
+        );
+        return Ok(());
+    }
+
+    let automation = PhaseAutomation {
+        trigger,
+        phase: deferred.phase.clone(),
+        actions: deferred.actions.clone(),
+    };
+
+    if let Err((status, body)) = resume_deferred_automation(pool, &automation, &case, &workflow, &deferred, secrets).await {
+        return Err(anyhow::anyhow!("{}: {}", status, body.0));
+    }
+
+    info!("Resumed deferred {} automation for case {}", deferred.trigger, case.id);
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::api::cases::retry_automation_run;
+use crate::models::automation::{AutomationRun, AutomationTrigger};
+use crate::models::case::CaseStatus;
+use crate::repositories::{AutomationRunRepository, CaseRepository, WorkflowRepository};
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+use crate::services::secrets::SecretCipher;
+
+const CLAIM_BATCH_SIZE: i64 = 50;
+
+#[derive(Debug, Clone)]
+pub struct AutomationRetryConfig {
+    pub poll_interval: Duration,
+    /// Attempts per run, counting the original one.
+    pub max_attempts: i32,
+    /// Wait before a retry, multiplied by the attempt number.
+    pub backoff: Duration,
+    /// Runs that failed longer ago than this are not retried.
+    pub window: Duration,
+}
+
+impl AutomationRetryConfig {
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|s| s.parse().ok())
+        }
+
+        Self {
+            poll_interval: Duration::from_secs(var("AUTOMATION_RETRY_POLL_SECS").filter(|secs| *secs > 0).unwrap_or(60)),
+            max_attempts: var("AUTOMATION_RETRY_MAX_ATTEMPTS").unwrap_or(3),
+            backoff: Duration::from_secs(var("AUTOMATION_RETRY_BACKOFF_SECS").unwrap_or(60)),
+            window: Duration::from_secs(var::<u64>("AUTOMATION_RETRY_WINDOW_HOURS").unwrap_or(24) * 3600),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.max_attempts > 1
+    }
+}
+
+/// Re-runs automations whose run failed on a transient error (a timeout,
+/// connection error or 5xx/429 from a webhook), up to `max_attempts`.
+pub fn spawn_automation_retry_worker(
+    pool: PgPool,
+    secrets: Option<SecretCipher>,
+    shedder: LoadShedder,
+    config: AutomationRetryConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Sla, "automation retry").await;
+
+            if let Err(err) = retry_failed_runs(&pool, secrets.as_ref(), &config).await {
+                error!("Failed to claim automation runs for retry: {}", err);
+            }
+        }
+    })
+}
+
+/// One pass of the retry worker. Returns the number of runs retried.
+pub async fn retry_failed_runs(
+    pool: &PgPool,
+    secrets: Option<&SecretCipher>,
+    config: &AutomationRetryConfig,
+) -> anyhow::Result<usize> {
+    let since = chrono::Utc::now() - chrono::Duration::from_std(config.window)?;
+    let due = AutomationRunRepository::new(pool)
+        .claim_retryable(since, config.max_attempts, config.backoff.as_secs() as i64, CLAIM_BATCH_SIZE)
+        .await?;
+
+    let mut retried = 0;
+    for run in due {
+        match retry_run(pool, secrets, &run).await {
+            Ok(true) => retried += 1,
+            Ok(false) => {}
+            Err(err) => error!("Failed to retry automation run {}: {}", run.id, err),
+        }
+    }
+
+    Ok(retried)
+}
+
+async fn retry_run(pool: &PgPool, secrets: Option<&SecretCipher>, run: &AutomationRun) -> anyhow::Result<bool> {
+    let case = CaseRepository::new(pool).find_by_id(run.case_id).await?;
+    let workflow = WorkflowRepository::new(pool).find_by_id(run.workflow_id).await?;
+
+    let (Some(case), Some(workflow)) = (case, workflow) else {
+        info!("Automation run {} not retried: case or workflow no longer exists", run.id);
+        return Ok(false);
+    };
+
+    if case.status != CaseStatus::Active {
+        info!("Automation run {} not retried: case {} is no longer active", run.id, case.id);
+        return Ok(false);
+    }
+
+    let trigger = match run.trigger.as_str() {
+        "on_exit" => AutomationTrigger::OnExit,
+        "on_reply" => AutomationTrigger::OnReply,
+        _ => AutomationTrigger::OnEnter,
+    };
+
+    if trigger != AutomationTrigger::OnExit && case.current_phase != run.phase {
+        info!(
+            "Automation run {} not retried: case {} left phase '{}'",
+            run.id, case.id, run.phase
+        );
+        return Ok(false);
+    }
+
+    let automations: Vec<_> = workflow
+        .automations
+        .iter()
+        .flat_map(|config| config.automations.iter())
+        .filter(|automation| automation.trigger == trigger && automation.phase == run.phase)
+        .collect();
+
+    if automations.is_empty() {
+        info!("Automation run {} not retried: the phase no longer has {} automations", run.id, run.trigger);
+        return Ok(false);
+    }
+
+    if let Err((status, body)) = retry_automation_run(pool, &automations, &case, &workflow, run, secrets).await {
+        return Err(anyhow::anyhow!("{}: {}", status, body.0));
+    }
+
+    info!("Retried {} automation run {} for case {} (attempt {})", run.trigger, run.id, case.id, run.attempt + 1);
+    Ok(true)
+}
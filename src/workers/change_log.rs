@@ -0,0 +1,38 @@
+use std::time::Duration;
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error};
+
+use crate::repositories::ChangeRepository;
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Drops change log entries older than `retention`. A client whose cursor
+/// is older than that misses the dropped changes and should reload.
+pub fn spawn_change_log_prune_worker(pool: PgPool, shedder: LoadShedder, retention: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PRUNE_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Retention, "change log pruning").await;
+
+            let before = match chrono::Duration::from_std(retention) {
+                Ok(retention) => Utc::now() - retention,
+                Err(err) => {
+                    error!("Invalid change log retention: {}", err);
+                    return;
+                }
+            };
+
+            match ChangeRepository::new(&pool).prune(before).await {
+                Ok(pruned) => debug!("Pruned {} change log entries", pruned),
+                Err(err) => error!("Failed to prune change log: {}", err),
+            }
+        }
+    })
+}
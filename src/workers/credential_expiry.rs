@@ -0,0 +1,125 @@
+use std::time::Duration;
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::models::credential::ExpiringCredential;
+use crate::models::portal::PortalToken;
+use crate::models::outbox::OutboxMessage;
+use crate::models::{Case, Workflow};
+use crate::repositories::{CaseRepository, OutboxRepository, PortalTokenRepository, WorkflowRepository};
+use crate::services::webhook::CredentialWebhookPayload;
+use crate::services::WebhookSender;
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+
+const SWEEP_BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Clone)]
+pub struct CredentialExpiryConfig {
+    pub poll_interval: Duration,
+    /// How long before expiry the reminder goes out; zero disables reminders.
+    pub reminder_window: Duration,
+}
+
+impl CredentialExpiryConfig {
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|s| s.parse().ok())
+        }
+
+        Self {
+            poll_interval: Duration::from_secs(var("CREDENTIAL_EXPIRY_POLL_SECS").filter(|secs| *secs > 0).unwrap_or(300)),
+            reminder_window: Duration::from_secs(var::<u64>("CREDENTIAL_REMINDER_HOURS").unwrap_or(72) * 3600),
+        }
+    }
+}
+
+/// What one sweep did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExpirySweep {
+    pub revoked: usize,
+    pub reminded: usize,
+}
+
+/// Revokes expired portal tokens and sends `credential.expiring` reminders
+/// to the workflow webhook, so integrations can issue a replacement link.
+pub fn spawn_credential_expiry_worker(
+    pool: PgPool,
+    webhook_sender: WebhookSender,
+    shedder: LoadShedder,
+    config: CredentialExpiryConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Sla, "credential expiry sweep").await;
+
+            if let Err(err) = sweep_credentials(&pool, &webhook_sender, &config).await {
+                error!("Failed to sweep expiring credentials: {}", err);
+            }
+        }
+    })
+}
+
+/// One pass of the expiry worker.
+pub async fn sweep_credentials(
+    pool: &PgPool,
+    webhook_sender: &WebhookSender,
+    config: &CredentialExpiryConfig,
+) -> anyhow::Result<ExpirySweep> {
+    let repo = PortalTokenRepository::new(pool);
+    let mut sweep = ExpirySweep::default();
+
+    for token in repo.revoke_expired(SWEEP_BATCH_SIZE).await? {
+        info!("Revoked expired portal token {} for case {}", token.id, token.case_id);
+        notify(pool, webhook_sender, &token, "credential.revoked").await;
+        sweep.revoked += 1;
+    }
+
+    if !config.reminder_window.is_zero() {
+        let before = Utc::now() + chrono::Duration::from_std(config.reminder_window)?;
+        for token in repo.claim_reminders(before, SWEEP_BATCH_SIZE).await? {
+            notify(pool, webhook_sender, &token, "credential.expiring").await;
+            sweep.reminded += 1;
+        }
+    }
+
+    Ok(sweep)
+}
+
+async fn notify(pool: &PgPool, webhook_sender: &WebhookSender, token: &PortalToken, action: &str) {
+    let Some(credential) = ExpiringCredential::from_portal_token(token, Utc::now()) else {
+        return;
+    };
+
+    match webhook_workflow(pool, token.case_id).await {
+        Ok(Some((case, workflow))) => {
+            let Some(webhook) = workflow.webhook() else {
+                return;
+            };
+            let payload = CredentialWebhookPayload { action: action.to_string(), data: credential };
+            let message = OutboxMessage::webhook(&case, webhook, action, &payload);
+            match OutboxRepository::new(pool).enqueue(&[message]).await {
+                Ok(ids) => webhook_sender.dispatch_queued(pool, ids).await,
+                Err(err) => error!("Failed to queue {} webhook for portal token {}: {}", action, token.id, err),
+            }
+        }
+        Ok(None) => {}
+        Err(err) => error!("Failed to look up webhook for portal token {}: {}", token.id, err),
+    }
+}
+
+async fn webhook_workflow(pool: &PgPool, case_id: Uuid) -> anyhow::Result<Option<(Case, Workflow)>> {
+    let Some(case) = CaseRepository::new(pool).find_by_id(case_id).await? else {
+        return Ok(None);
+    };
+    let workflow = WorkflowRepository::new(pool).find_by_id(case.workflow_id).await?;
+
+    Ok(workflow.map(|workflow| (case, workflow)))
+}
@@ -0,0 +1,30 @@
+use chrono::Utc;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::services::digest::DigestService;
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+
+pub fn spawn_digest_worker(service: DigestService, shedder: LoadShedder) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let config = service.config().clone();
+        info!("Digest worker started ({:?} at {}:00 UTC)", config.period, config.hour_utc);
+
+        loop {
+            let now = Utc::now();
+            let next_run = config.period.next_run_after(now, config.hour_utc);
+            let wait = (next_run - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+            shedder.wait_for_turn(WorkTier::Analytics, "operator digest").await;
+
+            match service.build(Utc::now()).await {
+                Ok(digest) => {
+                    if let Err(err) = service.deliver(&digest).await {
+                        error!("Failed to deliver operator digest: {}", err);
+                    }
+                }
+                Err(err) => error!("Failed to build operator digest: {}", err),
+            }
+        }
+    })
+}
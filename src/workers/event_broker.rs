@@ -0,0 +1,91 @@
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::api::events::internal_create_and_trigger_event;
+use crate::api::region::Region;
+use crate::api::AppState;
+use crate::models::event::CreateEvent;
+use crate::services::event_consumer::{to_event, ConsumerConfig, MessageSource};
+use crate::services::load_shedding::WorkTier;
+use crate::services::{CaseStream, EventPublisher};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Publishes the case changes made through this instance to the message
+/// broker. Changes it falls too far behind on are logged and skipped.
+pub fn spawn_case_event_forwarder(stream: CaseStream, publisher: EventPublisher) -> JoinHandle<()> {
+    let mut changes = stream.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match changes.recv().await {
+                Ok(change) => publisher.publish_case_change(&change),
+                Err(RecvError::Lagged(missed)) => warn!("Skipped {} case changes for the event broker", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Ingests the messages on the configured topic as events, reconnecting
+/// whenever the subscription drops.
+pub fn spawn_event_consumer(state: AppState, config: ConsumerConfig) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match config.connect().await {
+                Ok(mut source) => {
+                    info!("Consuming events from {} '{}'", config.broker, config.topic);
+                    if let Err(err) = consume_events(&state, &config, source.as_mut()).await {
+                        error!("Event consumer for '{}' stopped: {}", config.topic, err);
+                    }
+                }
+                Err(err) => error!("Failed to subscribe to {} '{}': {}", config.broker, config.topic, err),
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+/// Stores each message from `source` as an event in the configured region
+/// and runs the flows it matches, acknowledging it afterwards, until the
+/// subscription ends. Messages that aren't valid events are logged and
+/// acknowledged; a database failure is retried until the event is stored,
+/// so delivery is at least once and in order.
+pub async fn consume_events(
+    state: &AppState,
+    config: &ConsumerConfig,
+    source: &mut dyn MessageSource,
+) -> anyhow::Result<()> {
+    let region = Region::resolve(state, config.region.clone()).map_err(|err| anyhow::anyhow!(err.0))?;
+
+    while let Some(delivery) = source.next().await? {
+        state.load_shedder.wait_for_turn(WorkTier::Sla, "event consumer").await;
+
+        match to_event(&config.broker, &delivery) {
+            Ok(event) => ingest(state, &region, event).await,
+            Err(message) => warn!("Dropped message from '{}': {}", delivery.topic, message),
+        }
+        source.ack().await?;
+    }
+    Ok(())
+}
+
+async fn ingest(state: &AppState, region: &Region, event: CreateEvent) {
+    let mut delay = Duration::from_secs(1);
+    loop {
+        match internal_create_and_trigger_event(state, region, event.clone()).await {
+            Ok(_) => return,
+            Err(err) if err.status.is_server_error() => {
+                error!("Failed to ingest {} event, retrying in {:?}: {}", event.event_type, delay, err.message);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+            Err(err) => {
+                warn!("Rejected {} event from the broker: {}", event.event_type, err.message);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error, info, warn};
+
+use crate::engine::{Executor, FlowConcurrencyLimiter};
+use crate::models::execution::{Execution, ExecutionStatus};
+use crate::repositories::{EventRepository, ExecutionRepository, FlowRepository};
+use crate::services::clock::{Clock, SharedClock};
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+use crate::services::execution_payloads::{stored_execution, ExecutionPayloads};
+use crate::services::{EventPublisher, SecretCipher, Secrets, WebhookSender};
+
+const CLAIM_BATCH_SIZE: i64 = 50;
+/// Longer than resuming an execution normally takes, so it isn't resumed
+/// twice while the first attempt is still running.
+const LEASE: Duration = Duration::from_secs(300);
+
+/// Picks up executions suspended by a `delay_until` step once their resume
+/// time has passed and runs the remaining steps of the pinned flow version.
+/// Executions that fail on resume are reported as `execution.failed`, and
+/// finished ones are published through `event_publisher`. `secrets`
+/// decrypts the secrets their webhooks refer to. An execution whose flow is
+/// at its current concurrency limit goes back to waiting until the next
+/// poll, and one that can't be resumed is marked failed.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_flow_resume_worker(
+    pool: PgPool,
+    limiter: FlowConcurrencyLimiter,
+    webhook_sender: WebhookSender,
+    event_publisher: EventPublisher,
+    execution_payloads: Option<ExecutionPayloads>,
+    secrets: Option<SecretCipher>,
+    shedder: LoadShedder,
+    clock: SharedClock,
+    poll_interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let executor = Executor::new().with_clock(clock.clone());
+        let mut ticker = tokio::time::interval(poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Sla, "flow resume").await;
+
+            let due = match ExecutionRepository::new(&pool).claim_due_waiting(clock.now(), CLAIM_BATCH_SIZE, LEASE).await {
+                Ok(due) => due,
+                Err(err) => {
+                    error!("Failed to claim waiting executions: {}", err);
+                    continue;
+                }
+            };
+
+            for execution in due {
+                let mut claimed = execution.clone();
+                let resumed = resume_execution(
+                    &pool,
+                    &executor,
+                    &limiter,
+                    &webhook_sender,
+                    &event_publisher,
+                    execution_payloads.as_ref(),
+                    secrets.as_ref(),
+                    clock.as_ref(),
+                    execution,
+                )
+                .await;
+                if let Err(err) = resumed {
+                    error!("Failed to resume execution {}: {}", claimed.id, err);
+                    fail(&mut claimed, format!("Failed to resume: {}", err), clock.as_ref());
+                    if let Err(err) = ExecutionRepository::new(&pool).update(&claimed).await {
+                        error!("Failed to mark execution {} as failed: {}", claimed.id, err);
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn resume_execution(
+    pool: &PgPool,
+    executor: &Executor,
+    limiter: &FlowConcurrencyLimiter,
+    webhook_sender: &WebhookSender,
+    event_publisher: &EventPublisher,
+    execution_payloads: Option<&ExecutionPayloads>,
+    secrets: Option<&SecretCipher>,
+    clock: &dyn Clock,
+    mut execution: Execution,
+) -> anyhow::Result<()> {
+    let execution_repo = ExecutionRepository::new(pool);
+
+    let flow_version = match execution.flow_version {
+        Some(version) => FlowRepository::new(pool).find_version(execution.flow_id, version).await?,
+        None => None,
+    };
+    let event = EventRepository::new(pool).find_by_id(execution.event_id).await?;
+
+    let (Some(flow_version), Some(event)) = (flow_version, event) else {
+        warn!("Execution {} can no longer be resumed: flow version or event missing", execution.id);
+        fail(&mut execution, "Flow version or event no longer exists".to_string(), clock);
+        return execution_repo.update(&execution).await;
+    };
+
+    // The pinned version's steps, under the flow's current limit: live
+    // triggers use that one, and the limiter keeps one semaphore per limit.
+    let mut flow = flow_version.to_flow();
+    if let Some(current) = FlowRepository::new(pool).find_by_id(execution.flow_id).await? {
+        flow.max_concurrent_executions = current.max_concurrent_executions;
+    }
+    let Ok(permit) = limiter.try_acquire(&flow) else {
+        debug!("Flow '{}' is at its concurrency limit, execution {} keeps waiting", flow.name, execution.id);
+        execution.status = ExecutionStatus::Waiting;
+        return execution_repo.update(&execution).await;
+    };
+    let executor = executor.clone().with_secrets(Secrets::for_definition(pool, secrets, &flow.steps).await);
+    let resumed = executor.resume(&flow, &event, execution).await?;
+    drop(permit);
+
+    info!("Execution {} resumed, now {:?}", resumed.id, resumed.status);
+    execution_repo.update(&stored_execution(execution_payloads, &resumed).await).await?;
+
+    if matches!(resumed.status, ExecutionStatus::Failed) {
+        webhook_sender.notify_execution_failed(pool.clone(), &resumed, &flow.name, &event);
+    }
+    event_publisher.publish_execution(&resumed, &flow.name, &event);
+    Ok(())
+}
+
+fn fail(execution: &mut Execution, error: String, clock: &dyn Clock) {
+    execution.status = ExecutionStatus::Failed;
+    execution.error = Some(error);
+    execution.completed_at = Some(clock.now());
+    execution.resume_at = None;
+    execution.resume_step = None;
+}
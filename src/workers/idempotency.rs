@@ -0,0 +1,30 @@
+use std::time::Duration;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error};
+
+use crate::repositories::IdempotencyRepository;
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+use crate::services::SharedClock;
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Drops expired idempotency keys. Expired keys are already ignored when a
+/// request comes in; this only keeps the table small.
+pub fn spawn_idempotency_prune_worker(pool: PgPool, shedder: LoadShedder, clock: SharedClock) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PRUNE_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Retention, "idempotency key pruning").await;
+
+            match IdempotencyRepository::new(&pool).prune(clock.now()).await {
+                Ok(pruned) => debug!("Pruned {} idempotency keys", pruned),
+                Err(err) => error!("Failed to prune idempotency keys: {}", err),
+            }
+        }
+    })
+}
@@ -0,0 +1,24 @@
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+use crate::services::outbound_http::http_client;
+use crate::services::JwtAuth;
+
+/// Reloads the keys published at the JWKS URL, so keys the identity
+/// provider rotates in are accepted and retired ones stop being.
+pub fn spawn_jwks_refresh_worker(auth: JwtAuth, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let client = http_client(Duration::from_secs(10));
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match auth.refresh_jwks(&client).await {
+                Ok(count) => debug!("Loaded {} JWKS keys", count),
+                Err(err) => error!("Failed to load JWKS keys: {}", err),
+            }
+        }
+    })
+}
@@ -0,0 +1,31 @@
+pub mod automation_resume;
+pub mod automation_retry;
+pub mod change_log;
+pub mod credential_expiry;
+pub mod digest;
+pub mod event_broker;
+pub mod flow_resume;
+pub mod idempotency;
+pub mod jwks;
+pub mod outbox;
+pub mod partitions;
+pub mod retention;
+pub mod secrets;
+pub mod signing_keys;
+pub mod usage;
+
+pub use automation_resume::spawn_automation_resume_worker;
+pub use automation_retry::{spawn_automation_retry_worker, AutomationRetryConfig};
+pub use change_log::spawn_change_log_prune_worker;
+pub use credential_expiry::{spawn_credential_expiry_worker, CredentialExpiryConfig};
+pub use digest::spawn_digest_worker;
+pub use event_broker::{consume_events, spawn_case_event_forwarder, spawn_event_consumer};
+pub use flow_resume::spawn_flow_resume_worker;
+pub use idempotency::spawn_idempotency_prune_worker;
+pub use jwks::spawn_jwks_refresh_worker;
+pub use outbox::spawn_outbox_dispatcher;
+pub use partitions::spawn_partition_maintenance_worker;
+pub use retention::spawn_retention_purge_worker;
+pub use secrets::spawn_secret_reencrypt_job;
+pub use signing_keys::spawn_signing_key_refresh_worker;
+pub use usage::spawn_usage_flush_worker;
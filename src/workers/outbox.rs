@@ -0,0 +1,38 @@
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::api::outbox::dispatch_outbox;
+use crate::api::region::Region;
+use crate::api::AppState;
+use crate::models::outbox::OutboxScope;
+use crate::services::load_shedding::WorkTier;
+
+/// Delivers the region's outbox messages that are due: retries of failed
+/// deliveries and messages a restart interrupted before they went out.
+pub fn spawn_outbox_dispatcher(state: AppState, region: String, poll_interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let region = match Region::resolve(&state, Some(region.clone())) {
+            Ok(region) => region,
+            Err(err) => {
+                error!("Outbox dispatcher not started for region {}: {}", region, err.0);
+                return;
+            }
+        };
+
+        let mut ticker = tokio::time::interval(poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            state.load_shedder.wait_for_turn(WorkTier::Sla, "outbox dispatch").await;
+
+            match dispatch_outbox(&state, &region, OutboxScope::All).await {
+                Ok(0) => {}
+                Ok(delivered) => info!("Delivered {} outbox messages in region {}", delivered, region.name),
+                Err(err) => error!("Failed to dispatch outbox in region {}: {}", region.name, err),
+            }
+        }
+    })
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+use chrono::{Months, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::repositories::PartitionRepository;
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+
+/// Keeps monthly partitions of events, case history and executions created
+/// `months_ahead` months ahead, so rows never fall through to the default
+/// partition.
+pub fn spawn_partition_maintenance_worker(pool: PgPool, shedder: LoadShedder, months_ahead: u32) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(MAINTENANCE_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Retention, "partition maintenance").await;
+
+            let now = Utc::now();
+            let until = now.checked_add_months(Months::new(months_ahead)).unwrap_or(now);
+            match PartitionRepository::new(&pool).create_monthly(now, until).await {
+                Ok(0) => {}
+                Ok(created) => info!("Created {} monthly partitions", created),
+                Err(err) => error!("Failed to create monthly partitions: {}", err),
+            }
+        }
+    })
+}
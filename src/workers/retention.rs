@@ -0,0 +1,39 @@
+use std::time::Duration;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+use crate::services::retention::{purge, PurgeReport, RetentionConfig};
+use crate::services::SharedClock;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Deletes executions, events and webhook attempts older than `config`
+/// keeps them.
+pub fn spawn_retention_purge_worker(
+    pool: PgPool,
+    shedder: LoadShedder,
+    clock: SharedClock,
+    config: RetentionConfig,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PURGE_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Retention, "retention purge").await;
+
+            match purge(&pool, &config, clock.now()).await {
+                Ok(report) if report != PurgeReport::default() => info!(
+                    "Purged {} executions, {} events and {} webhook deliveries",
+                    report.executions, report.events, report.webhook_deliveries
+                ),
+                Ok(_) => {}
+                Err(err) => error!("Failed to purge old executions, events and webhook deliveries: {}", err),
+            }
+        }
+    })
+}
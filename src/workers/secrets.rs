@@ -0,0 +1,19 @@
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use crate::services::{reencrypt_secrets, SecretCipher};
+
+/// Re-encrypts, once at startup, the secrets still under the previous
+/// `SECRETS_KEY`, so `SECRETS_KEY_PREVIOUS` can be unset on the next deploy.
+pub fn spawn_secret_reencrypt_job(pool: PgPool, cipher: SecretCipher) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        match reencrypt_secrets(&pool, &cipher).await {
+            Ok(report) if !report.failed.is_empty() => {
+                warn!("Secrets {:?} can't be decrypted with SECRETS_KEY or SECRETS_KEY_PREVIOUS", report.failed)
+            }
+            Ok(_) => {}
+            Err(err) => error!("Failed to re-encrypt secrets: {}", err),
+        }
+    })
+}
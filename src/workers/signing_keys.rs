@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+use crate::repositories::SigningKeyRepository;
+use crate::services::WebhookSigner;
+
+/// Retired keys are listed this long before they are deleted.
+const RETIRED_KEY_RETENTION: chrono::Duration = chrono::Duration::days(30);
+
+/// Loads the signing keys still in use into `signer`. Returns how many.
+pub async fn refresh_signing_keys(pool: &PgPool, signer: &WebhookSigner) -> anyhow::Result<usize> {
+    let keys = SigningKeyRepository::new(pool).list_signing(Utc::now()).await?;
+    signer.set_managed_keys(keys.iter().map(|key| key.signing_key()).collect());
+    Ok(keys.len())
+}
+
+/// Keeps `signer` in step with keys rotated on any instance, and deletes
+/// keys long retired.
+pub fn spawn_signing_key_refresh_worker(pool: PgPool, signer: WebhookSigner, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+
+            match refresh_signing_keys(&pool, &signer).await {
+                Ok(count) => debug!("Loaded {} managed signing keys", count),
+                Err(err) => error!("Failed to load signing keys: {}", err),
+            }
+
+            if let Err(err) = SigningKeyRepository::new(&pool).prune(Utc::now() - RETIRED_KEY_RETENTION).await {
+                error!("Failed to prune retired signing keys: {}", err);
+            }
+        }
+    })
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error};
+
+use crate::repositories::UsageRepository;
+use crate::services::UsageRecorder;
+use crate::services::load_shedding::{LoadShedder, WorkTier};
+
+pub fn spawn_usage_flush_worker(
+    recorder: UsageRecorder,
+    pool: PgPool,
+    shedder: LoadShedder,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            shedder.wait_for_turn(WorkTier::Analytics, "usage flush").await;
+
+            let entries = recorder.drain();
+            if entries.is_empty() {
+                continue;
+            }
+
+            match UsageRepository::new(&pool).upsert(&entries).await {
+                Ok(_) => debug!("Flushed {} API usage bucket(s)", entries.len()),
+                Err(err) => {
+                    error!("Failed to flush API usage: {}", err);
+                    recorder.restore(entries);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,57 @@
+use chrono::{Duration, Utc};
+use orchepy::api::{build_router, AppState};
+use orchepy::middleware::UNMATCHED_ROUTE;
+use orchepy::repositories::UsageRepository;
+use orchepy::services::{UsageRecorder, WebhookSender};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_usage_rollup_accumulates_across_flushes(pool: PgPool) {
+    let recorder = UsageRecorder::new();
+    let now = Utc::now();
+
+    recorder.record("key-a", "GET", "/cases/{id}", 200, 12, now);
+    recorder.record("key-a", "GET", "/cases/{id}", 404, 8, now);
+    recorder.record("key-b", "POST", "/cases", 201, 40, now);
+
+    let repo = UsageRepository::new(&pool);
+    repo.upsert(&recorder.drain()).await.unwrap();
+
+    recorder.record("key-a", "GET", "/cases/{id}", 500, 30, now);
+    repo.upsert(&recorder.drain()).await.unwrap();
+
+    let since = now - Duration::days(1);
+    let totals = repo.totals("key-a", since).await.unwrap();
+    assert_eq!(totals.requests, 3);
+    assert_eq!(totals.client_errors, 1);
+    assert_eq!(totals.server_errors, 1);
+    assert_eq!(totals.max_latency_ms, Some(30));
+
+    let routes = repo.by_route("key-a", since).await.unwrap();
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].route, "/cases/{id}");
+
+    let other = repo.totals("key-b", since).await.unwrap();
+    assert_eq!(other.requests, 1);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_unknown_paths_share_one_route(pool: PgPool) {
+    let state = AppState::new(pool, WebhookSender::new());
+    let recorder = state.usage.clone();
+    let base = common::serve(build_router(state)).await;
+
+    let client = reqwest::Client::new();
+    for path in ["/nope/abc123", "/nope/def456", "/also-missing"] {
+        let response = client.get(format!("{}{}", base, path)).header("X-Api-Key", "key-a").send().await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+
+    let usage = recorder.drain();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].0.route, UNMATCHED_ROUTE);
+    assert_eq!(usage[0].1.requests, 3);
+    assert_eq!(usage[0].1.client_errors, 3);
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use jsonwebtoken::{encode, EncodingKey, Header};
+use orchepy::api::{build_router, AppState};
+use orchepy::repositories::UsageRepository;
+use orchepy::services::usage::key_fingerprint;
+use orchepy::services::{JwtAuth, WebhookSender};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+const SECRET: &str = "test-secret";
+
+fn secured_state(pool: &PgPool) -> AppState {
+    let auth = JwtAuth::with_secret(SECRET).issuer("https://idp.example.com");
+    AppState::new(pool.clone(), WebhookSender::new()).with_auth(auth)
+}
+
+fn token(role: &str) -> String {
+    let claims = json!({
+        "sub": format!("{}@example.com", role),
+        "iss": "https://idp.example.com",
+        "exp": chrono::Utc::now().timestamp() + 600,
+        "roles": [role],
+    });
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET.as_bytes())).unwrap()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_routes_require_a_role(pool: PgPool) {
+    let base = common::serve(build_router(secured_state(&pool))).await;
+    let client = reqwest::Client::new();
+    let url = |path: &str| format!("{}{}", base, path);
+
+    assert_eq!(client.get(url("/health")).send().await.unwrap().status(), 200);
+
+    let anonymous = client.get(url("/cases")).send().await.unwrap();
+    assert_eq!(anonymous.status(), 401);
+    assert_eq!(anonymous.headers()["www-authenticate"], "Bearer");
+    let forged = client.get(url("/cases")).bearer_auth("not-a-token").send().await.unwrap();
+    assert_eq!(forged.status(), 401);
+
+    let workflow = json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"});
+    let denied = client
+        .post(url("/workflows"))
+        .bearer_auth(token("operator"))
+        .json(&workflow)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(denied.status(), 403);
+
+    let created = client
+        .post(url("/workflows"))
+        .bearer_auth(token("admin"))
+        .json(&workflow)
+        .send()
+        .await
+        .unwrap();
+    assert!(created.status().is_success());
+    let workflow: Value = created.json().await.unwrap();
+
+    let case: Value = client
+        .post(url("/cases"))
+        .bearer_auth(token("operator"))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case_url = url(&format!("/cases/{}", case["id"].as_str().unwrap()));
+
+    let viewer = token("viewer");
+    assert_eq!(client.get(&case_url).bearer_auth(&viewer).send().await.unwrap().status(), 200);
+
+    let move_to_done = json!({"to_phase": "Done"});
+    let moved = |token: String| client.put(format!("{}/move", case_url)).bearer_auth(token).json(&move_to_done).send();
+    assert_eq!(moved(viewer).await.unwrap().status(), 403);
+    assert_eq!(moved(token("operator")).await.unwrap().status(), 200);
+
+    assert_eq!(client.get(url("/admin/load")).bearer_auth(token("operator")).send().await.unwrap().status(), 403);
+    assert_eq!(client.get(url("/admin/load")).bearer_auth(token("admin")).send().await.unwrap().status(), 200);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_idempotency_and_usage_follow_the_token_subject(pool: PgPool) {
+    let state = secured_state(&pool);
+    let usage = state.usage.clone();
+    let base = common::serve(build_router(state)).await;
+    let client = reqwest::Client::new();
+    let url = |path: &str| format!("{}{}", base, path);
+
+    let workflow: Value = client
+        .post(url("/workflows"))
+        .bearer_auth(token("admin"))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case = json!({"workflow_id": workflow["id"], "data": {}});
+    let create = |token: String, api_key: &str| {
+        client
+            .post(url("/cases"))
+            .bearer_auth(token)
+            .header("Idempotency-Key", "case-1")
+            .header("X-Api-Key", api_key)
+            .json(&case)
+            .send()
+    };
+
+    // The X-Api-Key header is ignored: the same subject replays, another one doesn't.
+    let first: Value = create(token("operator"), "key-a").await.unwrap().json().await.unwrap();
+    let retry = create(token("operator"), "key-b").await.unwrap();
+    assert_eq!(retry.headers()["idempotent-replayed"], "true");
+    assert_eq!(retry.json::<Value>().await.unwrap()["id"], first["id"]);
+    let other = create(token("admin"), "key-a").await.unwrap();
+    assert!(other.headers().get("idempotent-replayed").is_none());
+    assert_ne!(other.json::<Value>().await.unwrap()["id"], first["id"]);
+
+    let operator = key_fingerprint("operator@example.com");
+    let drained = usage.drain();
+    let mut requests = BTreeMap::new();
+    for (key, counters) in &drained {
+        *requests.entry(key.key_id.clone()).or_insert(0) += counters.requests;
+    }
+    assert_eq!(requests, BTreeMap::from([(key_fingerprint("admin@example.com"), 2), (operator.clone(), 2)]));
+
+    UsageRepository::new(&pool).upsert(&drained).await.unwrap();
+    let mine: Value = client
+        .get(url("/me/usage"))
+        .bearer_auth(token("operator"))
+        .header("X-Api-Key", "key-a")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(mine["key_id"], operator);
+    assert_eq!(mine["totals"]["requests"], 2);
+}
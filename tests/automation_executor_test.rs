@@ -1,6 +1,7 @@
-use orchepy::engine::AutomationExecutor;
+use orchepy::engine::{AutomationExecutor, LimitExceeded};
 use orchepy::models::automation::*;
 use orchepy::models::case::Case;
+use orchepy::models::message::MessageChannel;
 use serde_json::json;
 use uuid::Uuid;
 
@@ -66,7 +67,7 @@ async fn test_set_field_action() {
 
     assert_eq!(result.modifications.len(), 1);
     match &result.modifications[0] {
-        CaseModification::SetField { field, value } => {
+        CaseModification::SetField { field, value, .. } => {
             assert_eq!(field, "data.processed");
             assert_eq!(value, &json!(true));
         }
@@ -163,6 +164,78 @@ async fn test_conditional_simple_false() {
     }
 }
 
+#[tokio::test]
+async fn test_conditional_traces_record_values() {
+    let executor = AutomationExecutor::new();
+
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: vec![AutomationAction::Conditional {
+            name: Some("Check risk".to_string()),
+            condition: Condition::Complex {
+                operator: LogicalOperator::And,
+                conditions: vec![
+                    SimpleCondition {
+                        field: "data.amount".to_string(),
+                        operator: ">".to_string(),
+                        value: json!(1000),
+                    },
+                    SimpleCondition {
+                        field: "data.country".to_string(),
+                        operator: "==".to_string(),
+                        value: json!("BR"),
+                    },
+                    SimpleCondition {
+                        field: "data.score".to_string(),
+                        operator: "<".to_string(),
+                        value: json!(50),
+                    },
+                ],
+            },
+            then: vec![],
+            r#else: Some(vec![AutomationAction::Conditional {
+                name: None,
+                condition: Condition::Simple {
+                    field: "data.missing".to_string(),
+                    operator: "==".to_string(),
+                    value: json!(true),
+                },
+                then: vec![],
+                r#else: None,
+            }]),
+        }],
+    };
+
+    let case = Case::new(
+        Uuid::new_v4(),
+        "Review".to_string(),
+        json!({"amount": 5000, "country": "US", "score": 10}),
+        None,
+    );
+
+    let (result, conditions) = executor
+        .execute_automations_traced(&[&automation], &case, None)
+        .await;
+
+    // A condition that fails to evaluate skips both branches without stopping the run.
+    assert!(result.is_ok());
+    assert_eq!(conditions.len(), 2);
+
+    assert_eq!(conditions[0].path, "automations[0].actions[0]");
+    assert_eq!(conditions[0].result, Some(false));
+    assert_eq!(conditions[0].comparisons.len(), 2);
+    assert_eq!(conditions[0].comparisons[0].actual, Some(json!(5000)));
+    assert_eq!(conditions[0].comparisons[0].outcome, Some(true));
+    assert_eq!(conditions[0].comparisons[1].actual, Some(json!("US")));
+    assert_eq!(conditions[0].comparisons[1].outcome, Some(false));
+
+    assert_eq!(conditions[1].path, "automations[0].actions[0].else[0]");
+    assert_eq!(conditions[1].result, None);
+    assert_eq!(conditions[1].comparisons[0].actual, None);
+    assert!(conditions[1].comparisons[0].error.is_some());
+}
+
 #[tokio::test]
 async fn test_conditional_complex_and_true() {
     let executor = AutomationExecutor::new();
@@ -210,7 +283,7 @@ async fn test_conditional_complex_and_true() {
 
     assert_eq!(result.modifications.len(), 1);
     match &result.modifications[0] {
-        CaseModification::SetField { field, value } => {
+        CaseModification::SetField { field, value, .. } => {
             assert_eq!(field, "data.priority");
             assert_eq!(value, &json!("high"));
         }
@@ -563,3 +636,468 @@ async fn test_comparison_operators() {
         .unwrap();
     assert_eq!(result.modifications.len(), 1);
 }
+
+#[tokio::test]
+async fn test_max_actions_per_run_limit() {
+    let executor = AutomationExecutor::with_limits(AutomationLimits {
+        max_actions_per_run: 2,
+        ..Default::default()
+    });
+
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: (0..3)
+            .map(|i| AutomationAction::SetField {
+                name: None,
+                field: format!("data.field_{}", i),
+                value: json!(i),
+            })
+            .collect(),
+    };
+
+    let case = Case::new(Uuid::new_v4(), "Review".to_string(), json!({}), None);
+
+    let err = executor
+        .execute_automations(&[&automation], &case, None)
+        .await
+        .unwrap_err();
+
+    let limit = err.downcast_ref::<LimitExceeded>().expect("limit error");
+    assert_eq!(limit.limit, "max_actions_per_run");
+    assert_eq!(limit.max, 2);
+}
+
+#[tokio::test]
+async fn test_max_total_delay_limit_ignores_on_error() {
+    let executor = AutomationExecutor::with_limits(AutomationLimits {
+        max_total_delay_ms: 15,
+        ..Default::default()
+    });
+
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: vec![
+            AutomationAction::Delay { name: None, duration_ms: 10 },
+            AutomationAction::Conditional {
+                name: None,
+                condition: Condition::Simple {
+                    field: "data.amount".to_string(),
+                    operator: ">".to_string(),
+                    value: json!(0),
+                },
+                then: vec![AutomationAction::Delay { name: None, duration_ms: 10 }],
+                r#else: None,
+            },
+        ],
+    };
+
+    let case = Case::new(Uuid::new_v4(), "Review".to_string(), json!({"amount": 1}), None);
+
+    let err = executor
+        .execute_automations(&[&automation], &case, None)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<LimitExceeded>().map(|l| l.limit),
+        Some("max_total_delay_ms")
+    );
+}
+
+#[tokio::test]
+async fn test_max_total_delay_limit_counts_deferred_delays() {
+    let limits = AutomationLimits {
+        max_total_delay_ms: 3_600_000,
+        ..Default::default()
+    };
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: vec![AutomationAction::Delay { name: None, duration_ms: 2_400_000 }],
+    };
+    let case = Case::new(Uuid::new_v4(), "Review".to_string(), json!({}), None);
+
+    let result = AutomationExecutor::with_limits(limits.clone())
+        .execute_automations(&[&automation], &case, None)
+        .await
+        .unwrap();
+    assert!(result.deferred.is_some());
+    assert_eq!(result.total_delay_ms, 2_400_000);
+
+    // The resumed run starts from the delay the automation already spent.
+    let err = AutomationExecutor::with_limits(limits)
+        .with_delay_spent(result.total_delay_ms)
+        .execute_automations(&[&automation], &case, None)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<LimitExceeded>().map(|l| l.limit),
+        Some("max_total_delay_ms")
+    );
+}
+
+#[tokio::test]
+async fn test_max_webhook_body_bytes_limit() {
+    let executor = AutomationExecutor::with_limits(AutomationLimits {
+        max_webhook_body_bytes: 64,
+        ..Default::default()
+    });
+
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: vec![AutomationAction::Webhook {
+            id: None,
+            name: None,
+            url: "http://127.0.0.1:9/unreachable".to_string(),
+            method: None,
+            headers: None,
+            fields: Some(vec!["data".to_string()]),
+            use_response_from: None,
+            retry: RetryConfig::default(),
+            on_error: OnError::Continue,
+        }],
+    };
+
+    let case = Case::new(
+        Uuid::new_v4(),
+        "Review".to_string(),
+        json!({"blob": "x".repeat(128)}),
+        None,
+    );
+
+    let err = executor
+        .execute_automations(&[&automation], &case, None)
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<LimitExceeded>().map(|l| l.limit),
+        Some("max_webhook_body_bytes")
+    );
+}
+
+#[tokio::test]
+async fn test_result_reports_usage() {
+    let executor = AutomationExecutor::new();
+
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: vec![
+            AutomationAction::Delay { name: None, duration_ms: 5 },
+            AutomationAction::MoveToPhase {
+                name: None,
+                phase: "Done".to_string(),
+            },
+        ],
+    };
+
+    let case = Case::new(Uuid::new_v4(), "Review".to_string(), json!({}), None);
+
+    let result = executor
+        .execute_automations(&[&automation], &case, None)
+        .await
+        .unwrap();
+
+    assert_eq!(result.actions_executed, 2);
+    assert_eq!(result.total_delay_ms, 5);
+}
+
+#[tokio::test]
+async fn test_long_delay_defers_remaining_actions() {
+    let executor = AutomationExecutor::with_limits(AutomationLimits {
+        max_total_delay_ms: 86_400_000,
+        ..Default::default()
+    });
+
+    let first = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: vec![
+            AutomationAction::SetField {
+                name: None,
+                field: "data.reminded".to_string(),
+                value: json!(false),
+            },
+            AutomationAction::Conditional {
+                name: None,
+                condition: Condition::Simple {
+                    field: "data.amount".to_string(),
+                    operator: ">".to_string(),
+                    value: json!(0),
+                },
+                then: vec![
+                    AutomationAction::Delay { name: None, duration_ms: 86_400_000 },
+                    AutomationAction::SetField {
+                        name: None,
+                        field: "data.reminded".to_string(),
+                        value: json!(true),
+                    },
+                ],
+                r#else: None,
+            },
+            AutomationAction::MoveToPhase {
+                name: None,
+                phase: "Escalated".to_string(),
+            },
+        ],
+    };
+    let second = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Review".to_string(),
+        actions: vec![AutomationAction::MoveToPhase {
+            name: None,
+            phase: "Closed".to_string(),
+        }],
+    };
+
+    let case = Case::new(Uuid::new_v4(), "Review".to_string(), json!({"amount": 1}), None);
+
+    let started = std::time::Instant::now();
+    let result = executor
+        .execute_automations(&[&first, &second], &case, None)
+        .await
+        .unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+    assert_eq!(result.modifications.len(), 1);
+    assert_eq!(result.total_delay_ms, 86_400_000);
+
+    let deferred = result.deferred.expect("run should be deferred");
+    assert!(deferred.resume_at > chrono::Utc::now() + chrono::Duration::hours(23));
+
+    let remaining: Vec<_> = deferred
+        .actions
+        .iter()
+        .map(|a| serde_json::to_value(a).unwrap()["type"].clone())
+        .collect();
+    assert_eq!(remaining, vec![json!("set_field"), json!("move_to_phase"), json!("move_to_phase")]);
+}
+
+async fn spawn_twilio_stub(
+    status: axum::http::StatusCode,
+    response: serde_json::Value,
+) -> (String, std::sync::Arc<std::sync::Mutex<Vec<std::collections::HashMap<String, String>>>>) {
+    use axum::{extract::Form, routing::post, Json, Router};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let recorded = received.clone();
+    let app = Router::new().route(
+        "/2010-04-01/Accounts/AC123/Messages.json",
+        post(move |Form(form): Form<HashMap<String, String>>| {
+            let recorded = recorded.clone();
+            let response = response.clone();
+            async move {
+                recorded.lock().unwrap().push(form);
+                (status, Json(response))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    (format!("http://{}", addr), received)
+}
+
+fn twilio_config(api_base: String) -> orchepy::services::notification::TwilioConfig {
+    orchepy::services::notification::TwilioConfig {
+        account_sid: "AC123".to_string(),
+        auth_token: "secret".to_string(),
+        from: "+15550000000".to_string(),
+        whatsapp_from: None,
+        api_base,
+    }
+}
+
+fn send_message(channel: MessageChannel) -> PhaseAutomation {
+    PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Shipped".to_string(),
+        actions: vec![AutomationAction::SendMessage {
+            id: Some("sms".to_string()),
+            name: None,
+            channel,
+            to: "${data.phone}".to_string(),
+            subject: None,
+            body: "Hi ${data.name}, order ${data.order.number} is ${current_phase}".to_string(),
+            opt_out_field: "data.sms_opt_out".to_string(),
+            on_error: OnError::Stop,
+        }],
+    }
+}
+
+#[tokio::test]
+async fn test_send_message_renders_template() {
+    let (api_base, received) = spawn_twilio_stub(
+        axum::http::StatusCode::CREATED,
+        json!({"sid": "SM1", "status": "queued"}),
+    )
+    .await;
+    let executor = AutomationExecutor::new().with_twilio(Some(twilio_config(api_base)));
+
+    let case = Case::new(
+        Uuid::new_v4(),
+        "Shipped".to_string(),
+        json!({"phone": "+15551234567", "name": "Ana", "order": {"number": 42}}),
+        None,
+    );
+
+    let result = executor
+        .execute_automations(&[&send_message(MessageChannel::Whatsapp)], &case, None)
+        .await
+        .unwrap();
+
+    match &result.modifications[..] {
+        [CaseModification::RecordMessage(message)] => {
+            assert_eq!(message.case_id, case.id);
+            assert_eq!(message.channel, MessageChannel::Whatsapp);
+            assert_eq!(message.recipient.as_deref(), Some("+15551234567"));
+            assert_eq!(message.external_id.as_deref(), Some("SM1"));
+            assert!(message.reply_token.is_some());
+        }
+        other => panic!("Expected RecordMessage, got {:?}", other),
+    }
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0]["To"], "whatsapp:+15551234567");
+    assert_eq!(received[0]["From"], "whatsapp:+15550000000");
+    assert_eq!(received[0]["Body"], "Hi Ana, order 42 is Shipped");
+}
+
+#[tokio::test]
+async fn test_send_message_skips_opted_out_case() {
+    let executor = AutomationExecutor::new();
+
+    let case = Case::new(
+        Uuid::new_v4(),
+        "Shipped".to_string(),
+        json!({"phone": "+15551234567", "name": "Ana", "order": {"number": 42}, "sms_opt_out": true}),
+        None,
+    );
+
+    let result = executor
+        .execute_automations(&[&send_message(MessageChannel::Sms)], &case, None)
+        .await
+        .unwrap();
+
+    assert!(result.modifications.is_empty());
+}
+
+#[tokio::test]
+async fn test_send_message_unsubscribed_recipient_sets_opt_out() {
+    let (api_base, _) = spawn_twilio_stub(
+        axum::http::StatusCode::BAD_REQUEST,
+        json!({"code": 21610, "message": "Attempt to send to unsubscribed recipient", "status": 400}),
+    )
+    .await;
+    let executor = AutomationExecutor::new().with_twilio(Some(twilio_config(api_base)));
+
+    let case = Case::new(
+        Uuid::new_v4(),
+        "Shipped".to_string(),
+        json!({"phone": "+15551234567", "name": "Ana", "order": {"number": 42}}),
+        None,
+    );
+
+    let result = executor
+        .execute_automations(&[&send_message(MessageChannel::Sms)], &case, None)
+        .await
+        .unwrap();
+
+    match &result.modifications[..] {
+        [CaseModification::SetField { field, value, .. }] => {
+            assert_eq!(field, "data.sms_opt_out");
+            assert_eq!(value, &json!(true));
+        }
+        other => panic!("Expected opt-out SetField, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_send_message_without_twilio_fails() {
+    let executor = AutomationExecutor::new();
+
+    let case = Case::new(
+        Uuid::new_v4(),
+        "Shipped".to_string(),
+        json!({"phone": "+15551234567", "name": "Ana", "order": {"number": 42}}),
+        None,
+    );
+
+    assert!(executor
+        .execute_automations(&[&send_message(MessageChannel::Sms)], &case, None)
+        .await
+        .is_err());
+}
+
+struct RecordingMailer {
+    sent: std::sync::Mutex<Vec<orchepy::services::mailer::OutboundEmail>>,
+}
+
+#[async_trait::async_trait]
+impl orchepy::services::mailer::Mailer for RecordingMailer {
+    async fn send(&self, email: &orchepy::services::mailer::OutboundEmail) -> anyhow::Result<()> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_send_email_records_reply_token() {
+    let mailer = std::sync::Arc::new(RecordingMailer {
+        sent: std::sync::Mutex::new(Vec::new()),
+    });
+    let executor = AutomationExecutor::new().with_mailer(Some(mailer.clone()));
+
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "Shipped".to_string(),
+        actions: vec![AutomationAction::SendMessage {
+            id: None,
+            name: None,
+            channel: MessageChannel::Email,
+            to: "${data.email}".to_string(),
+            subject: Some("Order ${data.order.number}".to_string()),
+            body: "Hi ${data.name}, reply to this email with any questions.".to_string(),
+            opt_out_field: "data.sms_opt_out".to_string(),
+            on_error: OnError::Stop,
+        }],
+    };
+
+    let case = Case::new(
+        Uuid::new_v4(),
+        "Shipped".to_string(),
+        json!({"email": "ana@example.com", "name": "Ana", "order": {"number": 42}}),
+        None,
+    );
+
+    let result = executor
+        .execute_automations(&[&automation], &case, None)
+        .await
+        .unwrap();
+
+    let token = match &result.modifications[..] {
+        [CaseModification::RecordMessage(message)] => {
+            assert_eq!(message.channel, MessageChannel::Email);
+            assert_eq!(message.recipient.as_deref(), Some("ana@example.com"));
+            assert_eq!(message.subject.as_deref(), Some("Order 42"));
+            message.reply_token.clone().unwrap()
+        }
+        other => panic!("Expected RecordMessage, got {:?}", other),
+    };
+
+    let sent = mailer.sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "ana@example.com");
+    assert_eq!(sent[0].subject, "Order 42");
+    assert_eq!(sent[0].reply_token.as_deref(), Some(token.as_str()));
+}
@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use orchepy::api::{build_router, AppState};
+use orchepy::services::{LocalStorage, WebhookSender};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+async fn create_case(client: &reqwest::Client, base: &str) -> String {
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Claims", "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    format!("{}/cases/{}", base, case["id"].as_str().unwrap())
+}
+
+/// Files below `dir`, in any subdirectory.
+fn files_in(dir: &Path) -> usize {
+    walk(dir).len()
+}
+
+fn walk(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .flat_map(|entry| if entry.path().is_dir() { walk(&entry.path()) } else { vec![entry.path()] })
+        .collect()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_attachments(pool: PgPool) {
+    let root = std::env::temp_dir().join(format!("orchepy-attachments-{}", Uuid::new_v4().simple()));
+    let state = AppState::new(pool.clone(), WebhookSender::new()).with_storage(Arc::new(LocalStorage::new(&root)));
+    let base = common::serve(build_router(state)).await;
+    let client = reqwest::Client::new();
+    let case_url = create_case(&client, &base).await;
+
+    let response = client
+        .post(format!("{}/attachments?filename=Relatório.pdf&uploaded_by=ana", case_url))
+        .header("Content-Type", "application/pdf")
+        .body("%PDF-1.7 claim report")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let attachment: Value = response.json().await.unwrap();
+    assert_eq!(attachment["filename"], "Relatório.pdf");
+    assert_eq!(attachment["content_type"], "application/pdf");
+    assert_eq!(attachment["size_bytes"], 21);
+    assert_eq!(attachment["uploaded_by"], "ana");
+    assert!(attachment.get("storage_key").is_none());
+
+    let invalid = client
+        .post(format!("{}/attachments?filename=../etc/passwd", case_url))
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(invalid.status(), 422);
+
+    let attachments: Vec<Value> =
+        client.get(format!("{}/attachments", case_url)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0]["id"], attachment["id"]);
+
+    let attachment_url = format!("{}/attachments/{}", case_url, attachment["id"].as_str().unwrap());
+    let download = client.get(&attachment_url).send().await.unwrap();
+    assert_eq!(download.status(), 200);
+    assert_eq!(download.headers()["content-type"], "application/pdf");
+    assert_eq!(
+        download.headers()["content-disposition"],
+        "attachment; filename=\"Relat_rio.pdf\"; filename*=UTF-8''Relat%C3%B3rio.pdf"
+    );
+    assert_eq!(download.text().await.unwrap(), "%PDF-1.7 claim report");
+    assert_eq!(files_in(&root), 1);
+
+    let response = client.delete(&attachment_url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(files_in(&root), 0);
+
+    let missing = client.get(&attachment_url).send().await.unwrap();
+    assert_eq!(missing.status(), 404);
+    assert_eq!(missing.json::<Value>().await.unwrap()["code"], "CASE_ATTACHMENT_NOT_FOUND");
+
+    let response = client
+        .post(format!("{}/cases/{}/attachments?filename=a.txt", base, Uuid::new_v4()))
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    // Purging the case removes the files of its attachments.
+    let response = client.post(format!("{}/attachments?filename=notes.txt", case_url)).body("notes").send().await.unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(files_in(&root), 1);
+    client.delete(&case_url).send().await.unwrap();
+    let response = client.delete(format!("{}/purge", case_url)).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(files_in(&root), 0);
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_attachments_need_storage(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+    let case_url = create_case(&client, &base).await;
+
+    let response = client.post(format!("{}/attachments?filename=a.txt", case_url)).body("x").send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.json::<Value>().await.unwrap()["code"], "STORAGE_NOT_CONFIGURED");
+}
@@ -0,0 +1,106 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+async fn create_workflow(client: &reqwest::Client, base: &str, name: &str) -> String {
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": name, "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    workflow["id"].as_str().unwrap().to_string()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_external_id_lookup_and_upsert(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+    let orders = create_workflow(&client, &base, "Orders").await;
+    let returns = create_workflow(&client, &base, "Returns").await;
+
+    let create = |body: Value| {
+        let request = client.post(format!("{}/cases", base)).json(&body);
+        async move {
+            let response = request.send().await.unwrap();
+            let status = response.status().as_u16();
+            (status, response.json::<Value>().await.unwrap())
+        }
+    };
+
+    let (status, case) = create(json!({
+        "workflow_id": orders,
+        "external_id": "crm-42",
+        "data": {"amount": 10, "customer": "Acme"},
+        "tags": ["vip"]
+    }))
+    .await;
+    assert_eq!(status, 201);
+    assert_eq!(case["external_id"], "crm-42");
+
+    let (status, conflict) = create(json!({"workflow_id": orders, "external_id": "crm-42", "data": {}})).await;
+    assert_eq!(status, 409);
+    assert_eq!(conflict["code"], "CASE_ALREADY_EXISTS");
+    assert_eq!(conflict["case_id"], case["id"]);
+
+    let (status, upserted) = create(json!({
+        "workflow_id": orders,
+        "external_id": "crm-42",
+        "upsert": true,
+        "data": {"amount": 25, "customer": null},
+        "tags": ["eu"],
+        "triggered_by": "crm"
+    }))
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(upserted["id"], case["id"]);
+    assert_eq!(upserted["data"], json!({"amount": 25}));
+    assert_eq!(upserted["tags"], json!(["vip", "eu"]));
+    assert_eq!(upserted["field_provenance"]["amount"]["actor"], "crm");
+    assert_eq!(upserted["current_phase"], "New");
+
+    let (status, other) = create(json!({"workflow_id": returns, "external_id": "crm-42", "data": {}})).await;
+    assert_eq!(status, 201);
+    assert_ne!(other["id"], case["id"]);
+
+    let (status, created) =
+        create(json!({"workflow_id": orders, "external_id": "crm-43", "upsert": true, "data": {"amount": 1}})).await;
+    assert_eq!(status, 201);
+    assert_eq!(created["external_id"], "crm-43");
+
+    let (status, _) = create(json!({"workflow_id": orders, "upsert": true, "data": {}})).await;
+    assert_eq!(status, 422);
+    let (status, _) = create(json!({"workflow_id": orders, "external_id": "", "data": {}})).await;
+    assert_eq!(status, 422);
+
+    let lookup = |external_id: &'static str, workflow_id: &str| {
+        client
+            .get(format!("{}/cases/by-external-id/{}", base, external_id))
+            .query(&[("workflow_id", workflow_id)])
+            .send()
+    };
+
+    let response = lookup("crm-42", &orders).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["etag"], format!("\"{}\"", upserted["version"]).as_str());
+    let found: Value = response.json().await.unwrap();
+    assert_eq!(found["id"], case["id"]);
+
+    let found: Value = lookup("crm-42", &returns).await.unwrap().json().await.unwrap();
+    assert_eq!(found["id"], other["id"]);
+    assert_eq!(lookup("crm-99", &orders).await.unwrap().status(), 404);
+
+    let response = client.delete(format!("{}/cases/{}", base, case["id"].as_str().unwrap())).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(lookup("crm-42", &orders).await.unwrap().status(), 404);
+
+    let (status, recreated) = create(json!({"workflow_id": orders, "external_id": "crm-42", "data": {}})).await;
+    assert_eq!(status, 201);
+    assert_ne!(recreated["id"], case["id"]);
+}
@@ -0,0 +1,238 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_sort_and_filter_case_listing(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let mut ids = Vec::new();
+    for (data, source) in [
+        (json!({"amount": 20}), "zapier"),
+        (json!({"amount": 5}), "api"),
+        (json!({}), "zapier"),
+        (json!({"amount": 100}), "zapier"),
+    ] {
+        let case: Value = client
+            .post(format!("{}/cases", base))
+            .json(&json!({"workflow_id": workflow["id"], "data": data, "metadata": {"source": source}}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push(case["id"].clone());
+    }
+
+    let list = |query: &'static str| {
+        let request = client.get(format!("{}/cases?workflow_id={}&{}", base, workflow["id"].as_str().unwrap(), query));
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200, "{}", query);
+            let cases: Vec<Value> = response.json().await.unwrap();
+            cases.iter().map(|case| case["id"].clone()).collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(list("").await, vec![ids[3].clone(), ids[2].clone(), ids[1].clone(), ids[0].clone()]);
+    assert_eq!(list("sort=created_at&order=asc").await, ids);
+    assert_eq!(
+        list("sort=data.amount&order=asc").await,
+        vec![ids[1].clone(), ids[0].clone(), ids[3].clone(), ids[2].clone()]
+    );
+    assert_eq!(list("sort=data.amount&order=desc&limit=2").await, vec![ids[3].clone(), ids[0].clone()]);
+    assert_eq!(
+        list("metadata.source=zapier&sort=data.amount").await,
+        vec![ids[3].clone(), ids[0].clone(), ids[2].clone()]
+    );
+
+    for query in ["sort=priority", "sort=data.a'b", "order=sideways", "metadata.source[between]=1"] {
+        let response = client.get(format!("{}/cases?{}", base, query)).send().await.unwrap();
+        assert_eq!(response.status(), 400, "{}", query);
+    }
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_embed_workflow_summaries(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let mut workflows = Vec::new();
+    for (name, phases) in [("Sales", json!(["Lead", "Won"])), ("Support", json!(["Open", "Closed"]))] {
+        let workflow: Value = client
+            .post(format!("{}/workflows", base))
+            .json(&json!({"name": name, "phases": phases, "initial_phase": phases[0]}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let response = client
+                .post(format!("{}/cases", base))
+                .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 201);
+        }
+        workflows.push(workflow);
+    }
+
+    let cases: Vec<Value> = client
+        .get(format!("{}/cases?embed=workflow", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(cases.len(), 4);
+    for case in &cases {
+        let workflow = workflows.iter().find(|w| w["id"] == case["workflow_id"]).unwrap();
+        assert_eq!(case["workflow"]["name"], workflow["name"]);
+        assert_eq!(case["workflow"]["phases"], workflow["phases"]);
+        assert_eq!(case["workflow"]["initial_phase"], workflow["initial_phase"]);
+    }
+
+    let plain: Vec<Value> = client.get(format!("{}/cases", base)).send().await.unwrap().json().await.unwrap();
+    assert!(plain.iter().all(|case| case.get("workflow").is_none()));
+
+    let response = client.get(format!("{}/cases?embed=flows", base)).send().await.unwrap();
+    assert_eq!(response.status(), 400);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_sparse_fieldsets(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {"amount": 10, "notes": "long text"}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let get = |path: &str| client.get(format!("{}{}", base, path)).send();
+
+    let cases: Vec<Value> = get("/cases?fields=id,current_phase,data.amount").await.unwrap().json().await.unwrap();
+    assert_eq!(cases, vec![json!({"id": case["id"], "current_phase": "New", "data": {"amount": 10}})]);
+
+    let page: Value = get("/cases/search?fields=id&envelope=true&data.amount=10").await.unwrap().json().await.unwrap();
+    assert_eq!(page["items"], json!([{"id": case["id"]}]));
+    assert_eq!(page["total"], 1);
+
+    let embedded: Vec<Value> =
+        get("/cases?embed=workflow&fields=id,workflow.name").await.unwrap().json().await.unwrap();
+    assert_eq!(embedded, vec![json!({"id": case["id"], "workflow": {"name": "Orders"}})]);
+
+    assert_eq!(get("/executions?fields=id,status").await.unwrap().status(), 200);
+
+    for path in ["/cases?fields=secret", "/cases?fields=status.name", "/cases?fields=", "/executions?fields=data"] {
+        let response = get(path).await.unwrap();
+        assert_eq!(response.status(), 400, "{}", path);
+    }
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_data_containment_filter(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let mut ids = Vec::new();
+    for data in [
+        json!({"vip": true, "customer": {"tier": "gold"}, "labels": ["a", "b"]}),
+        json!({"vip": false, "customer": {"tier": "gold"}}),
+        json!({"vip": true, "customer": {"tier": "silver"}, "labels": ["b"]}),
+    ] {
+        let case: Value = client
+            .post(format!("{}/cases", base))
+            .json(&json!({"workflow_id": workflow["id"], "data": data}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push(case["id"].clone());
+    }
+
+    let list = |data_contains: &'static str| {
+        let request = client.get(format!("{}/cases", base)).query(&[
+            ("workflow_id", workflow["id"].as_str().unwrap()),
+            ("data_contains", data_contains),
+            ("sort", "created_at"),
+            ("order", "asc"),
+        ]);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200, "{}", data_contains);
+            let cases: Vec<Value> = response.json().await.unwrap();
+            cases.iter().map(|case| case["id"].clone()).collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(list(r#"{"vip":true}"#).await, vec![ids[0].clone(), ids[2].clone()]);
+    assert_eq!(list(r#"{"customer":{"tier":"gold"}}"#).await, vec![ids[0].clone(), ids[1].clone()]);
+    assert_eq!(list(r#"{"vip":true,"labels":["b"]}"#).await, vec![ids[0].clone(), ids[2].clone()]);
+    assert_eq!(list(r#"{"labels":["a"]}"#).await, vec![ids[0].clone()]);
+    assert!(list(r#"{"vip":"yes"}"#).await.is_empty());
+
+    for data_contains in ["[1]", "{vip", "true"] {
+        let response = client
+            .get(format!("{}/cases", base))
+            .query(&[("data_contains", data_contains)])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400, "{}", data_contains);
+    }
+
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("SET enable_seqscan = off").execute(&mut *conn).await.unwrap();
+    let plan: Vec<String> = sqlx::query_scalar("EXPLAIN SELECT * FROM orchepy_cases WHERE data @> $1")
+        .bind(json!({"vip": true}))
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+    assert!(plan.iter().any(|line| line.contains("idx_orchepy_cases_data")), "{:?}", plan);
+}
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{extract::State, routing::post, Json, Router};
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_move_rolls_back_when_automation_writes_fail(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Deals",
+            "phases": ["New", "Review", "Won"],
+            "initial_phase": "New",
+            "automations": {"automations": [
+                {
+                    "trigger": "on_enter",
+                    "phase": "Review",
+                    "actions": [{"type": "set_field", "name": "flag", "field": "data.reviewed", "value": true}]
+                },
+                {
+                    "trigger": "on_enter",
+                    "phase": "Won",
+                    "actions": [{"type": "set_field", "name": "block", "field": "data.blocked", "value": true}]
+                }
+            ]}
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {"value": 100}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case_url = format!("{}/cases/{}", base, case["id"].as_str().unwrap());
+
+    // Any write of data.blocked fails, as a crash half-way through would.
+    sqlx::query("ALTER TABLE orchepy_cases ADD CONSTRAINT no_blocked CHECK (NOT data ? 'blocked')")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let response = client.put(format!("{}/move", case_url)).json(&json!({"to_phase": "Review"})).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let moved: Value = response.json().await.unwrap();
+    assert_eq!(moved["current_phase"], "Review");
+    assert_eq!(moved["data"]["reviewed"], true);
+
+    let response = client.put(format!("{}/move", case_url)).json(&json!({"to_phase": "Won"})).send().await.unwrap();
+    assert_eq!(response.status(), 500);
+
+    let case: Value = client.get(&case_url).send().await.unwrap().json().await.unwrap();
+    assert_eq!(case["current_phase"], "Review");
+    assert_eq!(case["previous_phase"], "New");
+    assert!(case["data"].get("blocked").is_none());
+
+    let history: Vec<Value> = client.get(format!("{}/history", case_url)).send().await.unwrap().json().await.unwrap();
+    let phases: Vec<_> = history.iter().map(|entry| entry["to_phase"].clone()).collect();
+    assert!(!phases.contains(&json!("Won")));
+
+    let runs: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orchepy_automation_runs WHERE phase = 'Won'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(runs, 0);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_concurrent_moves_of_a_case_serialize(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let phases = ["New", "A", "B", "C", "D", "E", "F", "G", "H"];
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Board", "phases": phases, "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case_url = format!("{}/cases/{}", base, case["id"].as_str().unwrap());
+
+    let moves = phases[1..].iter().map(|phase| {
+        let (client, case_url) = (client.clone(), case_url.clone());
+        let body = json!({"to_phase": phase, "reason": "concurrent"});
+        async move { client.put(format!("{}/move", case_url)).json(&body).send().await.unwrap() }
+    });
+    for response in futures::future::join_all(moves).await {
+        assert_eq!(response.status(), 200);
+    }
+
+    // Each move starts from the phase the one before it ended in.
+    let mut history: Vec<Value> = client.get(format!("{}/history", case_url)).send().await.unwrap().json().await.unwrap();
+    history.reverse();
+    let transitions: Vec<_> = history.iter().filter(|entry| entry["reason"] == "concurrent").collect();
+    assert_eq!(transitions.len(), phases.len() - 1);
+    assert_eq!(transitions[0]["from_phase"], "New");
+    for pair in transitions.windows(2) {
+        assert_eq!(pair[1]["from_phase"], pair[0]["to_phase"]);
+    }
+
+    let case: Value = client.get(&case_url).send().await.unwrap().json().await.unwrap();
+    let last = transitions.last().unwrap();
+    assert_eq!(case["current_phase"], last["to_phase"]);
+    assert_eq!(case["previous_phase"], last["from_phase"]);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_automation_webhooks_run_after_the_move_commits(pool: PgPool) {
+    // A receiver that holds every webhook until it is released.
+    let (tx, mut received) = tokio::sync::mpsc::unbounded_channel::<Value>();
+    let release = Arc::new(tokio::sync::Notify::new());
+    let receiver = Router::new()
+        .route(
+            "/hook",
+            post(|State((tx, release)): State<(tokio::sync::mpsc::UnboundedSender<Value>, Arc<tokio::sync::Notify>)>,
+                  Json(body): Json<Value>| async move {
+                tx.send(body).unwrap();
+                release.notified().await;
+                Json(json!({"ok": true}))
+            }),
+        )
+        .with_state((tx, release.clone()));
+    let hook = format!("{}/hook", common::serve(receiver).await);
+
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Deals",
+            "phases": ["New", "Review", "Won"],
+            "initial_phase": "New",
+            "automations": {"automations": [{
+                "trigger": "on_enter",
+                "phase": "Review",
+                "actions": [
+                    {"type": "set_field", "name": "before", "field": "data.before", "value": true},
+                    {"type": "webhook", "name": "notify", "url": hook},
+                    {"type": "set_field", "name": "after", "field": "data.after", "value": true}
+                ]
+            }]}
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case_url = format!("{}/cases/{}", base, case["id"].as_str().unwrap());
+
+    // The move commits with the actions before the webhook, without waiting
+    // for the receiver.
+    let response = client.put(format!("{}/move", case_url)).json(&json!({"to_phase": "Review"})).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let moved: Value = response.json().await.unwrap();
+    assert_eq!(moved["data"]["before"], true);
+    assert!(moved["data"].get("after").is_none());
+
+    let sent = tokio::time::timeout(Duration::from_secs(5), received.recv()).await.unwrap().unwrap();
+    assert_eq!(sent["current_phase"], "Review");
+    assert_eq!(sent["data"]["before"], true);
+
+    // The case isn't locked while the webhook waits.
+    let response = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.put(format!("{}/move", case_url)).json(&json!({"to_phase": "Won"})).send(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(response.status(), 200);
+
+    release.notify_one();
+    let mut case = Value::Null;
+    for _ in 0..50 {
+        case = client.get(&case_url).send().await.unwrap().json().await.unwrap();
+        if case["data"]["after"] == true {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(case["data"]["after"], true);
+    assert_eq!(case["current_phase"], "Won");
+}
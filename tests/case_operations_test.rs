@@ -1,6 +1,7 @@
-use orchepy::models::case::{Case, CaseStatus};
+use orchepy::models::case::{Case, CaseHistory, CaseLifecycleAction, CaseSearch, CaseStatus, FieldProvenance, ProvenanceSource};
+use orchepy::models::patch::{DataPatch, PatchError, PatchOutcome};
 use orchepy::models::Workflow;
-use orchepy::repositories::{CaseRepository, WorkflowRepository};
+use orchepy::repositories::{CaseRepository, ChangeRepository, WorkflowRepository};
 use serde_json::json;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -19,13 +20,24 @@ async fn setup_test_workflow(pool: &PgPool) -> Workflow {
     let workflow = Workflow {
         id: Uuid::new_v4(),
         name: "Test Workflow".to_string(),
-        phases: phases.clone(),
+        phases: phases.into_iter().map(Into::into).collect(),
         initial_phase: "New".to_string(),
         webhook_url: None,
+        webhook_template: None,
+        webhook_headers: Default::default(),
+        webhook_auth: None,
         active: true,
         description: None,
         automations: None,
         sla_config: None,
+        execution_limits: Default::default(),
+        data_conflicts: Default::default(),
+        transitions: Default::default(),
+        region: None,
+        version: 1,
+        updated_by: None,
+        archived_at: None,
+        archived_by: None,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
     };
@@ -109,6 +121,33 @@ async fn test_jsonb_set_field(pool: PgPool) {
     assert_eq!(updated_case.data["amount"], 1000);
 }
 
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_field_provenance(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+    let repo = CaseRepository::new(&pool);
+
+    let api = FieldProvenance::api(Some("agent@example.com".to_string()));
+    let data = json!({"amount": 1500, "note": "raised"});
+    assert!(repo.update_data_by(case.id, &data, &api).await.unwrap());
+
+    let automation = FieldProvenance::automation(Some("flag".to_string()), "on_enter");
+    repo.set_field_by(case.id, "flagged", &json!(true), &automation).await.unwrap();
+
+    let case = repo.find_by_id(case.id).await.unwrap().unwrap();
+    let provenance = &case.field_provenance;
+    assert_eq!(provenance["amount"].actor.as_deref(), Some("agent@example.com"));
+    assert_eq!(provenance["note"].source, ProvenanceSource::Api);
+    assert_eq!(provenance["flagged"].source, ProvenanceSource::Automation);
+    assert_eq!(provenance["flagged"].actor.as_deref(), Some("flag"));
+    assert_eq!(provenance["flagged"].trigger.as_deref(), Some("on_enter"));
+
+    repo.update_data_by(case.id, &json!({"amount": 1500}), &FieldProvenance::api(None)).await.unwrap();
+    let case = repo.find_by_id(case.id).await.unwrap().unwrap();
+    assert_eq!(case.field_provenance.keys().collect::<Vec<_>>(), vec!["amount"]);
+    assert_eq!(case.field_provenance["amount"].actor.as_deref(), Some("agent@example.com"));
+}
+
 #[sqlx::test(migrations = "src/db/migrations")]
 async fn test_case_history_creation(pool: PgPool) {
     let workflow = setup_test_workflow(&pool).await;
@@ -157,6 +196,33 @@ async fn test_list_cases_by_phase(pool: PgPool) {
     assert_eq!(cases_in_review[0].id, case1.id);
 }
 
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_soft_delete_and_purge(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+    let _other = create_test_case(&pool, workflow.id).await;
+
+    let repo = CaseRepository::new(&pool);
+    repo.update_phase(case.id, "Review", Some("New")).await.unwrap();
+    repo.create_history(&CaseHistory::new(case.id, Some("New".to_string()), "Review".to_string(), None, None))
+        .await
+        .unwrap();
+    assert!(!repo.purge(case.id).await.unwrap(), "active cases can't be purged");
+
+    assert!(repo.soft_delete(case.id).await.unwrap());
+    assert!(!repo.soft_delete(case.id).await.unwrap());
+
+    assert!(repo.find_by_id(case.id).await.unwrap().is_none());
+    let deleted = repo.find_by_id_with_deleted(case.id).await.unwrap().unwrap();
+    assert!(deleted.deleted_at.is_some());
+    assert_eq!(repo.list_by_workflow(workflow.id, 10, 0).await.unwrap().len(), 1);
+    assert_eq!(repo.count_by_workflow(workflow.id).await.unwrap(), 1);
+
+    assert!(repo.purge(case.id).await.unwrap());
+    assert!(repo.find_by_id_with_deleted(case.id).await.unwrap().is_none());
+    assert!(repo.get_history(case.id).await.unwrap().is_empty());
+}
+
 #[sqlx::test(migrations = "src/db/migrations")]
 async fn test_phase_entered_at_tracking(pool: PgPool) {
     let workflow = setup_test_workflow(&pool).await;
@@ -190,6 +256,33 @@ async fn test_case_status_transitions(pool: PgPool) {
     assert_eq!(updated_case.status, CaseStatus::Completed);
 }
 
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_lifecycle_status_history(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let mut case = create_test_case(&pool, workflow.id).await;
+    let repo = CaseRepository::new(&pool);
+
+    CaseLifecycleAction::Pause.apply(&mut case);
+    assert!(repo.transition_status(&case, &CaseStatus::Active).await.unwrap());
+    assert!(!repo.transition_status(&case, &CaseStatus::Active).await.unwrap());
+
+    CaseLifecycleAction::Complete.apply(&mut case);
+    assert!(repo.transition_status(&case, &CaseStatus::Paused).await.unwrap());
+    repo.create_history(&CaseHistory::status_change(&case, CaseStatus::Paused, Some("Done".to_string()), None))
+        .await
+        .unwrap();
+
+    let stored = repo.find_by_id(case.id).await.unwrap().unwrap();
+    assert_eq!(stored.status, CaseStatus::Completed);
+    assert!(stored.completed_at.is_some());
+
+    let histories = repo.get_history(case.id).await.unwrap();
+    assert_eq!(histories.len(), 1);
+    assert_eq!(histories[0].from_status, Some(CaseStatus::Paused));
+    assert_eq!(histories[0].to_status, Some(CaseStatus::Completed));
+    assert_eq!(histories[0].to_phase, "New");
+}
+
 #[sqlx::test(migrations = "src/db/migrations")]
 async fn test_metadata_handling(pool: PgPool) {
     let workflow = setup_test_workflow(&pool).await;
@@ -205,3 +298,1054 @@ async fn test_metadata_handling(pool: PgPool) {
     let updated_case = repo.find_by_id(case.id).await.unwrap().unwrap();
     assert!(updated_case.metadata.is_some());
 }
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_deferred_automation_claim(pool: PgPool) {
+    use orchepy::models::automation::{AutomationAction, DeferredAutomation};
+    use orchepy::repositories::DeferredAutomationRepository;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+
+    let deferred = |resume_at| DeferredAutomation {
+        id: Uuid::new_v4(),
+        case_id: case.id,
+        workflow_id: workflow.id,
+        trigger: "on_enter".to_string(),
+        phase: "New".to_string(),
+        from_phase: None,
+        actions: vec![AutomationAction::MoveToPhase {
+            name: None,
+            phase: "Review".to_string(),
+        }],
+        resume_at,
+        created_at: chrono::Utc::now(),
+        total_delay_ms: 0,
+        attempts: 0,
+    };
+
+    let repo = DeferredAutomationRepository::new(&pool);
+    let lease = std::time::Duration::from_secs(300);
+    let due = deferred(chrono::Utc::now() - chrono::Duration::seconds(1));
+    repo.create(&due).await.unwrap();
+    repo.create(&deferred(chrono::Utc::now() + chrono::Duration::hours(1))).await.unwrap();
+
+    let claimed = repo.claim_due(chrono::Utc::now(), 10, lease).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_eq!(claimed[0].id, due.id);
+    assert_eq!(claimed[0].actions.len(), 1);
+    assert_eq!(claimed[0].attempts, 1);
+
+    // Leased until the run is settled, or the lease runs out.
+    assert!(repo.claim_due(chrono::Utc::now(), 10, lease).await.unwrap().is_empty());
+    let expired = chrono::Utc::now() + chrono::Duration::minutes(10);
+    let reclaimed = repo.claim_due(expired, 10, lease).await.unwrap();
+    assert_eq!(reclaimed.len(), 1);
+    assert_eq!(reclaimed[0].attempts, 2);
+
+    let retry_at = chrono::Utc::now() + chrono::Duration::hours(3);
+    repo.release(due.id, retry_at).await.unwrap();
+    let later = chrono::Utc::now() + chrono::Duration::hours(2);
+    let claimed = repo.claim_due(later, 10, lease).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert_ne!(claimed[0].id, due.id);
+
+    repo.delete(due.id).await.unwrap();
+    let much_later = chrono::Utc::now() + chrono::Duration::hours(4);
+    let remaining = repo.claim_due(much_later, 10, lease).await.unwrap();
+    assert!(remaining.iter().all(|deferred| deferred.id != due.id));
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_transient_automation_failures_are_retried(pool: PgPool) {
+    use orchepy::models::automation::{AutomationRun, AutomationRunStatus, WorkflowAutomations};
+    use orchepy::repositories::AutomationRunRepository;
+    use orchepy::workers::automation_retry::{retry_failed_runs, AutomationRetryConfig};
+    use std::time::Duration;
+
+    let mut workflow = setup_test_workflow(&pool).await;
+    // Nothing listens on the discard port, so every attempt fails to connect.
+    let automations: WorkflowAutomations = serde_json::from_value(json!({"automations": [{
+        "trigger": "on_enter",
+        "phase": "New",
+        "actions": [{"type": "webhook", "url": "http://127.0.0.1:9/hook"}]
+    }]}))
+    .unwrap();
+    workflow.automations = Some(automations);
+    WorkflowRepository::new(&pool).update(&workflow).await.unwrap();
+    let case = create_test_case(&pool, workflow.id).await;
+
+    let failed = |transient| AutomationRun {
+        id: Uuid::new_v4(),
+        case_id: case.id,
+        workflow_id: workflow.id,
+        trigger: "on_enter".to_string(),
+        phase: "New".to_string(),
+        status: AutomationRunStatus::Failed,
+        actions_executed: 1,
+        total_delay_ms: 0,
+        error: Some("Action 'action_0' failed: HTTP 503".to_string()),
+        started_at: chrono::Utc::now(),
+        completed_at: chrono::Utc::now(),
+        conditions: vec![],
+        from_phase: None,
+        transient,
+        attempt: 1,
+        retry_of: None,
+        retried_at: None,
+        run_as: None,
+        workflow_version: None,
+        definition_hash: None,
+    };
+    let runs = AutomationRunRepository::new(&pool);
+    let original = failed(true);
+    runs.create(&original).await.unwrap();
+    runs.create(&failed(false)).await.unwrap();
+
+    let config = AutomationRetryConfig {
+        poll_interval: Duration::from_secs(1),
+        max_attempts: 3,
+        backoff: Duration::ZERO,
+        window: Duration::from_secs(3600),
+    };
+    assert_eq!(retry_failed_runs(&pool, None, &config).await.unwrap(), 1);
+    assert_eq!(retry_failed_runs(&pool, None, &config).await.unwrap(), 1);
+    assert_eq!(retry_failed_runs(&pool, None, &config).await.unwrap(), 0);
+
+    let retries = runs.list_retries(original.id).await.unwrap();
+    assert_eq!(retries.iter().map(|run| run.attempt).collect::<Vec<_>>(), vec![2, 3]);
+    assert!(retries.iter().all(|run| run.transient && run.status == AutomationRunStatus::Failed));
+    assert!(retries[0].retried_at.is_some());
+    assert!(retries[1].retried_at.is_none());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_automation_runs_pin_their_definition(pool: PgPool) {
+    use orchepy::models::automation::{AutomationRun, AutomationRunStatus, WorkflowAutomations};
+    use orchepy::models::snapshot::DefinitionSnapshot;
+    use orchepy::repositories::{AutomationRunRepository, DefinitionSnapshotRepository};
+    use orchepy::workers::automation_retry::{retry_failed_runs, AutomationRetryConfig};
+    use std::time::Duration;
+
+    let mut workflow = setup_test_workflow(&pool).await;
+    let automations: WorkflowAutomations = serde_json::from_value(json!({"automations": [{
+        "trigger": "on_enter",
+        "phase": "New",
+        "actions": [{"type": "webhook", "url": "http://127.0.0.1:9/hook"}]
+    }]}))
+    .unwrap();
+    workflow.automations = Some(automations);
+    workflow.version = WorkflowRepository::new(&pool).update(&workflow).await.unwrap();
+    let case = create_test_case(&pool, workflow.id).await;
+
+    let original = AutomationRun {
+        id: Uuid::new_v4(),
+        case_id: case.id,
+        workflow_id: workflow.id,
+        trigger: "on_enter".to_string(),
+        phase: "New".to_string(),
+        status: AutomationRunStatus::Failed,
+        actions_executed: 1,
+        total_delay_ms: 0,
+        error: Some("connection refused".to_string()),
+        started_at: chrono::Utc::now(),
+        completed_at: chrono::Utc::now(),
+        conditions: vec![],
+        from_phase: None,
+        transient: true,
+        attempt: 1,
+        retry_of: None,
+        retried_at: None,
+        run_as: None,
+        workflow_version: None,
+        definition_hash: None,
+    };
+    let runs = AutomationRunRepository::new(&pool);
+    runs.create(&original).await.unwrap();
+
+    let config = AutomationRetryConfig {
+        poll_interval: Duration::from_secs(1),
+        max_attempts: 2,
+        backoff: Duration::ZERO,
+        window: Duration::from_secs(3600),
+    };
+    assert_eq!(retry_failed_runs(&pool, None, &config).await.unwrap(), 1);
+
+    let retry = runs.list_retries(original.id).await.unwrap().remove(0);
+    assert_eq!(retry.workflow_version, Some(workflow.version));
+    let hash = retry.definition_hash.expect("run should record its definition");
+    assert_eq!(hash, DefinitionSnapshot::of_workflow(&workflow).hash);
+
+    let snapshot = DefinitionSnapshotRepository::new(&pool).find(&hash).await.unwrap().unwrap();
+    assert_eq!(snapshot.kind, "workflow");
+    assert_eq!(snapshot.definition["automations"]["automations"][0]["phase"], "New");
+    assert!(snapshot.definition.get("version").is_none());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_automations_run_as_service_account(pool: PgPool) {
+    use orchepy::models::automation::{AutomationRun, AutomationRunStatus, WorkflowAutomations};
+    use orchepy::models::service_account::{CreateServiceAccount, ServiceAccount, UpdateServiceAccount};
+    use orchepy::repositories::{AutomationRunRepository, ServiceAccountRepository};
+    use orchepy::workers::automation_retry::{retry_failed_runs, AutomationRetryConfig};
+    use std::time::Duration;
+
+    let accounts = ServiceAccountRepository::new(&pool);
+    let mut account = ServiceAccount::new(CreateServiceAccount {
+        name: "closer".to_string(),
+        description: None,
+        allowed_phases: None,
+        allowed_statuses: Some(vec![CaseStatus::Completed]),
+    });
+    assert!(accounts.create(&account).await.unwrap());
+    assert!(!accounts.create(&account).await.unwrap());
+
+    let mut workflow = setup_test_workflow(&pool).await;
+    let automations: WorkflowAutomations = serde_json::from_value(json!({
+        "run_as": "closer",
+        "automations": [{
+            "trigger": "on_enter",
+            "phase": "New",
+            "actions": [{"type": "set_status", "status": "completed"}]
+        }]
+    }))
+    .unwrap();
+    workflow.automations = Some(automations);
+    WorkflowRepository::new(&pool).update(&workflow).await.unwrap();
+
+    // The retry worker is the public way to re-run a phase's automations.
+    let runs = AutomationRunRepository::new(&pool);
+    let config = AutomationRetryConfig {
+        poll_interval: Duration::from_secs(1),
+        max_attempts: 2,
+        backoff: Duration::ZERO,
+        window: Duration::from_secs(3600),
+    };
+    let run_for = |case: &Case| AutomationRun {
+        id: Uuid::new_v4(),
+        case_id: case.id,
+        workflow_id: workflow.id,
+        trigger: "on_enter".to_string(),
+        phase: "New".to_string(),
+        status: AutomationRunStatus::Failed,
+        actions_executed: 0,
+        total_delay_ms: 0,
+        error: Some("connection refused".to_string()),
+        started_at: chrono::Utc::now(),
+        completed_at: chrono::Utc::now(),
+        conditions: vec![],
+        from_phase: None,
+        transient: true,
+        attempt: 1,
+        retry_of: None,
+        retried_at: None,
+        run_as: None,
+        workflow_version: None,
+        definition_hash: None,
+    };
+
+    let repo = CaseRepository::new(&pool);
+    let allowed = create_test_case(&pool, workflow.id).await;
+    let original = run_for(&allowed);
+    runs.create(&original).await.unwrap();
+    assert_eq!(retry_failed_runs(&pool, None, &config).await.unwrap(), 1);
+
+    let retry = &runs.list_retries(original.id).await.unwrap()[0];
+    assert_eq!(retry.status, AutomationRunStatus::Completed);
+    assert_eq!(retry.run_as.as_deref(), Some("closer"));
+    assert_eq!(repo.find_by_id(allowed.id).await.unwrap().unwrap().status, CaseStatus::Completed);
+    let history = repo.get_history(allowed.id).await.unwrap();
+    assert_eq!(history[0].to_status, Some(CaseStatus::Completed));
+    assert_eq!(history[0].triggered_by.as_deref(), Some("closer"));
+
+    account.apply(UpdateServiceAccount {
+        description: None,
+        allowed_phases: None,
+        allowed_statuses: Some(vec![CaseStatus::Paused]),
+        active: true,
+    });
+    assert!(accounts.update(&account).await.unwrap());
+
+    let denied = create_test_case(&pool, workflow.id).await;
+    let original = run_for(&denied);
+    runs.create(&original).await.unwrap();
+    assert_eq!(retry_failed_runs(&pool, None, &config).await.unwrap(), 1);
+
+    let retry = &runs.list_retries(original.id).await.unwrap()[0];
+    assert_eq!(retry.status, AutomationRunStatus::Failed);
+    assert!(retry.error.as_deref().unwrap().contains("may not set status 'completed'"));
+    assert_eq!(repo.find_by_id(denied.id).await.unwrap().unwrap().status, CaseStatus::Active);
+
+    assert!(accounts.delete("closer").await.unwrap());
+    assert!(accounts.find_by_name("closer").await.unwrap().is_none());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_tags(pool: PgPool) {
+    use orchepy::engine::AutomationExecutor;
+    use orchepy::models::automation::{AutomationAction, AutomationTrigger, Condition, PhaseAutomation};
+
+    let workflow = setup_test_workflow(&pool).await;
+    let repo = CaseRepository::new(&pool);
+
+    let mut vip = Case::new(workflow.id, "New".to_string(), json!({}), None);
+    vip.add_tags(["vip".to_string(), "vip".to_string(), "region:eu".to_string()]);
+    assert_eq!(vip.tags, vec!["vip", "region:eu"]);
+    repo.create(&vip).await.unwrap();
+    let plain = create_test_case(&pool, workflow.id).await;
+
+    let tags = repo.add_tags(vip.id, &["urgent".to_string(), "vip".to_string()], 50).await.unwrap();
+    assert_eq!(tags.unwrap(), vec!["vip", "region:eu", "urgent"]);
+    assert!(repo.add_tags(vip.id, &["one-too-many".to_string()], 3).await.unwrap().is_none());
+    assert!(repo.add_tags(Uuid::new_v4(), &["vip".to_string()], 50).await.unwrap().is_none());
+
+    let params = |tags: &[&str]| -> Vec<(String, String)> {
+        tags.iter().map(|tag| ("tag".to_string(), tag.to_string())).collect()
+    };
+    let found = repo.search(&CaseSearch::from_params(&params(&["vip", "urgent"])).unwrap(), 10, 0).await.unwrap();
+    assert_eq!(found.iter().map(|c| c.id).collect::<Vec<_>>(), vec![vip.id]);
+    assert!(repo.search(&CaseSearch::from_params(&params(&["vip", "gold"])).unwrap(), 10, 0).await.unwrap().is_empty());
+
+    let tags = repo.remove_tag(vip.id, "urgent").await.unwrap().unwrap();
+    assert_eq!(tags, vec!["vip", "region:eu"]);
+    assert_eq!(repo.remove_tag(plain.id, "vip").await.unwrap().unwrap(), Vec::<String>::new());
+
+    let automation = PhaseAutomation {
+        trigger: AutomationTrigger::OnEnter,
+        phase: "New".to_string(),
+        actions: vec![AutomationAction::Conditional {
+            name: None,
+            condition: Condition::Simple {
+                field: "tags".to_string(),
+                operator: "contains".to_string(),
+                value: json!("vip"),
+            },
+            then: vec![AutomationAction::AddTag {
+                name: None,
+                tag: "priority".to_string(),
+            }],
+            r#else: None,
+        }],
+    };
+    let executor = AutomationExecutor::new();
+    let vip = repo.find_by_id(vip.id).await.unwrap().unwrap();
+    assert_eq!(executor.execute_automations(&[&automation], &vip, None).await.unwrap().modifications.len(), 1);
+    assert!(executor.execute_automations(&[&automation], &plain, None).await.unwrap().modifications.is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_search_cases_by_metadata(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let repo = CaseRepository::new(&pool);
+
+    let mut ids = Vec::new();
+    for metadata in [
+        Some(json!({"source": "zapier", "region": "eu", "attempt": 2})),
+        Some(json!({"source": "zapier", "region": "us"})),
+        Some(json!({"source": "api"})),
+        None,
+    ] {
+        let case = Case::new(workflow.id, "New".to_string(), json!({"source": "api"}), metadata);
+        repo.create(&case).await.unwrap();
+        ids.push(case.id);
+    }
+
+    let found = |params: &[(&str, &str)]| {
+        let params: Vec<(String, String)> =
+            params.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        let repo = &repo;
+        async move {
+            let mut found: Vec<Uuid> = repo
+                .search(&CaseSearch::from_params(&params).unwrap(), 10, 0)
+                .await
+                .unwrap()
+                .iter()
+                .map(|case| case.id)
+                .collect();
+            found.sort();
+            found
+        }
+    };
+    let sorted = |mut expected: Vec<Uuid>| {
+        expected.sort();
+        expected
+    };
+
+    assert_eq!(found(&[("metadata.source", "zapier")]).await, sorted(vec![ids[0], ids[1]]));
+    assert_eq!(found(&[("metadata.source", "zapier"), ("metadata.region", "eu")]).await, vec![ids[0]]);
+    assert_eq!(found(&[("metadata.attempt[gte]", "2")]).await, vec![ids[0]]);
+    assert_eq!(found(&[("metadata.region[exists]", "false")]).await, sorted(vec![ids[2], ids[3]]));
+    assert!(found(&[("data.source", "zapier")]).await.is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_import_historical_cases(pool: PgPool) {
+    use orchepy::models::import::ImportCase;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let repo = CaseRepository::new(&pool);
+    let now = chrono::Utc::now();
+    let created = now - chrono::Duration::days(30);
+    let reviewed = now - chrono::Duration::days(20);
+
+    let imported: ImportCase = serde_json::from_value(json!({
+        "data": {"amount": 1200},
+        "status": "completed",
+        "timeline": [
+            {"phase": "New", "entered_at": created},
+            {"phase": "Review", "entered_at": reviewed, "reason": "Spreadsheet row 12"}
+        ]
+    }))
+    .unwrap();
+    let (case, history) = imported.into_case(&workflow, Some("legacy-import"), now).unwrap();
+    let cases = vec![(case.clone(), history)];
+
+    assert!(repo.import(&cases).await.unwrap().is_none());
+    assert_eq!(repo.import(&cases).await.unwrap(), Some(case.id));
+
+    let stored = repo.find_by_id(case.id).await.unwrap().unwrap();
+    assert_eq!(stored.current_phase, "Review");
+    assert_eq!(stored.status, CaseStatus::Completed);
+    assert_eq!(stored.created_at.timestamp(), created.timestamp());
+    assert_eq!(stored.completed_at.map(|t| t.timestamp()), Some(reviewed.timestamp()));
+
+    let history = repo.get_history(case.id).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].to_phase, "Review");
+    assert_eq!(history[0].transitioned_at.timestamp(), reviewed.timestamp());
+    assert_eq!(history[1].triggered_by.as_deref(), Some("legacy-import"));
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_create_cases_and_history_in_batches(pool: PgPool) {
+    use orchepy::repositories::CaseRevisionRepository;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let repo = CaseRepository::new(&pool);
+    let cases: Vec<Case> = (0..1200)
+        .map(|index| Case::new(workflow.id, "New".to_string(), json!({"index": index}), None))
+        .collect();
+    repo.create_batch(&cases).await.unwrap();
+    assert_eq!(repo.count_by_workflow(workflow.id).await.unwrap(), 1200);
+
+    let revisions = CaseRevisionRepository::new(&pool).list_by_case(cases[700].id, 10).await.unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].data, json!({"index": 700}));
+
+    let history: Vec<CaseHistory> = cases
+        .iter()
+        .map(|case| CaseHistory::new(case.id, Some("New".to_string()), "In Progress".to_string(), None, Some("sla".to_string())))
+        .collect();
+    repo.create_history_batch(&history).await.unwrap();
+    let stored = repo.get_history(cases[1100].id).await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].to_phase, "In Progress");
+
+    let fresh = Case::new(workflow.id, "New".to_string(), json!({}), None);
+    assert!(repo.create_batch(&[fresh.clone(), cases[0].clone()]).await.is_err());
+    assert!(repo.find_by_id(fresh.id).await.unwrap().is_none());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_links(pool: PgPool) {
+    use orchepy::models::link::{CaseLink, CaseRelation, CreateCaseLink};
+    use orchepy::repositories::CaseLinkRepository;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let deal = create_test_case(&pool, workflow.id).await;
+    let part = create_test_case(&pool, workflow.id).await;
+    let other = create_test_case(&pool, workflow.id).await;
+    let links = CaseLinkRepository::new(&pool);
+
+    let link = |case_id: Uuid, other: Uuid, relation| {
+        CaseLink::new(case_id, CreateCaseLink { case_id: other, relation, created_by: Some("alice".to_string()) })
+    };
+
+    let split = link(deal.id, part.id, CaseRelation::Child);
+    assert!(links.create(&split).await.unwrap());
+    assert!(!links.create(&link(part.id, deal.id, CaseRelation::Parent)).await.unwrap());
+    assert!(!links.create(&link(deal.id, part.id, CaseRelation::Parent)).await.unwrap());
+    assert!(links.create(&link(other.id, deal.id, CaseRelation::Related)).await.unwrap());
+    assert!(!links.create(&link(deal.id, other.id, CaseRelation::Related)).await.unwrap());
+
+    let seen: Vec<_> = links
+        .list_by_case(deal.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|link| link.seen_from(deal.id))
+        .map(|linked| (linked.case_id, linked.relation))
+        .collect();
+    assert_eq!(seen, vec![(part.id, CaseRelation::Child), (other.id, CaseRelation::Related)]);
+
+    let from_part = links.list_by_case(part.id).await.unwrap()[0].seen_from(part.id);
+    assert_eq!((from_part.case_id, from_part.relation), (deal.id, CaseRelation::Parent));
+
+    assert!(!links.delete(other.id, split.id).await.unwrap());
+    assert!(links.delete(part.id, split.id).await.unwrap());
+    assert_eq!(links.list_by_case(part.id).await.unwrap().len(), 0);
+
+    let repo = CaseRepository::new(&pool);
+    assert!(repo.soft_delete(other.id).await.unwrap());
+    assert!(repo.purge(other.id).await.unwrap());
+    assert!(links.list_by_case(deal.id).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_in_several_workflows(pool: PgPool) {
+    use orchepy::models::membership::CaseWorkflow;
+    use orchepy::repositories::CaseWorkflowRepository;
+
+    let sales = setup_test_workflow(&pool).await;
+    let legal = setup_test_workflow(&pool).await;
+    let deal = create_test_case(&pool, sales.id).await;
+    let other = create_test_case(&pool, sales.id).await;
+    let repo = CaseRepository::new(&pool);
+    let memberships = CaseWorkflowRepository::new(&pool);
+
+    let mut membership = CaseWorkflow::new(deal.id, legal.id, "New".to_string(), Some("alice".to_string()));
+    assert!(memberships.create(&membership).await.unwrap());
+    assert!(!memberships.create(&membership).await.unwrap());
+
+    membership.move_to_phase("Review".to_string());
+    assert!(memberships.update_phase(&membership).await.unwrap());
+    repo.create_history(&membership.history(Some("New".to_string()), None, None)).await.unwrap();
+
+    let search = |workflow_id, phase: &str| CaseSearch {
+        workflow_id: Some(workflow_id),
+        current_phase: Some(phase.to_string()),
+        ..Default::default()
+    };
+    let in_review = repo.search(&search(legal.id, "Review"), 10, 0).await.unwrap();
+    let in_review = memberships.scope(legal.id, in_review).await.unwrap();
+    assert_eq!(in_review.len(), 1);
+    assert_eq!((in_review[0].id, in_review[0].workflow_id), (deal.id, legal.id));
+    assert_eq!(in_review[0].current_phase, "Review");
+
+    assert!(repo.search(&search(legal.id, "New"), 10, 0).await.unwrap().is_empty());
+    assert_eq!(repo.count_search(&search(sales.id, "New")).await.unwrap(), 2);
+
+    let stored = repo.find_by_id(deal.id).await.unwrap().unwrap();
+    assert_eq!((stored.workflow_id, stored.current_phase.as_str()), (sales.id, "New"));
+    let history = repo.get_history(deal.id).await.unwrap();
+    assert_eq!(history[0].workflow_id, Some(legal.id));
+
+    assert!(!memberships.delete(other.id, legal.id).await.unwrap());
+    assert!(memberships.delete(deal.id, legal.id).await.unwrap());
+    assert!(memberships.list_by_case(deal.id).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_global_search_and_board(pool: PgPool) {
+    use orchepy::models::board::{BoardGrouping, BoardQuery};
+    use orchepy::models::case::GlobalSearchQuery;
+
+    let sales = setup_test_workflow(&pool).await;
+    let legal = setup_test_workflow(&pool).await;
+    let repo = CaseRepository::new(&pool);
+
+    for (workflow_id, customer, assignee) in [
+        (sales.id, "Acme 100% Corp", Some("alice")),
+        (legal.id, "Acme Legal", Some("alice")),
+        (legal.id, "Globex", Some("bob")),
+        (sales.id, "Initech", None),
+    ] {
+        let case = Case::new(workflow_id, "New".to_string(), json!({"customer": customer, "assignee": assignee}), None);
+        repo.create(&case).await.unwrap();
+    }
+
+    let pattern = |q: &str| GlobalSearchQuery { q: q.to_string(), status: None, limit: None, offset: None }.pattern();
+    assert!(pattern("  ").is_err());
+
+    let acme = pattern("acme").unwrap();
+    let found = repo.search_text(&acme, None, 10, 0).await.unwrap();
+    let workflows: Vec<_> = found.iter().map(|case| case.workflow_id).collect();
+    assert_eq!(found.len(), 2);
+    assert!(workflows.contains(&sales.id) && workflows.contains(&legal.id));
+    assert_eq!(repo.count_text(&pattern("100%").unwrap(), None).await.unwrap(), 1);
+    assert_eq!(repo.count_text(&pattern("0%C").unwrap(), None).await.unwrap(), 0);
+    assert_eq!(repo.count_text(&acme, Some(&CaseStatus::Completed)).await.unwrap(), 0);
+
+    let board = |group_by, assignee: Option<&str>| BoardQuery {
+        group_by,
+        assignee: assignee.map(str::to_string),
+        assignee_field: None,
+        status: None,
+        limit: None,
+    };
+
+    let mine = repo.board(&board(BoardGrouping::Status, Some("alice")), 20).await.unwrap();
+    assert_eq!(mine.len(), 1);
+    assert_eq!((mine[0].key.as_deref(), mine[0].count, mine[0].cases.len()), (Some("active"), 2, 2));
+
+    let by_assignee = repo.board(&board(BoardGrouping::Assignee, None), 1).await.unwrap();
+    let columns: Vec<_> = by_assignee
+        .iter()
+        .map(|column| (column.key.as_deref(), column.count, column.cases.len()))
+        .collect();
+    assert_eq!(columns, vec![(Some("alice"), 2, 1), (Some("bob"), 1, 1), (None, 1, 1)]);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_presence(pool: PgPool) {
+    use orchepy::repositories::CasePresenceRepository;
+    use std::time::Duration;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+    let presence = CasePresenceRepository::new(&pool);
+    let ttl = Duration::from_secs(60);
+
+    presence.heartbeat(case.id, "alice", false, ttl).await.unwrap();
+    presence.heartbeat(case.id, "bob", true, ttl).await.unwrap();
+    presence.heartbeat(case.id, "alice", true, ttl).await.unwrap();
+
+    let active = presence.list_active(case.id).await.unwrap();
+    let seen: Vec<_> = active.iter().map(|p| (p.user.as_str(), p.editing)).collect();
+    assert_eq!(seen, vec![("alice", true), ("bob", true)]);
+    assert!(active[0].last_seen_at > active[0].first_seen_at);
+
+    presence.heartbeat(case.id, "carol", false, Duration::from_millis(1)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(presence.list_active(case.id).await.unwrap().len(), 2);
+    assert!(!presence.leave(case.id, "carol").await.unwrap());
+
+    assert!(presence.leave(case.id, "bob").await.unwrap());
+    let users: Vec<_> = presence.list_active(case.id).await.unwrap().into_iter().map(|p| p.user).collect();
+    assert_eq!(users, vec!["alice"]);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_workflow_versions(pool: PgPool) {
+    let mut workflow = setup_test_workflow(&pool).await;
+    let workflows = WorkflowRepository::new(&pool);
+    let repo = CaseRepository::new(&pool);
+    let case = create_test_case(&pool, workflow.id).await;
+
+    let moved = |from: &str, to: &str| CaseHistory::new(case.id, Some(from.to_string()), to.to_string(), None, None);
+    repo.create_history(&moved("New", "In Progress")).await.unwrap();
+
+    workflow.phases.push("Archived".into());
+    workflow.updated_by = Some("alice".to_string());
+    assert_eq!(workflows.update(&workflow).await.unwrap(), 2);
+    repo.create_history(&moved("In Progress", "Archived")).await.unwrap();
+
+    let versions: Vec<_> = repo.get_history(case.id).await.unwrap().iter().map(|h| h.workflow_version).collect();
+    assert_eq!(versions, vec![Some(2), Some(1)]);
+
+    let saved = workflows.list_versions(workflow.id).await.unwrap();
+    assert_eq!(saved.iter().map(|v| v.version).collect::<Vec<_>>(), vec![2, 1]);
+    assert_eq!(saved[0].created_by.as_deref(), Some("alice"));
+    assert_eq!(saved[0].phases.len(), 5);
+
+    let first = workflows.find_version(workflow.id, 1).await.unwrap().unwrap();
+    workflow.restore(&first);
+    assert_eq!(workflows.update(&workflow).await.unwrap(), 3);
+
+    let current = workflows.find_by_id(workflow.id).await.unwrap().unwrap();
+    assert_eq!((current.version, current.phases.len()), (3, 4));
+    assert!(workflows.find_version(workflow.id, 4).await.unwrap().is_none());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_comments(pool: PgPool) {
+    use orchepy::models::comment::{CaseComment, CreateCaseComment};
+    use orchepy::repositories::CaseCommentRepository;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+    let comments = CaseCommentRepository::new(&pool);
+
+    for (author, body) in [("alice", "Called the customer"), ("bob", "Waiting on the invoice")] {
+        let comment = CaseComment::new(
+            case.id,
+            CreateCaseComment {
+                author: author.to_string(),
+                body: body.to_string(),
+            },
+        );
+        assert!(comments.create(&comment).await.unwrap());
+    }
+
+    let listed = comments.list_by_case(case.id, 10).await.unwrap();
+    assert_eq!(listed.iter().map(|c| c.author.as_str()).collect::<Vec<_>>(), vec!["alice", "bob"]);
+
+    let repo = CaseRepository::new(&pool);
+    assert_eq!(repo.find_by_id(case.id).await.unwrap().unwrap().comment_count, 2);
+
+    let orphan = CaseComment::new(
+        Uuid::new_v4(),
+        CreateCaseComment {
+            author: "alice".to_string(),
+            body: "Lost".to_string(),
+        },
+    );
+    assert!(!comments.create(&orphan).await.unwrap());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_message_correlation(pool: PgPool) {
+    use orchepy::models::message::{CaseMessage, InboundMessage, MessageChannel, MessageDirection};
+    use orchepy::repositories::CaseMessageRepository;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let first = create_test_case(&pool, workflow.id).await;
+    let second = create_test_case(&pool, workflow.id).await;
+    let repo = CaseMessageRepository::new(&pool);
+
+    let email = CaseMessage::outbound(
+        first.id,
+        MessageChannel::Email,
+        Some("ana@example.com".to_string()),
+        Some("Your invoice".to_string()),
+        "Please confirm".to_string(),
+    );
+    repo.create(&email).await.unwrap();
+
+    for case_id in [first.id, second.id] {
+        let mut sms = CaseMessage::outbound(
+            case_id,
+            MessageChannel::Sms,
+            Some("+15551234567".to_string()),
+            None,
+            "Reply YES to confirm".to_string(),
+        );
+        sms.created_at = chrono::Utc::now() + chrono::Duration::seconds(if case_id == first.id { 0 } else { 1 });
+        repo.create(&sms).await.unwrap();
+    }
+
+    let token = email.reply_token.as_deref().unwrap();
+    assert_eq!(repo.find_case_by_reply_token(token).await.unwrap(), Some(first.id));
+    assert_eq!(repo.find_case_by_reply_token("unknown").await.unwrap(), None);
+
+    assert_eq!(
+        repo.find_case_by_last_recipient(MessageChannel::Sms, "+15551234567").await.unwrap(),
+        Some(second.id)
+    );
+    assert_eq!(
+        repo.find_case_by_last_recipient(MessageChannel::Whatsapp, "+15551234567").await.unwrap(),
+        None
+    );
+    assert_eq!(
+        repo.find_case_by_last_recipient(MessageChannel::Email, "ANA@example.com").await.unwrap(),
+        Some(first.id)
+    );
+
+    let reply = CaseMessage::inbound(
+        first.id,
+        InboundMessage {
+            channel: MessageChannel::Email,
+            from: "ana@example.com".to_string(),
+            to: None,
+            subject: Some("Re: Your invoice".to_string()),
+            body: "Confirmed".to_string(),
+            reply_token: Some(token.to_string()),
+            external_id: None,
+        },
+    );
+    repo.create(&reply).await.unwrap();
+
+    let thread = repo.list_by_case(first.id, 100).await.unwrap();
+    assert_eq!(thread.len(), 3);
+    assert_eq!(thread[0].direction, MessageDirection::Outbound);
+    assert_eq!(thread[2].direction, MessageDirection::Inbound);
+    assert_eq!(thread[2].body, "Confirmed");
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_search_cases_by_data(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let repo = CaseRepository::new(&pool);
+
+    let mut ids = Vec::new();
+    for data in [
+        json!({"customer": {"tier": "gold", "name": "Acme Corp"}, "amount": 2500, "due": "2026-03-01"}),
+        json!({"customer": {"tier": "silver", "name": "Globex"}, "amount": 800, "due": "2026-01-15"}),
+        json!({"customer": {"tier": "gold"}, "amount": "1500"}),
+    ] {
+        let case = Case::new(workflow.id, "New".to_string(), data, None);
+        repo.create(&case).await.unwrap();
+        ids.push(case.id);
+    }
+
+    let search = |params: &[(&str, &str)]| {
+        let mut params: Vec<(String, String)> = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        params.push(("workflow_id".to_string(), workflow.id.to_string()));
+        CaseSearch::from_params(&params).unwrap()
+    };
+    let found = |cases: Vec<Case>| {
+        let mut found: Vec<usize> = cases.iter().map(|c| ids.iter().position(|id| *id == c.id).unwrap()).collect();
+        found.sort();
+        found
+    };
+
+    let gold = search(&[("data.customer.tier", "gold")]);
+    assert_eq!(found(repo.search(&gold, 50, 0).await.unwrap()), vec![0, 2]);
+    assert_eq!(repo.count_search(&gold).await.unwrap(), 2);
+
+    let amount = search(&[("data.amount", "1500")]);
+    assert_eq!(found(repo.search(&amount, 50, 0).await.unwrap()), vec![2]);
+
+    let large = search(&[("data.amount[gte]", "1000")]);
+    assert_eq!(found(repo.search(&large, 50, 0).await.unwrap()), vec![0]);
+
+    let combined = search(&[("data.customer.tier[ne]", "gold"), ("data.amount[lt]", "1000")]);
+    assert_eq!(found(repo.search(&combined, 50, 0).await.unwrap()), vec![1]);
+
+    let due = search(&[("data.due[lt]", "2026-02-01")]);
+    assert_eq!(found(repo.search(&due, 50, 0).await.unwrap()), vec![1]);
+
+    let named = search(&[("data.customer.name[contains]", "Corp")]);
+    assert_eq!(found(repo.search(&named, 50, 0).await.unwrap()), vec![0]);
+
+    let unnamed = search(&[("data.customer.name[exists]", "false")]);
+    assert_eq!(found(repo.search(&unnamed, 50, 0).await.unwrap()), vec![2]);
+
+    repo.soft_delete(ids[0]).await.unwrap();
+    assert_eq!(found(repo.search(&gold, 50, 0).await.unwrap()), vec![2]);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_concurrent_data_patches_do_not_clobber(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+
+    let handles: Vec<_> = (0..10)
+        .map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let patch = DataPatch::Merge(json!({ format!("field_{}", i): i }));
+                CaseRepository::new(&pool)
+                    .patch_data(case.id, &patch, &FieldProvenance::api(None))
+                    .await
+                    .unwrap()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert!(matches!(handle.await.unwrap(), PatchOutcome::Applied(_)));
+    }
+
+    let repo = CaseRepository::new(&pool);
+    let patch = DataPatch::Json(
+        serde_json::from_value(json!([
+            {"op": "test", "path": "/amount", "value": 1000},
+            {"op": "remove", "path": "/field_0"}
+        ]))
+        .unwrap(),
+    );
+    assert!(matches!(
+        repo.patch_data(case.id, &patch, &FieldProvenance::api(None)).await.unwrap(),
+        PatchOutcome::Applied(_)
+    ));
+
+    let case = repo.find_by_id(case.id).await.unwrap().unwrap();
+    let fields = case.data.as_object().unwrap();
+    assert_eq!(fields.len(), 10);
+    assert_eq!(case.data["field_9"], 9);
+    assert!(!fields.contains_key("field_0"));
+
+    let stale = DataPatch::Json(serde_json::from_value(json!([{"op": "test", "path": "/amount", "value": 1}])).unwrap());
+    assert!(matches!(
+        repo.patch_data(case.id, &stale, &FieldProvenance::api(None)).await.unwrap(),
+        PatchOutcome::Rejected(PatchError::TestFailed(_))
+    ));
+    assert!(matches!(
+        repo.patch_data(Uuid::new_v4(), &stale, &FieldProvenance::api(None)).await.unwrap(),
+        PatchOutcome::NotFound
+    ));
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_updates_at_a_stale_version_are_refused(pool: PgPool) {
+    use orchepy::models::phase::PhaseMove;
+
+    let workflow = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+    let repo = CaseRepository::new(&pool);
+    let writer = FieldProvenance::api(None);
+    assert_eq!(repo.version(case.id).await.unwrap(), Some(1));
+
+    let patch = DataPatch::Merge(json!({"amount": 1}));
+    assert!(matches!(
+        repo.patch_data_at_version(case.id, &patch, &writer, Some(1)).await.unwrap(),
+        PatchOutcome::Applied(_)
+    ));
+    assert!(matches!(
+        repo.patch_data_at_version(case.id, &patch, &writer, Some(1)).await.unwrap(),
+        PatchOutcome::Stale { version: 2 }
+    ));
+
+    // A new comment is not a change to the case.
+    sqlx::query("UPDATE orchepy_cases SET comment_count = comment_count + 1, updated_at = NOW() WHERE id = $1")
+        .bind(case.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(repo.version(case.id).await.unwrap(), Some(2));
+
+    let move_at = |version| repo.update_phase_within_limit(case.id, workflow.id, "Review", Some("New"), None, version);
+    assert_eq!(move_at(Some(1)).await.unwrap(), PhaseMove::Stale { version: 2 });
+    assert_eq!(move_at(Some(2)).await.unwrap(), PhaseMove::Moved);
+    assert_eq!(repo.version(case.id).await.unwrap(), Some(3));
+
+    repo.soft_delete(case.id).await.unwrap();
+    assert_eq!(move_at(Some(3)).await.unwrap(), PhaseMove::NotFound);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_data_conflict_strategies(pool: PgPool) {
+    let mut workflow = setup_test_workflow(&pool).await;
+    workflow.data_conflicts = serde_json::from_value(json!({
+        "strategy": "reject",
+        "fields": {"labels": "merge_arrays", "notes": "last_write_wins"}
+    }))
+    .unwrap();
+    WorkflowRepository::new(&pool).update(&workflow).await.unwrap();
+
+    let case = create_test_case(&pool, workflow.id).await;
+    let repo = CaseRepository::new(&pool);
+    let (ana, bob) = (
+        FieldProvenance::api(Some("ana".to_string())),
+        FieldProvenance::api(Some("bob".to_string())),
+    );
+
+    let first = DataPatch::Merge(json!({"labels": ["a"], "notes": "x", "owner": "ana"}));
+    assert!(matches!(repo.patch_data(case.id, &first, &ana).await.unwrap(), PatchOutcome::Applied(_)));
+
+    let conflicting = DataPatch::Merge(json!({"owner": "bob", "notes": "y"}));
+    match repo.patch_data(case.id, &conflicting, &bob).await.unwrap() {
+        PatchOutcome::Conflict(fields) => assert_eq!(fields, vec!["owner"]),
+        outcome => panic!("expected a conflict, got {:?}", outcome),
+    }
+
+    let merged = DataPatch::Merge(json!({"labels": ["b"], "notes": "y"}));
+    assert!(matches!(repo.patch_data(case.id, &merged, &bob).await.unwrap(), PatchOutcome::Applied(_)));
+    assert!(!repo.set_field_by(case.id, "owner", &json!("bob"), &bob).await.unwrap());
+    assert!(repo.set_field_by(case.id, "owner", &json!("ana"), &ana).await.unwrap());
+
+    let case = repo.find_by_id(case.id).await.unwrap().unwrap();
+    assert_eq!(case.data, json!({"amount": 1000, "labels": ["a", "b"], "notes": "y", "owner": "ana"}));
+
+    let notes = &case.field_provenance["notes"];
+    assert_eq!(notes.actor.as_deref(), Some("bob"));
+    assert_eq!(notes.overwrote.as_ref().and_then(|last| last.actor.as_deref()), Some("ana"));
+    assert!(case.field_provenance["owner"].overwrote.is_none());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_change_log(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let changes = ChangeRepository::new(&pool);
+    let start = changes.latest().await.unwrap();
+
+    let case = create_test_case(&pool, workflow.id).await;
+    let repo = CaseRepository::new(&pool);
+    repo.update_data(case.id, &json!({"amount": 5})).await.unwrap();
+    repo.soft_delete(case.id).await.unwrap();
+
+    let feed = changes.since(start, 100).await.unwrap();
+    let operations: Vec<_> = feed.iter().map(|change| change.operation.as_str()).collect();
+    assert_eq!(operations, vec!["created", "updated", "deleted"]);
+    assert!(feed.iter().all(|change| change.entity == "case" && change.entity_id == case.id));
+    assert_eq!(feed[0].workflow_id, Some(workflow.id));
+
+    let cursor = feed[1].cursor.parse().unwrap();
+    let rest = changes.since(cursor, 100).await.unwrap();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(changes.latest().await.unwrap().to_string(), rest[0].cursor);
+    assert!(changes.since(changes.latest().await.unwrap(), 100).await.unwrap().is_empty());
+
+    assert_eq!(changes.prune(chrono::Utc::now() + chrono::Duration::seconds(1)).await.unwrap(), 3);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_workflow_archiving_and_deletion(pool: PgPool) {
+    let workflow = setup_test_workflow(&pool).await;
+    let unused = setup_test_workflow(&pool).await;
+    let case = create_test_case(&pool, workflow.id).await;
+    let repo = WorkflowRepository::new(&pool);
+
+    assert!(!repo.delete_if_unused(workflow.id).await.unwrap());
+    assert_eq!(repo.count_cases(workflow.id).await.unwrap(), 1);
+    assert!(repo.delete_if_unused(unused.id).await.unwrap());
+
+    let archived = repo.archive(workflow.id, Some("alice")).await.unwrap().unwrap();
+    assert!(archived.is_archived());
+    let again = repo.archive(workflow.id, Some("bob")).await.unwrap().unwrap();
+    assert_eq!((again.archived_at, again.archived_by.as_deref()), (archived.archived_at, Some("alice")));
+
+    assert!(repo.find_active_by_id(workflow.id).await.unwrap().is_none());
+    assert!(repo.list_active().await.unwrap().is_empty());
+    assert!(CaseRepository::new(&pool).find_by_id(case.id).await.unwrap().is_some());
+
+    assert!(!repo.unarchive(workflow.id).await.unwrap().unwrap().is_archived());
+    assert!(repo.find_active_by_id(workflow.id).await.unwrap().is_some());
+
+    assert!(repo.delete(workflow.id).await.unwrap());
+    assert!(CaseRepository::new(&pool).find_by_id(case.id).await.unwrap().is_none());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_phase_wip_limits(pool: PgPool) {
+    use orchepy::models::membership::CaseWorkflow;
+    use orchepy::models::phase::{Phase, PhaseMove};
+    use orchepy::repositories::CaseWorkflowRepository;
+
+    let mut workflow = setup_test_workflow(&pool).await;
+    workflow.phases[2] = Phase { wip_limit: Some(1), color: Some("#f60".to_string()), ..Phase::new("Review") };
+    let workflows = WorkflowRepository::new(&pool);
+    workflows.update(&workflow).await.unwrap();
+
+    let stored = workflows.find_by_id(workflow.id).await.unwrap().unwrap();
+    assert_eq!(stored.phases, workflow.phases);
+    assert_eq!((stored.wip_limit("Review"), stored.wip_limit("New")), (Some(1), None));
+
+    let other = setup_test_workflow(&pool).await;
+    let (first, second, joined) = (
+        create_test_case(&pool, workflow.id).await,
+        create_test_case(&pool, workflow.id).await,
+        create_test_case(&pool, other.id).await,
+    );
+    let repo = CaseRepository::new(&pool);
+    let memberships = CaseWorkflowRepository::new(&pool);
+    let move_to_review = |id| repo.update_phase_within_limit(id, workflow.id, "Review", Some("New"), Some(1), None);
+
+    assert_eq!(move_to_review(first.id).await.unwrap(), PhaseMove::Moved);
+    assert_eq!(move_to_review(second.id).await.unwrap(), PhaseMove::AtWipLimit { limit: 1, in_progress: 1 });
+    assert_eq!(repo.find_by_id(second.id).await.unwrap().unwrap().current_phase, "New");
+
+    let mut membership = CaseWorkflow::new(joined.id, workflow.id, "New".to_string(), None);
+    memberships.create(&membership).await.unwrap();
+    membership.move_to_phase("Review".to_string());
+    assert_eq!(
+        memberships.update_phase_within_limit(&membership, Some(1)).await.unwrap(),
+        PhaseMove::AtWipLimit { limit: 1, in_progress: 1 }
+    );
+
+    repo.update_status(first.id, &CaseStatus::Completed).await.unwrap();
+    assert_eq!(memberships.update_phase_within_limit(&membership, Some(1)).await.unwrap(), PhaseMove::Moved);
+    assert_eq!(move_to_review(second.id).await.unwrap(), PhaseMove::AtWipLimit { limit: 1, in_progress: 1 });
+    assert_eq!(
+        repo.update_phase_within_limit(Uuid::new_v4(), workflow.id, "Done", None, None, None).await.unwrap(),
+        PhaseMove::NotFound
+    );
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_phase_transitions_are_versioned(pool: PgPool) {
+    let mut workflow = setup_test_workflow(&pool).await;
+    let workflows = WorkflowRepository::new(&pool);
+
+    workflow.transitions.insert("Review".to_string(), vec!["Done".to_string()]);
+    assert_eq!(workflows.update(&workflow).await.unwrap(), 2);
+
+    let stored = workflows.find_by_id(workflow.id).await.unwrap().unwrap();
+    assert!(stored.allows_transition("Review", "Done"));
+    assert!(!stored.allows_transition("Review", "New"));
+    assert_eq!(workflows.find_version(workflow.id, 2).await.unwrap().unwrap().transitions, workflow.transitions);
+
+    workflow.restore(&workflows.find_version(workflow.id, 1).await.unwrap().unwrap());
+    workflows.update(&workflow).await.unwrap();
+    assert!(workflows.find_by_id(workflow.id).await.unwrap().unwrap().allows_transition("Review", "New"));
+}
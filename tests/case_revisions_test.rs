@@ -0,0 +1,104 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_data_revisions(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Deals",
+            "phases": ["New", "Won"],
+            "initial_phase": "New",
+            "automations": {"automations": [{
+                "trigger": "on_enter",
+                "phase": "Won",
+                "actions": [{"type": "set_field", "name": "mark-won", "field": "data.closed", "value": true}]
+            }]}
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {"value": 100, "notes": "first"}, "triggered_by": "ana"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case_url = format!("{}/cases/{}", base, case["id"].as_str().unwrap());
+
+    let response = client
+        .patch(format!("{}/data?triggered_by=bob", case_url))
+        .header("Content-Type", "application/merge-patch+json")
+        .body(r#"{"value": 200, "notes": null}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Writing the data it already has is not a revision.
+    let response = client
+        .patch(format!("{}/data", case_url))
+        .header("Content-Type", "application/merge-patch+json")
+        .body(r#"{"value": 200}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let response = client.put(format!("{}/move", case_url)).json(&json!({"to_phase": "Won"})).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    let revisions: Vec<Value> = client.get(format!("{}/revisions", case_url)).send().await.unwrap().json().await.unwrap();
+    let summary: Vec<_> = revisions
+        .iter()
+        .map(|r| (r["revision"].clone(), r["changed_fields"].clone(), r["source"].clone(), r["actor"].clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (json!(3), json!(["closed"]), json!("automation"), json!("mark-won")),
+            (json!(2), json!(["notes", "value"]), json!("api"), json!("bob")),
+            (json!(1), json!(["notes", "value"]), json!("api"), json!("ana")),
+        ]
+    );
+    assert_eq!(revisions[2]["data"], json!({"value": 100, "notes": "first"}));
+
+    let restored: Value = client
+        .post(format!("{}/revisions/1/restore?triggered_by=carol", case_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(restored["data"], json!({"value": 100, "notes": "first"}));
+
+    let revisions: Vec<Value> = client.get(format!("{}/revisions", case_url)).send().await.unwrap().json().await.unwrap();
+    assert_eq!((&revisions[0]["revision"], &revisions[0]["actor"]), (&json!(4), &json!("carol")));
+    assert_eq!(revisions[0]["changed_fields"], json!(["closed", "notes", "value"]));
+
+    let missing = client.post(format!("{}/revisions/9/restore", case_url)).send().await.unwrap();
+    assert_eq!(missing.status(), 404);
+    assert_eq!(missing.json::<Value>().await.unwrap()["code"], "CASE_REVISION_NOT_FOUND");
+
+    let stale = client
+        .post(format!("{}/revisions/2/restore", case_url))
+        .header("If-Match", "\"1\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stale.status(), 409);
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+/// `(event, data)` pairs read from an SSE response until it goes quiet.
+async fn read_events(response: &mut reqwest::Response) -> Vec<(String, Value)> {
+    let mut text = String::new();
+    while let Ok(Ok(Some(chunk))) = tokio::time::timeout(Duration::from_millis(500), response.chunk()).await {
+        text.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+
+    text.split("\n\n")
+        .filter_map(|block| {
+            let field = |name: &str| {
+                block
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .map(|value| value.trim_start().to_string())
+            };
+            Some((field("event:")?, serde_json::from_str(&field("data:")?).unwrap()))
+        })
+        .collect()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_changes_are_streamed(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let mut workflows = Vec::new();
+    for name in ["Orders", "Returns"] {
+        let workflow: Value = client
+            .post(format!("{}/workflows", base))
+            .json(&json!({"name": name, "phases": ["New", "Done"], "initial_phase": "New"}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        workflows.push(workflow["id"].as_str().unwrap().to_string());
+    }
+
+    let mut stream = client
+        .get(format!("{}/cases/stream?workflow_id={}", base, workflows[0]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stream.headers()["content-type"], "text/event-stream");
+
+    let mut case_ids = Vec::new();
+    for workflow_id in &workflows {
+        let case: Value = client
+            .post(format!("{}/cases", base))
+            .json(&json!({"workflow_id": workflow_id, "data": {}}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        case_ids.push(case["id"].as_str().unwrap().to_string());
+    }
+
+    client
+        .put(format!("{}/cases/{}/move", base, case_ids[0]))
+        .json(&json!({"to_phase": "Done"}))
+        .send()
+        .await
+        .unwrap();
+    client
+        .patch(format!("{}/cases/{}/data", base, case_ids[0]))
+        .json(&json!({"data": {"priority": "high"}}))
+        .send()
+        .await
+        .unwrap();
+    client
+        .post(format!("{}/cases/{}/complete", base, case_ids[1]))
+        .send()
+        .await
+        .unwrap();
+
+    let events = read_events(&mut stream).await;
+    let kinds: Vec<&str> = events.iter().map(|(event, _)| event.as_str()).collect();
+    assert_eq!(kinds, vec!["case.created", "case.moved", "case.updated"]);
+    assert!(events.iter().all(|(_, change)| change["case"]["id"] == case_ids[0].as_str()));
+    assert_eq!(events[1].1["case"]["current_phase"], "Done");
+    assert_eq!(events[2].1["case"]["data"]["priority"], "high");
+    assert_eq!(events[2].1["type"], "case.updated");
+}
@@ -0,0 +1,170 @@
+use chrono::{Duration, Utc};
+use orchepy::models::case::Case;
+use orchepy::models::message::{CaseMessage, MessageAttachment, MessageChannel};
+use orchepy::models::portal::{CreatePortalToken, PortalToken};
+use orchepy::models::Workflow;
+use orchepy::repositories::{CaseMessageRepository, CaseRepository, PortalTokenRepository, WorkflowRepository};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn create_test_case(pool: &PgPool) -> Case {
+    let workflow = Workflow {
+        id: Uuid::new_v4(),
+        name: "Support".to_string(),
+        phases: vec!["Open".into(), "Solved".into()],
+        initial_phase: "Open".to_string(),
+        webhook_url: None,
+        webhook_template: None,
+        webhook_headers: Default::default(),
+        webhook_auth: None,
+        active: true,
+        description: None,
+        automations: None,
+        sla_config: None,
+        execution_limits: Default::default(),
+        data_conflicts: Default::default(),
+        transitions: Default::default(),
+        region: None,
+        version: 1,
+        updated_by: None,
+        archived_at: None,
+        archived_by: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    WorkflowRepository::new(pool).create(&workflow).await.unwrap();
+
+    let case = Case::new(workflow.id, "Open".to_string(), json!({"order": {"number": 42}}), None);
+    CaseRepository::new(pool).create(&case).await.unwrap();
+
+    case
+}
+
+fn portal_token(case_id: Uuid, expires_in_hours: Option<i64>) -> PortalToken {
+    PortalToken::new(
+        case_id,
+        CreatePortalToken {
+            fields: vec!["order.number".to_string()],
+            allow_replies: true,
+            expires_in_hours,
+        },
+    )
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_portal_token_lifecycle(pool: PgPool) {
+    let case = create_test_case(&pool).await;
+    let repo = PortalTokenRepository::new(&pool);
+
+    let token = portal_token(case.id, Some(24));
+    let secret = token.token.clone().unwrap();
+    assert_eq!(secret.len(), 64);
+    assert_ne!(token.token_hash, secret);
+    repo.create(&token).await.unwrap();
+
+    let found = repo.find_active(&secret).await.unwrap().unwrap();
+    assert_eq!(found.case_id, case.id);
+    assert!(found.token.is_none());
+    assert_eq!(found.fields, vec!["order.number".to_string()]);
+    assert!(repo.find_active("unknown").await.unwrap().is_none());
+
+    let mut expired = portal_token(case.id, None);
+    expired.expires_at = Some(Utc::now() - Duration::minutes(1));
+    repo.create(&expired).await.unwrap();
+    assert!(repo.find_active(expired.token.as_deref().unwrap()).await.unwrap().is_none());
+
+    assert!(repo.revoke(case.id, token.id).await.unwrap());
+    assert!(!repo.revoke(case.id, token.id).await.unwrap());
+    assert!(!repo.revoke(Uuid::new_v4(), expired.id).await.unwrap());
+    assert!(repo.find_active(&secret).await.unwrap().is_none());
+
+    // Listed tokens carry neither the token nor its hash.
+    let listed = repo.list_by_case(case.id).await.unwrap();
+    assert_eq!(listed.len(), 2);
+    let listed = serde_json::to_value(&listed).unwrap();
+    assert!(listed.as_array().unwrap().iter().all(|token| token.get("token").is_none() && token.get("token_hash").is_none()));
+    assert!(!listed.to_string().contains(&secret));
+    assert_eq!(serde_json::to_value(&token).unwrap()["token"], secret);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_expiring_portal_tokens_are_reminded_and_revoked(pool: PgPool) {
+    use orchepy::services::WebhookSender;
+    use orchepy::workers::credential_expiry::{sweep_credentials, CredentialExpiryConfig, ExpirySweep};
+
+    let case = create_test_case(&pool).await;
+    let repo = PortalTokenRepository::new(&pool);
+
+    let mut expired = portal_token(case.id, None);
+    expired.expires_at = Some(Utc::now() - Duration::minutes(1));
+    let soon = portal_token(case.id, Some(24));
+    let later = portal_token(case.id, Some(200));
+    let forever = portal_token(case.id, None);
+    for token in [&expired, &soon, &later, &forever] {
+        repo.create(token).await.unwrap();
+    }
+
+    let expiring = repo.list_expiring(Utc::now() + Duration::hours(72), 10).await.unwrap();
+    assert_eq!(expiring.iter().map(|t| t.id).collect::<Vec<_>>(), vec![expired.id, soon.id]);
+
+    let config = CredentialExpiryConfig {
+        poll_interval: std::time::Duration::from_secs(1),
+        reminder_window: std::time::Duration::from_secs(72 * 3600),
+    };
+    let webhooks = WebhookSender::new();
+    assert_eq!(
+        sweep_credentials(&pool, &webhooks, &config).await.unwrap(),
+        ExpirySweep { revoked: 1, reminded: 1 }
+    );
+    assert_eq!(sweep_credentials(&pool, &webhooks, &config).await.unwrap(), ExpirySweep::default());
+
+    let tokens = repo.list_by_case(case.id).await.unwrap();
+    let find = |id: Uuid| tokens.iter().find(|t| t.id == id).unwrap();
+    assert!(find(expired.id).revoked_at.is_some());
+    assert!(find(soon.id).reminded_at.is_some() && find(soon.id).revoked_at.is_none());
+    assert!(find(later.id).reminded_at.is_none());
+    assert!(find(forever.id).reminded_at.is_none());
+
+    let expiring = repo.list_expiring(Utc::now() + Duration::hours(72), 10).await.unwrap();
+    assert_eq!(expiring.iter().map(|t| t.id).collect::<Vec<_>>(), vec![soon.id]);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_portal_shows_public_messages_only(pool: PgPool) {
+    let case = create_test_case(&pool).await;
+    let repo = CaseMessageRepository::new(&pool);
+
+    let internal = CaseMessage::outbound(
+        case.id,
+        MessageChannel::Email,
+        Some("supplier@example.com".to_string()),
+        None,
+        "Can you ship order 42 today?".to_string(),
+    );
+    repo.create(&internal).await.unwrap();
+
+    let mut update = CaseMessage::outbound(
+        case.id,
+        MessageChannel::Email,
+        Some("ana@example.com".to_string()),
+        None,
+        "Your order is on its way".to_string(),
+    );
+    update.public = true;
+    update.attachments = vec![MessageAttachment {
+        filename: "label.pdf".to_string(),
+        url: "https://files.example.com/label.pdf".to_string(),
+        content_type: Some("application/pdf".to_string()),
+        size_bytes: Some(2048),
+    }];
+    repo.create(&update).await.unwrap();
+
+    let public = repo.list_public_by_case(case.id, 100).await.unwrap();
+    assert_eq!(public.len(), 1);
+    assert_eq!(public[0].id, update.id);
+    assert_eq!(public[0].attachments.len(), 1);
+    assert_eq!(public[0].attachments[0].filename, "label.pdf");
+
+    assert_eq!(repo.list_by_case(case.id, 100).await.unwrap().len(), 2);
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use orchepy::api::{build_router, AppState};
+use orchepy::services::{DataRegions, PoolConfig, PoolStats, WebhookSender};
+use serde_json::{json, Value};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::PgPool;
+
+mod common;
+
+/// The API state with `replica` as the default region's read replica.
+fn state_with_replica(pool: &PgPool, replica: PgPool) -> AppState {
+    let mut state = AppState::new(pool.clone(), WebhookSender::new());
+    state.regions = DataRegions::single(pool.clone()).with_replica("default", replica);
+    state
+}
+
+async fn create_case(client: &reqwest::Client, base: &str) -> Value {
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let response = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {"amount": 10}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    response.json().await.unwrap()
+}
+
+#[sqlx::test(migrations = false)]
+async fn test_pool_settings(pool: PgPool) {
+    let config = PoolConfig {
+        max_connections: 2,
+        min_connections: 1,
+        statement_timeout: Some(Duration::from_millis(250)),
+        ..PoolConfig::default()
+    };
+    let configured = config.connect_with((*pool.connect_options()).clone()).await.unwrap();
+
+    let timeout: String = sqlx::query_scalar("SHOW statement_timeout").fetch_one(&configured).await.unwrap();
+    assert_eq!(timeout, "250ms");
+    let slow = sqlx::query("SELECT pg_sleep(1)").execute(&configured).await;
+    assert!(slow.unwrap_err().to_string().contains("statement timeout"));
+
+    let held = configured.acquire().await.unwrap();
+    let stats = PoolStats::of(&configured);
+    assert_eq!(stats.max_connections, 2);
+    assert!(stats.in_use >= 1);
+    assert_eq!(stats.in_use + stats.idle, stats.size);
+    drop(held);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_reads_use_the_replica(pool: PgPool) {
+    // A read-only session on the same database stands in for the replica:
+    // reads routed to it work, writes routed to it would fail.
+    let options = (*pool.connect_options()).clone().options([("default_transaction_read_only", "on")]);
+    let replica = PgPoolOptions::new().max_connections(2).connect_with(options).await.unwrap();
+    let base = common::serve(build_router(state_with_replica(&pool, replica))).await;
+    let client = reqwest::Client::new();
+
+    let case = create_case(&client, &base).await;
+    let workflow_id = case["workflow_id"].as_str().unwrap();
+
+    for path in [
+        format!("/cases?workflow_id={}", workflow_id),
+        format!("/cases/{}", case["id"].as_str().unwrap()),
+        format!("/cases/{}/history", case["id"].as_str().unwrap()),
+        format!("/workflows/{}", workflow_id),
+        format!("/workflows/{}/analytics", workflow_id),
+        format!("/workflows/{}/counts", workflow_id),
+        "/workflows".to_string(),
+        "/events".to_string(),
+        "/flows".to_string(),
+        "/executions".to_string(),
+    ] {
+        let response = client.get(format!("{}{}", base, path)).send().await.unwrap();
+        assert_eq!(response.status(), 200, "{}", path);
+    }
+
+    let moved = client
+        .put(format!("{}/cases/{}/move", base, case["id"].as_str().unwrap()))
+        .json(&json!({"to_phase": "Done"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(moved.status(), 200);
+
+    let health: Value = client.get(format!("{}/health?deep=true", base)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(health["status"], "healthy");
+    assert_eq!(health["regions"][0]["replica"]["status"], "healthy");
+    assert_eq!(health["regions"][0]["replica"]["pool"]["max_connections"], 2);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_writes_go_to_the_primary(pool: PgPool) {
+    let unreachable = PgConnectOptions::new().host("127.0.0.1").port(1);
+    let replica = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(500))
+        .connect_lazy_with(unreachable);
+    let base = common::serve(build_router(state_with_replica(&pool, replica))).await;
+    let client = reqwest::Client::new();
+
+    // Writes still succeed with the replica down; lists read from it fail.
+    let case = create_case(&client, &base).await;
+    let list = client
+        .get(format!("{}/cases?workflow_id={}", base, case["workflow_id"].as_str().unwrap()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(list.status(), 500);
+
+    let response = client.get(format!("{}/health?deep=true", base)).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    let health: Value = response.json().await.unwrap();
+    assert_eq!(health["regions"][0]["database"]["status"], "healthy");
+    assert_eq!(health["regions"][0]["replica"]["status"], "unhealthy");
+}
@@ -0,0 +1,69 @@
+use chrono::Utc;
+use orchepy::api::{build_router, AppState};
+use orchepy::services::{DigestConfig, DigestService, NotificationRegistry, WebhookSender};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_digest_counts_failed_automation_runs(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Deals", "phases": ["New", "Review"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let workflow_id: Uuid = workflow["id"].as_str().unwrap().parse().unwrap();
+    let case_id: Uuid = case["id"].as_str().unwrap().parse().unwrap();
+
+    let mut failed = Vec::new();
+    for (status, error) in [("failed", Some("Webhook returned status 503")), ("limit_exceeded", None), ("completed", None)] {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO orchepy_automation_runs (id, case_id, workflow_id, trigger, phase, status, error)
+             VALUES ($1, $2, $3, 'on_enter', 'Review', $4::automation_run_status, $5)",
+        )
+        .bind(id)
+        .bind(case_id)
+        .bind(workflow_id)
+        .bind(status)
+        .bind(error)
+        .execute(&pool)
+        .await
+        .unwrap();
+        if status != "completed" {
+            failed.push(id);
+        }
+    }
+
+    let mut config = DigestConfig::from_env();
+    config.dashboard_url = "https://orchepy.example.com".to_string();
+    let digest = DigestService::new(pool, config, NotificationRegistry::new()).build(Utc::now()).await.unwrap();
+
+    assert_eq!(digest.failed_automation_runs, 2);
+    assert_eq!(digest.top_failing_workflows.len(), 1);
+    assert_eq!(digest.top_failing_workflows[0].workflow_name, "Deals");
+    assert_eq!(digest.top_failing_workflows[0].failures, 2);
+    let mut recent: Vec<Uuid> = digest.recent_automation_failures.iter().map(|failure| failure.run_id).collect();
+    recent.sort();
+    failed.sort();
+    assert_eq!(recent, failed);
+    let link = &digest.recent_automation_failures[0].link;
+    assert!(link.starts_with(&format!("https://orchepy.example.com/cases/{}/automation-runs/", case_id)));
+}
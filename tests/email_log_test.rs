@@ -0,0 +1,57 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::repositories::EmailLogRepository;
+use orchepy::services::mailer::{Mailer, OutboundEmail, SmtpMailer};
+use orchepy::services::WebhookSender;
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+/// A port nothing listens on.
+async fn closed_port() -> u16 {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_sends_are_logged(pool: PgPool) {
+    let case_id = Uuid::new_v4();
+    let mailer = SmtpMailer::new("127.0.0.1", closed_port().await, None, "orchepy@example.com")
+        .unwrap()
+        .with_log(pool.clone());
+
+    let email = OutboundEmail {
+        to: "ana@example.com, bo@example.com".to_string(),
+        subject: "Order 42".to_string(),
+        body: "Shipped".to_string(),
+        reply_token: None,
+        kind: "automation".to_string(),
+        case_id: Some(case_id),
+    };
+    assert!(mailer.send(&email).await.is_err());
+    EmailLogRepository::new(&pool).record("digest", None, "ops@example.com", "Daily digest", None).await.unwrap();
+
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let emails: Vec<Value> = client.get(format!("{}/admin/emails", base)).send().await.unwrap().json().await.unwrap();
+    let kinds: Vec<&str> = emails.iter().map(|email| email["kind"].as_str().unwrap()).collect();
+    assert_eq!(kinds, vec!["digest", "automation"]);
+
+    let failed: Vec<Value> = client
+        .get(format!("{}/admin/emails?status=failed&case_id={}", base, case_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["recipients"], "ana@example.com, bo@example.com");
+    assert_eq!(failed[0]["subject"], "Order 42");
+    assert!(failed[0]["error"].as_str().is_some_and(|error| !error.is_empty()));
+
+    let response = client.get(format!("{}/admin/emails?status=bounced", base)).send().await.unwrap();
+    assert_eq!(response.status(), 400);
+}
@@ -0,0 +1,76 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+async fn error_of(response: reqwest::Response) -> (u16, Value) {
+    (response.status().as_u16(), response.json().await.unwrap())
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_errors_have_specific_codes(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let missing = client.get(format!("{}/workflows/{}", base, Uuid::new_v4())).send().await.unwrap();
+    let (status, body) = error_of(missing).await;
+    assert_eq!((status, body["code"].as_str()), (404, Some("WORKFLOW_NOT_FOUND")));
+
+    // A definition with only SLA problems is reported as such.
+    let workflow = json!({
+        "name": "Support",
+        "phases": ["New", "Done"],
+        "initial_phase": "New",
+        "sla_config": {"Closed": {"hours": 4}}
+    });
+    let rejected = client.post(format!("{}/workflows", base)).json(&workflow).send().await.unwrap();
+    let (status, body) = error_of(rejected).await;
+    assert_eq!((status, body["code"].as_str()), (422, Some("SLA_CONFIG_INVALID")));
+    assert_eq!(body["errors"][0]["code"], "SLA_CONFIG_INVALID");
+    assert_eq!(body["errors"][0]["path"], "sla_config.Closed");
+
+    // Mixed problems keep the general code; each problem has its own.
+    let mut workflow = workflow;
+    workflow["transitions"] = json!({"Gone": ["Done"]});
+    let (_, body) = error_of(client.post(format!("{}/workflows", base)).json(&workflow).send().await.unwrap()).await;
+    assert_eq!(body["code"], "WORKFLOW_INVALID");
+    let codes: Vec<_> = body["errors"].as_array().unwrap().iter().map(|err| err["code"].clone()).collect();
+    assert_eq!(codes, vec![json!("TRANSITION_INVALID"), json!("SLA_CONFIG_INVALID")]);
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let (status, body) = error_of(
+        client
+            .put(format!("{}/cases/{}/move", base, case["id"].as_str().unwrap()))
+            .json(&json!({"to_phase": "Nowhere"}))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!((status, body["code"].as_str()), (400, Some("PHASE_INVALID")));
+
+    let missing = client.get(format!("{}/cases/{}", base, Uuid::new_v4())).send().await.unwrap();
+    let (status, body) = error_of(missing).await;
+    assert_eq!((status, body["code"].as_str()), (404, Some("CASE_NOT_FOUND")));
+}
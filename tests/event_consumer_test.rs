@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+
+use async_trait::async_trait;
+use orchepy::api::AppState;
+use orchepy::models::event::EventFilter;
+use orchepy::repositories::{EventRepository, ExecutionRepository};
+use orchepy::services::event_consumer::{BrokerDelivery, ConsumerConfig, MessageSource};
+use orchepy::services::WebhookSender;
+use orchepy::workers::consume_events;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+struct QueuedSource {
+    messages: VecDeque<BrokerDelivery>,
+    acked: usize,
+}
+
+#[async_trait]
+impl MessageSource for QueuedSource {
+    async fn next(&mut self) -> anyhow::Result<Option<BrokerDelivery>> {
+        Ok(self.messages.pop_front())
+    }
+
+    async fn ack(&mut self) -> anyhow::Result<()> {
+        self.acked += 1;
+        Ok(())
+    }
+}
+
+fn delivery(topic: &str, payload: &str) -> BrokerDelivery {
+    BrokerDelivery { topic: topic.to_string(), payload: payload.as_bytes().to_vec() }
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_broker_messages_become_events(pool: PgPool) {
+    let flow_id = Uuid::new_v4();
+    sqlx::query(
+        r#"INSERT INTO orchepy_flows (id, name, trigger, steps) VALUES ($1, 'Ship', '{"event_type": "order.paid"}', '[]')"#,
+    )
+    .bind(flow_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let state = AppState::new(pool.clone(), WebhookSender::new());
+    let config = ConsumerConfig {
+        broker: "kafka".to_string(),
+        url: "localhost:9092".to_string(),
+        topic: "orders".to_string(),
+        group: "orchepy".to_string(),
+        region: None,
+    };
+    let mut source = QueuedSource {
+        messages: VecDeque::from([
+            delivery("orders", r#"{"event_type": "order.paid", "data": {"order": 7}}"#),
+            delivery("orders", "not json"),
+            delivery("order.refunded", r#"{"order": 8}"#),
+        ]),
+        acked: 0,
+    };
+
+    consume_events(&state, &config, &mut source).await.unwrap();
+    assert_eq!(source.acked, 3);
+
+    let repo = EventRepository::new(&pool);
+    let mut events = repo.search(&EventFilter::default(), None, 0).await.unwrap();
+    events.sort_by_key(|event| event.event_type.clone());
+    assert_eq!(events.len(), 2);
+
+    assert_eq!(events[0].event_type, "order.paid");
+    assert_eq!(events[0].data, json!({"order": 7}));
+    assert_eq!(events[0].metadata, Some(json!({"broker": {"type": "kafka", "topic": "orders"}})));
+    let executions = ExecutionRepository::new(&pool).list_by_event(events[0].id).await.unwrap();
+    assert_eq!(executions.len(), 1);
+    assert_eq!(executions[0].flow_id, flow_id);
+
+    assert_eq!(events[1].event_type, "order.refunded");
+    assert_eq!(events[1].data, json!({"order": 8}));
+}
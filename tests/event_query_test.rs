@@ -0,0 +1,126 @@
+use chrono::{Duration, Utc};
+use orchepy::models::event::EventSearch;
+use orchepy::models::execution::{ExecutionFilter, ExecutionStatus};
+use orchepy::repositories::{EventRepository, ExecutionRepository};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn insert_event(pool: &PgPool, event_type: &str, data: serde_json::Value, hours_ago: i64) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orchepy_events (id, event_type, data, received_at) VALUES ($1, $2, $3, $4)")
+        .bind(id)
+        .bind(event_type)
+        .bind(data)
+        .bind(Utc::now() - Duration::hours(hours_ago))
+        .execute(pool)
+        .await
+        .unwrap();
+    id
+}
+
+fn params(pairs: &[(&str, String)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_search_events_and_their_executions(pool: PgPool) {
+    let gold = insert_event(&pool, "order.created", json!({"customer": {"tier": "gold"}, "total": 250}), 1).await;
+    insert_event(&pool, "order.created", json!({"customer": {"tier": "silver"}, "total": 40}), 2).await;
+    insert_event(&pool, "order.created", json!({"customer": {"tier": "gold"}, "total": 90}), 48).await;
+    insert_event(&pool, "case.moved", json!({"total": 500}), 1).await;
+
+    let repo = EventRepository::new(&pool);
+    let since = (Utc::now() - Duration::days(1)).to_rfc3339();
+
+    let search = EventSearch::from_params(&params(&[
+        ("event_type", "order.created".to_string()),
+        ("since", since.clone()),
+    ]))
+    .unwrap();
+    assert_eq!(repo.count(&search.filter).await.unwrap(), 2);
+    let events = repo.search(&search.filter, Some(1), 0).await.unwrap();
+    assert_eq!(events[0].id, gold);
+
+    let search = EventSearch::from_params(&params(&[
+        ("data.customer.tier", "gold".to_string()),
+        ("data.total[gte]", "100".to_string()),
+    ]))
+    .unwrap();
+    let events = repo.search(&search.filter, None, 0).await.unwrap();
+    assert_eq!(events.iter().map(|event| event.id).collect::<Vec<_>>(), vec![gold]);
+
+    let flow_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orchepy_flows (id, name, trigger, steps) VALUES ($1, 'Notify', '{}', '[]')")
+        .bind(flow_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    for minutes_ago in [5, 1] {
+        sqlx::query("INSERT INTO orchepy_executions (id, flow_id, event_id, status, started_at) VALUES ($1, $2, $3, 'completed', $4)")
+            .bind(Uuid::new_v4())
+            .bind(flow_id)
+            .bind(gold)
+            .bind(Utc::now() - Duration::minutes(minutes_ago))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let executions = ExecutionRepository::new(&pool).list_by_event(gold).await.unwrap();
+    assert_eq!(executions.len(), 2);
+    assert!(executions[0].started_at < executions[1].started_at);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_filter_executions(pool: PgPool) {
+    let event_id = insert_event(&pool, "order.created", json!({}), 1).await;
+    let flow_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orchepy_flows (id, name, trigger, steps) VALUES ($1, 'Notify', '{}', '[]')")
+        .bind(flow_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let mut failed = None;
+    for (status, error, hours_ago) in [("completed", None, 1), ("failed", Some("timeout"), 2), ("completed", None, 30)] {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO orchepy_executions (id, flow_id, event_id, status, error, started_at) VALUES ($1, $2, $3, $4::execution_status, $5, $6)",
+        )
+        .bind(id)
+        .bind(flow_id)
+        .bind(event_id)
+        .bind(status)
+        .bind(error)
+        .bind(Utc::now() - Duration::hours(hours_ago))
+        .execute(&pool)
+        .await
+        .unwrap();
+        if error.is_some() {
+            failed = Some(id);
+        }
+    }
+
+    let repo = ExecutionRepository::new(&pool);
+    let recent = ExecutionFilter {
+        event_id: Some(event_id),
+        since: Some(Utc::now() - Duration::days(1)),
+        ..Default::default()
+    };
+    assert_eq!(repo.count(&recent).await.unwrap(), 2);
+    assert_eq!(repo.list(&recent, Some(1), 1).await.unwrap()[0].id, failed.unwrap());
+
+    let errored = ExecutionFilter { has_error: Some(true), ..Default::default() };
+    assert_eq!(repo.list(&errored, None, 0).await.unwrap().len(), 1);
+
+    let completed = ExecutionFilter {
+        status: Some(ExecutionStatus::Completed),
+        flow_id: Some(flow_id),
+        has_error: Some(false),
+        until: Some(Utc::now() - Duration::days(1)),
+        ..Default::default()
+    };
+    assert_eq!(repo.count(&completed).await.unwrap(), 1);
+    assert_eq!(repo.count(&ExecutionFilter { event_id: Some(Uuid::new_v4()), ..Default::default() }).await.unwrap(), 0);
+}
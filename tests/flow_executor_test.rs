@@ -0,0 +1,267 @@
+use chrono::{Duration, Utc};
+use orchepy::engine::Executor;
+use orchepy::models::event::CreateEvent;
+use orchepy::models::execution::ExecutionStatus;
+use orchepy::models::flow::{CreateFlow, FlowTrigger};
+use orchepy::models::step::{FailureAction, Step, StepType};
+use orchepy::models::{Event, Flow};
+use orchepy::services::clock::VirtualClock;
+use serde_json::json;
+
+fn create_flow(steps: Vec<Step>) -> Flow {
+    Flow::new(CreateFlow {
+        name: "Reminder".to_string(),
+        trigger: FlowTrigger {
+            event_type: "reminder.scheduled".to_string(),
+            filters: serde_json::Value::Null,
+            case: None,
+        },
+        steps,
+        max_concurrent_executions: None,
+        active: true,
+    })
+}
+
+fn step(name: &str, step_type: StepType) -> Step {
+    Step {
+        name: name.to_string(),
+        step_type,
+        on_failure: FailureAction::Stop,
+    }
+}
+
+fn create_event(data: serde_json::Value) -> Event {
+    Event::new(CreateEvent {
+        event_type: "reminder.scheduled".to_string(),
+        data,
+        metadata: None,
+    })
+}
+
+#[tokio::test]
+async fn test_delay_until_future_suspends_execution() {
+    let send_at = Utc::now() + Duration::hours(2);
+    let flow = create_flow(vec![
+        step(
+            "wait",
+            StepType::DelayUntil {
+                until: "${event.data.send_at}".to_string(),
+            },
+        ),
+        step("after", StepType::Delay { duration_ms: 1 }),
+    ]);
+    let event = create_event(json!({"send_at": send_at.to_rfc3339()}));
+
+    let executor = Executor::new();
+    let execution = executor.execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Waiting));
+    assert_eq!(execution.resume_step, Some(1));
+    assert_eq!(
+        execution.resume_at.unwrap().timestamp(),
+        send_at.timestamp()
+    );
+    assert!(execution.completed_at.is_none());
+
+    let resumed = executor.resume(&flow, &event, execution).await.unwrap();
+    assert!(matches!(resumed.status, ExecutionStatus::Completed));
+    assert!(resumed.resume_at.is_none());
+    assert_eq!(resumed.steps_status["wait"]["status"], "completed");
+    assert!(resumed.steps_status["wait"]["completed_at"].is_string());
+    assert!(resumed.steps_status["wait"]["duration_ms"].is_u64());
+    assert_eq!(resumed.steps_status["after"]["status"], "completed");
+}
+
+#[tokio::test]
+async fn test_delays_follow_a_virtual_clock() {
+    let start = Utc::now();
+    let flow = create_flow(vec![
+        step("pause", StepType::Delay { duration_ms: 3_600_000 }),
+        step(
+            "wait",
+            StepType::DelayUntil {
+                until: (start + Duration::hours(2)).to_rfc3339(),
+            },
+        ),
+    ]);
+    let event = create_event(json!({}));
+
+    let clock = VirtualClock::new(start);
+    let executor = Executor::new().with_clock(std::sync::Arc::new(clock.clone()));
+    let execution = executor.execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Waiting));
+    assert_eq!(execution.steps_status["pause"]["duration_ms"], 3_600_000);
+
+    clock.advance(Duration::hours(1));
+    let resumed = executor.resume(&flow, &event, execution).await.unwrap();
+    assert!(matches!(resumed.status, ExecutionStatus::Completed));
+    assert_eq!(resumed.completed_at, Some(start + Duration::hours(2)));
+}
+
+#[tokio::test]
+async fn test_delay_until_past_runs_inline() {
+    let flow = create_flow(vec![step(
+        "wait",
+        StepType::DelayUntil {
+            until: "2020-01-01T00:00:00Z".to_string(),
+        },
+    )]);
+    let event = create_event(json!({}));
+
+    let execution = Executor::new().execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Completed));
+    assert_eq!(execution.steps_status["wait"]["status"], "completed");
+}
+
+#[tokio::test]
+async fn test_delay_until_invalid_timestamp_fails() {
+    let flow = create_flow(vec![step(
+        "wait",
+        StepType::DelayUntil {
+            until: "${event.data.missing}".to_string(),
+        },
+    )]);
+    let event = create_event(json!({}));
+
+    let execution = Executor::new().execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Failed));
+    assert!(execution.error.unwrap().contains("Invalid delay_until timestamp"));
+}
+
+async fn spawn_echo_server() -> String {
+    use axum::{routing::post, Json, Router};
+
+    let app = Router::new().route(
+        "/echo",
+        post(|Json(body): Json<serde_json::Value>| async move { Json(body) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    format!("http://{}/echo", addr)
+}
+
+#[tokio::test]
+async fn test_fan_out_runs_child_per_item() {
+    let url = spawn_echo_server().await;
+    let flow = create_flow(vec![step(
+        "notify_lines",
+        StepType::FanOut {
+            items: "${event.data.order.lines}".to_string(),
+            step: Box::new(step(
+                "notify_line",
+                StepType::Webhook {
+                    url,
+                    method: "POST".to_string(),
+                    headers: Default::default(),
+                    body_template: json!({
+                        "sku": "${item.sku}",
+                        "position": "${item_index}",
+                        "line": "${item}",
+                    }),
+                    timeout_ms: None,
+                    retry: None,
+                },
+            )),
+            max_concurrency: Some(2),
+        },
+    )]);
+    let event = create_event(json!({
+        "order": {"lines": [{"sku": "A-1", "qty": 2}, {"sku": "B-2", "qty": 1}, {"sku": "C-3", "qty": 5}]}
+    }));
+
+    let execution = Executor::new().execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Completed));
+    let response = &execution.steps_status["notify_lines"]["response"];
+    assert_eq!(response["total"], 3);
+    assert_eq!(response["completed"], 3);
+
+    let items = response["items"].as_array().unwrap();
+    assert_eq!(items[1]["index"], 1);
+    assert_eq!(items[1]["response"]["sku"], "B-2");
+    assert_eq!(items[1]["response"]["position"], "1");
+    assert_eq!(items[2]["response"]["line"], json!({"sku": "C-3", "qty": 5}));
+}
+
+#[tokio::test]
+async fn test_fan_out_requires_array() {
+    let flow = create_flow(vec![step(
+        "fan",
+        StepType::FanOut {
+            items: "${event.data.lines}".to_string(),
+            step: Box::new(step("wait", StepType::Delay { duration_ms: 1 })),
+            max_concurrency: None,
+        },
+    )]);
+    let event = create_event(json!({"lines": "not-an-array"}));
+
+    let execution = Executor::new().execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Failed));
+    assert!(execution.error.unwrap().contains("must be an array"));
+}
+
+#[tokio::test]
+async fn test_step_status_records_attempts_and_duration() {
+    use axum::{http::StatusCode, routing::post, Router};
+    use orchepy::models::step::{BackoffStrategy, RetryConfig};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let calls = Arc::new(AtomicU32::new(0));
+    let counter = calls.clone();
+    let app = Router::new().route(
+        "/flaky",
+        post(move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::OK
+                }
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let flow = create_flow(vec![
+        step(
+            "call",
+            StepType::Webhook {
+                url: format!("http://{}/flaky", addr),
+                method: "POST".to_string(),
+                headers: Default::default(),
+                body_template: json!({}),
+                timeout_ms: None,
+                retry: Some(RetryConfig {
+                    max_attempts: 3,
+                    backoff: BackoffStrategy::Fixed,
+                    initial_delay_ms: 20,
+                }),
+            },
+        ),
+        step("pause", StepType::Delay { duration_ms: 1 }),
+    ]);
+    let event = create_event(json!({}));
+
+    let execution = Executor::new().execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Completed));
+    assert_eq!(execution.steps_status["call"]["attempts"], 2);
+    assert!(execution.steps_status["call"]["duration_ms"].as_u64().unwrap() >= 20);
+    assert_eq!(execution.steps_status["pause"]["attempts"], 1);
+    assert!(execution.steps_status["pause"]["duration_ms"].is_u64());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
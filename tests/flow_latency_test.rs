@@ -0,0 +1,67 @@
+use chrono::{Duration, Utc};
+use orchepy::repositories::ExecutionRepository;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn insert_execution(pool: &PgPool, flow_id: Uuid, event_id: Uuid, total_ms: i64, steps: serde_json::Value) {
+    let started_at = Utc::now() - Duration::minutes(5);
+    sqlx::query(
+        "INSERT INTO orchepy_executions (id, flow_id, event_id, status, steps_status, started_at, completed_at)
+         VALUES ($1, $2, $3, 'completed', $4, $5, $6)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(flow_id)
+    .bind(event_id)
+    .bind(steps)
+    .bind(started_at)
+    .bind(started_at + Duration::milliseconds(total_ms))
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_flow_and_step_latency(pool: PgPool) {
+    let flow_id = Uuid::new_v4();
+    let event_id = Uuid::new_v4();
+
+    sqlx::query("INSERT INTO orchepy_flows (id, name, trigger, steps) VALUES ($1, 'Sync', '{}', '[]')")
+        .bind(flow_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO orchepy_events (id, event_type, data) VALUES ($1, 'sync', '{}')")
+        .bind(event_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    insert_execution(&pool, flow_id, event_id, 100, json!({
+        "fetch": {"status": "completed", "attempts": 1, "duration_ms": 80},
+        "notify": {"status": "completed", "attempts": 1, "duration_ms": 20}
+    }))
+    .await;
+    insert_execution(&pool, flow_id, event_id, 300, json!({
+        "fetch": {"status": "failed", "attempts": 3, "duration_ms": 280},
+        "legacy": {"status": "completed", "attempts": 1}
+    }))
+    .await;
+
+    let repo = ExecutionRepository::new(&pool);
+    let since = Utc::now() - Duration::days(1);
+
+    let executions = repo.flow_latency(flow_id, since).await.unwrap();
+    assert_eq!(executions.samples, 2);
+    assert_eq!(executions.max_ms, Some(300.0));
+    assert_eq!(executions.avg_ms, Some(200.0));
+
+    let steps = repo.step_latency(flow_id, since).await.unwrap();
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0].step, "fetch");
+    assert_eq!(steps[0].samples, 2);
+    assert_eq!(steps[0].failures, 1);
+    assert_eq!(steps[0].max_ms, Some(280.0));
+    assert_eq!(steps[0].avg_attempts, Some(2.0));
+    assert_eq!(steps[1].step, "notify");
+}
@@ -0,0 +1,131 @@
+use chrono::{Duration, Utc};
+use orchepy::engine::{Executor, FlowConcurrencyLimiter};
+use orchepy::models::event::CreateEvent;
+use orchepy::models::execution::ExecutionStatus;
+use orchepy::models::flow::{CreateFlow, FlowTrigger};
+use orchepy::models::step::{FailureAction, Step, StepType};
+use orchepy::models::{Event, Flow};
+use orchepy::repositories::{EventRepository, ExecutionRepository, FlowRepository};
+use orchepy::services::clock::system_clock;
+use orchepy::services::{EventPublisher, LoadShedder, LoadSheddingConfig, WebhookSender};
+use orchepy::workers::spawn_flow_resume_worker;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const LEASE: std::time::Duration = std::time::Duration::from_secs(300);
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_claimed_executions_are_leased(pool: PgPool) {
+    let flow_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orchepy_flows (id, name, trigger, steps) VALUES ($1, 'Remind', '{}', '[]')")
+        .bind(flow_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let execution_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO orchepy_executions (id, flow_id, event_id, status, steps_status, started_at, resume_at, resume_step)
+         VALUES ($1, $2, $3, 'waiting', '{}', $4, $4, 1)",
+    )
+    .bind(execution_id)
+    .bind(flow_id)
+    .bind(Uuid::new_v4())
+    .bind(Utc::now() - Duration::minutes(1))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let repo = ExecutionRepository::new(&pool);
+    let claimed = repo.claim_due_waiting(Utc::now(), 10, LEASE).await.unwrap();
+    assert_eq!(claimed.len(), 1);
+    assert!(matches!(claimed[0].status, ExecutionStatus::Running));
+
+    // Running under the lease, then claimed again once it has run out, as
+    // when the worker that claimed it crashed.
+    assert!(repo.claim_due_waiting(Utc::now(), 10, LEASE).await.unwrap().is_empty());
+    let expired = Utc::now() + Duration::minutes(10);
+    let reclaimed = repo.claim_due_waiting(expired, 10, LEASE).await.unwrap();
+    assert_eq!(reclaimed.len(), 1);
+    assert_eq!(reclaimed[0].id, execution_id);
+
+    // Saving the outcome ends the lease.
+    let mut completed = reclaimed[0].clone();
+    completed.status = ExecutionStatus::Completed;
+    completed.completed_at = Some(Utc::now());
+    completed.resume_at = None;
+    repo.update(&completed).await.unwrap();
+    let much_later = Utc::now() + Duration::hours(1);
+    assert!(repo.claim_due_waiting(much_later, 10, LEASE).await.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_resume_respects_the_current_flow_limit(pool: PgPool) {
+    let mut flow = Flow::new(CreateFlow {
+        name: "Remind".to_string(),
+        trigger: FlowTrigger { event_type: "reminder.scheduled".to_string(), filters: serde_json::Value::Null, case: None },
+        steps: vec![
+            Step {
+                name: "wait".to_string(),
+                step_type: StepType::DelayUntil { until: "${event.data.send_at}".to_string() },
+                on_failure: FailureAction::Stop,
+            },
+            Step { name: "after".to_string(), step_type: StepType::Delay { duration_ms: 1 }, on_failure: FailureAction::Stop },
+        ],
+        max_concurrent_executions: Some(2),
+        active: true,
+    });
+    let flows = FlowRepository::new(&pool);
+    flows.create(&flow).await.unwrap();
+
+    let event = Event::new(CreateEvent {
+        event_type: "reminder.scheduled".to_string(),
+        data: json!({"send_at": (Utc::now() + Duration::hours(1)).to_rfc3339()}),
+        metadata: None,
+    });
+    EventRepository::new(&pool).create(&event).await.unwrap();
+    let mut execution = Executor::new().execute(&flow, &event).await.unwrap();
+    assert!(matches!(execution.status, ExecutionStatus::Waiting));
+    execution.flow_version = Some(flow.version);
+    execution.resume_at = Some(Utc::now() - Duration::minutes(1));
+    ExecutionRepository::new(&pool).create(&execution).await.unwrap();
+
+    // The limit drops to 1 while the execution waits, and a live trigger
+    // holds that one permit.
+    flow.max_concurrent_executions = Some(1);
+    flow.version += 1;
+    assert!(flows.update(&flow).await.unwrap());
+    let limiter = FlowConcurrencyLimiter::new();
+    let live = limiter.acquire(&flow).await;
+    assert!(live.is_some());
+
+    spawn_flow_resume_worker(
+        pool.clone(),
+        limiter.clone(),
+        WebhookSender::new(),
+        EventPublisher::default(),
+        None,
+        None,
+        LoadShedder::new(LoadSheddingConfig::disabled()),
+        system_clock(),
+        std::time::Duration::from_millis(50),
+    );
+
+    // The resume waits at the current limit rather than running under the
+    // pinned version's, and live triggers stay capped too.
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let repo = ExecutionRepository::new(&pool);
+    assert!(matches!(repo.find_by_id(execution.id).await.unwrap().unwrap().status, ExecutionStatus::Waiting));
+    assert!(limiter.try_acquire(&flow).is_err());
+
+    drop(live);
+    let mut status = ExecutionStatus::Waiting;
+    for _ in 0..50 {
+        status = repo.find_by_id(execution.id).await.unwrap().unwrap().status;
+        if matches!(status, ExecutionStatus::Completed) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    assert!(matches!(status, ExecutionStatus::Completed));
+}
@@ -0,0 +1,227 @@
+use chrono::{Duration, Utc};
+use orchepy::engine::simulation::MockSource;
+use orchepy::engine::{Executor, MockResponse, ReplayReport, Simulation};
+use orchepy::models::event::{CreateEvent, EventFilter};
+use orchepy::models::execution::ExecutionStatus;
+use orchepy::models::flow::{CreateFlow, FlowTrigger};
+use orchepy::models::step::{FailureAction, Step, StepType};
+use orchepy::models::{Event, Flow};
+use orchepy::repositories::{EventRepository, ExecutionRepository};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn create_flow(steps: Vec<Step>) -> Flow {
+    Flow::new(CreateFlow {
+        name: "Onboarding".to_string(),
+        trigger: FlowTrigger {
+            event_type: "customer.created".to_string(),
+            filters: serde_json::Value::Null,
+            case: None,
+        },
+        steps,
+        max_concurrent_executions: None,
+        active: true,
+    })
+}
+
+fn webhook(name: &str, url: &str) -> Step {
+    Step {
+        name: name.to_string(),
+        step_type: StepType::Webhook {
+            url: url.to_string(),
+            method: "post".to_string(),
+            headers: HashMap::from([("X-Customer".to_string(), "${event.data.id}".to_string())]),
+            body_template: json!({"email": "${event.data.email}"}),
+            timeout_ms: None,
+            retry: None,
+        },
+        on_failure: FailureAction::Stop,
+    }
+}
+
+fn create_event() -> Event {
+    Event::new(CreateEvent {
+        event_type: "customer.created".to_string(),
+        data: json!({"id": "c-1", "email": "ana@example.com"}),
+        metadata: None,
+    })
+}
+
+#[tokio::test]
+async fn test_simulation_uses_mocks_without_sending() {
+    let flow = create_flow(vec![
+        webhook("crm", "http://127.0.0.1:9/customers/${event.data.id}"),
+        Step {
+            name: "wait".to_string(),
+            step_type: StepType::Delay { duration_ms: 60_000 },
+            on_failure: FailureAction::Stop,
+        },
+        Step {
+            name: "later".to_string(),
+            step_type: StepType::DelayUntil {
+                until: (Utc::now() + Duration::days(1)).to_rfc3339(),
+            },
+            on_failure: FailureAction::Stop,
+        },
+        webhook("welcome", "http://127.0.0.1:9/welcome"),
+    ]);
+
+    let simulation = Arc::new(Simulation::new(
+        HashMap::from([(
+            "crm".to_string(),
+            MockResponse { status: 201, body: json!({"crm_id": 7}) },
+        )]),
+        HashMap::new(),
+    ));
+
+    let started = std::time::Instant::now();
+    let execution = Executor::simulated(simulation.clone())
+        .execute(&flow, &create_event())
+        .await
+        .unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+    assert!(matches!(execution.status, ExecutionStatus::Completed));
+    assert_eq!(execution.steps_status["crm"]["response"], json!({"crm_id": 7}));
+    assert_eq!(
+        execution.steps_status["crm"]["request"],
+        json!({"method": "POST", "url": "http://127.0.0.1:9/customers/c-1", "body": {"email": "ana@example.com"}})
+    );
+    assert!(execution.steps_status["later"]["response"]["would_wait_until"].is_string());
+
+    let requests = simulation.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].url, "http://127.0.0.1:9/customers/c-1");
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].headers["X-Customer"], "c-1");
+    assert_eq!(requests[0].body, json!({"email": "ana@example.com"}));
+    assert_eq!(requests[0].source, MockSource::Provided);
+    assert_eq!(requests[1].source, MockSource::Default);
+}
+
+#[tokio::test]
+async fn test_simulation_error_mock_fails_step() {
+    let flow = create_flow(vec![
+        webhook("crm", "http://127.0.0.1:9/customers"),
+        webhook("welcome", "http://127.0.0.1:9/welcome"),
+    ]);
+
+    let simulation = Arc::new(Simulation::new(
+        HashMap::from([(
+            "crm".to_string(),
+            MockResponse { status: 503, body: json!("unavailable") },
+        )]),
+        HashMap::from([("welcome".to_string(), json!({"sent": true}))]),
+    ));
+
+    let execution = Executor::simulated(simulation.clone())
+        .execute(&flow, &create_event())
+        .await
+        .unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Failed));
+    assert!(execution.error.unwrap().contains("HTTP 503"));
+    assert_eq!(simulation.requests().len(), 1);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_recorded_responses_use_latest_success(pool: PgPool) {
+    let flow_id = Uuid::new_v4();
+    let event_id = Uuid::new_v4();
+
+    sqlx::query("INSERT INTO orchepy_flows (id, name, trigger, steps) VALUES ($1, 'Sync', '{}', '[]')")
+        .bind(flow_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO orchepy_events (id, event_type, data) VALUES ($1, 'sync', '{}')")
+        .bind(event_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    for (minutes_ago, steps) in [
+        (30, json!({"crm": {"status": "completed", "response": {"crm_id": 1}}})),
+        (20, json!({"crm": {"status": "completed", "response": {"crm_id": 2}}})),
+        (10, json!({
+            "crm": {"status": "failed", "error": "HTTP 500"},
+            "welcome": {"status": "completed", "response": {"sent": true}}
+        })),
+    ] {
+        sqlx::query(
+            "INSERT INTO orchepy_executions (id, flow_id, event_id, status, steps_status, started_at)
+             VALUES ($1, $2, $3, 'completed', $4, $5)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(flow_id)
+        .bind(event_id)
+        .bind(steps)
+        .bind(Utc::now() - Duration::minutes(minutes_ago))
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let recorded = ExecutionRepository::new(&pool)
+        .recorded_responses(flow_id)
+        .await
+        .unwrap();
+
+    assert_eq!(recorded.len(), 2);
+    assert_eq!(recorded["crm"], json!({"crm_id": 2}));
+    assert_eq!(recorded["welcome"], json!({"sent": true}));
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_replay_sample_reports_branch_coverage(pool: PgPool) {
+    for (event_type, amount) in [("customer.created", 50), ("customer.created", 500), ("customer.deleted", 900)] {
+        sqlx::query("INSERT INTO orchepy_events (id, event_type, data, received_at) VALUES ($1, $2, $3, $4)")
+            .bind(Uuid::new_v4())
+            .bind(event_type)
+            .bind(json!({"id": "c-1", "amount": amount}))
+            .bind(Utc::now())
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let flow = create_flow(vec![Step {
+        name: "route".to_string(),
+        step_type: StepType::Condition {
+            condition: "${event.data.amount} > 100".to_string(),
+            if_true: Box::new(webhook("review", "http://127.0.0.1:9/review")),
+            if_false: Box::new(webhook("approve", "http://127.0.0.1:9/approve")),
+        },
+        on_failure: FailureAction::Stop,
+    }]);
+
+    let filter = EventFilter {
+        event_type: Some("customer.created".to_string()),
+        ..Default::default()
+    };
+    let events = EventRepository::new(&pool).list_filtered(&filter, 10).await.unwrap();
+    assert_eq!(events.len(), 2);
+
+    let mocks = HashMap::from([(
+        "review".to_string(),
+        MockResponse {
+            status: 500,
+            body: json!({"error": "unavailable"}),
+        },
+    )]);
+
+    let mut report = ReplayReport::new(&flow);
+    for event in &events {
+        let simulation = Arc::new(Simulation::new(mocks.clone(), HashMap::new()));
+        let execution = Executor::simulated(simulation.clone()).execute(&flow, event).await.unwrap();
+        report.record(&execution, &simulation.branches());
+    }
+
+    assert_eq!((report.trigger_matched, report.completed, report.failed), (2, 1, 1));
+    assert_eq!((report.branches[0].if_true, report.branches[0].if_false), (1, 1));
+    assert!(report.branches[0].fully_covered());
+    assert_eq!(report.failures[0].step.as_deref(), Some("route"));
+}
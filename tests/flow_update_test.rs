@@ -0,0 +1,118 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_concurrent_flow_updates_conflict(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let flow: Value = client
+        .post(format!("{}/flows", base))
+        .json(&json!({
+            "name": "Sync lead",
+            "trigger": {"event_type": "lead.created"},
+            "steps": [{
+                "name": "push",
+                "type": "webhook",
+                "url": "https://example.com/hook",
+                "method": "POST",
+                "body_template": {"lead": "${event.data.id}"}
+            }]
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let flow_url = format!("{}/flows/{}", base, flow["id"].as_str().unwrap());
+
+    let updates = (0..8).map(|i| {
+        let (client, flow_url) = (client.clone(), flow_url.clone());
+        async move { client.put(&flow_url).json(&json!({"name": format!("Sync lead {}", i)})).send().await.unwrap() }
+    });
+    let mut updated = 0;
+    for response in futures::future::join_all(updates).await {
+        match response.status().as_u16() {
+            200 => updated += 1,
+            409 => assert_eq!(response.json::<Value>().await.unwrap()["code"], "VERSION_CONFLICT"),
+            status => panic!("unexpected status {}", status),
+        }
+    }
+    assert!(updated >= 1);
+
+    // Each update that went through made exactly one version.
+    let flow: Value = client.get(&flow_url).send().await.unwrap().json().await.unwrap();
+    assert_eq!(flow["version"], 1 + updated);
+    let versions: Vec<Value> = client.get(format!("{}/versions", flow_url)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(versions.len() as i64, 1 + updated);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_flow_update_validates_steps(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+    let step = json!({"name": "push", "type": "webhook", "url": "https://example.com/hook", "method": "POST"});
+    let flow: Value = client
+        .post(format!("{}/flows", base))
+        .json(&json!({"name": "Sync lead", "trigger": {"event_type": "lead.created"}, "steps": [step]}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let flow_url = format!("{}/flows/{}", base, flow["id"].as_str().unwrap());
+
+    let invalid = [
+        json!([]),
+        json!([{"name": "push", "type": "webhook", "url": "ftp://example.com/hook", "method": "POST"}]),
+        json!([{"name": "push", "type": "webhook", "url": "https://example.com/hook", "method": "POST", "timeout_ms": 0}]),
+    ];
+    for steps in invalid {
+        let response = client.put(&flow_url).json(&json!({"steps": steps})).send().await.unwrap();
+        assert_eq!(response.status(), 422);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "VALIDATION_FAILED");
+        assert!(body["fields"]["steps"][0].is_string());
+    }
+
+    let flow: Value = client.get(&flow_url).send().await.unwrap().json().await.unwrap();
+    assert_eq!(flow["version"], 1);
+    assert_eq!(flow["steps"][0]["url"], "https://example.com/hook");
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_flow_update_sets_and_clears_concurrency_limit(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+    let step = json!({"name": "push", "type": "webhook", "url": "https://example.com/hook", "method": "POST"});
+    let flow: Value = client
+        .post(format!("{}/flows", base))
+        .json(&json!({"name": "Sync lead", "trigger": {"event_type": "lead.created"}, "steps": [step], "max_concurrent_executions": 2}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let flow_url = format!("{}/flows/{}", base, flow["id"].as_str().unwrap());
+
+    let response = client.put(&flow_url).json(&json!({"max_concurrent_executions": 0})).send().await.unwrap();
+    assert_eq!(response.status(), 422);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "VALIDATION_FAILED");
+    assert_eq!(body["fields"]["max_concurrent_executions"][0], "must be at least 1");
+
+    // Leaving the field out keeps the limit; null removes it.
+    let flow: Value = client.put(&flow_url).json(&json!({"name": "Sync leads"})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(flow["max_concurrent_executions"], 2);
+    let flow: Value = client.put(&flow_url).json(&json!({"max_concurrent_executions": null})).send().await.unwrap().json().await.unwrap();
+    assert!(flow["max_concurrent_executions"].is_null());
+    let flow: Value = client.put(&flow_url).json(&json!({"max_concurrent_executions": 3})).send().await.unwrap().json().await.unwrap();
+    assert_eq!(flow["max_concurrent_executions"], 3);
+}
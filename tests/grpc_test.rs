@@ -0,0 +1,162 @@
+#![cfg(feature = "grpc")]
+
+use chrono::Utc;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use orchepy::api::AppState;
+use orchepy::grpc::proto::ingestion_client::IngestionClient;
+use orchepy::grpc::proto::{CreateCaseRequest, EventRequest, MoveCaseRequest};
+use orchepy::models::Workflow;
+use orchepy::repositories::WorkflowRepository;
+use orchepy::services::{JwtAuth, WebhookSender};
+use sqlx::PgPool;
+use tonic::transport::Channel;
+use uuid::Uuid;
+
+mod common;
+
+async fn create_workflow(pool: &PgPool) -> Workflow {
+    let workflow = Workflow {
+        id: Uuid::new_v4(),
+        name: "Orders".to_string(),
+        phases: vec!["New".into(), "Shipped".into()],
+        initial_phase: "New".to_string(),
+        webhook_url: None,
+        webhook_template: None,
+        webhook_headers: Default::default(),
+        webhook_auth: None,
+        active: true,
+        description: None,
+        automations: None,
+        sla_config: None,
+        execution_limits: Default::default(),
+        data_conflicts: Default::default(),
+        transitions: Default::default(),
+        region: None,
+        version: 1,
+        updated_by: None,
+        archived_at: None,
+        archived_by: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    WorkflowRepository::new(pool).create(&workflow).await.unwrap();
+    workflow
+}
+
+async fn connect(pool: &PgPool) -> IngestionClient<Channel> {
+    connect_to(AppState::new(pool.clone(), WebhookSender::new())).await
+}
+
+async fn connect_to(state: AppState) -> IngestionClient<Channel> {
+    IngestionClient::connect(common::serve(orchepy::grpc::router(state)).await).await.unwrap()
+}
+
+fn event(event_type: &str, data_json: &str) -> EventRequest {
+    EventRequest {
+        event_type: event_type.to_string(),
+        data_json: data_json.to_string(),
+        metadata_json: None,
+    }
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_grpc_case_lifecycle(pool: PgPool) {
+    let workflow = create_workflow(&pool).await;
+    let mut client = connect(&pool).await;
+
+    let created = client
+        .create_case(CreateCaseRequest {
+            workflow_id: workflow.id.to_string(),
+            data_json: r#"{"order": 7}"#.to_string(),
+            tags: vec!["priority".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(created.current_phase, "New");
+    assert_eq!(created.status, "active");
+    let case: serde_json::Value = serde_json::from_str(&created.case_json).unwrap();
+    assert_eq!(case["data"]["order"], 7);
+
+    let moved = client
+        .move_case(MoveCaseRequest {
+            case_id: created.id.clone(),
+            to_phase: "Shipped".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(moved.id, created.id);
+    assert_eq!(moved.current_phase, "Shipped");
+
+    let missing = client
+        .move_case(MoveCaseRequest {
+            case_id: Uuid::new_v4().to_string(),
+            to_phase: "Shipped".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(missing.code(), tonic::Code::NotFound);
+
+    let invalid = client
+        .create_case(CreateCaseRequest {
+            workflow_id: workflow.id.to_string(),
+            data_json: "{not json".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_grpc_event_ingestion(pool: PgPool) {
+    let mut client = connect(&pool).await;
+
+    let sent = client.send_event(event("order.created", r#"{"id": 1}"#)).await.unwrap().into_inner();
+    assert!(Uuid::parse_str(&sent.event_id).is_ok());
+    assert_eq!(sent.matched_flows, 0);
+
+    let mut events: Vec<_> = (0..50).map(|i| event("order.created", &format!(r#"{{"id": {}}}"#, i))).collect();
+    events.push(event("order.created", "oops"));
+
+    let reply = client.ingest_events(futures::stream::iter(events)).await.unwrap().into_inner();
+    assert_eq!((reply.accepted, reply.rejected), (50, 1));
+    assert_eq!(reply.event_ids.len(), 50);
+    assert_eq!(reply.errors.len(), 1);
+
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orchepy_events").fetch_one(&pool).await.unwrap();
+    assert_eq!(stored, 51);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_grpc_calls_need_an_operator_token(pool: PgPool) {
+    let auth = JwtAuth::with_secret("test-secret");
+    let mut client = connect_to(AppState::new(pool.clone(), WebhookSender::new()).with_auth(auth)).await;
+
+    let request = |role: Option<&str>| {
+        let mut request = tonic::Request::new(event("order.created", "{}"));
+        if let Some(role) = role {
+            let claims = serde_json::json!({
+                "sub": format!("{}@example.com", role),
+                "exp": Utc::now().timestamp() + 600,
+                "roles": [role],
+            });
+            let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test-secret")).unwrap();
+            request.metadata_mut().insert("authorization", format!("Bearer {}", token).parse().unwrap());
+        }
+        request
+    };
+
+    let anonymous = client.send_event(request(None)).await.unwrap_err();
+    assert_eq!(anonymous.code(), tonic::Code::Unauthenticated);
+    let viewer = client.send_event(request(Some("viewer"))).await.unwrap_err();
+    assert_eq!(viewer.code(), tonic::Code::PermissionDenied);
+    assert!(client.send_event(request(Some("operator"))).await.is_ok());
+
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orchepy_events").fetch_one(&pool).await.unwrap();
+    assert_eq!(stored, 1);
+}
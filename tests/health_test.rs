@@ -0,0 +1,79 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::{DataRegions, WebhookSender, WorkerMonitor};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+async fn get_health(base: &str, query: &str) -> (StatusCode, Value) {
+    let response = reqwest::get(format!("{}/health{}", base, query)).await.unwrap();
+    (response.status(), response.json().await.unwrap())
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_deep_health_check(pool: PgPool) {
+    let workers = WorkerMonitor::new();
+    workers.track("usage_flush", tokio::spawn(std::future::pending()));
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()).with_workers(workers.clone()))).await;
+
+    let (status, body) = get_health(&base, "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "healthy");
+    assert!(body.get("regions").is_none());
+
+    let (status, body) = get_health(&base, "?deep=true").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "healthy");
+    let region = &body["regions"][0];
+    assert_eq!(region["region"], "default");
+    assert_eq!(region["database"]["status"], "healthy");
+    assert_eq!(region["migrations"]["pending"], json!([]));
+    assert_eq!(region["queues"]["status"], "healthy");
+    assert!(region["pool"]["max_connections"].as_u64().unwrap() > 0);
+    assert!(region["pool"]["in_use"].is_u64());
+    assert_eq!(body["workers"]["workers"], json!([{"name": "usage_flush", "running": true}]));
+
+    // A worker that stopped degrades the service without taking it down.
+    let stopped = tokio::spawn(async {});
+    while !stopped.is_finished() {
+        tokio::task::yield_now().await;
+    }
+    workers.track_in_region("flow_resume", "default", stopped);
+
+    let (status, body) = get_health(&base, "?deep=true").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["workers"]["status"], "degraded");
+
+    // Missing migrations leave the schema behind the code.
+    let latest: i64 = sqlx::query_scalar(
+        "DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations) RETURNING version",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let (status, body) = get_health(&base, "?deep=true").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "unhealthy");
+    assert_eq!(body["regions"][0]["migrations"]["pending"], json!([latest]));
+}
+
+#[sqlx::test(migrations = false)]
+async fn test_startup_migrations(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+
+    let (status, body) = get_health(&base, "?deep=true").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["regions"][0]["migrations"]["applied"], 0);
+
+    let regions = DataRegions::single(pool.clone());
+    regions.migrate().await.unwrap();
+    // Running them again finds nothing left to do.
+    regions.migrate().await.unwrap();
+
+    let (status, body) = get_health(&base, "?deep=true").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["regions"][0]["migrations"]["pending"], json!([]));
+}
@@ -0,0 +1,90 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+async fn count(pool: &PgPool, from: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", from))
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_retries_with_an_idempotency_key_replay_the_response(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+    let post = |path: &str, key: &str, body: Value| {
+        client
+            .post(format!("{}{}", base, path))
+            .header("Idempotency-Key", key)
+            .json(&body)
+            .send()
+    };
+
+    let workflow = json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New"});
+    let first = post("/workflows", "wf-1", workflow.clone()).await.unwrap();
+    assert!(first.status().is_success());
+    assert!(first.headers().get("idempotent-replayed").is_none());
+    let first: Value = first.json().await.unwrap();
+
+    let retry = post("/workflows", "wf-1", workflow.clone()).await.unwrap();
+    assert_eq!(retry.headers()["idempotent-replayed"], "true");
+    assert_eq!(retry.headers()["content-type"], "application/json");
+    assert_eq!(retry.json::<Value>().await.unwrap(), first);
+    assert_eq!(count(&pool, "orchepy_workflows").await, 1);
+
+    let reused = post("/workflows", "wf-1", json!({"name": "Other", "phases": ["A"], "initial_phase": "A"}))
+        .await
+        .unwrap();
+    assert_eq!(reused.status(), 422);
+
+    let case = json!({"workflow_id": first["id"], "data": {"order": 1}});
+    let created: Value = post("/cases", "case-1", case.clone()).await.unwrap().json().await.unwrap();
+    let replayed: Value = post("/cases", "case-1", case.clone()).await.unwrap().json().await.unwrap();
+    assert_eq!(created["id"], replayed["id"]);
+    post("/cases", "case-2", case).await.unwrap();
+    assert_eq!(count(&pool, "orchepy_cases").await, 2);
+
+    let event = json!({"event_type": "order.created", "data": {"order": 1}});
+    for _ in 0..3 {
+        post("/events", "event-1", event.clone()).await.unwrap();
+    }
+    assert_eq!(count(&pool, "orchepy_events WHERE event_type = 'order.created'").await, 1);
+
+    // Keys are per route: the same key on another endpoint is a new request.
+    post("/events", "case-1", event).await.unwrap();
+    assert_eq!(count(&pool, "orchepy_events WHERE event_type = 'order.created'").await, 2);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_failed_requests_can_be_retried_with_the_same_key(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let missing = json!({"workflow_id": uuid::Uuid::new_v4(), "data": {}});
+    let response = client
+        .post(format!("{}/cases", base))
+        .header("Idempotency-Key", "retry-me")
+        .json(&missing)
+        .send()
+        .await
+        .unwrap();
+    let status = response.status();
+    assert!(status.is_client_error());
+
+    let again = client
+        .post(format!("{}/cases", base))
+        .header("Idempotency-Key", "retry-me")
+        .json(&missing)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(again.status(), status);
+    assert_eq!(again.headers()["idempotent-replayed"], "true");
+
+    let repo = orchepy::repositories::IdempotencyRepository::new(&pool);
+    assert_eq!(repo.prune(chrono::Utc::now() + chrono::Duration::days(2)).await.unwrap(), 1);
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tokio_tungstenite::tungstenite::Message;
+
+mod common;
+
+/// Messages received until the socket goes quiet.
+async fn drain<S>(socket: &mut S) -> Vec<Value>
+where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    let mut messages = Vec::new();
+    while let Ok(Some(Ok(message))) = tokio::time::timeout(Duration::from_millis(500), socket.next()).await {
+        if let Message::Text(text) = message {
+            messages.push(serde_json::from_str(text.as_str()).unwrap());
+        }
+    }
+    messages
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_dashboards_receive_changes_to_subscribed_workflows(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("{}/ws", base.replacen("http", "ws", 1))).await.unwrap();
+
+    let mut workflows = Vec::new();
+    for name in ["Orders", "Returns"] {
+        let workflow: Value = client
+            .post(format!("{}/workflows", base))
+            .json(&json!({"name": name, "phases": ["New", "Done"], "initial_phase": "New"}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        workflows.push(workflow["id"].as_str().unwrap().to_string());
+    }
+
+    let created = drain(&mut socket).await;
+    assert_eq!(created.len(), 2);
+    assert!(created.iter().all(|change| change["type"] == "workflow.created"));
+
+    socket
+        .send(Message::Text(json!({"type": "subscribe", "workflow_ids": [workflows[0]]}).to_string().into()))
+        .await
+        .unwrap();
+    let replies = drain(&mut socket).await;
+    assert_eq!(replies, vec![json!({"type": "subscribed", "workflow_ids": [workflows[0]]})]);
+
+    for workflow_id in &workflows {
+        let case: Value = client
+            .post(format!("{}/cases", base))
+            .json(&json!({"workflow_id": workflow_id, "data": {}}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        client
+            .put(format!("{}/cases/{}/move", base, case["id"].as_str().unwrap()))
+            .json(&json!({"to_phase": "Done"}))
+            .send()
+            .await
+            .unwrap();
+        client
+            .put(format!("{}/workflows/{}", base, workflow_id))
+            .json(&json!({"description": "Updated"}))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let changes = drain(&mut socket).await;
+    let types: Vec<&str> = changes.iter().map(|change| change["type"].as_str().unwrap()).collect();
+    assert_eq!(types, vec!["case.created", "case.moved", "workflow.updated"]);
+    assert_eq!(changes[1]["case"]["workflow_id"], workflows[0].as_str());
+    assert_eq!(changes[1]["case"]["current_phase"], "Done");
+    assert_eq!(changes[2]["workflow"]["description"], "Updated");
+
+    socket.send(Message::Text("{\"type\": \"shout\"}".into())).await.unwrap();
+    assert_eq!(drain(&mut socket).await[0]["type"], "error");
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "memory-store")]
+
+use chrono::{Duration, Utc};
+use orchepy::engine::{apply_modifications, AutomationExecutor, Executor};
+use orchepy::models::automation::PhaseAutomation;
+use orchepy::models::event::CreateEvent;
+use orchepy::models::execution::ExecutionStatus;
+use orchepy::models::flow::{CreateFlow, FlowTrigger};
+use orchepy::models::step::{FailureAction, Step, StepType};
+use orchepy::models::{Case, Event, Flow, Workflow};
+use orchepy::storage::{ExecutionStore, MemoryStore};
+use serde_json::json;
+use uuid::Uuid;
+
+fn workflow() -> Workflow {
+    serde_json::from_value(json!({
+        "id": Uuid::new_v4(),
+        "name": "Support",
+        "phases": ["New", "Triage", "Closed"],
+        "initial_phase": "New",
+        "active": true,
+        "execution_limits": {},
+        "created_at": Utc::now(),
+        "updated_at": Utc::now(),
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_automation_run_without_database() {
+    let workflow = workflow();
+    let mut store = MemoryStore::new();
+    let case = Case::new(workflow.id, "New".to_string(), json!({"priority": "high"}), None);
+    store.insert_case(case.clone());
+
+    let automation: PhaseAutomation = serde_json::from_value(json!({
+        "trigger": "on_enter",
+        "phase": "New",
+        "actions": [{
+            "type": "conditional",
+            "field": "data.priority",
+            "operator": "==",
+            "value": "high",
+            "then": [
+                {"type": "set_field", "field": "data.escalated", "value": true},
+                {"type": "move_to_phase", "phase": "Triage"}
+            ]
+        }]
+    }))
+    .unwrap();
+
+    let result = AutomationExecutor::new().execute_automations(&[&automation], &case, None).await.unwrap();
+    apply_modifications(&mut store, case.id, &workflow, result, "on_enter", "automation").await.unwrap();
+
+    let stored = store.case(case.id).unwrap();
+    assert_eq!(stored.current_phase, "Triage");
+    assert_eq!(stored.data["escalated"], true);
+    assert_eq!(store.history(case.id)[0].from_phase.as_deref(), Some("New"));
+}
+
+#[tokio::test]
+async fn test_execution_resumes_from_store() {
+    let flow = Flow::new(CreateFlow {
+        name: "Reminder".to_string(),
+        trigger: FlowTrigger {
+            event_type: "reminder.scheduled".to_string(),
+            filters: serde_json::Value::Null,
+            case: None,
+        },
+        steps: vec![
+            Step {
+                name: "wait".to_string(),
+                step_type: StepType::DelayUntil { until: "${event.data.send_at}".to_string() },
+                on_failure: FailureAction::Stop,
+            },
+            Step {
+                name: "after".to_string(),
+                step_type: StepType::Delay { duration_ms: 1 },
+                on_failure: FailureAction::Stop,
+            },
+        ],
+        max_concurrent_executions: None,
+        active: true,
+    });
+    let event = Event::new(CreateEvent {
+        event_type: "reminder.scheduled".to_string(),
+        data: json!({"send_at": (Utc::now() + Duration::hours(1)).to_rfc3339()}),
+        metadata: None,
+    });
+
+    let store = MemoryStore::new();
+    let executor = Executor::new();
+    let execution = executor.execute(&flow, &event).await.unwrap();
+    store.create_execution(&execution).await.unwrap();
+
+    let waiting = store.find_execution(execution.id).await.unwrap().unwrap();
+    assert!(matches!(waiting.status, ExecutionStatus::Waiting));
+
+    let resumed = executor.resume(&flow, &event, waiting).await.unwrap();
+    store.update_execution(&resumed).await.unwrap();
+    assert!(matches!(store.execution(execution.id).unwrap().status, ExecutionStatus::Completed));
+}
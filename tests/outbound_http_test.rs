@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::{extract::Request, routing::post, Router};
+use orchepy::engine::Executor;
+use orchepy::models::event::CreateEvent;
+use orchepy::models::execution::ExecutionStatus;
+use orchepy::models::flow::{CreateFlow, FlowTrigger};
+use orchepy::models::step::{FailureAction, Step, StepType};
+use orchepy::models::{Event, Flow};
+use orchepy::services::OutboundHttpConfig;
+use serde_json::json;
+
+/// A server recording the URI each request asked for: the full URL when it
+/// came through as a proxy, the path when it was the destination.
+async fn spawn_recorder() -> (String, Arc<Mutex<Vec<String>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let recorded = received.clone();
+    let app = Router::new().route(
+        "/hook",
+        post(move |request: Request| {
+            let recorded = recorded.clone();
+            async move {
+                recorded.lock().unwrap().push(request.uri().to_string());
+                "ok"
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    (format!("http://{}", addr), received)
+}
+
+fn webhook_flow(urls: &[String]) -> Flow {
+    let steps = urls
+        .iter()
+        .enumerate()
+        .map(|(index, url)| Step {
+            name: format!("notify_{}", index),
+            step_type: StepType::Webhook {
+                url: url.clone(),
+                method: "POST".to_string(),
+                headers: HashMap::new(),
+                body_template: json!({}),
+                timeout_ms: None,
+                retry: None,
+            },
+            on_failure: FailureAction::Stop,
+        })
+        .collect();
+
+    Flow::new(CreateFlow {
+        name: "Notify".to_string(),
+        trigger: FlowTrigger {
+            event_type: "order.created".to_string(),
+            filters: serde_json::Value::Null,
+            case: None,
+        },
+        steps,
+        max_concurrent_executions: None,
+        active: true,
+    })
+}
+
+#[tokio::test]
+async fn test_webhooks_go_through_the_proxy() {
+    let (proxy, proxied) = spawn_recorder().await;
+    let (direct_base, direct) = spawn_recorder().await;
+
+    OutboundHttpConfig {
+        http_proxy: Some(proxy),
+        no_proxy: Some("127.0.0.1".to_string()),
+        ..Default::default()
+    }
+    .load()
+    .unwrap()
+    .install();
+
+    let flow = webhook_flow(&["http://erp.example.invalid/hook".to_string(), format!("{}/hook", direct_base)]);
+    let event = Event::new(CreateEvent {
+        event_type: "order.created".to_string(),
+        data: json!({}),
+        metadata: None,
+    });
+    let execution = Executor::new().execute(&flow, &event).await.unwrap();
+
+    assert!(matches!(execution.status, ExecutionStatus::Completed));
+    assert_eq!(proxied.lock().unwrap().clone(), vec!["http://erp.example.invalid/hook".to_string()]);
+    assert_eq!(direct.lock().unwrap().clone(), vec!["/hook".to_string()]);
+}
@@ -0,0 +1,435 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::post, Json, Router};
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use orchepy::workers::spawn_outbox_dispatcher;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+
+mod common;
+
+/// A webhook receiver passing on each body it accepts; while `failing` is
+/// set it answers 500 instead.
+async fn spawn_receiver(failing: Arc<AtomicBool>) -> (String, mpsc::UnboundedReceiver<Value>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(
+                |State((tx, failing)): State<(mpsc::UnboundedSender<Value>, Arc<AtomicBool>)>,
+                 Json(body): Json<Value>| async move {
+                    if failing.load(Ordering::SeqCst) {
+                        return StatusCode::INTERNAL_SERVER_ERROR;
+                    }
+                    tx.send(body).unwrap();
+                    StatusCode::OK
+                },
+            ),
+        )
+        .with_state((tx, failing));
+    (format!("{}/hook", common::serve(app).await), rx)
+}
+
+/// Deliveries until none arrive for a moment.
+async fn drain(rx: &mut mpsc::UnboundedReceiver<Value>) -> Vec<Value> {
+    let mut deliveries = Vec::new();
+    while let Ok(Some(delivery)) = tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+        deliveries.push(delivery);
+    }
+    deliveries
+}
+
+async fn pending_messages(pool: &PgPool) -> Vec<(String, i32, bool)> {
+    sqlx::query_as("SELECT kind, attempts, failed_at IS NOT NULL FROM orchepy_outbox ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_events_and_webhooks_go_through_the_outbox(pool: PgPool) {
+    let failing = Arc::new(AtomicBool::new(false));
+    let (hook, mut deliveries) = spawn_receiver(failing.clone()).await;
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New", "webhook_url": hook}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {"amount": 10}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let response = client
+        .put(format!("{}/cases/{}/move", base, case["id"].as_str().unwrap()))
+        .json(&json!({"to_phase": "Done"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let webhooks = drain(&mut deliveries).await;
+    let phases: Vec<(Value, Value)> = webhooks
+        .iter()
+        .map(|webhook| (webhook["data"]["from_phase"].clone(), webhook["data"]["to_phase"].clone()))
+        .collect();
+    assert_eq!(phases, vec![(Value::Null, json!("New")), (json!("New"), json!("Done"))]);
+
+    let events: Vec<String> = sqlx::query_scalar("SELECT event_type FROM orchepy_events ORDER BY received_at")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(events, vec!["case.created", "case.moved"]);
+    assert!(pending_messages(&pool).await.is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_failed_webhooks_are_retried_in_order(pool: PgPool) {
+    let failing = Arc::new(AtomicBool::new(true));
+    let (hook, mut deliveries) = spawn_receiver(failing.clone()).await;
+    let state = AppState::new(pool.clone(), WebhookSender::new());
+    let base = common::serve(build_router(state.clone())).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Review", "Done"], "initial_phase": "New", "webhook_url": hook}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case_id = case["id"].as_str().unwrap();
+    for phase in ["Review", "Done"] {
+        client
+            .put(format!("{}/cases/{}/move", base, case_id))
+            .json(&json!({"to_phase": phase}))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    // The first webhook failed once; the moves' webhooks wait behind it,
+    // while the case's events went out.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(deliveries.try_recv().is_err());
+    assert_eq!(
+        pending_messages(&pool).await,
+        vec![
+            ("webhook".to_string(), 1, false),
+            ("webhook".to_string(), 0, false),
+            ("webhook".to_string(), 0, false),
+        ]
+    );
+
+    // The first webhook is given up on after its last attempt, which lets
+    // the next one through.
+    sqlx::query("UPDATE orchepy_outbox SET attempts = 11, next_attempt_at = NOW() WHERE attempts = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let dispatcher = spawn_outbox_dispatcher(
+        state.clone(),
+        state.regions.default_region().to_string(),
+        Duration::from_millis(100),
+    );
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(
+        pending_messages(&pool).await,
+        vec![
+            ("webhook".to_string(), 12, true),
+            ("webhook".to_string(), 1, false),
+            ("webhook".to_string(), 0, false),
+        ]
+    );
+
+    failing.store(false, Ordering::SeqCst);
+    sqlx::query("UPDATE orchepy_outbox SET next_attempt_at = NOW() WHERE failed_at IS NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let phases: Vec<Value> = drain(&mut deliveries).await.iter().map(|webhook| webhook["data"]["to_phase"].clone()).collect();
+    assert_eq!(phases, vec![json!("Review"), json!("Done")]);
+    assert_eq!(pending_messages(&pool).await, vec![("webhook".to_string(), 12, true)]);
+
+    dispatcher.abort();
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_failed_subscription_deliveries_are_listed_and_retried(pool: PgPool) {
+    let failing = Arc::new(AtomicBool::new(true));
+    let (hook, mut deliveries) = spawn_receiver(failing.clone()).await;
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let subscription: Value = client
+        .post(format!("{}/webhooks", base))
+        .json(&json!({"url": hook, "event_types": ["order.paid"]}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    client
+        .post(format!("{}/events", base))
+        .json(&json!({"event_type": "order.paid", "data": {"order": 7}}))
+        .send()
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let pending: Value = client
+        .get(format!("{}/webhook-deliveries/queue?status=pending", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(pending.as_array().unwrap().len(), 1);
+    assert_eq!(pending[0]["status"], "pending");
+    assert_eq!(pending[0]["attempts"], 1);
+    assert_eq!(pending[0]["delivery"]["kind"], "subscription");
+    assert_eq!(pending[0]["delivery"]["subscription_id"], subscription["id"]);
+    assert!(pending[0]["last_error"].as_str().unwrap().contains("500"));
+
+    let id = pending[0]["id"].as_i64().unwrap();
+    let retry = client.post(format!("{}/webhook-deliveries/queue/{}/retry", base, id)).send().await.unwrap();
+    assert_eq!(retry.status(), 404);
+
+    sqlx::query("UPDATE orchepy_outbox SET failed_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let failed: Value = client
+        .get(format!("{}/webhook-deliveries/queue?status=failed", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(failed[0]["id"], id);
+    assert_eq!(failed[0]["status"], "failed");
+    let invalid = client.get(format!("{}/webhook-deliveries/queue?status=lost", base)).send().await.unwrap();
+    assert_eq!(invalid.status(), 400);
+
+    failing.store(false, Ordering::SeqCst);
+    let retry = client.post(format!("{}/webhook-deliveries/queue/{}/retry", base, id)).send().await.unwrap();
+    assert_eq!(retry.status(), 200);
+    let delivered = drain(&mut deliveries).await;
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0]["action"], "order.paid");
+    assert_eq!(delivered[0]["data"], json!({"order": 7}));
+    assert!(pending_messages(&pool).await.is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_status_webhooks_go_through_the_outbox(pool: PgPool) {
+    let failing = Arc::new(AtomicBool::new(false));
+    let (hook, mut deliveries) = spawn_receiver(failing).await;
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New"], "initial_phase": "New", "webhook_url": hook}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let response = client
+        .post(format!("{}/cases/{}/complete", base, case["id"].as_str().unwrap()))
+        .json(&json!({"reason": "Paid"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let actions: Vec<Value> = drain(&mut deliveries).await.iter().map(|webhook| webhook["action"].clone()).collect();
+    assert_eq!(actions, vec![json!("case.moved"), json!("case.completed")]);
+    assert!(pending_messages(&pool).await.is_empty());
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_webhook_payload_templates(pool: PgPool) {
+    let failing = Arc::new(AtomicBool::new(false));
+    let (hook, mut deliveries) = spawn_receiver(failing).await;
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Orders",
+            "phases": ["New"],
+            "initial_phase": "New",
+            "webhook_url": hook,
+            "webhook_template": {"order": {"amount": "${event.data.amount}"}}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 422);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "WEBHOOK_TEMPLATE_INVALID");
+    assert_eq!(body["errors"][0]["path"], "webhook_template.order.amount");
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Orders",
+            "phases": ["New"],
+            "initial_phase": "New",
+            "webhook_url": hook,
+            "webhook_template": {
+                "DocType": "ORDER",
+                "Reference": "ORD-${case.data.number}",
+                "Amount": "${case.data.amount}",
+                "Stage": "${case.current_phase}",
+                "Event": "${action}"
+            }
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {"number": 7, "amount": 12.5}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    client
+        .post(format!("{}/cases/{}/complete", base, case["id"].as_str().unwrap()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        drain(&mut deliveries).await,
+        vec![
+            json!({"DocType": "ORDER", "Reference": "ORD-7", "Amount": 12.5, "Stage": "New", "Event": "case.moved"}),
+            json!({"DocType": "ORDER", "Reference": "ORD-7", "Amount": 12.5, "Stage": "New", "Event": "case.completed"}),
+        ]
+    );
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_webhook_headers_and_auth(pool: PgPool) {
+    let (tx, mut received) = mpsc::unbounded_channel();
+    let receiver = Router::new()
+        .route(
+            "/hook",
+            post(|State(tx): State<mpsc::UnboundedSender<Value>>, headers: HeaderMap| async move {
+                let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+                tx.send(json!([header("authorization"), header("x-tenant")])).unwrap();
+                StatusCode::OK
+            }),
+        )
+        .with_state(tx);
+    let hook = format!("{}/hook", common::serve(receiver).await);
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Orders",
+            "phases": ["New", "Done"],
+            "initial_phase": "New",
+            "webhook_url": hook,
+            "webhook_headers": {"Content-Type": "text/plain"}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 422);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "WEBHOOK_HEADER_INVALID");
+    assert_eq!(body["errors"][0]["path"], "webhook_headers.Content-Type");
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Orders",
+            "phases": ["New", "Done"],
+            "initial_phase": "New",
+            "webhook_url": hook,
+            "webhook_headers": {"X-Tenant": "acme"},
+            "webhook_auth": {"type": "bearer", "token": "erp-token"}
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(workflow["webhook_auth"], json!({"type": "bearer", "token": "********"}));
+    let workflow_id = workflow["id"].as_str().unwrap();
+
+    // Sending the redacted auth back keeps the token.
+    let response = client
+        .put(format!("{}/workflows/{}", base, workflow_id))
+        .json(&json!({"webhook_auth": workflow["webhook_auth"], "webhook_headers": {"X-Tenant": "acme-eu"}}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow_id, "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(case["id"].is_string());
+
+    assert_eq!(drain(&mut received).await, vec![json!(["Bearer erp-token", "acme-eu"])]);
+}
@@ -0,0 +1,62 @@
+use chrono::{Months, Utc};
+use orchepy::models::event::CreateEvent;
+use orchepy::models::Event;
+use orchepy::repositories::partition_repository::PARTITIONED_TABLES;
+use orchepy::repositories::{EventRepository, PartitionRepository};
+use serde_json::json;
+use sqlx::PgPool;
+
+async fn partition_of(pool: &PgPool, event: &Event) -> String {
+    sqlx::query_scalar("SELECT tableoid::regclass::text FROM orchepy_events WHERE id = $1")
+        .bind(event.id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+fn event(months_ahead: u32) -> Event {
+    let mut event = Event::new(CreateEvent {
+        event_type: "order.created".to_string(),
+        data: json!({}),
+        metadata: None,
+    });
+    event.received_at = Utc::now().checked_add_months(Months::new(months_ahead)).unwrap();
+    event
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_monthly_partitions(pool: PgPool) {
+    let repo = PartitionRepository::new(&pool);
+    let month = |months_ahead: u32| {
+        Utc::now().checked_add_months(Months::new(months_ahead)).unwrap().format("%Y_%m").to_string()
+    };
+
+    for table in PARTITIONED_TABLES {
+        let partitions = repo.list(table).await.unwrap();
+        assert!(partitions.contains(&format!("{}_{}", table, month(0))), "{:?}", partitions);
+        assert!(partitions.contains(&format!("{}_{}", table, month(3))), "{:?}", partitions);
+    }
+
+    let events = EventRepository::new(&pool);
+    let current = event(0);
+    events.create(&current).await.unwrap();
+    assert_eq!(partition_of(&pool, &current).await, format!("orchepy_events_{}", month(0)));
+
+    // Rows past the last partition land in the default one, and that month
+    // can't be created over them; the months around it still are.
+    let ahead = event(6);
+    events.create(&ahead).await.unwrap();
+    assert_eq!(partition_of(&pool, &ahead).await, "orchepy_events_default");
+
+    let until = Utc::now().checked_add_months(Months::new(8)).unwrap();
+    let created = repo.create_monthly(Utc::now(), until).await.unwrap();
+    assert_eq!(created, 3 * 5 - 1);
+
+    let partitions = repo.list("orchepy_events").await.unwrap();
+    assert!(!partitions.contains(&format!("orchepy_events_{}", month(6))));
+    assert!(partitions.contains(&format!("orchepy_events_{}", month(7))));
+    assert!(repo.list("orchepy_executions").await.unwrap().contains(&format!("orchepy_executions_{}", month(6))));
+
+    assert_eq!(repo.create_monthly(Utc::now(), until).await.unwrap(), 0);
+    assert_eq!(events.find_by_id(ahead.id).await.unwrap().unwrap().id, ahead.id);
+}
@@ -0,0 +1,73 @@
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+fn request_id(response: &reqwest::Response) -> String {
+    response.headers()["x-request-id"].to_str().unwrap().to_string()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_errors_carry_the_request_id(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    // The caller's id is kept.
+    let response = client
+        .get(format!("{}/cases/{}", base, Uuid::new_v4()))
+        .header("X-Request-Id", "lb-7f3c.42")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(request_id(&response), "lb-7f3c.42");
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body, json!({"error": "Case not found", "code": "CASE_NOT_FOUND", "request_id": "lb-7f3c.42"}));
+
+    // One that is unsafe to echo is replaced.
+    let response = client
+        .get(format!("{}/health", base))
+        .header("X-Request-Id", "not an id")
+        .send()
+        .await
+        .unwrap();
+    assert!(Uuid::parse_str(&request_id(&response)).is_ok());
+
+    // Handlers on `ApiError` and the validation rejections share the envelope.
+    let response = client
+        .get(format!("{}/changes?since=nope", base))
+        .send()
+        .await
+        .unwrap();
+    let id = request_id(&response);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!((body["code"].as_str(), body["request_id"].as_str()), (Some("BAD_REQUEST"), Some(id.as_str())));
+
+    let response = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "", "phases": [], "initial_phase": ""}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 422);
+    let id = request_id(&response);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "VALIDATION_FAILED");
+    assert_eq!(body["request_id"], id);
+    assert!(body["fields"]["name"].is_array());
+
+    let response = client
+        .post(format!("{}/cases", base))
+        .header("content-type", "application/json")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "BAD_REQUEST");
+    assert!(body["error"].as_str().unwrap().contains("JSON"));
+}
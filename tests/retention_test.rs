@@ -0,0 +1,132 @@
+use chrono::{Duration, Utc};
+use orchepy::api::{build_router, AppState};
+use orchepy::services::{RetentionConfig, WebhookSender};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+async fn insert_event(pool: &PgPool, days_ago: i64) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orchepy_events (id, event_type, data, received_at) VALUES ($1, 'order.created', '{}', $2)")
+        .bind(id)
+        .bind(Utc::now() - Duration::days(days_ago))
+        .execute(pool)
+        .await
+        .unwrap();
+    id
+}
+
+async fn insert_execution(pool: &PgPool, flow_id: Uuid, event_id: Uuid, status: &str, days_ago: i64) -> Uuid {
+    let id = Uuid::new_v4();
+    let started_at = Utc::now() - Duration::days(days_ago);
+    let finished = matches!(status, "completed" | "failed");
+    sqlx::query(
+        "INSERT INTO orchepy_executions (id, flow_id, event_id, status, started_at, completed_at)
+         VALUES ($1, $2, $3, $4::execution_status, $5, $6)",
+    )
+    .bind(id)
+    .bind(flow_id)
+    .bind(event_id)
+    .bind(status)
+    .bind(started_at)
+    .bind(finished.then(|| started_at + Duration::minutes(1)))
+    .execute(pool)
+    .await
+    .unwrap();
+    id
+}
+
+async fn exists(pool: &PgPool, table: &str, id: Uuid) -> bool {
+    sqlx::query_scalar(&format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1)", table))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_purge_old_executions_and_events(pool: PgPool) {
+    let flow_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orchepy_flows (id, name, trigger, steps) VALUES ($1, 'Sync', '{}', '[]')")
+        .bind(flow_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let old_event = insert_event(&pool, 40).await;
+    let waiting_event = insert_event(&pool, 40).await;
+    let orphan_event = insert_event(&pool, 40).await;
+    let recent_event = insert_event(&pool, 1).await;
+    let old_completed = insert_execution(&pool, flow_id, old_event, "completed", 40).await;
+    let old_failed = insert_execution(&pool, flow_id, old_event, "failed", 35).await;
+    let old_waiting = insert_execution(&pool, flow_id, waiting_event, "waiting", 40).await;
+    let recent_completed = insert_execution(&pool, flow_id, recent_event, "completed", 1).await;
+
+    // A batch of one makes the purge loop over every row.
+    let retention = RetentionConfig {
+        execution_days: Some(30),
+        event_days: None,
+        webhook_delivery_days: None,
+        batch_size: 1,
+    };
+    let state = AppState::new(pool.clone(), WebhookSender::new()).with_retention(retention);
+    let base = common::serve(build_router(state)).await;
+    let client = reqwest::Client::new();
+
+    let response = client.post(format!("{}/admin/purge", base)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["deleted"], json!({"executions": 2, "events": 0, "webhook_deliveries": 0}));
+    assert_eq!(body["event_days"], Value::Null);
+    assert!(!exists(&pool, "orchepy_executions", old_completed).await);
+    assert!(!exists(&pool, "orchepy_executions", old_failed).await);
+    assert!(exists(&pool, "orchepy_executions", old_waiting).await);
+    assert!(exists(&pool, "orchepy_executions", recent_completed).await);
+
+    // Days in the body override the configured ones; an event with a
+    // waiting execution stays until the execution finishes.
+    let response = client
+        .post(format!("{}/admin/purge", base))
+        .json(&json!({"event_days": 30}))
+        .send()
+        .await
+        .unwrap();
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["deleted"], json!({"executions": 0, "events": 2, "webhook_deliveries": 0}));
+    assert!(!exists(&pool, "orchepy_events", old_event).await);
+    assert!(!exists(&pool, "orchepy_events", orphan_event).await);
+    assert!(exists(&pool, "orchepy_events", waiting_event).await);
+    assert!(exists(&pool, "orchepy_events", recent_event).await);
+
+    // Logged webhook attempts go once they are older than their days.
+    for days_ago in [40, 40, 1] {
+        sqlx::query(
+            "INSERT INTO orchepy_webhook_deliveries (url, payload, status, latency_ms, created_at)
+             VALUES ('https://example.com/hook', '{}', 'delivered', 5, $1)",
+        )
+        .bind(Utc::now() - Duration::days(days_ago))
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+    let response = client
+        .post(format!("{}/admin/purge", base))
+        .json(&json!({"webhook_delivery_days": 30}))
+        .send()
+        .await
+        .unwrap();
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["deleted"], json!({"executions": 0, "events": 0, "webhook_deliveries": 2}));
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orchepy_webhook_deliveries").fetch_one(&pool).await.unwrap();
+    assert_eq!(remaining, 1);
+
+    let response = client
+        .post(format!("{}/admin/purge", base))
+        .json(&json!({"event_days": 0}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 422);
+}
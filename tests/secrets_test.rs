@@ -0,0 +1,268 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{extract::Query, http::HeaderMap, routing::post, Router};
+use orchepy::api::{build_router, AppState};
+use orchepy::services::{SecretCipher, WebhookSender};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+/// A receiver that records the `token` query parameter and `Authorization`
+/// header of each request.
+async fn spawn_receiver() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let recorded = received.clone();
+    let app = Router::new().route(
+        "/hook",
+        post(move |Query(query): Query<std::collections::HashMap<String, String>>, headers: HeaderMap| {
+            let recorded = recorded.clone();
+            async move {
+                let authorization = headers["authorization"].to_str().unwrap().to_string();
+                recorded.lock().unwrap().push((query["token"].clone(), authorization));
+                "ok"
+            }
+        }),
+    );
+    (format!("{}/hook", common::serve(app).await), received)
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_secrets_crud(pool: PgPool) {
+    let state = AppState::new(pool.clone(), WebhookSender::new()).with_secrets(SecretCipher::new(&[7; 32]));
+    let base = common::serve(build_router(state)).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/secrets", base))
+        .json(&json!({"name": "CRM_TOKEN", "value": "tok_live_123", "description": "CRM API"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let secret: Value = response.json().await.unwrap();
+    assert_eq!(secret["name"], "CRM_TOKEN");
+    assert_eq!(secret["description"], "CRM API");
+    assert!(secret.get("value").is_none());
+    assert!(secret.get("ciphertext").is_none());
+
+    let ciphertext: Vec<u8> = sqlx::query_scalar("SELECT ciphertext FROM orchepy_secrets WHERE name = 'CRM_TOKEN'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(!ciphertext.windows(12).any(|window| window == b"tok_live_123"));
+
+    let duplicate = client
+        .post(format!("{}/secrets", base))
+        .json(&json!({"name": "CRM_TOKEN", "value": "other"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(duplicate.status(), 409);
+    let invalid = client
+        .post(format!("{}/secrets", base))
+        .json(&json!({"name": "crm-token", "value": "x"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(invalid.status(), 422);
+
+    let updated: Value = client
+        .put(format!("{}/secrets/CRM_TOKEN", base))
+        .json(&json!({"value": "tok_live_456"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(updated["name"], "CRM_TOKEN");
+    assert!(updated["description"].is_null());
+
+    let secrets: Vec<Value> = client.get(format!("{}/secrets", base)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(secrets.len(), 1);
+    assert!(secrets[0].get("value").is_none());
+
+    let response = client.delete(format!("{}/secrets/CRM_TOKEN", base)).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    let missing = client.get(format!("{}/secrets/CRM_TOKEN", base)).send().await.unwrap();
+    assert_eq!(missing.status(), 404);
+    assert_eq!(missing.json::<Value>().await.unwrap()["code"], "SECRET_NOT_FOUND");
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_flow_webhooks_use_secrets(pool: PgPool) {
+    let (hook, received) = spawn_receiver().await;
+    let state = AppState::new(pool.clone(), WebhookSender::new()).with_secrets(SecretCipher::new(&[7; 32]));
+    let base = common::serve(build_router(state)).await;
+    let client = reqwest::Client::new();
+
+    client
+        .post(format!("{}/secrets", base))
+        .json(&json!({"name": "CRM_TOKEN", "value": "tok_live_123"}))
+        .send()
+        .await
+        .unwrap();
+    let response = client
+        .post(format!("{}/flows", base))
+        .json(&json!({
+            "name": "Sync lead",
+            "trigger": {"event_type": "lead.created"},
+            "steps": [{
+                "name": "push",
+                "type": "webhook",
+                "url": format!("{}?token=${{secrets.CRM_TOKEN}}", hook),
+                "method": "POST",
+                "headers": {"Authorization": "Bearer ${secrets.CRM_TOKEN}"},
+                "body_template": {"lead": "${event.data.id}"}
+            }]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    let triggered: Value = client
+        .post(format!("{}/events", base))
+        .json(&json!({"event_type": "lead.created", "data": {"id": "L-1"}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        received.lock().unwrap().clone(),
+        vec![("tok_live_123".to_string(), "Bearer tok_live_123".to_string())]
+    );
+
+    let execution_id = triggered["executions"][0].as_str().unwrap();
+    let steps: Vec<Value> = client
+        .get(format!("{}/executions/{}/steps", base, execution_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(steps[0]["status"], "completed");
+    assert_eq!(steps[0]["request"]["url"], format!("{}?token=********", hook));
+    assert!(!steps.iter().any(|step| step.to_string().contains("tok_live_123")));
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_automation_webhooks_use_secrets(pool: PgPool) {
+    let (hook, received) = spawn_receiver().await;
+    let state = AppState::new(pool.clone(), WebhookSender::new()).with_secrets(SecretCipher::new(&[7; 32]));
+    let base = common::serve(build_router(state)).await;
+    let client = reqwest::Client::new();
+
+    client
+        .post(format!("{}/secrets", base))
+        .json(&json!({"name": "CRM_TOKEN", "value": "tok_live_123"}))
+        .send()
+        .await
+        .unwrap();
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({
+            "name": "Sync leads",
+            "phases": ["New", "Review"],
+            "initial_phase": "New",
+            "automations": {"automations": [{
+                "trigger": "on_enter",
+                "phase": "Review",
+                "actions": [{
+                    "type": "webhook",
+                    "name": "push",
+                    "url": format!("{}?token=${{secrets.CRM_TOKEN}}", hook),
+                    "headers": {"Authorization": "Bearer ${secrets.CRM_TOKEN}"}
+                }]
+            }]}
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow["id"], "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let response = client
+        .put(format!("{}/cases/{}/move", base, case["id"].as_str().unwrap()))
+        .json(&json!({"to_phase": "Review"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // The webhook goes out through the outbox once the move has committed.
+    for _ in 0..50 {
+        if !received.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(
+        received.lock().unwrap().clone(),
+        vec![("tok_live_123".to_string(), "Bearer tok_live_123".to_string())]
+    );
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_secrets_need_a_key(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool, WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/secrets", base))
+        .json(&json!({"name": "CRM_TOKEN", "value": "tok_live_123"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.json::<Value>().await.unwrap()["code"], "SECRETS_NOT_CONFIGURED");
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_reencrypt_after_key_rotation(pool: PgPool) {
+    let state = AppState::new(pool.clone(), WebhookSender::new()).with_secrets(SecretCipher::new(&[7; 32]));
+    let old = common::serve(build_router(state)).await;
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/secrets", old))
+        .json(&json!({"name": "CRM_TOKEN", "value": "tok_live_123"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    let rotated = SecretCipher::new(&[8; 32]).with_previous(&[7; 32]);
+    let state = AppState::new(pool.clone(), WebhookSender::new()).with_secrets(rotated);
+    let base = common::serve(build_router(state)).await;
+
+    let response = client.post(format!("{}/admin/secrets/reencrypt", base)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["secrets"]["reencrypted"], 1);
+    assert_eq!(body["secrets"]["failed"], json!([]));
+
+    // Now under the new key alone, and a second run has nothing to do.
+    let ciphertext: Vec<u8> = sqlx::query_scalar("SELECT ciphertext FROM orchepy_secrets WHERE name = 'CRM_TOKEN'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(SecretCipher::new(&[8; 32]).decrypt("CRM_TOKEN", &ciphertext).unwrap(), "tok_live_123");
+
+    let body: Value =
+        client.post(format!("{}/admin/secrets/reencrypt", base)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(body["secrets"]["reencrypted"], 0);
+}
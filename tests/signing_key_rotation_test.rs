@@ -0,0 +1,147 @@
+use chrono::{Duration, Utc};
+use orchepy::models::signing_key::{ManagedSigningKey, SigningKeyStatus};
+use orchepy::repositories::SigningKeyRepository;
+use orchepy::services::webhook_signing::{SigningKey, WEBHOOK_SIGNATURE_HEADER};
+use orchepy::services::{WebhookSigner, WebhookVerifier};
+use orchepy::workers::signing_keys::refresh_signing_keys;
+use sqlx::PgPool;
+
+mod common;
+
+fn signature_ids(signer: &WebhookSigner) -> Vec<String> {
+    signer
+        .headers("wh_1", b"{}", Utc::now())
+        .into_iter()
+        .find(|(name, _)| *name == WEBHOOK_SIGNATURE_HEADER)
+        .map(|(_, value)| value.split(", ").map(|pair| pair.split('=').next().unwrap().to_string()).collect())
+        .unwrap_or_default()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_signing_key_rotation(pool: PgPool) {
+    let repo = SigningKeyRepository::new(&pool);
+    let signer = WebhookSigner::new(vec![SigningKey::new("env", "configured-secret")]);
+
+    let first = ManagedSigningKey::new(Some("ops".to_string()));
+    assert!(repo.rotate(&first, Utc::now() + Duration::hours(1)).await.unwrap().is_empty());
+    refresh_signing_keys(&pool, &signer).await.unwrap();
+    assert_eq!(signature_ids(&signer), vec!["env".to_string(), first.id.clone()]);
+
+    let second = ManagedSigningKey::new(None);
+    assert_eq!(repo.rotate(&second, Utc::now() + Duration::hours(1)).await.unwrap(), vec![first.id.clone()]);
+    refresh_signing_keys(&pool, &signer.clone()).await.unwrap();
+    assert_eq!(signature_ids(&signer).len(), 3);
+
+    let headers = signer
+        .headers("wh_1", b"{}", Utc::now())
+        .into_iter()
+        .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+        .collect();
+    assert!(WebhookVerifier::new([first.secret.clone()]).verify(&headers, b"{}").is_ok());
+    assert!(WebhookVerifier::new([second.secret.clone()]).verify(&headers, b"{}").is_ok());
+
+    let third = ManagedSigningKey::new(None);
+    let retiring = repo.rotate(&third, Utc::now() - Duration::seconds(1)).await.unwrap();
+    assert_eq!(retiring.len(), 2);
+    refresh_signing_keys(&pool, &signer).await.unwrap();
+    assert_eq!(signature_ids(&signer), vec!["env".to_string(), third.id.clone()]);
+
+    let now = Utc::now();
+    let statuses: Vec<_> = repo.list().await.unwrap().iter().map(|key| (key.id.clone(), key.status(now))).collect();
+    assert!(statuses.contains(&(third.id.clone(), SigningKeyStatus::Active)));
+    assert!(statuses.contains(&(first.id.clone(), SigningKeyStatus::Retired)));
+
+    assert_eq!(repo.prune(now + Duration::seconds(1)).await.unwrap(), 2);
+    assert_eq!(repo.list().await.unwrap().len(), 1);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_workflow_webhook_secret(pool: PgPool) {
+    use axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Router};
+    use orchepy::api::{build_router, AppState};
+    use orchepy::services::WebhookSender;
+    use serde_json::{json, Value};
+    use tokio::sync::mpsc;
+
+    let (tx, mut deliveries) = mpsc::unbounded_channel::<(HeaderMap, Bytes)>();
+    let receiver = Router::new()
+        .route(
+            "/hook",
+            post(|State(tx): State<mpsc::UnboundedSender<(HeaderMap, Bytes)>>, headers: HeaderMap, body: Bytes| async move {
+                tx.send((headers, body)).unwrap();
+            }),
+        )
+        .with_state(tx);
+    let hook = format!("{}/hook", common::serve(receiver).await);
+
+    let sender = WebhookSender::new().with_signer(WebhookSigner::new(vec![SigningKey::new("env", "configured-secret")]));
+    let base = common::serve(build_router(AppState::new(pool.clone(), sender))).await;
+    let client = reqwest::Client::new();
+
+    let workflow: Value = client
+        .post(format!("{}/workflows", base))
+        .json(&json!({"name": "Orders", "phases": ["New", "Done"], "initial_phase": "New", "webhook_url": hook}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let workflow_id = workflow["id"].as_str().unwrap();
+
+    let short = client
+        .post(format!("{}/workflows/{}/webhook-secret", base, workflow_id))
+        .json(&json!({"secret": "short"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(short.status(), 422);
+
+    let response = client
+        .post(format!("{}/workflows/{}/webhook-secret", base, workflow_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let secret: Value = response.json().await.unwrap();
+    let secret = secret["secret"].as_str().unwrap().to_string();
+    assert_eq!(secret.len(), 64);
+
+    let case: Value = client
+        .post(format!("{}/cases", base))
+        .json(&json!({"workflow_id": workflow_id, "data": {}}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let (headers, body) = deliveries.recv().await.unwrap();
+    assert!(headers[WEBHOOK_SIGNATURE_HEADER].to_str().unwrap().starts_with(&format!("{}=", workflow_id)));
+    assert!(WebhookVerifier::new([secret.clone()]).verify(&headers, &body).is_ok());
+    assert!(WebhookVerifier::new(["configured-secret"]).verify(&headers, &body).is_err());
+
+    let removed = client
+        .delete(format!("{}/workflows/{}/webhook-secret", base, workflow_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(removed.status(), 204);
+
+    client
+        .put(format!("{}/cases/{}/move", base, case["id"].as_str().unwrap()))
+        .json(&json!({"to_phase": "Done"}))
+        .send()
+        .await
+        .unwrap();
+    let (headers, body) = deliveries.recv().await.unwrap();
+    assert!(WebhookVerifier::new(["configured-secret"]).verify(&headers, &body).is_ok());
+    assert!(WebhookVerifier::new([secret]).verify(&headers, &body).is_err());
+
+    let missing = client
+        .post(format!("{}/workflows/{}/webhook-secret", base, uuid::Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+}
@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode, routing::post, Router};
+use orchepy::api::{build_router, AppState};
+use orchepy::services::WebhookSender;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+mod common;
+
+/// A webhook receiver answering 500 while `failing` is set, 200 otherwise.
+async fn spawn_receiver(failing: Arc<AtomicBool>) -> String {
+    let app = Router::new()
+        .route(
+            "/hook",
+            post(|State(failing): State<Arc<AtomicBool>>| async move {
+                if failing.load(Ordering::SeqCst) {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                } else {
+                    StatusCode::OK
+                }
+            }),
+        )
+        .with_state(failing);
+    format!("{}/hook", common::serve(app).await)
+}
+
+async fn get_json(client: &reqwest::Client, url: String) -> Value {
+    client.get(url).send().await.unwrap().json().await.unwrap()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_webhook_attempts_are_logged_and_redelivered(pool: PgPool) {
+    let failing = Arc::new(AtomicBool::new(true));
+    let hook = spawn_receiver(failing.clone()).await;
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let client = reqwest::Client::new();
+
+    let subscription: Value = client
+        .post(format!("{}/webhooks", base))
+        .json(&json!({"url": hook, "event_types": ["order.paid"]}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let subscription_id = subscription["id"].as_str().unwrap();
+    client
+        .post(format!("{}/events", base))
+        .json(&json!({"event_type": "order.paid", "data": {"order": 7}}))
+        .send()
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let log = get_json(&client, format!("{}/webhook-deliveries", base)).await;
+    assert_eq!(log.as_array().unwrap().len(), 1);
+    let failed = &log[0];
+    assert_eq!(failed["status"], "failed");
+    assert_eq!(failed["status_code"], 500);
+    assert_eq!(failed["url"], hook);
+    assert_eq!(failed["subscription_id"], subscription["id"]);
+    assert_eq!(failed["payload"]["data"], json!({"order": 7}));
+    assert!(failed["latency_ms"].as_i64().unwrap() >= 0);
+    assert!(failed["error"].as_str().unwrap().contains("500"));
+    assert!(failed["redelivery_of"].is_null());
+
+    failing.store(false, Ordering::SeqCst);
+    let id = failed["id"].as_i64().unwrap();
+    let response = client.post(format!("{}/webhook-deliveries/{}/redeliver", base, id)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let redelivered: Value = response.json().await.unwrap();
+    assert_eq!(redelivered["status"], "delivered");
+    assert_eq!(redelivered["status_code"], 200);
+    assert!(redelivered["error"].is_null());
+    assert_eq!(redelivered["redelivery_of"], id);
+    assert_eq!(redelivered["payload"], failed["payload"]);
+
+    let delivered = get_json(&client, format!("{}/webhook-deliveries?status=delivered", base)).await;
+    assert_eq!(delivered.as_array().unwrap().len(), 1);
+    assert_eq!(delivered[0]["id"], redelivered["id"]);
+    let failures = get_json(&client, format!("{}/webhook-deliveries?status=failed", base)).await;
+    assert_eq!(failures.as_array().unwrap().len(), 1);
+    assert_eq!(failures[0]["id"], id);
+
+    let all = get_json(&client, format!("{}/webhook-deliveries?subscription_id={}", base, subscription_id)).await;
+    let ids: Vec<&Value> = all.as_array().unwrap().iter().map(|entry| &entry["id"]).collect();
+    assert_eq!(ids, vec![&redelivered["id"], &failed["id"]]);
+    let other = get_json(&client, format!("{}/webhook-deliveries?workflow_id={}", base, uuid::Uuid::new_v4())).await;
+    assert!(other.as_array().unwrap().is_empty());
+    let later = get_json(&client, format!("{}/webhook-deliveries?since=2999-01-01T00:00:00Z", base)).await;
+    assert!(later.as_array().unwrap().is_empty());
+    let first = get_json(&client, format!("{}/webhook-deliveries?limit=1&offset=1", base)).await;
+    assert_eq!(first[0]["id"], id);
+
+    let invalid = client.get(format!("{}/webhook-deliveries?status=lost", base)).send().await.unwrap();
+    assert_eq!(invalid.status(), 400);
+    let missing = client.post(format!("{}/webhook-deliveries/999999/redeliver", base)).send().await.unwrap();
+    assert_eq!(missing.status(), 404);
+    assert_eq!(missing.json::<Value>().await.unwrap()["code"], "WEBHOOK_DELIVERY_NOT_FOUND");
+
+    client.delete(format!("{}/webhooks/{}", base, subscription_id)).send().await.unwrap();
+    let gone = client.post(format!("{}/webhook-deliveries/{}/redeliver", base, id)).send().await.unwrap();
+    assert_eq!(gone.status(), 409);
+}
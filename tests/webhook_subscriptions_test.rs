@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Router};
+use orchepy::api::{build_router, AppState};
+use orchepy::services::{WebhookSender, WebhookVerifier};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+
+mod common;
+
+/// A receiver passing on each delivery's path, headers and body.
+async fn spawn_receiver() -> (String, mpsc::UnboundedReceiver<(String, HeaderMap, Bytes)>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route(
+            "/{hook}",
+            post(
+                |State(tx): State<mpsc::UnboundedSender<(String, HeaderMap, Bytes)>>,
+                 axum::extract::Path(hook): axum::extract::Path<String>,
+                 headers: HeaderMap,
+                 body: Bytes| async move {
+                    tx.send((hook, headers, body)).unwrap();
+                },
+            ),
+        )
+        .with_state(tx);
+    (common::serve(app).await, rx)
+}
+
+/// Deliveries until none arrive for a moment.
+async fn drain(rx: &mut mpsc::UnboundedReceiver<(String, HeaderMap, Bytes)>) -> Vec<(String, HeaderMap, Bytes)> {
+    let mut deliveries = Vec::new();
+    while let Ok(Some(delivery)) = tokio::time::timeout(Duration::from_millis(500), rx.recv()).await {
+        deliveries.push(delivery);
+    }
+    deliveries
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_events_are_sent_to_matching_subscriptions(pool: PgPool) {
+    let base = common::serve(build_router(AppState::new(pool.clone(), WebhookSender::new()))).await;
+    let (receiver, mut deliveries) = spawn_receiver().await;
+    let client = reqwest::Client::new();
+    let post = |path: &str, body: Value| client.post(format!("{}{}", base, path)).json(&body).send();
+
+    let mut workflows = Vec::new();
+    for name in ["Orders", "Returns"] {
+        let workflow: Value = post("/workflows", json!({"name": name, "phases": ["New", "Done"], "initial_phase": "New"}))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        workflows.push(workflow["id"].as_str().unwrap().to_string());
+    }
+
+    let invalid = post("/webhooks", json!({"url": "ftp://example.com", "event_types": []})).await.unwrap();
+    assert_eq!(invalid.status(), 422);
+
+    let moves = post(
+        "/webhooks",
+        json!({"url": format!("{}/moves", receiver), "event_types": ["case.moved"], "workflow_id": workflows[0]}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(moves.status(), 201);
+    let moves: Value = moves.json().await.unwrap();
+    let secret = moves["secret"].as_str().unwrap().to_string();
+
+    post(
+        "/webhooks",
+        json!({"url": format!("{}/failures", receiver), "event_types": ["execution.failed"], "secret": "failures-secret-123"}),
+    )
+    .await
+    .unwrap();
+
+    let listed: Value = client.get(format!("{}/webhooks", base)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    assert!(listed[0].get("secret").is_none());
+
+    for workflow_id in &workflows {
+        let case: Value = post("/cases", json!({"workflow_id": workflow_id, "data": {}})).await.unwrap().json().await.unwrap();
+        let moved = client
+            .put(format!("{}/cases/{}/move", base, case["id"].as_str().unwrap()))
+            .json(&json!({"to_phase": "Done"}))
+            .send()
+            .await
+            .unwrap();
+        assert!(moved.status().is_success());
+    }
+
+    post(
+        "/flows",
+        json!({
+            "name": "Scheduled",
+            "trigger": {"event_type": "reminder.due"},
+            "steps": [{"name": "wait", "type": "delay_until", "until": "${event.data.send_at}"}]
+        }),
+    )
+    .await
+    .unwrap();
+    post("/events", json!({"event_type": "reminder.due", "data": {"send_at": "someday"}})).await.unwrap();
+
+    let received = drain(&mut deliveries).await;
+    assert_eq!(received.len(), 2);
+
+    let (_, headers, body) = received.iter().find(|(hook, _, _)| hook == "moves").unwrap();
+    assert!(WebhookVerifier::new([secret]).verify(headers, body).is_ok());
+    let moved: Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(moved["action"], "case.moved");
+    assert_eq!(moved["subscription_id"], moves["id"]);
+    assert_eq!(moved["data"]["workflow_id"], workflows[0].as_str());
+
+    let (_, _, body) = received.iter().find(|(hook, _, _)| hook == "failures").unwrap();
+    let failed: Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(failed["action"], "execution.failed");
+    assert_eq!(failed["data"]["flow_name"], "Scheduled");
+    assert!(failed["data"]["error"].is_string());
+
+    let deleted = client
+        .delete(format!("{}/webhooks/{}", base, moves["id"].as_str().unwrap()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), 204);
+    let missing = client
+        .get(format!("{}/webhooks/{}", base, moves["id"].as_str().unwrap()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+}
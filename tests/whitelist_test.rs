@@ -0,0 +1,61 @@
+use axum::middleware;
+use orchepy::api::{build_router, AppState};
+use orchepy::middleware::{whitelist_middleware, Whitelist, WhitelistConfig};
+use orchepy::services::WebhookSender;
+use serde_json::Value;
+use sqlx::PgPool;
+
+mod common;
+
+async fn status_from(client: &reqwest::Client, url: &str, ip: &str) -> u16 {
+    client.get(url).header("X-Forwarded-For", ip).send().await.unwrap().status().as_u16()
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_whitelist_is_loaded_once_and_reloaded(pool: PgPool) {
+    // The only test in this binary, so nothing else reads these meanwhile.
+    std::env::set_var("WHITELIST_ENABLED", "true");
+    std::env::set_var("WHITELIST_IPS", "10.0.0.0/8");
+
+    let whitelist = Whitelist::new(WhitelistConfig::from_env());
+    let app = build_router(AppState::new(pool, WebhookSender::new()).with_whitelist(whitelist.clone()))
+        .layer(middleware::from_fn_with_state(whitelist, whitelist_middleware));
+    let base = common::serve(app).await;
+    let client = reqwest::Client::new();
+    let workflows = format!("{}/workflows", base);
+
+    assert_eq!(status_from(&client, &workflows, "10.1.2.3").await, 200);
+    assert_eq!(status_from(&client, &workflows, "192.168.1.7").await, 403);
+
+    // Changing the environment has no effect until the whitelist is reloaded.
+    std::env::set_var("WHITELIST_IPS", "192.168.1.0/24, 2001:db8::/32, 10.0.0.1/40");
+    assert_eq!(status_from(&client, &workflows, "192.168.1.7").await, 403);
+
+    let before: Value = client
+        .get(format!("{}/admin/whitelist", base))
+        .header("X-Forwarded-For", "127.0.0.1")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(before["config"]["enabled"], true);
+    assert_eq!(before["config"]["allowed_ips"], serde_json::json!(["10.0.0.0/8"]));
+
+    let response = client
+        .post(format!("{}/admin/whitelist/reload", base))
+        .header("X-Forwarded-For", "127.0.0.1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let reloaded: Value = response.json().await.unwrap();
+    assert_eq!(reloaded["config"]["allowed_ips"], serde_json::json!(["192.168.1.0/24", "2001:db8::/32"]));
+    assert_eq!(reloaded["config"]["invalid_entries"], serde_json::json!(["10.0.0.1/40"]));
+    assert!(reloaded["loaded_at"].as_str().unwrap() > before["loaded_at"].as_str().unwrap());
+
+    assert_eq!(status_from(&client, &workflows, "192.168.1.7").await, 200);
+    assert_eq!(status_from(&client, &workflows, "2001:db8::5").await, 200);
+    assert_eq!(status_from(&client, &workflows, "10.1.2.3").await, 403);
+}
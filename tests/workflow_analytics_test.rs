@@ -0,0 +1,151 @@
+use chrono::{DateTime, Duration, Utc};
+use orchepy::models::case::{Case, CaseHistory};
+use orchepy::models::Workflow;
+use orchepy::repositories::{AnalyticsRepository, CaseRepository, WorkflowRepository};
+use orchepy::services::clock::VirtualClock;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+async fn create_workflow(pool: &PgPool) -> Workflow {
+    let workflow = Workflow {
+        id: Uuid::new_v4(),
+        name: "Support".to_string(),
+        phases: vec!["New".into(), "Review".into()],
+        initial_phase: "New".to_string(),
+        webhook_url: None,
+        webhook_template: None,
+        webhook_headers: Default::default(),
+        webhook_auth: None,
+        active: true,
+        description: None,
+        automations: None,
+        sla_config: Some(serde_json::from_value(json!({"New": {"hours": 1}})).unwrap()),
+        execution_limits: Default::default(),
+        data_conflicts: Default::default(),
+        transitions: Default::default(),
+        region: None,
+        version: 1,
+        updated_by: None,
+        archived_at: None,
+        archived_by: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    WorkflowRepository::new(pool).create(&workflow).await.unwrap();
+    workflow
+}
+
+/// A case created at `created_at` and moved to Review at `moved_at`.
+async fn create_case(pool: &PgPool, workflow: &Workflow, created_at: DateTime<Utc>, moved_at: Option<DateTime<Utc>>) -> Uuid {
+    let repo = CaseRepository::new(pool);
+    let mut case = Case::with_clock(workflow.id, "New".to_string(), json!({}), None, &VirtualClock::new(created_at));
+    if moved_at.is_some() {
+        case.current_phase = "Review".to_string();
+    }
+    repo.create(&case).await.unwrap();
+
+    let mut entered = CaseHistory::new(case.id, None, "New".to_string(), None, None);
+    entered.transitioned_at = created_at;
+    repo.create_history(&entered).await.unwrap();
+
+    if let Some(moved_at) = moved_at {
+        let mut moved = CaseHistory::new(case.id, Some("New".to_string()), "Review".to_string(), None, None);
+        moved.transitioned_at = moved_at;
+        repo.create_history(&moved).await.unwrap();
+    }
+    case.id
+}
+
+async fn set_status(pool: &PgPool, case_id: Uuid, status: &str, completed_at: DateTime<Utc>) {
+    sqlx::query("UPDATE orchepy_cases SET status = $1::case_status, completed_at = $2 WHERE id = $3")
+        .bind(status)
+        .bind(completed_at)
+        .bind(case_id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_workflow_analytics(pool: PgPool) {
+    let workflow = create_workflow(&pool).await;
+    let now = Utc::now();
+    let hours = |h: f64| now - Duration::minutes((h * 60.0) as i64);
+
+    create_case(&pool, &workflow, hours(5.0), Some(hours(3.0))).await;
+    let completed = create_case(&pool, &workflow, hours(2.0), Some(hours(1.5))).await;
+    set_status(&pool, completed, "completed", hours(1.0)).await;
+    create_case(&pool, &workflow, hours(3.0), None).await;
+    let failed = create_case(&pool, &workflow, hours(3.0), None).await;
+    set_status(&pool, failed, "failed", hours(2.5)).await;
+
+    let repo = AnalyticsRepository::new(&pool);
+
+    let counts = repo.cases_per_phase(workflow.id).await.unwrap();
+    let counts: Vec<_> = counts.iter().map(|c| (c.phase.as_str(), c.cases, c.in_progress)).collect();
+    assert_eq!(counts, vec![("New", 2, 1), ("Review", 2, 1)]);
+
+    let timings = repo.phase_timings(workflow.id, now - Duration::days(1), now).await.unwrap();
+    let new = timings.iter().find(|t| t.phase == "New").unwrap();
+    assert_eq!(new.samples, 2);
+    assert_eq!(new.avg_secs.map(f64::round), Some(4500.0));
+    assert_eq!(new.max_secs.map(f64::round), Some(7200.0));
+    assert_eq!((new.sla_samples, new.sla_breaches), (3, 2));
+    let review = timings.iter().find(|t| t.phase == "Review").unwrap();
+    assert_eq!((review.samples, review.sla_samples, review.avg_secs), (0, 0, None));
+
+    let throughput = repo.daily_throughput(workflow.id, now - Duration::days(2), now).await.unwrap();
+    assert_eq!(throughput.len(), 3);
+    assert_eq!(throughput.last().unwrap().day, now.date_naive());
+    assert_eq!(throughput.iter().map(|day| day.created).sum::<i64>(), 4);
+    assert_eq!(throughput.iter().map(|day| day.completed).sum::<i64>(), 1);
+}
+
+#[sqlx::test(migrations = "src/db/migrations")]
+async fn test_case_counts(pool: PgPool) {
+    use orchepy::models::case::CaseStatus;
+
+    let workflow = create_workflow(&pool).await;
+    let now = Utc::now();
+    create_case(&pool, &workflow, now, Some(now)).await;
+    create_case(&pool, &workflow, now, None).await;
+    let failed = create_case(&pool, &workflow, now, None).await;
+    set_status(&pool, failed, "failed", now).await;
+    let deleted = create_case(&pool, &workflow, now, None).await;
+    let repo = CaseRepository::new(&pool);
+    repo.soft_delete(deleted).await.unwrap();
+
+    assert_eq!(
+        repo.count_by_phase(workflow.id).await.unwrap(),
+        vec![("New".to_string(), 2), ("Review".to_string(), 1)]
+    );
+    assert_eq!(
+        repo.count_by_status(workflow.id).await.unwrap(),
+        vec![(CaseStatus::Active, 2), (CaseStatus::Failed, 1)]
+    );
+
+    let app = orchepy::api::build_router(orchepy::api::AppState::new(pool.clone(), orchepy::services::WebhookSender::new()));
+    let base = common::serve(app).await;
+
+    let counts: serde_json::Value = reqwest::get(format!("{}/workflows/{}/counts", base, workflow.id))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        counts,
+        json!({
+            "workflow_id": workflow.id,
+            "total": 3,
+            "phases": [{"phase": "New", "cases": 2}, {"phase": "Review", "cases": 1}],
+            "statuses": {"active": 2, "paused": 0, "completed": 0, "failed": 1},
+        })
+    );
+
+    let missing = reqwest::get(format!("{}/workflows/{}/counts", base, Uuid::new_v4())).await.unwrap();
+    assert_eq!(missing.status(), 404);
+}